@@ -0,0 +1,20 @@
+//! Helpers shared by the benchmarks.
+
+/// Define a pair of benchmarks that run the same body, once with the tree-walking evaluator and
+/// once with the `AST` compiled into bytecode.
+///
+/// The body is a function taking the [`Bencher`][test::Bencher] and whether the `AST` should be
+/// compiled into bytecode (via `AST::compile_bytecode`).
+macro_rules! bench_eval {
+    ($name:ident, $name_bytecode:ident, $body:ident) => {
+        #[bench]
+        fn $name(bench: &mut Bencher) {
+            $body(bench, false)
+        }
+
+        #[bench]
+        fn $name_bytecode(bench: &mut Bencher) {
+            $body(bench, true)
+        }
+    };
+}
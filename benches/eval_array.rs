@@ -6,32 +6,56 @@ extern crate test;
 use rhai::{Engine, OptimizationLevel};
 use test::Bencher;
 
-#[bench]
-fn bench_eval_array_small_get(bench: &mut Bencher) {
+#[macro_use]
+mod common;
+
+bench_eval!(
+    bench_eval_array_small_get,
+    bench_eval_array_small_get_bytecode,
+    eval_array_small_get
+);
+
+fn eval_array_small_get(bench: &mut Bencher, bytecode: bool) {
     let script = "let x = [1, 2, 3, 4, 5]; x[3]";
 
     let mut engine = Engine::new();
     engine.set_optimization_level(OptimizationLevel::None);
 
-    let ast = engine.compile(script).unwrap();
+    let mut ast = engine.compile(script).unwrap();
+    if bytecode {
+        ast.compile_bytecode();
+    }
 
     bench.iter(|| engine.consume_ast(&ast).unwrap());
 }
 
-#[bench]
-fn bench_eval_array_small_set(bench: &mut Bencher) {
+bench_eval!(
+    bench_eval_array_small_set,
+    bench_eval_array_small_set_bytecode,
+    eval_array_small_set
+);
+
+fn eval_array_small_set(bench: &mut Bencher, bytecode: bool) {
     let script = "let x = [1, 2, 3, 4, 5]; x[3] = 42;";
 
     let mut engine = Engine::new();
     engine.set_optimization_level(OptimizationLevel::None);
 
-    let ast = engine.compile(script).unwrap();
+    let mut ast = engine.compile(script).unwrap();
+    if bytecode {
+        ast.compile_bytecode();
+    }
 
     bench.iter(|| engine.consume_ast(&ast).unwrap());
 }
 
-#[bench]
-fn bench_eval_array_large_get(bench: &mut Bencher) {
+bench_eval!(
+    bench_eval_array_large_get,
+    bench_eval_array_large_get_bytecode,
+    eval_array_large_get
+);
+
+fn eval_array_large_get(bench: &mut Bencher, bytecode: bool) {
     let script = r#"let x = [ 1, 2.345, "hello", true,
                                 [ 1, 2, 3, [ "hey", [ "deeply", "nested" ], "jude" ] ]
                             ];
@@ -41,13 +65,21 @@ fn bench_eval_array_large_get(bench: &mut Bencher) {
     let mut engine = Engine::new();
     engine.set_optimization_level(OptimizationLevel::None);
 
-    let ast = engine.compile(script).unwrap();
+    let mut ast = engine.compile(script).unwrap();
+    if bytecode {
+        ast.compile_bytecode();
+    }
 
     bench.iter(|| engine.consume_ast(&ast).unwrap());
 }
 
-#[bench]
-fn bench_eval_array_large_set(bench: &mut Bencher) {
+bench_eval!(
+    bench_eval_array_large_set,
+    bench_eval_array_large_set_bytecode,
+    eval_array_large_set
+);
+
+fn eval_array_large_set(bench: &mut Bencher, bytecode: bool) {
     let script = r#"let x = [ 1, 2.345, "hello", true,
                                 [ 1, 2, 3, [ "hey", [ "deeply", "nested" ], "jude" ] ]
                             ];
@@ -57,13 +89,21 @@ fn bench_eval_array_large_set(bench: &mut Bencher) {
     let mut engine = Engine::new();
     engine.set_optimization_level(OptimizationLevel::None);
 
-    let ast = engine.compile(script).unwrap();
+    let mut ast = engine.compile(script).unwrap();
+    if bytecode {
+        ast.compile_bytecode();
+    }
 
     bench.iter(|| engine.consume_ast(&ast).unwrap());
 }
 
-#[bench]
-fn bench_eval_array_loop(bench: &mut Bencher) {
+bench_eval!(
+    bench_eval_array_loop,
+    bench_eval_array_loop_bytecode,
+    eval_array_loop
+);
+
+fn eval_array_loop(bench: &mut Bencher, bytecode: bool) {
     let script = r#"
             let list = [];
             
@@ -81,7 +121,10 @@ fn bench_eval_array_loop(bench: &mut Bencher) {
     let mut engine = Engine::new();
     engine.set_optimization_level(OptimizationLevel::None);
 
-    let ast = engine.compile(script).unwrap();
+    let mut ast = engine.compile(script).unwrap();
+    if bytecode {
+        ast.compile_bytecode();
+    }
 
     bench.iter(|| engine.consume_ast(&ast).unwrap());
 }
@@ -6,44 +6,76 @@ extern crate test;
 use rhai::{Engine, OptimizationLevel};
 use test::Bencher;
 
-#[bench]
-fn bench_eval_expression_single(bench: &mut Bencher) {
+#[macro_use]
+mod common;
+
+bench_eval!(
+    bench_eval_expression_single,
+    bench_eval_expression_single_bytecode,
+    eval_expression_single
+);
+
+fn eval_expression_single(bench: &mut Bencher, bytecode: bool) {
     let script = "1";
 
     let mut engine = Engine::new();
     engine.set_optimization_level(OptimizationLevel::None);
 
-    let ast = engine.compile_expression(script).unwrap();
+    let mut ast = engine.compile_expression(script).unwrap();
+    if bytecode {
+        ast.compile_bytecode();
+    }
 
     bench.iter(|| engine.consume_ast(&ast).unwrap());
 }
 
-#[bench]
-fn bench_eval_expression_number_literal(bench: &mut Bencher) {
+bench_eval!(
+    bench_eval_expression_number_literal,
+    bench_eval_expression_number_literal_bytecode,
+    eval_expression_number_literal
+);
+
+fn eval_expression_number_literal(bench: &mut Bencher, bytecode: bool) {
     let script = "2 > 1";
 
     let mut engine = Engine::new();
     engine.set_optimization_level(OptimizationLevel::None);
 
-    let ast = engine.compile_expression(script).unwrap();
+    let mut ast = engine.compile_expression(script).unwrap();
+    if bytecode {
+        ast.compile_bytecode();
+    }
 
     bench.iter(|| engine.consume_ast(&ast).unwrap());
 }
 
-#[bench]
-fn bench_eval_expression_number_operators(bench: &mut Bencher) {
+bench_eval!(
+    bench_eval_expression_number_operators,
+    bench_eval_expression_number_operators_bytecode,
+    eval_expression_number_operators
+);
+
+fn eval_expression_number_operators(bench: &mut Bencher, bytecode: bool) {
     let script = "2 + 2 == 4";
 
     let mut engine = Engine::new();
     engine.set_optimization_level(OptimizationLevel::None);
 
-    let ast = engine.compile_expression(script).unwrap();
+    let mut ast = engine.compile_expression(script).unwrap();
+    if bytecode {
+        ast.compile_bytecode();
+    }
 
     bench.iter(|| engine.consume_ast(&ast).unwrap());
 }
 
-#[bench]
-fn bench_eval_expression_optimized_simple(bench: &mut Bencher) {
+bench_eval!(
+    bench_eval_expression_optimized_simple,
+    bench_eval_expression_optimized_simple_bytecode,
+    eval_expression_optimized_simple
+);
+
+fn eval_expression_optimized_simple(bench: &mut Bencher, bytecode: bool) {
     let script = r#"
             2 > 1 &&
             "something" != "nothing" ||
@@ -54,13 +86,21 @@ fn bench_eval_expression_optimized_simple(bench: &mut Bencher) {
 
     let mut engine = Engine::new();
     engine.set_optimization_level(OptimizationLevel::Simple);
-    let ast = engine.compile_expression(script).unwrap();
+    let mut ast = engine.compile_expression(script).unwrap();
+    if bytecode {
+        ast.compile_bytecode();
+    }
 
     bench.iter(|| engine.consume_ast(&ast).unwrap());
 }
 
-#[bench]
-fn bench_eval_expression_optimized_full(bench: &mut Bencher) {
+bench_eval!(
+    bench_eval_expression_optimized_full,
+    bench_eval_expression_optimized_full_bytecode,
+    eval_expression_optimized_full
+);
+
+fn eval_expression_optimized_full(bench: &mut Bencher, bytecode: bool) {
     let script = r#"
             2 > 1 &&
             "something" != "nothing" ||
@@ -71,7 +111,10 @@ fn bench_eval_expression_optimized_full(bench: &mut Bencher) {
 
     let mut engine = Engine::new();
     engine.set_optimization_level(OptimizationLevel::Full);
-    let ast = engine.compile_expression(script).unwrap();
+    let mut ast = engine.compile_expression(script).unwrap();
+    if bytecode {
+        ast.compile_bytecode();
+    }
 
     bench.iter(|| engine.consume_ast(&ast).unwrap());
 }
@@ -106,8 +149,13 @@ fn bench_eval_call(bench: &mut Bencher) {
     bench.iter(|| engine.eval::<bool>(script).unwrap());
 }
 
-#[bench]
-fn bench_eval_loop_number(bench: &mut Bencher) {
+bench_eval!(
+    bench_eval_loop_number,
+    bench_eval_loop_number_bytecode,
+    eval_loop_number
+);
+
+fn eval_loop_number(bench: &mut Bencher, bytecode: bool) {
     let script = r#"
         let s = 0;
         for x in range(0, 10000) {
@@ -118,13 +166,21 @@ fn bench_eval_loop_number(bench: &mut Bencher) {
     let mut engine = Engine::new();
     engine.set_optimization_level(OptimizationLevel::None);
 
-    let ast = engine.compile(script).unwrap();
+    let mut ast = engine.compile(script).unwrap();
+    if bytecode {
+        ast.compile_bytecode();
+    }
 
     bench.iter(|| engine.consume_ast(&ast).unwrap());
 }
 
-#[bench]
-fn bench_eval_loop_strings_build(bench: &mut Bencher) {
+bench_eval!(
+    bench_eval_loop_strings_build,
+    bench_eval_loop_strings_build_bytecode,
+    eval_loop_strings_build
+);
+
+fn eval_loop_strings_build(bench: &mut Bencher, bytecode: bool) {
     let script = r#"
         let s = "hello";
         for x in range(0, 10000) {
@@ -135,13 +191,21 @@ fn bench_eval_loop_strings_build(bench: &mut Bencher) {
     let mut engine = Engine::new();
     engine.set_optimization_level(OptimizationLevel::None);
 
-    let ast = engine.compile(script).unwrap();
+    let mut ast = engine.compile(script).unwrap();
+    if bytecode {
+        ast.compile_bytecode();
+    }
 
     bench.iter(|| engine.consume_ast(&ast).unwrap());
 }
 
-#[bench]
-fn bench_eval_loop_strings_no_build(bench: &mut Bencher) {
+bench_eval!(
+    bench_eval_loop_strings_no_build,
+    bench_eval_loop_strings_no_build_bytecode,
+    eval_loop_strings_no_build
+);
+
+fn eval_loop_strings_no_build(bench: &mut Bencher, bytecode: bool) {
     let script = r#"
         let s = "hello";
         for x in range(0, 10000) {
@@ -152,13 +216,17 @@ fn bench_eval_loop_strings_no_build(bench: &mut Bencher) {
     let mut engine = Engine::new();
     engine.set_optimization_level(OptimizationLevel::None);
 
-    let ast = engine.compile(script).unwrap();
+    let mut ast = engine.compile(script).unwrap();
+    if bytecode {
+        ast.compile_bytecode();
+    }
 
     bench.iter(|| engine.consume_ast(&ast).unwrap());
 }
 
-#[bench]
-fn bench_eval_switch(bench: &mut Bencher) {
+bench_eval!(bench_eval_switch, bench_eval_switch_bytecode, eval_switch);
+
+fn eval_switch(bench: &mut Bencher, bytecode: bool) {
     let script = r#"
         let sum = 0;
         let rem = 0;
@@ -184,13 +252,21 @@ fn bench_eval_switch(bench: &mut Bencher) {
     let mut engine = Engine::new();
     engine.set_optimization_level(OptimizationLevel::None);
 
-    let ast = engine.compile(script).unwrap();
+    let mut ast = engine.compile(script).unwrap();
+    if bytecode {
+        ast.compile_bytecode();
+    }
 
     bench.iter(|| engine.consume_ast(&ast).unwrap());
 }
 
-#[bench]
-fn bench_eval_nested_if(bench: &mut Bencher) {
+bench_eval!(
+    bench_eval_nested_if,
+    bench_eval_nested_if_bytecode,
+    eval_nested_if
+);
+
+fn eval_nested_if(bench: &mut Bencher, bytecode: bool) {
     let script = r#"
         let sum = 0;
         let rem = 0;
@@ -214,7 +290,10 @@ fn bench_eval_nested_if(bench: &mut Bencher) {
     let mut engine = Engine::new();
     engine.set_optimization_level(OptimizationLevel::None);
 
-    let ast = engine.compile(script).unwrap();
+    let mut ast = engine.compile(script).unwrap();
+    if bytecode {
+        ast.compile_bytecode();
+    }
 
     bench.iter(|| engine.consume_ast(&ast).unwrap());
 }
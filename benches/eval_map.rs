@@ -6,32 +6,56 @@ extern crate test;
 use rhai::{Engine, OptimizationLevel};
 use test::Bencher;
 
-#[bench]
-fn bench_eval_map_small_get(bench: &mut Bencher) {
+#[macro_use]
+mod common;
+
+bench_eval!(
+    bench_eval_map_small_get,
+    bench_eval_map_small_get_bytecode,
+    eval_map_small_get
+);
+
+fn eval_map_small_get(bench: &mut Bencher, bytecode: bool) {
     let script = "let x = #{a:1}; x.a";
 
     let mut engine = Engine::new();
     engine.set_optimization_level(OptimizationLevel::None);
 
-    let ast = engine.compile(script).unwrap();
+    let mut ast = engine.compile(script).unwrap();
+    if bytecode {
+        ast.compile_bytecode();
+    }
 
     bench.iter(|| engine.consume_ast(&ast).unwrap());
 }
 
-#[bench]
-fn bench_eval_map_small_set(bench: &mut Bencher) {
+bench_eval!(
+    bench_eval_map_small_set,
+    bench_eval_map_small_set_bytecode,
+    eval_map_small_set
+);
+
+fn eval_map_small_set(bench: &mut Bencher, bytecode: bool) {
     let script = "let x = #{a:1}; x.a = 42;";
 
     let mut engine = Engine::new();
     engine.set_optimization_level(OptimizationLevel::None);
 
-    let ast = engine.compile(script).unwrap();
+    let mut ast = engine.compile(script).unwrap();
+    if bytecode {
+        ast.compile_bytecode();
+    }
 
     bench.iter(|| engine.consume_ast(&ast).unwrap());
 }
 
-#[bench]
-fn bench_eval_map_large_get(bench: &mut Bencher) {
+bench_eval!(
+    bench_eval_map_large_get,
+    bench_eval_map_large_get_bytecode,
+    eval_map_large_get
+);
+
+fn eval_map_large_get(bench: &mut Bencher, bytecode: bool) {
     let script = r#"let x = #{
                                 a:1,
                                 b:2.345,
@@ -45,13 +69,21 @@ fn bench_eval_map_large_get(bench: &mut Bencher) {
     let mut engine = Engine::new();
     engine.set_optimization_level(OptimizationLevel::None);
 
-    let ast = engine.compile(script).unwrap();
+    let mut ast = engine.compile(script).unwrap();
+    if bytecode {
+        ast.compile_bytecode();
+    }
 
     bench.iter(|| engine.consume_ast(&ast).unwrap());
 }
 
-#[bench]
-fn bench_eval_map_large_set(bench: &mut Bencher) {
+bench_eval!(
+    bench_eval_map_large_set,
+    bench_eval_map_large_set_bytecode,
+    eval_map_large_set
+);
+
+fn eval_map_large_set(bench: &mut Bencher, bytecode: bool) {
     let script = r#"let x = #{
                                 a:1,
                                 b:2.345,
@@ -65,7 +97,10 @@ fn bench_eval_map_large_set(bench: &mut Bencher) {
     let mut engine = Engine::new();
     engine.set_optimization_level(OptimizationLevel::None);
 
-    let ast = engine.compile(script).unwrap();
+    let mut ast = engine.compile(script).unwrap();
+    if bytecode {
+        ast.compile_bytecode();
+    }
 
     bench.iter(|| engine.consume_ast(&ast).unwrap());
 }
@@ -6,8 +6,12 @@ extern crate test;
 use rhai::{Engine, Module, OptimizationLevel};
 use test::Bencher;
 
-#[bench]
-fn bench_eval_module(bench: &mut Bencher) {
+#[macro_use]
+mod common;
+
+bench_eval!(bench_eval_module, bench_eval_module_bytecode, eval_module);
+
+fn eval_module(bench: &mut Bencher, bytecode: bool) {
     let script = r#"
         fn foo(x) { x + 1 }
         fn bar(x) { foo(x) }
@@ -22,7 +26,7 @@ fn bench_eval_module(bench: &mut Bencher) {
 
     engine.register_static_module("testing", module.into());
 
-    let ast = engine
+    let mut ast = engine
         .compile(
             r#"
                 fn foo(x) { x - 1 }
@@ -30,16 +34,24 @@ fn bench_eval_module(bench: &mut Bencher) {
     "#,
         )
         .unwrap();
+    if bytecode {
+        ast.compile_bytecode();
+    }
 
     bench.iter(|| engine.consume_ast(&ast).unwrap());
 }
 
-#[bench]
-fn bench_eval_function_call(bench: &mut Bencher) {
+bench_eval!(
+    bench_eval_function_call,
+    bench_eval_function_call_bytecode,
+    eval_function_call
+);
+
+fn eval_function_call(bench: &mut Bencher, bytecode: bool) {
     let mut engine = Engine::new();
     engine.set_optimization_level(OptimizationLevel::None);
 
-    let ast = engine
+    let mut ast = engine
         .compile(
             r#"
                 fn foo(x) { x - 1 }
@@ -48,6 +60,9 @@ fn bench_eval_function_call(bench: &mut Bencher) {
     "#,
         )
         .unwrap();
+    if bytecode {
+        ast.compile_bytecode();
+    }
 
     bench.iter(|| engine.consume_ast(&ast).unwrap());
 }
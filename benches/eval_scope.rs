@@ -6,8 +6,16 @@ extern crate test;
 use rhai::{Engine, OptimizationLevel, Scope, INT};
 use test::Bencher;
 
-#[bench]
-fn bench_eval_scope_single(bench: &mut Bencher) {
+#[macro_use]
+mod common;
+
+bench_eval!(
+    bench_eval_scope_single,
+    bench_eval_scope_single_bytecode,
+    eval_scope_single
+);
+
+fn eval_scope_single(bench: &mut Bencher, bytecode: bool) {
     let script = "requests_made == requests_made";
 
     let mut engine = Engine::new();
@@ -16,13 +24,21 @@ fn bench_eval_scope_single(bench: &mut Bencher) {
     let mut scope = Scope::new();
     scope.push("requests_made", 99 as INT);
 
-    let ast = engine.compile_expression(script).unwrap();
+    let mut ast = engine.compile_expression(script).unwrap();
+    if bytecode {
+        ast.compile_bytecode();
+    }
 
     bench.iter(|| engine.consume_ast_with_scope(&mut scope, &ast).unwrap());
 }
 
-#[bench]
-fn bench_eval_scope_multiple(bench: &mut Bencher) {
+bench_eval!(
+    bench_eval_scope_multiple,
+    bench_eval_scope_multiple_bytecode,
+    eval_scope_multiple
+);
+
+fn eval_scope_multiple(bench: &mut Bencher, bytecode: bool) {
     let script = "requests_made > requests_succeeded";
 
     let mut engine = Engine::new();
@@ -32,13 +48,21 @@ fn bench_eval_scope_multiple(bench: &mut Bencher) {
     scope.push("requests_made", 99 as INT);
     scope.push("requests_succeeded", 90 as INT);
 
-    let ast = engine.compile_expression(script).unwrap();
+    let mut ast = engine.compile_expression(script).unwrap();
+    if bytecode {
+        ast.compile_bytecode();
+    }
 
     bench.iter(|| engine.consume_ast_with_scope(&mut scope, &ast).unwrap());
 }
 
-#[bench]
-fn bench_eval_scope_longer(bench: &mut Bencher) {
+bench_eval!(
+    bench_eval_scope_longer,
+    bench_eval_scope_longer_bytecode,
+    eval_scope_longer
+);
+
+fn eval_scope_longer(bench: &mut Bencher, bytecode: bool) {
     let script = "(requests_made * requests_succeeded / 100) >= 90";
 
     let mut engine = Engine::new();
@@ -48,13 +72,21 @@ fn bench_eval_scope_longer(bench: &mut Bencher) {
     scope.push("requests_made", 99 as INT);
     scope.push("requests_succeeded", 90 as INT);
 
-    let ast = engine.compile_expression(script).unwrap();
+    let mut ast = engine.compile_expression(script).unwrap();
+    if bytecode {
+        ast.compile_bytecode();
+    }
 
     bench.iter(|| engine.consume_ast_with_scope(&mut scope, &ast).unwrap());
 }
 
-#[bench]
-fn bench_eval_scope_complex(bench: &mut Bencher) {
+bench_eval!(
+    bench_eval_scope_complex,
+    bench_eval_scope_complex_bytecode,
+    eval_scope_complex
+);
+
+fn eval_scope_complex(bench: &mut Bencher, bytecode: bool) {
     let script = r#"
             2 > 1 &&
             "something" != "nothing" ||
@@ -71,7 +103,10 @@ fn bench_eval_scope_complex(bench: &mut Bencher) {
     scope.push("variableName", 90 as INT);
     scope.push("modifierTest", 5 as INT);
 
-    let ast = engine.compile_expression(script).unwrap();
+    let mut ast = engine.compile_expression(script).unwrap();
+    if bytecode {
+        ast.compile_bytecode();
+    }
 
     bench.iter(|| engine.consume_ast_with_scope(&mut scope, &ast).unwrap());
 }
@@ -6,6 +6,9 @@ extern crate test;
 use rhai::{Engine, OptimizationLevel, Scope, INT};
 use test::Bencher;
 
+#[macro_use]
+mod common;
+
 #[derive(Debug, Clone)]
 struct Test {
     x: INT,
@@ -26,8 +29,9 @@ impl Test {
     }
 }
 
-#[bench]
-fn bench_type_field(bench: &mut Bencher) {
+bench_eval!(bench_type_field, bench_type_field_bytecode, type_field);
+
+fn type_field(bench: &mut Bencher, bytecode: bool) {
     let script = "foo.field";
 
     let mut engine = Engine::new();
@@ -36,7 +40,10 @@ fn bench_type_field(bench: &mut Bencher) {
     engine.register_type_with_name::<Test>("Test");
     engine.register_get("field", Test::get_x);
 
-    let ast = engine.compile_expression(script).unwrap();
+    let mut ast = engine.compile_expression(script).unwrap();
+    if bytecode {
+        ast.compile_bytecode();
+    }
 
     let mut scope = Scope::new();
     scope.push("foo", Test { x: 42 });
@@ -44,8 +51,9 @@ fn bench_type_field(bench: &mut Bencher) {
     bench.iter(|| engine.consume_ast_with_scope(&mut scope, &ast).unwrap());
 }
 
-#[bench]
-fn bench_type_method(bench: &mut Bencher) {
+bench_eval!(bench_type_method, bench_type_method_bytecode, type_method);
+
+fn type_method(bench: &mut Bencher, bytecode: bool) {
     let script = "foo.action()";
 
     let mut engine = Engine::new();
@@ -54,7 +62,10 @@ fn bench_type_method(bench: &mut Bencher) {
     engine.register_type_with_name::<Test>("Test");
     engine.register_fn("action", Test::action);
 
-    let ast = engine.compile_expression(script).unwrap();
+    let mut ast = engine.compile_expression(script).unwrap();
+    if bytecode {
+        ast.compile_bytecode();
+    }
 
     let mut scope = Scope::new();
     scope.push("foo", Test { x: 42 });
@@ -62,8 +73,13 @@ fn bench_type_method(bench: &mut Bencher) {
     bench.iter(|| engine.consume_ast_with_scope(&mut scope, &ast).unwrap());
 }
 
-#[bench]
-fn bench_type_method_with_params(bench: &mut Bencher) {
+bench_eval!(
+    bench_type_method_with_params,
+    bench_type_method_with_params_bytecode,
+    type_method_with_params
+);
+
+fn type_method_with_params(bench: &mut Bencher, bytecode: bool) {
     let script = "foo.update(1)";
 
     let mut engine = Engine::new();
@@ -72,7 +88,10 @@ fn bench_type_method_with_params(bench: &mut Bencher) {
     engine.register_type_with_name::<Test>("Test");
     engine.register_fn("update", Test::update);
 
-    let ast = engine.compile_expression(script).unwrap();
+    let mut ast = engine.compile_expression(script).unwrap();
+    if bytecode {
+        ast.compile_bytecode();
+    }
 
     let mut scope = Scope::new();
     scope.push("foo", Test { x: 42 });
@@ -80,8 +99,13 @@ fn bench_type_method_with_params(bench: &mut Bencher) {
     bench.iter(|| engine.consume_ast_with_scope(&mut scope, &ast).unwrap());
 }
 
-#[bench]
-fn bench_type_method_nested(bench: &mut Bencher) {
+bench_eval!(
+    bench_type_method_nested,
+    bench_type_method_nested_bytecode,
+    type_method_nested
+);
+
+fn type_method_nested(bench: &mut Bencher, bytecode: bool) {
     let script = "foo.nest.field";
 
     let mut engine = Engine::new();
@@ -91,7 +115,10 @@ fn bench_type_method_nested(bench: &mut Bencher) {
     engine.register_get("field", Test::get_x);
     engine.register_get("nest", Test::get_nest);
 
-    let ast = engine.compile_expression(script).unwrap();
+    let mut ast = engine.compile_expression(script).unwrap();
+    if bytecode {
+        ast.compile_bytecode();
+    }
 
     let mut scope = Scope::new();
     scope.push("foo", Test { x: 42 });
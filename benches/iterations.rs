@@ -6,8 +6,16 @@ extern crate test;
 use rhai::{Engine, OptimizationLevel, INT};
 use test::Bencher;
 
-#[bench]
-fn bench_iterations_1000(bench: &mut Bencher) {
+#[macro_use]
+mod common;
+
+bench_eval!(
+    bench_iterations_1000,
+    bench_iterations_1000_bytecode,
+    iterations_1000
+);
+
+fn iterations_1000(bench: &mut Bencher, bytecode: bool) {
     let script = r#"
             let x = 1_000;
             
@@ -19,13 +27,21 @@ fn bench_iterations_1000(bench: &mut Bencher) {
     let mut engine = Engine::new();
     engine.set_optimization_level(OptimizationLevel::None);
 
-    let ast = engine.compile(script).unwrap();
+    let mut ast = engine.compile(script).unwrap();
+    if bytecode {
+        ast.compile_bytecode();
+    }
 
     bench.iter(|| engine.consume_ast(&ast).unwrap());
 }
 
-#[bench]
-fn bench_iterations_fibonacci(bench: &mut Bencher) {
+bench_eval!(
+    bench_iterations_fibonacci,
+    bench_iterations_fibonacci_bytecode,
+    iterations_fibonacci
+);
+
+fn iterations_fibonacci(bench: &mut Bencher, bytecode: bool) {
     let script = r#"
         fn fibonacci(n) {
             if n < 2 {
@@ -41,7 +57,10 @@ fn bench_iterations_fibonacci(bench: &mut Bencher) {
     let mut engine = Engine::new();
     engine.set_optimization_level(OptimizationLevel::None);
 
-    let ast = engine.compile(script).unwrap();
+    let mut ast = engine.compile(script).unwrap();
+    if bytecode {
+        ast.compile_bytecode();
+    }
 
     bench.iter(|| engine.eval_ast::<INT>(&ast).unwrap());
 }
@@ -6,6 +6,9 @@ extern crate test;
 use rhai::{Engine, OptimizationLevel};
 use test::Bencher;
 
+#[macro_use]
+mod common;
+
 // This script uses the Sieve of Eratosthenes to calculate prime numbers.
 
 const SCRIPT: &str = r#"
@@ -32,12 +35,16 @@ for p in range(2, MAX_NUMBER_TO_CHECK) {
 }
 "#;
 
-#[bench]
-fn bench_eval_primes(bench: &mut Bencher) {
+bench_eval!(bench_eval_primes, bench_eval_primes_bytecode, eval_primes);
+
+fn eval_primes(bench: &mut Bencher, bytecode: bool) {
     let mut engine = Engine::new();
     engine.set_optimization_level(OptimizationLevel::None);
 
-    let ast = engine.compile(SCRIPT).unwrap();
+    let mut ast = engine.compile(SCRIPT).unwrap();
+    if bytecode {
+        ast.compile_bytecode();
+    }
 
     bench.iter(|| engine.consume_ast(&ast).unwrap());
 }
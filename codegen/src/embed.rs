@@ -0,0 +1,317 @@
+use std::path::{Path, PathBuf};
+
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+
+/// Extension of script files.
+const SCRIPT_EXTENSION: &str = "rhai";
+
+/// Arguments to `embed_scripts!`: a directory path, optionally followed by `check_lexical`.
+pub struct EmbedScripts {
+    dir: syn::LitStr,
+    check_lexical: bool,
+}
+
+impl Parse for EmbedScripts {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let dir = input.parse()?;
+        let mut check_lexical = false;
+
+        if input.parse::<Option<syn::Token![,]>>()?.is_some() && !input.is_empty() {
+            let flag: syn::Ident = input.parse()?;
+
+            if flag == "check_lexical" {
+                check_lexical = true;
+            } else {
+                return Err(syn::Error::new(
+                    flag.span(),
+                    format!("unknown option '{}', expected 'check_lexical'", flag),
+                ));
+            }
+            input.parse::<Option<syn::Token![,]>>()?;
+        }
+
+        Ok(Self { dir, check_lexical })
+    }
+}
+
+/// Recursively collect all script files under a directory, sorted by path, together with the
+/// directory and all its sub-directories.
+fn collect_scripts(
+    dir: &Path,
+    dirs: &mut Vec<PathBuf>,
+    files: &mut Vec<PathBuf>,
+) -> std::io::Result<()> {
+    let mut entries = std::fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+
+    dirs.push(dir.to_path_buf());
+
+    for path in entries {
+        if path.is_dir() {
+            collect_scripts(&path, dirs, files)?;
+        } else if path.extension() == Some(SCRIPT_EXTENSION.as_ref()) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Module path of a script file relative to the embedded directory, i.e. `/`-separated and
+/// without the extension.
+fn module_path(dir: &Path, file: &Path) -> String {
+    file.strip_prefix(dir)
+        .unwrap()
+        .with_extension("")
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+impl EmbedScripts {
+    pub fn generate(&self) -> syn::Result<proc_macro2::TokenStream> {
+        let span = self.dir.span();
+
+        let root = std::env::var("CARGO_MANIFEST_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_default();
+        let dir = root.join(self.dir.value());
+
+        let mut dirs = Vec::new();
+        let mut files = Vec::new();
+
+        collect_scripts(&dir, &mut dirs, &mut files).map_err(|err| {
+            syn::Error::new(
+                span,
+                format!("cannot read script directory '{}': {}", dir.display(), err),
+            )
+        })?;
+
+        // Rebuild when scripts are added to or removed from any directory.
+        // Stable Rust cannot track directories, so this needs the `nightly` feature.
+        #[cfg(feature = "nightly")]
+        for dir in dirs.iter() {
+            proc_macro::tracked::path(dir);
+        }
+        #[cfg(not(feature = "nightly"))]
+        drop(dirs);
+
+        let mut inserts = Vec::new();
+
+        for file in files.iter() {
+            let path = module_path(&dir, file);
+            let file_name = file.to_string_lossy();
+
+            if self.check_lexical {
+                let script = std::fs::read_to_string(file).map_err(|err| {
+                    syn::Error::new(
+                        span,
+                        format!("cannot read script file '{}': {}", file_name, err),
+                    )
+                })?;
+
+                if let Err(err) = check_lexical(&script) {
+                    return Err(syn::Error::new(
+                        span,
+                        format!("lexical error in script '{}': {}", file_name, err),
+                    ));
+                }
+            }
+
+            inserts.push(quote! {
+                resolver.insert_script(#path, include_str!(#file_name));
+            });
+        }
+
+        Ok(quote! {
+            {
+                let mut resolver = rhai::module_resolvers::StaticModuleResolver::new();
+                #(#inserts)*
+                resolver
+            }
+        })
+    }
+}
+
+/// A lexical error found by [`check_lexical`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LexicalError {
+    pub line: usize,
+    pub position: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for LexicalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} (line {}, position {})",
+            self.message, self.line, self.position
+        )
+    }
+}
+
+/// Scanner checking the lexical structure of a script.
+struct Scanner<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    line: usize,
+    position: usize,
+    /// Open brackets, with `` ` `` standing for `${` in an interpolated string.
+    stack: Vec<(char, usize, usize)>,
+}
+
+impl<'a> Scanner<'a> {
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+
+        if c == '\n' {
+            self.line += 1;
+            self.position = 0;
+        } else {
+            self.position += 1;
+        }
+        Some(c)
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.chars.peek() == Some(&c) {
+            self.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn error(line: usize, position: usize, message: impl Into<String>) -> LexicalError {
+        LexicalError {
+            line,
+            position,
+            message: message.into(),
+        }
+    }
+
+    /// Scan a `"` string or `'` character literal, after the opening quote.
+    fn quoted(&mut self, quote: char, line: usize, position: usize) -> Result<(), LexicalError> {
+        loop {
+            match self.next() {
+                Some(c) if c == quote => return Ok(()),
+                // Escapes, including line continuation
+                Some('\\') => {
+                    self.next();
+                }
+                Some('\n') | None if quote == '"' => {
+                    return Err(Self::error(line, position, "unterminated string literal"))
+                }
+                Some('\n') | None => {
+                    return Err(Self::error(
+                        line,
+                        position,
+                        "unterminated character literal",
+                    ))
+                }
+                Some(_) => (),
+            }
+        }
+    }
+
+    /// Scan a `` ` `` string, after the opening quote or the `}` ending an interpolation.
+    fn backtick(&mut self, line: usize, position: usize) -> Result<(), LexicalError> {
+        loop {
+            match self.next() {
+                Some('`') => return Ok(()),
+                Some('$') if self.eat('{') => {
+                    self.stack.push(('`', line, position));
+                    return Ok(());
+                }
+                Some(_) => (),
+                None => return Err(Self::error(line, position, "unterminated string literal")),
+            }
+        }
+    }
+
+    /// Scan a (nested) block comment, after the opening `/*`.
+    fn block_comment(&mut self, line: usize, position: usize) -> Result<(), LexicalError> {
+        let mut level = 1;
+
+        while level > 0 {
+            match self.next() {
+                Some('/') if self.eat('*') => level += 1,
+                Some('*') if self.eat('/') => level -= 1,
+                Some(_) => (),
+                None => return Err(Self::error(line, position, "unterminated block comment")),
+            }
+        }
+        Ok(())
+    }
+
+    fn scan(&mut self) -> Result<(), LexicalError> {
+        while let Some(c) = self.next() {
+            let (line, position) = (self.line, self.position);
+
+            match c {
+                '/' if self.eat('/') => while !matches!(self.next(), Some('\n') | None) {},
+                '/' if self.eat('*') => self.block_comment(line, position)?,
+                '"' | '\'' => self.quoted(c, line, position)?,
+                '`' => self.backtick(line, position)?,
+                '(' | '[' | '{' => self.stack.push((c, line, position)),
+                ')' | ']' | '}' => {
+                    let open = match c {
+                        ')' => '(',
+                        ']' => '[',
+                        _ => '{',
+                    };
+
+                    match self.stack.pop() {
+                        Some((o, _, _)) if o == open => (),
+                        // End of interpolation - continue the string
+                        Some(('`', l, p)) if c == '}' => self.backtick(l, p)?,
+                        Some((o, l, p)) => {
+                            return Err(Self::error(
+                                line,
+                                position,
+                                format!(
+                                    "'{}' does not match '{}' at line {}, position {}",
+                                    c, o, l, p
+                                ),
+                            ))
+                        }
+                        None => {
+                            return Err(Self::error(line, position, format!("unexpected '{}'", c)))
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        match self.stack.pop() {
+            Some(('`', l, p)) => Err(Self::error(l, p, "unterminated string literal")),
+            Some((o, l, p)) => Err(Self::error(l, p, format!("'{}' is never closed", o))),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Check the lexical structure of a script: string and character literals and block comments
+/// must be terminated, and brackets must be balanced.
+///
+/// This is not a syntax check.  Other errors (e.g. `let x = ;`) are only reported when the script
+/// is compiled by Rhai.
+pub fn check_lexical(script: &str) -> Result<(), LexicalError> {
+    let mut scanner = Scanner {
+        chars: script.chars().peekable(),
+        line: 1,
+        position: 0,
+        stack: Vec::new(),
+    };
+
+    // Skip shebang
+    if script.starts_with("#!") {
+        while !matches!(scanner.next(), Some('\n') | None) {}
+    }
+
+    scanner.scan()
+}
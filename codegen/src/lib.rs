@@ -87,10 +87,13 @@
 //! ```
 //!
 
+#![cfg_attr(feature = "nightly", feature(proc_macro_tracked_path))]
+
 use quote::quote;
 use syn::parse_macro_input;
 
 mod attrs;
+mod embed;
 mod function;
 mod module;
 mod register;
@@ -397,3 +400,49 @@ pub fn set_exported_global_fn(args: proc_macro::TokenStream) -> proc_macro::Toke
         Err(e) => e.to_compile_error().into(),
     }
 }
+
+/// Macro to embed all Rhai scripts under a directory into the binary, returning a
+/// `StaticModuleResolver` that serves them.
+///
+/// The directory path is relative to the crate root (i.e. the directory containing `Cargo.toml`).
+/// Each `.rhai` file (including those in sub-directories) is included at compile time, keyed by
+/// its path relative to the directory without the extension, e.g. `utils/strings` for
+/// `utils/strings.rhai`. Scripts are only compiled into modules when first imported.
+///
+/// With the `check_lexical` option, the build fails if any script has an unterminated string,
+/// character literal or block comment, or unbalanced brackets.  This is only a lexical check, not
+/// a full syntax check: other errors (e.g. `let x = ;`) are reported when a module is first imported.
+///
+/// Changes to existing scripts trigger a rebuild.  Scripts added to or removed from the directory
+/// only trigger a rebuild with the `nightly` feature of `rhai_codegen`, which requires a nightly
+/// compiler.  On stable Rust, add a build script to the crate containing:
+///
+/// ```ignore
+/// println!("cargo:rerun-if-changed=scripts");
+/// ```
+///
+/// # Usage
+///
+/// ```
+/// # use rhai::{Engine, EvalAltResult};
+/// use rhai::plugin::*;
+///
+/// # fn main() -> Result<(), Box<EvalAltResult>> {
+/// let resolver = embed_scripts!("tests/scripts", check_lexical);
+///
+/// let mut engine = Engine::new();
+/// engine.set_module_resolver(resolver);
+///
+/// assert_eq!(engine.eval::<i64>(r#"import "math" as m; m::double(21)"#)?, 42);
+/// # Ok(())
+/// # }
+/// ```
+#[proc_macro]
+pub fn embed_scripts(args: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let args = parse_macro_input!(args as crate::embed::EmbedScripts);
+
+    match args.generate() {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
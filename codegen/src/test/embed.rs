@@ -0,0 +1,49 @@
+#[cfg(test)]
+mod check_lexical_tests {
+    use crate::embed::check_lexical;
+
+    fn check_err(script: &str) -> (usize, usize, String) {
+        let err = check_lexical(script).unwrap_err();
+        (err.line, err.position, err.message)
+    }
+
+    #[test]
+    fn valid_scripts() {
+        assert!(check_lexical("#!/usr/bin/rhai (\nlet x = [1, (2), #{a: 3}];").is_ok());
+        assert!(check_lexical("let s = \"a ( \\\" \\\n b\"; let c = ')';").is_ok());
+        assert!(check_lexical("// ( [\n/* ( /* nested ] */ { */ x").is_ok());
+        assert!(check_lexical("let s = `${ if x { `${y}}` } else { \"}\" } } }`;").is_ok());
+
+        // Not a syntax check
+        assert!(check_lexical("let x = ;").is_ok());
+    }
+
+    #[test]
+    fn invalid_scripts() {
+        assert_eq!(
+            check_err("let x = \"abc\nlet y = 1;"),
+            (1, 9, "unterminated string literal".into())
+        );
+        assert_eq!(
+            check_err("let x = 'a;"),
+            (1, 9, "unterminated character literal".into())
+        );
+        assert_eq!(
+            check_err("x /* /* */"),
+            (1, 3, "unterminated block comment".into())
+        );
+        assert_eq!(
+            check_err("let x = `a ${ b }"),
+            (1, 9, "unterminated string literal".into())
+        );
+        assert_eq!(
+            check_err("fn f() {\n    (1 + 2];\n}"),
+            (2, 11, "']' does not match '(' at line 2, position 5".into())
+        );
+        assert_eq!(check_err("x)"), (1, 2, "unexpected ')'".into()));
+        assert_eq!(
+            check_err("fn f() {\n  [1, 2]"),
+            (1, 8, "'{' is never closed".into())
+        );
+    }
+}
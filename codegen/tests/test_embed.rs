@@ -0,0 +1,26 @@
+use rhai::{embed_scripts, Engine, EvalAltResult, INT};
+
+#[test]
+fn embed_scripts_test() -> Result<(), Box<EvalAltResult>> {
+    let resolver = embed_scripts!("tests/scripts", check_lexical);
+
+    assert_eq!(
+        resolver.paths().collect::<Vec<_>>(),
+        ["math", "utils/strings"]
+    );
+    assert!(!resolver.is_compiled("math"));
+
+    let mut engine = Engine::new();
+    engine.set_module_resolver(resolver);
+
+    assert_eq!(
+        engine.eval::<INT>(r#"import "math" as m; m::double(21)"#)?,
+        42
+    );
+    assert_eq!(
+        engine.eval::<String>(r#"import "utils/strings" as s; s::shout("hello")"#)?,
+        "hello! x2"
+    );
+
+    Ok(())
+}
@@ -9,13 +9,14 @@ use crate::stdlib::{
     fmt,
     hash::Hash,
     iter::empty,
+    mem,
     num::NonZeroUsize,
     ops::{Add, AddAssign},
     string::String,
     vec,
     vec::Vec,
 };
-use crate::token::Token;
+use crate::token::{Span, Token};
 use crate::utils::calc_fn_hash;
 use crate::{
     Dynamic, FnNamespace, FnPtr, Identifier, ImmutableString, Module, Position, Shared, StaticVec,
@@ -63,6 +64,8 @@ pub struct ScriptFnDef {
     pub externals: crate::stdlib::collections::BTreeSet<Identifier>,
     /// Function doc-comments (if any).
     pub comments: StaticVec<String>,
+    /// Bytecode compiled from the function body, if any.
+    pub(crate) bytecode: Option<Shared<crate::vm::Chunk>>,
 }
 
 impl fmt::Display for ScriptFnDef {
@@ -155,6 +158,11 @@ pub struct AST {
     /// Embedded module resolver, if any.
     #[cfg(not(feature = "no_module"))]
     resolver: Option<Shared<crate::module::resolvers::StaticModuleResolver>>,
+    /// Cache of modules resolved by imports inside functions.
+    #[cfg(not(feature = "no_module"))]
+    module_cache: Shared<crate::engine::ModuleCache>,
+    /// Bytecode compiled from the global statements, if any.
+    bytecode: Option<Shared<crate::vm::Chunk>>,
 }
 
 impl Default for AST {
@@ -166,6 +174,9 @@ impl Default for AST {
             functions: Default::default(),
             #[cfg(not(feature = "no_module"))]
             resolver: None,
+            #[cfg(not(feature = "no_module"))]
+            module_cache: Default::default(),
+            bytecode: None,
         }
     }
 }
@@ -186,6 +197,9 @@ impl AST {
             functions: functions.into(),
             #[cfg(not(feature = "no_module"))]
             resolver: None,
+            #[cfg(not(feature = "no_module"))]
+            module_cache: Default::default(),
+            bytecode: None,
         }
     }
     /// Create a new [`AST`] with a source name.
@@ -204,6 +218,9 @@ impl AST {
             functions: functions.into(),
             #[cfg(not(feature = "no_module"))]
             resolver: None,
+            #[cfg(not(feature = "no_module"))]
+            module_cache: Default::default(),
+            bytecode: None,
         }
     }
     /// Get the source, if any.
@@ -251,6 +268,7 @@ impl AST {
     #[cfg(not(feature = "no_optimize"))]
     #[inline(always)]
     pub(crate) fn statements_mut(&mut self) -> &mut StaticVec<Stmt> {
+        self.bytecode = None;
         &mut self.body.statements
     }
     /// Get the internal shared [`Module`] containing all script-defined functions.
@@ -314,6 +332,24 @@ impl AST {
         self.resolver = Some(resolver.into());
         self
     }
+    /// Get the cache of [modules][crate::Module] resolved by `import` statements inside functions.
+    #[cfg(not(feature = "no_module"))]
+    #[inline(always)]
+    pub(crate) fn module_cache(&self) -> Shared<crate::engine::ModuleCache> {
+        self.module_cache.clone()
+    }
+    /// Clear the cache of [modules][crate::Module] resolved by `import` statements inside functions,
+    /// so that they are resolved again when next imported (e.g. to pick up changed module scripts).
+    ///
+    /// The cache is shared by all clones of this [`AST`], and is also emptied when the [`AST`] is
+    /// evaluated by an [`Engine`][crate::Engine] with a different module resolver.
+    ///
+    /// Not available under `no_module`.
+    #[cfg(not(feature = "no_module"))]
+    #[inline(always)]
+    pub fn clear_module_cache(&self) {
+        self.module_cache.clear();
+    }
     /// Clone the [`AST`]'s functions into a new [`AST`].
     /// No statements are cloned.
     /// Not available under `no_function`.
@@ -343,6 +379,9 @@ impl AST {
             functions: functions.into(),
             #[cfg(not(feature = "no_module"))]
             resolver: self.resolver.clone(),
+            #[cfg(not(feature = "no_module"))]
+            module_cache: self.module_cache.clone(),
+            bytecode: None,
         }
     }
     /// Clone the [`AST`]'s script statements into a new [`AST`].
@@ -355,6 +394,9 @@ impl AST {
             functions: Default::default(),
             #[cfg(not(feature = "no_module"))]
             resolver: self.resolver.clone(),
+            #[cfg(not(feature = "no_module"))]
+            module_cache: self.module_cache.clone(),
+            bytecode: self.bytecode.clone(),
         }
     }
     /// Merge two [`AST`] into one.  Both [`AST`]'s are untouched and a new, merged,
@@ -607,6 +649,7 @@ impl AST {
         self.body
             .statements
             .extend(other.body.statements.into_iter());
+        self.bytecode = None;
 
         if !other.functions.is_empty() {
             shared_make_mut(&mut self.functions).merge_filtered(&other.functions, &filter);
@@ -653,7 +696,6 @@ impl AST {
     ///
     /// Not available under [`no_function`].
     #[cfg(not(feature = "no_function"))]
-    #[cfg(not(feature = "no_module"))]
     #[inline(always)]
     pub(crate) fn iter_fn_def(&self) -> impl Iterator<Item = &ScriptFnDef> {
         self.functions
@@ -682,11 +724,95 @@ impl AST {
     #[inline(always)]
     pub fn clear_statements(&mut self) {
         self.body = Default::default();
+        self.bytecode = None;
+    }
+    /// Compile the [`AST`] into bytecode, so that it is evaluated by the bytecode backend instead
+    /// of the tree-walking evaluator.
+    ///
+    /// Both global statements and script-defined functions are compiled.
+    ///
+    /// The bytecode is discarded whenever the statements of the [`AST`] change (e.g. via
+    /// [`combine`][AST::combine]), and is never serialized.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::{Engine, INT};
+    ///
+    /// let engine = Engine::new();
+    ///
+    /// let mut ast = engine.compile("let x = 0; for n in range(0, 10) { x += n; } x")?;
+    /// ast.compile_bytecode();
+    ///
+    /// assert!(ast.has_bytecode());
+    /// assert_eq!(engine.eval_ast::<INT>(&ast)?, 45);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn compile_bytecode(&mut self) -> &mut Self {
+        self.bytecode = Some(crate::vm::Chunk::compile(&self.body.statements).into());
+
+        #[cfg(not(feature = "no_function"))]
+        if !self.functions.is_empty() {
+            let functions = shared_make_mut(&mut self.functions);
+
+            let fn_defs: StaticVec<_> = functions
+                .iter_script_fn()
+                .filter(|(_, _, _, _, fn_def)| fn_def.bytecode.is_none())
+                .map(|(_, _, _, _, fn_def)| fn_def.clone())
+                .collect();
+
+            fn_defs.into_iter().for_each(|fn_def| {
+                let mut fn_def = crate::fn_native::shared_take_or_clone(fn_def);
+                fn_def.bytecode =
+                    Some(crate::vm::Chunk::compile_fn(&fn_def.body.statements).into());
+                functions.set_script_fn(fn_def);
+            });
+        }
+
+        self
+    }
+    /// Discard any bytecode compiled by [`compile_bytecode`][AST::compile_bytecode], so that the
+    /// [`AST`] is evaluated by the tree-walking evaluator.
+    pub fn clear_bytecode(&mut self) -> &mut Self {
+        self.bytecode = None;
+
+        #[cfg(not(feature = "no_function"))]
+        if self
+            .functions
+            .iter_script_fn()
+            .any(|(_, _, _, _, fn_def)| fn_def.bytecode.is_some())
+        {
+            let functions = shared_make_mut(&mut self.functions);
+
+            let fn_defs: StaticVec<_> = functions
+                .iter_script_fn()
+                .map(|(_, _, _, _, fn_def)| fn_def.clone())
+                .collect();
+
+            fn_defs.into_iter().for_each(|fn_def| {
+                let mut fn_def = crate::fn_native::shared_take_or_clone(fn_def);
+                fn_def.bytecode = None;
+                functions.set_script_fn(fn_def);
+            });
+        }
+
+        self
+    }
+    /// Are the global statements of the [`AST`] compiled into bytecode?
+    #[inline(always)]
+    pub fn has_bytecode(&self) -> bool {
+        self.bytecode.is_some()
+    }
+    /// Get the bytecode compiled from the global statements, if any.
+    #[inline(always)]
+    pub(crate) fn bytecode(&self) -> Option<&Shared<crate::vm::Chunk>> {
+        self.bytecode.as_ref()
     }
     /// Recursively walk the [`AST`], including function bodies (if any).
     /// Return `false` from the callback to terminate the walk.
     #[cfg(not(feature = "internals"))]
-    #[cfg(not(feature = "no_module"))]
     #[inline(always)]
     pub(crate) fn walk(&self, on_node: &mut impl FnMut(&[ASTNode]) -> bool) -> bool {
         let path = &mut Default::default();
@@ -765,7 +891,7 @@ impl AsRef<Module> for AST {
 /// # Volatile Data Structure
 ///
 /// This type is volatile and may change.
-#[derive(Clone, Eq, PartialEq, Hash)]
+#[derive(Clone, Eq, PartialEq, Hash, Default)]
 pub struct Ident {
     /// Identifier name.
     pub name: Identifier,
@@ -920,10 +1046,10 @@ impl Default for Stmt {
 
 impl From<Stmt> for StmtBlock {
     #[inline(always)]
-    fn from(stmt: Stmt) -> Self {
+    fn from(mut stmt: Stmt) -> Self {
         match stmt {
-            Stmt::Block(block, pos) => Self {
-                statements: block.into(),
+            Stmt::Block(ref mut block, pos) => Self {
+                statements: mem::take(block).into(),
                 pos,
             },
             Stmt::Noop(pos) => Self {
@@ -1275,7 +1401,7 @@ pub struct CustomExpr {
 /// # Volatile Data Structure
 ///
 /// This type is volatile and may change.
-#[derive(Debug, Clone, Hash)]
+#[derive(Debug, Clone, Hash, Default)]
 pub struct BinaryExpr {
     /// LHS expression.
     pub lhs: Expr,
@@ -1425,6 +1551,8 @@ pub struct FnCallExpr {
     pub namespace: Option<NamespaceRef>,
     /// Function name.
     pub name: Identifier,
+    /// Source text of the function call, captured for assertions only.
+    pub source_text: Option<ImmutableString>,
 }
 
 impl FnCallExpr {
@@ -1606,39 +1734,63 @@ impl Expr {
     ///
     /// Returns [`None`] if the expression is not constant.
     #[inline]
+    #[allow(unused_variables)]
     pub fn get_constant_value(&self) -> Option<Dynamic> {
-        Some(match self {
-            Self::DynamicConstant(x, _) => x.as_ref().clone(),
-            Self::IntegerConstant(x, _) => (*x).into(),
-            #[cfg(not(feature = "no_float"))]
-            Self::FloatConstant(x, _) => (*x).into(),
-            Self::CharConstant(x, _) => (*x).into(),
-            Self::StringConstant(x, _) => x.clone().into(),
-            Self::FnPointer(x, _) => Dynamic(Union::FnPtr(
-                Box::new(FnPtr::new_unchecked(x.clone(), Default::default())),
-                AccessMode::ReadOnly,
-            )),
-            Self::BoolConstant(x, _) => (*x).into(),
-            Self::Unit(_) => Dynamic::UNIT,
+        // Literals can nest very deeply, so their items are evaluated with an explicit stack,
+        // and collected into the literals afterwards
+        let mut exprs = vec![(self, false)];
+        let mut values: Vec<Dynamic> = Vec::new();
+
+        while let Some((expr, collect)) = exprs.pop() {
+            let value = match expr {
+                Self::DynamicConstant(x, _) => x.as_ref().clone(),
+                Self::IntegerConstant(x, _) => (*x).into(),
+                #[cfg(not(feature = "no_float"))]
+                Self::FloatConstant(x, _) => (*x).into(),
+                Self::CharConstant(x, _) => (*x).into(),
+                Self::StringConstant(x, _) => x.clone().into(),
+                Self::FnPointer(x, _) => Dynamic(Union::FnPtr(
+                    Box::new(FnPtr::new_unchecked(x.clone(), Default::default())),
+                    AccessMode::ReadOnly,
+                )),
+                Self::BoolConstant(x, _) => (*x).into(),
+                Self::Unit(_) => Dynamic::UNIT,
 
-            #[cfg(not(feature = "no_index"))]
-            Self::Array(x, _) if self.is_constant() => {
-                let mut arr = Array::with_capacity(x.len());
-                arr.extend(x.iter().map(|v| v.get_constant_value().unwrap()));
-                arr.into()
-            }
+                #[cfg(not(feature = "no_index"))]
+                Self::Array(x, _) if !collect => {
+                    exprs.push((expr, true));
+                    exprs.extend(x.iter().rev().map(|v| (v, false)));
+                    continue;
+                }
+                #[cfg(not(feature = "no_index"))]
+                Self::Array(x, _) => {
+                    let arr: Array = values.split_off(values.len() - x.len());
+                    arr.into()
+                }
 
-            #[cfg(not(feature = "no_object"))]
-            Self::Map(x, _) if self.is_constant() => {
-                let mut map = x.1.clone();
-                x.0.iter().for_each(|(k, v)| {
-                    *map.get_mut(k.name.as_str()).unwrap() = v.get_constant_value().unwrap()
-                });
-                map.into()
-            }
+                #[cfg(not(feature = "no_object"))]
+                Self::Map(x, _) if !collect => {
+                    exprs.push((expr, true));
+                    exprs.extend(x.0.iter().rev().map(|(_, v)| (v, false)));
+                    continue;
+                }
+                #[cfg(not(feature = "no_object"))]
+                Self::Map(x, _) => {
+                    let mut map = x.1.clone();
+                    let items = values.split_off(values.len() - x.0.len());
+                    x.0.iter().zip(items).for_each(|((k, _), v)| {
+                        *map.get_mut(k.name.as_str()).unwrap() = v;
+                    });
+                    map.into()
+                }
+
+                _ => return None,
+            };
 
-            _ => return None,
-        })
+            values.push(value);
+        }
+
+        values.pop()
     }
     /// Return the variable name if the expression a simple variable access.
     #[inline(always)]
@@ -1651,31 +1803,38 @@ impl Expr {
     /// Get the [position][Position] of the expression.
     #[inline]
     pub fn position(&self) -> Position {
-        match self {
-            #[cfg(not(feature = "no_float"))]
-            Self::FloatConstant(_, pos) => *pos,
-
-            Self::DynamicConstant(_, pos) => *pos,
-            Self::BoolConstant(_, pos) => *pos,
-            Self::IntegerConstant(_, pos) => *pos,
-            Self::CharConstant(_, pos) => *pos,
-            Self::StringConstant(_, pos) => *pos,
-            Self::InterpolatedString(x) => x.first().unwrap().position(),
-            Self::FnPointer(_, pos) => *pos,
-            Self::Array(_, pos) => *pos,
-            Self::Map(_, pos) => *pos,
-            Self::Property(x) => (x.2).pos,
-            Self::Stmt(x) => x.pos,
-            Self::Variable(x) => (x.2).pos,
-            Self::FnCall(_, pos) => *pos,
-
-            Self::And(x, _) | Self::Or(x, _) => x.lhs.position(),
-
-            Self::Unit(pos) => *pos,
+        let mut expr = self;
+
+        // Binary expressions take the position of the left-most operand, which is looked up
+        // in a loop because operator chains can nest very deeply
+        loop {
+            return match expr {
+                #[cfg(not(feature = "no_float"))]
+                Self::FloatConstant(_, pos) => *pos,
+
+                Self::DynamicConstant(_, pos) => *pos,
+                Self::BoolConstant(_, pos) => *pos,
+                Self::IntegerConstant(_, pos) => *pos,
+                Self::CharConstant(_, pos) => *pos,
+                Self::StringConstant(_, pos) => *pos,
+                Self::InterpolatedString(x) => x.first().unwrap().position(),
+                Self::FnPointer(_, pos) => *pos,
+                Self::Array(_, pos) => *pos,
+                Self::Map(_, pos) => *pos,
+                Self::Property(x) => (x.2).pos,
+                Self::Stmt(x) => x.pos,
+                Self::Variable(x) => (x.2).pos,
+                Self::FnCall(_, pos) => *pos,
+
+                Self::And(x, _) | Self::Or(x, _) | Self::Dot(x, _) | Self::Index(x, _) => {
+                    expr = &x.lhs;
+                    continue;
+                }
 
-            Self::Dot(x, _) | Self::Index(x, _) => x.lhs.position(),
+                Self::Unit(pos) => *pos,
 
-            Self::Custom(_, pos) => *pos,
+                Self::Custom(_, pos) => *pos,
+            };
         }
     }
     /// Override the [position][Position] of the expression.
@@ -1708,26 +1867,64 @@ impl Expr {
 
         self
     }
+    /// Get the [`Span`] of the expression, as recorded by the parser.
+    ///
+    /// Errors raised by the expression carry this [`Span`] in their [position][Position].
+    /// If none is recorded, the [`Span`] covers only the character at the expression's position.
+    #[inline(always)]
+    pub fn span(&self) -> Span {
+        match self {
+            Self::And(_, pos) | Self::Or(_, pos) | Self::Dot(_, pos) | Self::Index(_, pos) => {
+                pos.span()
+            }
+            _ => self.position().span(),
+        }
+    }
+    /// Set the [`Span`] of the expression.
+    pub(crate) fn set_span(&mut self, span: Span) -> &mut Self {
+        match self {
+            Self::And(_, pos) | Self::Or(_, pos) | Self::Dot(_, pos) | Self::Index(_, pos) => {
+                *pos = pos.with_span(span)
+            }
+            _ => {
+                let pos = self.position();
+                self.set_position(pos.with_span(span));
+            }
+        }
+
+        self
+    }
     /// Is the expression pure?
     ///
     /// A pure expression has no side effects.
     #[inline]
     pub fn is_pure(&self) -> bool {
-        match self {
-            Self::InterpolatedString(x) | Self::Array(x, _) => x.iter().all(Self::is_pure),
+        // Sub-expressions can nest very deeply, so they are checked with an explicit stack
+        let mut exprs: StaticVec<&Self> = Default::default();
+        exprs.push(self);
 
-            Self::Map(x, _) => x.0.iter().map(|(_, v)| v).all(Self::is_pure),
+        while let Some(expr) = exprs.pop() {
+            match expr {
+                Self::InterpolatedString(x) | Self::Array(x, _) => exprs.extend(x.iter()),
 
-            Self::Index(x, _) | Self::And(x, _) | Self::Or(x, _) => {
-                x.lhs.is_pure() && x.rhs.is_pure()
-            }
+                Self::Map(x, _) => exprs.extend(x.0.iter().map(|(_, v)| v)),
+
+                Self::Index(x, _) | Self::And(x, _) | Self::Or(x, _) => {
+                    exprs.push(&x.rhs);
+                    exprs.push(&x.lhs);
+                }
+
+                Self::Stmt(x) if x.statements.iter().all(Stmt::is_pure) => (),
 
-            Self::Stmt(x) => x.statements.iter().all(Stmt::is_pure),
+                Self::Variable(_) => (),
 
-            Self::Variable(_) => true,
+                expr if expr.is_constant() => (),
 
-            _ => self.is_constant(),
+                _ => return false,
+            }
         }
+
+        true
     }
     /// Is the expression the unit `()` literal?
     #[inline(always)]
@@ -1740,24 +1937,32 @@ impl Expr {
     /// Is the expression a constant?
     #[inline]
     pub fn is_constant(&self) -> bool {
-        match self {
-            #[cfg(not(feature = "no_float"))]
-            Self::FloatConstant(_, _) => true,
+        // Literals can nest very deeply, so their items are checked with an explicit stack
+        let mut exprs: StaticVec<&Self> = Default::default();
+        exprs.push(self);
 
-            Self::DynamicConstant(_, _)
-            | Self::BoolConstant(_, _)
-            | Self::IntegerConstant(_, _)
-            | Self::CharConstant(_, _)
-            | Self::StringConstant(_, _)
-            | Self::FnPointer(_, _)
-            | Self::Unit(_) => true,
+        while let Some(expr) = exprs.pop() {
+            match expr {
+                #[cfg(not(feature = "no_float"))]
+                Self::FloatConstant(_, _) => (),
 
-            Self::InterpolatedString(x) | Self::Array(x, _) => x.iter().all(Self::is_constant),
+                Self::DynamicConstant(_, _)
+                | Self::BoolConstant(_, _)
+                | Self::IntegerConstant(_, _)
+                | Self::CharConstant(_, _)
+                | Self::StringConstant(_, _)
+                | Self::FnPointer(_, _)
+                | Self::Unit(_) => (),
 
-            Self::Map(x, _) => x.0.iter().map(|(_, expr)| expr).all(Self::is_constant),
+                Self::InterpolatedString(x) | Self::Array(x, _) => exprs.extend(x.iter()),
 
-            _ => false,
+                Self::Map(x, _) => exprs.extend(x.0.iter().map(|(_, expr)| expr)),
+
+                _ => return false,
+            }
         }
+
+        true
     }
     /// Is a particular [token][Token] allowed as a postfix operator to this expression?
     #[inline]
@@ -1879,6 +2084,179 @@ impl Expr {
     }
 }
 
+/// A node, or a constant value held by a node, detached from an [`AST`] to be dropped.
+enum DetachedNode {
+    Stmt(Stmt),
+    Expr(Expr),
+    #[cfg(any(not(feature = "no_index"), not(feature = "no_object")))]
+    Value(Dynamic),
+}
+
+impl DetachedNode {
+    /// Detach a [statement][Stmt] if it has child nodes.
+    #[inline(always)]
+    fn detach_stmt(stmt: &mut Stmt, nodes: &mut Vec<Self>) {
+        match stmt {
+            Stmt::Noop(_) | Stmt::Continue(_) | Stmt::Break(_) | Stmt::Return(_, None, _) => (),
+            #[cfg(not(feature = "no_module"))]
+            Stmt::Export(_, _) => (),
+            #[cfg(not(feature = "no_closure"))]
+            Stmt::Share(_) => (),
+            _ => nodes.push(Self::Stmt(mem::take(stmt))),
+        }
+    }
+    /// Detach an [expression][Expr] if it has child nodes.
+    #[inline(always)]
+    fn detach_expr(expr: &mut Expr, nodes: &mut Vec<Self>) {
+        match expr {
+            Expr::InterpolatedString(_)
+            | Expr::Stmt(_)
+            | Expr::FnCall(_, _)
+            | Expr::Dot(_, _)
+            | Expr::Index(_, _)
+            | Expr::And(_, _)
+            | Expr::Or(_, _)
+            | Expr::Custom(_, _) => nodes.push(Self::Expr(mem::take(expr))),
+            #[cfg(not(feature = "no_index"))]
+            Expr::Array(_, _) => nodes.push(Self::Expr(mem::take(expr))),
+            #[cfg(not(feature = "no_object"))]
+            Expr::Map(_, _) => nodes.push(Self::Expr(mem::take(expr))),
+            #[cfg(any(not(feature = "no_index"), not(feature = "no_object")))]
+            Expr::DynamicConstant(x, _) => Self::detach_value(x, nodes),
+            _ => (),
+        }
+    }
+    /// Detach a constant [value][Dynamic] if it is a container with items.
+    #[cfg(any(not(feature = "no_index"), not(feature = "no_object")))]
+    #[inline(always)]
+    fn detach_value(value: &mut Dynamic, nodes: &mut Vec<Self>) {
+        match value.0 {
+            #[cfg(not(feature = "no_index"))]
+            Union::Array(ref a, _) if !a.is_empty() => nodes.push(Self::Value(mem::take(value))),
+            #[cfg(not(feature = "no_object"))]
+            Union::Map(ref m, _) if !m.is_empty() => nodes.push(Self::Value(mem::take(value))),
+            _ => (),
+        }
+    }
+    /// Detach all the child nodes of a [statement][Stmt] that have child nodes of their own.
+    fn detach_stmt_children(stmt: &mut Stmt, nodes: &mut Vec<Self>) {
+        match stmt {
+            Stmt::If(e, x, _) => {
+                Self::detach_expr(e, nodes);
+                x.0.statements
+                    .iter_mut()
+                    .chain(x.1.statements.iter_mut())
+                    .for_each(|s| Self::detach_stmt(s, nodes));
+            }
+            Stmt::Switch(e, x, _) => {
+                Self::detach_expr(e, nodes);
+                x.0.values_mut()
+                    .flat_map(|block| block.statements.iter_mut())
+                    .chain(x.1.statements.iter_mut())
+                    .for_each(|s| Self::detach_stmt(s, nodes));
+            }
+            Stmt::While(e, s, _) | Stmt::Do(s, e, _, _) => {
+                Self::detach_expr(e, nodes);
+                s.statements
+                    .iter_mut()
+                    .for_each(|s| Self::detach_stmt(s, nodes));
+            }
+            Stmt::For(e, x, _) => {
+                Self::detach_expr(e, nodes);
+                x.1.statements
+                    .iter_mut()
+                    .for_each(|s| Self::detach_stmt(s, nodes));
+            }
+            Stmt::Let(e, _, _, _)
+            | Stmt::Const(e, _, _, _)
+            | Stmt::Expr(e)
+            | Stmt::Return(_, Some(e), _) => Self::detach_expr(e, nodes),
+            #[cfg(not(feature = "no_module"))]
+            Stmt::Import(e, _, _) => Self::detach_expr(e, nodes),
+            Stmt::Assignment(x, _) => {
+                Self::detach_expr(&mut x.0, nodes);
+                Self::detach_expr(&mut x.2, nodes);
+            }
+            Stmt::Block(x, _) => x.iter_mut().for_each(|s| Self::detach_stmt(s, nodes)),
+            Stmt::TryCatch(x, _, _) => {
+                x.0.statements
+                    .iter_mut()
+                    .chain(x.2.statements.iter_mut())
+                    .for_each(|s| Self::detach_stmt(s, nodes));
+            }
+            _ => (),
+        }
+    }
+    /// Detach all the child nodes of an [expression][Expr] that have child nodes of their own.
+    fn detach_expr_children(expr: &mut Expr, nodes: &mut Vec<Self>) {
+        match expr {
+            Expr::Stmt(x) => x
+                .statements
+                .iter_mut()
+                .for_each(|s| Self::detach_stmt(s, nodes)),
+            Expr::InterpolatedString(x) => x.iter_mut().for_each(|e| Self::detach_expr(e, nodes)),
+            #[cfg(not(feature = "no_index"))]
+            Expr::Array(x, _) => x.iter_mut().for_each(|e| Self::detach_expr(e, nodes)),
+            #[cfg(not(feature = "no_object"))]
+            Expr::Map(x, _) => {
+                x.0.iter_mut()
+                    .for_each(|(_, e)| Self::detach_expr(e, nodes))
+            }
+            Expr::Index(x, _) | Expr::Dot(x, _) | Expr::And(x, _) | Expr::Or(x, _) => {
+                Self::detach_expr(&mut x.lhs, nodes);
+                Self::detach_expr(&mut x.rhs, nodes);
+            }
+            Expr::FnCall(x, _) => x.args.iter_mut().for_each(|e| Self::detach_expr(e, nodes)),
+            Expr::Custom(x, _) => x
+                .keywords
+                .iter_mut()
+                .for_each(|e| Self::detach_expr(e, nodes)),
+            _ => (),
+        }
+    }
+    /// Drop detached nodes, detaching their children in turn, so that dropping a deeply-nested
+    /// [`AST`] only consumes heap memory instead of recursing.
+    fn drop_all(mut nodes: Vec<Self>) {
+        while let Some(node) = nodes.pop() {
+            // The node itself is dropped here, without any nested child nodes left to recurse into
+            match node {
+                Self::Stmt(mut stmt) => Self::detach_stmt_children(&mut stmt, &mut nodes),
+                Self::Expr(mut expr) => Self::detach_expr_children(&mut expr, &mut nodes),
+                #[cfg(any(not(feature = "no_index"), not(feature = "no_object")))]
+                Self::Value(mut value) => match value.0 {
+                    #[cfg(not(feature = "no_index"))]
+                    Union::Array(ref mut a, _) => {
+                        a.iter_mut().for_each(|v| Self::detach_value(v, &mut nodes))
+                    }
+                    #[cfg(not(feature = "no_object"))]
+                    Union::Map(ref mut m, _) => m
+                        .values_mut()
+                        .for_each(|v| Self::detach_value(v, &mut nodes)),
+                    _ => (),
+                },
+            }
+        }
+    }
+}
+
+impl Drop for Stmt {
+    #[inline]
+    fn drop(&mut self) {
+        let mut nodes = Vec::new();
+        DetachedNode::detach_stmt_children(self, &mut nodes);
+        DetachedNode::drop_all(nodes);
+    }
+}
+
+impl Drop for Expr {
+    #[inline]
+    fn drop(&mut self) {
+        let mut nodes = Vec::new();
+        DetachedNode::detach_expr_children(self, &mut nodes);
+        DetachedNode::drop_all(nodes);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     /// This test is to make sure no code changes increase the sizes of critical data structures.
@@ -1889,15 +2267,15 @@ mod tests {
 
         assert_eq!(size_of::<Dynamic>(), 16);
         assert_eq!(size_of::<Option<Dynamic>>(), 16);
-        assert_eq!(size_of::<Position>(), 4);
-        assert_eq!(size_of::<ast::Expr>(), 16);
-        assert_eq!(size_of::<Option<ast::Expr>>(), 16);
-        assert_eq!(size_of::<ast::Stmt>(), 32);
-        assert_eq!(size_of::<Option<ast::Stmt>>(), 32);
+        assert_eq!(size_of::<Position>(), 12);
+        assert_eq!(size_of::<ast::Expr>(), 24);
+        assert_eq!(size_of::<Option<ast::Expr>>(), 24);
+        assert_eq!(size_of::<ast::Stmt>(), 48);
+        assert_eq!(size_of::<Option<ast::Stmt>>(), 48);
         assert_eq!(size_of::<FnPtr>(), 80);
         assert_eq!(size_of::<Scope>(), 288);
-        assert_eq!(size_of::<LexError>(), 56);
-        assert_eq!(size_of::<ParseError>(), 16);
+        assert_eq!(size_of::<LexError>(), 48);
+        assert_eq!(size_of::<ParseError>(), 24);
         assert_eq!(size_of::<EvalAltResult>(), 72);
     }
 }
@@ -0,0 +1,205 @@
+#[cfg(feature = "serde")]
+#[cfg(feature = "metadata")]
+use rhai::{DocGenerator, Engine};
+
+#[cfg(feature = "serde")]
+#[cfg(feature = "metadata")]
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use std::process::exit;
+
+#[cfg(feature = "serde")]
+#[cfg(feature = "metadata")]
+fn print_usage() {
+    eprintln!("Usage: rhai-doc [options] <files or directories>...");
+    eprintln!();
+    eprintln!("Options:");
+    eprintln!("    --out <dir>              write documentation into <dir> (default: doc)");
+    eprintln!("    --format <format>        output format: html, markdown or both (default: html)");
+    eprintln!("    --title <text>           title of the documentation");
+    eprintln!("    --include-std            include functions in the standard packages");
+}
+
+/// Add all script files under a path, naming each module by its path relative to `root`.
+#[cfg(feature = "serde")]
+#[cfg(feature = "metadata")]
+fn add_files(root: &Path, path: PathBuf, files: &mut Vec<(String, PathBuf)>) {
+    if !path.is_dir() {
+        let relative = path.strip_prefix(root).unwrap_or(&path).with_extension("");
+
+        let name = relative
+            .iter()
+            .map(|s| s.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("::");
+
+        files.push((name, path));
+        return;
+    }
+
+    let mut entries: Vec<_> = match fs::read_dir(&path) {
+        Ok(entries) => entries.filter_map(|e| e.ok()).map(|e| e.path()).collect(),
+        Err(err) => {
+            eprintln!(
+                "Error reading directory: {}\n{}",
+                path.to_string_lossy(),
+                err
+            );
+            exit(1);
+        }
+    };
+
+    entries.sort();
+
+    for entry in entries {
+        if entry.is_dir() || entry.extension().is_some_and(|ext| ext == "rhai") {
+            add_files(root, entry, files);
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+#[cfg(feature = "metadata")]
+fn main() {
+    let mut out = None;
+    let mut format = None;
+    let mut title = None;
+    let mut include_std = false;
+    let mut files = Vec::new();
+
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        let target = match arg.as_str() {
+            "--out" => &mut out,
+            "--format" => &mut format,
+            "--title" => &mut title,
+            "--include-std" => {
+                include_std = true;
+                continue;
+            }
+            "--help" | "-h" => {
+                print_usage();
+                exit(0);
+            }
+            _ => {
+                let path = PathBuf::from(arg);
+
+                if path.is_dir() {
+                    add_files(&path.clone(), path, &mut files);
+                } else {
+                    let root = path.parent().map(Path::to_path_buf).unwrap_or_default();
+                    add_files(&root, path, &mut files);
+                }
+                continue;
+            }
+        };
+
+        match args.next() {
+            Some(value) => *target = Some(value),
+            None => {
+                eprintln!("Missing value after '{}'", arg);
+                print_usage();
+                exit(1);
+            }
+        }
+    }
+
+    let (markdown, html) = match format.as_deref() {
+        None | Some("html") => (false, true),
+        Some("markdown") | Some("md") => (true, false),
+        Some("both") => (true, true),
+        Some(format) => {
+            eprintln!("Invalid value for '--format': {}", format);
+            print_usage();
+            exit(1);
+        }
+    };
+
+    let engine = Engine::new();
+    let mut asts = Vec::new();
+
+    for (name, filename) in files {
+        let contents = match fs::read_to_string(&filename) {
+            Ok(contents) => contents,
+            Err(err) => {
+                eprintln!(
+                    "Error reading script file: {}\n{}",
+                    filename.to_string_lossy(),
+                    err
+                );
+                exit(1);
+            }
+        };
+
+        let contents = if contents.starts_with("#!") {
+            // Skip shebang
+            &contents[contents.find('\n').unwrap_or(0)..]
+        } else {
+            &contents[..]
+        };
+
+        match engine.compile(contents) {
+            Ok(ast) => asts.push((name, ast)),
+            Err(err) => {
+                eprintln!("{}: {}", filename.to_string_lossy(), err);
+                exit(1);
+            }
+        }
+    }
+
+    let mut docs = DocGenerator::new(&engine);
+
+    docs.set_include_global(include_std);
+
+    if let Some(title) = title {
+        docs.set_title(title);
+    }
+
+    for (name, ast) in asts.iter() {
+        docs.add_script_module(name.as_str(), ast);
+    }
+
+    let out = PathBuf::from(out.unwrap_or_else(|| "doc".into()));
+    let mut pages = Vec::new();
+
+    if markdown {
+        pages.extend(docs.gen_markdown());
+    }
+    if html {
+        pages.extend(docs.gen_html());
+    }
+
+    if let Err(err) = fs::create_dir_all(&out) {
+        eprintln!(
+            "Error creating directory: {}\n{}",
+            out.to_string_lossy(),
+            err
+        );
+        exit(1);
+    }
+
+    for (file, contents) in pages.iter() {
+        let path = out.join(file);
+
+        if let Err(err) = fs::write(&path, contents) {
+            eprintln!("Error writing file: {}\n{}", path.to_string_lossy(), err);
+            exit(1);
+        }
+    }
+
+    println!(
+        "Generated {} file(s) in {}",
+        pages.len(),
+        out.to_string_lossy()
+    );
+}
+
+#[cfg(not(all(feature = "serde", feature = "metadata")))]
+fn main() {
+    eprintln!("rhai-doc requires the 'serde' and 'metadata' features.");
+    exit(1);
+}
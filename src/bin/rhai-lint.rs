@@ -0,0 +1,161 @@
+use rhai::{Engine, Lint, LintLevel, LintWarning, Linter, Position};
+
+use std::{env, fs::File, io::Read, path::Path, process::exit};
+
+fn eprint_warning(lines: &[&str], pos: Position, msg: &str) {
+    if pos.is_none() {
+        // No position
+        eprintln!("{}", msg);
+        eprintln!("");
+        return;
+    }
+
+    let line = pos.line().unwrap();
+    let line_no = format!("{}: ", line);
+
+    eprintln!("{}{}", line_no, lines[line - 1]);
+    eprintln!(
+        "{:>1$} {2}",
+        "^",
+        line_no.len() + pos.position().unwrap(),
+        msg
+    );
+    eprintln!("");
+}
+
+fn eprint_header(filename: &str) {
+    eprintln!("{:=<1$}", "", filename.len());
+    eprintln!("{}", filename);
+    eprintln!("{:=<1$}", "", filename.len());
+    eprintln!("");
+}
+
+fn print_usage() {
+    eprintln!("Usage: rhai-lint [--allow <lint>] [--warn <lint>] [--deny <lint>] <files>...");
+    eprintln!("");
+    eprintln!("Available lints:");
+
+    for lint in Lint::ALL.iter() {
+        eprintln!("    {}", lint);
+    }
+}
+
+fn main() {
+    let engine = Engine::new();
+    let mut linter = Linter::new(&engine);
+    let mut files = Vec::new();
+
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        let level = match arg.as_str() {
+            "--allow" | "-A" => LintLevel::Allow,
+            "--warn" | "-W" => LintLevel::Warn,
+            "--deny" | "-D" => LintLevel::Deny,
+            "--help" | "-h" => {
+                print_usage();
+                exit(0);
+            }
+            _ => {
+                files.push(arg);
+                continue;
+            }
+        };
+
+        match args.next().as_deref().map(Lint::from_id) {
+            Some(Some(lint)) => {
+                linter.set_level(lint, level);
+            }
+            Some(None) | None => {
+                eprintln!("Missing or unknown lint after '{}'", arg);
+                print_usage();
+                exit(1);
+            }
+        }
+    }
+
+    if files.is_empty() {
+        print_usage();
+        exit(1);
+    }
+
+    let mut failed = false;
+    let mut contents = String::new();
+
+    for filename in files {
+        let filename = match Path::new(&filename).canonicalize() {
+            Err(err) => {
+                eprintln!("Error script file path: {}\n{}", filename, err);
+                exit(1);
+            }
+            Ok(f) => f,
+        };
+
+        let mut f = match File::open(&filename) {
+            Err(err) => {
+                eprintln!(
+                    "Error reading script file: {}\n{}",
+                    filename.to_string_lossy(),
+                    err
+                );
+                exit(1);
+            }
+            Ok(f) => f,
+        };
+
+        contents.clear();
+
+        if let Err(err) = f.read_to_string(&mut contents) {
+            eprintln!(
+                "Error reading script file: {}\n{}",
+                filename.to_string_lossy(),
+                err
+            );
+            exit(1);
+        }
+
+        let contents = if contents.starts_with("#!") {
+            // Skip shebang
+            &contents[contents.find('\n').unwrap_or(0)..]
+        } else {
+            &contents[..]
+        };
+
+        let lines: Vec<_> = contents.split('\n').collect();
+
+        let warnings = match linter.lint(contents) {
+            Ok(warnings) if warnings.is_empty() => continue,
+            Ok(warnings) => warnings,
+            Err(err) => {
+                failed = true;
+                eprint_header(&filename.to_string_lossy());
+                eprint_warning(&lines, err.1, &format!("error: {}", err.0));
+                continue;
+            }
+        };
+
+        eprint_header(&filename.to_string_lossy());
+
+        for LintWarning {
+            lint,
+            level,
+            message,
+            pos,
+        } in warnings
+        {
+            let kind = match level {
+                LintLevel::Deny => {
+                    failed = true;
+                    "error"
+                }
+                _ => "warning",
+            };
+
+            eprint_warning(&lines, pos, &format!("{}: {} [{}]", kind, message, lint));
+        }
+    }
+
+    if failed {
+        exit(1);
+    }
+}
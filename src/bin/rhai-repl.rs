@@ -1,59 +1,758 @@
-use rhai::{Dynamic, Engine, EvalAltResult, Module, Scope, AST};
+use rhai::{Dynamic, Engine, EvalAltResult, Module, Scope, Snippet, AST};
 
 use std::{
     env,
-    fs::File,
-    io::{stdin, stdout, Read, Write},
-    path::Path,
+    fs::{self, File, OpenOptions},
+    io::{stdin, stdout, IsTerminal, Read, Write},
+    path::{Path, PathBuf},
     process::exit,
+    time::Instant,
 };
 
+/// Name of the history file, placed in the user's home directory.
+const HISTORY_FILE: &str = ".rhai_repl_history";
+
+/// Maximum number of entries kept in the history.
+const MAX_HISTORY: usize = 1000;
+
+/// Prompt for the first line of input.
+const PROMPT: &str = "rhai-repl> ";
+
+/// Prompt for continuation lines of input.
+const PROMPT_CONTINUE: &str = "         > ";
+
+/// Meta-commands, for completion.
+const COMMANDS: &[&str] = &[
+    ":help",
+    ":quit",
+    ":exit",
+    ":scope",
+    ":functions",
+    ":ast",
+    ":astu",
+    ":load",
+    ":reset",
+    ":optimize",
+    ":time",
+    ":type",
+    ":doc",
+];
+
+/// Keywords, for completion.
+const KEYWORDS: &[&str] = &[
+    "let",
+    "const",
+    "if",
+    "else",
+    "switch",
+    "while",
+    "loop",
+    "do",
+    "until",
+    "for",
+    "in",
+    "break",
+    "continue",
+    "return",
+    "throw",
+    "try",
+    "catch",
+    "fn",
+    "private",
+    "import",
+    "export",
+    "as",
+    "true",
+    "false",
+    "this",
+    "type_of",
+    "print",
+    "debug",
+    "eval",
+    "Fn",
+    "call",
+    "curry",
+    "is_def_var",
+    "is_def_fn",
+    "is_shared",
+];
+
 /// Pretty-print error.
-fn print_error(input: &str, mut err: EvalAltResult) {
-    let lines: Vec<_> = input.trim().split('\n').collect();
-    let pos = err.take_position();
+fn print_error(input: &str, err: EvalAltResult) {
+    // Print error
+    println!("{}", Snippet::new(input).render_error(&err));
 
-    let line_no = if lines.len() > 1 {
-        if pos.is_none() {
-            "".to_string()
-        } else {
-            format!("{}: ", pos.line().unwrap())
-        }
-    } else {
-        "".to_string()
-    };
+    // Print call stack
+    let stack = err.backtrace();
 
-    // Print error position
-    if pos.is_none() {
-        // No position
-        println!("{}", err);
-    } else {
-        // Specific position - print line text
-        println!("{}{}", line_no, lines[pos.line().unwrap() - 1]);
-
-        // Display position marker
-        println!(
-            "{0:>1$} {2}",
-            "^",
-            line_no.len() + pos.position().unwrap(),
-            err
-        );
+    if !stack.is_empty() {
+        println!("Call stack (most recent call first):");
+
+        for frame in stack {
+            println!("    {}", frame);
+        }
     }
 }
 
 /// Print help text.
 fn print_help() {
-    println!("help       => print this help");
-    println!("quit, exit => quit");
-    println!("scope      => print all variables in the scope");
+    println!(":help             => print this help");
+    println!(":quit, :exit      => quit");
+    println!(":scope            => print all variables in the scope");
     #[cfg(feature = "metadata")]
-    println!("functions  => print all functions defined");
-    println!("ast        => print the last AST (optimized)");
-    println!("astu       => print the last raw, un-optimized AST");
-    println!(r"end a line with '\' to continue to the next line.");
+    println!(":functions        => print all functions defined");
+    println!(":ast              => print the last AST (optimized)");
+    println!(":astu             => print the last raw, un-optimized AST");
+    println!(":load <file>      => run a script file");
+    println!(":reset            => clear all variables and functions");
+    #[cfg(not(feature = "no_optimize"))]
+    println!(":optimize <level> => set the optimization level (none, simple or full)");
+    println!(":time             => toggle printing the time taken to run each input");
+    println!(":type <expr>      => print the type of the value of an expression");
+    println!(":doc <function>   => print the signatures and documentation of a function");
+    println!("input with unbalanced brackets continues onto the next line.");
+    println!("press Tab to complete names, Up/Down to recall history.");
     println!();
 }
 
+/// Is a piece of script incomplete, i.e. ending with unbalanced brackets,
+/// inside a multi-line string or inside a block comment?
+fn is_incomplete(script: &str) -> bool {
+    let mut depth = 0_isize;
+    let mut comment_depth = 0_usize;
+    let mut chars = script.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        if comment_depth > 0 {
+            match (ch, chars.peek()) {
+                ('*', Some('/')) => {
+                    chars.next();
+                    comment_depth -= 1;
+                }
+                ('/', Some('*')) => {
+                    chars.next();
+                    comment_depth += 1;
+                }
+                _ => (),
+            }
+            continue;
+        }
+
+        match ch {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            '/' if chars.peek() == Some(&'/') => {
+                // Skip line comment
+                while chars.peek().is_some_and(|&ch| ch != '\n') {
+                    chars.next();
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                comment_depth += 1;
+            }
+            '"' | '\'' | '`' => {
+                // Skip string or character literal
+                let mut escaped = false;
+
+                loop {
+                    match chars.next() {
+                        // A multi-line string is incomplete
+                        None => return ch == '`' || escaped,
+                        Some('\\') if ch != '`' => escaped = !escaped,
+                        Some('\n') if ch != '`' && !escaped => break,
+                        Some(c) if c == ch && !escaped => break,
+                        Some(_) => escaped = false,
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    depth > 0 || comment_depth > 0
+}
+
+/// Result of reading a line of input.
+enum ReadLine {
+    /// A line of input.
+    Line(String),
+    /// Input is interrupted by `Ctrl-C`.
+    Interrupted,
+    /// End of input.
+    Eof,
+}
+
+/// Terminal set to non-canonical mode without echo, so key presses can be read one by one.
+/// The original terminal settings are restored when dropped.
+///
+/// `stty` is used to avoid depending on platform-specific crates.
+struct RawMode(String);
+
+impl RawMode {
+    /// Switch the terminal attached to standard input into raw mode.
+    #[cfg(unix)]
+    fn enable() -> Option<Self> {
+        use std::process::{Command, Stdio};
+
+        let saved = Command::new("stty")
+            .arg("-g")
+            .stdin(Stdio::inherit())
+            .output()
+            .ok()
+            .filter(|output| output.status.success())?;
+        let saved = String::from_utf8(saved.stdout).ok()?.trim().to_string();
+
+        Command::new("stty")
+            .args(["-icanon", "-echo", "-isig", "-iexten", "min", "1"])
+            .stdin(Stdio::inherit())
+            .status()
+            .ok()
+            .filter(|status| status.success())
+            .map(|_| Self(saved))
+    }
+    /// Raw mode is only supported on Unix.
+    #[cfg(not(unix))]
+    fn enable() -> Option<Self> {
+        None
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        let _ = std::process::Command::new("stty")
+            .arg(&self.0)
+            .stdin(std::process::Stdio::inherit())
+            .status();
+    }
+}
+
+/// A simple line editor with history and completion.
+///
+/// Falls back to reading plain lines when standard input is not a terminal.
+struct LineEditor {
+    /// Lines entered, oldest first.
+    history: Vec<String>,
+    /// File to persist the history into, if any.
+    history_file: Option<PathBuf>,
+}
+
+impl LineEditor {
+    /// Create a new [`LineEditor`], loading the history from the history file in the home directory.
+    fn new() -> Self {
+        let history_file = env::var_os("HOME")
+            .or_else(|| env::var_os("USERPROFILE"))
+            .map(|home| Path::new(&home).join(HISTORY_FILE));
+
+        let mut history: Vec<String> = history_file
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|text| text.lines().map(Into::into).collect())
+            .unwrap_or_default();
+
+        if history.len() > MAX_HISTORY {
+            history.drain(..history.len() - MAX_HISTORY);
+
+            if let Some(ref path) = history_file {
+                let _ = fs::write(path, history.join("\n") + "\n");
+            }
+        }
+
+        Self {
+            history,
+            history_file,
+        }
+    }
+    /// Add a line to the history, appending it to the history file.
+    fn add_history(&mut self, line: &str) {
+        if line.trim().is_empty() || self.history.last().is_some_and(|s| s == line) {
+            return;
+        }
+
+        self.history.push(line.into());
+
+        if let Some(ref path) = self.history_file {
+            if let Ok(mut f) = OpenOptions::new().create(true).append(true).open(path) {
+                let _ = writeln!(f, "{}", line);
+            }
+        }
+    }
+    /// Read a line of input.
+    ///
+    /// `complete` is called with the text before the cursor when Tab is pressed.
+    /// It returns the byte offset where the word to complete starts, and the candidates.
+    fn read_line(
+        &mut self,
+        prompt: &str,
+        complete: &dyn Fn(&str) -> (usize, Vec<String>),
+    ) -> ReadLine {
+        let raw = if stdin().is_terminal() {
+            RawMode::enable()
+        } else {
+            None
+        };
+
+        if raw.is_none() {
+            print!("{}", prompt);
+            stdout().flush().expect("couldn't flush stdout");
+
+            let mut line = String::new();
+
+            return match stdin().read_line(&mut line) {
+                Ok(0) => ReadLine::Eof,
+                Ok(_) => ReadLine::Line(line.trim_end_matches(&['\r', '\n'][..]).into()),
+                Err(err) => panic!("input error: {}", err),
+            };
+        }
+
+        let mut buf: Vec<char> = Vec::new();
+        let mut pos = 0;
+        let mut history_index = self.history.len();
+        let mut current = String::new();
+
+        redraw(prompt, &buf, pos);
+
+        loop {
+            let byte = match read_byte() {
+                Some(byte) => byte,
+                None if buf.is_empty() => return ReadLine::Eof,
+                None => 13,
+            };
+
+            match byte {
+                // Enter
+                b'\r' | b'\n' => {
+                    println!();
+                    return ReadLine::Line(buf.into_iter().collect());
+                }
+                // Ctrl-C
+                3 => {
+                    println!("^C");
+                    return ReadLine::Interrupted;
+                }
+                // Ctrl-D
+                4 if buf.is_empty() => {
+                    println!();
+                    return ReadLine::Eof;
+                }
+                4 if pos < buf.len() => {
+                    buf.remove(pos);
+                }
+                // Ctrl-A
+                1 => pos = 0,
+                // Ctrl-E
+                5 => pos = buf.len(),
+                // Ctrl-B
+                2 => pos = pos.saturating_sub(1),
+                // Ctrl-F
+                6 => pos = (pos + 1).min(buf.len()),
+                // Ctrl-K
+                11 => buf.truncate(pos),
+                // Ctrl-U
+                21 => {
+                    buf.drain(..pos);
+                    pos = 0;
+                }
+                // Ctrl-W
+                23 => {
+                    let mut start = pos;
+                    while start > 0 && buf[start - 1].is_whitespace() {
+                        start -= 1;
+                    }
+                    while start > 0 && !buf[start - 1].is_whitespace() {
+                        start -= 1;
+                    }
+                    buf.drain(start..pos);
+                    pos = start;
+                }
+                // Ctrl-L
+                12 => print!("\x1b[2J\x1b[H"),
+                // Backspace
+                8 | 127 if pos > 0 => {
+                    pos -= 1;
+                    buf.remove(pos);
+                }
+                // Tab
+                b'\t' => {
+                    let before: String = buf[..pos].iter().collect();
+                    let (start, mut candidates) = complete(&before);
+                    let word = &before[start..];
+
+                    candidates.retain(|c| c.starts_with(word));
+                    candidates.sort();
+                    candidates.dedup();
+
+                    let prefix = common_prefix(&candidates);
+
+                    if candidates.is_empty() {
+                        print!("\x07");
+                    } else if prefix.len() > word.len() {
+                        let insert: Vec<char> = prefix[word.len()..].chars().collect();
+                        let len = insert.len();
+                        buf.splice(pos..pos, insert);
+                        pos += len;
+                    } else if candidates.len() > 1 {
+                        println!();
+                        println!("{}", candidates.join("  "));
+                    }
+                }
+                // Escape sequence
+                27 => match (read_byte(), read_byte()) {
+                    (Some(b'['), Some(b'A')) | (Some(b'O'), Some(b'A')) if history_index > 0 => {
+                        if history_index == self.history.len() {
+                            current = buf.iter().collect();
+                        }
+                        history_index -= 1;
+                        buf = self.history[history_index].chars().collect();
+                        pos = buf.len();
+                    }
+                    (Some(b'['), Some(b'B')) | (Some(b'O'), Some(b'B'))
+                        if history_index < self.history.len() =>
+                    {
+                        history_index += 1;
+                        buf = match self.history.get(history_index) {
+                            Some(line) => line.chars().collect(),
+                            None => current.chars().collect(),
+                        };
+                        pos = buf.len();
+                    }
+                    (Some(b'['), Some(b'C')) | (Some(b'O'), Some(b'C')) => {
+                        pos = (pos + 1).min(buf.len())
+                    }
+                    (Some(b'['), Some(b'D')) | (Some(b'O'), Some(b'D')) => {
+                        pos = pos.saturating_sub(1)
+                    }
+                    (Some(b'['), Some(b'H')) | (Some(b'O'), Some(b'H')) => pos = 0,
+                    (Some(b'['), Some(b'F')) | (Some(b'O'), Some(b'F')) => pos = buf.len(),
+                    (Some(b'['), Some(digit)) if digit.is_ascii_digit() => {
+                        // Sequences in the form of ESC [ <n> ~
+                        let mut code = vec![digit];
+                        while let Some(byte) = read_byte() {
+                            if byte == b'~' {
+                                break;
+                            }
+                            code.push(byte);
+                        }
+                        match &code[..] {
+                            b"3" if pos < buf.len() => {
+                                buf.remove(pos);
+                            }
+                            b"1" | b"7" => pos = 0,
+                            b"4" | b"8" => pos = buf.len(),
+                            _ => (),
+                        }
+                    }
+                    _ => (),
+                },
+                // Printable character, in UTF-8
+                byte if byte >= 32 && byte != 127 => {
+                    let len = match byte {
+                        0xf0..=0xff => 4,
+                        0xe0..=0xef => 3,
+                        0xc0..=0xdf => 2,
+                        _ => 1,
+                    };
+                    let mut bytes = vec![byte];
+                    (1..len)
+                        .filter_map(|_| read_byte())
+                        .for_each(|b| bytes.push(b));
+
+                    if let Ok(text) = String::from_utf8(bytes) {
+                        for ch in text.chars() {
+                            buf.insert(pos, ch);
+                            pos += 1;
+                        }
+                    }
+                }
+                _ => (),
+            }
+
+            redraw(prompt, &buf, pos);
+        }
+    }
+}
+
+/// Read a byte from standard input.
+fn read_byte() -> Option<u8> {
+    let mut byte = [0_u8];
+
+    match stdin().lock().read(&mut byte) {
+        Ok(1) => Some(byte[0]),
+        _ => None,
+    }
+}
+
+/// Redraw the line being edited, placing the cursor at a position.
+fn redraw(prompt: &str, buf: &[char], pos: usize) {
+    let line: String = buf.iter().collect();
+    print!("\r{}{}\x1b[K", prompt, line);
+
+    if pos < buf.len() {
+        print!("\x1b[{}D", buf.len() - pos);
+    }
+
+    stdout().flush().expect("couldn't flush stdout");
+}
+
+/// Longest common prefix of a list of strings.
+fn common_prefix(candidates: &[String]) -> String {
+    let first = match candidates.first() {
+        Some(first) => first,
+        None => return String::new(),
+    };
+
+    let mut prefix: &str = first;
+
+    for candidate in &candidates[1..] {
+        while !candidate.starts_with(prefix) {
+            let mut chars = prefix.chars();
+            chars.next_back();
+            prefix = chars.as_str();
+        }
+    }
+
+    prefix.into()
+}
+
+/// State of the REPL.
+struct Repl {
+    engine: Engine,
+    scope: Scope<'static>,
+    /// All functions defined so far.
+    main_ast: AST,
+    /// The last raw, un-optimized AST.
+    ast_u: AST,
+    /// The last AST (optimized).
+    ast: AST,
+    #[cfg(not(feature = "no_optimize"))]
+    optimization_level: rhai::OptimizationLevel,
+    /// Print the time taken to run each input?
+    show_time: bool,
+}
+
+impl Repl {
+    /// Candidates for completing the word at the end of a piece of text.
+    fn complete(&self, text: &str) -> (usize, Vec<String>) {
+        // Meta-commands
+        if text.starts_with(':') && !text.contains(char::is_whitespace) {
+            return (0, COMMANDS.iter().map(|&s| s.into()).collect());
+        }
+
+        let start = text
+            .rfind(|ch: char| !ch.is_alphanumeric() && ch != '_')
+            .map_or(0, |i| i + 1);
+
+        let mut candidates: Vec<String> = KEYWORDS.iter().map(|&s| s.into()).collect();
+
+        candidates.extend(self.scope.iter_raw().map(|(name, _, _)| name.to_string()));
+
+        #[cfg(not(feature = "no_function"))]
+        candidates.extend(self.main_ast.iter_functions().map(|f| f.name.to_string()));
+
+        #[cfg(feature = "metadata")]
+        candidates.extend(
+            self.engine
+                .gen_fn_signatures(true)
+                .into_iter()
+                .filter_map(|sig| sig.split('(').next().map(|name| name.to_string()))
+                .filter(|name| {
+                    name.starts_with(|ch: char| ch.is_alphabetic() || ch == '_')
+                        && !name.contains('$')
+                }),
+        );
+
+        (start, candidates)
+    }
+    /// Run a compiled script, keeping the functions it defines.
+    fn run(&mut self, r: AST) -> Result<Dynamic, Box<EvalAltResult>> {
+        let engine = &self.engine;
+        let scope = &mut self.scope;
+        let main_ast = &mut self.main_ast;
+        let ast = &mut self.ast;
+
+        self.ast_u = r.clone();
+
+        #[cfg(not(feature = "no_optimize"))]
+        {
+            *ast = engine.optimize_ast(scope, r, self.optimization_level);
+        }
+
+        #[cfg(feature = "no_optimize")]
+        {
+            *ast = r;
+        }
+
+        // Merge the AST into the main
+        *main_ast += ast.clone();
+
+        // Evaluate
+        let result = engine.eval_ast_with_scope::<Dynamic>(scope, main_ast);
+
+        // Throw away all the statements, leaving only the functions
+        main_ast.clear_statements();
+
+        result
+    }
+    /// Run a script and print the result.
+    fn run_and_print(&mut self, script: &str) {
+        let start = Instant::now();
+
+        let result = match self.engine.compile_with_scope(&self.scope, script) {
+            Ok(ast) => self.run(ast),
+            Err(err) => {
+                println!();
+                println!("{}", Snippet::new(script).render_parse_error(&err));
+                println!();
+                return;
+            }
+        };
+
+        if self.show_time {
+            println!("Time taken: {:?}", start.elapsed());
+        }
+
+        match result {
+            Ok(result) if !result.is::<()>() => {
+                println!("=> {:?}", result);
+                println!();
+            }
+            Ok(_) => (),
+            Err(err) => {
+                println!();
+                print_error(script, *err);
+                println!();
+            }
+        }
+    }
+    /// Run a meta-command. Returns `false` to quit.
+    fn run_command(&mut self, command: &str, arg: &str) -> bool {
+        match command {
+            "help" => print_help(),
+            "exit" | "quit" => return false,
+            "scope" => {
+                self.scope
+                    .iter_raw()
+                    .enumerate()
+                    .for_each(|(i, (name, constant, value))| {
+                        println!(
+                            "[{}] {}{}{} = {:?}",
+                            i + 1,
+                            if constant { "const " } else { "" },
+                            name,
+                            if value.is_shared() { " (shared)" } else { "" },
+                            *value.read_lock::<Dynamic>().unwrap(),
+                        )
+                    });
+                println!();
+            }
+            // print the last un-optimized AST
+            "astu" => println!("{:#?}\n", self.ast_u),
+            // print the last AST
+            "ast" => println!("{:#?}\n", self.ast),
+            #[cfg(feature = "metadata")]
+            "functions" => {
+                // print a list of all registered functions
+                self.engine
+                    .gen_fn_signatures(false)
+                    .into_iter()
+                    .for_each(|f| println!("{}", f));
+
+                #[cfg(not(feature = "no_function"))]
+                self.main_ast
+                    .iter_functions()
+                    .for_each(|f| println!("{}", f));
+
+                println!();
+            }
+            "load" if arg.is_empty() => println!("Usage: :load <file>\n"),
+            "load" => match fs::read_to_string(arg) {
+                Ok(contents) => self.run_and_print(&contents),
+                Err(err) => println!("Error reading script file: {}\n{}\n", arg, err),
+            },
+            "reset" => {
+                self.scope.clear();
+                self.main_ast = Default::default();
+                self.ast_u = Default::default();
+                self.ast = Default::default();
+                println!("Scope and functions cleared.\n");
+            }
+            #[cfg(not(feature = "no_optimize"))]
+            "optimize" => {
+                use rhai::OptimizationLevel;
+
+                match arg {
+                    "" => (),
+                    "none" => self.optimization_level = OptimizationLevel::None,
+                    "simple" => self.optimization_level = OptimizationLevel::Simple,
+                    "full" => self.optimization_level = OptimizationLevel::Full,
+                    _ => {
+                        println!("Usage: :optimize <none|simple|full>\n");
+                        return true;
+                    }
+                }
+                println!("Optimization level: {:?}\n", self.optimization_level);
+            }
+            "time" => {
+                self.show_time = !self.show_time;
+                println!("Timing is {}.\n", if self.show_time { "on" } else { "off" });
+            }
+            "type" if arg.is_empty() => println!("Usage: :type <expression>\n"),
+            "type" => match self.engine.compile_expression_with_scope(&self.scope, arg) {
+                Ok(ast) => match self
+                    .engine
+                    .eval_ast_with_scope::<Dynamic>(&mut self.scope, &ast)
+                {
+                    Ok(value) => println!("{}\n", self.engine.map_type_name(value.type_name())),
+                    Err(err) => {
+                        print_error(arg, *err);
+                        println!();
+                    }
+                },
+                Err(err) => println!("{}\n", Snippet::new(arg).render_parse_error(&err)),
+            },
+            "doc" if arg.is_empty() => println!("Usage: :doc <function>\n"),
+            "doc" => self.print_doc(arg),
+            _ => println!("Unknown command: :{}. Type ':help' for help.\n", command),
+        }
+
+        true
+    }
+    /// Print the signatures and documentation of all functions with a particular name.
+    fn print_doc(&self, name: &str) {
+        let mut found = false;
+
+        #[cfg(not(feature = "no_function"))]
+        self.main_ast
+            .iter_functions()
+            .filter(|f| f.name == name)
+            .for_each(|f| {
+                f.comments.iter().for_each(|c| println!("{}", c));
+                println!("fn {}", f);
+                found = true;
+            });
+
+        #[cfg(feature = "metadata")]
+        self.engine
+            .gen_fn_signatures(true)
+            .into_iter()
+            .filter(|sig| {
+                let fn_name = sig.split('(').next().unwrap_or("");
+                fn_name == name || fn_name.rsplit("::").next() == Some(name)
+            })
+            .for_each(|sig| {
+                println!("fn {}", sig);
+                found = true;
+            });
+
+        if found {
+            println!();
+        } else {
+            println!("No function named '{}'.\n", name);
+        }
+    }
+}
+
 fn main() {
     println!("Rhai REPL tool");
     println!("==============");
@@ -101,13 +800,18 @@ fn main() {
                 exit(1);
             }
 
-            let module = match engine
-                .compile(&contents)
-                .map_err(|err| err.into())
-                .and_then(|mut ast| {
+            let snippet = Snippet::new(&contents);
+
+            let module = match engine.compile(&contents) {
+                Ok(mut ast) => {
                     ast.set_source(filename.to_string_lossy());
                     Module::eval_ast_as_new(Default::default(), &ast, &engine)
-                }) {
+                        .map_err(|err| snippet.render_error(&err))
+                }
+                Err(err) => Err(snippet.render_parse_error(&err)),
+            };
+
+            let module = match module {
                 Err(err) => {
                     let filename = filename.to_string_lossy();
 
@@ -116,7 +820,7 @@ fn main() {
                     eprintln!("{:=<1$}", "", filename.len());
                     eprintln!("");
 
-                    print_error(&contents, *err);
+                    eprintln!("{}", err);
                     exit(1);
                 }
                 Ok(m) => m,
@@ -147,147 +851,75 @@ fn main() {
         engine.set_module_resolver(resolver);
     }
 
-    // Make Engine immutable
-    let engine = engine;
+    let mut repl = Repl {
+        engine,
+        scope: Scope::new(),
+        main_ast: Default::default(),
+        ast_u: Default::default(),
+        ast: Default::default(),
+        #[cfg(not(feature = "no_optimize"))]
+        optimization_level: rhai::OptimizationLevel::Simple,
+        show_time: false,
+    };
 
-    // Create scope
-    let mut scope = Scope::new();
+    let mut editor = LineEditor::new();
 
     // REPL loop
     let mut input = String::new();
-    let mut main_ast: AST = Default::default();
-    let mut ast_u: AST = Default::default();
-    let mut ast: AST = Default::default();
 
-    'main_loop: loop {
-        print!("rhai-repl> ");
-        stdout().flush().expect("couldn't flush stdout");
-
-        input.clear();
-
-        loop {
-            match stdin().read_line(&mut input) {
-                Ok(0) => break 'main_loop,
-                Ok(_) => (),
-                Err(err) => panic!("input error: {}", err),
-            }
-
-            let line = input.as_str().trim_end();
+    loop {
+        let prompt = if input.is_empty() {
+            PROMPT
+        } else {
+            PROMPT_CONTINUE
+        };
 
-            // Allow line continuation
-            if line.ends_with('\\') {
-                let len = line.len();
-                input.truncate(len - 1);
-                input.push('\n');
-            } else {
-                break;
+        let line = match editor.read_line(prompt, &|text| repl.complete(text)) {
+            ReadLine::Line(line) => line,
+            ReadLine::Interrupted => {
+                input.clear();
+                continue;
             }
+            ReadLine::Eof => break,
+        };
 
-            print!("> ");
-            stdout().flush().expect("couldn't flush stdout");
-        }
+        editor.add_history(&line);
 
-        let script = input.trim();
-
-        if script.is_empty() {
-            continue;
-        }
+        // Implement meta-commands
+        if input.is_empty() {
+            let line = line.trim();
 
-        // Implement standard commands
-        match script {
-            "help" => {
-                print_help();
-                continue;
-            }
-            "exit" | "quit" => break, // quit
-            "scope" => {
-                scope
-                    .iter_raw()
-                    .enumerate()
-                    .for_each(|(i, (name, constant, value))| {
-                        println!(
-                            "[{}] {}{}{} = {:?}",
-                            i + 1,
-                            if constant { "const " } else { "" },
-                            name,
-                            if value.is_shared() { " (shared)" } else { "" },
-                            *value.read_lock::<Dynamic>().unwrap(),
-                        )
-                    });
-                println!();
-                continue;
-            }
-            "astu" => {
-                // print the last un-optimized AST
-                println!("{:#?}\n", ast_u);
-                continue;
-            }
-            "ast" => {
-                // print the last AST
-                println!("{:#?}\n", ast);
-                continue;
-            }
-            #[cfg(feature = "metadata")]
-            "functions" => {
-                // print a list of all registered functions
-                engine
-                    .gen_fn_signatures(false)
-                    .into_iter()
-                    .for_each(|f| println!("{}", f));
+            // Commands without the ':' prefix are kept for compatibility
+            let command = match line {
+                "help" | "exit" | "quit" | "scope" | "functions" | "ast" | "astu" => Some(line),
+                _ => line.strip_prefix(':'),
+            };
 
-                #[cfg(not(feature = "no_function"))]
-                main_ast.iter_functions().for_each(|f| println!("{}", f));
+            if let Some(command) = command {
+                let mut parts = command.splitn(2, char::is_whitespace);
+                let name = parts.next().unwrap_or("");
+                let arg = parts.next().unwrap_or("").trim();
 
-                println!();
+                if !repl.run_command(name, arg) {
+                    break;
+                }
                 continue;
             }
-            // "json" => {
-            //     println!(
-            //         "{}",
-            //         engine
-            //             .gen_fn_metadata_with_ast_to_json(&main_ast, true)
-            //             .unwrap()
-            //     );
-            //     continue;
-            // }
-            _ => (),
         }
 
-        match engine
-            .compile_with_scope(&scope, &script)
-            .map_err(Into::into)
-            .and_then(|r| {
-                ast_u = r.clone();
-
-                #[cfg(not(feature = "no_optimize"))]
-                {
-                    ast = engine.optimize_ast(&scope, r, rhai::OptimizationLevel::Simple);
-                }
+        input.push_str(&line);
+        input.push('\n');
 
-                #[cfg(feature = "no_optimize")]
-                {
-                    ast = r;
-                }
+        // Continue onto the next line when brackets are unbalanced
+        if is_incomplete(&input) {
+            continue;
+        }
 
-                // Merge the AST into the main
-                main_ast += ast.clone();
+        let script = input.trim().to_string();
+        input.clear();
 
-                // Evaluate
-                engine.eval_ast_with_scope::<Dynamic>(&mut scope, &main_ast)
-            }) {
-            Ok(result) if !result.is::<()>() => {
-                println!("=> {:?}", result);
-                println!();
-            }
-            Ok(_) => (),
-            Err(err) => {
-                println!();
-                print_error(&input, *err);
-                println!();
-            }
+        if !script.is_empty() {
+            repl.run_and_print(&script);
         }
-
-        // Throw away all the statements, leaving only the functions
-        main_ast.clear_statements();
     }
 }
@@ -1,57 +1,348 @@
-use rhai::{Engine, EvalAltResult, Position};
+use rhai::{Checker, Dynamic, Engine, EvalAltResult, Position, Profiler, Scope, Snippet, INT};
+
+#[cfg(not(feature = "no_index"))]
+use rhai::Array;
+
+#[cfg(not(feature = "no_module"))]
+use rhai::module_resolvers::FileModuleResolver;
 
 #[cfg(not(feature = "no_optimize"))]
 use rhai::OptimizationLevel;
 
-use std::{env, fs::File, io::Read, path::Path, process::exit};
-
-fn eprint_error(input: &str, mut err: EvalAltResult) {
-    fn eprint_line(lines: &[&str], pos: Position, err_msg: &str) {
-        let line = pos.line().unwrap();
-        let line_no = format!("{}: ", line);
-
-        eprintln!("{}{}", line_no, lines[line - 1]);
-        eprintln!(
-            "{:>1$} {2}",
-            "^",
-            line_no.len() + pos.position().unwrap(),
-            err_msg
-        );
-        eprintln!("");
-    }
+use std::{
+    env, fs,
+    io::{self, Read},
+    path::Path,
+    process::exit,
+};
 
-    let lines: Vec<_> = input.split('\n').collect();
+fn print_usage() {
+    eprintln!("Usage: rhai-run [options] <script | -> [arguments]...");
+    eprintln!("       rhai-run --check [options] <files>...");
+    eprintln!();
+    eprintln!("Runs <script>, or reads the script from standard input if it is '-'.");
+    eprintln!("Trailing arguments are available to the script in the 'ARGV' array constant.");
+    eprintln!("The process exits with the script's result if it is an integer, or with the");
+    eprintln!("code passed to 'exit(code)'. Codes outside 0..=255 exit with 1 (failure).");
+    eprintln!();
+    eprintln!("With '--check', the files and all modules they import are compiled without");
+    eprintln!("running them, and errors and warnings are printed in JSON format.");
+    eprintln!();
+    eprintln!("Options:");
+    eprintln!("    --check                        check files instead of running a script");
+    eprintln!("    -O, --optimize <level>         none, simple or full (default)");
+    eprintln!("    --strip-assertions             strip all assertions when optimizing");
+    eprintln!("    -I, --module-path <dir>        add <dir> to the module search paths");
+    eprintln!("    --max-operations <n>           maximum number of operations");
+    eprintln!("    --max-call-levels <n>          maximum depth of function calls");
+    eprintln!("    --max-expr-depth <n>           maximum nesting depth of expressions");
+    eprintln!("    --max-function-expr-depth <n>  maximum nesting depth in functions");
+    eprintln!("    --max-modules <n>              maximum number of modules loaded");
+    eprintln!("    --max-string-size <n>          maximum length of strings");
+    eprintln!("    --max-array-size <n>           maximum size of arrays");
+    eprintln!("    --max-map-size <n>             maximum size of object maps");
+    eprintln!("    --profile[=<file>]             print a profile summary, and optionally");
+    eprintln!("                                   write collapsed stacks to <file>");
+    eprintln!("    -h, --help                     print this help");
+    eprintln!();
+    eprintln!("A limit of zero means unlimited.");
+}
+
+fn eprint_header(filename: &str) {
+    eprintln!("{:=<1$}", "", filename.len());
+    eprintln!("{}", filename);
+    eprintln!("{:=<1$}", "", filename.len());
+    eprintln!();
+}
 
+fn eprint_error(input: &str, err: EvalAltResult) {
     // Print error
-    let pos = err.take_position();
+    eprintln!("{}", Snippet::new(input).render_error(&err));
+    eprintln!();
+
+    // Print call stack
+    let stack = err.backtrace();
+
+    if !stack.is_empty() {
+        eprintln!("Call stack (most recent call first):");
+
+        for frame in stack {
+            eprintln!("    {}", frame);
+        }
+        eprintln!();
+    }
+}
 
-    if pos.is_none() {
-        // No position
-        eprintln!("{}", err);
+/// Parse the value of a numeric option.
+fn parse_number<T: std::str::FromStr>(option: &str, value: &str) -> T {
+    match value.parse() {
+        Ok(n) => n,
+        Err(_) => {
+            eprintln!("Invalid value for '{}': {}", option, value);
+            exit(1);
+        }
+    }
+}
+
+/// Map an integer to a process exit code.
+///
+/// Exit codes are truncated to 8 bits by the operating system, so values outside `0..=255` are
+/// treated as a failure (exit code 1) instead of wrapping around (e.g. 256 to success).
+fn to_exit_code(code: INT) -> i32 {
+    if (0..=255).contains(&code) {
+        code as i32
     } else {
-        // Specific position
-        eprint_line(&lines, pos, &err.to_string())
+        1
+    }
+}
+
+/// Get the exit code requested via `exit(code)`, if the error was raised by it.
+fn exit_code(err: &EvalAltResult) -> Option<i32> {
+    match err {
+        EvalAltResult::ErrorTerminated(code, _) => code.as_int().ok().map(to_exit_code),
+        EvalAltResult::ErrorInFunctionCall(_, _, err, _)
+        | EvalAltResult::ErrorInModule(_, err, _) => exit_code(err),
+        _ => None,
     }
 }
 
 fn main() {
+    let mut profile: Option<Option<String>> = None;
+    let mut settings: Vec<(String, String)> = Vec::new();
+    let mut module_paths: Vec<String> = Vec::new();
+    let mut script = None;
+    let mut check = false;
+
+    let mut args = env::args().skip(1);
+
+    // Parse options up to the script name
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--profile" => {
+                // Print profile summary
+                profile = Some(None);
+            }
+            "--check" => check = true,
+            "--strip-assertions" => settings.push((arg, String::new())),
+            "--help" | "-h" => {
+                print_usage();
+                exit(0);
+            }
+            "--" => {
+                script = args.next();
+                break;
+            }
+            "-O"
+            | "--optimize"
+            | "-I"
+            | "--module-path"
+            | "--max-operations"
+            | "--max-call-levels"
+            | "--max-expr-depth"
+            | "--max-function-expr-depth"
+            | "--max-modules"
+            | "--max-string-size"
+            | "--max-array-size"
+            | "--max-map-size" => {
+                let value = match args.next() {
+                    Some(value) => value,
+                    None => {
+                        eprintln!("Missing value after '{}'", arg);
+                        print_usage();
+                        exit(1);
+                    }
+                };
+
+                match arg.as_str() {
+                    "-I" | "--module-path" => module_paths.push(value),
+                    _ => settings.push((arg, value)),
+                }
+            }
+            _ => {
+                if let Some(path) = arg.strip_prefix("--profile=") {
+                    // Print profile summary and write collapsed stacks to file
+                    profile = Some(Some(path.to_string()));
+                } else if arg.starts_with('-') && arg != "-" {
+                    eprintln!("Unknown option: {}", arg);
+                    print_usage();
+                    exit(1);
+                } else {
+                    script = Some(arg);
+                    break;
+                }
+            }
+        }
+    }
+
+    let script = match script {
+        Some(script) => script,
+        None => {
+            print_usage();
+            exit(1);
+        }
+    };
+
+    // All remaining arguments are passed to the script
+    let argv: Vec<String> = args.collect();
+
+    let mut engine = Engine::new();
+
+    #[cfg(not(feature = "no_optimize"))]
+    engine.set_optimization_level(OptimizationLevel::Full);
+
+    for (option, value) in settings.iter() {
+        let (option, value) = (option.as_str(), value.as_str());
+
+        match option {
+            #[cfg(not(feature = "no_optimize"))]
+            "-O" | "--optimize" => {
+                engine.set_optimization_level(match value {
+                    "none" => OptimizationLevel::None,
+                    "simple" => OptimizationLevel::Simple,
+                    "full" => OptimizationLevel::Full,
+                    _ => {
+                        eprintln!("Invalid value for '{}': {}", option, value);
+                        print_usage();
+                        exit(1);
+                    }
+                });
+            }
+            #[cfg(not(feature = "no_optimize"))]
+            "--strip-assertions" => {
+                engine.set_strip_assertions(true);
+            }
+            #[cfg(not(feature = "unchecked"))]
+            "--max-operations" => {
+                engine.set_max_operations(parse_number(option, value));
+            }
+            #[cfg(not(feature = "unchecked"))]
+            #[cfg(not(feature = "no_function"))]
+            "--max-call-levels" => {
+                engine.set_max_call_levels(parse_number(option, value));
+            }
+            #[cfg(not(feature = "unchecked"))]
+            "--max-expr-depth" => {
+                engine.set_max_expr_depths(
+                    parse_number(option, value),
+                    #[cfg(not(feature = "no_function"))]
+                    engine.max_function_expr_depth(),
+                );
+            }
+            #[cfg(not(feature = "unchecked"))]
+            #[cfg(not(feature = "no_function"))]
+            "--max-function-expr-depth" => {
+                engine.set_max_expr_depths(engine.max_expr_depth(), parse_number(option, value));
+            }
+            #[cfg(not(feature = "unchecked"))]
+            #[cfg(not(feature = "no_module"))]
+            "--max-modules" => {
+                engine.set_max_modules(parse_number(option, value));
+            }
+            #[cfg(not(feature = "unchecked"))]
+            "--max-string-size" => {
+                engine.set_max_string_size(parse_number(option, value));
+            }
+            #[cfg(not(feature = "unchecked"))]
+            #[cfg(not(feature = "no_index"))]
+            "--max-array-size" => {
+                engine.set_max_array_size(parse_number(option, value));
+            }
+            #[cfg(not(feature = "unchecked"))]
+            #[cfg(not(feature = "no_object"))]
+            "--max-map-size" => {
+                engine.set_max_map_size(parse_number(option, value));
+            }
+            _ => {
+                eprintln!("Option '{}' is not supported in this build.", option);
+                exit(1);
+            }
+        }
+    }
+
+    // Resolve modules relative to the importing script, then along the search paths
+    #[cfg(not(feature = "no_module"))]
+    if !module_paths.is_empty() {
+        let mut resolver = FileModuleResolver::new();
+        for path in module_paths.iter() {
+            resolver.add_search_path(path);
+        }
+        engine.set_module_resolver(resolver);
+    }
+    #[cfg(feature = "no_module")]
+    if !module_paths.is_empty() {
+        eprintln!("Module search paths are not supported in this build.");
+        exit(1);
+    }
+
+    // `exit(code)` terminates the script with an exit code.
+    // `exit` is a reserved keyword, so it is registered as custom syntax with an optional code.
+    engine.register_custom_syntax_raw(
+        "exit",
+        |stream, look_ahead| match stream.len() {
+            1 => Ok(Some("(".into())),
+            2 if look_ahead == ")" => Ok(Some(")".into())),
+            2 => Ok(Some("$expr$".into())),
+            3 if stream[2] != ")" => Ok(Some(")".into())),
+            _ => Ok(None),
+        },
+        0,
+        |context, inputs| {
+            let (code, pos) = match inputs.get(0) {
+                Some(expr) => (context.eval_expression_tree(expr)?, expr.position()),
+                None => ((0 as INT).into(), Position::NONE),
+            };
+
+            if !code.is::<INT>() {
+                let type_name = context.engine().map_type_name(code.type_name());
+                return Err(EvalAltResult::ErrorMismatchDataType(
+                    context
+                        .engine()
+                        .map_type_name(std::any::type_name::<INT>())
+                        .into(),
+                    type_name.into(),
+                    pos,
+                )
+                .into());
+            }
+
+            Err(EvalAltResult::ErrorTerminated(code, pos).into())
+        },
+    );
+
+    if check {
+        let mut checker = Checker::new(&engine);
+
+        for path in module_paths.iter() {
+            checker.add_module_path(path);
+        }
+
+        let files: Vec<_> = std::iter::once(script).chain(argv).collect();
+        let report = checker.check_files(&files);
+
+        print!("{}", report.to_json());
+
+        exit(if report.has_errors() { 1 } else { 0 });
+    }
+
     let mut contents = String::new();
 
-    for filename in env::args().skip(1) {
-        let filename = match Path::new(&filename).canonicalize() {
+    let source = if script == "-" {
+        if let Err(err) = io::stdin().read_to_string(&mut contents) {
+            eprintln!("Error reading script from standard input\n{}", err);
+            exit(1);
+        }
+        None
+    } else {
+        let filename = match Path::new(&script).canonicalize() {
             Err(err) => {
-                eprintln!("Error script file path: {}\n{}", filename, err);
+                eprintln!("Error script file path: {}\n{}", script, err);
                 exit(1);
             }
             Ok(f) => f,
         };
 
-        let mut engine = Engine::new();
-
-        #[cfg(not(feature = "no_optimize"))]
-        engine.set_optimization_level(OptimizationLevel::Full);
-
-        let mut f = match File::open(&filename) {
+        match fs::read_to_string(&filename) {
+            Ok(s) => contents = s,
             Err(err) => {
                 eprintln!(
                     "Error reading script file: {}\n{}",
@@ -60,43 +351,81 @@ fn main() {
                 );
                 exit(1);
             }
-            Ok(f) => f,
-        };
+        }
+
+        Some(filename.to_string_lossy().to_string())
+    };
 
-        contents.clear();
+    let contents = if contents.starts_with("#!") {
+        // Skip shebang
+        &contents[contents.find('\n').unwrap_or(contents.len())..]
+    } else {
+        &contents[..]
+    };
+
+    let mut scope = Scope::new();
+
+    #[cfg(not(feature = "no_index"))]
+    scope.push_constant(
+        "ARGV",
+        argv.into_iter().map(Dynamic::from).collect::<Array>(),
+    );
+    #[cfg(feature = "no_index")]
+    drop(argv);
 
-        if let Err(err) = f.read_to_string(&mut contents) {
-            eprintln!(
-                "Error reading script file: {}\n{}",
-                filename.to_string_lossy(),
-                err
-            );
+    let filename = source.as_deref().unwrap_or("<stdin>");
+
+    let ast = match engine.compile(contents) {
+        Ok(mut ast) => {
+            if let Some(ref source) = source {
+                ast.set_source(source.as_str());
+            }
+            ast
+        }
+        Err(err) => {
+            eprint_header(filename);
+            eprintln!("{}", Snippet::new(contents).render_parse_error(&err));
             exit(1);
         }
+    };
 
-        let contents = if contents.starts_with("#!") {
-            // Skip shebang
-            &contents[contents.find('\n').unwrap_or(0)..]
-        } else {
-            &contents[..]
-        };
+    // Attach the profiler only after compiling, so that calls folded away by the optimizer are not counted
+    let profiler = Profiler::new();
+
+    if profile.is_some() {
+        engine.set_profiler(Some(profiler.clone()));
+    }
 
-        if let Err(err) = engine
-            .compile(contents)
-            .map_err(|err| Box::new(err.into()) as Box<EvalAltResult>)
-            .and_then(|mut ast| {
-                ast.set_source(filename.to_string_lossy());
-                engine.consume_ast(&ast)
-            })
-        {
-            let filename = filename.to_string_lossy();
-
-            eprintln!("{:=<1$}", "", filename.len());
-            eprintln!("{}", filename);
-            eprintln!("{:=<1$}", "", filename.len());
-            eprintln!("");
-
-            eprint_error(contents, *err);
+    let code = match engine.eval_ast_with_scope::<Dynamic>(&mut scope, &ast) {
+        Ok(value) if value.is::<INT>() => to_exit_code(value.as_int().unwrap()),
+        Ok(value) if value.is::<bool>() => {
+            if value.as_bool().unwrap() {
+                0
+            } else {
+                1
+            }
+        }
+        Ok(_) => 0,
+        Err(err) => match exit_code(&err) {
+            Some(code) => code,
+            None => {
+                eprint_header(filename);
+                eprint_error(contents, *err);
+                1
+            }
+        },
+    };
+
+    if let Some(path) = profile {
+        eprintln!("{}", profiler.summary());
+
+        if let Some(path) = path {
+            if let Err(err) = fs::write(&path, profiler.collapsed_stacks()) {
+                eprintln!("Error writing profile file: {}\n{}", path, err);
+                exit(1);
+            }
         }
     }
+
+    exit(code);
 }
@@ -0,0 +1,267 @@
+#[cfg(not(feature = "no_function"))]
+use rhai::{Engine, EvalAltResult, Snippet, TestOutcome, TestReport, TestRunner};
+
+#[cfg(not(feature = "no_function"))]
+use std::{
+    env,
+    fs::{self, File},
+    io::Read,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use std::process::exit;
+
+#[cfg(not(feature = "no_function"))]
+fn print_usage() {
+    eprintln!("Usage: rhai-test [options] <files or directories>...");
+    eprintln!("");
+    eprintln!("Options:");
+    eprintln!("    --filter <text>          only run tests with names containing <text>");
+    eprintln!("    --junit <file>           write results in JUnit XML format to <file>");
+    eprintln!(
+        "    --max-operations <n>     maximum number of operations for each test (0 for unlimited)"
+    );
+    eprintln!("    --timeout <ms>           maximum time (in milliseconds) for each test (0 for unlimited)");
+}
+
+/// Add all script files under a path.
+#[cfg(not(feature = "no_function"))]
+fn add_files(path: PathBuf, files: &mut Vec<PathBuf>) {
+    if !path.is_dir() {
+        files.push(path);
+        return;
+    }
+
+    let mut entries: Vec<_> = match fs::read_dir(&path) {
+        Ok(entries) => entries.filter_map(|e| e.ok()).map(|e| e.path()).collect(),
+        Err(err) => {
+            eprintln!(
+                "Error reading directory: {}\n{}",
+                path.to_string_lossy(),
+                err
+            );
+            exit(1);
+        }
+    };
+
+    entries.sort();
+
+    for entry in entries {
+        if entry.is_dir() || entry.extension().is_some_and(|ext| ext == "rhai") {
+            add_files(entry, files);
+        }
+    }
+}
+
+#[cfg(not(feature = "no_function"))]
+fn eprint_error(input: &str, err: &EvalAltResult) {
+    // Print error
+    eprintln!("{}", Snippet::new(input).render_error(err));
+
+    // Print call stack
+    let stack = err.backtrace();
+
+    if !stack.is_empty() {
+        eprintln!("");
+        eprintln!("Call stack (most recent call first):");
+
+        for frame in stack {
+            eprintln!("    {}", frame);
+        }
+    }
+}
+
+#[cfg(not(feature = "no_function"))]
+fn main() {
+    let mut filter = None;
+    let mut junit = None;
+    let mut max_operations = None;
+    let mut timeout = None;
+    let mut files = Vec::new();
+
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        let target = match arg.as_str() {
+            "--filter" => &mut filter,
+            "--junit" => &mut junit,
+            "--max-operations" => &mut max_operations,
+            "--timeout" => &mut timeout,
+            "--help" | "-h" => {
+                print_usage();
+                exit(0);
+            }
+            _ => {
+                add_files(PathBuf::from(arg), &mut files);
+                continue;
+            }
+        };
+
+        match args.next() {
+            Some(value) => *target = Some(value),
+            None => {
+                eprintln!("Missing value after '{}'", arg);
+                print_usage();
+                exit(1);
+            }
+        }
+    }
+
+    if files.is_empty() {
+        print_usage();
+        exit(1);
+    }
+
+    let parse_number = |name: &str, value: &str| -> u64 {
+        value.parse().unwrap_or_else(|_| {
+            eprintln!("Invalid value for '{}': {}", name, value);
+            exit(1);
+        })
+    };
+
+    #[cfg(not(feature = "unchecked"))]
+    let max_operations = max_operations.map(|v| parse_number("--max-operations", &v));
+    let timeout = timeout.map(|v| parse_number("--timeout", &v));
+
+    let mut engine = Engine::new();
+    let mut report = TestReport::default();
+    let mut failures = Vec::new();
+    let mut contents = String::new();
+
+    for filename in files {
+        let filename = match Path::new(&filename).canonicalize() {
+            Err(err) => {
+                eprintln!(
+                    "Error script file path: {}\n{}",
+                    filename.to_string_lossy(),
+                    err
+                );
+                exit(1);
+            }
+            Ok(f) => f,
+        };
+
+        let mut f = match File::open(&filename) {
+            Err(err) => {
+                eprintln!(
+                    "Error reading script file: {}\n{}",
+                    filename.to_string_lossy(),
+                    err
+                );
+                exit(1);
+            }
+            Ok(f) => f,
+        };
+
+        contents.clear();
+
+        if let Err(err) = f.read_to_string(&mut contents) {
+            eprintln!(
+                "Error reading script file: {}\n{}",
+                filename.to_string_lossy(),
+                err
+            );
+            exit(1);
+        }
+
+        let contents = if contents.starts_with("#!") {
+            // Skip shebang
+            &contents[contents.find('\n').unwrap_or(0)..]
+        } else {
+            &contents[..]
+        };
+
+        let filename = filename.to_string_lossy();
+
+        let mut ast = match engine.compile(contents) {
+            Ok(ast) => ast,
+            Err(err) => {
+                eprintln!("{}", filename);
+                eprintln!("{}", Snippet::new(contents).render_parse_error(&err));
+                exit(1);
+            }
+        };
+
+        ast.set_source(filename.as_ref());
+
+        let mut runner = TestRunner::new(&mut engine);
+
+        if let Some(ref filter) = filter {
+            runner.set_filter(filter.as_str());
+        }
+        #[cfg(not(feature = "unchecked"))]
+        if let Some(max_operations) = max_operations {
+            runner.set_max_operations(max_operations);
+        }
+        if let Some(timeout) = timeout {
+            runner.set_time_limit(match timeout {
+                0 => None,
+                ms => Some(Duration::from_millis(ms)),
+            });
+        }
+
+        let tests = runner.tests(&ast);
+
+        if tests.is_empty() {
+            continue;
+        }
+
+        println!("running {} test(s) in {}", tests.len(), filename);
+
+        for result in runner.run(&ast).results {
+            println!("test {} ... {}", result.name, result.outcome);
+
+            if !result.outcome.is_success() {
+                failures.push((contents.to_string(), filename.to_string()));
+            }
+
+            report.results.push(result);
+        }
+
+        println!("");
+    }
+
+    // Print failures
+    let failed = report
+        .results
+        .iter()
+        .filter(|r| !r.outcome.is_success())
+        .zip(failures.iter());
+
+    for (result, (contents, filename)) in failed {
+        eprintln!("---- {} ({}) ----", result.name, filename);
+
+        match result.outcome {
+            TestOutcome::Failed(ref err) => eprint_error(contents, err),
+            TestOutcome::TimedOut => eprintln!("Test timed out"),
+            _ => eprintln!("Test is expected to fail but did not"),
+        }
+
+        eprintln!("");
+    }
+
+    println!(
+        "test result: {}. {} passed; {} failed",
+        if report.is_success() { "ok" } else { "FAILED" },
+        report.passed(),
+        report.failed()
+    );
+
+    if let Some(path) = junit {
+        if let Err(err) = fs::write(&path, report.to_junit_xml()) {
+            eprintln!("Error writing JUnit XML file: {}\n{}", path, err);
+            exit(1);
+        }
+    }
+
+    if !report.is_success() {
+        exit(1);
+    }
+}
+
+#[cfg(feature = "no_function")]
+fn main() {
+    eprintln!("rhai-test is not available under 'no_function'.");
+    exit(1);
+}
@@ -0,0 +1,405 @@
+//! Module implementing compile-only checking of script files and the modules they import.
+
+use crate::coverage::json_string;
+use crate::lint::{LintLevel, LintWarning, Linter};
+use crate::optimize::OptimizationLevel;
+use crate::stdlib::{
+    collections::BTreeSet,
+    fmt::{self, Write},
+    format, fs,
+    path::{Path, PathBuf},
+    string::{String, ToString},
+    vec::Vec,
+};
+use crate::{Engine, Position};
+
+/// Extension of script files.
+#[cfg(not(feature = "no_module"))]
+const SCRIPT_EXTENSION: &str = "rhai";
+
+/// Severity of a [`Diagnostic`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Severity {
+    /// The script cannot be compiled, or violates a denied [lint][crate::Lint].
+    Error,
+    /// The script violates a [lint][crate::Lint].
+    Warning,
+}
+
+impl Severity {
+    /// Name of this severity, i.e. `error` or `warning`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::Warning => "warning",
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    #[inline(always)]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A problem found by the [`Checker`].
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Diagnostic {
+    /// Path of the script file.
+    pub file: String,
+    /// Severity of the problem.
+    pub severity: Severity,
+    /// Code identifying the problem: the [code][crate::ParseErrorType::code] of a parse error,
+    /// the [ID][crate::Lint::id] of a lint, `module_not_found` or `io_error`.
+    pub code: &'static str,
+    /// Description of the problem.
+    pub message: String,
+    /// Location of the problem.
+    pub pos: Position,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.file)?;
+
+        // Do not write any position if None
+        if let Some(line) = self.pos.line() {
+            write!(f, ":{}", line)?;
+
+            if let Some(column) = self.pos.position() {
+                write!(f, ":{}", column)?;
+            }
+        }
+
+        write!(f, ": {}[{}]: {}", self.severity, self.code, self.message)
+    }
+}
+
+/// Result of checking script files with a [`Checker`].
+#[derive(Debug, Clone, Default)]
+pub struct CheckReport {
+    /// Paths of all script files checked, including imported modules, in the order checked.
+    pub files: Vec<String>,
+    /// Problems found, in the order found.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl CheckReport {
+    /// Number of diagnostics with [`Severity::Error`].
+    pub fn errors(&self) -> usize {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Error)
+            .count()
+    }
+    /// Number of diagnostics with [`Severity::Warning`].
+    pub fn warnings(&self) -> usize {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Warning)
+            .count()
+    }
+    /// Are there any errors?
+    #[inline(always)]
+    pub fn has_errors(&self) -> bool {
+        self.errors() > 0
+    }
+    /// Generate the report in JSON format.
+    ///
+    /// The output is an object with a `files` array of checked file paths, a `diagnostics` array of
+    /// `{ "file", "line", "column", "severity", "code", "message" }` objects (`line` and `column`
+    /// are `null` if there is no position), and the numbers of `errors` and `warnings`.
+    pub fn to_json(&self) -> String {
+        let mut output = String::from("{\n  \"files\": [");
+
+        for (i, file) in self.files.iter().enumerate() {
+            if i > 0 {
+                output.push(',');
+            }
+            write!(output, "\n    {}", json_string(file)).unwrap();
+        }
+
+        output.push_str("\n  ],\n  \"diagnostics\": [");
+
+        for (i, diagnostic) in self.diagnostics.iter().enumerate() {
+            if i > 0 {
+                output.push(',');
+            }
+
+            let line = diagnostic
+                .pos
+                .line()
+                .map_or("null".to_string(), |n| n.to_string());
+            let column = diagnostic
+                .pos
+                .position()
+                .map_or("null".to_string(), |n| n.to_string());
+
+            write!(
+                output,
+                "\n    {{ \"file\": {}, \"line\": {}, \"column\": {}, \"severity\": \"{}\", \"code\": \"{}\", \"message\": {} }}",
+                json_string(&diagnostic.file),
+                line,
+                column,
+                diagnostic.severity,
+                diagnostic.code,
+                json_string(&diagnostic.message)
+            )
+            .unwrap();
+        }
+
+        write!(
+            output,
+            "\n  ],\n  \"errors\": {},\n  \"warnings\": {}\n}}\n",
+            self.errors(),
+            self.warnings()
+        )
+        .unwrap();
+
+        output
+    }
+}
+
+/// A checker that compiles script files, and all the modules they import, without running them.
+///
+/// All parse errors in each file are reported (see
+/// [`Engine::compile_with_diagnostics`][Engine::compile_with_diagnostics]), together with warnings
+/// from a [`Linter`] for files without parse errors.
+///
+/// Imports with constant paths are followed the same way as
+/// [`FileModuleResolver`][crate::module_resolvers::FileModuleResolver]: relative paths are resolved
+/// under the directory of the importing script, then under each module path added via
+/// [`add_module_path`][Checker::add_module_path]. Each file is checked only once.
+///
+/// Not available under `no_std`.
+///
+/// # Example
+///
+/// ```no_run
+/// use rhai::{Checker, Engine, Lint};
+///
+/// let engine = Engine::new();
+/// let mut checker = Checker::new(&engine);
+///
+/// checker.linter_mut().deny(Lint::UnknownFunction);
+///
+/// let report = checker.check_files(&["main.rhai"]);
+///
+/// for diagnostic in report.diagnostics.iter() {
+///     eprintln!("{}", diagnostic);
+/// }
+///
+/// if report.has_errors() {
+///     std::process::exit(1);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Checker<'e> {
+    /// The [`Engine`] compiling scripts.
+    engine: &'e Engine,
+    /// The [`Linter`] reporting warnings.
+    linter: Linter<'e>,
+    /// Report warnings from the linter?
+    lint: bool,
+    /// Directories searched for imported modules.
+    module_paths: Vec<PathBuf>,
+}
+
+impl<'e> Checker<'e> {
+    /// Create a new [`Checker`] compiling scripts with an [`Engine`].
+    #[inline(always)]
+    pub fn new(engine: &'e Engine) -> Self {
+        Self {
+            engine,
+            linter: Linter::new(engine),
+            lint: true,
+            module_paths: Default::default(),
+        }
+    }
+    /// Get a mutable reference to the [`Linter`] reporting warnings, in order to configure lints.
+    #[inline(always)]
+    pub fn linter_mut(&mut self) -> &mut Linter<'e> {
+        &mut self.linter
+    }
+    /// Enable/disable reporting warnings from the [`Linter`]. Enabled by default.
+    #[inline(always)]
+    pub fn set_lint(&mut self, enable: bool) -> &mut Self {
+        self.lint = enable;
+        self
+    }
+    /// Add a directory to search for imported modules.
+    #[inline(always)]
+    pub fn add_module_path(&mut self, path: impl Into<PathBuf>) -> &mut Self {
+        self.module_paths.push(path.into());
+        self
+    }
+    /// Check a script file and all the modules it imports.
+    #[inline(always)]
+    pub fn check_file(&self, path: impl AsRef<Path>) -> CheckReport {
+        self.check_files(&[path])
+    }
+    /// Check script files and all the modules they import.
+    pub fn check_files(&self, paths: &[impl AsRef<Path>]) -> CheckReport {
+        let mut report = Default::default();
+        let mut visited = Default::default();
+
+        for path in paths {
+            self.check_path(
+                path.as_ref(),
+                Position::NONE,
+                None,
+                &mut report,
+                &mut visited,
+            );
+        }
+
+        report
+    }
+    /// Check a script file, reporting any error reading it at `pos` in `importer`.
+    fn check_path(
+        &self,
+        path: &Path,
+        pos: Position,
+        importer: Option<&str>,
+        report: &mut CheckReport,
+        visited: &mut BTreeSet<PathBuf>,
+    ) {
+        let path = path.canonicalize().unwrap_or_else(|_| path.into());
+
+        if !visited.insert(path.clone()) {
+            return;
+        }
+
+        let file = path.to_string_lossy().to_string();
+
+        let mut contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) => {
+                report.diagnostics.push(Diagnostic {
+                    file: importer.unwrap_or(&file).into(),
+                    severity: Severity::Error,
+                    code: "io_error",
+                    message: format!("Cannot read script file '{}': {}", file, err),
+                    pos,
+                });
+                return;
+            }
+        };
+
+        if contents.starts_with("#!") {
+            // Remove shebang, keeping the line
+            contents.drain(..contents.find('\n').unwrap_or(contents.len()));
+        }
+
+        report.files.push(file.clone());
+
+        let (mut ast, errors) = self.engine.compile_scripts_with_diagnostics(
+            &Default::default(),
+            &[&contents],
+            OptimizationLevel::None,
+        );
+        ast.set_source(file.as_str());
+
+        let no_errors = errors.is_empty();
+
+        report
+            .diagnostics
+            .extend(errors.into_iter().map(|err| Diagnostic {
+                file: file.clone(),
+                severity: Severity::Error,
+                code: err.0.code(),
+                message: err.0.to_string(),
+                pos: err.1,
+            }));
+
+        // A partial AST may produce spurious warnings
+        if self.lint && no_errors {
+            report
+                .diagnostics
+                .extend(self.linter.lint_ast(&ast).into_iter().map(
+                    |LintWarning {
+                         lint,
+                         level,
+                         message,
+                         pos,
+                     }| Diagnostic {
+                        file: file.clone(),
+                        severity: if level == LintLevel::Deny {
+                            Severity::Error
+                        } else {
+                            Severity::Warning
+                        },
+                        code: lint.id(),
+                        message,
+                        pos,
+                    },
+                ));
+        }
+
+        #[cfg(not(feature = "no_module"))]
+        self.check_imports(&path, &file, &ast, report, visited);
+    }
+    /// Check all modules imported with constant paths by a script file.
+    #[cfg(not(feature = "no_module"))]
+    fn check_imports(
+        &self,
+        path: &Path,
+        file: &str,
+        ast: &crate::AST,
+        report: &mut CheckReport,
+        visited: &mut BTreeSet<PathBuf>,
+    ) {
+        use crate::ast::{ASTNode, Expr, Stmt};
+        use crate::stdlib::{iter, vec};
+
+        let mut imports = Vec::new();
+
+        ast.walk(&mut |path| {
+            if let ASTNode::Stmt(Stmt::Import(Expr::StringConstant(s, _), _, pos)) =
+                path.last().unwrap()
+            {
+                imports.push((s.to_string(), *pos));
+            }
+            true
+        });
+
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+        for (import, pos) in imports {
+            let mut module = PathBuf::from(&import);
+            module.set_extension(SCRIPT_EXTENSION);
+
+            let candidates: Vec<_> = if module.is_absolute() {
+                vec![module]
+            } else {
+                iter::once(dir)
+                    .chain(self.module_paths.iter().map(|p| p.as_path()))
+                    .map(|dir| dir.join(&module))
+                    .collect()
+            };
+
+            match candidates.iter().find(|p| p.is_file()) {
+                Some(module) => self.check_path(module, pos, Some(file), report, visited),
+                None => {
+                    let tried: Vec<_> = candidates
+                        .iter()
+                        .map(|p| p.to_string_lossy().to_string())
+                        .collect();
+
+                    report.diagnostics.push(Diagnostic {
+                        file: file.into(),
+                        severity: Severity::Error,
+                        code: "module_not_found",
+                        message: format!(
+                            "Module not found: '{}' (tried: {})",
+                            import,
+                            tried.join(", ")
+                        ),
+                        pos,
+                    });
+                }
+            }
+        }
+    }
+}
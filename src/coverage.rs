@@ -0,0 +1,321 @@
+//! Module implementing the line and branch coverage collector.
+
+use crate::ast::{ASTNode, Expr, Stmt};
+use crate::fn_native::{Locked, Shared};
+use crate::stdlib::{
+    collections::BTreeMap,
+    fmt::Write,
+    ops::DerefMut,
+    string::{String, ToString},
+    vec::Vec,
+};
+use crate::{Position, AST};
+
+/// Coverage data recorded for a source.
+#[derive(Debug, Clone, Default)]
+struct SourceCoverage {
+    /// Number of times each statement is executed, keyed by its [position][Position].
+    statements: BTreeMap<Position, u64>,
+    /// Number of times each branch is taken, keyed by the [position][Position] of the branch point
+    /// and the index of the branch.
+    branches: BTreeMap<(Position, usize), u64>,
+}
+
+/// Coverage data for all sources, keyed by source (empty if none), and the file paths of the
+/// sources loaded from script files.
+#[derive(Debug, Clone, Default)]
+struct CoverageData {
+    /// Coverage data keyed by source.
+    sources: BTreeMap<String, SourceCoverage>,
+    /// Paths of script files, keyed by source.
+    file_paths: BTreeMap<String, String>,
+}
+
+/// A coverage collector recording the statements executed and the branches taken by all scripts
+/// evaluated by an [`Engine`][crate::Engine].
+///
+/// Coverage is keyed by the source of each script (i.e. [`AST::source`]).
+/// Scripts without a source are recorded under an empty source.
+///
+/// Reports list [modules][crate::Module] loaded via
+/// [`FileModuleResolver`][crate::module_resolvers::FileModuleResolver] under the path of the
+/// script file instead (see [`file_path`][Coverage::file_path]).
+///
+/// Branch points are:
+/// * `if` statements: branch 0 is the `if` block, branch 1 is the `else` block.
+/// * `switch` statements: one branch per match clause (in internal order), followed by the default clause.
+/// * `&&` and `||` operators: branch 0 is short-circuited, branch 1 evaluates the right-hand side.
+/// * `try` statements: branch 0 completes without error, branch 1 runs the `catch` block.
+///
+/// A [`Coverage`] is a shared handle. Cloning it shares the same coverage data.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+/// use rhai::{Coverage, Engine};
+///
+/// let coverage = Coverage::new();
+///
+/// let mut engine = Engine::new();
+/// engine.set_coverage(Some(coverage.clone()));
+///
+/// let mut ast = engine.compile("let x = 42;\nif x > 0 {\n    x = 1;\n} else {\n    x = 2;\n}")?;
+/// ast.set_source("test.rhai");
+///
+/// engine.consume_ast(&ast)?;
+///
+/// assert_eq!(coverage.lines("test.rhai"), vec![(1, 1), (2, 1), (3, 1), (5, 0)]);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Coverage(Shared<Locked<CoverageData>>);
+
+impl Coverage {
+    /// Create a new [`Coverage`] collector.
+    #[inline(always)]
+    pub fn new() -> Self {
+        Default::default()
+    }
+    /// Lock the coverage data for writing.
+    #[inline(always)]
+    fn data(&self) -> impl DerefMut<Target = CoverageData> + '_ {
+        #[cfg(not(feature = "sync"))]
+        return self.0.borrow_mut();
+        #[cfg(feature = "sync")]
+        return self.0.write().unwrap();
+    }
+    /// Register all statements and branch points in an [`AST`], so that those never executed
+    /// are reported.
+    pub(crate) fn register(&self, ast: &AST) {
+        let mut data = self.data();
+        let cov = data
+            .sources
+            .entry(ast.source().unwrap_or_default().to_string())
+            .or_default();
+
+        ast.walk(&mut |path| {
+            let (pos, branches) = match path.last() {
+                Some(ASTNode::Stmt(stmt)) => {
+                    let pos = stmt.position();
+
+                    if !pos.is_none() {
+                        cov.statements.entry(pos).or_insert(0);
+                    }
+
+                    match stmt {
+                        Stmt::If(_, _, pos) | Stmt::TryCatch(_, pos, _) => (*pos, 2),
+                        Stmt::Switch(_, x, pos) => (*pos, x.0.len() + 1),
+                        _ => return true,
+                    }
+                }
+                Some(ASTNode::Expr(Expr::And(_, pos))) | Some(ASTNode::Expr(Expr::Or(_, pos))) => {
+                    (*pos, 2)
+                }
+                _ => return true,
+            };
+
+            if !pos.is_none() {
+                (0..branches).for_each(|branch| {
+                    cov.branches.entry((pos, branch)).or_insert(0);
+                });
+            }
+            true
+        });
+    }
+    /// Record the execution of a statement.
+    pub(crate) fn hit_statement(&self, source: Option<&str>, pos: Position) {
+        if !pos.is_none() {
+            let mut data = self.data();
+            let cov = data
+                .sources
+                .entry(source.unwrap_or_default().into())
+                .or_default();
+            *cov.statements.entry(pos).or_insert(0) += 1;
+        }
+    }
+    /// Record a branch taken at a branch point.
+    pub(crate) fn hit_branch(&self, source: Option<&str>, pos: Position, branch: usize) {
+        if !pos.is_none() {
+            let mut data = self.data();
+            let cov = data
+                .sources
+                .entry(source.unwrap_or_default().into())
+                .or_default();
+            *cov.branches.entry((pos, branch)).or_insert(0) += 1;
+        }
+    }
+    /// Record the path of the script file loaded as a source.
+    pub(crate) fn set_file_path(&self, source: &str, path: impl Into<String>) {
+        self.data()
+            .file_paths
+            .insert(source.to_string(), path.into());
+    }
+    /// Clear all coverage data.
+    #[inline(always)]
+    pub fn clear(&self) {
+        let mut data = self.data();
+        data.sources.clear();
+        data.file_paths.clear();
+    }
+    /// Get all sources recorded, in sorted order.
+    #[inline(always)]
+    pub fn sources(&self) -> Vec<String> {
+        self.data().sources.keys().cloned().collect()
+    }
+    /// Get the path of the script file loaded as a source, if any.
+    ///
+    /// Only [modules][crate::Module] loaded via
+    /// [`FileModuleResolver`][crate::module_resolvers::FileModuleResolver] have file paths.
+    #[inline(always)]
+    pub fn file_path(&self, source: &str) -> Option<String> {
+        self.data().file_paths.get(source).cloned()
+    }
+    /// Get the hit counts of all lines containing statements in a source, in ascending order of
+    /// line number.
+    ///
+    /// The hit count of a line is the maximum hit count of the statements starting on it.
+    pub fn lines(&self, source: &str) -> Vec<(usize, u64)> {
+        let data = self.data();
+        let mut lines = BTreeMap::<usize, u64>::new();
+
+        if let Some(cov) = data.sources.get(source) {
+            cov.statements.iter().for_each(|(pos, &hits)| {
+                let line = lines.entry(pos.line().unwrap()).or_insert(0);
+                *line = (*line).max(hits);
+            });
+        }
+
+        lines.into_iter().collect()
+    }
+    /// Get the hit counts of all branches in a source, as tuples of the [position][Position] of
+    /// the branch point, the index of the branch and the number of times it is taken.
+    pub fn branches(&self, source: &str) -> Vec<(Position, usize, u64)> {
+        self.data()
+            .sources
+            .get(source)
+            .map(|cov| {
+                cov.branches
+                    .iter()
+                    .map(|(&(pos, branch), &hits)| (pos, branch, hits))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+    /// Generate the coverage data in `lcov` tracefile format.
+    pub fn to_lcov(&self) -> String {
+        let mut output = String::new();
+
+        for source in self.sources() {
+            let file_path = self.file_path(&source);
+            writeln!(output, "SF:{}", file_path.as_ref().unwrap_or(&source)).unwrap();
+
+            let branches = self.branches(&source);
+
+            for (pos, branch, hits) in branches.iter() {
+                // A branch point never reached is marked with `-`
+                let reached = branches.iter().any(|(p, _, hits)| p == pos && *hits > 0);
+                let block = pos.position().unwrap_or(0);
+                let line = pos.line().unwrap();
+
+                if reached {
+                    writeln!(output, "BRDA:{},{},{},{}", line, block, branch, hits).unwrap();
+                } else {
+                    writeln!(output, "BRDA:{},{},{},-", line, block, branch).unwrap();
+                }
+            }
+
+            let branches_hit = branches.iter().filter(|(_, _, hits)| *hits > 0).count();
+            writeln!(output, "BRF:{}", branches.len()).unwrap();
+            writeln!(output, "BRH:{}", branches_hit).unwrap();
+
+            let lines = self.lines(&source);
+
+            for (line, hits) in lines.iter() {
+                writeln!(output, "DA:{},{}", line, hits).unwrap();
+            }
+
+            let lines_hit = lines.iter().filter(|(_, hits)| *hits > 0).count();
+            writeln!(output, "LF:{}", lines.len()).unwrap();
+            writeln!(output, "LH:{}", lines_hit).unwrap();
+            writeln!(output, "end_of_record").unwrap();
+        }
+
+        output
+    }
+    /// Generate the coverage data in JSON format.
+    ///
+    /// The output is an object keyed by source (or the path of the script file, if any), each containing a `lines` array of
+    /// `{ "line", "hits" }` objects and a `branches` array of
+    /// `{ "line", "position", "branch", "hits" }` objects.
+    pub fn to_json(&self) -> String {
+        let mut output = String::from("{");
+
+        for (i, source) in self.sources().iter().enumerate() {
+            if i > 0 {
+                output.push(',');
+            }
+
+            let file_path = self.file_path(source);
+            let key = json_string(file_path.as_ref().unwrap_or(source));
+            write!(output, "\n  {}: {{\n    \"lines\": [", key).unwrap();
+
+            for (j, (line, hits)) in self.lines(source).into_iter().enumerate() {
+                if j > 0 {
+                    output.push(',');
+                }
+                write!(
+                    output,
+                    "\n      {{ \"line\": {}, \"hits\": {} }}",
+                    line, hits
+                )
+                .unwrap();
+            }
+
+            output.push_str("\n    ],\n    \"branches\": [");
+
+            for (j, (pos, branch, hits)) in self.branches(source).into_iter().enumerate() {
+                if j > 0 {
+                    output.push(',');
+                }
+                write!(
+                    output,
+                    "\n      {{ \"line\": {}, \"position\": {}, \"branch\": {}, \"hits\": {} }}",
+                    pos.line().unwrap(),
+                    pos.position().unwrap_or(0),
+                    branch,
+                    hits
+                )
+                .unwrap();
+            }
+
+            output.push_str("\n    ]\n  }");
+        }
+
+        output.push_str("\n}\n");
+        output
+    }
+}
+
+/// Quote and escape a string as a JSON string literal.
+pub(crate) fn json_string(s: &str) -> String {
+    let mut output = String::with_capacity(s.len() + 2);
+
+    output.push('"');
+
+    for ch in s.chars() {
+        match ch {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            ch if (ch as u32) < 0x20 => write!(output, "\\u{:04x}", ch as u32).unwrap(),
+            ch => output.push(ch),
+        }
+    }
+
+    output.push('"');
+    output
+}
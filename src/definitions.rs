@@ -0,0 +1,385 @@
+//! Module implementing the generation of API definition files for external tools.
+
+use crate::module::FuncInfo;
+use crate::stdlib::{
+    fmt::Write,
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use crate::token::is_valid_identifier;
+use crate::{Engine, FnAccess, Module};
+use serde_json::{json, Value};
+
+#[cfg(not(feature = "no_index"))]
+use crate::engine::{FN_IDX_GET, FN_IDX_SET};
+
+#[cfg(not(feature = "no_object"))]
+use crate::engine::{FN_GET, FN_SET};
+
+/// Name of the module holding functions registered into the global namespace.
+const GLOBAL_MODULE: &str = "global";
+
+/// File extension of definition files.
+const DEFINITIONS_EXTENSION: &str = "d.rhai";
+
+/// Name of the JSON definitions file written by [`Engine::write_definitions`].
+#[cfg(not(feature = "no_std"))]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+const DEFINITIONS_JSON_FILE: &str = "definitions.json";
+
+/// Kind of a function definition.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+enum DefKind {
+    Function,
+    Operator,
+    #[cfg(not(feature = "no_object"))]
+    Getter,
+    #[cfg(not(feature = "no_object"))]
+    Setter,
+    #[cfg(not(feature = "no_index"))]
+    IndexGetter,
+    #[cfg(not(feature = "no_index"))]
+    IndexSetter,
+}
+
+impl DefKind {
+    /// Name of this kind in JSON definitions.
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Function => "function",
+            Self::Operator => "operator",
+            #[cfg(not(feature = "no_object"))]
+            Self::Getter => "getter",
+            #[cfg(not(feature = "no_object"))]
+            Self::Setter => "setter",
+            #[cfg(not(feature = "no_index"))]
+            Self::IndexGetter => "indexGetter",
+            #[cfg(not(feature = "no_index"))]
+            Self::IndexSetter => "indexSetter",
+        }
+    }
+}
+
+/// Definition of a registered function.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+struct FnDef {
+    /// Kind of function.
+    kind: DefKind,
+    /// Name of the function, or the property for getters and setters.
+    name: String,
+    /// Parameter names and types.
+    params: Vec<(String, String)>,
+    /// Return type, `None` if the function returns `()`.
+    return_type: Option<String>,
+    /// Doc-comments (script-defined functions only).
+    comments: Vec<String>,
+}
+
+impl FnDef {
+    /// Get the kind of a registered function, and the property name for getters and setters,
+    /// from its name.
+    fn kind_of(name: &str) -> (DefKind, &str) {
+        #[cfg(not(feature = "no_object"))]
+        if let Some(prop) = name.strip_prefix(FN_GET) {
+            return (DefKind::Getter, prop);
+        }
+        #[cfg(not(feature = "no_object"))]
+        if let Some(prop) = name.strip_prefix(FN_SET) {
+            return (DefKind::Setter, prop);
+        }
+        #[cfg(not(feature = "no_index"))]
+        if name == FN_IDX_GET {
+            return (DefKind::IndexGetter, "");
+        }
+        #[cfg(not(feature = "no_index"))]
+        if name == FN_IDX_SET {
+            return (DefKind::IndexSetter, "");
+        }
+
+        if is_valid_identifier(name.chars()) {
+            (DefKind::Function, name)
+        } else {
+            (DefKind::Operator, name)
+        }
+    }
+    /// Create a function definition from the metadata of a registered function.
+    fn new(info: &FuncInfo) -> Self {
+        let (kind, name) = Self::kind_of(&info.name);
+
+        let (params, return_type) = if info.param_names.is_empty() {
+            // No metadata available
+            let params = (0..info.params).map(|_| ("_".into(), "?".into())).collect();
+            let return_type = if info.func.is_script() {
+                Some("Dynamic".into())
+            } else {
+                Some("?".into())
+            };
+            (params, return_type)
+        } else {
+            let params = info
+                .param_names
+                .iter()
+                .take(info.params)
+                .map(|s| {
+                    let mut seg = s.splitn(2, ':');
+                    let name = seg.next().unwrap_or("_").trim();
+                    let name = name.strip_prefix("mut ").unwrap_or(name).trim();
+                    let typ = seg.next().map_or("Dynamic".into(), def_type);
+                    (name.to_string(), typ)
+                })
+                .collect();
+            let return_type = info
+                .param_names
+                .get(info.params)
+                .map(|s| def_type(s))
+                .filter(|s| s != "()");
+            (params, return_type)
+        };
+
+        #[cfg(not(feature = "no_function"))]
+        let comments = if info.func.is_script() {
+            info.func.get_fn_def().comments.to_vec()
+        } else {
+            Default::default()
+        };
+        #[cfg(feature = "no_function")]
+        let comments = Default::default();
+
+        Self {
+            kind,
+            name: name.into(),
+            params,
+            return_type,
+            comments,
+        }
+    }
+    /// Generate the declaration of this function in Rhai syntax, without the terminating `;`.
+    fn declaration(&self) -> String {
+        let mut decl = match self.kind {
+            DefKind::Function => format!("fn {}(", self.name),
+            DefKind::Operator => format!("op {}(", self.name),
+            #[cfg(not(feature = "no_object"))]
+            DefKind::Getter => format!("fn get {}(", self.name),
+            #[cfg(not(feature = "no_object"))]
+            DefKind::Setter => format!("fn set {}(", self.name),
+            #[cfg(not(feature = "no_index"))]
+            DefKind::IndexGetter => "fn index get(".into(),
+            #[cfg(not(feature = "no_index"))]
+            DefKind::IndexSetter => "fn index set(".into(),
+        };
+
+        let params: Vec<_> = self
+            .params
+            .iter()
+            .map(|(name, typ)| format!("{}: {}", name, typ))
+            .collect();
+        decl.push_str(&params.join(", "));
+        decl.push(')');
+
+        if let Some(ref return_type) = self.return_type {
+            decl.push_str(" -> ");
+            decl.push_str(return_type);
+        }
+
+        decl
+    }
+    /// Generate the JSON definition of this function.
+    fn to_json(&self) -> Value {
+        let params: Vec<_> = self
+            .params
+            .iter()
+            .map(|(name, typ)| json!({ "name": name, "type": typ }))
+            .collect();
+
+        let mut def = json!({
+            "kind": self.kind.as_str(),
+            "name": self.name,
+            "params": params,
+            "signature": self.declaration(),
+        });
+
+        if let Some(ref return_type) = self.return_type {
+            def["returnType"] = return_type.as_str().into();
+        }
+        if !self.comments.is_empty() {
+            def["docComments"] = self.comments.clone().into();
+        }
+
+        def
+    }
+}
+
+/// Normalize the Rust name of a parameter or return type into the name shown in definitions.
+fn def_type(typ: &str) -> String {
+    let typ = typ.trim();
+    let typ = typ
+        .strip_prefix("&mut ")
+        .or_else(|| typ.strip_prefix('&'))
+        .unwrap_or(typ)
+        .trim();
+
+    // Fallible functions return the type in `Ok`
+    if let Some(inner) = typ
+        .strip_prefix("Result<")
+        .and_then(|s| s.strip_suffix(", Box<EvalAltResult>>"))
+    {
+        return def_type(inner);
+    }
+
+    match typ {
+        "INT" => "int".into(),
+        "FLOAT" => "float".into(),
+        "str" | "String" | "ImmutableString" => "String".into(),
+        typ => typ.into(),
+    }
+}
+
+/// Collect the definitions of all public functions in a [`Module`].
+fn module_defs(module: &Module) -> Vec<FnDef> {
+    module
+        .iter_fn()
+        .filter(|f| f.access == FnAccess::Public)
+        .map(FnDef::new)
+        .collect()
+}
+
+/// Collect the definitions of a static module and all its sub-modules.
+fn collect_static_module(name: &str, module: &Module, modules: &mut Vec<(String, Vec<FnDef>)>) {
+    modules.push((name.into(), module_defs(module)));
+
+    module.iter_sub_modules().for_each(|(sub_name, m)| {
+        collect_static_module(&format!("{}::{}", name, sub_name), m.as_ref(), modules)
+    });
+}
+
+impl Engine {
+    /// Collect the definitions of all registered functions, organised by module,
+    /// with the global namespace first.
+    fn gen_definition_modules(&self, include_packages: bool) -> Vec<(String, Vec<FnDef>)> {
+        let mut global = module_defs(&self.global_namespace);
+
+        if include_packages {
+            self.global_modules
+                .iter()
+                .for_each(|m| global.extend(module_defs(m)));
+        }
+
+        let mut modules = vec![(GLOBAL_MODULE.to_string(), global)];
+
+        self.global_sub_modules
+            .iter()
+            .for_each(|(name, m)| collect_static_module(name, m, &mut modules));
+
+        modules.iter_mut().for_each(|(_, defs)| {
+            defs.sort();
+            defs.dedup();
+        });
+
+        modules
+    }
+    /// _(METADATA)_ Generate definition files, in Rhai syntax, of all registered functions,
+    /// operators, property getters/setters and indexers.
+    /// Exported under the `metadata` feature only.
+    ///
+    /// One file is generated for the global namespace (named `global.d.rhai`) and one for each
+    /// static module registered via [`register_static_module`][Engine::register_static_module]
+    /// (named after the module, with `::` in the names of sub-modules replaced by `.`).
+    ///
+    /// Functions in packages (e.g. the standard library) are included only if `include_packages`
+    /// is `true`.
+    ///
+    /// Returns a list of file names and contents.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rhai::Engine;
+    ///
+    /// let mut engine = Engine::new();
+    ///
+    /// engine.register_fn("add", |x: i64, y: i64| x + y);
+    ///
+    /// let files = engine.gen_definitions(false);
+    ///
+    /// assert_eq!(files[0].0, "global.d.rhai");
+    /// assert!(files[0].1.contains("fn add(_: i64, _: i64) -> i64;"));
+    /// ```
+    pub fn gen_definitions(&self, include_packages: bool) -> Vec<(String, String)> {
+        self.gen_definition_modules(include_packages)
+            .into_iter()
+            .map(|(name, defs)| {
+                let mut output = format!(
+                    "// Definitions of the functions in module `{}`, for use by external tools.\n\n\
+                    module {};\n",
+                    name, name
+                );
+
+                for def in defs {
+                    output.push('\n');
+                    def.comments
+                        .iter()
+                        .for_each(|c| writeln!(output, "{}", c).unwrap());
+                    writeln!(output, "{};", def.declaration()).unwrap();
+                }
+
+                let file = format!("{}.{}", name.replace("::", "."), DEFINITIONS_EXTENSION);
+                (file, output)
+            })
+            .collect()
+    }
+    /// _(METADATA)_ Generate definitions, in JSON format, of all registered functions,
+    /// operators, property getters/setters and indexers.
+    /// Exported under the `metadata` feature only.
+    ///
+    /// The JSON contains a list of modules, the global namespace first, each with its list of
+    /// function definitions: kind (`function`, `operator`, `getter`, `setter`, `indexGetter` or
+    /// `indexSetter`), name (the property name for getters and setters), parameter names and types,
+    /// return type and signature.
+    ///
+    /// Functions in packages (e.g. the standard library) are included only if `include_packages`
+    /// is `true`.
+    pub fn gen_definitions_json(&self, include_packages: bool) -> serde_json::Result<String> {
+        let modules: Vec<_> = self
+            .gen_definition_modules(include_packages)
+            .into_iter()
+            .map(|(name, defs)| {
+                let functions: Vec<_> = defs.iter().map(FnDef::to_json).collect();
+                json!({ "name": name, "functions": functions })
+            })
+            .collect();
+
+        serde_json::to_string_pretty(&json!({ "modules": modules }))
+    }
+    /// _(METADATA)_ Write definition files of all registered functions into a directory:
+    /// one `.d.rhai` file per module (see [`gen_definitions`][Engine::gen_definitions])
+    /// plus `definitions.json` (see [`gen_definitions_json`][Engine::gen_definitions_json]).
+    /// Exported under the `metadata` feature only.
+    ///
+    /// The directory is created if it does not exist.
+    ///
+    /// Not available under `no_std` or `WASM`.
+    #[cfg(not(feature = "no_std"))]
+    #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+    pub fn write_definitions(
+        &self,
+        dir: impl AsRef<crate::stdlib::path::Path>,
+        include_packages: bool,
+    ) -> crate::stdlib::io::Result<()> {
+        use crate::stdlib::{fs, io};
+
+        let dir = dir.as_ref();
+
+        fs::create_dir_all(dir)?;
+
+        for (file, contents) in self.gen_definitions(include_packages) {
+            fs::write(dir.join(file), contents)?;
+        }
+
+        let json = self
+            .gen_definitions_json(include_packages)
+            .map_err(|err| io::Error::other(err.to_string()))?;
+        fs::write(dir.join(DEFINITIONS_JSON_FILE), json)
+    }
+}
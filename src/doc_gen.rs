@@ -0,0 +1,829 @@
+//! Module implementing a documentation generator for an [`Engine`]'s API and script modules.
+
+use crate::serde::metadata::{FnAccess, FnMetadata};
+use crate::stdlib::{
+    collections::BTreeMap,
+    fmt::Write,
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use crate::{Engine, Module, AST};
+use serde::Serialize;
+
+/// Name of the documentation page for functions registered directly into an [`Engine`].
+const GLOBAL_MODULE: &str = "global";
+
+/// Prefix of the anchors of functions in a documentation page.
+const FN_ANCHOR_PREFIX: &str = "fn.";
+
+/// Style sheet shared by all HTML pages.
+const HTML_STYLE: &str = "body{font-family:sans-serif;margin:0;display:flex}\
+nav{min-width:14em;padding:1em;background:#f4f4f4;min-height:100vh}\
+nav ul{list-style:none;padding-left:0}main{padding:1em 2em;max-width:60em}\
+pre{background:#f4f4f4;padding:.5em}code{font-family:monospace}\
+.fn{border-top:1px solid #ddd;margin-top:1.5em}#results li{margin:.3em 0}";
+
+/// Script for searching the search index on the HTML index page.
+const HTML_SEARCH_SCRIPT: &str = "function search(text){\
+var list=document.getElementById('results');list.innerHTML='';text=text.toLowerCase();\
+if(!text)return;SEARCH_INDEX.filter(function(e){return e.name.toLowerCase().indexOf(text)>=0})\
+.slice(0,50).forEach(function(e){var li=document.createElement('li');var a=document.createElement('a');\
+a.href=e.url;a.textContent=e.module+'::'+e.signature;li.appendChild(a);\
+if(e.summary){li.appendChild(document.createTextNode(' \\u2014 '+e.summary))}list.appendChild(li)})}";
+
+/// Documentation of a module.
+#[derive(Debug, Clone)]
+struct ModuleDoc {
+    /// Name of the module.
+    name: String,
+    /// Description of where the module comes from.
+    kind: &'static str,
+    /// Public functions in the module, sorted by name.
+    functions: Vec<FnMetadata>,
+}
+
+impl ModuleDoc {
+    /// Name of the file holding the page of this module, without extension.
+    fn page(&self) -> String {
+        page_name(&self.name)
+    }
+    /// Iterate over groups of overloaded functions sharing the same name.
+    fn fn_groups(&self) -> impl Iterator<Item = &[FnMetadata]> {
+        let mut rest = &self.functions[..];
+
+        crate::stdlib::iter::from_fn(move || {
+            let first = rest.first()?;
+            let len = rest.iter().take_while(|f| f.name == first.name).count();
+            let (group, tail) = rest.split_at(len);
+            rest = tail;
+            Some(group)
+        })
+    }
+    /// Does this module contain a function with a particular name?
+    fn contains_fn(&self, name: &str) -> bool {
+        self.functions.iter().any(|f| f.name == name)
+    }
+}
+
+/// An entry in the search index.
+#[derive(Debug, Clone, Serialize)]
+struct SearchEntry<'a> {
+    name: &'a str,
+    module: &'a str,
+    signature: &'a str,
+    #[serde(skip_serializing_if = "str::is_empty")]
+    summary: String,
+    url: String,
+}
+
+/// Output format of documentation.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Format {
+    Markdown,
+    Html,
+}
+
+impl Format {
+    /// File extension of documentation pages.
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Html => "html",
+        }
+    }
+}
+
+/// _(METADATA)_ Generator of browsable documentation for the API surface of an [`Engine`]
+/// together with a number of script modules.
+/// Exported under the `serde` and `metadata` features only.
+///
+/// One page is generated for each module, listing its functions with their signatures
+/// (including parameter names and types, where available) and doc-comments.
+///
+/// References to functions in doc-comments, in the form of `` [`name`] `` or `` [`module::name`] ``,
+/// are turned into links to the documentation of those functions.
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(not(feature = "no_function"))]
+/// # {
+/// use rhai::{DocGenerator, Engine};
+///
+/// let engine = Engine::new();
+///
+/// let ast = engine.compile(r"
+///     /// Add one to a number.
+///     fn inc(x) { x + 1 }
+/// ").unwrap();
+///
+/// let mut docs = DocGenerator::new(&engine);
+/// docs.add_script_module("utils", &ast);
+///
+/// let pages = docs.gen_markdown();
+///
+/// assert!(pages["utils.md"].contains("Add one to a number."));
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct DocGenerator<'e> {
+    /// The [`Engine`] whose API is documented.
+    engine: &'e Engine,
+    /// Title of the documentation.
+    title: String,
+    /// Include functions in global modules (e.g. the standard packages)?
+    include_global: bool,
+    /// Script modules to document.
+    scripts: Vec<ModuleDoc>,
+}
+
+impl<'e> DocGenerator<'e> {
+    /// Create a new [`DocGenerator`] for an [`Engine`].
+    #[inline(always)]
+    pub fn new(engine: &'e Engine) -> Self {
+        Self {
+            engine,
+            title: "Rhai API".into(),
+            include_global: false,
+            scripts: Default::default(),
+        }
+    }
+    /// Set the title of the documentation.
+    #[inline(always)]
+    pub fn set_title(&mut self, title: impl Into<String>) -> &mut Self {
+        self.title = title.into();
+        self
+    }
+    /// Include functions in global modules, such as the standard packages, in the documentation
+    /// of the global namespace? Default is `false`.
+    #[inline(always)]
+    pub fn set_include_global(&mut self, include_global: bool) -> &mut Self {
+        self.include_global = include_global;
+        self
+    }
+    /// Add the public functions defined in an [`AST`] as a script module with a particular name.
+    ///
+    /// Nested modules are named with `::` separators, e.g. `utils::strings`.
+    pub fn add_script_module(&mut self, name: impl Into<String>, ast: &AST) -> &mut Self {
+        let _ast = ast;
+
+        #[cfg(not(feature = "no_function"))]
+        let mut functions: Vec<FnMetadata> = _ast
+            .iter_functions()
+            .filter(|f| f.access == crate::FnAccess::Public)
+            .map(|f| f.into())
+            .collect();
+        #[cfg(feature = "no_function")]
+        let mut functions: Vec<FnMetadata> = Default::default();
+
+        functions.sort();
+
+        self.scripts.push(ModuleDoc {
+            name: name.into(),
+            kind: "Script module",
+            functions,
+        });
+        self
+    }
+    /// Collect the documentation of all modules, sorted by name with the global namespace first.
+    fn modules(&self) -> Vec<ModuleDoc> {
+        let mut functions: Vec<FnMetadata> = Default::default();
+
+        if self.include_global {
+            self.engine
+                .global_modules
+                .iter()
+                .flat_map(|m| m.iter_fn())
+                .for_each(|f| functions.push(f.into()));
+        }
+
+        self.engine
+            .global_namespace
+            .iter_fn()
+            .for_each(|f| functions.push(f.into()));
+
+        functions.retain(|f| f.access == FnAccess::Public);
+        functions.sort();
+        functions.dedup_by(|a, b| a.signature == b.signature);
+
+        let mut modules = vec![ModuleDoc {
+            name: GLOBAL_MODULE.into(),
+            kind: "Global namespace",
+            functions,
+        }];
+
+        let mut others = Vec::new();
+
+        self.engine
+            .global_sub_modules
+            .iter()
+            .for_each(|(name, m)| collect_static_module(name, m, &mut others));
+
+        others.extend(self.scripts.iter().cloned());
+        others.sort_by(|a, b| a.name.cmp(&b.name));
+
+        modules.extend(others);
+        modules
+    }
+    /// Generate documentation in Markdown format.
+    ///
+    /// Returns a map of relative file paths to file contents, including an `index.md` page.
+    pub fn gen_markdown(&self) -> BTreeMap<String, String> {
+        self.gen_pages(Format::Markdown)
+    }
+    /// Generate documentation in static HTML format.
+    ///
+    /// Returns a map of relative file paths to file contents, including an `index.html` page
+    /// with a search box, plus the search index as `search-index.json` and `search-index.js`.
+    pub fn gen_html(&self) -> BTreeMap<String, String> {
+        let mut pages = self.gen_pages(Format::Html);
+        let index = self.gen_search_index();
+
+        pages.insert(
+            "search-index.js".into(),
+            format!("var SEARCH_INDEX = {};\n", index),
+        );
+        pages.insert("search-index.json".into(), index);
+        pages
+    }
+    /// Generate a search index of all functions in JSON format.
+    ///
+    /// Each entry contains the function's name, module, signature, a one-line summary
+    /// and the URL of its documentation relative to the generated HTML pages.
+    pub fn gen_search_index(&self) -> String {
+        let modules = self.modules();
+
+        let entries: Vec<_> = modules
+            .iter()
+            .flat_map(|m| {
+                m.functions.iter().map(move |f| SearchEntry {
+                    name: &f.name,
+                    module: &m.name,
+                    signature: &f.signature,
+                    summary: summary(&f.doc_comments),
+                    url: format!("{}.html#{}", m.page(), fn_anchor(&f.name)),
+                })
+            })
+            .collect();
+
+        serde_json::to_string_pretty(&entries).unwrap()
+    }
+    /// Generate the index page and one page per module.
+    fn gen_pages(&self, format: Format) -> BTreeMap<String, String> {
+        let modules = self.modules();
+        let ext = format.extension();
+        let mut pages = BTreeMap::new();
+
+        pages.insert(
+            format!("index.{}", ext),
+            match format {
+                Format::Markdown => self.gen_markdown_index(&modules),
+                Format::Html => self.gen_html_index(&modules),
+            },
+        );
+
+        for module in modules.iter() {
+            let links = Links {
+                modules: &modules,
+                current: module,
+                format,
+            };
+
+            pages.insert(
+                format!("{}.{}", module.page(), ext),
+                match format {
+                    Format::Markdown => gen_markdown_module(module, &links),
+                    Format::Html => self.gen_html_module(module, &links),
+                },
+            );
+        }
+
+        pages
+    }
+    /// Generate the Markdown index page.
+    fn gen_markdown_index(&self, modules: &[ModuleDoc]) -> String {
+        let mut output = format!(
+            "# {}\n\n| Module | Kind | Functions |\n|---|---|---|\n",
+            self.title
+        );
+
+        for module in modules {
+            writeln!(
+                output,
+                "| [`{}`]({}.md) | {} | {} |",
+                module.name,
+                module.page(),
+                module.kind,
+                module.fn_groups().count()
+            )
+            .unwrap();
+        }
+
+        output
+    }
+    /// Generate the HTML index page.
+    fn gen_html_index(&self, modules: &[ModuleDoc]) -> String {
+        let mut body = String::from(
+            "<input type=\"search\" placeholder=\"Search functions...\" \
+            oninput=\"search(this.value)\" autofocus>\n<ul id=\"results\"></ul>\n\
+            <h2>Modules</h2>\n<table>\n<tr><th>Module</th><th>Kind</th><th>Functions</th></tr>\n",
+        );
+
+        for module in modules {
+            writeln!(
+                body,
+                "<tr><td><a href=\"{}.html\"><code>{}</code></a></td><td>{}</td><td>{}</td></tr>",
+                module.page(),
+                html_escape(&module.name),
+                module.kind,
+                module.fn_groups().count()
+            )
+            .unwrap();
+        }
+
+        body.push_str("</table>\n<script src=\"search-index.js\"></script>\n");
+        writeln!(body, "<script>{}</script>", HTML_SEARCH_SCRIPT).unwrap();
+
+        self.gen_html_page(&self.title, modules, &body)
+    }
+    /// Generate the HTML page of a module.
+    fn gen_html_module(&self, module: &ModuleDoc, links: &Links) -> String {
+        let mut body = format!("<p>{}</p>\n<ul>\n", module.kind);
+
+        for group in module.fn_groups() {
+            let name = &group[0].name;
+            writeln!(
+                body,
+                "<li><a href=\"#{}\"><code>{}</code></a></li>",
+                fn_anchor(name),
+                html_escape(name)
+            )
+            .unwrap();
+        }
+
+        body.push_str("</ul>\n");
+
+        for group in module.fn_groups() {
+            let name = &group[0].name;
+
+            writeln!(
+                body,
+                "<div class=\"fn\" id=\"{}\">\n<h2><code>{}</code></h2>",
+                fn_anchor(name),
+                html_escape(name)
+            )
+            .unwrap();
+
+            for f in group {
+                writeln!(
+                    body,
+                    "<pre><code>fn {}</code></pre>",
+                    html_escape(&f.signature)
+                )
+                .unwrap();
+
+                let docs = doc_text(&f.doc_comments);
+
+                if !docs.is_empty() {
+                    body.push_str(&render_html(&docs, links));
+                }
+            }
+
+            body.push_str("</div>\n");
+        }
+
+        let title = format!("Module <code>{}</code>", html_escape(&module.name));
+        self.gen_html_page(&title, links.modules, &body)
+    }
+    /// Wrap the body of an HTML page with the common layout.
+    fn gen_html_page(&self, title: &str, modules: &[ModuleDoc], body: &str) -> String {
+        let mut output = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+            <style>{}</style>\n</head>\n<body>\n<nav>\n<h3><a href=\"index.html\">{}</a></h3>\n<ul>\n",
+            html_escape(&self.title),
+            HTML_STYLE,
+            html_escape(&self.title)
+        );
+
+        for module in modules {
+            writeln!(
+                output,
+                "<li><a href=\"{}.html\">{}</a></li>",
+                module.page(),
+                html_escape(&module.name)
+            )
+            .unwrap();
+        }
+
+        write!(
+            output,
+            "</ul>\n</nav>\n<main>\n<h1>{}</h1>\n{}</main>\n</body>\n</html>\n",
+            title, body
+        )
+        .unwrap();
+
+        output
+    }
+}
+
+/// Collect the documentation of a static module and all its sub-modules.
+fn collect_static_module(name: &str, module: &Module, modules: &mut Vec<ModuleDoc>) {
+    let mut functions: Vec<FnMetadata> = module
+        .iter_fn()
+        .map(|f| f.into())
+        .filter(|f: &FnMetadata| f.access == FnAccess::Public)
+        .collect();
+    functions.sort();
+
+    modules.push(ModuleDoc {
+        name: name.into(),
+        kind: "Static module",
+        functions,
+    });
+
+    module.iter_sub_modules().for_each(|(sub_name, m)| {
+        collect_static_module(&format!("{}::{}", name, sub_name), m.as_ref(), modules)
+    });
+}
+
+/// Generate the Markdown page of a module.
+fn gen_markdown_module(module: &ModuleDoc, links: &Links) -> String {
+    let mut output = format!(
+        "# Module `{}`\n\n{} &middot; [Index](index.md)\n\n",
+        module.name, module.kind
+    );
+
+    for group in module.fn_groups() {
+        let name = &group[0].name;
+        writeln!(output, "- [`{}`](#{})", name, fn_anchor(name)).unwrap();
+    }
+
+    for group in module.fn_groups() {
+        let name = &group[0].name;
+
+        write!(
+            output,
+            "\n<a name=\"{}\"></a>\n## `{}`\n",
+            fn_anchor(name),
+            name
+        )
+        .unwrap();
+
+        for f in group {
+            write!(output, "\n```rust\nfn {}\n```\n", f.signature).unwrap();
+
+            let docs = doc_text(&f.doc_comments);
+
+            if !docs.is_empty() {
+                write!(output, "\n{}\n", render_markdown(&docs, links)).unwrap();
+            }
+        }
+    }
+
+    output
+}
+
+/// Resolver of references to functions and modules into links.
+struct Links<'a> {
+    /// All documented modules.
+    modules: &'a [ModuleDoc],
+    /// The module whose page is being generated.
+    current: &'a ModuleDoc,
+    /// Output format.
+    format: Format,
+}
+
+impl Links<'_> {
+    /// Resolve a reference, in the form of `name`, `module::name` or `module`, into a URL.
+    ///
+    /// Functions in the current module take precedence, followed by those in the global namespace.
+    fn resolve(&self, target: &str) -> Option<String> {
+        let ext = self.format.extension();
+        let page = |m: &ModuleDoc, name: &str| format!("{}.{}#{}", m.page(), ext, fn_anchor(name));
+
+        if let Some(index) = target.rfind("::") {
+            let (module, name) = (&target[..index], &target[index + 2..]);
+
+            if let Some(m) = self
+                .modules
+                .iter()
+                .find(|m| m.name == module && m.contains_fn(name))
+            {
+                return Some(page(m, name));
+            }
+        }
+
+        if self.current.contains_fn(target) {
+            return Some(format!("#{}", fn_anchor(target)));
+        }
+
+        if let Some(m) = self
+            .modules
+            .iter()
+            .find(|m| m.name == GLOBAL_MODULE && m.contains_fn(target))
+            .or_else(|| self.modules.iter().find(|m| m.contains_fn(target)))
+        {
+            return Some(page(m, target));
+        }
+
+        self.modules
+            .iter()
+            .find(|m| m.name == target)
+            .map(|m| format!("{}.{}", m.page(), ext))
+    }
+}
+
+/// Name of the page of a module, with characters unsafe for file names replaced.
+fn page_name(module: &str) -> String {
+    module
+        .replace("::", ".")
+        .chars()
+        .map(|ch| match ch {
+            ch if ch.is_ascii_alphanumeric() => ch,
+            '.' | '_' | '-' => ch,
+            _ => '_',
+        })
+        .collect()
+}
+
+/// Anchor of a function in a documentation page.
+///
+/// Characters other than ASCII letters, digits and `_` are encoded, so operators can be anchored.
+fn fn_anchor(name: &str) -> String {
+    let mut anchor = String::from(FN_ANCHOR_PREFIX);
+
+    for ch in name.chars() {
+        if ch.is_ascii_alphanumeric() || ch == '_' {
+            anchor.push(ch);
+        } else {
+            write!(anchor, "-{:x}", ch as u32).unwrap();
+        }
+    }
+
+    anchor
+}
+
+/// Strip the doc-comment leaders (`///` or `/** ... */`) from doc-comments, joining them into text.
+fn doc_text(comments: &[String]) -> String {
+    let mut lines = Vec::new();
+
+    for comment in comments {
+        if let Some(line) = comment.strip_prefix("///") {
+            lines.push(line.strip_prefix(' ').unwrap_or(line).to_string());
+        } else if let Some(block) = comment.strip_prefix("/**") {
+            let block = block.strip_suffix("*/").unwrap_or(block);
+
+            for line in block.lines() {
+                let line = line.trim_start();
+                let line = line.strip_prefix('*').unwrap_or(line);
+                lines.push(
+                    line.strip_prefix(' ')
+                        .unwrap_or(line)
+                        .trim_end()
+                        .to_string(),
+                );
+            }
+        }
+    }
+
+    // Trim blank lines at both ends
+    while lines.last().is_some_and(|s| s.trim().is_empty()) {
+        lines.pop();
+    }
+    let start = lines.iter().take_while(|s| s.trim().is_empty()).count();
+
+    lines[start..].join("\n")
+}
+
+/// The first paragraph of doc-comments, as a single line.
+fn summary(comments: &[String]) -> String {
+    doc_text(comments)
+        .lines()
+        .take_while(|s| !s.trim().is_empty())
+        .map(str::trim)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Find a reference, in the form of `` [`target`] `` or `[target]` but not `[text](url)`,
+/// at the start of a piece of text.
+///
+/// Returns the length of the reference in bytes and the target.
+fn find_reference(text: &str) -> Option<(usize, &str)> {
+    let end = text.find(']')?;
+    let inner = &text[1..end];
+
+    if text[end + 1..].starts_with('(') || text[end + 1..].starts_with('[') {
+        return None;
+    }
+
+    let target = inner.trim_matches('`');
+
+    if target.is_empty() || target.contains(char::is_whitespace) {
+        None
+    } else {
+        Some((end + 1, target))
+    }
+}
+
+/// Render doc-comment text into Markdown, resolving references into links.
+fn render_markdown(text: &str, links: &Links) -> String {
+    let mut output = String::new();
+    let mut in_code = false;
+
+    for line in text.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+        } else if !in_code {
+            let mut rest = line;
+
+            while let Some(index) = rest.find('[') {
+                output.push_str(&rest[..index]);
+                rest = &rest[index..];
+
+                match find_reference(rest) {
+                    Some((len, target)) => {
+                        match links.resolve(target) {
+                            Some(url) => write!(output, "[`{}`]({})", target, url).unwrap(),
+                            None => output.push_str(&rest[..len]),
+                        }
+                        rest = &rest[len..];
+                    }
+                    None => {
+                        output.push('[');
+                        rest = &rest[1..];
+                    }
+                }
+            }
+
+            output.push_str(rest);
+            output.push('\n');
+            continue;
+        }
+
+        output.push_str(line);
+        output.push('\n');
+    }
+
+    output
+}
+
+/// Render doc-comment text, written in a subset of Markdown, into HTML,
+/// resolving references into links.
+///
+/// Supported are paragraphs, headings, bullet lists, fenced code blocks, code spans and links.
+fn render_html(text: &str, links: &Links) -> String {
+    let mut output = String::new();
+    let mut paragraph = String::new();
+    let mut code_block: Option<String> = None;
+    let mut in_list = false;
+
+    let flush = |output: &mut String, paragraph: &mut String, in_list: &mut bool| {
+        if !paragraph.is_empty() {
+            writeln!(
+                output,
+                "<p>{}</p>",
+                render_html_inline(paragraph.trim_end(), links)
+            )
+            .unwrap();
+            paragraph.clear();
+        }
+        if *in_list {
+            output.push_str("</ul>\n");
+            *in_list = false;
+        }
+    };
+
+    for line in text.lines() {
+        let trimmed = line.trim();
+
+        if let Some(ref mut code) = code_block {
+            if trimmed.starts_with("```") {
+                writeln!(output, "<pre><code>{}</code></pre>", html_escape(code)).unwrap();
+                code_block = None;
+            } else {
+                code.push_str(line);
+                code.push('\n');
+            }
+        } else if trimmed.starts_with("```") {
+            flush(&mut output, &mut paragraph, &mut in_list);
+            code_block = Some(String::new());
+        } else if trimmed.is_empty() {
+            flush(&mut output, &mut paragraph, &mut in_list);
+        } else if trimmed.starts_with('#') {
+            flush(&mut output, &mut paragraph, &mut in_list);
+            let heading = trimmed.trim_start_matches('#').trim();
+            writeln!(output, "<h4>{}</h4>", render_html_inline(heading, links)).unwrap();
+        } else if let Some(item) = trimmed
+            .strip_prefix("- ")
+            .or_else(|| trimmed.strip_prefix("* "))
+        {
+            if !in_list {
+                flush(&mut output, &mut paragraph, &mut in_list);
+                output.push_str("<ul>\n");
+                in_list = true;
+            }
+            writeln!(output, "<li>{}</li>", render_html_inline(item, links)).unwrap();
+        } else {
+            if in_list {
+                flush(&mut output, &mut paragraph, &mut in_list);
+            }
+            paragraph.push_str(trimmed);
+            paragraph.push('\n');
+        }
+    }
+
+    if let Some(code) = code_block {
+        writeln!(output, "<pre><code>{}</code></pre>", html_escape(&code)).unwrap();
+    }
+    flush(&mut output, &mut paragraph, &mut in_list);
+
+    output
+}
+
+/// Render a line of text with code spans, links and references into HTML.
+fn render_html_inline(text: &str, links: &Links) -> String {
+    let mut output = String::new();
+    let mut rest = text;
+
+    while let Some(index) = rest.find(['`', '[']) {
+        output.push_str(&html_escape(&rest[..index]));
+        rest = &rest[index..];
+
+        if rest.starts_with('`') {
+            match rest[1..].find('`') {
+                Some(end) => {
+                    write!(output, "<code>{}</code>", html_escape(&rest[1..end + 1])).unwrap();
+                    rest = &rest[end + 2..];
+                }
+                None => {
+                    output.push('`');
+                    rest = &rest[1..];
+                }
+            }
+            continue;
+        }
+
+        // Explicit link: [text](url)
+        if let Some(end) = rest.find("](") {
+            if let Some(close) = rest[end..].find(')') {
+                let label = &rest[1..end];
+                let url = &rest[end + 2..end + close];
+
+                if !label.contains('[') {
+                    write!(
+                        output,
+                        "<a href=\"{}\">{}</a>",
+                        html_escape(url),
+                        render_html_inline(label, links)
+                    )
+                    .unwrap();
+                    rest = &rest[end + close + 1..];
+                    continue;
+                }
+            }
+        }
+
+        match find_reference(rest) {
+            Some((len, target)) => {
+                match links.resolve(target) {
+                    Some(url) => write!(
+                        output,
+                        "<a href=\"{}\"><code>{}</code></a>",
+                        html_escape(&url),
+                        html_escape(target)
+                    )
+                    .unwrap(),
+                    None => output.push_str(&html_escape(&rest[..len])),
+                }
+                rest = &rest[len..];
+            }
+            None => {
+                output.push('[');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    output.push_str(&html_escape(rest));
+    output
+}
+
+/// Escape special characters in HTML text.
+fn html_escape(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+
+    for ch in text.chars() {
+        match ch {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            ch => output.push(ch),
+        }
+    }
+
+    output
+}
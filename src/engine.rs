@@ -2,6 +2,7 @@
 
 use crate::ast::{Expr, FnCallExpr, Ident, OpAssignment, ReturnType, Stmt, StmtBlock};
 use crate::dynamic::{map_std_type_name, AccessMode, Union, Variant};
+use crate::eval_stack::is_stacked_expr;
 use crate::fn_native::{
     CallableFunction, IteratorFn, OnDebugCallback, OnPrintCallback, OnProgressCallback,
     OnVarCallback,
@@ -17,6 +18,7 @@ use crate::stdlib::{
     collections::{BTreeMap, BTreeSet},
     fmt, format,
     hash::{Hash, Hasher},
+    mem,
     num::{NonZeroU8, NonZeroUsize},
     ops::DerefMut,
     string::{String, ToString},
@@ -30,7 +32,7 @@ use crate::{
 };
 
 #[cfg(not(feature = "no_index"))]
-use crate::{calc_fn_hash, stdlib::iter::empty, Array};
+use crate::{calc_fn_hash, stdlib::iter::empty};
 
 #[cfg(not(feature = "no_object"))]
 use crate::Map;
@@ -53,7 +55,167 @@ pub type Precedence = NonZeroU8;
 // the module name will live beyond the AST of the eval script text.
 // The best we can do is a shared reference.
 #[derive(Debug, Clone, Default)]
-pub struct Imports(StaticVec<Identifier>, StaticVec<Shared<Module>>);
+pub struct Imports(
+    StaticVec<Identifier>,
+    StaticVec<Shared<Module>>,
+    #[cfg(not(feature = "no_module"))] StaticVec<Option<LazyModule>>,
+);
+
+/// A [module][Module] imported lazily, i.e. only resolved when one of its members is first
+/// accessed.
+#[cfg(not(feature = "no_module"))]
+#[derive(Debug, Clone)]
+pub(crate) struct LazyModule {
+    /// Path of the [module][Module].
+    path: ImmutableString,
+    /// Source of the script doing the import, if any.
+    source: Option<Identifier>,
+    /// Position of the import path.
+    pos: Position,
+    /// The [module][Module] once resolved, shared among all copies of the import.
+    module: Shared<crate::fn_native::Locked<Option<Shared<Module>>>>,
+}
+
+#[cfg(not(feature = "no_module"))]
+impl LazyModule {
+    /// Create a new [`LazyModule`] that is not yet resolved.
+    #[inline(always)]
+    pub(crate) fn new(path: ImmutableString, source: Option<Identifier>, pos: Position) -> Self {
+        Self {
+            path,
+            source,
+            pos,
+            module: Default::default(),
+        }
+    }
+    /// Get the path of the [module][Module].
+    #[allow(dead_code)]
+    #[inline(always)]
+    pub(crate) fn path(&self) -> &ImmutableString {
+        &self.path
+    }
+    /// Get the source of the script doing the import, if any.
+    #[allow(dead_code)]
+    #[inline(always)]
+    pub(crate) fn source(&self) -> Option<&str> {
+        self.source.as_ref().map(|s| s.as_str())
+    }
+    /// Get the position of the import path.
+    #[allow(dead_code)]
+    #[inline(always)]
+    pub(crate) fn position(&self) -> Position {
+        self.pos
+    }
+    /// Get the [module][Module], if it has been resolved.
+    #[inline(always)]
+    fn get(&self) -> Option<Shared<Module>> {
+        #[cfg(not(feature = "sync"))]
+        return self.module.borrow().clone();
+        #[cfg(feature = "sync")]
+        return self.module.read().unwrap().clone();
+    }
+    /// Set the resolved [module][Module].
+    #[inline(always)]
+    fn set(&self, module: Shared<Module>) {
+        #[cfg(not(feature = "sync"))]
+        let mut value = self.module.borrow_mut();
+        #[cfg(feature = "sync")]
+        let mut value = self.module.write().unwrap();
+
+        *value = Some(module);
+    }
+}
+
+/// _(INTERNALS)_ A cache of [modules][Module] resolved by `import` statements inside functions,
+/// keyed by the source of the importing script and the import path.
+/// Exported under the `internals` feature only.
+///
+/// Each [`AST`][crate::AST] holds its own cache.  The cache is emptied when the
+/// [`AST`][crate::AST] is evaluated by an [`Engine`] with a different module resolver, so
+/// [modules][Module] are always resolved by the module resolver of the [`Engine`] running the script.
+#[cfg(not(feature = "no_module"))]
+#[derive(Debug, Default)]
+pub struct ModuleCache(crate::fn_native::Locked<ModuleCacheEntries>);
+
+/// Key of a [`ModuleCache`]: the source of the importing script and the import path.
+#[cfg(not(feature = "no_module"))]
+type ModuleCacheKey = (Option<Identifier>, ImmutableString);
+
+/// [Modules][Module] in a [`ModuleCache`].
+#[cfg(not(feature = "no_module"))]
+#[derive(Debug, Default)]
+struct ModuleCacheEntries {
+    /// Identity of the module resolver that resolved the [modules][Module].
+    resolver: Option<Shared<()>>,
+    /// Cached [modules][Module].
+    modules: BTreeMap<ModuleCacheKey, Shared<Module>>,
+}
+
+#[cfg(not(feature = "no_module"))]
+impl ModuleCache {
+    /// Get a mutable reference to the cache.
+    #[inline(always)]
+    fn cache(&self) -> impl DerefMut<Target = ModuleCacheEntries> + '_ {
+        #[cfg(not(feature = "sync"))]
+        return self.0.borrow_mut();
+        #[cfg(feature = "sync")]
+        return self.0.write().unwrap();
+    }
+    /// Get the number of cached [modules][Module].
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.cache().modules.len()
+    }
+    /// Is the cache empty?
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.cache().modules.is_empty()
+    }
+    /// Empty the cache.
+    #[inline(always)]
+    pub fn clear(&self) {
+        let mut cache = self.cache();
+        cache.resolver = None;
+        cache.modules.clear();
+    }
+    /// Get the [module][Module] cached for an import path, given the source of the importing
+    /// script, if it was resolved by the same module resolver.
+    #[inline(always)]
+    pub(crate) fn get(
+        &self,
+        resolver: &Shared<()>,
+        source: &Option<Identifier>,
+        path: &ImmutableString,
+    ) -> Option<Shared<Module>> {
+        let cache = self.cache();
+
+        match cache.resolver {
+            Some(ref r) if Shared::ptr_eq(r, resolver) => {
+                cache.modules.get(&(source.clone(), path.clone())).cloned()
+            }
+            _ => None,
+        }
+    }
+    /// Cache the [module][Module] for an import path, given the source of the importing script,
+    /// replacing all [modules][Module] resolved by a different module resolver.
+    #[inline(always)]
+    pub(crate) fn insert(
+        &self,
+        resolver: &Shared<()>,
+        source: Option<Identifier>,
+        path: ImmutableString,
+        module: Shared<Module>,
+    ) {
+        let mut cache = self.cache();
+
+        if !matches!(cache.resolver, Some(ref r) if Shared::ptr_eq(r, resolver)) {
+            cache.resolver = Some(resolver.clone());
+            cache.modules.clear();
+        }
+
+        cache.modules.insert((source, path), module);
+    }
+}
 
 impl Imports {
     /// Get the length of this stack of imported [modules][Module].
@@ -67,10 +229,18 @@ impl Imports {
         self.0.is_empty()
     }
     /// Get the imported [modules][Module] at a particular index.
+    ///
+    /// A [module][Module] imported lazily is returned as an empty [module][Module].
     #[inline(always)]
     pub fn get(&self, index: usize) -> Option<Shared<Module>> {
         self.1.get(index).cloned()
     }
+    /// Get the [module][Module] imported lazily at a particular index, if any.
+    #[cfg(not(feature = "no_module"))]
+    #[inline(always)]
+    pub(crate) fn get_lazy(&self, index: usize) -> Option<&LazyModule> {
+        self.2.get(index).and_then(|m| m.as_ref())
+    }
     /// Get the index of an imported [modules][Module] by name.
     #[inline(always)]
     pub fn find(&self, name: &str) -> Option<usize> {
@@ -85,12 +255,39 @@ impl Imports {
     pub fn push(&mut self, name: impl Into<Identifier>, module: impl Into<Shared<Module>>) {
         self.0.push(name.into());
         self.1.push(module.into());
+        #[cfg(not(feature = "no_module"))]
+        self.2.push(None);
+    }
+    /// Push a [module][Module] imported lazily onto the stack.
+    ///
+    /// An empty [module][Module] stands in for it until it is resolved.
+    #[cfg(not(feature = "no_module"))]
+    #[inline(always)]
+    pub(crate) fn push_lazy(&mut self, name: impl Into<Identifier>, module: LazyModule) {
+        self.0.push(name.into());
+        self.1.push(Module::new().into());
+        self.2.push(Some(module));
+    }
+    /// Push an imported [module][Module], which may be imported lazily, onto the stack.
+    #[cfg(not(feature = "no_module"))]
+    #[inline(always)]
+    pub(crate) fn push_raw(
+        &mut self,
+        name: Identifier,
+        module: Shared<Module>,
+        lazy: Option<LazyModule>,
+    ) {
+        self.0.push(name);
+        self.1.push(module);
+        self.2.push(lazy);
     }
     /// Truncate the stack of imported [modules][Module] to a particular length.
     #[inline(always)]
     pub fn truncate(&mut self, size: usize) {
         self.0.truncate(size);
         self.1.truncate(size);
+        #[cfg(not(feature = "no_module"))]
+        self.2.truncate(size);
     }
     /// Get an iterator to this stack of imported [modules][Module] in reverse order.
     #[allow(dead_code)]
@@ -108,6 +305,17 @@ impl Imports {
     pub(crate) fn iter_raw(&self) -> impl Iterator<Item = (&Identifier, &Shared<Module>)> {
         self.0.iter().rev().zip(self.1.iter().rev())
     }
+    /// Get an iterator to this stack of imported [modules][Module], including those imported
+    /// lazily, in reverse order.
+    #[cfg(not(feature = "no_module"))]
+    #[inline(always)]
+    pub(crate) fn iter_lazy_raw(
+        &self,
+    ) -> impl Iterator<Item = (&Identifier, &Shared<Module>, Option<&LazyModule>)> {
+        self.iter_raw()
+            .zip(self.2.iter().rev())
+            .map(|((name, module), lazy)| (name, module, lazy.as_ref()))
+    }
     /// Get an iterator to this stack of imported [modules][Module] in forward order.
     #[allow(dead_code)]
     #[inline(always)]
@@ -208,6 +416,11 @@ pub const OP_CONCAT: &str = "+=";
 /// The `in` operator is implemented as a call to this method.
 pub const OP_CONTAINS: &str = "contains";
 
+/// Built-in assertion functions.
+///
+/// The source text of calls to these functions is captured by the parser for error messages.
+pub const FN_ASSERTIONS: [&str; 4] = ["assert", "assert_eq", "assert_ne", "assert_approx"];
+
 /// Method of chaining.
 #[cfg(any(not(feature = "no_index"), not(feature = "no_object")))]
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
@@ -499,6 +712,23 @@ pub struct FnResolutionCacheEntry {
     pub source: Option<Identifier>,
 }
 
+/// A call to a script-defined function in tail position, with its arguments already evaluated.
+///
+/// Instead of calling the function, the caller returns and the call is made in its place, so
+/// that tail recursion does not grow the call stack.
+#[cfg(not(feature = "no_function"))]
+#[derive(Debug, Clone)]
+pub(crate) struct TailCall {
+    /// Function to call.
+    pub func: FnResolutionCacheEntry,
+    /// Argument values.
+    pub args: StaticVec<Dynamic>,
+    /// Position of the function call.
+    pub pos: Position,
+    /// Call level of the caller.
+    pub level: usize,
+}
+
 /// A function resolution cache.
 pub type FnResolutionCache = BTreeMap<u64, Option<FnResolutionCacheEntry>>;
 
@@ -512,6 +742,9 @@ pub type FnResolutionCache = BTreeMap<u64, Option<FnResolutionCacheEntry>>;
 pub struct State {
     /// Source of the current context.
     pub source: Option<Identifier>,
+    /// Source of the [module][Module] defining the script-defined function being called, if any.
+    /// Coverage of the function is recorded under it, without changing the current source.
+    pub fn_source: Option<Identifier>,
     /// Normally, access to variables are parsed with a relative offset into the scope to avoid a lookup.
     /// In some situation, e.g. after running an `eval` statement, subsequent offsets become mis-aligned.
     /// When that happens, this flag is turned on to force a scope lookup by name.
@@ -519,6 +752,8 @@ pub struct State {
     /// Level of the current scope.  The global (root) level is zero, a new block
     /// (or function call) is one level higher, and so on.
     pub scope_level: usize,
+    /// Is a script-defined function being called?
+    pub in_function: bool,
     /// Number of operations performed.
     pub operations: u64,
     /// Number of modules loaded.
@@ -526,8 +761,16 @@ pub struct State {
     /// Embedded module resolver.
     #[cfg(not(feature = "no_module"))]
     pub resolver: Option<Shared<crate::module::resolvers::StaticModuleResolver>>,
+    /// Cache of modules resolved by imports inside functions.
+    #[cfg(not(feature = "no_module"))]
+    pub module_cache: Option<Shared<ModuleCache>>,
+    /// Is the next statement in tail position of a script-defined function?
+    pub in_tail: bool,
     /// Function resolution cache and free list.
     fn_resolution_caches: (StaticVec<FnResolutionCache>, Vec<FnResolutionCache>),
+    /// Pending call to a script-defined function in tail position.
+    #[cfg(not(feature = "no_function"))]
+    tail_call: Option<Box<TailCall>>,
 }
 
 impl State {
@@ -536,6 +779,14 @@ impl State {
     pub fn is_global(&self) -> bool {
         self.scope_level == 0
     }
+    /// Get the source under which coverage of the current context is recorded.
+    #[inline(always)]
+    pub fn coverage_source(&self) -> Option<&str> {
+        self.fn_source
+            .as_ref()
+            .or(self.source.as_ref())
+            .map(|s| s.as_str())
+    }
     /// Get a mutable reference to the current function resolution cache.
     pub fn fn_resolution_cache_mut(&mut self) -> &mut FnResolutionCache {
         if self.fn_resolution_caches.0.is_empty() {
@@ -560,6 +811,29 @@ impl State {
         cache.clear();
         self.fn_resolution_caches.1.push(cache);
     }
+    /// Set the pending call to a script-defined function in tail position.
+    #[cfg(not(feature = "no_function"))]
+    #[inline(always)]
+    pub(crate) fn set_tail_call(&mut self, tail_call: TailCall) {
+        self.tail_call = Some(tail_call.into());
+    }
+    /// Get the pending call to a script-defined function in tail position, if it was made at
+    /// the specified call level.
+    #[cfg(not(feature = "no_function"))]
+    #[inline(always)]
+    pub(crate) fn tail_call(&self, level: usize) -> Option<&TailCall> {
+        self.tail_call.as_deref().filter(|t| t.level == level)
+    }
+    /// Take the pending call to a script-defined function in tail position, if it was made at
+    /// the specified call level.
+    #[cfg(not(feature = "no_function"))]
+    #[inline(always)]
+    pub(crate) fn take_tail_call(&mut self, level: usize) -> Option<Box<TailCall>> {
+        match self.tail_call {
+            Some(ref t) if t.level == level => self.tail_call.take(),
+            _ => None,
+        }
+    }
 }
 
 /// _(INTERNALS)_ A type containing all the limits imposed by the [`Engine`].
@@ -713,6 +987,15 @@ pub struct Engine {
     /// A module resolution service.
     #[cfg(not(feature = "no_module"))]
     pub(crate) module_resolver: Box<dyn crate::ModuleResolver>,
+    /// Identity of the module resolution service, which changes whenever it is set.
+    #[cfg(not(feature = "no_module"))]
+    pub(crate) module_resolver_id: Shared<()>,
+    /// Resolve imported modules lazily?
+    #[cfg(not(feature = "no_module"))]
+    pub(crate) lazy_imports: bool,
+    /// Allow import paths that are not string literals?
+    #[cfg(not(feature = "no_module"))]
+    pub(crate) computed_imports: bool,
 
     /// A map mapping type names to pretty-print names.
     pub(crate) type_names: BTreeMap<Identifier, Identifier>,
@@ -735,9 +1018,17 @@ pub struct Engine {
     pub(crate) debug: OnDebugCallback,
     /// Callback closure for progress reporting.
     pub(crate) progress: Option<OnProgressCallback>,
+    /// Profiler recording all function calls.
+    #[cfg(not(feature = "no_std"))]
+    pub(crate) profiler: Option<crate::Profiler>,
+    /// Coverage collector recording all statements executed.
+    pub(crate) coverage: Option<crate::Coverage>,
 
     /// Optimize the AST after compilation.
     pub(crate) optimization_level: OptimizationLevel,
+    /// Strip calls to built-in assertion functions during optimization.
+    #[cfg(not(feature = "no_optimize"))]
+    pub(crate) strip_assertions: bool,
 
     /// Max limits.
     #[cfg(not(feature = "unchecked"))]
@@ -802,6 +1093,19 @@ fn default_debug(_s: &str, _source: Option<&str>, _pos: Position) {
     }
 }
 
+/// Index a [module][Module] (making a clone copy if necessary) if it is not indexed.
+#[cfg(not(feature = "no_module"))]
+#[inline(always)]
+fn index_module(module: Shared<Module>) -> Shared<Module> {
+    if module.is_indexed() {
+        module
+    } else {
+        let mut module = crate::fn_native::shared_take_or_clone(module);
+        module.build_index();
+        module.into()
+    }
+}
+
 impl Engine {
     /// Create a new [`Engine`]
     #[inline]
@@ -819,6 +1123,12 @@ impl Engine {
             #[cfg(not(feature = "no_module"))]
             #[cfg(any(feature = "no_std", target_arch = "wasm32",))]
             module_resolver: Box::new(crate::module::resolvers::DummyModuleResolver::new()),
+            #[cfg(not(feature = "no_module"))]
+            module_resolver_id: Default::default(),
+            #[cfg(not(feature = "no_module"))]
+            lazy_imports: false,
+            #[cfg(not(feature = "no_module"))]
+            computed_imports: true,
 
             type_names: Default::default(),
             empty_string: Default::default(),
@@ -836,12 +1146,21 @@ impl Engine {
             // progress callback
             progress: None,
 
+            // profiler
+            #[cfg(not(feature = "no_std"))]
+            profiler: None,
+
+            // coverage
+            coverage: None,
+
             // optimization level
             optimization_level: if cfg!(feature = "no_optimize") {
                 OptimizationLevel::None
             } else {
                 OptimizationLevel::Simple
             },
+            #[cfg(not(feature = "no_optimize"))]
+            strip_assertions: false,
 
             #[cfg(not(feature = "unchecked"))]
             limits: Limits {
@@ -880,6 +1199,12 @@ impl Engine {
 
             #[cfg(not(feature = "no_module"))]
             module_resolver: Box::new(crate::module::resolvers::DummyModuleResolver::new()),
+            #[cfg(not(feature = "no_module"))]
+            module_resolver_id: Default::default(),
+            #[cfg(not(feature = "no_module"))]
+            lazy_imports: false,
+            #[cfg(not(feature = "no_module"))]
+            computed_imports: true,
 
             type_names: Default::default(),
             empty_string: Default::default(),
@@ -892,12 +1217,17 @@ impl Engine {
             print: Box::new(|_| {}),
             debug: Box::new(|_, _, _| {}),
             progress: None,
+            #[cfg(not(feature = "no_std"))]
+            profiler: None,
+            coverage: None,
 
             optimization_level: if cfg!(feature = "no_optimize") {
                 OptimizationLevel::None
             } else {
                 OptimizationLevel::Simple
             },
+            #[cfg(not(feature = "no_optimize"))]
+            strip_assertions: false,
 
             #[cfg(not(feature = "unchecked"))]
             limits: Limits {
@@ -921,13 +1251,15 @@ impl Engine {
     }
 
     /// Search for a module within an imports stack.
+    ///
+    /// A module imported lazily is resolved when it is first found.
     #[inline]
     pub(crate) fn search_imports(
         &self,
         mods: &Imports,
         state: &mut State,
         namespace: &NamespaceRef,
-    ) -> Option<Shared<Module>> {
+    ) -> Result<Option<Shared<Module>>, Box<EvalAltResult>> {
         let root = &namespace[0].name;
 
         // Qualified - check if the root module is directly indexed
@@ -937,14 +1269,65 @@ impl Engine {
             namespace.index()
         };
 
+        let index = match index {
+            Some(index) => Some(mods.len() - index.get()),
+            None => mods.find(root),
+        };
+
         if let Some(index) = index {
-            let offset = mods.len() - index.get();
-            Some(mods.get(offset).expect("invalid index in Imports"))
+            #[cfg(not(feature = "no_module"))]
+            if let Some(lazy) = mods.get_lazy(index) {
+                return self.resolve_lazy_module(state, lazy).map(Some);
+            }
+
+            Ok(Some(mods.get(index).expect("invalid index in Imports")))
         } else {
-            mods.find(root)
-                .map(|n| mods.get(n).expect("invalid index in Imports"))
-                .or_else(|| self.global_sub_modules.get(root).cloned())
+            Ok(self.global_sub_modules.get(root).cloned())
+        }
+    }
+
+    /// Resolve a module by path, first with the embedded module resolver (if any), then with the
+    /// [`Engine`]'s module resolver.
+    #[cfg(not(feature = "no_module"))]
+    fn resolve_module(
+        &self,
+        state: &State,
+        source: Option<&str>,
+        path: &str,
+        pos: Position,
+    ) -> Result<Shared<Module>, Box<EvalAltResult>> {
+        use crate::ModuleResolver;
+
+        state
+            .resolver
+            .as_ref()
+            .and_then(|r| match r.resolve(self, source, path, pos) {
+                Ok(m) => Some(Ok(m)),
+                Err(err) => match *err {
+                    EvalAltResult::ErrorModuleNotFound(_, _, _) => None,
+                    _ => Some(Err(err)),
+                },
+            })
+            .unwrap_or_else(|| self.module_resolver.resolve(self, source, path, pos))
+    }
+
+    /// Resolve a module imported lazily, if it is not yet resolved.
+    #[cfg(not(feature = "no_module"))]
+    fn resolve_lazy_module(
+        &self,
+        state: &State,
+        lazy: &LazyModule,
+    ) -> Result<Shared<Module>, Box<EvalAltResult>> {
+        if let Some(module) = lazy.get() {
+            return Ok(module);
         }
+
+        let source = lazy.source.as_ref().map(|s| s.as_str());
+        let module = index_module(self.resolve_module(state, source, &lazy.path, lazy.pos)?);
+
+        lazy.set(module.clone());
+
+        Ok(module)
     }
 
     /// Search for a variable within the scope or within imports,
@@ -962,9 +1345,10 @@ impl Engine {
             Expr::Variable(v) => match v.as_ref() {
                 // Qualified variable
                 (_, Some((hash_var, modules)), Ident { name, pos, .. }) => {
-                    let module = self.search_imports(mods, state, modules).ok_or_else(|| {
+                    let module = self.search_imports(mods, state, modules)?.ok_or_else(|| {
                         EvalAltResult::ErrorModuleNotFound(
                             modules[0].name.to_string(),
+                            Default::default(),
                             modules[0].pos,
                         )
                     })?;
@@ -1054,6 +1438,9 @@ impl Engine {
 
     /// Chain-evaluate a dot/index chain.
     /// [`Position`] in [`EvalAltResult`] is [`NONE`][Position::NONE] and must be set afterwards.
+    ///
+    /// Links into nested arrays and object maps are followed by reference in a loop, so that
+    /// deep chains do not grow the native stack.
     #[cfg(any(not(feature = "no_index"), not(feature = "no_object")))]
     fn eval_dot_index_chain_helper(
         &self,
@@ -1067,11 +1454,132 @@ impl Engine {
         chain_type: ChainType,
         level: usize,
         new_val: Option<((Dynamic, Position), (Option<OpAssignment>, Position))>,
+    ) -> Result<(Dynamic, bool), Box<EvalAltResult>> {
+        /// Object at the current link of the chain.
+        enum Link<'t, 'a> {
+            /// The original target.
+            Target(&'t mut Target<'a>),
+            /// A value inside the original target.
+            Ref(&'t mut Dynamic),
+        }
+
+        let mut link = Link::Target(target);
+        let mut rhs = rhs;
+        let mut chain_type = chain_type;
+        let mut link_pos = Position::NONE;
+
+        while let Expr::Dot(x, x_pos) | Expr::Index(x, x_pos) = rhs {
+            let (index, idx_pos) = match chain_type {
+                // xxx[idx].expr... | xxx[idx][expr]...
+                #[cfg(not(feature = "no_index"))]
+                ChainType::Index => {
+                    let index = idx_values.pop().unwrap().as_index_value();
+                    (index, x.lhs.position())
+                }
+                // {xxx:map}.prop.expr... | {xxx:map}.prop[expr]...
+                #[cfg(not(feature = "no_object"))]
+                ChainType::Dot => {
+                    let is_map = match link {
+                        Link::Target(ref target) => target.as_ref().is::<Map>(),
+                        Link::Ref(ref value) => value.is::<Map>(),
+                    };
+
+                    match x.lhs {
+                        Expr::Property(ref p) if is_map => {
+                            idx_values.pop();
+                            let Ident { name, pos, .. } = &p.2;
+                            (name.into(), *pos)
+                        }
+                        _ => break,
+                    }
+                }
+                chain_type => unreachable!("invalid ChainType: {:?}", chain_type),
+            };
+
+            let (is_ref, target_val) = match link {
+                Link::Target(target) => (target.is_ref(), target.as_mut()),
+                Link::Ref(value) => (true, value),
+            };
+
+            let obj_ptr = self
+                .get_indexed_mut(
+                    mods, state, lib, target_val, index, idx_pos, false, is_ref, true, level,
+                )
+                .map_err(|err| err.fill_position(link_pos))?;
+
+            chain_type = match rhs {
+                Expr::Index(_, _) => ChainType::Index,
+                _ => ChainType::Dot,
+            };
+            rhs = &x.rhs;
+
+            match obj_ptr {
+                Target::Ref(value) => {
+                    link = Link::Ref(value);
+                    link_pos = *x_pos;
+                }
+                // Temporary values are chain-evaluated recursively
+                mut obj_ptr => {
+                    return self
+                        .eval_dot_index_chain_helper(
+                            mods,
+                            state,
+                            lib,
+                            this_ptr,
+                            &mut obj_ptr,
+                            rhs,
+                            idx_values,
+                            chain_type,
+                            level,
+                            new_val,
+                        )
+                        .map_err(|err| err.fill_position(*x_pos))
+                }
+            }
+        }
+
+        match link {
+            Link::Target(target) => self.eval_dot_index_chain_link(
+                mods, state, lib, this_ptr, target, rhs, idx_values, chain_type, level, new_val,
+            ),
+            Link::Ref(value) => self.eval_dot_index_chain_link(
+                mods,
+                state,
+                lib,
+                this_ptr,
+                &mut Target::Ref(value),
+                rhs,
+                idx_values,
+                chain_type,
+                level,
+                new_val,
+            ),
+        }
+        .map_err(|err| err.fill_position(link_pos))
+    }
+
+    /// Chain-evaluate a link of a dot/index chain which is not followed by reference in
+    /// [`eval_dot_index_chain_helper`][Engine::eval_dot_index_chain_helper].
+    /// [`Position`] in [`EvalAltResult`] is [`NONE`][Position::NONE] and must be set afterwards.
+    #[cfg(any(not(feature = "no_index"), not(feature = "no_object")))]
+    fn eval_dot_index_chain_link(
+        &self,
+        mods: &mut Imports,
+        state: &mut State,
+        lib: &[&Module],
+        _this_ptr: &mut Option<&mut Dynamic>,
+        target: &mut Target,
+        rhs: &Expr,
+        idx_values: &mut StaticVec<ChainArgument>,
+        chain_type: ChainType,
+        level: usize,
+        new_val: Option<((Dynamic, Position), (Option<OpAssignment>, Position))>,
     ) -> Result<(Dynamic, bool), Box<EvalAltResult>> {
         assert!(chain_type != ChainType::NonChaining);
 
         let is_ref = target.is_ref();
 
+        #[cfg(not(feature = "no_object"))]
         let next_chain = match rhs {
             Expr::Index(_, _) => ChainType::Index,
             Expr::Dot(_, _) => ChainType::Dot,
@@ -1089,21 +1597,6 @@ impl Engine {
                 let pos = rhs.position();
 
                 match rhs {
-                    // xxx[idx].expr... | xxx[idx][expr]...
-                    Expr::Dot(x, x_pos) | Expr::Index(x, x_pos) => {
-                        let idx_pos = x.lhs.position();
-                        let idx_val = idx_val.as_index_value();
-                        let obj_ptr = &mut self.get_indexed_mut(
-                            mods, state, lib, target_val, idx_val, idx_pos, false, is_ref, true,
-                            level,
-                        )?;
-
-                        self.eval_dot_index_chain_helper(
-                            mods, state, lib, this_ptr, obj_ptr, &x.rhs, idx_values, next_chain,
-                            level, new_val,
-                        )
-                        .map_err(|err| err.fill_position(*x_pos))
-                    }
                     // xxx[rhs] op= new_val
                     _ if new_val.is_some() => {
                         let idx_val = idx_val.as_index_value();
@@ -1252,18 +1745,14 @@ impl Engine {
                         )
                         .map(|(v, _)| (v, false))
                     }
-                    // {xxx:map}.sub_lhs[expr] | {xxx:map}.sub_lhs.expr
+                    // {xxx:map}.fn_name(arg_expr_list)[expr] | {xxx:map}.fn_name(arg_expr_list).expr
                     Expr::Index(x, x_pos) | Expr::Dot(x, x_pos) if target_val.is::<Map>() => {
                         let mut val = match &x.lhs {
-                            Expr::Property(p) => {
-                                let Ident { name, pos, .. } = &p.2;
-                                let index = name.into();
-                                self.get_indexed_mut(
-                                    mods, state, lib, target_val, index, *pos, false, is_ref, true,
-                                    level,
-                                )?
+                            Expr::Property(_) => {
+                                unreachable!(
+                                    "property of object map should be followed by reference"
+                                )
                             }
-                            // {xxx:map}.fn_name(arg_expr_list)[expr] | {xxx:map}.fn_name(arg_expr_list).expr
                             Expr::FnCall(x, pos) if x.namespace.is_none() => {
                                 let FnCallExpr { name, hash, .. } = x.as_ref();
                                 let mut args = idx_val.as_fn_call_args();
@@ -1281,7 +1770,7 @@ impl Engine {
                         };
 
                         self.eval_dot_index_chain_helper(
-                            mods, state, lib, this_ptr, &mut val, &x.rhs, idx_values, next_chain,
+                            mods, state, lib, _this_ptr, &mut val, &x.rhs, idx_values, next_chain,
                             level, new_val,
                         )
                         .map_err(|err| err.fill_position(*x_pos))
@@ -1309,7 +1798,7 @@ impl Engine {
                                         mods,
                                         state,
                                         lib,
-                                        this_ptr,
+                                        _this_ptr,
                                         &mut val.into(),
                                         &x.rhs,
                                         idx_values,
@@ -1352,7 +1841,7 @@ impl Engine {
                                 let target = &mut val.into();
 
                                 self.eval_dot_index_chain_helper(
-                                    mods, state, lib, this_ptr, target, &x.rhs, idx_values,
+                                    mods, state, lib, _this_ptr, target, &x.rhs, idx_values,
                                     next_chain, level, new_val,
                                 )
                                 .map_err(|err| err.fill_position(*pos))
@@ -1396,7 +1885,7 @@ impl Engine {
         let idx_values = &mut Default::default();
 
         self.eval_indexed_chain(
-            scope, mods, state, lib, this_ptr, rhs, chain_type, idx_values, 0, level,
+            scope, mods, state, lib, this_ptr, rhs, chain_type, idx_values, level,
         )?;
 
         match lhs {
@@ -1457,99 +1946,115 @@ impl Engine {
         expr: &Expr,
         parent_chain_type: ChainType,
         idx_values: &mut StaticVec<ChainArgument>,
-        size: usize,
         level: usize,
     ) -> Result<(), Box<EvalAltResult>> {
-        self.inc_operations(state, expr.position())?;
+        let mut expr = expr;
+        let mut parent_chain_type = parent_chain_type;
+        // Values of the links in the chain, in left-to-right order
+        let mut lhs_values: StaticVec<ChainArgument> = Default::default();
 
-        match expr {
-            Expr::FnCall(x, _) if parent_chain_type == ChainType::Dot && x.namespace.is_none() => {
-                let mut arg_positions: StaticVec<_> = Default::default();
+        loop {
+            self.inc_operations(state, expr.position())?;
 
-                let mut arg_values = x
-                    .args
-                    .iter()
-                    .inspect(|arg_expr| arg_positions.push(arg_expr.position()))
-                    .map(|arg_expr| {
-                        self.eval_expr(scope, mods, state, lib, this_ptr, arg_expr, level)
-                            .map(Dynamic::flatten)
-                    })
-                    .collect::<Result<StaticVec<_>, _>>()?;
+            match expr {
+                Expr::FnCall(x, _)
+                    if parent_chain_type == ChainType::Dot && x.namespace.is_none() =>
+                {
+                    let mut arg_positions: StaticVec<_> = Default::default();
+
+                    let mut arg_values = x
+                        .args
+                        .iter()
+                        .inspect(|arg_expr| arg_positions.push(arg_expr.position()))
+                        .map(|arg_expr| {
+                            self.eval_expr(scope, mods, state, lib, this_ptr, arg_expr, level)
+                                .map(Dynamic::flatten)
+                        })
+                        .collect::<Result<StaticVec<_>, _>>()?;
 
-                x.constant_args
-                    .iter()
-                    .inspect(|(_, pos)| arg_positions.push(*pos))
-                    .for_each(|(v, _)| arg_values.push(v.clone()));
+                    x.constant_args
+                        .iter()
+                        .inspect(|(_, pos)| arg_positions.push(*pos))
+                        .for_each(|(v, _)| arg_values.push(v.clone()));
 
-                idx_values.push((arg_values, arg_positions).into());
-            }
-            Expr::FnCall(_, _) if parent_chain_type == ChainType::Dot => {
-                unreachable!("function call in dot chain should not be namespace-qualified")
-            }
+                    idx_values.push((arg_values, arg_positions).into());
+                }
+                Expr::FnCall(_, _) if parent_chain_type == ChainType::Dot => {
+                    unreachable!("function call in dot chain should not be namespace-qualified")
+                }
 
-            Expr::Property(x) if parent_chain_type == ChainType::Dot => {
-                idx_values.push(ChainArgument::Property(x.2.pos))
-            }
-            Expr::Property(_) => unreachable!("unexpected Expr::Property for indexing"),
+                Expr::Property(x) if parent_chain_type == ChainType::Dot => {
+                    idx_values.push(ChainArgument::Property(x.2.pos))
+                }
+                Expr::Property(_) => unreachable!("unexpected Expr::Property for indexing"),
 
-            Expr::Index(x, _) | Expr::Dot(x, _) => {
-                let crate::ast::BinaryExpr { lhs, rhs, .. } = x.as_ref();
+                Expr::Index(x, _) | Expr::Dot(x, _) => {
+                    let crate::ast::BinaryExpr { lhs, rhs, .. } = x.as_ref();
 
-                // Evaluate in left-to-right order
-                let lhs_val = match lhs {
-                    Expr::Property(x) if parent_chain_type == ChainType::Dot => {
-                        ChainArgument::Property(x.2.pos)
-                    }
-                    Expr::Property(_) => unreachable!("unexpected Expr::Property for indexing"),
-                    Expr::FnCall(x, _)
-                        if parent_chain_type == ChainType::Dot && x.namespace.is_none() =>
-                    {
-                        let mut arg_positions: StaticVec<_> = Default::default();
-
-                        let mut arg_values = x
-                            .args
-                            .iter()
-                            .inspect(|arg_expr| arg_positions.push(arg_expr.position()))
-                            .map(|arg_expr| {
-                                self.eval_expr(scope, mods, state, lib, this_ptr, arg_expr, level)
+                    // Evaluate in left-to-right order
+                    let lhs_val = match lhs {
+                        Expr::Property(x) if parent_chain_type == ChainType::Dot => {
+                            ChainArgument::Property(x.2.pos)
+                        }
+                        Expr::Property(_) => unreachable!("unexpected Expr::Property for indexing"),
+                        Expr::FnCall(x, _)
+                            if parent_chain_type == ChainType::Dot && x.namespace.is_none() =>
+                        {
+                            let mut arg_positions: StaticVec<_> = Default::default();
+
+                            let mut arg_values = x
+                                .args
+                                .iter()
+                                .inspect(|arg_expr| arg_positions.push(arg_expr.position()))
+                                .map(|arg_expr| {
+                                    self.eval_expr(
+                                        scope, mods, state, lib, this_ptr, arg_expr, level,
+                                    )
                                     .map(Dynamic::flatten)
-                            })
-                            .collect::<Result<StaticVec<_>, _>>()?;
+                                })
+                                .collect::<Result<StaticVec<_>, _>>()?;
 
-                        x.constant_args
-                            .iter()
-                            .inspect(|(_, pos)| arg_positions.push(*pos))
-                            .for_each(|(v, _)| arg_values.push(v.clone()));
+                            x.constant_args
+                                .iter()
+                                .inspect(|(_, pos)| arg_positions.push(*pos))
+                                .for_each(|(v, _)| arg_values.push(v.clone()));
 
-                        (arg_values, arg_positions).into()
-                    }
-                    Expr::FnCall(_, _) if parent_chain_type == ChainType::Dot => {
-                        unreachable!("function call in dot chain should not be namespace-qualified")
-                    }
-                    _ => self
-                        .eval_expr(scope, mods, state, lib, this_ptr, lhs, level)
-                        .map(|v| (v.flatten(), lhs.position()).into())?,
-                };
+                            (arg_values, arg_positions).into()
+                        }
+                        Expr::FnCall(_, _) if parent_chain_type == ChainType::Dot => {
+                            unreachable!(
+                                "function call in dot chain should not be namespace-qualified"
+                            )
+                        }
+                        _ => self
+                            .eval_expr(scope, mods, state, lib, this_ptr, lhs, level)
+                            .map(|v| (v.flatten(), lhs.position()).into())?,
+                    };
 
-                // Push in reverse order
-                let chain_type = match expr {
-                    Expr::Index(_, _) => ChainType::Index,
-                    Expr::Dot(_, _) => ChainType::Dot,
-                    _ => unreachable!("index or dot chain expected, but gets {:?}", expr),
-                };
-                self.eval_indexed_chain(
-                    scope, mods, state, lib, this_ptr, rhs, chain_type, idx_values, size, level,
-                )?;
+                    lhs_values.push(lhs_val);
+
+                    // Continue with the rest of the chain
+                    parent_chain_type = match expr {
+                        Expr::Index(_, _) => ChainType::Index,
+                        Expr::Dot(_, _) => ChainType::Dot,
+                        _ => unreachable!("index or dot chain expected, but gets {:?}", expr),
+                    };
+                    expr = rhs;
+                    continue;
+                }
 
-                idx_values.push(lhs_val);
+                _ => idx_values.push(
+                    self.eval_expr(scope, mods, state, lib, this_ptr, expr, level)
+                        .map(|v| (v.flatten(), expr.position()).into())?,
+                ),
             }
 
-            _ => idx_values.push(
-                self.eval_expr(scope, mods, state, lib, this_ptr, expr, level)
-                    .map(|v| (v.flatten(), expr.position()).into())?,
-            ),
+            break;
         }
 
+        // Push in reverse order
+        idx_values.extend(lhs_values.into_iter().rev());
+
         Ok(())
     }
 
@@ -1668,6 +2173,12 @@ impl Engine {
         expr: &Expr,
         level: usize,
     ) -> RhaiResult {
+        // Function calls, logic operators, statement blocks and data literals may nest deeply,
+        // so they are evaluated on a work stack instead of recursively
+        if is_stacked_expr(expr) {
+            return self.eval_stacked_expr(scope, mods, state, lib, this_ptr, expr, level);
+        }
+
         self.inc_operations(state, expr.position())?;
 
         let result = match expr {
@@ -1687,13 +2198,6 @@ impl Engine {
                 .search_namespace(scope, mods, state, lib, this_ptr, expr)
                 .map(|(val, _)| val.take_or_clone()),
 
-            // Statement block
-            Expr::Stmt(x) if x.is_empty() => Ok(Dynamic::UNIT),
-            Expr::Stmt(x) => {
-                let statements = &x.statements;
-                self.eval_stmt_block(scope, mods, state, lib, this_ptr, statements, true, level)
-            }
-
             // lhs[idx_expr]
             #[cfg(not(feature = "no_index"))]
             Expr::Index(_, _) => {
@@ -1734,30 +2238,7 @@ impl Engine {
                 Ok(result)
             }
 
-            #[cfg(not(feature = "no_index"))]
-            Expr::Array(x, _) => {
-                let mut arr = Array::with_capacity(x.len());
-                for item in x.as_ref() {
-                    arr.push(
-                        self.eval_expr(scope, mods, state, lib, this_ptr, item, level)?
-                            .flatten(),
-                    );
-                }
-                Ok(arr.into())
-            }
-
-            #[cfg(not(feature = "no_object"))]
-            Expr::Map(x, _) => {
-                let mut map = x.1.clone();
-                for (Ident { name: key, .. }, expr) in &x.0 {
-                    *map.get_mut(key.as_str()).unwrap() = self
-                        .eval_expr(scope, mods, state, lib, this_ptr, expr, level)?
-                        .flatten();
-                }
-                Ok(map.into())
-            }
-
-            // Normal function call
+            // Function call needing special handling - e.g. `call`, `eval`, capturing the scope
             Expr::FnCall(x, pos) if x.namespace.is_none() => {
                 let FnCallExpr {
                     name,
@@ -1767,10 +2248,23 @@ impl Engine {
                     constant_args: c_args,
                     ..
                 } = x.as_ref();
-                self.make_function_call(
+                let result = self.make_function_call(
                     scope, mods, state, lib, this_ptr, name, args, c_args, *hash, *pos, *capture,
                     level,
-                )
+                );
+
+                match x.source_text {
+                    // Fill in the source text of a failed assertion
+                    Some(ref text) => result.map_err(|mut err| {
+                        if let EvalAltResult::ErrorAssertion(_, _, ref mut expr, _) = *err {
+                            if expr.is_empty() {
+                                *expr = text.to_string();
+                            }
+                        }
+                        err
+                    }),
+                    None => result,
+                }
             }
 
             // Namespace-qualified function call
@@ -1791,32 +2285,6 @@ impl Engine {
                 )
             }
 
-            Expr::And(x, _) => {
-                Ok((self
-                    .eval_expr(scope, mods, state, lib, this_ptr, &x.lhs, level)?
-                    .as_bool()
-                    .map_err(|err| self.make_type_mismatch_err::<bool>(err, x.lhs.position()))?
-                    && // Short-circuit using &&
-                self
-                    .eval_expr(scope, mods, state, lib, this_ptr, &x.rhs, level)?
-                    .as_bool()
-                    .map_err(|err| self.make_type_mismatch_err::<bool>(err, x.rhs.position()))?)
-                .into())
-            }
-
-            Expr::Or(x, _) => {
-                Ok((self
-                    .eval_expr(scope, mods, state, lib, this_ptr, &x.lhs, level)?
-                    .as_bool()
-                    .map_err(|err| self.make_type_mismatch_err::<bool>(err, x.lhs.position()))?
-                    || // Short-circuit using ||
-                self
-                    .eval_expr(scope, mods, state, lib, this_ptr, &x.rhs, level)?
-                    .as_bool()
-                    .map_err(|err| self.make_type_mismatch_err::<bool>(err, x.rhs.position()))?)
-                .into())
-            }
-
             Expr::BoolConstant(x, _) => Ok((*x).into()),
             Expr::Unit(_) => Ok(Dynamic::UNIT),
 
@@ -1848,83 +2316,7 @@ impl Engine {
         self.check_data_size(result, expr.position())
     }
 
-    /// Evaluate a statements block.
-    pub(crate) fn eval_stmt_block(
-        &self,
-        scope: &mut Scope,
-        mods: &mut Imports,
-        state: &mut State,
-        lib: &[&Module],
-        this_ptr: &mut Option<&mut Dynamic>,
-        statements: &[Stmt],
-        restore_prev_state: bool,
-        level: usize,
-    ) -> RhaiResult {
-        if statements.is_empty() {
-            return Ok(Dynamic::UNIT);
-        }
-
-        let mut _extra_fn_resolution_cache = false;
-        let prev_always_search = state.always_search;
-        let prev_scope_len = scope.len();
-        let prev_mods_len = mods.len();
-
-        if restore_prev_state {
-            state.scope_level += 1;
-        }
-
-        let result = statements.iter().try_fold(Dynamic::UNIT, |_, stmt| {
-            let _mods_len = mods.len();
-
-            let r = self.eval_stmt(scope, mods, state, lib, this_ptr, stmt, level)?;
-
-            #[cfg(not(feature = "no_module"))]
-            if matches!(stmt, Stmt::Import(_, _, _)) {
-                // Get the extra modules - see if any functions are marked global.
-                // Without global functions, the extra modules never affect function resolution.
-                if mods
-                    .scan_raw()
-                    .skip(_mods_len)
-                    .any(|(_, m)| m.contains_indexed_global_functions())
-                {
-                    if _extra_fn_resolution_cache {
-                        // When new module is imported with global functions and there is already
-                        // a new cache, clear it - notice that this is expensive as all function
-                        // resolutions must start again
-                        state.fn_resolution_cache_mut().clear();
-                    } else if restore_prev_state {
-                        // When new module is imported with global functions, push a new cache
-                        state.push_fn_resolution_cache();
-                        _extra_fn_resolution_cache = true;
-                    } else {
-                        // When the block is to be evaluated in-place, just clear the current cache
-                        state.fn_resolution_cache_mut().clear();
-                    }
-                }
-            }
-
-            Ok(r)
-        });
-
-        if _extra_fn_resolution_cache {
-            // If imports list is modified, pop the functions lookup cache
-            state.pop_fn_resolution_cache();
-        }
-
-        if restore_prev_state {
-            scope.rewind(prev_scope_len);
-            mods.truncate(prev_mods_len);
-            state.scope_level -= 1;
-
-            // The impact of new local variables goes away at the end of a block
-            // because any new variables introduced will go out of scope
-            state.always_search = prev_always_search;
-        }
-
-        result
-    }
-
-    pub(crate) fn eval_op_assignment(
+    pub(crate) fn eval_op_assignment(
         &self,
         mods: &mut Imports,
         state: &mut State,
@@ -1998,12 +2390,37 @@ impl Engine {
         stmt: &Stmt,
         level: usize,
     ) -> RhaiResult {
+        let in_tail = mem::take(&mut state.in_tail);
+
         self.inc_operations(state, stmt.position())?;
 
+        if let Some(ref coverage) = self.coverage {
+            coverage.hit_statement(state.coverage_source(), stmt.position());
+        }
+
         let result = match stmt {
             // No-op
             Stmt::Noop(_) => Ok(Dynamic::UNIT),
 
+            // Function call in tail position
+            #[cfg(not(feature = "no_function"))]
+            Stmt::Expr(expr @ Expr::FnCall(_, _)) if in_tail => self
+                .make_tail_call(
+                    scope,
+                    mods,
+                    state,
+                    lib,
+                    this_ptr,
+                    expr,
+                    expr.position(),
+                    level,
+                )
+                .unwrap_or_else(|| {
+                    Ok(self
+                        .eval_expr(scope, mods, state, lib, this_ptr, expr, level)?
+                        .flatten())
+                }),
+
             // Expression as statement
             Stmt::Expr(expr) => Ok(self
                 .eval_expr(scope, mods, state, lib, this_ptr, expr, level)?
@@ -2087,11 +2504,12 @@ impl Engine {
             // Block scope
             Stmt::Block(statements, _) if statements.is_empty() => Ok(Dynamic::UNIT),
             Stmt::Block(statements, _) => {
+                state.in_tail = in_tail;
                 self.eval_stmt_block(scope, mods, state, lib, this_ptr, statements, true, level)
             }
 
             // If statement
-            Stmt::If(expr, x, _) => {
+            Stmt::If(expr, x, pos) => {
                 let (
                     StmtBlock {
                         statements: if_stmt,
@@ -2106,8 +2524,14 @@ impl Engine {
                     .as_bool()
                     .map_err(|err| self.make_type_mismatch_err::<bool>(err, expr.position()))
                     .and_then(|guard_val| {
+                        if let Some(ref coverage) = self.coverage {
+                            let source = state.coverage_source();
+                            coverage.hit_branch(source, *pos, !guard_val as usize);
+                        }
+
                         if guard_val {
                             if !if_stmt.is_empty() {
+                                state.in_tail = in_tail;
                                 self.eval_stmt_block(
                                     scope, mods, state, lib, this_ptr, if_stmt, true, level,
                                 )
@@ -2116,6 +2540,7 @@ impl Engine {
                             }
                         } else {
                             if !else_stmt.is_empty() {
+                                state.in_tail = in_tail;
                                 self.eval_stmt_block(
                                     scope, mods, state, lib, this_ptr, else_stmt, true, level,
                                 )
@@ -2127,43 +2552,9 @@ impl Engine {
             }
 
             // Switch statement
-            Stmt::Switch(match_expr, x, _) => {
-                let (table, def_stmt) = x.as_ref();
-
-                let value = self.eval_expr(scope, mods, state, lib, this_ptr, match_expr, level)?;
-
-                if value.is_hashable() {
-                    let hasher = &mut get_hasher();
-                    value.hash(hasher);
-                    let hash = hasher.finish();
-
-                    table.get(&hash).map(|t| {
-                        let statements = &t.statements;
-
-                        if !statements.is_empty() {
-                            self.eval_stmt_block(
-                                scope, mods, state, lib, this_ptr, statements, true, level,
-                            )
-                        } else {
-                            Ok(Dynamic::UNIT)
-                        }
-                    })
-                } else {
-                    // Non-hashable values never match any specific clause
-                    None
-                }
-                .unwrap_or_else(|| {
-                    // Default match clause
-                    let def_stmt = &def_stmt.statements;
-                    if !def_stmt.is_empty() {
-                        self.eval_stmt_block(
-                            scope, mods, state, lib, this_ptr, def_stmt, true, level,
-                        )
-                    } else {
-                        Ok(Dynamic::UNIT)
-                    }
-                })
-            }
+            Stmt::Switch(match_expr, x, pos) => self.eval_stmt_switch(
+                scope, mods, state, lib, this_ptr, match_expr, x, *pos, in_tail, level,
+            ),
 
             // While loop
             Stmt::While(expr, body, _) => {
@@ -2234,79 +2625,7 @@ impl Engine {
 
             // For loop
             Stmt::For(expr, x, _) => {
-                let (Ident { name, .. }, StmtBlock { statements, pos }) = x.as_ref();
-                let iter_obj = self
-                    .eval_expr(scope, mods, state, lib, this_ptr, expr, level)?
-                    .flatten();
-                let iter_type = iter_obj.type_id();
-
-                // lib should only contain scripts, so technically they cannot have iterators
-
-                // Search order:
-                // 1) Global namespace - functions registered via Engine::register_XXX
-                // 2) Global modules - packages
-                // 3) Imported modules - functions marked with global namespace
-                // 4) Global sub-modules - functions marked with global namespace
-                let func = self
-                    .global_namespace
-                    .get_iter(iter_type)
-                    .or_else(|| {
-                        self.global_modules
-                            .iter()
-                            .find_map(|m| m.get_iter(iter_type))
-                    })
-                    .or_else(|| mods.get_iter(iter_type))
-                    .or_else(|| {
-                        self.global_sub_modules
-                            .values()
-                            .find_map(|m| m.get_qualified_iter(iter_type))
-                    });
-
-                if let Some(func) = func {
-                    // Add the loop variable
-                    let var_name: Cow<'_, str> = if state.is_global() {
-                        name.to_string().into()
-                    } else {
-                        unsafe_cast_var_name_to_lifetime(name).into()
-                    };
-                    scope.push(var_name, ());
-                    let index = scope.len() - 1;
-                    state.scope_level += 1;
-
-                    for iter_value in func(iter_obj) {
-                        let loop_var = scope.get_mut_by_index(index);
-                        let value = iter_value.flatten();
-
-                        if cfg!(not(feature = "no_closure")) && loop_var.is_shared() {
-                            *loop_var.write_lock().unwrap() = value;
-                        } else {
-                            *loop_var = value;
-                        }
-
-                        self.inc_operations(state, *pos)?;
-
-                        if statements.is_empty() {
-                            continue;
-                        }
-
-                        match self.eval_stmt_block(
-                            scope, mods, state, lib, this_ptr, statements, true, level,
-                        ) {
-                            Ok(_) => (),
-                            Err(err) => match *err {
-                                EvalAltResult::LoopBreak(false, _) => (),
-                                EvalAltResult::LoopBreak(true, _) => break,
-                                _ => return Err(err),
-                            },
-                        }
-                    }
-
-                    state.scope_level -= 1;
-                    scope.rewind(scope.len() - 1);
-                    Ok(Dynamic::UNIT)
-                } else {
-                    EvalAltResult::ErrorFor(expr.position()).into()
-                }
+                self.eval_stmt_for(scope, mods, state, lib, this_ptr, expr, x, level)
             }
 
             // Continue statement
@@ -2316,101 +2635,21 @@ impl Engine {
             Stmt::Break(pos) => EvalAltResult::LoopBreak(true, *pos).into(),
 
             // Try/Catch statement
-            Stmt::TryCatch(x, _, _) => {
-                let (
-                    StmtBlock {
-                        statements: try_body,
-                        ..
-                    },
-                    err_var,
-                    StmtBlock {
-                        statements: catch_body,
-                        ..
-                    },
-                ) = x.as_ref();
-
-                let result = self
-                    .eval_stmt_block(scope, mods, state, lib, this_ptr, try_body, true, level)
-                    .map(|_| Dynamic::UNIT);
-
-                match result {
-                    Ok(_) => result,
-                    Err(err) if err.is_pseudo_error() => Err(err),
-                    Err(err) if !err.is_catchable() => Err(err),
-                    Err(mut err) => {
-                        let err_value = match *err {
-                            EvalAltResult::ErrorRuntime(ref x, _) => x.clone(),
-
-                            #[cfg(feature = "no_object")]
-                            _ => {
-                                err.take_position();
-                                err.to_string().into()
-                            }
-                            #[cfg(not(feature = "no_object"))]
-                            _ => {
-                                use crate::INT;
-
-                                let mut err_map: Map = Default::default();
-                                let err_pos = err.take_position();
-
-                                err_map.insert("message".into(), err.to_string().into());
-
-                                if let Some(ref source) = state.source {
-                                    err_map.insert("source".into(), source.into());
-                                }
-
-                                if err_pos.is_none() {
-                                    // No position info
-                                } else {
-                                    err_map.insert(
-                                        "line".into(),
-                                        (err_pos.line().unwrap() as INT).into(),
-                                    );
-                                    err_map.insert(
-                                        "position".into(),
-                                        if err_pos.is_beginning_of_line() {
-                                            0
-                                        } else {
-                                            err_pos.position().unwrap() as INT
-                                        }
-                                        .into(),
-                                    );
-                                }
-
-                                err.dump_fields(&mut err_map);
-                                err_map.into()
-                            }
-                        };
-
-                        let orig_scope_len = scope.len();
-                        state.scope_level += 1;
-
-                        if let Some(Ident { name, .. }) = err_var {
-                            scope.push(unsafe_cast_var_name_to_lifetime(&name), err_value);
-                        }
-
-                        let result = self.eval_stmt_block(
-                            scope, mods, state, lib, this_ptr, catch_body, true, level,
-                        );
-
-                        state.scope_level -= 1;
-                        scope.rewind(orig_scope_len);
-
-                        match result {
-                            Ok(_) => Ok(Dynamic::UNIT),
-                            Err(result_err) => match *result_err {
-                                // Re-throw exception
-                                EvalAltResult::ErrorRuntime(Dynamic(Union::Unit(_, _)), pos) => {
-                                    err.set_position(pos);
-                                    Err(err)
-                                }
-                                _ => Err(result_err),
-                            },
-                        }
-                    }
-                }
+            Stmt::TryCatch(x, try_pos, _) => {
+                self.eval_stmt_try_catch(scope, mods, state, lib, this_ptr, x, *try_pos, level)
             }
 
+            // Return function call
+            #[cfg(not(feature = "no_function"))]
+            Stmt::Return(ReturnType::Return, Some(expr @ Expr::FnCall(_, _)), pos) => self
+                .make_tail_call(scope, mods, state, lib, this_ptr, expr, *pos, level)
+                .unwrap_or_else(|| {
+                    let value = self
+                        .eval_expr(scope, mods, state, lib, this_ptr, expr, level)?
+                        .flatten();
+                    EvalAltResult::Return(value, *pos).into()
+                }),
+
             // Return value
             Stmt::Return(ReturnType::Return, Some(expr), pos) => {
                 let value = self
@@ -2438,87 +2677,35 @@ impl Engine {
             }
 
             // Let/const statement
-            Stmt::Let(expr, x, export, _) | Stmt::Const(expr, x, export, _) => {
-                let name = &x.name;
-                let entry_type = match stmt {
-                    Stmt::Let(_, _, _, _) => AccessMode::ReadWrite,
-                    Stmt::Const(_, _, _, _) => AccessMode::ReadOnly,
-                    _ => unreachable!("should be Stmt::Let or Stmt::Const, but gets {:?}", stmt),
-                };
-
-                let value = self
-                    .eval_expr(scope, mods, state, lib, this_ptr, expr, level)?
-                    .flatten();
-
-                let (var_name, _alias): (Cow<'_, str>, _) = if state.is_global() {
-                    (
-                        name.to_string().into(),
-                        if *export { Some(name.clone()) } else { None },
-                    )
-                } else if *export {
-                    unreachable!("exported variable not on global level");
-                } else {
-                    (unsafe_cast_var_name_to_lifetime(name).into(), None)
-                };
-
-                scope.push_dynamic_value(var_name, entry_type, value);
-
-                #[cfg(not(feature = "no_module"))]
-                if let Some(alias) = _alias {
-                    scope.add_entry_alias(scope.len() - 1, alias);
-                }
-                Ok(Dynamic::UNIT)
-            }
+            Stmt::Let(expr, x, export, _) => self.eval_stmt_let(
+                scope,
+                mods,
+                state,
+                lib,
+                this_ptr,
+                expr,
+                x,
+                AccessMode::ReadWrite,
+                *export,
+                level,
+            ),
+            Stmt::Const(expr, x, export, _) => self.eval_stmt_let(
+                scope,
+                mods,
+                state,
+                lib,
+                this_ptr,
+                expr,
+                x,
+                AccessMode::ReadOnly,
+                *export,
+                level,
+            ),
 
             // Import statement
             #[cfg(not(feature = "no_module"))]
-            Stmt::Import(expr, export, _pos) => {
-                // Guard against too many modules
-                #[cfg(not(feature = "unchecked"))]
-                if state.modules >= self.max_modules() {
-                    return EvalAltResult::ErrorTooManyModules(*_pos).into();
-                }
-
-                if let Some(path) = self
-                    .eval_expr(scope, mods, state, lib, this_ptr, &expr, level)?
-                    .try_cast::<ImmutableString>()
-                {
-                    use crate::ModuleResolver;
-
-                    let source = state.source.as_ref().map(|s| s.as_str());
-                    let expr_pos = expr.position();
-
-                    let module = state
-                        .resolver
-                        .as_ref()
-                        .and_then(|r| match r.resolve(self, source, &path, expr_pos) {
-                            Ok(m) => return Some(Ok(m)),
-                            Err(err) => match *err {
-                                EvalAltResult::ErrorModuleNotFound(_, _) => None,
-                                _ => return Some(Err(err)),
-                            },
-                        })
-                        .unwrap_or_else(|| {
-                            self.module_resolver.resolve(self, source, &path, expr_pos)
-                        })?;
-
-                    if let Some(name) = export.as_ref().map(|x| x.name.clone()) {
-                        if !module.is_indexed() {
-                            // Index the module (making a clone copy if necessary) if it is not indexed
-                            let mut module = crate::fn_native::shared_take_or_clone(module);
-                            module.build_index();
-                            mods.push(name, module);
-                        } else {
-                            mods.push(name, module);
-                        }
-                    }
-
-                    state.modules += 1;
-
-                    Ok(Dynamic::UNIT)
-                } else {
-                    Err(self.make_type_mismatch_err::<ImmutableString>("", expr.position()))
-                }
+            Stmt::Import(expr, export, pos) => {
+                self.eval_stmt_import(scope, mods, state, lib, this_ptr, expr, export, *pos, level)
             }
 
             // Export statement
@@ -2554,18 +2741,410 @@ impl Engine {
         self.check_data_size(result, stmt.position())
     }
 
+    /// Evaluate a `switch` statement.
+    fn eval_stmt_switch(
+        &self,
+        scope: &mut Scope,
+        mods: &mut Imports,
+        state: &mut State,
+        lib: &[&Module],
+        this_ptr: &mut Option<&mut Dynamic>,
+        match_expr: &Expr,
+        x: &(BTreeMap<u64, Box<StmtBlock>>, StmtBlock),
+        pos: Position,
+        in_tail: bool,
+        level: usize,
+    ) -> RhaiResult {
+        let (table, def_stmt) = x;
+
+        let value = self.eval_expr(scope, mods, state, lib, this_ptr, match_expr, level)?;
+
+        let hash = if value.is_hashable() {
+            let hasher = &mut get_hasher();
+            value.hash(hasher);
+            Some(hasher.finish())
+        } else {
+            // Non-hashable values never match any specific clause
+            None
+        };
+
+        if let Some(ref coverage) = self.coverage {
+            // Branches are numbered by clause order, with the default clause last
+            let branch = match hash {
+                Some(hash) if table.contains_key(&hash) => table.range(..hash).count(),
+                _ => table.len(),
+            };
+            coverage.hit_branch(state.coverage_source(), pos, branch);
+        }
+
+        if let Some(hash) = hash {
+            table.get(&hash).map(|t| {
+                let statements = &t.statements;
+
+                if !statements.is_empty() {
+                    state.in_tail = in_tail;
+                    self.eval_stmt_block(scope, mods, state, lib, this_ptr, statements, true, level)
+                } else {
+                    Ok(Dynamic::UNIT)
+                }
+            })
+        } else {
+            // Non-hashable values never match any specific clause
+            None
+        }
+        .unwrap_or_else(|| {
+            // Default match clause
+            let def_stmt = &def_stmt.statements;
+            if !def_stmt.is_empty() {
+                state.in_tail = in_tail;
+                self.eval_stmt_block(scope, mods, state, lib, this_ptr, def_stmt, true, level)
+            } else {
+                Ok(Dynamic::UNIT)
+            }
+        })
+    }
+
+    /// Evaluate a `for` loop.
+    fn eval_stmt_for(
+        &self,
+        scope: &mut Scope,
+        mods: &mut Imports,
+        state: &mut State,
+        lib: &[&Module],
+        this_ptr: &mut Option<&mut Dynamic>,
+        expr: &Expr,
+        x: &(Ident, StmtBlock),
+        level: usize,
+    ) -> RhaiResult {
+        let (Ident { name, .. }, StmtBlock { statements, pos }) = x;
+        let iter_obj = self
+            .eval_expr(scope, mods, state, lib, this_ptr, expr, level)?
+            .flatten();
+        let iter_type = iter_obj.type_id();
+
+        // lib should only contain scripts, so technically they cannot have iterators
+
+        // Search order:
+        // 1) Global namespace - functions registered via Engine::register_XXX
+        // 2) Global modules - packages
+        // 3) Imported modules - functions marked with global namespace
+        // 4) Global sub-modules - functions marked with global namespace
+        let func = self
+            .global_namespace
+            .get_iter(iter_type)
+            .or_else(|| {
+                self.global_modules
+                    .iter()
+                    .find_map(|m| m.get_iter(iter_type))
+            })
+            .or_else(|| mods.get_iter(iter_type))
+            .or_else(|| {
+                self.global_sub_modules
+                    .values()
+                    .find_map(|m| m.get_qualified_iter(iter_type))
+            });
+
+        if let Some(func) = func {
+            // Add the loop variable
+            let var_name: Cow<'_, str> = if state.is_global() {
+                name.to_string().into()
+            } else {
+                unsafe_cast_var_name_to_lifetime(name).into()
+            };
+            scope.push(var_name, ());
+            let index = scope.len() - 1;
+            state.scope_level += 1;
+
+            for iter_value in func(iter_obj) {
+                let loop_var = scope.get_mut_by_index(index);
+                let value = iter_value.flatten();
+
+                if cfg!(not(feature = "no_closure")) && loop_var.is_shared() {
+                    *loop_var.write_lock().unwrap() = value;
+                } else {
+                    *loop_var = value;
+                }
+
+                self.inc_operations(state, *pos)?;
+
+                if statements.is_empty() {
+                    continue;
+                }
+
+                match self
+                    .eval_stmt_block(scope, mods, state, lib, this_ptr, statements, true, level)
+                {
+                    Ok(_) => (),
+                    Err(err) => match *err {
+                        EvalAltResult::LoopBreak(false, _) => (),
+                        EvalAltResult::LoopBreak(true, _) => break,
+                        _ => return Err(err),
+                    },
+                }
+            }
+
+            state.scope_level -= 1;
+            scope.rewind(scope.len() - 1);
+            Ok(Dynamic::UNIT)
+        } else {
+            EvalAltResult::ErrorFor(expr.position()).into()
+        }
+    }
+
+    /// Evaluate a `try` ... `catch` statement.
+    fn eval_stmt_try_catch(
+        &self,
+        scope: &mut Scope,
+        mods: &mut Imports,
+        state: &mut State,
+        lib: &[&Module],
+        this_ptr: &mut Option<&mut Dynamic>,
+        x: &(StmtBlock, Option<Ident>, StmtBlock),
+        try_pos: Position,
+        level: usize,
+    ) -> RhaiResult {
+        let (
+            StmtBlock {
+                statements: try_body,
+                ..
+            },
+            err_var,
+            StmtBlock {
+                statements: catch_body,
+                ..
+            },
+        ) = x;
+
+        let result = self.eval_stmt_block(scope, mods, state, lib, this_ptr, try_body, true, level);
+
+        // A call in tail position cannot leave the `try` block, so make it here
+        #[cfg(not(feature = "no_function"))]
+        let result = self.finish_tail_call(mods, state, lib, result, level);
+
+        let result = result.map(|_| Dynamic::UNIT);
+
+        if let Some(ref coverage) = self.coverage {
+            let branch = match result {
+                Err(ref err) if !err.is_pseudo_error() && err.is_catchable() => 1,
+                _ => 0,
+            };
+            coverage.hit_branch(state.coverage_source(), try_pos, branch);
+        }
+
+        match result {
+            Ok(_) => result,
+            Err(err) if err.is_pseudo_error() => Err(err),
+            Err(err) if !err.is_catchable() => Err(err),
+            Err(mut err) => {
+                let err_value = match *err {
+                    EvalAltResult::ErrorRuntime(ref x, _) => x.clone(),
+
+                    #[cfg(feature = "no_object")]
+                    _ => {
+                        err.take_position();
+                        err.to_string().into()
+                    }
+                    #[cfg(not(feature = "no_object"))]
+                    _ => {
+                        use crate::INT;
+
+                        let mut err_map: Map = Default::default();
+                        let err_pos = err.take_position();
+
+                        err_map.insert("message".into(), err.to_string().into());
+
+                        if let Some(ref source) = state.source {
+                            err_map.insert("source".into(), source.into());
+                        }
+
+                        if err_pos.is_none() {
+                            // No position info
+                        } else {
+                            err_map.insert("line".into(), (err_pos.line().unwrap() as INT).into());
+                            err_map.insert(
+                                "position".into(),
+                                if err_pos.is_beginning_of_line() {
+                                    0
+                                } else {
+                                    err_pos.position().unwrap() as INT
+                                }
+                                .into(),
+                            );
+                        }
+
+                        // Restore the position for the call stack
+                        err.set_position(err_pos);
+                        err.dump_fields(&mut err_map);
+                        err_map.into()
+                    }
+                };
+
+                let orig_scope_len = scope.len();
+                state.scope_level += 1;
+
+                if let Some(Ident { name, .. }) = err_var {
+                    scope.push(unsafe_cast_var_name_to_lifetime(&name), err_value);
+                }
+
+                let result = self
+                    .eval_stmt_block(scope, mods, state, lib, this_ptr, catch_body, true, level);
+
+                state.scope_level -= 1;
+                scope.rewind(orig_scope_len);
+
+                match result {
+                    Ok(_) => Ok(Dynamic::UNIT),
+                    Err(result_err) => match *result_err {
+                        // Re-throw exception
+                        EvalAltResult::ErrorRuntime(Dynamic(Union::Unit(_, _)), pos) => {
+                            err.set_position(pos);
+                            Err(err)
+                        }
+                        _ => Err(result_err),
+                    },
+                }
+            }
+        }
+    }
+
+    /// Evaluate a `let` or `const` statement.
+    fn eval_stmt_let(
+        &self,
+        scope: &mut Scope,
+        mods: &mut Imports,
+        state: &mut State,
+        lib: &[&Module],
+        this_ptr: &mut Option<&mut Dynamic>,
+        expr: &Expr,
+        x: &Ident,
+        entry_type: AccessMode,
+        export: bool,
+        level: usize,
+    ) -> RhaiResult {
+        let name = &x.name;
+
+        let value = self
+            .eval_expr(scope, mods, state, lib, this_ptr, expr, level)?
+            .flatten();
+
+        let (var_name, _alias): (Cow<'_, str>, _) = if state.is_global() {
+            (
+                name.to_string().into(),
+                if export { Some(name.clone()) } else { None },
+            )
+        } else if export {
+            unreachable!("exported variable not on global level");
+        } else {
+            (unsafe_cast_var_name_to_lifetime(name).into(), None)
+        };
+
+        scope.push_dynamic_value(var_name, entry_type, value);
+
+        #[cfg(not(feature = "no_module"))]
+        if let Some(alias) = _alias {
+            scope.add_entry_alias(scope.len() - 1, alias);
+        }
+        Ok(Dynamic::UNIT)
+    }
+
+    /// Evaluate an `import` statement.
+    #[cfg(not(feature = "no_module"))]
+    fn eval_stmt_import(
+        &self,
+        scope: &mut Scope,
+        mods: &mut Imports,
+        state: &mut State,
+        lib: &[&Module],
+        this_ptr: &mut Option<&mut Dynamic>,
+        expr: &Expr,
+        export: &Option<Box<Ident>>,
+        _pos: Position,
+        level: usize,
+    ) -> RhaiResult {
+        // Guard against computed import paths
+        if !self.computed_imports && !matches!(expr, Expr::StringConstant(_, _)) {
+            return EvalAltResult::ErrorComputedImportPath(expr.position()).into();
+        }
+
+        // Guard against too many modules
+        #[cfg(not(feature = "unchecked"))]
+        if state.modules >= self.max_modules() {
+            return EvalAltResult::ErrorTooManyModules(_pos).into();
+        }
+
+        if let Some(path) = self
+            .eval_expr(scope, mods, state, lib, this_ptr, &expr, level)?
+            .try_cast::<ImmutableString>()
+        {
+            let expr_pos = expr.position();
+            let name = export.as_ref().map(|x| x.name.clone());
+
+            // Modules imported inside functions are cached in the AST
+            let cache = if state.in_function {
+                state.module_cache.clone()
+            } else {
+                None
+            };
+
+            if let Some(module) = cache
+                .as_ref()
+                .and_then(|c| c.get(&self.module_resolver_id, &state.source, &path))
+            {
+                if let Some(name) = name {
+                    mods.push(name, module);
+                }
+                state.modules += 1;
+
+                return Ok(Dynamic::UNIT);
+            }
+
+            // Defer resolving the module until it is accessed
+            if let (true, Some(name)) = (self.lazy_imports, name.as_ref()) {
+                let module = LazyModule::new(path, state.source.clone(), expr_pos);
+                mods.push_lazy(name.clone(), module);
+                state.modules += 1;
+
+                return Ok(Dynamic::UNIT);
+            }
+
+            let source = state.source.as_ref().map(|s| s.as_str());
+            let mut module = self.resolve_module(state, source, &path, expr_pos)?;
+
+            if name.is_some() || cache.is_some() {
+                module = index_module(module);
+            }
+            if let Some(cache) = cache {
+                cache.insert(
+                    &self.module_resolver_id,
+                    state.source.clone(),
+                    path,
+                    module.clone(),
+                );
+            }
+            if let Some(name) = name {
+                mods.push(name, module);
+            }
+
+            state.modules += 1;
+
+            Ok(Dynamic::UNIT)
+        } else {
+            Err(self.make_type_mismatch_err::<ImmutableString>("", expr.position()))
+        }
+    }
+
     /// Check a result to ensure that the data size is within allowable limit.
     /// [`Position`] in [`EvalAltResult`] may be None and should be set afterwards.
     #[cfg(feature = "unchecked")]
     #[inline(always)]
-    fn check_data_size(&self, result: RhaiResult, _pos: Position) -> RhaiResult {
+    pub(crate) fn check_data_size(&self, result: RhaiResult, _pos: Position) -> RhaiResult {
         result
     }
 
     /// Check a result to ensure that the data size is within allowable limit.
     #[cfg(not(feature = "unchecked"))]
     #[inline(always)]
-    fn check_data_size(&self, result: RhaiResult, pos: Position) -> RhaiResult {
+    pub(crate) fn check_data_size(&self, result: RhaiResult, pos: Position) -> RhaiResult {
         // Simply return all errors
         if result.is_err() {
             return result;
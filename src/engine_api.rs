@@ -11,6 +11,7 @@ use crate::stdlib::{
     boxed::Box,
     num::NonZeroUsize,
     string::String,
+    vec::Vec,
 };
 use crate::{
     scope::Scope, Dynamic, Engine, EvalAltResult, FnAccess, FnNamespace, Identifier, Module,
@@ -1168,6 +1169,7 @@ impl Engine {
             #[cfg(not(feature = "no_function"))]
             NonZeroUsize::new(self.max_function_expr_depth()),
         );
+        state.set_scripts(scripts);
         self.parse(
             &mut stream.peekable(),
             &mut state,
@@ -1175,6 +1177,86 @@ impl Engine {
             optimization_level,
         )
     }
+    /// Compile a string into an [`AST`], collecting _all_ parse errors instead of stopping at the
+    /// first one.
+    ///
+    /// When an error is encountered, the parser skips ahead to the next statement boundary
+    /// (a `;` or the `}` closing the current block) and continues parsing from there.
+    ///
+    /// Returns the [`AST`] holding all the statements and functions that parsed successfully,
+    /// together with a list of all [parse errors][ParseError] found, in order of appearance.
+    /// The script is free of syntax errors only if the list is empty.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[cfg(not(feature = "no_function"))]
+    /// # {
+    /// use rhai::Engine;
+    ///
+    /// let engine = Engine::new();
+    ///
+    /// let (ast, errors) = engine.compile_with_diagnostics(
+    ///     "
+    ///         let x = 40 +;
+    ///         let y = 2;
+    ///         let z = (y;
+    ///         fn foo() { 42 }
+    ///     ",
+    /// );
+    ///
+    /// assert_eq!(errors.len(), 2);
+    /// assert_eq!(errors[0].1.line(), Some(2));
+    /// assert_eq!(errors[1].1.line(), Some(4));
+    ///
+    /// // The AST still contains whatever parsed successfully
+    /// assert_eq!(ast.iter_functions().count(), 1);
+    /// # }
+    /// ```
+    #[inline(always)]
+    pub fn compile_with_diagnostics(&self, script: &str) -> (AST, Vec<ParseError>) {
+        self.compile_scripts_with_diagnostics(
+            &Default::default(),
+            &[script],
+            self.optimization_level,
+        )
+    }
+
+    /// Join a list of strings and compile into an [`AST`] using own scope at a specific optimization level,
+    /// collecting all parse errors.
+    pub(crate) fn compile_scripts_with_diagnostics(
+        &self,
+        scope: &Scope,
+        scripts: &[&str],
+        optimization_level: OptimizationLevel,
+    ) -> (AST, Vec<ParseError>) {
+        let (stream, buffer) = self.lex_raw(scripts, None);
+        let mut state = ParseState::new(
+            self,
+            buffer,
+            #[cfg(not(feature = "unchecked"))]
+            NonZeroUsize::new(self.max_expr_depth()),
+            #[cfg(not(feature = "unchecked"))]
+            #[cfg(not(feature = "no_function"))]
+            NonZeroUsize::new(self.max_function_expr_depth()),
+        );
+        state.set_scripts(scripts);
+        state.enable_error_recovery();
+
+        match self.parse(
+            &mut stream.peekable(),
+            &mut state,
+            scope,
+            optimization_level,
+        ) {
+            Ok(ast) => (ast, state.take_errors()),
+            Err(err) => {
+                let mut errors = state.take_errors();
+                errors.push(err);
+                (Default::default(), errors)
+            }
+        }
+    }
     /// Read the contents of a file into a string.
     #[cfg(not(feature = "no_std"))]
     #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
@@ -1466,6 +1548,7 @@ impl Engine {
             #[cfg(not(feature = "no_function"))]
             NonZeroUsize::new(self.max_function_expr_depth()),
         );
+        state.set_scripts(&scripts);
         self.parse_global_expr(&mut peekable, &mut state, scope, self.optimization_level)
     }
     /// Evaluate a script file.
@@ -1634,6 +1717,7 @@ impl Engine {
             #[cfg(not(feature = "no_function"))]
             NonZeroUsize::new(self.max_function_expr_depth()),
         );
+        state.set_scripts(&scripts);
 
         // No need to optimize a lone expression
         let ast = self.parse_global_expr(
@@ -1728,14 +1812,19 @@ impl Engine {
     ) -> RhaiResult {
         let mut state: State = Default::default();
         state.source = ast.clone_source();
+
+        if let Some(ref coverage) = self.coverage {
+            coverage.register(ast);
+        }
+
         #[cfg(not(feature = "no_module"))]
         {
             state.resolver = ast.resolver();
+            state.module_cache = Some(ast.module_cache());
         }
 
-        let statements = ast.statements();
         let lib = &[ast.lib()];
-        self.eval_global_statements(scope, mods, &mut state, statements, lib, level)
+        self.eval_global_statements(scope, mods, &mut state, ast, lib, level)
     }
     /// Evaluate a file, but throw away the result and only return error (if any).
     /// Useful for when you don't need the result, but still need to keep track of possible errors.
@@ -1789,6 +1878,7 @@ impl Engine {
             #[cfg(not(feature = "no_function"))]
             NonZeroUsize::new(self.max_function_expr_depth()),
         );
+        state.set_scripts(&scripts);
 
         let ast = self.parse(
             &mut stream.peekable(),
@@ -1816,13 +1906,18 @@ impl Engine {
         let mods = &mut Default::default();
         let mut state: State = Default::default();
         state.source = ast.clone_source();
+
+        if let Some(ref coverage) = self.coverage {
+            coverage.register(ast);
+        }
+
         #[cfg(not(feature = "no_module"))]
         {
             state.resolver = ast.resolver();
+            state.module_cache = Some(ast.module_cache());
         }
-        let statements = ast.statements();
         let lib = &[ast.lib()];
-        self.eval_global_statements(scope, mods, &mut state, statements, lib, 0)?;
+        self.eval_global_statements(scope, mods, &mut state, ast, lib, 0)?;
         Ok(())
     }
     /// Call a script function defined in an [`AST`] with multiple arguments.
@@ -1980,12 +2075,22 @@ impl Engine {
         this_ptr: &mut Option<&mut Dynamic>,
         args: &mut FnCallArgs,
     ) -> RhaiResult {
-        let state = &mut Default::default();
+        let state: &mut State = &mut Default::default();
+        state.source = ast.clone_source();
         let mods = &mut Default::default();
         let lib = &[ast.lib()];
 
+        #[cfg(not(feature = "no_module"))]
+        {
+            state.module_cache = Some(ast.module_cache());
+        }
+
+        if let Some(ref coverage) = self.coverage {
+            coverage.register(ast);
+        }
+
         if eval_ast {
-            self.eval_global_statements(scope, mods, state, ast.statements(), lib, 0)?;
+            self.eval_global_statements(scope, mods, state, ast, lib, 0)?;
         }
 
         let fn_def = ast
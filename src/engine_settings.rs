@@ -33,6 +33,61 @@ impl Engine {
     pub fn optimization_level(&self) -> crate::OptimizationLevel {
         self.optimization_level
     }
+    /// Control whether the [`Engine`] strips all calls to built-in assertion functions
+    /// (`assert`, `assert_eq`, `assert_ne` and `assert_approx`) when optimizing an
+    /// [`AST`][crate::AST].
+    ///
+    /// Assertions are only stripped if the optimization level is not
+    /// [`None`][crate::OptimizationLevel::None].
+    /// Take care that arguments to assertions are not evaluated, so any side effects in them are lost.
+    ///
+    /// Not available under `no_optimize`.
+    #[cfg(not(feature = "no_optimize"))]
+    #[inline(always)]
+    pub fn set_strip_assertions(&mut self, enable: bool) -> &mut Self {
+        self.strip_assertions = enable;
+        self
+    }
+    /// Are calls to built-in assertion functions stripped when optimizing an [`AST`][crate::AST]?
+    ///
+    /// Not available under `no_optimize`.
+    #[cfg(not(feature = "no_optimize"))]
+    #[inline(always)]
+    pub fn strip_assertions(&self) -> bool {
+        self.strip_assertions
+    }
+    /// Set a [`Profiler`][crate::Profiler] to record all function calls, or [`None`] to disable profiling.
+    ///
+    /// Calls to functions with constant arguments that the optimizer folds while compiling
+    /// a script are also recorded, so set the profiler after compiling to leave them out.
+    ///
+    /// Not available under `no_std`.
+    #[cfg(not(feature = "no_std"))]
+    #[inline(always)]
+    pub fn set_profiler(&mut self, profiler: Option<crate::Profiler>) -> &mut Self {
+        self.profiler = profiler;
+        self
+    }
+    /// The [`Profiler`][crate::Profiler] recording all function calls, if any.
+    ///
+    /// Not available under `no_std`.
+    #[cfg(not(feature = "no_std"))]
+    #[inline(always)]
+    pub fn profiler(&self) -> Option<&crate::Profiler> {
+        self.profiler.as_ref()
+    }
+    /// Set a [`Coverage`][crate::Coverage] collector to record all statements executed and
+    /// branches taken, or [`None`] to disable coverage collection.
+    #[inline(always)]
+    pub fn set_coverage(&mut self, coverage: Option<crate::Coverage>) -> &mut Self {
+        self.coverage = coverage;
+        self
+    }
+    /// The [`Coverage`][crate::Coverage] collector recording all statements executed, if any.
+    #[inline(always)]
+    pub fn coverage(&self) -> Option<&crate::Coverage> {
+        self.coverage.as_ref()
+    }
     /// Enable/disable doc-comments.
     #[inline(always)]
     pub fn enable_doc_comments(&mut self, enable: bool) -> &mut Self {
@@ -98,6 +153,11 @@ impl Engine {
     }
     /// Set the depth limits for expressions (0 for unlimited).
     ///
+    /// Operator chains, parentheses, array and object map literals, statements blocks and
+    /// index/property chains are handled without recursion, so the limits are only a policy
+    /// choice for them. Other nested shapes (e.g. `if`, loops and function call arguments) still
+    /// recurse, so the limits guard against overflowing the native stack for those.
+    ///
     /// Not available under `unchecked`.
     #[cfg(not(feature = "unchecked"))]
     #[inline(always)]
@@ -197,8 +257,61 @@ impl Engine {
         resolver: impl crate::ModuleResolver + 'static,
     ) -> &mut Self {
         self.module_resolver = Box::new(resolver);
+        self.module_resolver_id = Default::default();
         self
     }
+    /// Enable/disable lazy resolution of imported [modules][crate::Module].
+    ///
+    /// When enabled, a [module][crate::Module] imported under an alias (e.g. `import "x" as x;`) is
+    /// only resolved when one of its members is first accessed.  This allows [modules][crate::Module]
+    /// that only define functions to import each other, because they are not initialized while
+    /// being loaded.  Other cyclic imports still fail with
+    /// [`ErrorCyclicImport`][crate::EvalAltResult::ErrorCyclicImport].
+    ///
+    /// Errors in resolving a lazily-imported [module][crate::Module] are raised when it is first
+    /// accessed.  Until then, it exposes no functions to the global namespace, and it is not made
+    /// a sub-[module][crate::Module] of a [module][crate::Module] created from a script.
+    ///
+    /// Not available under `no_module`.
+    #[cfg(not(feature = "no_module"))]
+    #[inline(always)]
+    pub fn enable_lazy_imports(&mut self, enable: bool) -> &mut Self {
+        self.lazy_imports = enable;
+        self
+    }
+    /// Are imported [modules][crate::Module] resolved lazily?
+    ///
+    /// Not available under `no_module`.
+    #[cfg(not(feature = "no_module"))]
+    #[inline(always)]
+    pub fn lazy_imports(&self) -> bool {
+        self.lazy_imports
+    }
+    /// Enable/disable import paths that are computed at runtime (default enabled), e.g.
+    /// `import base + "/plugin" as p;`.
+    ///
+    /// When disabled, scripts can only import [modules][crate::Module] via string literal paths,
+    /// which can be vetted before the script runs.  Import statements with any other expression
+    /// fail to compile with
+    /// [`ParseErrorType::ComputedImportPath`][crate::ParseErrorType::ComputedImportPath].
+    /// When an [`AST`][crate::AST] compiled with computed import paths enabled is evaluated, they
+    /// fail with [`EvalAltResult::ErrorComputedImportPath`][crate::EvalAltResult::ErrorComputedImportPath].
+    ///
+    /// Not available under `no_module`.
+    #[cfg(not(feature = "no_module"))]
+    #[inline(always)]
+    pub fn enable_computed_imports(&mut self, enable: bool) -> &mut Self {
+        self.computed_imports = enable;
+        self
+    }
+    /// Are import paths computed at runtime allowed?
+    ///
+    /// Not available under `no_module`.
+    #[cfg(not(feature = "no_module"))]
+    #[inline(always)]
+    pub fn computed_imports(&self) -> bool {
+        self.computed_imports
+    }
     /// Disable a particular keyword or operator in the language.
     ///
     /// # Examples
@@ -0,0 +1,515 @@
+//! Module implementing the evaluation of nested expressions and statements blocks on an explicit
+//! work stack.
+//!
+//! Function and operator calls, `&&`/`||` chains, array and object map literals, and statements
+//! blocks are evaluated in a loop instead of by recursion, so that deeply-nested scripts (e.g.
+//! long operator chains or generated data literals) only consume heap memory and cannot overflow
+//! the native stack. The parser, the optimizer and dropping an [`AST`][crate::AST] handle these
+//! shapes without recursion as well.
+//!
+//! Other statements (e.g. `if`, loops) nested inside each other are still evaluated recursively,
+//! so they are still limited by [`Engine::set_max_expr_depths`][crate::Engine::set_max_expr_depths].
+
+use crate::ast::{Expr, FnCallExpr, Stmt};
+use crate::engine::{
+    Imports, State, KEYWORD_EVAL, KEYWORD_FN_PTR, KEYWORD_FN_PTR_CALL, KEYWORD_FN_PTR_CURRY,
+    KEYWORD_IS_DEF_VAR,
+};
+use crate::stdlib::{boxed::Box, mem, string::ToString};
+use crate::{Dynamic, Engine, EvalAltResult, Module, Position, RhaiResult, Scope, StaticVec};
+
+#[cfg(not(feature = "no_index"))]
+use crate::Array;
+
+#[cfg(not(feature = "no_object"))]
+use crate::ast::Ident;
+
+/// Is this the name of a function call that needs special handling?
+fn is_special_fn(name: &str) -> bool {
+    match name {
+        KEYWORD_FN_PTR_CALL | KEYWORD_FN_PTR | KEYWORD_FN_PTR_CURRY | KEYWORD_IS_DEF_VAR
+        | KEYWORD_EVAL => true,
+        #[cfg(not(feature = "no_closure"))]
+        crate::engine::KEYWORD_IS_SHARED => true,
+        #[cfg(not(feature = "no_function"))]
+        crate::engine::KEYWORD_IS_DEF_FN => true,
+        _ => false,
+    }
+}
+
+/// Is this a function call that can be made with just the values of its arguments?
+#[inline(always)]
+pub(crate) fn is_plain_call(x: &FnCallExpr) -> bool {
+    x.namespace.is_none() && !x.capture && !is_special_fn(&x.name)
+}
+
+/// Get the first argument of a function call if it is a variable, which is not evaluated but
+/// passed by reference (method-call style) instead.
+#[inline(always)]
+pub(crate) fn first_var_arg(x: &FnCallExpr) -> Option<&Expr> {
+    x.args
+        .first()
+        .filter(|arg| arg.get_variable_access(false).is_some())
+}
+
+/// Is this expression evaluated on the work stack?
+#[inline(always)]
+pub(crate) fn is_stacked_expr(expr: &Expr) -> bool {
+    match expr {
+        Expr::Stmt(_) | Expr::And(_, _) | Expr::Or(_, _) => true,
+        Expr::FnCall(x, _) => is_plain_call(x),
+        #[cfg(not(feature = "no_index"))]
+        Expr::Array(_, _) => true,
+        #[cfg(not(feature = "no_object"))]
+        Expr::Map(_, _) => true,
+        _ => false,
+    }
+}
+
+/// A statements block being evaluated, with the state to restore when it is exited.
+#[derive(Debug)]
+pub(crate) struct BlockFrame {
+    /// Restore the previous state on exit?
+    restore: bool,
+    /// Number of variables in scope on entry.
+    scope_len: usize,
+    /// Number of imported modules on entry.
+    mods_len: usize,
+    /// Value of `always_search` on entry.
+    always_search: bool,
+    /// Has a new function resolution cache been pushed?
+    extra_cache: bool,
+}
+
+impl BlockFrame {
+    /// Enter a new block.
+    #[inline(always)]
+    pub fn new(scope: &Scope, mods: &Imports, state: &mut State, restore: bool) -> Self {
+        if restore {
+            state.scope_level += 1;
+        }
+
+        Self {
+            restore,
+            scope_len: scope.len(),
+            mods_len: mods.len(),
+            always_search: state.always_search,
+            extra_cache: false,
+        }
+    }
+    /// Update the function resolution cache after an `import` statement in the block, given the
+    /// number of imported modules before it.
+    #[cfg(not(feature = "no_module"))]
+    pub fn imported(&mut self, mods: &Imports, mods_len: usize, state: &mut State) {
+        // Get the extra modules - see if any functions are marked global.
+        // Without global functions, the extra modules never affect function resolution.
+        if !mods
+            .scan_raw()
+            .skip(mods_len)
+            .any(|(_, m)| m.contains_indexed_global_functions())
+        {
+            return;
+        }
+
+        if self.restore && !self.extra_cache {
+            // When new module is imported with global functions, push a new cache
+            state.push_fn_resolution_cache();
+            self.extra_cache = true;
+        } else {
+            // When there is already a new cache, or the block is evaluated in-place, just clear
+            // the current cache - notice that this is expensive as all function resolutions must
+            // start again
+            state.fn_resolution_cache_mut().clear();
+        }
+    }
+    /// Exit the block, restoring the previous state.
+    pub fn exit(self, scope: &mut Scope, mods: &mut Imports, state: &mut State) {
+        if self.extra_cache {
+            // If imports list is modified, pop the functions lookup cache
+            state.pop_fn_resolution_cache();
+        }
+
+        if self.restore {
+            scope.rewind(self.scope_len);
+            mods.truncate(self.mods_len);
+            state.scope_level -= 1;
+
+            // The impact of new local variables goes away at the end of a block
+            // because any new variables introduced will go out of scope
+            state.always_search = self.always_search;
+        }
+    }
+}
+
+/// A pending unit of work on the work stack.
+#[derive(Debug, Clone, Copy)]
+enum Work<'a> {
+    /// Evaluate an expression.
+    Expr(&'a Expr),
+    /// Evaluate a statements block, restoring the previous state on exit or not, in tail
+    /// position or not.
+    Block(&'a [Stmt], bool, bool),
+    /// Evaluate the statement at an index of a block (in tail position or not), discarding the
+    /// value of the previous statement.
+    Next(&'a [Stmt], usize, bool),
+    /// Evaluate a statement, in tail position or not.
+    Stmt(&'a Stmt, bool),
+    /// Exit the innermost block.
+    ExitBlock,
+    /// Check the data size of a value, flattening it first or not.
+    Check(Position, bool),
+    /// Build an array or object map out of the values of its items.
+    #[cfg(any(not(feature = "no_index"), not(feature = "no_object")))]
+    Collect(&'a Expr),
+    /// Make a function call with the values of its arguments.
+    Call(&'a Expr),
+    /// Test the left operand of `&&` or `||`, short-circuiting if possible.
+    Logic(&'a Expr),
+    /// Convert the right operand of `&&` or `||` into a boolean.
+    ToBool(&'a Expr),
+}
+
+/// State of the work stack.
+#[derive(Default)]
+struct WorkStack<'a> {
+    /// Pending work, the next unit on top.
+    work: StaticVec<Work<'a>>,
+    /// Values of evaluated expressions and statements.
+    values: StaticVec<Dynamic>,
+    /// Blocks being evaluated, the innermost on top.
+    blocks: StaticVec<BlockFrame>,
+}
+
+impl WorkStack<'_> {
+    #[inline(always)]
+    fn push(&mut self, value: Dynamic) {
+        self.values.push(value);
+    }
+    #[inline(always)]
+    fn pop(&mut self) -> Dynamic {
+        self.values.pop().expect("value stack should not be empty")
+    }
+    /// Remove the values of the last `n` items, in order.
+    #[inline(always)]
+    fn pop_n(&mut self, n: usize) -> impl Iterator<Item = Dynamic> + '_ {
+        let len = self.values.len();
+        self.values.drain(len - n..)
+    }
+}
+
+impl Engine {
+    /// Evaluate an expression on the work stack.
+    ///
+    /// The expression must satisfy [`is_stacked_expr`], otherwise it is evaluated by
+    /// [`eval_expr`][Engine::eval_expr] all over again.
+    #[inline(always)]
+    pub(crate) fn eval_stacked_expr(
+        &self,
+        scope: &mut Scope,
+        mods: &mut Imports,
+        state: &mut State,
+        lib: &[&Module],
+        this_ptr: &mut Option<&mut Dynamic>,
+        expr: &Expr,
+        level: usize,
+    ) -> RhaiResult {
+        self.eval_work(scope, mods, state, lib, this_ptr, Work::Expr(expr), level)
+    }
+
+    /// Evaluate a statements block.
+    pub(crate) fn eval_stmt_block(
+        &self,
+        scope: &mut Scope,
+        mods: &mut Imports,
+        state: &mut State,
+        lib: &[&Module],
+        this_ptr: &mut Option<&mut Dynamic>,
+        statements: &[Stmt],
+        restore_prev_state: bool,
+        level: usize,
+    ) -> RhaiResult {
+        // The last statement of a block in tail position is also in tail position
+        let in_tail = mem::take(&mut state.in_tail);
+        let work = Work::Block(statements, restore_prev_state, in_tail);
+
+        self.eval_work(scope, mods, state, lib, this_ptr, work, level)
+    }
+
+    /// Evaluate a unit of work, and all the work it spawns, in a loop.
+    fn eval_work(
+        &self,
+        scope: &mut Scope,
+        mods: &mut Imports,
+        state: &mut State,
+        lib: &[&Module],
+        this_ptr: &mut Option<&mut Dynamic>,
+        work: Work,
+        level: usize,
+    ) -> RhaiResult {
+        let stack = &mut WorkStack::default();
+        stack.work.push(work);
+
+        while let Some(work) = stack.work.pop() {
+            if let Err(err) =
+                self.eval_work_unit(scope, mods, state, lib, this_ptr, stack, work, level)
+            {
+                // Exit all blocks, the innermost first
+                while let Some(block) = stack.blocks.pop() {
+                    block.exit(scope, mods, state);
+                }
+                return Err(err);
+            }
+        }
+
+        Ok(stack.pop())
+    }
+
+    /// Evaluate a single unit of work, pushing any work it spawns onto the work stack.
+    fn eval_work_unit<'a>(
+        &self,
+        scope: &mut Scope,
+        mods: &mut Imports,
+        state: &mut State,
+        lib: &[&Module],
+        this_ptr: &mut Option<&mut Dynamic>,
+        stack: &mut WorkStack<'a>,
+        work: Work<'a>,
+        level: usize,
+    ) -> Result<(), Box<EvalAltResult>> {
+        match work {
+            Work::Expr(expr) if !is_stacked_expr(expr) => {
+                let value = self.eval_expr(scope, mods, state, lib, this_ptr, expr, level)?;
+                stack.push(value);
+            }
+
+            Work::Expr(expr) => {
+                self.inc_operations(state, expr.position())?;
+
+                match expr {
+                    // Statement block
+                    Expr::Stmt(x) => {
+                        stack.work.push(Work::Check(expr.position(), false));
+                        let in_tail = mem::take(&mut state.in_tail);
+                        stack.work.push(Work::Block(&x.statements, true, in_tail));
+                    }
+
+                    // Evaluate items in left-to-right order
+                    #[cfg(not(feature = "no_index"))]
+                    Expr::Array(x, _) => {
+                        stack.work.push(Work::Collect(expr));
+                        stack.work.extend(x.iter().rev().map(Work::Expr));
+                    }
+                    #[cfg(not(feature = "no_object"))]
+                    Expr::Map(x, _) => {
+                        stack.work.push(Work::Collect(expr));
+                        stack
+                            .work
+                            .extend(x.0.iter().rev().map(|(_, expr)| Work::Expr(expr)));
+                    }
+
+                    // The first argument, if a variable, is only searched when making the call
+                    Expr::FnCall(x, _) => {
+                        let skip = if first_var_arg(x).is_some() { 1 } else { 0 };
+                        stack.work.push(Work::Call(expr));
+                        stack
+                            .work
+                            .extend(x.args.iter().skip(skip).rev().map(Work::Expr));
+                    }
+
+                    Expr::And(x, _) | Expr::Or(x, _) => {
+                        stack.work.push(Work::Logic(expr));
+                        stack.work.push(Work::Expr(&x.lhs));
+                    }
+
+                    _ => unreachable!("expression cannot be stacked: {:?}", expr),
+                }
+            }
+
+            Work::Block(statements, _, _) if statements.is_empty() => stack.push(Dynamic::UNIT),
+
+            Work::Block(statements, restore_prev_state, in_tail) => {
+                let block = BlockFrame::new(scope, mods, state, restore_prev_state);
+                stack.blocks.push(block);
+                stack.work.push(Work::ExitBlock);
+                stack.work.push(Work::Next(statements, 0, in_tail));
+            }
+
+            Work::Next(statements, index, in_tail) => {
+                if index > 0 {
+                    stack.pop();
+                }
+
+                let is_last = index == statements.len() - 1;
+
+                if !is_last {
+                    stack.work.push(Work::Next(statements, index + 1, in_tail));
+                }
+                stack
+                    .work
+                    .push(Work::Stmt(&statements[index], in_tail && is_last));
+            }
+
+            // Nested block scope
+            Work::Stmt(stmt @ Stmt::Block(statements, _), in_tail) => {
+                self.inc_operations(state, stmt.position())?;
+
+                if let Some(ref coverage) = self.coverage {
+                    coverage.hit_statement(state.coverage_source(), stmt.position());
+                }
+
+                stack.work.push(Work::Check(stmt.position(), false));
+                stack.work.push(Work::Block(statements, true, in_tail));
+            }
+
+            // Expression as statement - except for function calls in tail position
+            Work::Stmt(stmt @ Stmt::Expr(expr), in_tail)
+                if is_stacked_expr(expr) && !(in_tail && matches!(expr, Expr::FnCall(_, _))) =>
+            {
+                self.inc_operations(state, stmt.position())?;
+
+                if let Some(ref coverage) = self.coverage {
+                    coverage.hit_statement(state.coverage_source(), stmt.position());
+                }
+
+                stack.work.push(Work::Check(stmt.position(), true));
+                stack.work.push(Work::Expr(expr));
+            }
+
+            Work::Stmt(stmt, in_tail) => {
+                let _mods_len = mods.len();
+
+                state.in_tail = in_tail;
+                let value = self.eval_stmt(scope, mods, state, lib, this_ptr, stmt, level)?;
+
+                #[cfg(not(feature = "no_module"))]
+                if matches!(stmt, Stmt::Import(_, _, _)) {
+                    let block = stack
+                        .blocks
+                        .last_mut()
+                        .expect("import should be in a block");
+                    block.imported(mods, _mods_len, state);
+                }
+
+                stack.push(value);
+            }
+
+            Work::ExitBlock => {
+                let block = stack.blocks.pop().expect("block stack should not be empty");
+                block.exit(scope, mods, state);
+            }
+
+            Work::Check(pos, flatten) => {
+                let value = stack.pop();
+                let value = if flatten { value.flatten() } else { value };
+                let value = self.check_data_size(Ok(value), pos)?;
+                stack.push(value);
+            }
+
+            #[cfg(any(not(feature = "no_index"), not(feature = "no_object")))]
+            Work::Collect(expr) => {
+                let value: Dynamic = match expr {
+                    #[cfg(not(feature = "no_index"))]
+                    Expr::Array(x, _) => stack
+                        .pop_n(x.len())
+                        .map(Dynamic::flatten)
+                        .collect::<Array>()
+                        .into(),
+
+                    #[cfg(not(feature = "no_object"))]
+                    Expr::Map(x, _) => {
+                        let mut map = x.1.clone();
+                        x.0.iter().zip(stack.pop_n(x.0.len())).for_each(
+                            |((Ident { name: key, .. }, _), value)| {
+                                *map.get_mut(key.as_str()).unwrap() = value.flatten()
+                            },
+                        );
+                        map.into()
+                    }
+
+                    _ => unreachable!("array or object map expected, but gets {:?}", expr),
+                };
+
+                let value = self.check_data_size(Ok(value), expr.position())?;
+                stack.push(value);
+            }
+
+            Work::Call(expr) => {
+                let (x, pos) = match expr {
+                    Expr::FnCall(x, pos) => (x, *pos),
+                    _ => unreachable!("function call expected, but gets {:?}", expr),
+                };
+
+                let first = first_var_arg(x);
+                let num_args = x.args.len() - if first.is_some() { 1 } else { 0 };
+                let arg_values = stack
+                    .pop_n(num_args)
+                    .map(Dynamic::flatten)
+                    .chain(x.constant_args.iter().map(|(v, _)| v.clone()))
+                    .collect();
+
+                let result = self.make_function_call_with_values(
+                    scope, mods, state, lib, this_ptr, &x.name, x.hash, first, arg_values, pos,
+                    level,
+                );
+
+                let result = match x.source_text {
+                    // Fill in the source text of a failed assertion
+                    Some(ref text) => result.map_err(|mut err| {
+                        if let EvalAltResult::ErrorAssertion(_, _, ref mut expr, _) = *err {
+                            if expr.is_empty() {
+                                *expr = text.to_string();
+                            }
+                        }
+                        err
+                    }),
+                    None => result,
+                };
+
+                let value = self.check_data_size(result, pos)?;
+                stack.push(value);
+            }
+
+            Work::Logic(expr) => {
+                let (x, pos, is_and) = match expr {
+                    Expr::And(x, pos) => (x, *pos, true),
+                    Expr::Or(x, pos) => (x, *pos, false),
+                    _ => unreachable!("&& or || expected, but gets {:?}", expr),
+                };
+
+                let lhs = stack
+                    .pop()
+                    .as_bool()
+                    .map_err(|err| self.make_type_mismatch_err::<bool>(err, x.lhs.position()))?;
+
+                if let Some(ref coverage) = self.coverage {
+                    let source = state.coverage_source();
+                    coverage.hit_branch(source, pos, (lhs == is_and) as usize);
+                }
+
+                if lhs == is_and {
+                    stack.work.push(Work::ToBool(expr));
+                    stack.work.push(Work::Expr(&x.rhs));
+                } else {
+                    // Short-circuit
+                    stack.push(lhs.into());
+                }
+            }
+
+            Work::ToBool(expr) => {
+                let x = match expr {
+                    Expr::And(x, _) | Expr::Or(x, _) => x,
+                    _ => unreachable!("&& or || expected, but gets {:?}", expr),
+                };
+
+                let rhs = stack
+                    .pop()
+                    .as_bool()
+                    .map_err(|err| self.make_type_mismatch_err::<bool>(err, x.rhs.position()))?;
+
+                stack.push(rhs.into());
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -20,16 +20,15 @@ use crate::stdlib::{
     string::{String, ToString},
     vec::Vec,
 };
-use crate::{
-    ast::{Expr, Stmt},
-    fn_native::CallableFunction,
-    RhaiResult,
-};
+use crate::{ast::Expr, fn_native::CallableFunction, RhaiResult, AST};
 use crate::{
     calc_fn_hash, calc_fn_params_hash, combine_hashes, Dynamic, Engine, EvalAltResult, FnPtr,
     ImmutableString, Module, ParseErrorType, Position, Scope, StaticVec,
 };
 
+#[cfg(not(feature = "no_function"))]
+use crate::{engine::TailCall, token::is_keyword_function, Shared};
+
 #[cfg(not(feature = "no_object"))]
 use crate::Map;
 
@@ -120,6 +119,30 @@ pub fn ensure_no_data_race(
     Ok(())
 }
 
+/// Wrap an error raised within a script-defined function.
+#[cfg(not(feature = "no_function"))]
+#[inline(always)]
+fn make_error(
+    name: String,
+    fn_def: &crate::ast::ScriptFnDef,
+    state: &State,
+    err: Box<EvalAltResult>,
+    pos: Position,
+) -> RhaiResult {
+    EvalAltResult::ErrorInFunctionCall(
+        name,
+        fn_def
+            .lib
+            .as_ref()
+            .and_then(|m| m.id().map(|id| id.to_string()))
+            .or_else(|| state.source.as_ref().map(|s| s.to_string()))
+            .unwrap_or_default(),
+        err,
+        pos,
+    )
+    .into()
+}
+
 impl Engine {
     /// Generate the signature for a function call.
     #[inline]
@@ -299,6 +322,8 @@ impl Engine {
         self.inc_operations(state, pos)?;
 
         let state_source = state.source.clone();
+        #[cfg(not(feature = "no_std"))]
+        let operations = state.operations;
 
         // Check if function access already in the cache
         let func = self.resolve_function(
@@ -322,17 +347,31 @@ impl Engine {
                 backup.as_mut().unwrap().change_first_arg_to_copy(args);
             }
 
+            #[cfg(not(feature = "no_std"))]
+            if let Some(ref profiler) = self.profiler {
+                let source = state_source.as_ref().map(|s| s.as_str());
+                profiler.enter(fn_name, source, pos, false, operations);
+            }
+
             // Run external function
             let source = source
                 .as_ref()
                 .or_else(|| state_source.as_ref())
                 .map(|s| s.as_str());
+
             let result = if func.is_plugin_fn() {
                 func.get_plugin_fn()
                     .call((self, fn_name, source, mods, lib).into(), args)
             } else {
                 func.get_native_fn()((self, fn_name, source, mods, lib).into(), args)
             };
+            let is_method = func.is_method();
+
+            // Count the operations performed during the call
+            #[cfg(not(feature = "no_std"))]
+            if let Some(ref profiler) = self.profiler {
+                profiler.exit(state.operations);
+            }
 
             // Restore the original reference
             if let Some(backup) = backup {
@@ -364,7 +403,7 @@ impl Engine {
                     let source = state.source.as_ref().map(|s| s.as_str());
                     ((self.debug)(&text, source, pos).into(), false)
                 }
-                _ => (result, func.is_method()),
+                _ => (result, is_method),
             });
         }
 
@@ -450,6 +489,9 @@ impl Engine {
     /// Function call arguments may be _consumed_ when the function requires them to be passed by value.
     /// All function arguments not in the first position are always passed by value and thus consumed.
     /// **DO NOT** reuse the argument values unless for the first `&mut` argument - all others are silently replaced by `()`!
+    ///
+    /// Calls in tail position to script-defined functions sharing the same encapsulated
+    /// environment are made in a loop, so that they do not grow the call stack.
     #[cfg(not(feature = "no_function"))]
     pub(crate) fn call_script_fn(
         &self,
@@ -463,28 +505,71 @@ impl Engine {
         pos: Position,
         level: usize,
     ) -> RhaiResult {
-        #[inline(always)]
-        fn make_error(
-            name: crate::stdlib::string::String,
-            fn_def: &crate::ast::ScriptFnDef,
-            state: &State,
-            err: Box<EvalAltResult>,
-            pos: Position,
-        ) -> RhaiResult {
-            EvalAltResult::ErrorInFunctionCall(
-                name,
-                fn_def
-                    .lib
-                    .as_ref()
-                    .and_then(|m| m.id().map(|id| id.to_string()))
-                    .or_else(|| state.source.as_ref().map(|s| s.to_string()))
-                    .unwrap_or_default(),
-                err,
+        let mut result =
+            self.call_script_fn_once(scope, mods, state, lib, this_ptr, fn_def, args, pos, level);
+        let mut tail_calls = 0;
+
+        while let Some(tail) = state.take_tail_call(level) {
+            let TailCall {
+                func: FnResolutionCacheEntry { func, source },
+                mut args,
                 pos,
-            )
-            .into()
+                ..
+            } = *tail;
+
+            tail_calls += 1;
+            let func = func.get_fn_def();
+
+            if func.body.is_empty() {
+                result = Ok(Dynamic::UNIT);
+                continue;
+            }
+
+            let orig_source = mem::replace(&mut state.source, source);
+            let mut args: StaticVec<_> = args.iter_mut().collect();
+
+            result = self.call_script_fn_once(
+                scope, mods, state, lib, &mut None, func, &mut args, pos, level,
+            );
+
+            state.source = orig_source;
         }
 
+        if tail_calls == 0 {
+            return result;
+        }
+
+        #[cfg(not(feature = "no_std"))]
+        if let Some(ref profiler) = self.profiler {
+            (0..tail_calls).for_each(|_| profiler.exit(state.operations));
+        }
+
+        // Keep the original function call in the error backtrace
+        result.or_else(|mut err| {
+            if err.is_system_exception() {
+                err.set_position(pos);
+                Err(err)
+            } else {
+                make_error(fn_def.name.to_string(), fn_def, state, err, pos)
+            }
+        })
+    }
+
+    /// Call a script-defined function once, leaving any call in tail position to a function in the
+    /// same encapsulated environment pending.
+    #[cfg(not(feature = "no_function"))]
+    fn call_script_fn_once(
+        &self,
+        scope: &mut Scope,
+        mods: &mut Imports,
+        state: &mut State,
+        lib: &[&Module],
+        this_ptr: &mut Option<&mut Dynamic>,
+        fn_def: &crate::ast::ScriptFnDef,
+        args: &mut FnCallArgs,
+        pos: Position,
+        level: usize,
+    ) -> RhaiResult {
         self.inc_operations(state, pos)?;
 
         if fn_def.body.is_empty() {
@@ -500,6 +585,7 @@ impl Engine {
 
         let orig_scope_level = state.scope_level;
         state.scope_level += 1;
+        let orig_in_function = crate::stdlib::mem::replace(&mut state.in_function, true);
 
         let prev_scope_len = scope.len();
         let prev_mods_len = mods.len();
@@ -535,41 +621,96 @@ impl Engine {
         if !fn_def.mods.is_empty() {
             fn_def
                 .mods
-                .iter_raw()
-                .for_each(|(n, m)| mods.push(n.clone(), m.clone()));
+                .iter_lazy_raw()
+                .for_each(|(n, m, lazy)| mods.push_raw(n.clone(), m.clone(), lazy.cloned()));
+        }
+
+        let fn_mods_len = mods.len();
+
+        // Coverage of functions defined in a module is recorded under the source of that module
+        let orig_fn_source = if self.coverage.is_some() {
+            let source = fn_def.lib.as_ref().and_then(|m| m.id()).map(|id| id.into());
+            Some(mem::replace(&mut state.fn_source, source))
+        } else {
+            None
+        };
+
+        #[cfg(not(feature = "no_std"))]
+        if let Some(ref profiler) = self.profiler {
+            let source = fn_def
+                .lib
+                .as_ref()
+                .and_then(|m| m.id())
+                .or_else(|| state.source.as_ref().map(|s| s.as_str()));
+            let pos = fn_def.body.pos;
+            profiler.enter(&fn_def.name, source, pos, true, state.operations);
         }
 
         // Evaluate the function
         let body = &fn_def.body.statements;
 
-        let result = self
-            .eval_stmt_block(scope, mods, state, unified_lib, this_ptr, body, true, level)
-            .or_else(|err| match *err {
-                // Convert return statement to return value
-                EvalAltResult::Return(x, _) => Ok(x),
-                // Error in sub function call
-                EvalAltResult::ErrorInFunctionCall(name, src, err, _) => {
-                    let fn_name = if src.is_empty() {
-                        format!("{} < {}", name, fn_def.name)
-                    } else {
-                        format!("{} @ '{}' < {}", name, src, fn_def.name)
-                    };
+        let result = match fn_def.bytecode {
+            Some(ref chunk) => self.eval_chunk(
+                scope,
+                mods,
+                state,
+                unified_lib,
+                this_ptr,
+                chunk,
+                true,
+                level,
+            ),
+            None => {
+                state.in_tail = true;
+                self.eval_stmt_block(scope, mods, state, unified_lib, this_ptr, body, true, level)
+            }
+        };
 
-                    make_error(fn_name, fn_def, state, err, pos)
-                }
-                // System errors are passed straight-through
-                mut err if err.is_system_exception() => {
-                    err.set_position(pos);
-                    err.into()
-                }
-                // Other errors are wrapped in `ErrorInFunctionCall`
-                _ => make_error(fn_def.name.to_string(), fn_def, state, err, pos),
-            });
+        // Calls in tail position to functions in a different environment, or after new modules
+        // are imported, are made normally
+        let result = match state.tail_call(level) {
+            Some(tail)
+                if mods.len() == fn_mods_len
+                    && match (&fn_def.lib, &tail.func.func.get_fn_def().lib) {
+                        (Some(lib1), Some(lib2)) => Shared::ptr_eq(lib1, lib2),
+                        (None, None) => true,
+                        _ => false,
+                    } =>
+            {
+                result
+            }
+            _ => self.finish_tail_call(mods, state, unified_lib, result, level),
+        }
+        .or_else(|err| match *err {
+            // Convert return statement to return value
+            EvalAltResult::Return(x, _) => Ok(x),
+            // System errors are passed straight-through
+            mut err if err.is_system_exception() => {
+                err.set_position(pos);
+                err.into()
+            }
+            // Other errors are wrapped in `ErrorInFunctionCall`
+            _ => make_error(fn_def.name.to_string(), fn_def, state, err, pos),
+        });
+
+        // The profiler keeps the frames of functions making calls in tail position until
+        // the last call returns
+        #[cfg(not(feature = "no_std"))]
+        if let Some(ref profiler) = self.profiler {
+            if state.tail_call(level).is_none() {
+                profiler.exit(state.operations);
+            }
+        }
 
         // Remove all local variables
         scope.rewind(prev_scope_len);
         mods.truncate(prev_mods_len);
         state.scope_level = orig_scope_level;
+        state.in_function = orig_in_function;
+
+        if let Some(source) = orig_fn_source {
+            state.fn_source = source;
+        }
 
         if unified {
             state.pop_fn_resolution_cache();
@@ -578,6 +719,158 @@ impl Engine {
         result
     }
 
+    /// Resolve a function call in tail position of a script-defined function.
+    ///
+    /// Returns [`None`] unless the function called is script-defined.
+    #[cfg(not(feature = "no_function"))]
+    pub(crate) fn resolve_tail_call(
+        &self,
+        mods: &Imports,
+        state: &mut State,
+        lib: &[&Module],
+        fn_name: &str,
+        hash: FnCallHash,
+    ) -> Option<FnResolutionCacheEntry> {
+        // Special functions are never script-defined
+        if !state.in_function || hash.is_native_only() || is_keyword_function(fn_name) {
+            return None;
+        }
+
+        #[cfg(not(feature = "no_closure"))]
+        if fn_name == crate::engine::KEYWORD_IS_SHARED {
+            return None;
+        }
+
+        self.resolve_function(
+            mods,
+            state,
+            lib,
+            fn_name,
+            hash.script_hash(),
+            None,
+            false,
+            false,
+        )
+        .clone()
+        .filter(|f| f.func.is_script())
+    }
+
+    /// Make a function call in tail position of a script-defined function, by evaluating its
+    /// arguments and leaving the call pending, returning from the function.
+    ///
+    /// Returns [`None`] if the function call must be made normally.
+    #[cfg(not(feature = "no_function"))]
+    pub(crate) fn make_tail_call(
+        &self,
+        scope: &mut Scope,
+        mods: &mut Imports,
+        state: &mut State,
+        lib: &[&Module],
+        this_ptr: &mut Option<&mut Dynamic>,
+        expr: &Expr,
+        return_pos: Position,
+        level: usize,
+    ) -> Option<RhaiResult> {
+        let (x, pos) = match expr {
+            Expr::FnCall(x, pos) if x.namespace.is_none() && !x.capture => (x.as_ref(), *pos),
+            _ => return None,
+        };
+
+        let func = self.resolve_tail_call(mods, state, lib, &x.name, x.hash)?;
+
+        // Evaluate the arguments in the same order as a normal function call
+        let args = self.inc_operations(state, pos).and_then(|_| {
+            let skip = match x.args.first() {
+                Some(arg) if arg.get_variable_access(false).is_some() => 1,
+                _ => 0,
+            };
+
+            let mut args = x
+                .args
+                .iter()
+                .skip(skip)
+                .map(|expr| {
+                    self.eval_expr(scope, mods, state, lib, this_ptr, expr, level)
+                        .map(Dynamic::flatten)
+                })
+                .chain(x.constant_args.iter().map(|(v, _)| Ok(v.clone())))
+                .collect::<Result<StaticVec<_>, _>>()?;
+
+            if skip > 0 {
+                let (target, var_pos) =
+                    self.search_namespace(scope, mods, state, lib, this_ptr, &x.args[0])?;
+                self.inc_operations(state, var_pos)?;
+                args.insert(0, target.take_or_clone().flatten());
+            }
+
+            Ok(args)
+        });
+
+        Some(args.and_then(|args| {
+            state.set_tail_call(TailCall {
+                func,
+                args,
+                pos,
+                level,
+            });
+            EvalAltResult::Return(Dynamic::UNIT, return_pos).into()
+        }))
+    }
+
+    /// Make any pending call in tail position as a normal function call, if the result is a
+    /// return from the function.
+    #[cfg(not(feature = "no_function"))]
+    pub(crate) fn finish_tail_call(
+        &self,
+        mods: &mut Imports,
+        state: &mut State,
+        lib: &[&Module],
+        result: RhaiResult,
+        level: usize,
+    ) -> RhaiResult {
+        let err = match result {
+            Err(err) if matches!(*err, EvalAltResult::Return(_, _)) => err,
+            result => return result,
+        };
+
+        let TailCall {
+            func: FnResolutionCacheEntry { func, source },
+            mut args,
+            pos,
+            ..
+        } = match state.take_tail_call(level) {
+            Some(tail) => *tail,
+            None => return Err(err),
+        };
+
+        let func = func.get_fn_def();
+
+        let value = if func.body.is_empty() {
+            Dynamic::UNIT
+        } else {
+            let scope: &mut Scope = &mut Default::default();
+            let orig_source = mem::replace(&mut state.source, source);
+            let mut args: StaticVec<_> = args.iter_mut().collect();
+
+            let result = self.call_script_fn(
+                scope,
+                mods,
+                state,
+                lib,
+                &mut None,
+                func,
+                &mut args,
+                pos,
+                level + 1,
+            );
+
+            state.source = orig_source;
+            result?
+        };
+
+        EvalAltResult::Return(value, err.position()).into()
+    }
+
     // Does a scripted function exist?
     #[cfg(not(feature = "no_function"))]
     #[inline(always)]
@@ -816,26 +1109,32 @@ impl Engine {
         )
     }
 
-    /// Evaluate a list of statements with no `this` pointer.
-    /// This is commonly used to evaluate a list of statements in an [`AST`] or a script function body.
+    /// Evaluate the global statements in an [`AST`] with no `this` pointer.
+    /// The bytecode compiled from the statements is evaluated instead, if any.
     #[inline(always)]
     pub(crate) fn eval_global_statements(
         &self,
         scope: &mut Scope,
         mods: &mut Imports,
         state: &mut State,
-        statements: &[Stmt],
+        ast: &AST,
         lib: &[&Module],
         level: usize,
     ) -> RhaiResult {
-        self.eval_stmt_block(scope, mods, state, lib, &mut None, statements, false, level)
-            .or_else(|err| match *err {
-                EvalAltResult::Return(out, _) => Ok(out),
-                EvalAltResult::LoopBreak(_, _) => {
-                    unreachable!("no outer loop scope to break out of")
-                }
-                _ => Err(err),
-            })
+        match ast.bytecode() {
+            Some(chunk) => self.eval_chunk(scope, mods, state, lib, &mut None, chunk, false, level),
+            None => {
+                let statements = ast.statements();
+                self.eval_stmt_block(scope, mods, state, lib, &mut None, statements, false, level)
+            }
+        }
+        .or_else(|err| match *err {
+            EvalAltResult::Return(out, _) => Ok(out),
+            EvalAltResult::LoopBreak(_, _) => {
+                unreachable!("no outer loop scope to break out of")
+            }
+            _ => Err(err),
+        })
     }
 
     /// Evaluate a text script in place - used primarily for 'eval'.
@@ -874,8 +1173,7 @@ impl Engine {
         new_state.source = state.source.clone();
         new_state.operations = state.operations;
 
-        let result =
-            self.eval_global_statements(scope, mods, &mut new_state, ast.statements(), lib, level);
+        let result = self.eval_global_statements(scope, mods, &mut new_state, &ast, lib, level);
 
         state.operations = new_state.operations;
 
@@ -1350,6 +1648,60 @@ impl Engine {
         .map(|(v, _)| v)
     }
 
+    /// Call a function in normal function-call style, with the values of its arguments.
+    ///
+    /// The first argument, if it is a variable, is not included in the values but passed
+    /// by reference (method-call style) instead in order to avoid cloning it.
+    pub(crate) fn make_function_call_with_values(
+        &self,
+        scope: &mut Scope,
+        mods: &mut Imports,
+        state: &mut State,
+        lib: &[&Module],
+        this_ptr: &mut Option<&mut Dynamic>,
+        fn_name: &str,
+        hash: FnCallHash,
+        first_arg: Option<&Expr>,
+        mut arg_values: StaticVec<Dynamic>,
+        pos: Position,
+        level: usize,
+    ) -> RhaiResult {
+        if let Some(first_arg) = first_arg {
+            // func(x, ...) -> x.func(...)
+            let (mut target, var_pos) =
+                self.search_namespace(scope, mods, state, lib, this_ptr, first_arg)?;
+
+            if target.as_ref().is_read_only() {
+                target = target.into_owned();
+            }
+
+            self.inc_operations(state, var_pos)?;
+
+            let mut is_ref = false;
+            let mut args: StaticVec<_> = if target.is_shared() || target.is_value() {
+                arg_values.insert(0, target.take_or_clone().flatten());
+                arg_values.iter_mut().collect()
+            } else {
+                // Turn it into a method call only if the object is not shared and not a simple value
+                is_ref = true;
+                once(target.take_ref().unwrap())
+                    .chain(arg_values.iter_mut())
+                    .collect()
+            };
+
+            self.exec_fn_call(
+                mods, state, lib, fn_name, hash, &mut args, is_ref, false, pos, None, level,
+            )
+        } else {
+            let mut args: StaticVec<_> = arg_values.iter_mut().collect();
+
+            self.exec_fn_call(
+                mods, state, lib, fn_name, hash, &mut args, false, false, pos, None, level,
+            )
+        }
+        .map(|(v, _)| v)
+    }
+
     /// Call a namespace-qualified function in normal function-call style.
     pub(crate) fn make_qualified_function_call(
         &self,
@@ -1426,9 +1778,15 @@ impl Engine {
             }
         }
 
-        let module = self.search_imports(mods, state, namespace).ok_or_else(|| {
-            EvalAltResult::ErrorModuleNotFound(namespace[0].name.to_string(), namespace[0].pos)
-        })?;
+        let module = self
+            .search_imports(mods, state, namespace)?
+            .ok_or_else(|| {
+                EvalAltResult::ErrorModuleNotFound(
+                    namespace[0].name.to_string(),
+                    Default::default(),
+                    namespace[0].pos,
+                )
+            })?;
 
         // First search in script-defined functions (can override built-in)
         let func = match module.get_qualified_fn(hash) {
@@ -1469,9 +1827,23 @@ impl Engine {
 
                     let level = level + 1;
 
-                    let result = self.call_script_fn(
-                        new_scope, mods, state, lib, &mut None, fn_def, args, pos, level,
-                    );
+                    let result = self
+                        .call_script_fn(
+                            new_scope, mods, state, lib, &mut None, fn_def, args, pos, level,
+                        )
+                        .map_err(|err| match *err {
+                            // Qualify the function name in the call frame
+                            EvalAltResult::ErrorInFunctionCall(name, src, err, pos) => {
+                                EvalAltResult::ErrorInFunctionCall(
+                                    format!("{}{}", namespace, name),
+                                    src,
+                                    err,
+                                    pos,
+                                )
+                                .into()
+                            }
+                            _ => err,
+                        });
 
                     state.source = source;
 
@@ -1479,20 +1851,28 @@ impl Engine {
                 }
             }
 
-            Some(f) if f.is_plugin_fn() => f
-                .get_plugin_fn()
-                .clone()
-                .call(
-                    (self, fn_name, module.id(), &*mods, lib).into(),
-                    args.as_mut(),
-                )
-                .map_err(|err| err.fill_position(pos)),
+            Some(f) if f.is_plugin_fn() || f.is_native() => {
+                #[cfg(not(feature = "no_std"))]
+                if let Some(ref profiler) = self.profiler {
+                    let source = state.source.as_ref().map(|s| s.as_str());
+                    profiler.enter(fn_name, source, pos, false, state.operations);
+                }
 
-            Some(f) if f.is_native() => f.get_native_fn()(
-                (self, fn_name, module.id(), &*mods, lib).into(),
-                args.as_mut(),
-            )
-            .map_err(|err| err.fill_position(pos)),
+                let context = (self, fn_name, module.id(), &*mods, lib).into();
+
+                let result = if f.is_plugin_fn() {
+                    f.get_plugin_fn().clone().call(context, args.as_mut())
+                } else {
+                    f.get_native_fn()(context, args.as_mut())
+                };
+
+                #[cfg(not(feature = "no_std"))]
+                if let Some(ref profiler) = self.profiler {
+                    profiler.exit(state.operations);
+                }
+
+                result.map_err(|err| err.fill_position(pos))
+            }
 
             Some(f) => unreachable!("unknown function type: {:?}", f),
 
@@ -39,11 +39,9 @@ pub use crate::stdlib::rc::Rc as Shared;
 pub use crate::stdlib::sync::Arc as Shared;
 
 /// Synchronized shared object.
-#[cfg(not(feature = "no_closure"))]
 #[cfg(not(feature = "sync"))]
 pub use crate::stdlib::cell::RefCell as Locked;
 /// Synchronized shared object.
-#[cfg(not(feature = "no_closure"))]
 #[cfg(feature = "sync")]
 pub use crate::stdlib::sync::RwLock as Locked;
 
@@ -64,29 +64,48 @@ extern crate alloc;
 // Internal modules
 
 mod ast;
+#[cfg(not(feature = "no_std"))]
+mod checker;
+mod coverage;
+#[cfg(feature = "metadata")]
+mod definitions;
+#[cfg(feature = "serde")]
+#[cfg(feature = "metadata")]
+mod doc_gen;
 mod dynamic;
 mod engine;
 mod engine_api;
 mod engine_settings;
+mod eval_stack;
 mod fn_args;
 mod fn_builtin;
 mod fn_call;
 mod fn_func;
 mod fn_native;
 mod fn_register;
+mod lint;
 mod module;
 mod optimize;
 pub mod packages;
 mod parse_error;
 mod parser;
 pub mod plugin;
+mod precompiled;
+#[cfg(not(feature = "no_std"))]
+mod profiler;
 mod result;
 mod scope;
+mod snippet;
 mod stdlib;
 mod syntax;
+#[cfg(not(feature = "no_function"))]
+#[cfg(not(feature = "no_std"))]
+mod test_runner;
 mod token;
+mod type_check;
 mod r#unsafe;
 mod utils;
+mod vm;
 
 type RhaiResult = stdlib::result::Result<Dynamic, stdlib::boxed::Box<EvalAltResult>>;
 
@@ -123,16 +142,30 @@ pub type FLOAT = f64;
 pub type FLOAT = f32;
 
 pub use ast::{FnAccess, AST};
+#[cfg(not(feature = "no_std"))]
+pub use checker::{CheckReport, Checker, Diagnostic, Severity};
+pub use coverage::Coverage;
+#[cfg(feature = "serde")]
+#[cfg(feature = "metadata")]
+pub use doc_gen::DocGenerator;
 pub use dynamic::Dynamic;
 pub use engine::{Engine, EvalContext, OP_CONTAINS, OP_EQUALS};
 pub use fn_native::{FnPtr, NativeCallContext};
 pub use fn_register::RegisterNativeFunction;
+pub use lint::{Lint, LintLevel, LintWarning, Linter};
 pub use module::{FnNamespace, Module};
 pub use parse_error::{LexError, ParseError, ParseErrorType};
-pub use result::EvalAltResult;
+#[cfg(not(feature = "no_std"))]
+pub use profiler::{ProfileEntry, Profiler};
+pub use result::{CallFrame, EvalAltResult};
 pub use scope::Scope;
+pub use snippet::Snippet;
 pub use syntax::Expression;
-pub use token::Position;
+#[cfg(not(feature = "no_function"))]
+#[cfg(not(feature = "no_std"))]
+pub use test_runner::{TestOutcome, TestReport, TestResult, TestRunner};
+pub use token::{Position, Span};
+pub use type_check::{TypeChecker, TypeError, TypeErrorType};
 pub use utils::ImmutableString;
 
 /// An identifier in Rhai. [`SmartString`](https://crates.io/crates/smartstring) is used because most
@@ -211,7 +244,10 @@ pub use dynamic::Variant;
 // Expose internal data structures.
 #[cfg(feature = "internals")]
 #[deprecated = "this type is volatile and may change"]
-pub use token::{get_next_token, parse_string_literal, InputStream, Token, TokenizeState};
+pub use token::{
+    get_next_token, parse_string_literal, InputStream, Token, TokenizeState, TokenizerControl,
+    TokenizerControlBlock,
+};
 
 #[cfg(feature = "internals")]
 #[deprecated = "this type is volatile and may change"]
@@ -224,6 +260,11 @@ pub use ast::{
 #[deprecated = "this type is volatile and may change"]
 pub use engine::{Imports, State as EvalState};
 
+#[cfg(feature = "internals")]
+#[cfg(not(feature = "no_module"))]
+#[deprecated = "this type is volatile and may change"]
+pub use engine::ModuleCache;
+
 #[cfg(feature = "internals")]
 #[cfg(not(feature = "unchecked"))]
 pub use engine::Limits;
@@ -232,6 +273,10 @@ pub use engine::Limits;
 #[deprecated = "this type is volatile and may change"]
 pub use module::NamespaceRef;
 
+#[cfg(feature = "internals")]
+#[deprecated = "this type is volatile and may change"]
+pub use vm::Chunk;
+
 /// Alias to [`smallvec::SmallVec<[T; 4]>`](https://crates.io/crates/smallvec), which is a
 /// specialized [`Vec`] backed by a small, inline, fixed-size array when there are ≤ 4 items stored.
 ///
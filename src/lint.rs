@@ -0,0 +1,678 @@
+//! Module implementing a lint pass over the [`AST`].
+
+use crate::ast::{ASTNode, Expr, Ident, Stmt};
+use crate::engine::{KEYWORD_EVAL, KEYWORD_FN_PTR_CURRY, OP_EQUALS};
+use crate::optimize::OptimizationLevel;
+use crate::stdlib::{
+    collections::{BTreeMap, BTreeSet},
+    fmt, format,
+    iter::empty,
+    string::{String, ToString},
+    vec::Vec,
+};
+use crate::token::is_valid_identifier;
+use crate::{calc_fn_hash, Engine, FnNamespace, Module, ParseError, Position, AST};
+
+/// A type of warning reported by the [`Linter`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[non_exhaustive]
+pub enum Lint {
+    /// A variable is declared but never used.
+    UnusedVariable,
+    /// A function parameter is never used.
+    UnusedParameter,
+    /// A variable shadows another variable declared in an outer scope.
+    ShadowedVariable,
+    /// A statement follows a `return`, `throw`, `break` or `continue` and can never be reached.
+    UnreachableCode,
+    /// The condition of an `if`, `while` or `do` statement is a constant.
+    ConstantCondition,
+    /// A function that is neither registered with the [`Engine`] nor defined in the script is called.
+    UnknownFunction,
+    /// A value assigned to a variable is never read.
+    UnusedAssignment,
+    /// A value is compared with `()`.
+    UnitComparison,
+}
+
+impl Lint {
+    /// All available lints.
+    pub const ALL: [Lint; 8] = [
+        Self::UnusedVariable,
+        Self::UnusedParameter,
+        Self::ShadowedVariable,
+        Self::UnreachableCode,
+        Self::ConstantCondition,
+        Self::UnknownFunction,
+        Self::UnusedAssignment,
+        Self::UnitComparison,
+    ];
+
+    /// Get the ID of this lint, e.g. `unused_variable`.
+    pub fn id(self) -> &'static str {
+        match self {
+            Self::UnusedVariable => "unused_variable",
+            Self::UnusedParameter => "unused_parameter",
+            Self::ShadowedVariable => "shadowed_variable",
+            Self::UnreachableCode => "unreachable_code",
+            Self::ConstantCondition => "constant_condition",
+            Self::UnknownFunction => "unknown_function",
+            Self::UnusedAssignment => "unused_assignment",
+            Self::UnitComparison => "unit_comparison",
+        }
+    }
+    /// Find a lint by its ID.
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|lint| lint.id() == id)
+    }
+}
+
+impl fmt::Display for Lint {
+    #[inline(always)]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.id())
+    }
+}
+
+/// Level of a [lint][Lint].
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum LintLevel {
+    /// The lint is not checked.
+    Allow,
+    /// The lint is reported as a warning.
+    Warn,
+    /// The lint is reported as an error.
+    Deny,
+}
+
+impl Default for LintLevel {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::Warn
+    }
+}
+
+/// A warning reported by the [`Linter`].
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct LintWarning {
+    /// The lint that is violated.
+    pub lint: Lint,
+    /// Level of the lint.
+    pub level: LintLevel,
+    /// Description of the problem.
+    pub message: String,
+    /// Location of the problem.
+    pub pos: Position,
+}
+
+impl fmt::Display for LintWarning {
+    #[inline(always)]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} [{}]", self.message, self.lint)?;
+
+        // Do not write any position if None
+        if !self.pos.is_none() {
+            write!(f, " ({})", self.pos)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A linter that checks scripts for common mistakes and code smells.
+///
+/// All [lints][Lint] default to [`LintLevel::Warn`].
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> Result<(), rhai::ParseError> {
+/// use rhai::{Engine, Lint, Linter};
+///
+/// let engine = Engine::new();
+/// let mut linter = Linter::new(&engine);
+///
+/// linter.allow(Lint::ShadowedVariable);
+///
+/// let warnings = linter.lint("let x = 42; let y = x; if true { y }")?;
+///
+/// assert_eq!(warnings.len(), 1);
+/// assert_eq!(warnings[0].lint, Lint::ConstantCondition);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Linter<'e> {
+    /// The [`Engine`] providing registered functions.
+    engine: &'e Engine,
+    /// Levels of lints that are not at the default level.
+    levels: BTreeMap<Lint, LintLevel>,
+}
+
+impl<'e> Linter<'e> {
+    /// Create a new [`Linter`] checking scripts against an [`Engine`].
+    #[inline(always)]
+    pub fn new(engine: &'e Engine) -> Self {
+        Self {
+            engine,
+            levels: Default::default(),
+        }
+    }
+    /// Get the level of a [lint][Lint].
+    #[inline(always)]
+    pub fn level(&self, lint: Lint) -> LintLevel {
+        self.levels.get(&lint).copied().unwrap_or_default()
+    }
+    /// Set the level of a [lint][Lint].
+    #[inline(always)]
+    pub fn set_level(&mut self, lint: Lint, level: LintLevel) -> &mut Self {
+        self.levels.insert(lint, level);
+        self
+    }
+    /// Do not check a [lint][Lint].
+    #[inline(always)]
+    pub fn allow(&mut self, lint: Lint) -> &mut Self {
+        self.set_level(lint, LintLevel::Allow)
+    }
+    /// Report a [lint][Lint] as a warning.
+    #[inline(always)]
+    pub fn warn(&mut self, lint: Lint) -> &mut Self {
+        self.set_level(lint, LintLevel::Warn)
+    }
+    /// Report a [lint][Lint] as an error.
+    #[inline(always)]
+    pub fn deny(&mut self, lint: Lint) -> &mut Self {
+        self.set_level(lint, LintLevel::Deny)
+    }
+    /// Compile a script without optimizations and check it.
+    ///
+    /// Warnings are returned in order of their positions.
+    pub fn lint(&self, script: &str) -> Result<Vec<LintWarning>, ParseError> {
+        let ast = self.engine.compile_with_scope_and_optimization_level(
+            &Default::default(),
+            &[script],
+            OptimizationLevel::None,
+        )?;
+        Ok(self.lint_ast(&ast))
+    }
+    /// Check an [`AST`].
+    ///
+    /// The [`AST`] should be compiled with [`OptimizationLevel::None`][crate::OptimizationLevel::None],
+    /// otherwise the optimizer may already have removed or altered code that would be reported.
+    ///
+    /// Warnings are returned in order of their positions.
+    #[allow(deprecated)]
+    pub fn lint_ast(&self, ast: &AST) -> Vec<LintWarning> {
+        let mut state = State {
+            linter: self,
+            warnings: Default::default(),
+            stack: Default::default(),
+            track_vars: true,
+        };
+
+        state.check_nodes(ast);
+
+        // Scripts calling `eval` may access any variable
+        if state.track_vars {
+            state.check_block(ast.statements(), None);
+        }
+
+        #[cfg(not(feature = "no_function"))]
+        ast.lib().iter_script_fn().for_each(|(_, _, _, _, fn_def)| {
+            state.stack = fn_def
+                .params
+                .iter()
+                .map(|name| Var::new(name, fn_def.body.pos, true))
+                .collect();
+
+            state.check_block(&fn_def.body.statements, None);
+            state.pop_scope(0);
+        });
+
+        state.warnings.sort_by_key(|w| w.pos);
+        state.warnings
+    }
+}
+
+/// A variable in scope during linting.
+#[derive(Debug, Clone)]
+struct Var<'a> {
+    /// Variable name.
+    name: &'a str,
+    /// Declaration position.
+    pos: Position,
+    /// Is this a function parameter?
+    is_param: bool,
+    /// Has the variable been read?
+    used: bool,
+    /// Has the variable been captured by a closure?
+    captured: bool,
+}
+
+impl<'a> Var<'a> {
+    /// Create a new [`Var`].
+    #[inline(always)]
+    fn new(name: &'a str, pos: Position, is_param: bool) -> Self {
+        Self {
+            name,
+            pos,
+            is_param,
+            // Variables starting with an underscore are intentionally unused
+            used: name.starts_with('_'),
+            captured: false,
+        }
+    }
+}
+
+/// Mutable state throughout a lint pass.
+struct State<'a, 'e> {
+    /// The [`Linter`].
+    linter: &'a Linter<'e>,
+    /// Warnings collected so far.
+    warnings: Vec<LintWarning>,
+    /// Variables currently in scope.
+    stack: Vec<Var<'a>>,
+    /// Track variable usage?
+    track_vars: bool,
+}
+
+impl<'a> State<'a, '_> {
+    /// Report a warning, unless the lint is allowed.
+    fn warn(&mut self, lint: Lint, pos: Position, message: String) {
+        let level = self.linter.level(lint);
+
+        if level != LintLevel::Allow {
+            self.warnings.push(LintWarning {
+                lint,
+                level,
+                message,
+                pos,
+            });
+        }
+    }
+    /// Check all [`AST`] nodes for lints that do not depend on scopes.
+    #[allow(deprecated)]
+    fn check_nodes(&mut self, ast: &AST) {
+        let engine = self.linter.engine;
+        let mut native_fns = BTreeSet::new();
+        collect_native_fns(&engine.global_namespace, true, &mut native_fns);
+        engine
+            .global_modules
+            .iter()
+            .for_each(|m| collect_native_fns(m, true, &mut native_fns));
+        engine
+            .global_sub_modules
+            .values()
+            .for_each(|m| collect_native_fns(m, false, &mut native_fns));
+
+        #[cfg(not(feature = "no_function"))]
+        let script_fns: BTreeSet<_> = ast
+            .lib()
+            .iter_script_fn()
+            .map(|(_, _, name, params, _)| (name, params))
+            .collect();
+        #[cfg(feature = "no_function")]
+        let script_fns: BTreeSet<(&str, usize)> = Default::default();
+
+        ast.walk(&mut |path| {
+            match path.last().unwrap() {
+                ASTNode::Stmt(Stmt::If(expr, _, _))
+                | ASTNode::Stmt(Stmt::While(expr, _, _))
+                | ASTNode::Stmt(Stmt::Do(_, expr, _, _))
+                    // `loop` is parsed as `while ()`
+                    if expr.is_constant() && !expr.is_unit() =>
+                {
+                    let message = match expr {
+                        Expr::BoolConstant(value, _) => format!("Condition is always {}", value),
+                        _ => "Condition is a constant".to_string(),
+                    };
+                    self.warn(Lint::ConstantCondition, expr.position(), message);
+                }
+
+                ASTNode::Expr(Expr::FnCall(x, pos)) if x.namespace.is_none() => {
+                    let name = x.name.as_str();
+                    let num_args = x.num_args();
+
+                    if name == KEYWORD_EVAL {
+                        self.track_vars = false;
+                    }
+
+                    if num_args == 2
+                        && [OP_EQUALS, "!=", "<", "<=", ">", ">="].contains(&name)
+                        && (x.args.iter().any(Expr::is_unit)
+                            || x.constant_args.iter().any(|(v, _)| v.is::<()>()))
+                    {
+                        self.warn(
+                            Lint::UnitComparison,
+                            *pos,
+                            format!("Comparison with () using '{}'", name),
+                        );
+                    }
+
+                    // Operators and keywords are always available
+                    if !is_valid_identifier(name.chars()) || is_keyword_function(name) {
+                        return true;
+                    }
+
+                    let is_method = x.hash.script.is_some()
+                        && x.hash.native == calc_fn_hash(empty(), name, num_args + 1);
+
+                    let native_args = if is_method { num_args + 1 } else { num_args };
+                    let found = script_fns.contains(&(name, num_args))
+                        || native_fns.contains(&calc_fn_hash(empty(), name, native_args));
+
+                    if !found {
+                        let message = format!(
+                            "{} '{}' with {} parameter{} is not defined",
+                            if is_method { "Method" } else { "Function" },
+                            name,
+                            num_args,
+                            if num_args == 1 { "" } else { "s" }
+                        );
+                        self.warn(Lint::UnknownFunction, *pos, message);
+                    }
+                }
+                _ => (),
+            }
+            true
+        });
+    }
+    /// Declare a variable in the current block.
+    fn declare(&mut self, ident: &'a Ident, block_start: usize) {
+        let name = ident.name.as_str();
+
+        if self.stack[..block_start].iter().any(|v| v.name == name) {
+            self.warn(
+                Lint::ShadowedVariable,
+                ident.pos,
+                format!("Variable '{}' shadows a variable in an outer scope", name),
+            );
+        }
+
+        self.stack.push(Var::new(name, ident.pos, false));
+    }
+    /// Find the variable that a name refers to.
+    fn find_var(&mut self, name: &str) -> Option<&mut Var<'a>> {
+        self.stack.iter_mut().rev().find(|v| v.name == name)
+    }
+    /// Remove all variables declared after a certain point, reporting unused ones.
+    fn pop_scope(&mut self, start: usize) {
+        if !self.track_vars {
+            self.stack.truncate(start);
+            return;
+        }
+
+        for var in self.stack.drain(start..).collect::<Vec<_>>() {
+            match var {
+                Var { used: true, .. } => (),
+                Var {
+                    is_param: true,
+                    name,
+                    pos,
+                    ..
+                } => self.warn(
+                    Lint::UnusedParameter,
+                    pos,
+                    format!("Parameter '{}' is never used", name),
+                ),
+                Var { name, pos, .. } => self.warn(
+                    Lint::UnusedVariable,
+                    pos,
+                    format!("Variable '{}' is never used", name),
+                ),
+            }
+        }
+    }
+    /// Check a statements block.
+    fn check_block(&mut self, statements: &'a [Stmt], var: Option<&'a Ident>) {
+        let block_start = self.stack.len();
+
+        if let Some(ident) = var {
+            self.declare(ident, block_start);
+        }
+
+        let mut terminated = false;
+
+        for (index, stmt) in statements.iter().enumerate() {
+            if terminated {
+                self.warn(
+                    Lint::UnreachableCode,
+                    stmt.position(),
+                    "Unreachable code".to_string(),
+                );
+                // Only report the first unreachable statement
+                terminated = false;
+            }
+
+            self.check_stmt(stmt, block_start);
+            self.check_assignment(statements, index, block_start);
+
+            if stmt.is_control_flow_break() {
+                terminated = index < statements.len() - 1;
+            }
+        }
+
+        self.pop_scope(block_start);
+    }
+    /// Check a statement.
+    fn check_stmt(&mut self, stmt: &'a Stmt, block_start: usize) {
+        match stmt {
+            Stmt::Let(expr, ident, _, _) | Stmt::Const(expr, ident, _, _) => {
+                self.check_expr(expr);
+                self.declare(ident, block_start);
+            }
+            Stmt::If(expr, x, _) => {
+                self.check_expr(expr);
+                self.check_block(&x.0.statements, None);
+                self.check_block(&x.1.statements, None);
+            }
+            Stmt::Switch(expr, x, _) => {
+                self.check_expr(expr);
+                x.0.values()
+                    .for_each(|block| self.check_block(&block.statements, None));
+                self.check_block(&x.1.statements, None);
+            }
+            Stmt::While(expr, body, _) => {
+                self.check_expr(expr);
+                self.check_block(&body.statements, None);
+            }
+            Stmt::Do(body, expr, _, _) => {
+                self.check_block(&body.statements, None);
+                self.check_expr(expr);
+            }
+            Stmt::For(expr, x, _) => {
+                self.check_expr(expr);
+                self.check_block(&x.1.statements, Some(&x.0));
+            }
+            Stmt::Assignment(x, _) => {
+                // Simple assignment to a variable does not read it
+                match x.0 {
+                    Expr::Variable(ref v) if x.1.is_none() && v.1.is_none() => (),
+                    ref lhs => self.check_expr(lhs),
+                }
+                self.check_expr(&x.2);
+            }
+            Stmt::Block(statements, _) => self.check_block(statements, None),
+            Stmt::TryCatch(x, _, _) => {
+                self.check_block(&x.0.statements, None);
+                self.check_block(&x.2.statements, x.1.as_ref());
+            }
+            Stmt::Expr(expr) | Stmt::Return(_, Some(expr), _) => self.check_expr(expr),
+
+            #[cfg(not(feature = "no_module"))]
+            Stmt::Import(expr, _, _) => self.check_expr(expr),
+            #[cfg(not(feature = "no_module"))]
+            Stmt::Export(list, _) => list.iter().for_each(|(ident, _)| {
+                if let Some(var) = self.find_var(&ident.name) {
+                    var.used = true;
+                }
+            }),
+
+            _ => (),
+        }
+    }
+    /// Check an expression.
+    fn check_expr(&mut self, expr: &'a Expr) {
+        match expr {
+            Expr::Variable(x) if x.1.is_none() => {
+                if let Some(var) = self.find_var(&x.2.name) {
+                    var.used = true;
+                }
+            }
+            Expr::Stmt(x) => self.check_block(&x.statements, None),
+            Expr::InterpolatedString(x) | Expr::Array(x, _) => {
+                x.iter().for_each(|e| self.check_expr(e))
+            }
+            Expr::Map(x, _) => x.0.iter().for_each(|(_, e)| self.check_expr(e)),
+            Expr::Index(x, _) | Expr::Dot(x, _) | Expr::And(x, _) | Expr::Or(x, _) => {
+                self.check_expr(&x.lhs);
+                self.check_expr(&x.rhs);
+            }
+            Expr::FnCall(x, _) => {
+                // Variables passed to `curry` are captured by a closure
+                let is_capture = x.name == KEYWORD_FN_PTR_CURRY && x.namespace.is_none();
+
+                x.args.iter().for_each(|e| match e {
+                    Expr::Variable(v) if is_capture && v.1.is_none() => {
+                        if let Some(var) = self.find_var(&v.2.name) {
+                            var.used = true;
+                            var.captured = true;
+                        }
+                    }
+                    _ => self.check_expr(e),
+                });
+            }
+            Expr::Custom(x, _) => x.keywords.iter().for_each(|e| self.check_expr(e)),
+            _ => (),
+        }
+    }
+    /// Check whether the value of a simple assignment to a local variable is ever read.
+    fn check_assignment(&mut self, statements: &'a [Stmt], index: usize, block_start: usize) {
+        if !self.track_vars {
+            return;
+        }
+
+        // An unread initial value is only reported when it is overwritten,
+        // otherwise the variable itself is unused
+        let (name, pos, is_init) = match statements[index] {
+            Stmt::Assignment(ref x, pos) if x.1.is_none() => match x.0 {
+                Expr::Variable(ref v) if v.1.is_none() => (v.2.name.as_str(), pos, false),
+                _ => return,
+            },
+            Stmt::Let(ref expr, ref ident, _, _) if !expr.is_unit() => {
+                (ident.name.as_str(), ident.pos, true)
+            }
+            _ => return,
+        };
+
+        if name.starts_with('_') {
+            return;
+        }
+
+        // Only check variables declared in this block and not captured by closures
+        match self.stack.iter().rposition(|v| v.name == name) {
+            Some(n) if n >= block_start && !self.stack[n].captured => (),
+            _ => return,
+        }
+
+        let mut overwritten = false;
+
+        for stmt in &statements[index + 1..] {
+            match stmt {
+                Stmt::Assignment(x, _) if x.1.is_none() && is_variable(&x.0, name) => {
+                    if expr_reads_var(&x.2, name) {
+                        return;
+                    }
+                    overwritten = true;
+                    break;
+                }
+                Stmt::Let(expr, ident, _, _) | Stmt::Const(expr, ident, _, _)
+                    if ident.name == name =>
+                {
+                    if expr_reads_var(expr, name) {
+                        return;
+                    }
+                    overwritten = true;
+                    break;
+                }
+                _ if stmt_reads_var(stmt, name) => return,
+                _ if stmt.is_control_flow_break() => break,
+                _ => (),
+            }
+        }
+
+        if is_init && !overwritten {
+            return;
+        }
+
+        self.warn(
+            Lint::UnusedAssignment,
+            pos,
+            format!("Value assigned to '{}' is never read", name),
+        );
+    }
+}
+
+/// Collect the hashes (by name and number of parameters) of all functions in a [`Module`] that
+/// are accessible without qualifiers.
+fn collect_native_fns(module: &Module, include_internal: bool, hashes: &mut BTreeSet<u64>) {
+    module
+        .iter_fn()
+        .filter(|f| include_internal || f.namespace == FnNamespace::Global)
+        .for_each(|f| {
+            hashes.insert(calc_fn_hash(empty(), &f.name, f.params));
+        });
+    module
+        .iter_sub_modules()
+        .for_each(|(_, m)| collect_native_fns(&m, false, hashes));
+}
+
+/// Is the function name a keyword that is handled by the [`Engine`] itself?
+fn is_keyword_function(name: &str) -> bool {
+    use crate::engine::*;
+
+    [
+        KEYWORD_PRINT,
+        KEYWORD_DEBUG,
+        KEYWORD_TYPE_OF,
+        KEYWORD_EVAL,
+        KEYWORD_FN_PTR,
+        KEYWORD_FN_PTR_CALL,
+        KEYWORD_FN_PTR_CURRY,
+        #[cfg(not(feature = "no_closure"))]
+        KEYWORD_IS_SHARED,
+        KEYWORD_IS_DEF_VAR,
+        #[cfg(not(feature = "no_function"))]
+        KEYWORD_IS_DEF_FN,
+    ]
+    .contains(&name)
+}
+
+/// Is the expression a simple access to a particular variable?
+#[inline(always)]
+fn is_variable(expr: &Expr, name: &str) -> bool {
+    match expr {
+        Expr::Variable(x) => x.1.is_none() && x.2.name == name,
+        _ => false,
+    }
+}
+
+/// Does the expression possibly read a particular variable?
+fn expr_reads_var(expr: &Expr, name: &str) -> bool {
+    !expr.walk(
+        &mut Default::default(),
+        &mut |path| !matches!(path.last().unwrap(), ASTNode::Expr(e) if is_variable(e, name)),
+    )
+}
+
+/// Does the statement possibly read a particular variable?
+fn stmt_reads_var(stmt: &Stmt, name: &str) -> bool {
+    match stmt {
+        #[cfg(not(feature = "no_module"))]
+        Stmt::Export(list, _) if list.iter().any(|(ident, _)| ident.name == name) => true,
+        _ => !stmt.walk(
+            &mut Default::default(),
+            &mut |path| !matches!(path.last().unwrap(), ASTNode::Expr(e) if is_variable(e, name)),
+        ),
+    }
+}
@@ -1425,10 +1425,16 @@ impl Module {
         // Extra modules left in the scope become sub-modules
         let mut func_mods: crate::engine::Imports = Default::default();
 
-        mods.into_iter().skip(orig_mods_len).for_each(|(alias, m)| {
-            func_mods.push(alias.clone(), m.clone());
-            module.set_sub_module(alias, m);
-        });
+        mods.iter_lazy_raw()
+            .skip(orig_mods_len)
+            .for_each(|(alias, m, lazy)| {
+                func_mods.push_raw(alias.clone(), m.clone(), lazy.cloned());
+
+                // Modules imported lazily are not resolved yet
+                if lazy.is_none() {
+                    module.set_sub_module(alias.clone(), m.clone());
+                }
+            });
 
         // Non-private functions defined become module functions
         #[cfg(not(feature = "no_function"))]
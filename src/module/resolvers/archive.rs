@@ -0,0 +1,453 @@
+use super::MemoryModuleResolver;
+use crate::stdlib::{
+    boxed::Box,
+    convert::TryInto,
+    format,
+    io::{Error as IoError, ErrorKind},
+    ops::{Deref, DerefMut},
+    string::{String, ToString},
+    vec::Vec,
+};
+use crate::{Engine, EvalAltResult, Module, ModuleResolver, Position, Shared, AST};
+
+/// A [module][Module] resolution service that loads [module][Module] scripts from a `zip` or `tar`
+/// archive (optionally compressed with `gzip`) read from bytes.
+///
+/// All script files in the archive are loaded into a [`MemoryModuleResolver`] when the archive is
+/// read, and paths are resolved the same way (i.e. relative to the directory of the script doing
+/// the loading). The underlying [`MemoryModuleResolver`] is accessible (via [`Deref`]) to change
+/// settings such as the base path, file extension and cache.
+///
+/// `zip` archives with entries that are stored or compressed with `deflate` are supported.
+/// Files that are not valid UTF-8 are skipped.
+///
+/// To guard against archive bombs, the uncompressed size of each entry is limited to 16MB and
+/// the total size of all entries to 64MB. Use [`from_bytes_with_limits`][Self::from_bytes_with_limits]
+/// etc. to change these limits.
+///
+/// Exported under the `archive` feature only.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+/// use rhai::Engine;
+/// use rhai::module_resolvers::ArchiveModuleResolver;
+///
+/// let bytes = std::fs::read("scripts.zip").unwrap();
+///
+/// let resolver = ArchiveModuleResolver::from_bytes(&bytes)?;
+///
+/// let mut engine = Engine::new();
+/// engine.set_module_resolver(resolver);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct ArchiveModuleResolver(MemoryModuleResolver);
+
+/// Make an error for an invalid archive.
+fn invalid_archive(msg: &str) -> Box<EvalAltResult> {
+    EvalAltResult::ErrorSystem(
+        "Invalid archive".to_string(),
+        IoError::new(ErrorKind::InvalidData, msg.to_string()).into(),
+    )
+    .into()
+}
+
+/// Default maximum uncompressed size of each entry in an archive.
+const MAX_ENTRY_SIZE: usize = 16 * 1024 * 1024;
+
+/// Default maximum total uncompressed size of all entries in an archive.
+const MAX_TOTAL_SIZE: usize = 64 * 1024 * 1024;
+
+/// Limits on the uncompressed sizes of the entries in an archive.  Zero means unlimited.
+#[derive(Debug, Clone, Copy)]
+struct Limits {
+    /// Maximum size of each entry.
+    max_entry_size: usize,
+    /// Maximum total size of all entries.
+    max_total_size: usize,
+    /// Total size of all entries so far.
+    total_size: usize,
+}
+
+impl Limits {
+    /// Create a new [`Limits`].
+    fn new(max_entry_size: usize, max_total_size: usize) -> Self {
+        Self {
+            max_entry_size,
+            max_total_size,
+            total_size: 0,
+        }
+    }
+    /// Add an entry with a declared uncompressed size, failing if it exceeds either limit.
+    fn add(&mut self, name: &str, size: usize) -> Result<(), Box<EvalAltResult>> {
+        if self.max_entry_size > 0 && size > self.max_entry_size {
+            return Err(EvalAltResult::ErrorDataTooLarge(
+                format!("Size of archive entry '{}'", name),
+                Position::NONE,
+            )
+            .into());
+        }
+
+        self.total_size = self.total_size.saturating_add(size);
+
+        if self.max_total_size > 0 && self.total_size > self.max_total_size {
+            return Err(EvalAltResult::ErrorDataTooLarge(
+                "Total size of archive entries".to_string(),
+                Position::NONE,
+            )
+            .into());
+        }
+
+        Ok(())
+    }
+}
+
+/// Decompress `deflate` data, failing with the error from `too_large` if it is larger than
+/// `max_size` bytes (zero means unlimited).
+fn inflate(
+    data: &[u8],
+    max_size: usize,
+    too_large: impl FnOnce() -> Box<EvalAltResult>,
+) -> Result<Vec<u8>, Box<EvalAltResult>> {
+    use miniz_oxide::inflate::{decompress_to_vec_with_limit, TINFLStatus};
+
+    let max_size = if max_size == 0 { usize::MAX } else { max_size };
+
+    decompress_to_vec_with_limit(data, max_size).map_err(|err| match err.status {
+        TINFLStatus::HasMoreOutput => too_large(),
+        _ => invalid_archive("bad compressed data"),
+    })
+}
+
+/// Read a little-endian [`u16`] at an offset.
+fn read_u16(bytes: &[u8], offset: usize) -> Option<usize> {
+    let b = bytes.get(offset..offset + 2)?;
+    Some(u16::from_le_bytes(b.try_into().ok()?) as usize)
+}
+
+/// Read a little-endian [`u32`] at an offset.
+fn read_u32(bytes: &[u8], offset: usize) -> Option<usize> {
+    let b = bytes.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes(b.try_into().ok()?) as usize)
+}
+
+/// Read a NUL-terminated string field in a `tar` header.
+fn tar_field(field: &[u8]) -> &[u8] {
+    let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    &field[..end]
+}
+
+/// Parse a numeric (octal) field in a `tar` header.
+fn tar_number(field: &[u8]) -> Option<usize> {
+    let s = crate::stdlib::str::from_utf8(tar_field(field)).ok()?.trim();
+
+    if s.is_empty() {
+        Some(0)
+    } else {
+        usize::from_str_radix(s, 8).ok()
+    }
+}
+
+/// Decompress `gzip` data, failing if it is larger than `max_size` bytes (zero means unlimited).
+fn gunzip(bytes: &[u8], max_size: usize) -> Result<Vec<u8>, Box<EvalAltResult>> {
+    const FHCRC: u8 = 0x02;
+    const FEXTRA: u8 = 0x04;
+    const FNAME: u8 = 0x08;
+    const FCOMMENT: u8 = 0x10;
+
+    let err = || invalid_archive("bad gzip header");
+
+    if bytes.len() < 18 || bytes[2] != 8 {
+        return Err(err());
+    }
+
+    let flags = bytes[3];
+    let mut offset = 10;
+
+    if flags & FEXTRA != 0 {
+        offset += 2 + read_u16(bytes, offset).ok_or_else(err)?;
+    }
+    for flag in [FNAME, FCOMMENT].iter() {
+        if flags & flag != 0 {
+            let rest = bytes.get(offset..).ok_or_else(err)?;
+            offset += rest.iter().position(|&b| b == 0).ok_or_else(err)? + 1;
+        }
+    }
+    if flags & FHCRC != 0 {
+        offset += 2;
+    }
+
+    let data = bytes.get(offset..).ok_or_else(err)?;
+
+    inflate(data, max_size, || {
+        EvalAltResult::ErrorDataTooLarge("Size of decompressed archive".to_string(), Position::NONE)
+            .into()
+    })
+}
+
+impl ArchiveModuleResolver {
+    /// Create a new [`ArchiveModuleResolver`] from the bytes of a `zip`, `tar` or `tar.gz` archive,
+    /// detecting the format automatically.
+    #[inline(always)]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Box<EvalAltResult>> {
+        Self::from_bytes_with_limits(bytes, MAX_ENTRY_SIZE, MAX_TOTAL_SIZE)
+    }
+    /// Create a new [`ArchiveModuleResolver`] from the bytes of a `zip`, `tar` or `tar.gz` archive,
+    /// detecting the format automatically, with limits on the uncompressed size of each entry and
+    /// the total size of all entries.  Zero means unlimited.
+    ///
+    /// Entries declared to be larger than the limits are rejected without being decompressed.
+    /// A `tar.gz` archive as a whole (including `tar` headers) is also limited to `max_total_size`
+    /// when decompressed.
+    pub fn from_bytes_with_limits(
+        bytes: &[u8],
+        max_entry_size: usize,
+        max_total_size: usize,
+    ) -> Result<Self, Box<EvalAltResult>> {
+        match bytes {
+            [0x50, 0x4b, 0x03, 0x04, ..] | [0x50, 0x4b, 0x05, 0x06, ..] => {
+                Self::from_zip_with_limits(bytes, max_entry_size, max_total_size)
+            }
+            [0x1f, 0x8b, ..] => Self::from_tar_with_limits(
+                &gunzip(bytes, max_total_size)?,
+                max_entry_size,
+                max_total_size,
+            ),
+            _ => Self::from_tar_with_limits(bytes, max_entry_size, max_total_size),
+        }
+    }
+    /// Create a new [`ArchiveModuleResolver`] from the bytes of a `tar` archive.
+    #[inline(always)]
+    pub fn from_tar(bytes: &[u8]) -> Result<Self, Box<EvalAltResult>> {
+        Self::from_tar_with_limits(bytes, MAX_ENTRY_SIZE, MAX_TOTAL_SIZE)
+    }
+    /// Create a new [`ArchiveModuleResolver`] from the bytes of a `tar` archive, with limits on the
+    /// size of each entry and the total size of all entries.  Zero means unlimited.
+    pub fn from_tar_with_limits(
+        bytes: &[u8],
+        max_entry_size: usize,
+        max_total_size: usize,
+    ) -> Result<Self, Box<EvalAltResult>> {
+        const BLOCK: usize = 512;
+
+        let mut limits = Limits::new(max_entry_size, max_total_size);
+        let mut resolver = MemoryModuleResolver::new();
+        let mut offset = 0;
+        let mut long_name: Option<String> = None;
+
+        while let Some(header) = bytes.get(offset..offset + BLOCK) {
+            // End of archive
+            if header.iter().all(|&b| b == 0) {
+                break;
+            }
+
+            let size =
+                tar_number(&header[124..136]).ok_or_else(|| invalid_archive("bad tar header"))?;
+            let data = bytes
+                .get(offset + BLOCK..offset + BLOCK + size)
+                .ok_or_else(|| invalid_archive("truncated tar entry"))?;
+
+            offset += BLOCK + size + (BLOCK - size % BLOCK) % BLOCK;
+
+            let name = match long_name.take() {
+                Some(name) => name,
+                None => {
+                    let name = String::from_utf8_lossy(tar_field(&header[0..100]));
+
+                    // ustar prefix
+                    match tar_field(&header[345..500]) {
+                        prefix if &header[257..262] == b"ustar" && !prefix.is_empty() => {
+                            format!("{}/{}", String::from_utf8_lossy(prefix), name)
+                        }
+                        _ => name.to_string(),
+                    }
+                }
+            };
+
+            match header[156] {
+                // Regular file
+                b'0' | 0 => {
+                    limits.add(&name, size)?;
+
+                    if let Ok(script) = crate::stdlib::str::from_utf8(data) {
+                        resolver.insert(name, script);
+                    }
+                }
+                // GNU long file name of the next entry
+                b'L' => long_name = Some(String::from_utf8_lossy(tar_field(data)).to_string()),
+                // PAX extended header with the path of the next entry
+                b'x' => {
+                    long_name = String::from_utf8_lossy(data)
+                        .lines()
+                        .filter_map(|line| line.split_once(' ').map(|(_, record)| record))
+                        .find_map(|record| record.strip_prefix("path="))
+                        .map(|path| path.to_string());
+                }
+                // Directories, links etc.
+                _ => (),
+            }
+        }
+
+        Ok(Self(resolver))
+    }
+    /// Create a new [`ArchiveModuleResolver`] from the bytes of a `zip` archive.
+    #[inline(always)]
+    pub fn from_zip(bytes: &[u8]) -> Result<Self, Box<EvalAltResult>> {
+        Self::from_zip_with_limits(bytes, MAX_ENTRY_SIZE, MAX_TOTAL_SIZE)
+    }
+    /// Create a new [`ArchiveModuleResolver`] from the bytes of a `zip` archive, with limits on the
+    /// uncompressed size of each entry and the total size of all entries.  Zero means unlimited.
+    ///
+    /// Entries declared to be larger than the limits are rejected without being decompressed.
+    pub fn from_zip_with_limits(
+        bytes: &[u8],
+        max_entry_size: usize,
+        max_total_size: usize,
+    ) -> Result<Self, Box<EvalAltResult>> {
+        const END_OF_CENTRAL_DIR: &[u8] = &[0x50, 0x4b, 0x05, 0x06];
+        const CENTRAL_DIR_HEADER: &[u8] = &[0x50, 0x4b, 0x01, 0x02];
+        const LOCAL_FILE_HEADER: &[u8] = &[0x50, 0x4b, 0x03, 0x04];
+        const STORED: usize = 0;
+        const DEFLATED: usize = 8;
+
+        let err = |msg: &'static str| move || invalid_archive(msg);
+
+        // The end of central directory record is at the end, followed by a comment
+        let end = (0..=bytes.len().saturating_sub(22))
+            .rev()
+            .take(0x10000)
+            .find(|&n| bytes[n..].starts_with(END_OF_CENTRAL_DIR))
+            .ok_or_else(err("missing zip central directory"))?;
+
+        let entries = read_u16(bytes, end + 10).ok_or_else(err("bad zip central directory"))?;
+        let mut offset = read_u32(bytes, end + 16).ok_or_else(err("bad zip central directory"))?;
+
+        let mut limits = Limits::new(max_entry_size, max_total_size);
+        let mut resolver = MemoryModuleResolver::new();
+
+        for _ in 0..entries {
+            let bad_entry = err("bad zip entry");
+
+            if !bytes
+                .get(offset..)
+                .unwrap_or_default()
+                .starts_with(CENTRAL_DIR_HEADER)
+            {
+                return Err(bad_entry());
+            }
+
+            let method = read_u16(bytes, offset + 10).ok_or_else(bad_entry)?;
+            let compressed_size = read_u32(bytes, offset + 20).ok_or_else(bad_entry)?;
+            let size = read_u32(bytes, offset + 24).ok_or_else(bad_entry)?;
+            let name_len = read_u16(bytes, offset + 28).ok_or_else(bad_entry)?;
+            let extra_len = read_u16(bytes, offset + 30).ok_or_else(bad_entry)?;
+            let comment_len = read_u16(bytes, offset + 32).ok_or_else(bad_entry)?;
+            let local_offset = read_u32(bytes, offset + 42).ok_or_else(bad_entry)?;
+            let name = bytes
+                .get(offset + 46..offset + 46 + name_len)
+                .ok_or_else(bad_entry)?;
+            let name = String::from_utf8_lossy(name).to_string();
+
+            offset += 46 + name_len + extra_len + comment_len;
+
+            // Skip directories
+            if name.ends_with('/') {
+                continue;
+            }
+
+            limits.add(&name, size)?;
+
+            if !bytes
+                .get(local_offset..)
+                .unwrap_or_default()
+                .starts_with(LOCAL_FILE_HEADER)
+            {
+                return Err(bad_entry());
+            }
+
+            let data_offset = local_offset
+                + 30
+                + read_u16(bytes, local_offset + 26).ok_or_else(bad_entry)?
+                + read_u16(bytes, local_offset + 28).ok_or_else(bad_entry)?;
+            let data = bytes
+                .get(data_offset..data_offset + compressed_size)
+                .ok_or_else(bad_entry)?;
+
+            // Never decompress more than the declared size, which is already within the limits
+            let data = match method {
+                STORED => data.to_vec(),
+                DEFLATED => inflate(data, size.max(1), bad_entry)?,
+                _ => return Err(invalid_archive("unsupported zip compression method")),
+            };
+
+            if data.len() != size {
+                return Err(bad_entry());
+            }
+
+            if let Ok(script) = String::from_utf8(data) {
+                resolver.insert(name, script);
+            }
+        }
+
+        Ok(Self(resolver))
+    }
+    /// Get the underlying [`MemoryModuleResolver`].
+    #[inline(always)]
+    pub fn into_inner(self) -> MemoryModuleResolver {
+        self.0
+    }
+}
+
+impl Deref for ArchiveModuleResolver {
+    type Target = MemoryModuleResolver;
+
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for ArchiveModuleResolver {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl ModuleResolver for ArchiveModuleResolver {
+    #[inline(always)]
+    fn resolve(
+        &self,
+        engine: &Engine,
+        source_path: Option<&str>,
+        path: &str,
+        pos: Position,
+    ) -> Result<Shared<Module>, Box<EvalAltResult>> {
+        self.0.resolve(engine, source_path, path, pos)
+    }
+
+    #[inline(always)]
+    fn resolve_ast(
+        &self,
+        engine: &Engine,
+        source_path: Option<&str>,
+        path: &str,
+        pos: Position,
+    ) -> Option<Result<AST, Box<EvalAltResult>>> {
+        self.0.resolve_ast(engine, source_path, path, pos)
+    }
+
+    #[inline(always)]
+    fn resolve_script(
+        &self,
+        engine: &Engine,
+        source_path: Option<&str>,
+        path: &str,
+        pos: Position,
+    ) -> Option<Result<String, Box<EvalAltResult>>> {
+        self.0.resolve_script(engine, source_path, path, pos)
+    }
+}
@@ -1,5 +1,5 @@
-use crate::stdlib::{boxed::Box, ops::AddAssign, vec::Vec};
-use crate::{Engine, EvalAltResult, Module, ModuleResolver, Position, Shared};
+use crate::stdlib::{boxed::Box, ops::AddAssign, string::String, vec::Vec};
+use crate::{Engine, EvalAltResult, Module, ModuleResolver, Position, Shared, AST};
 
 /// [Module] resolution service that holds a collection of module resolvers,
 /// to be searched in sequential order.
@@ -115,18 +115,56 @@ impl ModuleResolver for ModuleResolversCollection {
         path: &str,
         pos: Position,
     ) -> Result<Shared<Module>, Box<EvalAltResult>> {
+        let mut locations = Vec::new();
+
         for resolver in self.0.iter() {
             match resolver.resolve(engine, source_path, path, pos) {
                 Ok(module) => return Ok(module),
                 Err(err) => match *err {
-                    EvalAltResult::ErrorModuleNotFound(_, _) => continue,
+                    EvalAltResult::ErrorModuleNotFound(_, tried, _) => locations.extend(tried),
                     EvalAltResult::ErrorInModule(_, err, _) => return Err(err),
-                    _ => panic!("ModuleResolver::resolve returns error that is not ErrorModuleNotFound or ErrorInModule"),
+                    err => return Err(err.into()),
                 },
             }
         }
 
-        EvalAltResult::ErrorModuleNotFound(path.into(), pos).into()
+        EvalAltResult::ErrorModuleNotFound(path.into(), locations, pos).into()
+    }
+
+    /// Resolve an `AST` based on a path string.
+    ///
+    /// The first [module resolver][ModuleResolver] that finds the [module][Module] is used.
+    fn resolve_ast(
+        &self,
+        engine: &Engine,
+        source_path: Option<&str>,
+        path: &str,
+        pos: Position,
+    ) -> Option<Result<AST, Box<EvalAltResult>>> {
+        self.0.iter().find_map(|resolver| {
+            match resolver.resolve_ast(engine, source_path, path, pos)? {
+                Err(err) if matches!(*err, EvalAltResult::ErrorModuleNotFound(_, _, _)) => None,
+                result => Some(result),
+            }
+        })
+    }
+
+    /// Resolve the script text of a module based on a path string.
+    ///
+    /// The first [module resolver][ModuleResolver] that finds the [module][Module] is used.
+    fn resolve_script(
+        &self,
+        engine: &Engine,
+        source_path: Option<&str>,
+        path: &str,
+        pos: Position,
+    ) -> Option<Result<String, Box<EvalAltResult>>> {
+        self.0.iter().find_map(|resolver| {
+            match resolver.resolve_script(engine, source_path, path, pos)? {
+                Err(err) if matches!(*err, EvalAltResult::ErrorModuleNotFound(_, _, _)) => None,
+                result => Some(result),
+            }
+        })
     }
 }
 
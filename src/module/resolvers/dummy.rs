@@ -44,6 +44,6 @@ impl ModuleResolver for DummyModuleResolver {
         path: &str,
         pos: Position,
     ) -> Result<Shared<Module>, Box<EvalAltResult>> {
-        EvalAltResult::ErrorModuleNotFound(path.into(), pos).into()
+        EvalAltResult::ErrorModuleNotFound(path.into(), Default::default(), pos).into()
     }
 }
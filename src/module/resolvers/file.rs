@@ -1,9 +1,20 @@
+use super::{loader_id, ImportChain, LoaderId, PackageManifest};
+use crate::fn_native::{Locked, SendSync};
 use crate::stdlib::{
+    borrow::Cow,
     boxed::Box,
-    collections::BTreeMap,
-    io::Error as IoError,
-    path::{Path, PathBuf},
+    collections::{BTreeMap, BTreeSet},
+    fmt, format, fs,
+    hash::{Hash, Hasher},
+    io::{Error as IoError, ErrorKind},
+    ops::DerefMut,
+    path::{Component, Path, PathBuf},
+    string::{String, ToString},
+    time::SystemTime,
+    vec,
+    vec::Vec,
 };
+use crate::utils::get_hasher;
 use crate::{Engine, EvalAltResult, Identifier, Module, ModuleResolver, Position, Shared};
 
 pub const RHAI_SCRIPT_EXTENSION: &str = "rhai";
@@ -24,6 +35,34 @@ pub const RHAI_SCRIPT_EXTENSION: &str = "rhai";
 /// script are available, evan `private` ones.  In other words, functions defined in a module script
 /// can always cross-call each other.
 ///
+/// ## Search Paths and Packages
+///
+/// A relative path is first resolved under the base path or, if there is no base path, under the
+/// directory of the script doing the loading. If no script file exists there, it is then resolved
+/// under each search path added via [`add_search_path`][FileModuleResolver::add_search_path], in
+/// order.
+///
+/// Packages of scripts, each with a [`rhai.toml`][PackageManifest] manifest in its root
+/// directory, are added via [`add_package`][FileModuleResolver::add_package], together with all
+/// the packages they depend on. A relative path starting with the name of a package is finally
+/// resolved under the root directory of the package, e.g. `import "utils/strings"` loads
+/// `strings.rhai` in the root of the `utils` package, while `import "utils"` loads its entry
+/// [module][Module].
+///
+/// If no script file is found, the [`ErrorModuleNotFound`][EvalAltResult::ErrorModuleNotFound]
+/// error lists all the file paths tried.
+///
+/// ## Hot Reloading
+///
+/// When hot reloading is enabled (via [`enable_hot_reload`][FileModuleResolver::enable_hot_reload]),
+/// each time a cached [module][Module] is resolved, its script file and the script files of all the
+/// modules it imports (directly or indirectly) are checked for modifications (by modification time,
+/// then by content hash).
+///
+/// A modified [module][Module] is recompiled, and all cached [modules][Module] importing it are
+/// removed from the cache so they are recompiled when next resolved. A callback set via
+/// [`on_change`][FileModuleResolver::on_change] is notified of the path of each of these modules.
+///
 /// # Example
 ///
 /// ```
@@ -38,16 +77,90 @@ pub const RHAI_SCRIPT_EXTENSION: &str = "rhai";
 ///
 /// engine.set_module_resolver(resolver);
 /// ```
-#[derive(Debug)]
 pub struct FileModuleResolver {
     base_path: Option<PathBuf>,
+    search_paths: Vec<PathBuf>,
+    packages: BTreeMap<Identifier, Package>,
     extension: Identifier,
     cache_enabled: bool,
+    hot_reload: bool,
+    on_change: Option<OnModuleChangeCallback>,
+    cache: Locked<BTreeMap<PathBuf, CachedModule>>,
+    /// Script files being loaded, with the script files they import.
+    ///
+    /// Under the `sync` feature, script files loaded on different threads are tracked separately.
+    loading: Locked<Vec<(LoaderId, PathBuf, Vec<PathBuf>)>>,
+    /// Script files being loaded, to detect cyclic imports.
+    chain: ImportChain,
+}
 
-    #[cfg(not(feature = "sync"))]
-    cache: crate::stdlib::cell::RefCell<BTreeMap<PathBuf, Shared<Module>>>,
-    #[cfg(feature = "sync")]
-    cache: crate::stdlib::sync::RwLock<BTreeMap<PathBuf, Shared<Module>>>,
+/// A callback function notified of the path of a changed module.
+#[cfg(not(feature = "sync"))]
+pub type OnModuleChangeCallback = Box<dyn Fn(&Path) + 'static>;
+/// A callback function notified of the path of a changed module.
+#[cfg(feature = "sync")]
+pub type OnModuleChangeCallback = Box<dyn Fn(&Path) + Send + Sync + 'static>;
+
+/// A package of script files.
+#[derive(Debug, Clone)]
+struct Package {
+    /// Root directory of the package.
+    root: PathBuf,
+    /// Path of the entry module, relative to the root directory.
+    entry: PathBuf,
+}
+
+/// Modification time, size and content hash of a script file.
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct FileStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+    hash: u64,
+}
+
+impl FileStamp {
+    /// Read the stamp of a script file.
+    fn read(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        let contents = fs::read(path).ok()?;
+
+        Some(Self {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+            hash: Self::hash(&contents),
+        })
+    }
+    /// Calculate the hash of the contents of a script file.
+    fn hash(contents: &[u8]) -> u64 {
+        let hasher = &mut get_hasher();
+        contents.hash(hasher);
+        hasher.finish()
+    }
+}
+
+/// A [module][Module] in the cache.
+#[derive(Debug, Clone)]
+struct CachedModule {
+    module: Shared<Module>,
+    /// Stamp of the script file when loaded (hot reloading only).
+    stamp: Option<FileStamp>,
+    /// Script files imported while loading the module (hot reloading only).
+    dependencies: Vec<PathBuf>,
+}
+
+impl fmt::Debug for FileModuleResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FileModuleResolver")
+            .field("base_path", &self.base_path)
+            .field("search_paths", &self.search_paths)
+            .field("packages", &self.packages)
+            .field("extension", &self.extension)
+            .field("cache_enabled", &self.cache_enabled)
+            .field("hot_reload", &self.hot_reload)
+            .field("on_change", &self.on_change.is_some())
+            .field("cache", &self.cache)
+            .finish()
+    }
 }
 
 impl Default for FileModuleResolver {
@@ -120,9 +233,15 @@ impl FileModuleResolver {
     pub fn new_with_extension(extension: impl Into<Identifier>) -> Self {
         Self {
             base_path: None,
+            search_paths: Default::default(),
+            packages: Default::default(),
             extension: extension.into(),
             cache_enabled: true,
+            hot_reload: false,
+            on_change: None,
             cache: Default::default(),
+            loading: Default::default(),
+            chain: Default::default(),
         }
     }
 
@@ -148,9 +267,15 @@ impl FileModuleResolver {
     ) -> Self {
         Self {
             base_path: Some(path.into()),
+            search_paths: Default::default(),
+            packages: Default::default(),
             extension: extension.into(),
             cache_enabled: true,
+            hot_reload: false,
+            on_change: None,
             cache: Default::default(),
+            loading: Default::default(),
+            chain: Default::default(),
         }
     }
 
@@ -166,6 +291,134 @@ impl FileModuleResolver {
         self
     }
 
+    /// Get the search paths for script files.
+    #[inline(always)]
+    pub fn search_paths(&self) -> &[PathBuf] {
+        &self.search_paths
+    }
+    /// Add a search path for script files.
+    ///
+    /// Search paths are tried in the order they are added.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rhai::Engine;
+    /// use rhai::module_resolvers::FileModuleResolver;
+    ///
+    /// let mut resolver = FileModuleResolver::new();
+    ///
+    /// resolver
+    ///     .add_search_path("./lib")
+    ///     .add_search_path("/usr/share/rhai");
+    ///
+    /// let mut engine = Engine::new();
+    /// engine.set_module_resolver(resolver);
+    /// ```
+    #[inline(always)]
+    pub fn add_search_path(&mut self, path: impl Into<PathBuf>) -> &mut Self {
+        self.search_paths.push(path.into());
+        self
+    }
+
+    /// Add a package of script files, together with all the packages it depends on (directly or
+    /// indirectly), given the path to its [`rhai.toml`][PackageManifest] manifest or to its root
+    /// directory containing the manifest.
+    ///
+    /// Dependencies are added under the names they are declared with in the manifest.
+    ///
+    /// An error is returned if a manifest cannot be read or is invalid, or if a package with the
+    /// same name but a different root directory has already been added.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::Engine;
+    /// use rhai::module_resolvers::FileModuleResolver;
+    ///
+    /// let mut resolver = FileModuleResolver::new();
+    ///
+    /// // Add the 'app' package and its dependencies
+    /// resolver.add_package("./app/rhai.toml")?;
+    ///
+    /// let mut engine = Engine::new();
+    /// engine.set_module_resolver(resolver);
+    ///
+    /// // Load 'strings.rhai' from the root of the 'utils' package
+    /// engine.consume(r#"import "utils/strings" as strings;"#)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn add_package(&mut self, path: impl AsRef<Path>) -> Result<&mut Self, Box<EvalAltResult>> {
+        let path = path.as_ref();
+
+        let root = if path.is_dir() {
+            path
+        } else {
+            path.parent().unwrap_or_else(|| Path::new(""))
+        };
+
+        let manifest = PackageManifest::load(path)?;
+        let name = manifest.name.clone();
+
+        self.insert_package(name, root.into(), manifest)?;
+        Ok(self)
+    }
+    /// Add a package and its dependencies.
+    fn insert_package(
+        &mut self,
+        name: String,
+        root: PathBuf,
+        manifest: PackageManifest,
+    ) -> Result<(), Box<EvalAltResult>> {
+        let canonical = |path: &Path| path.canonicalize().unwrap_or_else(|_| path.into());
+
+        if let Some(package) = self.packages.get(name.as_str()) {
+            // A package can be depended on by multiple packages
+            if canonical(&package.root) == canonical(&root) {
+                return Ok(());
+            }
+
+            return Err(EvalAltResult::ErrorSystem(
+                format!("Duplicated package '{}'", name),
+                IoError::new(
+                    ErrorKind::InvalidInput,
+                    format!(
+                        "'{}' conflicts with '{}'",
+                        root.to_string_lossy(),
+                        package.root.to_string_lossy()
+                    ),
+                )
+                .into(),
+            )
+            .into());
+        }
+
+        self.packages.insert(
+            name.into(),
+            Package {
+                root: root.clone(),
+                entry: manifest.entry.into(),
+            },
+        );
+
+        for (name, path) in manifest.dependencies {
+            let root = root.join(path);
+            let manifest = PackageManifest::load(&root)?;
+            self.insert_package(name, root, manifest)?;
+        }
+
+        Ok(())
+    }
+    /// Get an iterator of the names and root directories of all packages.
+    #[inline(always)]
+    pub fn packages(&self) -> impl Iterator<Item = (&str, &Path)> {
+        self.packages
+            .iter()
+            .map(|(name, package)| (name.as_str(), package.root.as_path()))
+    }
+
     /// Get the script file extension.
     #[inline(always)]
     pub fn extension(&self) -> &str {
@@ -191,6 +444,44 @@ impl FileModuleResolver {
         self.cache_enabled
     }
 
+    /// Enable/disable hot reloading of modified script files.
+    ///
+    /// Hot reloading only takes effect when the cache is enabled.
+    #[inline(always)]
+    pub fn enable_hot_reload(&mut self, enable: bool) -> &mut Self {
+        self.hot_reload = enable;
+        self
+    }
+    /// Is hot reloading enabled?
+    #[inline(always)]
+    pub fn is_hot_reload_enabled(&self) -> bool {
+        self.hot_reload
+    }
+    /// Register a callback that is notified, during hot reloading, of the path of each modified
+    /// [module][Module] and of each cached [module][Module] importing it (directly or indirectly)
+    /// that is removed from the cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rhai::Engine;
+    /// use rhai::module_resolvers::FileModuleResolver;
+    ///
+    /// let mut resolver = FileModuleResolver::new_with_path("./scripts");
+    ///
+    /// resolver
+    ///     .enable_hot_reload(true)
+    ///     .on_change(|path| println!("Module changed: {}", path.to_string_lossy()));
+    ///
+    /// let mut engine = Engine::new();
+    /// engine.set_module_resolver(resolver);
+    /// ```
+    #[inline(always)]
+    pub fn on_change(&mut self, callback: impl Fn(&Path) + SendSync + 'static) -> &mut Self {
+        self.on_change = Some(Box::new(callback));
+        self
+    }
+
     /// Is a particular path cached?
     #[inline(always)]
     pub fn is_cached(&self, path: &str, source_path: Option<&str>) -> bool {
@@ -200,18 +491,12 @@ impl FileModuleResolver {
 
         let file_path = self.get_file_path(path, source_path);
 
-        #[cfg(not(feature = "sync"))]
-        return self.cache.borrow_mut().contains_key(&file_path);
-        #[cfg(feature = "sync")]
-        return self.cache.write().unwrap().contains_key(&file_path);
+        self.cache().contains_key(&file_path)
     }
     /// Empty the internal cache.
     #[inline(always)]
     pub fn clear_cache(&mut self) {
-        #[cfg(not(feature = "sync"))]
-        self.cache.borrow_mut().clear();
-        #[cfg(feature = "sync")]
-        self.cache.write().unwrap().clear();
+        self.cache().clear();
     }
     /// Remove the specified path from internal cache.
     ///
@@ -224,40 +509,214 @@ impl FileModuleResolver {
     ) -> Option<Shared<Module>> {
         let file_path = self.get_file_path(path, source_path);
 
+        self.cache().remove_entry(&file_path).map(|(_, v)| v.module)
+    }
+    /// Get a mutable reference to the internal cache.
+    #[inline(always)]
+    fn cache(&self) -> impl DerefMut<Target = BTreeMap<PathBuf, CachedModule>> + '_ {
         #[cfg(not(feature = "sync"))]
-        return self
-            .cache
-            .borrow_mut()
-            .remove_entry(&file_path)
-            .map(|(_, v)| v);
+        return self.cache.borrow_mut();
         #[cfg(feature = "sync")]
-        return self
-            .cache
-            .write()
-            .unwrap()
-            .remove_entry(&file_path)
-            .map(|(_, v)| v);
+        return self.cache.write().unwrap();
     }
-    /// Construct a full file path.
-    fn get_file_path(&self, path: &str, source_path: Option<&str>) -> PathBuf {
-        let path = Path::new(path);
+    /// Get a mutable reference to the stack of script files being loaded.
+    #[inline(always)]
+    fn loading(&self) -> impl DerefMut<Target = Vec<(LoaderId, PathBuf, Vec<PathBuf>)>> + '_ {
+        #[cfg(not(feature = "sync"))]
+        return self.loading.borrow_mut();
+        #[cfg(feature = "sync")]
+        return self.loading.write().unwrap();
+    }
+    /// Collect the script files, of a cached module and of all modules it imports (directly or
+    /// indirectly), that have changed since they were loaded.
+    fn find_changed(
+        &self,
+        file_path: &Path,
+        visited: &mut BTreeSet<PathBuf>,
+        changed: &mut Vec<PathBuf>,
+    ) {
+        if !visited.insert(file_path.into()) {
+            return;
+        }
+
+        let (stamp, dependencies) = match self.cache().get(file_path) {
+            Some(entry) => (entry.stamp.clone(), entry.dependencies.clone()),
+            // A module that is no longer cached has been reloaded
+            None => return changed.push(file_path.into()),
+        };
+
+        if let Some(stamp) = stamp {
+            let metadata = match fs::metadata(file_path) {
+                Ok(metadata) => metadata,
+                Err(_) => return changed.push(file_path.into()),
+            };
+
+            if metadata.modified().ok() != stamp.modified || metadata.len() != stamp.len {
+                match fs::read(file_path) {
+                    Ok(contents) if FileStamp::hash(&contents) == stamp.hash => {
+                        // Only touched - keep the cached module
+                        if let Some(entry) = self.cache().get_mut(file_path) {
+                            entry.stamp = Some(FileStamp {
+                                modified: metadata.modified().ok(),
+                                len: metadata.len(),
+                                hash: stamp.hash,
+                            });
+                        }
+                    }
+                    _ => return changed.push(file_path.into()),
+                }
+            }
+        }
+
+        dependencies
+            .iter()
+            .for_each(|path| self.find_changed(path, visited, changed));
+    }
+    /// Remove a module, and all cached modules importing it (directly or indirectly), from the cache,
+    /// notifying the [`on_change`][FileModuleResolver::on_change] callback of each of them.
+    fn invalidate(&self, file_path: &Path) {
+        let mut removed = vec![file_path.to_path_buf()];
+
+        {
+            let mut cache = self.cache();
+            cache.remove(file_path);
 
-        let mut file_path;
+            let mut index = 0;
 
-        if path.is_relative() {
-            file_path = self
+            while index < removed.len() {
+                let dependents: Vec<_> = cache
+                    .iter()
+                    .filter(|(_, entry)| entry.dependencies.contains(&removed[index]))
+                    .map(|(path, _)| path.clone())
+                    .collect();
+
+                for path in dependents {
+                    cache.remove(&path);
+                    removed.push(path);
+                }
+
+                index += 1;
+            }
+        }
+
+        if let Some(ref callback) = self.on_change {
+            removed.iter().for_each(|path| callback(path));
+        }
+    }
+    /// Construct the full file paths to try for a path, in order.
+    fn get_file_paths(&self, path: &str, source_path: Option<&str>) -> Vec<PathBuf> {
+        let path = Path::new(path);
+
+        let mut file_paths = if path.is_relative() {
+            let base_path = self
                 .base_path
                 .clone()
                 .or_else(|| source_path.map(|p| p.into()))
                 .unwrap_or_default();
-            file_path.push(path);
+
+            let mut file_paths = vec![base_path.join(path)];
+
+            file_paths.extend(self.search_paths.iter().map(|dir| dir.join(path)));
+
+            // A path starting with a package name is resolved under the package root
+            let mut components = path.components();
+
+            if let Some(Component::Normal(name)) = components.next() {
+                if let Some(package) = name.to_str().and_then(|name| self.packages.get(name)) {
+                    let rest = components.as_path();
+
+                    file_paths.push(if rest.as_os_str().is_empty() {
+                        package.root.join(&package.entry)
+                    } else {
+                        package.root.join(rest)
+                    });
+                }
+            }
+
+            file_paths
         } else {
-            file_path = path.into();
+            vec![path.into()]
+        };
+
+        // Force extension
+        file_paths.iter_mut().for_each(|file_path| {
+            file_path.set_extension(self.extension.as_str());
+        });
+        file_paths
+    }
+    /// Find the script file for a path, i.e. the first file path which exists when there are more
+    /// than one to try, otherwise return all the file paths tried.
+    fn find_file_path(
+        &self,
+        path: &str,
+        source_path: Option<&str>,
+    ) -> Result<PathBuf, Vec<PathBuf>> {
+        let mut file_paths = self.get_file_paths(path, source_path);
+
+        if file_paths.len() == 1 {
+            return Ok(file_paths.remove(0));
         }
 
-        file_path.set_extension(self.extension.as_str()); // Force extension
-        file_path
+        match file_paths.iter().position(|p| p.is_file()) {
+            Some(index) => Ok(file_paths.swap_remove(index)),
+            None => Err(file_paths),
+        }
+    }
+    /// Construct a full file path.
+    #[inline(always)]
+    fn get_file_path(&self, path: &str, source_path: Option<&str>) -> PathBuf {
+        self.find_file_path(path, source_path)
+            .unwrap_or_else(|mut file_paths| file_paths.remove(0))
     }
+    /// Load a script file and compile it into an [`AST`][crate::AST].
+    fn compile_file(
+        &self,
+        engine: &Engine,
+        path: &str,
+        source_path: Option<&str>,
+        pos: Position,
+    ) -> Result<(crate::AST, PathBuf), Box<EvalAltResult>> {
+        let file_path = self
+            .find_file_path(path, source_path)
+            .map_err(|tried| not_found(path, &tried, pos))?;
+
+        let mut ast = engine
+            .compile_file(file_path.clone())
+            .map_err(|err| match *err {
+                EvalAltResult::ErrorSystem(_, err) if err.is::<IoError>() => {
+                    not_found(path, crate::stdlib::slice::from_ref(&file_path), pos)
+                }
+                _ => Box::new(EvalAltResult::ErrorInModule(path.to_string(), err, pos)),
+            })?;
+
+        ast.set_source(path);
+
+        // Report coverage of the module under the path of the script file
+        if let Some(coverage) = engine.coverage() {
+            coverage.set_file_path(path, file_path.to_string_lossy());
+        }
+
+        Ok((ast, file_path))
+    }
+}
+
+/// Get the directory of the script doing the loading, if any.
+#[inline(always)]
+fn source_dir(source_path: Option<&str>) -> Option<Cow<'_, str>> {
+    source_path.and_then(|p| Path::new(p).parent().map(|p| p.to_string_lossy()))
+}
+
+/// Make an error for a script file that is not found.
+fn not_found(path: &str, tried: &[PathBuf], pos: Position) -> Box<EvalAltResult> {
+    EvalAltResult::ErrorModuleNotFound(
+        path.to_string(),
+        tried
+            .iter()
+            .map(|p| p.to_string_lossy().to_string())
+            .collect(),
+        pos,
+    )
+    .into()
 }
 
 impl ModuleResolver for FileModuleResolver {
@@ -269,49 +728,91 @@ impl ModuleResolver for FileModuleResolver {
         pos: Position,
     ) -> Result<Shared<Module>, Box<EvalAltResult>> {
         // Load relative paths from source if there is no base path specified
-        let source_path =
-            source_path.and_then(|p| Path::new(p).parent().map(|p| p.to_string_lossy()));
+        let source_path = source_dir(source_path);
+        let source_path = source_path.as_ref().map(|p| p.as_ref());
 
         // Construct the script file path
-        let file_path = self.get_file_path(path, source_path.as_ref().map(|p| p.as_ref()));
+        let file_path = self.get_file_path(path, source_path);
+
+        // Record the dependency of the module being loaded (on the same thread)
+        if self.hot_reload {
+            let id = loader_id();
+
+            if let Some((_, _, dependencies)) =
+                self.loading().iter_mut().rev().find(|(t, _, _)| *t == id)
+            {
+                dependencies.push(file_path.clone());
+            }
+        }
 
         // See if it is cached
         if self.is_cache_enabled() {
-            #[cfg(not(feature = "sync"))]
-            let c = self.cache.borrow();
-            #[cfg(feature = "sync")]
-            let c = self.cache.read().unwrap();
+            let cached = self.cache().contains_key(&file_path);
+
+            if cached && self.hot_reload {
+                let mut changed = Vec::new();
+                self.find_changed(&file_path, &mut Default::default(), &mut changed);
+                changed.iter().for_each(|path| self.invalidate(path));
+            }
 
-            if let Some(module) = c.get(&file_path) {
-                return Ok(module.clone());
+            if let Some(entry) = self.cache().get(&file_path) {
+                return Ok(entry.module.clone());
             }
         }
 
+        // Take the stamp before loading so that later modifications are detected
+        let stamp = if self.hot_reload {
+            FileStamp::read(&file_path)
+        } else {
+            None
+        };
+
         // Load the script file and compile it
         let scope = Default::default();
 
-        let mut ast = engine
-            .compile_file(file_path.clone())
-            .map_err(|err| match *err {
-                EvalAltResult::ErrorSystem(_, err) if err.is::<IoError>() => {
-                    Box::new(EvalAltResult::ErrorModuleNotFound(path.to_string(), pos))
-                }
-                _ => Box::new(EvalAltResult::ErrorInModule(path.to_string(), err, pos)),
-            })?;
+        let (ast, file_path) = self.compile_file(engine, path, source_path, pos)?;
 
-        ast.set_source(path);
+        // Guard against cyclic imports
+        self.chain
+            .enter(file_path.to_string_lossy().into_owned(), pos)?;
+
+        // Make a module from the AST, recording the modules it imports
+        if self.hot_reload {
+            self.loading()
+                .push((loader_id(), file_path.clone(), Default::default()));
+        }
+
+        let result = Module::eval_ast_as_new(scope, &ast, engine);
 
-        // Make a module from the AST
-        let m: Shared<Module> = Module::eval_ast_as_new(scope, &ast, engine)
+        self.chain.leave();
+
+        let dependencies = if self.hot_reload {
+            let id = loader_id();
+            let mut loading = self.loading();
+
+            loading
+                .iter()
+                .rposition(|(t, _, _)| *t == id)
+                .map(|index| loading.remove(index).2)
+                .unwrap_or_default()
+        } else {
+            Default::default()
+        };
+
+        let m: Shared<Module> = result
             .map_err(|err| Box::new(EvalAltResult::ErrorInModule(path.to_string(), err, pos)))?
             .into();
 
         // Put it into the cache
         if self.is_cache_enabled() {
-            #[cfg(not(feature = "sync"))]
-            self.cache.borrow_mut().insert(file_path, m.clone());
-            #[cfg(feature = "sync")]
-            self.cache.write().unwrap().insert(file_path, m.clone());
+            self.cache().insert(
+                file_path,
+                CachedModule {
+                    module: m.clone(),
+                    stamp,
+                    dependencies,
+                },
+            );
         }
 
         Ok(m)
@@ -327,21 +828,37 @@ impl ModuleResolver for FileModuleResolver {
         path: &str,
         pos: Position,
     ) -> Option<Result<crate::AST, Box<EvalAltResult>>> {
-        // Construct the script file path
-        let file_path = self.get_file_path(path, source_path);
+        let source_path = source_dir(source_path);
+        let source_path = source_path.as_ref().map(|p| p.as_ref());
 
         // Load the script file and compile it
-        match engine.compile_file(file_path).map_err(|err| match *err {
-            EvalAltResult::ErrorSystem(_, err) if err.is::<IoError>() => {
-                Box::new(EvalAltResult::ErrorModuleNotFound(path.to_string(), pos))
-            }
-            _ => Box::new(EvalAltResult::ErrorInModule(path.to_string(), err, pos)),
-        }) {
-            Ok(mut ast) => {
-                ast.set_source(path);
-                Some(Ok(ast))
-            }
-            err @ Err(_) => Some(err),
-        }
+        Some(
+            self.compile_file(engine, path, source_path, pos)
+                .map(|(ast, _)| ast),
+        )
+    }
+
+    /// Resolve the script text of a module based on a path string.
+    ///
+    /// The file system is accessed during each call; the internal cache is by-passed.
+    fn resolve_script(
+        &self,
+        _: &Engine,
+        source_path: Option<&str>,
+        path: &str,
+        pos: Position,
+    ) -> Option<Result<String, Box<EvalAltResult>>> {
+        let source_path = source_dir(source_path);
+        let source_path = source_path.as_ref().map(|p| p.as_ref());
+
+        let file_path = match self.find_file_path(path, source_path) {
+            Ok(file_path) => file_path,
+            Err(tried) => return Some(Err(not_found(path, &tried, pos))),
+        };
+
+        Some(
+            fs::read_to_string(&file_path)
+                .map_err(|_| not_found(path, crate::stdlib::slice::from_ref(&file_path), pos)),
+        )
     }
 }
@@ -0,0 +1,209 @@
+use crate::stdlib::{
+    boxed::Box,
+    format, fs,
+    io::{Error as IoError, ErrorKind},
+    path::{Path, PathBuf},
+    string::{String, ToString},
+    vec::Vec,
+};
+use crate::EvalAltResult;
+
+/// Default entry [module][crate::Module] of a package, relative to the package root.
+pub const DEFAULT_PACKAGE_ENTRY: &str = "lib";
+
+/// A package manifest, normally read from a [`rhai.toml`][PackageManifest::FILE_NAME] file in the
+/// root directory of a package of [module][crate::Module] scripts.
+///
+/// The manifest is a small subset of TOML:
+///
+/// ```toml
+/// [package]
+/// name = "utils"          # name of the package (required)
+/// entry = "lib"           # entry module, relative to the package root (default: "lib")
+///
+/// [dependencies]
+/// strings = "../strings"          # path to the root of a dependency package
+/// text = { path = "../text" }     # same as above
+/// ```
+///
+/// Other keys and sections are ignored.
+///
+/// See [`FileModuleResolver::add_package`][super::FileModuleResolver::add_package].
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct PackageManifest {
+    /// Name of the package.
+    pub name: String,
+    /// Path of the entry [module][crate::Module], relative to the package root.
+    pub entry: String,
+    /// Names of dependency packages, and paths to their roots relative to the package root.
+    pub dependencies: Vec<(String, PathBuf)>,
+}
+
+/// Make an error for an invalid package manifest.
+fn invalid_manifest(msg: String) -> Box<EvalAltResult> {
+    EvalAltResult::ErrorSystem(
+        "Invalid package manifest".to_string(),
+        IoError::new(ErrorKind::InvalidData, msg).into(),
+    )
+    .into()
+}
+
+/// Parse a string value, returning it with the rest of the text.
+fn parse_string(text: &str) -> Result<(String, &str), &'static str> {
+    let mut chars = text.char_indices();
+
+    let quote = match chars.next() {
+        Some((_, c)) if c == '"' || c == '\'' => c,
+        _ => return Err("expecting a string"),
+    };
+
+    let mut value = String::new();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            c if c == quote => return Ok((value, &text[i + 1..])),
+            // Escapes in basic strings
+            '\\' if quote == '"' => match chars.next().map(|(_, c)| c) {
+                Some('n') => value.push('\n'),
+                Some('t') => value.push('\t'),
+                Some('r') => value.push('\r'),
+                Some(c) if c == '\\' || c == '"' => value.push(c),
+                _ => return Err("invalid escape sequence"),
+            },
+            c => value.push(c),
+        }
+    }
+
+    Err("unterminated string")
+}
+
+/// Parse the value of a dependency: a path string or an inline table with a `path` string,
+/// returning it with the rest of the text.
+fn parse_dependency(text: &str) -> Result<(String, &str), &'static str> {
+    let mut rest = match text.strip_prefix('{') {
+        Some(table) => table.trim_start(),
+        None => return parse_string(text),
+    };
+
+    let mut path = None;
+
+    while !rest.starts_with('}') {
+        let eq = rest.find('=').ok_or("expecting '='")?;
+        let key = rest[..eq].trim();
+        let (value, next) = parse_string(rest[eq + 1..].trim_start())?;
+
+        if key == "path" {
+            path = Some(value);
+        }
+
+        rest = next.trim_start();
+
+        match rest.strip_prefix(',') {
+            Some(next) => rest = next.trim_start(),
+            None if rest.starts_with('}') => (),
+            None => return Err("expecting ',' or '}'"),
+        }
+    }
+
+    Ok((path.ok_or("missing dependency path")?, &rest[1..]))
+}
+
+/// Is the rest of a line empty or a comment?
+fn is_end_of_line(text: &str) -> bool {
+    let text = text.trim_start();
+    text.is_empty() || text.starts_with('#')
+}
+
+impl PackageManifest {
+    /// Name of package manifest files.
+    pub const FILE_NAME: &'static str = "rhai.toml";
+
+    /// Parse a package manifest.
+    pub fn parse(text: &str) -> Result<Self, Box<EvalAltResult>> {
+        let mut name = None;
+        let mut entry = None;
+        let mut dependencies = Vec::new();
+        let mut section = "";
+
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            let err = |msg: &str| invalid_manifest(format!("line {}: {}", n + 1, msg));
+
+            if is_end_of_line(line) {
+                continue;
+            }
+
+            // [section]
+            if let Some(header) = line.strip_prefix('[') {
+                let end = header.find(']').ok_or_else(|| err("expecting ']'"))?;
+
+                if !is_end_of_line(&header[end + 1..]) {
+                    return Err(err("unexpected text after section header"));
+                }
+
+                section = header[..end].trim();
+                continue;
+            }
+
+            // key = value
+            let eq = line.find('=').ok_or_else(|| err("expecting '='"))?;
+            let key = line[..eq].trim().trim_matches('"');
+            let value = line[eq + 1..].trim_start();
+
+            let (value, rest) = match (section, key) {
+                ("package", "name") | ("package", "entry") => parse_string(value),
+                ("dependencies", _) => parse_dependency(value),
+                // Ignore other keys
+                _ => continue,
+            }
+            .map_err(err)?;
+
+            if !is_end_of_line(rest) {
+                return Err(err("unexpected text after value"));
+            }
+
+            match (section, key) {
+                ("package", "name") if value.is_empty() || value.contains(&['/', '\\'][..]) => {
+                    return Err(err(&format!("invalid package name '{}'", value)))
+                }
+                ("package", "name") => name = Some(value),
+                ("package", "entry") => entry = Some(value),
+                _ => dependencies.push((key.to_string(), value.into())),
+            }
+        }
+
+        Ok(Self {
+            name: name.ok_or_else(|| invalid_manifest("missing package name".into()))?,
+            entry: entry.unwrap_or_else(|| DEFAULT_PACKAGE_ENTRY.into()),
+            dependencies,
+        })
+    }
+
+    /// Load a package manifest, given the path to the manifest file or to the package root
+    /// directory containing a [`rhai.toml`][PackageManifest::FILE_NAME] file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Box<EvalAltResult>> {
+        let path = path.as_ref();
+
+        let path = if path.is_dir() {
+            path.join(Self::FILE_NAME)
+        } else {
+            path.to_path_buf()
+        };
+
+        let text = fs::read_to_string(&path).map_err(|err| {
+            EvalAltResult::ErrorSystem(
+                format!("Cannot read package manifest '{}'", path.to_string_lossy()),
+                err.into(),
+            )
+        })?;
+
+        Self::parse(&text).map_err(|err| match *err {
+            EvalAltResult::ErrorSystem(_, err) => EvalAltResult::ErrorSystem(
+                format!("Invalid package manifest '{}'", path.to_string_lossy()),
+                err,
+            )
+            .into(),
+            err => err.into(),
+        })
+    }
+}
@@ -0,0 +1,338 @@
+use super::ImportChain;
+use crate::fn_native::Locked;
+use crate::stdlib::{
+    boxed::Box,
+    collections::BTreeMap,
+    format,
+    ops::DerefMut,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use crate::{Engine, EvalAltResult, Identifier, Module, ModuleResolver, Position, Shared, AST};
+
+/// Default file extension of module scripts.
+const RHAI_SCRIPT_EXTENSION: &str = "rhai";
+
+/// A [module][Module] resolution service that loads [module][Module] scripts from an in-memory
+/// virtual file system, i.e. a map of paths to script sources.
+///
+/// Paths are resolved the same way as [`FileModuleResolver`][super::FileModuleResolver]:
+/// relative paths are resolved under the base path, if any, or else under the directory of the
+/// script doing the loading. Paths are separated by `/` (or `\`) and may contain `.` and `..`
+/// segments; a path starting with `/` is resolved from the root.
+///
+/// ## Caching
+///
+/// Resolved [Modules][Module] are cached internally so scripts are not recompiled for subsequent
+/// requests. Adding a script removes any cached [module][Module] for the same path.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+/// use rhai::{Engine, INT};
+/// use rhai::module_resolvers::MemoryModuleResolver;
+///
+/// let mut resolver = MemoryModuleResolver::new();
+///
+/// resolver.insert("lib/math.rhai", r#"import "consts" as c; export const TAU = c::PI * 2;"#);
+/// resolver.insert("lib/consts.rhai", "export const PI = 3;");
+///
+/// let mut engine = Engine::new();
+/// engine.set_module_resolver(resolver);
+///
+/// assert_eq!(engine.eval::<INT>(r#"import "lib/math" as m; m::TAU"#)?, 6);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct MemoryModuleResolver {
+    base_path: Option<String>,
+    extension: Identifier,
+    scripts: BTreeMap<String, String>,
+    cache_enabled: bool,
+    cache: Locked<BTreeMap<String, Shared<Module>>>,
+    chain: ImportChain,
+}
+
+impl Default for MemoryModuleResolver {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Normalize a path into `/`-separated segments, removing `.` and resolving `..` segments.
+fn normalize_path(path: &str) -> String {
+    let mut segments = Vec::new();
+
+    for segment in path.split(&['/', '\\'][..]) {
+        match segment {
+            "" | "." => (),
+            ".." => {
+                segments.pop();
+            }
+            s => segments.push(s),
+        }
+    }
+
+    segments.join("/")
+}
+
+/// Get the directory part of a path.
+fn parent_path(path: &str) -> &str {
+    path.rfind(&['/', '\\'][..]).map_or("", |n| &path[..n])
+}
+
+impl MemoryModuleResolver {
+    /// Create a new empty [`MemoryModuleResolver`].
+    ///
+    /// The default extension is `.rhai`.
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self::new_with_extension(RHAI_SCRIPT_EXTENSION)
+    }
+    /// Create a new empty [`MemoryModuleResolver`] with a file extension.
+    #[inline(always)]
+    pub fn new_with_extension(extension: impl Into<Identifier>) -> Self {
+        Self {
+            base_path: None,
+            extension: extension.into(),
+            scripts: Default::default(),
+            cache_enabled: true,
+            cache: Default::default(),
+            chain: Default::default(),
+        }
+    }
+
+    /// Add a script keyed by its path (including the file extension).
+    ///
+    /// Any cached [module][Module] for the same path is removed.
+    #[inline(always)]
+    pub fn insert(&mut self, path: impl AsRef<str>, script: impl Into<String>) -> &mut Self {
+        let path = normalize_path(path.as_ref());
+        self.cache().remove(&path);
+        self.scripts.insert(path, script.into());
+        self
+    }
+    /// Remove a script given its path (including the file extension).
+    #[inline(always)]
+    pub fn remove(&mut self, path: &str) -> Option<String> {
+        let path = normalize_path(path);
+        self.cache().remove(&path);
+        self.scripts.remove(&path)
+    }
+    /// Does a script exist at the path (including the file extension)?
+    #[inline(always)]
+    pub fn contains_path(&self, path: &str) -> bool {
+        self.scripts.contains_key(&normalize_path(path))
+    }
+    /// Get the script at the path (including the file extension), if any.
+    #[inline(always)]
+    pub fn get(&self, path: &str) -> Option<&str> {
+        self.scripts.get(&normalize_path(path)).map(|s| s.as_str())
+    }
+    /// Get an iterator of all the script paths.
+    #[inline(always)]
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        self.scripts.keys().map(|s| s.as_str())
+    }
+    /// Get the number of scripts.
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.scripts.len()
+    }
+    /// Are there no scripts?
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.scripts.is_empty()
+    }
+
+    /// Get the base path for scripts.
+    #[inline(always)]
+    pub fn base_path(&self) -> Option<&str> {
+        self.base_path.as_deref()
+    }
+    /// Set the base path for scripts.
+    #[inline(always)]
+    pub fn set_base_path(&mut self, path: impl AsRef<str>) -> &mut Self {
+        self.base_path = Some(normalize_path(path.as_ref()));
+        self
+    }
+
+    /// Get the script file extension.
+    #[inline(always)]
+    pub fn extension(&self) -> &str {
+        &self.extension
+    }
+    /// Set the script file extension.
+    #[inline(always)]
+    pub fn set_extension(&mut self, extension: impl Into<Identifier>) -> &mut Self {
+        self.extension = extension.into();
+        self
+    }
+
+    /// Enable/disable the cache.
+    #[inline(always)]
+    pub fn enable_cache(&mut self, enable: bool) -> &mut Self {
+        self.cache_enabled = enable;
+        self
+    }
+    /// Is the cache enabled?
+    #[inline(always)]
+    pub fn is_cache_enabled(&self) -> bool {
+        self.cache_enabled
+    }
+    /// Is a particular path cached?
+    #[inline(always)]
+    pub fn is_cached(&self, path: &str, source_path: Option<&str>) -> bool {
+        self.cache_enabled
+            && self
+                .cache()
+                .contains_key(&self.get_file_path(path, source_path))
+    }
+    /// Empty the internal cache.
+    #[inline(always)]
+    pub fn clear_cache(&mut self) {
+        self.cache().clear();
+    }
+    /// Remove the specified path from internal cache.
+    ///
+    /// The next time this path is resolved, the script will be compiled once again.
+    #[inline(always)]
+    pub fn clear_cache_for_path(
+        &mut self,
+        path: &str,
+        source_path: Option<&str>,
+    ) -> Option<Shared<Module>> {
+        let file_path = self.get_file_path(path, source_path);
+        self.cache().remove(&file_path)
+    }
+    /// Get a mutable reference to the internal cache.
+    #[inline(always)]
+    fn cache(&self) -> impl DerefMut<Target = BTreeMap<String, Shared<Module>>> + '_ {
+        #[cfg(not(feature = "sync"))]
+        return self.cache.borrow_mut();
+        #[cfg(feature = "sync")]
+        return self.cache.write().unwrap();
+    }
+    /// Construct the full path of a script, given the directory of the script doing the loading.
+    fn get_file_path(&self, path: &str, source_dir: Option<&str>) -> String {
+        let path = if path.starts_with('/') || path.starts_with('\\') {
+            normalize_path(path)
+        } else {
+            match self.base_path.as_deref().or(source_dir) {
+                Some(dir) => normalize_path(&format!("{}/{}", dir, path)),
+                None => normalize_path(path),
+            }
+        };
+
+        // Force extension
+        let name_start = path.rfind('/').map_or(0, |n| n + 1);
+
+        let stem = match path[name_start..].rfind('.') {
+            Some(n) if n > 0 => &path[..name_start + n],
+            _ => &path[..],
+        };
+
+        format!("{}.{}", stem, self.extension)
+    }
+    /// Compile the script at a path into an [`AST`], with the path as its source.
+    fn compile(
+        &self,
+        engine: &Engine,
+        file_path: &str,
+        path: &str,
+        pos: Position,
+    ) -> Result<AST, Box<EvalAltResult>> {
+        let script = self.scripts.get(file_path).ok_or_else(|| {
+            EvalAltResult::ErrorModuleNotFound(path.to_string(), vec![file_path.to_string()], pos)
+        })?;
+
+        let mut ast = engine.compile(script).map_err(|err| {
+            Box::new(EvalAltResult::ErrorInModule(
+                path.to_string(),
+                err.into(),
+                pos,
+            ))
+        })?;
+
+        ast.set_source(file_path);
+        Ok(ast)
+    }
+}
+
+impl ModuleResolver for MemoryModuleResolver {
+    fn resolve(
+        &self,
+        engine: &Engine,
+        source_path: Option<&str>,
+        path: &str,
+        pos: Position,
+    ) -> Result<Shared<Module>, Box<EvalAltResult>> {
+        // Load relative paths from source if there is no base path specified
+        let file_path = self.get_file_path(path, source_path.map(parent_path));
+
+        // See if it is cached
+        if self.is_cache_enabled() {
+            if let Some(module) = self.cache().get(&file_path) {
+                return Ok(module.clone());
+            }
+        }
+
+        let ast = self.compile(engine, &file_path, path, pos)?;
+
+        // Guard against cyclic imports
+        self.chain.enter(file_path.clone(), pos)?;
+
+        // Make a module from the AST
+        let result = Module::eval_ast_as_new(Default::default(), &ast, engine);
+
+        self.chain.leave();
+
+        let m: Shared<Module> = result
+            .map_err(|err| Box::new(EvalAltResult::ErrorInModule(path.to_string(), err, pos)))?
+            .into();
+
+        // Put it into the cache
+        if self.is_cache_enabled() {
+            self.cache().insert(file_path, m.clone());
+        }
+
+        Ok(m)
+    }
+
+    /// Resolve an `AST` based on a path string.
+    ///
+    /// The internal cache is by-passed.
+    fn resolve_ast(
+        &self,
+        engine: &Engine,
+        source_path: Option<&str>,
+        path: &str,
+        pos: Position,
+    ) -> Option<Result<AST, Box<EvalAltResult>>> {
+        let file_path = self.get_file_path(path, source_path.map(parent_path));
+
+        Some(self.compile(engine, &file_path, path, pos))
+    }
+
+    /// Resolve the script text of a module based on a path string.
+    fn resolve_script(
+        &self,
+        _: &Engine,
+        source_path: Option<&str>,
+        path: &str,
+        pos: Position,
+    ) -> Option<Result<String, Box<EvalAltResult>>> {
+        let file_path = self.get_file_path(path, source_path.map(parent_path));
+
+        Some(match self.scripts.get(&file_path) {
+            Some(script) => Ok(script.clone()),
+            None => {
+                EvalAltResult::ErrorModuleNotFound(path.to_string(), vec![file_path], pos).into()
+            }
+        })
+    }
+}
@@ -1,5 +1,5 @@
-use crate::fn_native::SendSync;
-use crate::stdlib::boxed::Box;
+use crate::fn_native::{Locked, SendSync};
+use crate::stdlib::{boxed::Box, ops::DerefMut, string::String, vec::Vec};
 use crate::{Engine, EvalAltResult, Module, Position, Shared, AST};
 
 mod dummy;
@@ -16,6 +16,28 @@ mod file;
 #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
 pub use file::FileModuleResolver;
 
+#[cfg(not(feature = "no_std"))]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+mod manifest;
+
+#[cfg(not(feature = "no_std"))]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+pub use manifest::PackageManifest;
+
+mod memory;
+pub use memory::MemoryModuleResolver;
+
+#[cfg(feature = "archive")]
+#[cfg(not(feature = "no_std"))]
+mod archive;
+
+#[cfg(feature = "archive")]
+#[cfg(not(feature = "no_std"))]
+pub use archive::ArchiveModuleResolver;
+
+mod restricted;
+pub use restricted::RestrictedModuleResolver;
+
 mod stat;
 pub use stat::StaticModuleResolver;
 
@@ -49,4 +71,90 @@ pub trait ModuleResolver: SendSync {
     ) -> Option<Result<AST, Box<EvalAltResult>>> {
         None
     }
+
+    /// Resolve the script text of a module based on a path string.
+    ///
+    /// Returns [`None`] (default) if such resolution is not supported
+    /// (e.g. if the module is Rust-based).
+    ///
+    /// ## Low-Level API
+    ///
+    /// Override the default implementation of this method if the module resolver
+    /// serves modules based on Rhai scripts.
+    #[allow(unused_variables)]
+    fn resolve_script(
+        &self,
+        engine: &Engine,
+        source_path: Option<&str>,
+        path: &str,
+        pos: Position,
+    ) -> Option<Result<String, Box<EvalAltResult>>> {
+        None
+    }
+}
+
+/// Identifier of the thread loading a [module][Module].
+#[cfg(all(feature = "sync", not(feature = "no_std")))]
+type LoaderId = crate::stdlib::thread::ThreadId;
+/// Identifier of the thread loading a [module][Module].
+#[cfg(not(all(feature = "sync", not(feature = "no_std"))))]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+struct LoaderId;
+
+/// Get the identifier of the current thread.
+#[inline(always)]
+fn loader_id() -> LoaderId {
+    #[cfg(all(feature = "sync", not(feature = "no_std")))]
+    return crate::stdlib::thread::current().id();
+    #[cfg(not(all(feature = "sync", not(feature = "no_std"))))]
+    return LoaderId;
+}
+
+/// Chain of [modules][Module] being loaded by a [module resolver][ModuleResolver], used to detect
+/// cyclic imports.
+///
+/// Under the `sync` feature, [modules][Module] loaded on different threads are tracked separately.
+#[derive(Debug, Default)]
+pub(crate) struct ImportChain(Locked<Vec<(LoaderId, String)>>);
+
+impl ImportChain {
+    /// Get a mutable reference to the chain.
+    #[inline(always)]
+    fn chain(&self) -> impl DerefMut<Target = Vec<(LoaderId, String)>> + '_ {
+        #[cfg(not(feature = "sync"))]
+        return self.0.borrow_mut();
+        #[cfg(feature = "sync")]
+        return self.0.write().unwrap();
+    }
+    /// Add a [module][Module], keyed by its path, to the chain before loading it.
+    ///
+    /// Returns [`ErrorCyclicImport`][EvalAltResult::ErrorCyclicImport] if the same path is already
+    /// being loaded (on the same thread).
+    pub fn enter(&self, path: String, pos: Position) -> Result<(), Box<EvalAltResult>> {
+        let id = loader_id();
+        let mut chain = self.chain();
+
+        if let Some(start) = chain.iter().position(|(t, p)| *t == id && *p == path) {
+            let cycle = chain[start..]
+                .iter()
+                .filter(|(t, _)| *t == id)
+                .map(|(_, p)| p.clone())
+                .chain(crate::stdlib::iter::once(path))
+                .collect();
+
+            return EvalAltResult::ErrorCyclicImport(cycle, pos).into();
+        }
+
+        chain.push((id, path));
+        Ok(())
+    }
+    /// Remove the last [module][Module] added to the chain (on the same thread) after loading it.
+    pub fn leave(&self) {
+        let id = loader_id();
+        let mut chain = self.chain();
+
+        if let Some(index) = chain.iter().rposition(|(t, _)| *t == id) {
+            chain.remove(index);
+        }
+    }
 }
@@ -0,0 +1,474 @@
+use super::{loader_id, LoaderId};
+use crate::ast::{ASTNode, Expr};
+use crate::fn_native::Locked;
+use crate::stdlib::{
+    boxed::Box,
+    fmt, format,
+    num::NonZeroUsize,
+    ops::DerefMut,
+    string::{String, ToString},
+    vec::Vec,
+};
+use crate::token::is_valid_identifier;
+use crate::{Engine, EvalAltResult, Module, ModuleResolver, Position, Shared, AST};
+
+/// A [module][Module] resolution service that wraps another [module resolver][ModuleResolver]
+/// and restricts the [modules][Module] that may be loaded through it.
+///
+/// * Import paths are checked against lists of allowed and denied glob patterns.
+///
+/// * Function calls in [module][Module] scripts are checked against lists of allowed and denied
+///   glob patterns on function names, so that a [module][Module] can only call the native
+///   functions granted to it.
+///
+/// * The depth of nested imports is limited.
+///
+/// * The total size of [module][Module] scripts loaded for one import (i.e. a [module][Module]
+///   and all the [modules][Module] it imports while being loaded) is limited.
+///
+/// Violations are reported as [`ErrorModuleRestricted`][EvalAltResult::ErrorModuleRestricted].
+/// They are not catchable by scripts.
+///
+/// The wrapped resolver can be a [`ModuleResolversCollection`][super::ModuleResolversCollection],
+/// and a [`RestrictedModuleResolver`] can itself be added to a collection.
+///
+/// ## Patterns
+///
+/// In a glob pattern, `?` matches any single character except `/`, `*` matches any sequence of
+/// characters except `/`, and `**` matches any sequence of characters (including `/`).
+///
+/// Import paths are matched as written in the `import` statement, after `.` and `..` segments are
+/// normalized and `\` is replaced by `/`. For example, the pattern `../**` matches all paths
+/// leading out of the directory of the script doing the loading.
+///
+/// A path (or function name) is allowed if it does not match any denied pattern, and either there
+/// are no allowed patterns or it matches one of them.
+///
+/// ## Function Calls
+///
+/// Function calls are checked in the [`AST`] of a [module][Module] script, before the script is
+/// run, so the wrapped resolver must support [`resolve_ast`][ModuleResolver::resolve_ast].
+/// Qualified calls are checked with their namespaces (e.g. `math::sqrt`). Calls to functions
+/// defined in the same script and operators are not checked.
+///
+/// Functions called dynamically via function pointers or `eval` are not visible in the [`AST`],
+/// so `call`, `curry` and `eval` should not be allowed in order to enforce the restrictions.
+///
+/// ## Source Size
+///
+/// The size of a [module][Module] script is only known if the wrapped resolver supports
+/// [`resolve_script`][ModuleResolver::resolve_script]; otherwise it is not counted.
+///
+/// A [module][Module] served from the cache of the wrapped resolver does not load the
+/// [modules][Module] it imports again, so they do not count towards the import depth or the
+/// total source size.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+/// use rhai::{Engine, EvalAltResult, INT};
+/// use rhai::module_resolvers::{MemoryModuleResolver, RestrictedModuleResolver};
+///
+/// let mut scripts = MemoryModuleResolver::new();
+/// scripts.insert("lib/math.rhai", "export const ANSWER = 21 * 2;");
+/// scripts.insert("lib/io.rhai", r#"print("hello");"#);
+/// scripts.insert("private/keys.rhai", "export const KEY = 42;");
+///
+/// let mut resolver = RestrictedModuleResolver::new(scripts);
+/// resolver.allow_path("lib/*").deny_function("print");
+///
+/// let mut engine = Engine::new();
+/// engine.set_module_resolver(resolver);
+///
+/// assert_eq!(engine.eval::<INT>(r#"import "lib/math" as m; m::ANSWER"#)?, 42);
+///
+/// assert!(matches!(
+///     *engine.consume(r#"import "private/keys" as k;"#).unwrap_err(),
+///     EvalAltResult::ErrorModuleRestricted(..)
+/// ));
+/// assert!(matches!(
+///     *engine.consume(r#"import "lib/io" as io;"#).unwrap_err(),
+///     EvalAltResult::ErrorModuleRestricted(..)
+/// ));
+/// # Ok(())
+/// # }
+/// ```
+pub struct RestrictedModuleResolver {
+    resolver: Box<dyn ModuleResolver>,
+    allowed_paths: Vec<String>,
+    denied_paths: Vec<String>,
+    allowed_functions: Vec<String>,
+    denied_functions: Vec<String>,
+    max_depth: Option<NonZeroUsize>,
+    max_source_size: Option<NonZeroUsize>,
+    loading: Locked<Vec<Loading>>,
+}
+
+/// Imports being loaded on a thread.
+#[derive(Debug)]
+struct Loading {
+    /// Thread doing the loading.
+    id: LoaderId,
+    /// Number of nested imports being loaded.
+    depth: usize,
+    /// Total size of the scripts loaded so far.
+    size: usize,
+}
+
+impl fmt::Debug for RestrictedModuleResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RestrictedModuleResolver")
+            .field("allowed_paths", &self.allowed_paths)
+            .field("denied_paths", &self.denied_paths)
+            .field("allowed_functions", &self.allowed_functions)
+            .field("denied_functions", &self.denied_functions)
+            .field("max_depth", &self.max_depth())
+            .field("max_source_size", &self.max_source_size())
+            .finish()
+    }
+}
+
+/// Match a glob pattern against some text.
+fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        // `**/` also matches no directories at all
+        ['*', '*', '/', rest @ ..] => {
+            glob_match(rest, text)
+                || (0..text.len()).any(|n| text[n] == '/' && glob_match(rest, &text[n + 1..]))
+        }
+        ['*', '*', rest @ ..] => (0..=text.len()).any(|n| glob_match(rest, &text[n..])),
+        ['*', rest @ ..] => (0..=text.len())
+            .take_while(|&n| n == 0 || text[n - 1] != '/')
+            .any(|n| glob_match(rest, &text[n..])),
+        ['?', rest @ ..] => matches!(text, [c, ..] if *c != '/') && glob_match(rest, &text[1..]),
+        [p, rest @ ..] => matches!(text, [c, ..] if c == p) && glob_match(rest, &text[1..]),
+    }
+}
+
+/// Is a name allowed by lists of allowed and denied glob patterns?
+fn is_allowed(name: &str, allowed: &[String], denied: &[String]) -> bool {
+    let name: Vec<_> = name.chars().collect();
+    let matches = |pattern: &String| glob_match(&pattern.chars().collect::<Vec<_>>(), &name);
+
+    !denied.iter().any(matches) && (allowed.is_empty() || allowed.iter().any(matches))
+}
+
+/// Normalize an import path into `/`-separated segments, removing `.` and resolving `..` segments
+/// where possible.
+fn normalize_path(path: &str) -> String {
+    let mut segments = Vec::new();
+
+    for segment in path.split(&['/', '\\'][..]) {
+        match segment {
+            "" | "." => (),
+            ".." if matches!(segments.last(), Some(s) if *s != "..") => {
+                segments.pop();
+            }
+            s => segments.push(s),
+        }
+    }
+
+    if path.starts_with('/') || path.starts_with('\\') {
+        format!("/{}", segments.join("/"))
+    } else {
+        segments.join("/")
+    }
+}
+
+impl RestrictedModuleResolver {
+    /// Create a new [`RestrictedModuleResolver`] wrapping a [module resolver][ModuleResolver],
+    /// with no restrictions.
+    #[inline(always)]
+    pub fn new(resolver: impl ModuleResolver + 'static) -> Self {
+        Self {
+            resolver: Box::new(resolver),
+            allowed_paths: Default::default(),
+            denied_paths: Default::default(),
+            allowed_functions: Default::default(),
+            denied_functions: Default::default(),
+            max_depth: None,
+            max_source_size: None,
+            loading: Default::default(),
+        }
+    }
+    /// Get the wrapped [module resolver][ModuleResolver].
+    #[inline(always)]
+    pub fn inner(&self) -> &dyn ModuleResolver {
+        self.resolver.as_ref()
+    }
+
+    /// Allow import paths matching a glob pattern.
+    ///
+    /// Once any pattern is allowed, import paths not matching an allowed pattern are denied.
+    #[inline(always)]
+    pub fn allow_path(&mut self, pattern: impl Into<String>) -> &mut Self {
+        self.allowed_paths.push(pattern.into());
+        self
+    }
+    /// Deny import paths matching a glob pattern.
+    #[inline(always)]
+    pub fn deny_path(&mut self, pattern: impl Into<String>) -> &mut Self {
+        self.denied_paths.push(pattern.into());
+        self
+    }
+    /// Is an import path allowed?
+    #[inline(always)]
+    pub fn is_path_allowed(&self, path: &str) -> bool {
+        is_allowed(
+            &normalize_path(path),
+            &self.allowed_paths,
+            &self.denied_paths,
+        )
+    }
+
+    /// Allow [module][Module] scripts to call functions with names matching a glob pattern.
+    ///
+    /// Once any pattern is allowed, calls to functions not matching an allowed pattern are
+    /// denied.
+    #[inline(always)]
+    pub fn allow_function(&mut self, pattern: impl Into<String>) -> &mut Self {
+        self.allowed_functions.push(pattern.into());
+        self
+    }
+    /// Deny [module][Module] scripts from calling functions with names matching a glob pattern.
+    #[inline(always)]
+    pub fn deny_function(&mut self, pattern: impl Into<String>) -> &mut Self {
+        self.denied_functions.push(pattern.into());
+        self
+    }
+    /// Is a [module][Module] script allowed to call a function?
+    ///
+    /// The name of a qualified function includes its namespace (e.g. `math::sqrt`).
+    #[inline(always)]
+    pub fn is_function_allowed(&self, name: &str) -> bool {
+        is_allowed(name, &self.allowed_functions, &self.denied_functions)
+    }
+    /// Are function calls restricted?
+    #[inline(always)]
+    fn has_function_restrictions(&self) -> bool {
+        !self.allowed_functions.is_empty() || !self.denied_functions.is_empty()
+    }
+
+    /// Set the maximum depth of nested imports (0 for unlimited).
+    ///
+    /// A [module][Module] imported directly by a script is at depth 1.
+    #[inline(always)]
+    pub fn set_max_depth(&mut self, depth: usize) -> &mut Self {
+        self.max_depth = NonZeroUsize::new(depth);
+        self
+    }
+    /// The maximum depth of nested imports (0 for unlimited).
+    #[inline(always)]
+    pub fn max_depth(&self) -> usize {
+        self.max_depth.map_or(0, NonZeroUsize::get)
+    }
+    /// Set the maximum total size, in bytes, of the scripts of a [module][Module] and all the
+    /// [modules][Module] it imports while being loaded (0 for unlimited).
+    #[inline(always)]
+    pub fn set_max_source_size(&mut self, size: usize) -> &mut Self {
+        self.max_source_size = NonZeroUsize::new(size);
+        self
+    }
+    /// The maximum total size, in bytes, of the scripts of a [module][Module] and all the
+    /// [modules][Module] it imports while being loaded (0 for unlimited).
+    #[inline(always)]
+    pub fn max_source_size(&self) -> usize {
+        self.max_source_size.map_or(0, NonZeroUsize::get)
+    }
+
+    /// Get a mutable reference to the imports being loaded.
+    #[inline(always)]
+    fn loading(&self) -> impl DerefMut<Target = Vec<Loading>> + '_ {
+        #[cfg(not(feature = "sync"))]
+        return self.loading.borrow_mut();
+        #[cfg(feature = "sync")]
+        return self.loading.write().unwrap();
+    }
+    /// Check an import path against the allowed and denied patterns.
+    fn check_path(&self, path: &str, pos: Position) -> Result<(), Box<EvalAltResult>> {
+        if self.is_path_allowed(path) {
+            Ok(())
+        } else {
+            EvalAltResult::ErrorModuleRestricted(
+                path.to_string(),
+                "import path is not allowed".to_string(),
+                pos,
+            )
+            .into()
+        }
+    }
+    /// Check the function calls in the [`AST`] of a [module][Module] script.
+    fn check_functions(&self, ast: &AST, path: &str) -> Result<(), Box<EvalAltResult>> {
+        let mut denied = None;
+
+        ast.walk(&mut |nodes| match nodes.last() {
+            Some(ASTNode::Expr(Expr::FnCall(x, pos))) => {
+                let name = match x.namespace {
+                    Some(ref namespace) => format!("{}{}", namespace, x.name),
+                    // Operators
+                    None if !is_valid_identifier(x.name.chars()) => return true,
+                    None => x.name.to_string(),
+                };
+
+                // Functions defined in the script
+                #[cfg(not(feature = "no_function"))]
+                if x.namespace.is_none()
+                    && ast
+                        .iter_fn_def()
+                        .any(|f| f.name == x.name && f.params.len() == x.num_args())
+                {
+                    return true;
+                }
+
+                if self.is_function_allowed(&name) {
+                    true
+                } else {
+                    denied = Some((name, *pos));
+                    false
+                }
+            }
+            _ => true,
+        });
+
+        match denied {
+            Some((name, pos)) => EvalAltResult::ErrorModuleRestricted(
+                path.to_string(),
+                format!("call to function '{}' is not allowed", name),
+                pos,
+            )
+            .into(),
+            None => Ok(()),
+        }
+    }
+    /// Start loading an import, checking the depth and the total size of scripts loaded.
+    fn enter(&self, size: usize, path: &str, pos: Position) -> Result<(), Box<EvalAltResult>> {
+        let id = loader_id();
+        let mut loading = self.loading();
+
+        let index = match loading.iter().position(|l| l.id == id) {
+            Some(index) => index,
+            None => {
+                loading.push(Loading {
+                    id,
+                    depth: 0,
+                    size: 0,
+                });
+                loading.len() - 1
+            }
+        };
+
+        let Loading {
+            depth, size: total, ..
+        } = loading[index];
+
+        let violation = match (self.max_depth, self.max_source_size) {
+            (Some(max), _) if depth >= max.get() => {
+                Some(format!("import depth exceeds the limit of {}", max))
+            }
+            (_, Some(max)) if total + size > max.get() => Some(format!(
+                "total module source size exceeds the limit of {} bytes",
+                max
+            )),
+            _ => None,
+        };
+
+        if let Some(reason) = violation {
+            if depth == 0 {
+                loading.remove(index);
+            }
+            return EvalAltResult::ErrorModuleRestricted(path.to_string(), reason, pos).into();
+        }
+
+        loading[index].depth += 1;
+        loading[index].size += size;
+        Ok(())
+    }
+    /// Finish loading an import.
+    fn leave(&self) {
+        let id = loader_id();
+        let mut loading = self.loading();
+
+        if let Some(index) = loading.iter().position(|l| l.id == id) {
+            loading[index].depth -= 1;
+
+            if loading[index].depth == 0 {
+                loading.remove(index);
+            }
+        }
+    }
+}
+
+impl ModuleResolver for RestrictedModuleResolver {
+    fn resolve(
+        &self,
+        engine: &Engine,
+        source_path: Option<&str>,
+        path: &str,
+        pos: Position,
+    ) -> Result<Shared<Module>, Box<EvalAltResult>> {
+        self.check_path(path, pos)?;
+
+        let size = match self.max_source_size {
+            Some(_) => match self.resolver.resolve_script(engine, source_path, path, pos) {
+                Some(script) => script?.len(),
+                None => 0,
+            },
+            None => 0,
+        };
+
+        self.enter(size, path, pos)?;
+
+        let result = if self.has_function_restrictions() {
+            match self.resolver.resolve_ast(engine, source_path, path, pos) {
+                Some(Ok(ast)) => self.check_functions(&ast, path),
+                Some(Err(err)) => Err(err),
+                None => Ok(()),
+            }
+        } else {
+            Ok(())
+        }
+        .and_then(|_| self.resolver.resolve(engine, source_path, path, pos));
+
+        self.leave();
+
+        result
+    }
+
+    fn resolve_ast(
+        &self,
+        engine: &Engine,
+        source_path: Option<&str>,
+        path: &str,
+        pos: Position,
+    ) -> Option<Result<AST, Box<EvalAltResult>>> {
+        if let Err(err) = self.check_path(path, pos) {
+            return Some(Err(err));
+        }
+
+        self.resolver
+            .resolve_ast(engine, source_path, path, pos)
+            .map(|result| {
+                let ast = result?;
+                if self.has_function_restrictions() {
+                    self.check_functions(&ast, path)?;
+                }
+                Ok(ast)
+            })
+    }
+
+    fn resolve_script(
+        &self,
+        engine: &Engine,
+        source_path: Option<&str>,
+        path: &str,
+        pos: Position,
+    ) -> Option<Result<String, Box<EvalAltResult>>> {
+        if let Err(err) = self.check_path(path, pos) {
+            return Some(Err(err));
+        }
+
+        self.resolver.resolve_script(engine, source_path, path, pos)
+    }
+}
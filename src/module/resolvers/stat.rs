@@ -1,8 +1,20 @@
-use crate::stdlib::{boxed::Box, collections::BTreeMap, ops::AddAssign};
-use crate::{Engine, EvalAltResult, Identifier, Module, ModuleResolver, Position, Shared};
+use super::ImportChain;
+use crate::fn_native::Locked;
+use crate::stdlib::{
+    borrow::Cow,
+    boxed::Box,
+    collections::BTreeMap,
+    ops::{AddAssign, DerefMut},
+    string::{String, ToString},
+};
+use crate::{Engine, EvalAltResult, Identifier, Module, ModuleResolver, Position, Shared, AST};
 
 /// A static [module][Module] resolution service that serves [modules][Module] added into it.
 ///
+/// Scripts can also be added via [`insert_script`][StaticModuleResolver::insert_script]
+/// (for example by the [`embed_scripts!`][crate::embed_scripts] macro), in which case they are
+/// only compiled into [modules][Module] when first imported.
+///
 /// # Example
 ///
 /// ```
@@ -18,8 +30,25 @@ use crate::{Engine, EvalAltResult, Identifier, Module, ModuleResolver, Position,
 ///
 /// engine.set_module_resolver(resolver);
 /// ```
-#[derive(Debug, Clone, Default)]
-pub struct StaticModuleResolver(BTreeMap<Identifier, Shared<Module>>);
+#[derive(Debug, Default)]
+pub struct StaticModuleResolver {
+    modules: BTreeMap<Identifier, Shared<Module>>,
+    scripts: BTreeMap<Identifier, Cow<'static, str>>,
+    compiled: Locked<BTreeMap<Identifier, Shared<Module>>>,
+    chain: ImportChain,
+}
+
+impl Clone for StaticModuleResolver {
+    #[inline(always)]
+    fn clone(&self) -> Self {
+        Self {
+            modules: self.modules.clone(),
+            scripts: self.scripts.clone(),
+            compiled: Locked::new(self.compiled().clone()),
+            chain: Default::default(),
+        }
+    }
+}
 
 impl StaticModuleResolver {
     /// Create a new [`StaticModuleResolver`].
@@ -45,84 +74,244 @@ impl StaticModuleResolver {
     /// Add a [module][Module] keyed by its path.
     #[inline(always)]
     pub fn insert(&mut self, path: impl Into<Identifier>, mut module: Module) {
+        let path = path.into();
         module.build_index();
-        self.0.insert(path.into(), module.into());
+        self.scripts.remove(&path);
+        self.compiled().remove(&path);
+        self.modules.insert(path, module.into());
+    }
+    /// Add a script keyed by its path.
+    ///
+    /// The script is not compiled until the path is first resolved, after which the resulting
+    /// [module][Module] is kept.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::{Engine, INT};
+    /// use rhai::module_resolvers::StaticModuleResolver;
+    ///
+    /// let mut resolver = StaticModuleResolver::new();
+    ///
+    /// resolver.insert_script("hello", "export const ANSWER = 42;");
+    /// assert!(!resolver.is_compiled("hello"));
+    ///
+    /// let mut engine = Engine::new();
+    /// engine.set_module_resolver(resolver);
+    ///
+    /// assert_eq!(engine.eval::<INT>(r#"import "hello" as h; h::ANSWER"#)?, 42);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline(always)]
+    pub fn insert_script(
+        &mut self,
+        path: impl Into<Identifier>,
+        script: impl Into<Cow<'static, str>>,
+    ) {
+        let path = path.into();
+        self.modules.remove(&path);
+        self.compiled().remove(&path);
+        self.scripts.insert(path, script.into());
     }
     /// Remove a [module][Module] given its path.
+    ///
+    /// For a script, returns the compiled [module][Module], if it has been compiled.
     #[inline(always)]
     pub fn remove(&mut self, path: &str) -> Option<Shared<Module>> {
-        self.0.remove(path)
+        self.scripts.remove(path);
+        self.modules
+            .remove(path)
+            .or_else(|| self.compiled().remove(path))
     }
     /// Does the path exist?
     #[inline(always)]
     pub fn contains_path(&self, path: &str) -> bool {
-        self.0.contains_key(path)
+        self.modules.contains_key(path) || self.scripts.contains_key(path)
+    }
+    /// Is the path a script which has already been compiled, or a [module][Module]?
+    #[inline(always)]
+    pub fn is_compiled(&self, path: &str) -> bool {
+        self.modules.contains_key(path) || self.compiled().contains_key(path)
     }
     /// Get an iterator of all the [modules][Module].
+    ///
+    /// Scripts are not included.
     #[inline(always)]
     pub fn iter(&self) -> impl Iterator<Item = (&str, &Shared<Module>)> {
-        self.0.iter().map(|(k, v)| (k.as_str(), v))
+        self.modules.iter().map(|(k, v)| (k.as_str(), v))
     }
     /// Get a mutable iterator of all the [modules][Module].
+    ///
+    /// Scripts are not included.
     #[inline(always)]
     pub fn iter_mut(&mut self) -> impl Iterator<Item = (&str, &mut Shared<Module>)> {
-        self.0.iter_mut().map(|(k, v)| (k.as_str(), v))
+        self.modules.iter_mut().map(|(k, v)| (k.as_str(), v))
     }
     /// Get a mutable iterator of all the modules.
+    ///
+    /// Scripts are not included.
     #[inline(always)]
     pub fn into_iter(self) -> impl Iterator<Item = (Identifier, Shared<Module>)> {
-        self.0.into_iter()
+        self.modules.into_iter()
+    }
+    /// Get an iterator of all the scripts that are compiled only when first imported.
+    #[inline(always)]
+    pub(crate) fn iter_scripts(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.scripts.iter().map(|(k, v)| (k.as_str(), v.as_ref()))
     }
-    /// Get an iterator of all the [module][Module] paths.
+    /// Get an iterator of all the [module][Module] and script paths.
     #[inline(always)]
     pub fn paths(&self) -> impl Iterator<Item = &str> {
-        self.0.keys().map(|s| s.as_str())
+        self.modules
+            .keys()
+            .chain(self.scripts.keys())
+            .map(|s| s.as_str())
     }
     /// Get an iterator of all the [modules][Module].
+    ///
+    /// Scripts are not included.
     #[inline(always)]
     pub fn values(&self) -> impl Iterator<Item = &Shared<Module>> {
-        self.0.values().map(|m| m)
+        self.modules.values().map(|m| m)
     }
-    /// Remove all [modules][Module].
+    /// Remove all [modules][Module] and scripts.
     #[inline(always)]
     pub fn clear(&mut self) {
-        self.0.clear();
+        self.modules.clear();
+        self.scripts.clear();
+        self.compiled().clear();
     }
     /// Is this [`StaticModuleResolver`] empty?
     #[inline(always)]
     pub fn is_empty(&self) -> bool {
-        self.0.is_empty()
+        self.modules.is_empty() && self.scripts.is_empty()
     }
-    /// Get the number of [modules][Module] in this [`StaticModuleResolver`].
+    /// Get the number of [modules][Module] and scripts in this [`StaticModuleResolver`].
     #[inline(always)]
     pub fn len(&self) -> usize {
-        self.0.len()
+        self.modules.len() + self.scripts.len()
     }
     /// Merge another [`StaticModuleResolver`] into this.
     /// The other [`StaticModuleResolver`] is consumed.
     ///
-    /// Existing modules of the same path name are overwritten.
+    /// Existing modules and scripts of the same path name are overwritten.
     #[inline(always)]
     pub fn merge(&mut self, other: Self) {
         if !other.is_empty() {
-            self.0.extend(other.0.into_iter());
+            let compiled = crate::stdlib::mem::take(other.compiled().deref_mut());
+
+            for (path, module) in other.modules {
+                self.scripts.remove(&path);
+                self.compiled().remove(&path);
+                self.modules.insert(path, module);
+            }
+            for (path, script) in other.scripts {
+                self.modules.remove(&path);
+                self.compiled().remove(&path);
+                self.scripts.insert(path, script);
+            }
+            self.compiled().extend(compiled);
         }
     }
+    /// Get a mutable reference to the [modules][Module] compiled from scripts.
+    #[inline(always)]
+    fn compiled(&self) -> impl DerefMut<Target = BTreeMap<Identifier, Shared<Module>>> + '_ {
+        #[cfg(not(feature = "sync"))]
+        return self.compiled.borrow_mut();
+        #[cfg(feature = "sync")]
+        return self.compiled.write().unwrap();
+    }
+    /// Compile the script at a path into an [`AST`], with the path as its source.
+    fn compile(
+        &self,
+        engine: &Engine,
+        script: &str,
+        path: &str,
+        pos: Position,
+    ) -> Result<AST, Box<EvalAltResult>> {
+        let mut ast = engine.compile(script).map_err(|err| {
+            Box::new(EvalAltResult::ErrorInModule(
+                path.to_string(),
+                err.into(),
+                pos,
+            ))
+        })?;
+
+        ast.set_source(path);
+        Ok(ast)
+    }
 }
 
 impl ModuleResolver for StaticModuleResolver {
     #[inline(always)]
     fn resolve(
         &self,
-        _: &Engine,
+        engine: &Engine,
         _: Option<&str>,
         path: &str,
         pos: Position,
     ) -> Result<Shared<Module>, Box<EvalAltResult>> {
-        self.0
+        if let Some(module) = self.modules.get(path) {
+            return Ok(module.clone());
+        }
+
+        let script = self.scripts.get(path).ok_or_else(|| {
+            EvalAltResult::ErrorModuleNotFound(path.into(), Default::default(), pos)
+        })?;
+
+        let compiled = self.compiled().get(path).cloned();
+
+        if let Some(module) = compiled {
+            return Ok(module);
+        }
+
+        let ast = self.compile(engine, script, path, pos)?;
+
+        // Guard against cyclic imports
+        self.chain.enter(path.to_string(), pos)?;
+
+        // The lock is not held while evaluating, so the script can import other scripts
+        let result = Module::eval_ast_as_new(Default::default(), &ast, engine);
+
+        self.chain.leave();
+
+        let module: Shared<Module> = result
+            .map_err(|err| Box::new(EvalAltResult::ErrorInModule(path.to_string(), err, pos)))?
+            .into();
+
+        self.compiled().insert(path.into(), module.clone());
+
+        Ok(module)
+    }
+
+    /// Resolve an `AST` based on a path string.
+    ///
+    /// Returns [`None`] for [modules][Module] which were not added as scripts.
+    fn resolve_ast(
+        &self,
+        engine: &Engine,
+        _: Option<&str>,
+        path: &str,
+        pos: Position,
+    ) -> Option<Result<AST, Box<EvalAltResult>>> {
+        self.scripts
             .get(path)
-            .cloned()
-            .ok_or_else(|| EvalAltResult::ErrorModuleNotFound(path.into(), pos).into())
+            .map(|script| self.compile(engine, script, path, pos))
+    }
+
+    /// Resolve the script text of a module based on a path string.
+    ///
+    /// Returns [`None`] for [modules][Module] which were not added as scripts.
+    fn resolve_script(
+        &self,
+        _: &Engine,
+        _: Option<&str>,
+        path: &str,
+        _: Position,
+    ) -> Option<Result<String, Box<EvalAltResult>>> {
+        self.scripts.get(path).map(|script| Ok(script.to_string()))
     }
 }
 
@@ -2,7 +2,7 @@
 
 use crate::ast::{Expr, Stmt, StmtBlock};
 use crate::dynamic::AccessMode;
-use crate::engine::{KEYWORD_DEBUG, KEYWORD_EVAL, KEYWORD_PRINT, KEYWORD_TYPE_OF};
+use crate::engine::{FN_ASSERTIONS, KEYWORD_DEBUG, KEYWORD_EVAL, KEYWORD_PRINT, KEYWORD_TYPE_OF};
 use crate::fn_builtin::get_builtin_binary_op_fn;
 use crate::parser::map_dynamic_to_expr;
 use crate::stdlib::{
@@ -169,36 +169,55 @@ fn call_fn_with_constant_arguments(
         .map(|(v, _)| v)
 }
 
-/// Optimize a block of [statements][Stmt].
-fn optimize_stmt_block(
-    mut statements: Vec<Stmt>,
-    state: &mut State,
+/// A block of [statements][Stmt] being optimized by [`optimize_stmt_block`].
+struct BlockState {
+    statements: Vec<Stmt>,
     preserve_result: bool,
     is_internal: bool,
     reduce_return: bool,
-) -> Vec<Stmt> {
-    if statements.is_empty() {
-        return statements;
-    }
-
-    let mut is_dirty = state.is_dirty();
-
-    let is_pure = if is_internal {
-        Stmt::is_internally_pure
-    } else {
-        Stmt::is_pure
-    };
+    /// Has the [`AST`] been changed before or while optimizing this block?
+    is_dirty: bool,
+    /// Original number of constants in the state, for restore later
+    orig_constants_len: usize,
+    orig_propagate_constants: bool,
+    /// Index of the next statement to optimize.
+    index: usize,
+}
 
-    loop {
+impl BlockState {
+    /// Begin optimizing a block of [statements][Stmt].
+    fn new(
+        statements: Vec<Stmt>,
+        state: &mut State,
+        preserve_result: bool,
+        is_internal: bool,
+        reduce_return: bool,
+    ) -> Self {
+        let mut block = Self {
+            statements,
+            preserve_result,
+            is_internal,
+            reduce_return,
+            is_dirty: state.is_dirty(),
+            orig_constants_len: 0,
+            orig_propagate_constants: false,
+            index: 0,
+        };
+        block.begin_pass(state);
+        block
+    }
+    /// Begin an optimization pass over the block.
+    fn begin_pass(&mut self, state: &mut State) {
         state.clear_dirty();
 
-        let orig_constants_len = state.variables.len(); // Original number of constants in the state, for restore later
-        let orig_propagate_constants = state.propagate_constants;
+        self.orig_constants_len = state.variables.len();
+        self.orig_propagate_constants = state.propagate_constants;
+        self.index = 0;
 
         // Remove everything following control flow breaking statements
         let mut dead_code = false;
 
-        statements.retain(|stmt| {
+        self.statements.retain(|stmt| {
             if dead_code {
                 state.set_dirty();
                 false
@@ -209,31 +228,21 @@ fn optimize_stmt_block(
                 true
             }
         });
+    }
+    /// End an optimization pass over the block, after all the statements are optimized.
+    ///
+    /// Returns `true` if the block has changed and needs another pass.
+    fn end_pass(&mut self, state: &mut State) -> bool {
+        let is_pure = if self.is_internal {
+            Stmt::is_internally_pure
+        } else {
+            Stmt::is_pure
+        };
 
-        // Optimize each statement in the block
-        statements.iter_mut().for_each(|stmt| {
-            match stmt {
-                // Add constant literals into the state
-                Stmt::Const(value_expr, x, _, _) => {
-                    optimize_expr(value_expr, state);
-
-                    if value_expr.is_constant() {
-                        state.push_var(&x.name, AccessMode::ReadOnly, value_expr.clone());
-                    }
-                }
-                // Add variables into the state
-                Stmt::Let(value_expr, x, _, _) => {
-                    optimize_expr(value_expr, state);
-                    state.push_var(&x.name, AccessMode::ReadWrite, Expr::Unit(x.pos));
-                }
-                // Optimize the statement
-                _ => optimize_stmt(stmt, state, preserve_result),
-            }
-        });
-
-        // Remove all pure statements except the last one
+        // Remove all pure self.statements except the last one
         let mut index = 0;
-        let mut first_non_constant = statements
+        let mut first_non_constant = self
+            .statements
             .iter()
             .rev()
             .enumerate()
@@ -262,56 +271,58 @@ fn optimize_stmt_block(
 
                 _ => Some(i),
             })
-            .map_or(0, |n| statements.len() - n);
+            .map_or(0, |n| self.statements.len() - n);
 
-        while index < statements.len() {
-            if preserve_result && index >= statements.len() - 1 {
+        while index < self.statements.len() {
+            if self.preserve_result && index >= self.statements.len() - 1 {
                 break;
             } else {
-                match &statements[index] {
+                match &self.statements[index] {
                     stmt if is_pure(stmt) && index >= first_non_constant => {
                         state.set_dirty();
-                        statements.remove(index);
+                        self.statements.remove(index);
                     }
                     stmt if stmt.is_pure() => {
                         state.set_dirty();
                         if index < first_non_constant {
                             first_non_constant -= 1;
                         }
-                        statements.remove(index);
+                        self.statements.remove(index);
                     }
                     _ => index += 1,
                 }
             }
         }
 
-        // Remove all pure statements that do not return values at the end of a block.
-        // We cannot remove anything for non-pure statements due to potential side-effects.
-        if preserve_result {
+        // Remove all pure self.statements that do not return values at the end of a block.
+        // We cannot remove anything for non-pure self.statements due to potential side-effects.
+        if self.preserve_result {
             loop {
-                match &mut statements[..] {
+                match &mut self.statements[..] {
                     // { return; } -> {}
-                    [Stmt::Return(crate::ast::ReturnType::Return, None, _)] if reduce_return => {
+                    [Stmt::Return(crate::ast::ReturnType::Return, None, _)]
+                        if self.reduce_return =>
+                    {
                         state.set_dirty();
-                        statements.clear();
+                        self.statements.clear();
                     }
                     [stmt] if !stmt.returns_value() && is_pure(stmt) => {
                         state.set_dirty();
-                        statements.clear();
+                        self.statements.clear();
                     }
                     // { ...; return; } -> { ... }
                     [.., last_stmt, Stmt::Return(crate::ast::ReturnType::Return, None, _)]
-                        if reduce_return && !last_stmt.returns_value() =>
+                        if self.reduce_return && !last_stmt.returns_value() =>
                     {
                         state.set_dirty();
-                        statements.pop().unwrap();
+                        self.statements.pop().unwrap();
                     }
                     // { ...; return val; } -> { ...; val }
                     [.., Stmt::Return(crate::ast::ReturnType::Return, expr, pos)]
-                        if reduce_return =>
+                        if self.reduce_return =>
                     {
                         state.set_dirty();
-                        *statements.last_mut().unwrap() = if let Some(expr) = expr {
+                        *self.statements.last_mut().unwrap() = if let Some(expr) = expr {
                             Stmt::Expr(mem::take(expr))
                         } else {
                             Stmt::Noop(*pos)
@@ -323,9 +334,9 @@ fn optimize_stmt_block(
                     {
                         state.set_dirty();
                         if second_last_stmt.returns_value() {
-                            *statements.last_mut().unwrap() = Stmt::Noop(last_stmt.position());
+                            *self.statements.last_mut().unwrap() = Stmt::Noop(last_stmt.position());
                         } else {
-                            statements.pop().unwrap();
+                            self.statements.pop().unwrap();
                         }
                     }
                     _ => break,
@@ -333,28 +344,28 @@ fn optimize_stmt_block(
             }
         } else {
             loop {
-                match &statements[..] {
+                match &self.statements[..] {
                     [stmt] if is_pure(stmt) => {
                         state.set_dirty();
-                        statements.clear();
+                        self.statements.clear();
                     }
                     // { ...; return; } -> { ... }
                     [.., Stmt::Return(crate::ast::ReturnType::Return, None, _)]
-                        if reduce_return =>
+                        if self.reduce_return =>
                     {
                         state.set_dirty();
-                        statements.pop().unwrap();
+                        self.statements.pop().unwrap();
                     }
                     // { ...; return pure_val; } -> { ... }
                     [.., Stmt::Return(crate::ast::ReturnType::Return, Some(expr), _)]
-                        if reduce_return && expr.is_pure() =>
+                        if self.reduce_return && expr.is_pure() =>
                     {
                         state.set_dirty();
-                        statements.pop().unwrap();
+                        self.statements.pop().unwrap();
                     }
                     [.., last_stmt] if is_pure(last_stmt) => {
                         state.set_dirty();
-                        statements.pop().unwrap();
+                        self.statements.pop().unwrap();
                     }
                     _ => break,
                 }
@@ -362,22 +373,113 @@ fn optimize_stmt_block(
         }
 
         // Pop the stack and remove all the local constants
-        state.restore_var(orig_constants_len);
-        state.propagate_constants = orig_propagate_constants;
+        state.restore_var(self.orig_constants_len);
+        state.propagate_constants = self.orig_propagate_constants;
 
         if !state.is_dirty() {
-            break;
+            return false;
         }
 
-        is_dirty = true;
+        self.is_dirty = true;
+        true
     }
+    /// Finish optimizing the block.
+    fn finish(mut self, state: &mut State) -> Vec<Stmt> {
+        if self.is_dirty {
+            state.set_dirty();
+        }
 
-    if is_dirty {
-        state.set_dirty();
+        self.statements.shrink_to_fit();
+        mem::take(&mut self.statements)
     }
+}
 
-    statements.shrink_to_fit();
-    statements
+/// Optimize a block of [statements][Stmt].
+///
+/// Blocks nested directly in the block are optimized with an explicit stack of the enclosing
+/// blocks instead of recursively, so deeply-nested blocks do not overflow the call stack.
+fn optimize_stmt_block(
+    statements: Vec<Stmt>,
+    state: &mut State,
+    preserve_result: bool,
+    is_internal: bool,
+    reduce_return: bool,
+) -> Vec<Stmt> {
+    if statements.is_empty() {
+        return statements;
+    }
+
+    // Enclosing blocks, with the positions of the nested blocks
+    let mut outer: Vec<(BlockState, Position)> = Vec::new();
+    let mut block = BlockState::new(
+        statements,
+        state,
+        preserve_result,
+        is_internal,
+        reduce_return,
+    );
+
+    loop {
+        // Optimize each statement in the block
+        if let Some(stmt) = block.statements.get_mut(block.index) {
+            block.index += 1;
+
+            match stmt {
+                // Add constant literals into the state
+                Stmt::Const(value_expr, x, _, _) => {
+                    optimize_expr(value_expr, state);
+
+                    if value_expr.is_constant() {
+                        state.push_var(&x.name, AccessMode::ReadOnly, value_expr.clone());
+                    }
+                }
+                // Add variables into the state
+                Stmt::Let(value_expr, x, _, _) => {
+                    optimize_expr(value_expr, state);
+                    state.push_var(&x.name, AccessMode::ReadWrite, Expr::Unit(x.pos));
+                }
+                // { block } - optimize the nested block first
+                Stmt::Block(statements, pos) if !statements.is_empty() => {
+                    let statements = mem::take(statements);
+                    let pos = *pos;
+                    let preserve_result = block.preserve_result;
+                    outer.push((block, pos));
+                    block = BlockState::new(statements, state, preserve_result, true, false);
+                }
+                // Optimize the statement
+                _ => optimize_stmt(stmt, state, block.preserve_result),
+            }
+
+            continue;
+        }
+
+        if block.end_pass(state) {
+            block.begin_pass(state);
+            continue;
+        }
+
+        let statements = block.finish(state);
+
+        match outer.pop() {
+            None => return statements,
+            Some((parent, pos)) => {
+                block = parent;
+
+                block.statements[block.index - 1] = match statements {
+                    statements if statements.is_empty() => {
+                        state.set_dirty();
+                        Stmt::Noop(pos)
+                    }
+                    // Only one statement - promote
+                    mut statements if statements.len() == 1 => {
+                        state.set_dirty();
+                        statements.pop().unwrap()
+                    }
+                    statements => Stmt::Block(statements, pos),
+                };
+            }
+        }
+    }
 }
 
 /// Optimize a [statement][Stmt].
@@ -597,8 +699,159 @@ fn optimize_stmt(stmt: &mut Stmt, state: &mut State, preserve_result: bool) {
     }
 }
 
+/// Sub-expressions of an [expression][Expr] to optimize.
+#[derive(Debug, Clone, Copy)]
+enum Operands {
+    /// All sub-expressions.
+    All,
+    /// Only the left-hand side of a binary expression.
+    Lhs,
+    /// Only the right-hand side of a binary expression.
+    Rhs,
+}
+
+impl Operands {
+    /// Get the sub-expressions of an [expression][Expr].
+    fn get_mut(self, expr: &mut Expr) -> Vec<&mut Expr> {
+        match expr {
+            Expr::Dot(x, _) | Expr::Index(x, _) | Expr::And(x, _) | Expr::Or(x, _) => match self {
+                Self::All => vec![&mut x.lhs, &mut x.rhs],
+                Self::Lhs => vec![&mut x.lhs],
+                Self::Rhs => vec![&mut x.rhs],
+            },
+            Expr::InterpolatedString(x) | Expr::Array(x, _) => x.iter_mut().collect(),
+            Expr::Map(x, _) => x.0.iter_mut().map(|(_, expr)| expr).collect(),
+            Expr::FnCall(x, _) => x.args.iter_mut().collect(),
+            Expr::Custom(x, _) => x.keywords.iter_mut().collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// What to do with an [expression][Expr] after optimizing its sub-expressions.
+#[derive(Debug, Clone, Copy)]
+enum Post {
+    /// Nothing.
+    None,
+    /// Replace the expression with its only sub-expression.
+    Promote,
+    /// Merge consecutive strings in an interpolated string.
+    MergeStrings,
+    /// Move constant arguments of a function call to the right.
+    ///
+    /// If `true`, optimize the function call again instead if all its arguments become constants.
+    MoveConstantArgs(bool),
+}
+
 /// Optimize an [expression][Expr].
+///
+/// Sub-expressions are optimized with an explicit stack instead of recursively,
+/// so deeply-nested expressions do not overflow the call stack.
 fn optimize_expr(expr: &mut Expr, state: &mut State) {
+    enum Task {
+        /// Optimize an expression.
+        Enter(Expr),
+        /// Put the optimized sub-expressions back into an expression.
+        Exit(Expr, Operands, usize, Post),
+    }
+
+    let mut tasks = vec![Task::Enter(mem::take(expr))];
+    let mut done: Vec<Expr> = Vec::new();
+
+    while let Some(task) = tasks.pop() {
+        match task {
+            Task::Enter(mut expr) => match optimize_expr_node(&mut expr, state) {
+                Some((operands, post)) => {
+                    let sub_exprs: Vec<_> = operands
+                        .get_mut(&mut expr)
+                        .into_iter()
+                        .map(mem::take)
+                        .collect();
+                    tasks.push(Task::Exit(expr, operands, sub_exprs.len(), post));
+                    tasks.extend(sub_exprs.into_iter().rev().map(Task::Enter));
+                }
+                None => done.push(expr),
+            },
+            Task::Exit(mut expr, operands, len, post) => {
+                let sub_exprs = done.split_off(done.len() - len);
+
+                if let Post::Promote = post {
+                    done.extend(sub_exprs);
+                    continue;
+                }
+
+                operands
+                    .get_mut(&mut expr)
+                    .into_iter()
+                    .zip(sub_exprs)
+                    .for_each(|(expr, sub_expr)| *expr = sub_expr);
+
+                match (post, &mut expr) {
+                    (Post::MergeStrings, Expr::InterpolatedString(x)) => merge_strings(x, state),
+                    (Post::MoveConstantArgs(true), Expr::FnCall(x, _))
+                        if x.args.iter().all(Expr::is_constant) =>
+                    {
+                        tasks.push(Task::Enter(expr));
+                        continue;
+                    }
+                    (Post::MoveConstantArgs(_), Expr::FnCall(x, _)) => {
+                        // Move constant arguments to the right
+                        while x.args.last().map(Expr::is_constant).unwrap_or(false) {
+                            let arg = x.args.pop().unwrap();
+                            let arg_pos = arg.position();
+                            x.constant_args
+                                .insert(0, (arg.get_constant_value().unwrap(), arg_pos));
+                        }
+
+                        x.args.shrink_to_fit();
+                    }
+                    _ => (),
+                }
+
+                done.push(expr);
+            }
+        }
+    }
+
+    *expr = done.pop().unwrap();
+}
+
+/// Merge consecutive strings in an interpolated string.
+fn merge_strings(x: &mut StaticVec<Expr>, state: &mut State) {
+    let mut n = 0;
+
+    while n < x.len() - 1 {
+        match (&x[n], &x[n + 1]) {
+            (Expr::StringConstant(s1, pos), Expr::StringConstant(s2, _)) => {
+                x[n] = Expr::StringConstant(s1 + s2, *pos);
+                x.remove(n + 1);
+                state.set_dirty();
+            }
+            (_, Expr::Unit(_)) => {
+                x.remove(n + 1);
+                state.set_dirty();
+            }
+            (Expr::Unit(_), _) => {
+                x.remove(n);
+                state.set_dirty();
+            }
+            (_, Expr::StringConstant(s, _)) if s.is_empty() => {
+                x.remove(n + 1);
+                state.set_dirty();
+            }
+            (Expr::StringConstant(s, _), _) if s.is_empty() => {
+                x.remove(n);
+                state.set_dirty();
+            }
+            _ => n += 1,
+        }
+    }
+}
+
+/// Optimize an [expression][Expr], but not its sub-expressions.
+///
+/// Returns the sub-expressions to optimize, and what to do with the expression afterwards.
+fn optimize_expr_node(expr: &mut Expr, state: &mut State) -> Option<(Operands, Post)> {
     // These keywords are handled specially
     const DONT_EVAL_KEYWORDS: &[&str] = &[
         KEYWORD_PRINT, // side effects
@@ -608,19 +861,19 @@ fn optimize_expr(expr: &mut Expr, state: &mut State) {
 
     match expr {
         // {}
-        Expr::Stmt(x) if x.statements.is_empty() => { state.set_dirty(); *expr = Expr::Unit(x.pos) }
+        Expr::Stmt(x) if x.statements.is_empty() => { state.set_dirty(); *expr = Expr::Unit(x.pos); None }
         // { Stmt(Expr) }
         Expr::Stmt(x) if x.statements.len() == 1 && x.statements[0].is_pure() && matches!(x.statements[0], Stmt::Expr(_)) =>
         {
             state.set_dirty();
-            if let Stmt::Expr(e) = mem::take(&mut x.statements[0]) {
-                *expr = e;
-            } else {
-                unreachable!();
-            }
+            *expr = match x.statements[0] {
+                Stmt::Expr(ref mut e) => mem::take(e),
+                _ => unreachable!(),
+            };
+            None
         }
         // { stmt; ... } - do not count promotion as dirty because it gets turned back into an array
-        Expr::Stmt(x) => x.statements = optimize_stmt_block(mem::take(&mut x.statements).into_vec(), state, true, true, false).into(),
+        Expr::Stmt(x) => { x.statements = optimize_stmt_block(mem::take(&mut x.statements).into_vec(), state, true, true, false).into(); None }
         // lhs.rhs
         #[cfg(not(feature = "no_object"))]
         Expr::Dot(x, _) => match (&mut x.lhs, &mut x.rhs) {
@@ -633,11 +886,12 @@ fn optimize_expr(expr: &mut Expr, state: &mut State) {
                 *expr = mem::take(&mut m.0).into_iter().find(|(x, _)| &x.name == prop)
                             .map(|(_, mut expr)| { expr.set_position(*pos); expr })
                             .unwrap_or_else(|| Expr::Unit(*pos));
+                None
             }
             // var.rhs
-            (Expr::Variable(_), rhs) => optimize_expr(rhs, state),
+            (Expr::Variable(_), _) => Some((Operands::Rhs, Post::None)),
             // lhs.rhs
-            (lhs, rhs) => { optimize_expr(lhs, state); optimize_expr(rhs, state); }
+            (_, _) => Some((Operands::All, Post::None)),
         }
 
         // lhs[rhs]
@@ -653,6 +907,7 @@ fn optimize_expr(expr: &mut Expr, state: &mut State) {
                 let mut result = a.remove(*i as usize);
                 result.set_position(*pos);
                 *expr = result;
+                None
             }
             // map[string]
             (Expr::Map(m, pos), Expr::StringConstant(s, _)) if m.0.iter().all(|(_, x)| x.is_pure()) => {
@@ -662,142 +917,125 @@ fn optimize_expr(expr: &mut Expr, state: &mut State) {
                 *expr = mem::take(&mut m.0).into_iter().find(|(x, _)| x.name.as_str() == s.as_str())
                             .map(|(_, mut expr)| { expr.set_position(*pos); expr })
                             .unwrap_or_else(|| Expr::Unit(*pos));
+                None
             }
             // string[int]
             (Expr::StringConstant(s, pos), Expr::IntegerConstant(i, _)) if *i >= 0 && (*i as usize) < s.chars().count() => {
                 // String literal indexing - get the character
                 state.set_dirty();
                 *expr = Expr::CharConstant(s.chars().nth(*i as usize).unwrap(), *pos);
+                None
             }
             // var[rhs]
-            (Expr::Variable(_), rhs) => optimize_expr(rhs, state),
+            (Expr::Variable(_), _) => Some((Operands::Rhs, Post::None)),
             // lhs[rhs]
-            (lhs, rhs) => { optimize_expr(lhs, state); optimize_expr(rhs, state); }
+            (_, _) => Some((Operands::All, Post::None)),
         },
         // ``
         Expr::InterpolatedString(x) if x.is_empty() => {
             state.set_dirty();
             *expr = Expr::StringConstant(state.engine.empty_string.clone(), Position::NONE);
+            None
         }
         // `...`
         Expr::InterpolatedString(x) if x.len() == 1 && matches!(x[0], Expr::StringConstant(_, _)) => {
             state.set_dirty();
             *expr = mem::take(&mut x[0]);
+            None
         }
         // `... ${ ... } ...`
-        Expr::InterpolatedString(x) => {
-            x.iter_mut().for_each(|expr| optimize_expr(expr, state));
-
-            let mut n= 0;
-
-            // Merge consecutive strings
-            while n < x.len()-1 {
-                match (mem::take(&mut x[n]), mem::take(&mut x[n+1])) {
-                    (Expr::StringConstant(mut s1, pos), Expr::StringConstant(s2, _)) => {
-                        s1 += s2;
-                        x[n] = Expr::StringConstant(s1, pos);
-                        x.remove(n+1);
-                        state.set_dirty();
-                    }
-                    (expr1, Expr::Unit(_))  => {
-                        x[n] = expr1;
-                        x.remove(n+1);
-                        state.set_dirty();
-                    }
-                    (Expr::Unit(_), expr2) => {
-                        x[n+1] = expr2;
-                        x.remove(n);
-                        state.set_dirty();
-                    }
-                    (expr1, Expr::StringConstant(s, _)) if s.is_empty() => {
-                        x[n] = expr1;
-                        x.remove(n+1);
-                        state.set_dirty();
-                    }
-                    (Expr::StringConstant(s, _), expr2) if s.is_empty()=> {
-                        x[n+1] = expr2;
-                        x.remove(n);
-                        state.set_dirty();
-                    }
-                    (expr1, expr2) => {
-                        x[n] = expr1;
-                        x[n+1] = expr2;
-                        n += 1;
-                    }
-                }
-            }
-        }
+        Expr::InterpolatedString(_) => Some((Operands::All, Post::MergeStrings)),
         // [ constant .. ]
         #[cfg(not(feature = "no_index"))]
         Expr::Array(_, _) if expr.is_constant() => {
             state.set_dirty();
             *expr = Expr::DynamicConstant(Box::new(expr.get_constant_value().unwrap()), expr.position());
+            None
         }
         // [ items .. ]
         #[cfg(not(feature = "no_index"))]
-        Expr::Array(x, _) => x.iter_mut().for_each(|expr| optimize_expr(expr, state)),
+        Expr::Array(_, _) => Some((Operands::All, Post::None)),
         // #{ key:constant, .. }
         #[cfg(not(feature = "no_object"))]
         Expr::Map(_, _) if expr.is_constant() => {
             state.set_dirty();
             *expr = Expr::DynamicConstant(Box::new(expr.get_constant_value().unwrap()), expr.position());
+            None
         }
         // #{ key:value, .. }
         #[cfg(not(feature = "no_object"))]
-        Expr::Map(x, _) => x.0.iter_mut().for_each(|(_, expr)| optimize_expr(expr, state)),
+        Expr::Map(_, _) => Some((Operands::All, Post::None)),
         // lhs && rhs
         Expr::And(x, _) => match (&mut x.lhs, &mut x.rhs) {
             // true && rhs -> rhs
-            (Expr::BoolConstant(true, _), rhs) => {
+            (Expr::BoolConstant(true, _), _) => {
                 state.set_dirty();
-                optimize_expr(rhs, state);
-                *expr = mem::take(rhs);
+                Some((Operands::Rhs, Post::Promote))
             }
             // false && rhs -> false
             (Expr::BoolConstant(false, pos), _) => {
                 state.set_dirty();
                 *expr = Expr::BoolConstant(false, *pos);
+                None
             }
             // lhs && true -> lhs
-            (lhs, Expr::BoolConstant(true, _)) => {
+            (_, Expr::BoolConstant(true, _)) => {
                 state.set_dirty();
-                optimize_expr(lhs, state);
-                *expr = mem::take(lhs);
+                Some((Operands::Lhs, Post::Promote))
             }
             // lhs && rhs
-            (lhs, rhs) => { optimize_expr(lhs, state); optimize_expr(rhs, state); }
+            (_, _) => Some((Operands::All, Post::None)),
         },
         // lhs || rhs
         Expr::Or(ref mut x, _) => match (&mut x.lhs, &mut x.rhs) {
             // false || rhs -> rhs
-            (Expr::BoolConstant(false, _), rhs) => {
+            (Expr::BoolConstant(false, _), _) => {
                 state.set_dirty();
-                optimize_expr(rhs, state);
-                *expr = mem::take(rhs);
+                Some((Operands::Rhs, Post::Promote))
             }
             // true || rhs -> true
             (Expr::BoolConstant(true, pos), _) => {
                 state.set_dirty();
                 *expr = Expr::BoolConstant(true, *pos);
+                None
             }
             // lhs || false
-            (lhs, Expr::BoolConstant(false, _)) => {
+            (_, Expr::BoolConstant(false, _)) => {
                 state.set_dirty();
-                optimize_expr(lhs, state);
-                *expr = mem::take(lhs);
+                Some((Operands::Lhs, Post::Promote))
             }
             // lhs || rhs
-            (lhs, rhs) => { optimize_expr(lhs, state); optimize_expr(rhs, state); }
+            (_, _) => Some((Operands::All, Post::None)),
         },
 
         // eval!
         Expr::FnCall(x, _) if x.name == KEYWORD_EVAL => {
             state.propagate_constants = false;
+            None
         }
-        // Do not call some special keywords
-        Expr::FnCall(x, _) if DONT_EVAL_KEYWORDS.contains(&x.name.as_ref()) => {
-            x.args.iter_mut().for_each(|a| optimize_expr(a, state));
+        // Strip assertions
+        #[cfg(not(feature = "no_optimize"))]
+        Expr::FnCall(x, pos)
+                if x.namespace.is_none() // Non-qualified
+                && state.engine.strip_assertions // assertions stripped
+                && FN_ASSERTIONS.contains(&x.name.as_ref()) // built-in assertion
+        => {
+            // Script-defined functions can override built-in assertions
+            #[cfg(not(feature = "no_function"))]
+            let has_script_fn = state.lib.iter().any(|&m| m.get_script_fn(x.name.as_ref(), x.num_args()).is_some());
+            #[cfg(feature = "no_function")]
+            let has_script_fn = false;
+
+            if has_script_fn {
+                Some((Operands::All, Post::None))
+            } else {
+                state.set_dirty();
+                *expr = Expr::Unit(*pos);
+                None
+            }
         }
+        // Do not call some special keywords
+        Expr::FnCall(x, _) if DONT_EVAL_KEYWORDS.contains(&x.name.as_ref()) => Some((Operands::All, Post::None)),
 
         // Call built-in operators
         Expr::FnCall(x, pos)
@@ -825,20 +1063,11 @@ fn optimize_expr(expr: &mut Expr, state: &mut State) {
                 {
                     state.set_dirty();
                     *expr = result;
-                    return;
+                    return None;
                 }
             }
 
-            x.args.iter_mut().for_each(|a| optimize_expr(a, state));
-
-            // Move constant arguments to the right
-            while x.args.last().map(Expr::is_constant).unwrap_or(false) {
-                let arg = x.args.pop().unwrap();
-                let arg_pos = arg.position();
-                x.constant_args.insert(0, (arg.get_constant_value().unwrap(), arg_pos));
-            }
-
-            x.args.shrink_to_fit();
+            Some((Operands::All, Post::MoveConstantArgs(false)))
         }
 
         // Eagerly call functions
@@ -879,26 +1108,16 @@ fn optimize_expr(expr: &mut Expr, state: &mut State) {
                 {
                     state.set_dirty();
                     *expr = result;
-                    return;
+                    return None;
                 }
             }
 
-            x.args.iter_mut().for_each(|a| optimize_expr(a, state));
+            Some((Operands::All, Post::None))
         }
 
         // id(args ..) -> optimize function call arguments
-        Expr::FnCall(x, _) => {
-            x.args.iter_mut().for_each(|a| optimize_expr(a, state));
-
-            // Move constant arguments to the right
-            while x.args.last().map(Expr::is_constant).unwrap_or(false) {
-                let arg = x.args.pop().unwrap();
-                let arg_pos = arg.position();
-                x.constant_args.insert(0, (arg.get_constant_value().unwrap(), arg_pos));
-            }
-
-            x.args.shrink_to_fit();
-        }
+        // Retry the optimizations above if all arguments become constants
+        Expr::FnCall(x, _) => Some((Operands::All, Post::MoveConstantArgs(!x.args.iter().all(Expr::is_constant)))),
 
         // constant-name
         Expr::Variable(x) if x.1.is_none() && state.find_constant(&x.2.name).is_some() => {
@@ -908,6 +1127,7 @@ fn optimize_expr(expr: &mut Expr, state: &mut State) {
             let mut result = state.find_constant(&x.2.name).unwrap().clone();
             result.set_position(x.2.pos);
             *expr = result;
+            None
         }
 
         // Custom syntax
@@ -915,11 +1135,11 @@ fn optimize_expr(expr: &mut Expr, state: &mut State) {
             if x.scope_delta != 0 {
                 state.propagate_constants = false;
             }
-            x.keywords.iter_mut().for_each(|expr| optimize_expr(expr, state));
+            Some((Operands::All, Post::None))
         }
 
         // All other expressions - skip
-        _ => (),
+        _ => None,
     }
 }
 
@@ -990,6 +1210,7 @@ pub fn optimize_into_ast(
                     #[cfg(not(feature = "no_module"))]
                     mods: Default::default(),
                     comments: Default::default(),
+                    bytecode: None,
                 })
                 .for_each(|fn_def| {
                     lib2.set_script_fn(fn_def);
@@ -1015,6 +1236,7 @@ pub fn optimize_into_ast(
                         statements: body.into(),
                         pos,
                     };
+                    fn_def.bytecode = None;
 
                     fn_def
                 })
@@ -0,0 +1,115 @@
+use super::string_basic::{print_with_func, FUNC_TO_DEBUG};
+use crate::engine::OP_EQUALS;
+use crate::plugin::*;
+use crate::stdlib::{boxed::Box, string::String};
+use crate::{def_package, Dynamic, EvalAltResult, NativeCallContext, Position};
+
+#[cfg(not(feature = "no_float"))]
+use crate::stdlib::format;
+
+def_package!(crate:BasicAssertPackage:"Basic assertion functions.", lib, {
+    combine_with_exported_module!(lib, "assert", assert_functions);
+});
+
+/// Make an [`ErrorAssertion`][EvalAltResult::ErrorAssertion] with the operands rendered via `to_debug`.
+fn make_error(
+    ctx: &NativeCallContext,
+    message: String,
+    mut left: Dynamic,
+    mut right: Dynamic,
+) -> Box<EvalAltResult> {
+    let left = print_with_func(FUNC_TO_DEBUG, ctx, &mut left).into();
+    let right = print_with_func(FUNC_TO_DEBUG, ctx, &mut right).into();
+
+    let operands = Some(Box::new((left, right)));
+
+    EvalAltResult::ErrorAssertion(message, operands, String::new(), Position::NONE).into()
+}
+
+/// Compare two values for equality via the `==` operator.
+///
+/// Values of different types without an `==` operator defined are not equal.
+fn is_equal(
+    ctx: &NativeCallContext,
+    left: &mut Dynamic,
+    right: &mut Dynamic,
+) -> Result<bool, Box<EvalAltResult>> {
+    // The second operand may be consumed by the call
+    let result = ctx.call_fn_dynamic_raw(OP_EQUALS, true, &mut [&mut *left, &mut right.clone()]);
+
+    result
+        .or_else(|err| match *err {
+            EvalAltResult::ErrorFunctionNotFound(ref fn_sig, _)
+                if fn_sig.starts_with(OP_EQUALS) && left.type_id() != right.type_id() =>
+            {
+                Ok(Dynamic::FALSE)
+            }
+            _ => Err(err),
+        })
+        .map(|v| v.as_bool().unwrap_or(false))
+}
+
+#[export_module]
+mod assert_functions {
+    #[rhai_fn(name = "assert", return_raw)]
+    pub fn assert(condition: bool) -> Result<(), Box<EvalAltResult>> {
+        assert_with_message(condition, "")
+    }
+    #[rhai_fn(name = "assert", return_raw)]
+    pub fn assert_with_message(condition: bool, message: &str) -> Result<(), Box<EvalAltResult>> {
+        if condition {
+            Ok(())
+        } else {
+            EvalAltResult::ErrorAssertion(message.into(), None, String::new(), Position::NONE)
+                .into()
+        }
+    }
+    #[rhai_fn(return_raw)]
+    pub fn assert_eq(
+        ctx: NativeCallContext,
+        left: Dynamic,
+        right: Dynamic,
+    ) -> Result<(), Box<EvalAltResult>> {
+        let (mut left, mut right) = (left, right);
+
+        if is_equal(&ctx, &mut left, &mut right)? {
+            Ok(())
+        } else {
+            Err(make_error(&ctx, String::new(), left, right))
+        }
+    }
+    #[rhai_fn(return_raw)]
+    pub fn assert_ne(
+        ctx: NativeCallContext,
+        left: Dynamic,
+        right: Dynamic,
+    ) -> Result<(), Box<EvalAltResult>> {
+        let (mut left, mut right) = (left, right);
+
+        if is_equal(&ctx, &mut left, &mut right)? {
+            Err(make_error(&ctx, String::new(), left, right))
+        } else {
+            Ok(())
+        }
+    }
+
+    #[cfg(not(feature = "no_float"))]
+    pub mod float_functions {
+        use crate::FLOAT;
+
+        #[rhai_fn(return_raw)]
+        pub fn assert_approx(
+            ctx: NativeCallContext,
+            left: FLOAT,
+            right: FLOAT,
+            epsilon: FLOAT,
+        ) -> Result<(), Box<EvalAltResult>> {
+            if (left - right).abs() <= epsilon {
+                Ok(())
+            } else {
+                let message = format!("difference exceeds {}", epsilon);
+                Err(make_error(&ctx, message, left.into(), right.into()))
+            }
+        }
+    }
+}
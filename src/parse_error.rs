@@ -159,6 +159,11 @@ pub enum ParseErrorType {
     ///
     /// Never appears under the `no_module` feature.
     WrongExport,
+    /// Import path that is not a string literal, when computed import paths are disabled via
+    /// [`Engine::enable_computed_imports`][crate::Engine::enable_computed_imports].
+    ///
+    /// Never appears under the `no_module` feature.
+    ComputedImportPath,
     /// Assignment to an a constant variable. Wrapped value is the constant variable name.
     AssignmentToConstant(String),
     /// Assignment to an inappropriate LHS (left-hand-side) expression.
@@ -207,6 +212,7 @@ impl ParseErrorType {
             Self::FnMissingBody(_) => "Expecting body statement block for function declaration",
             Self::WrongDocComment => "Doc-comment must be followed immediately by a function definition",
             Self::WrongExport => "Export statement can only appear at global level",
+            Self::ComputedImportPath => "Import path must be a string literal",
             Self::AssignmentToConstant(_) => "Cannot assign to a constant value",
             Self::AssignmentToInvalidLHS(_) => "Expression cannot be assigned to",
             Self::ExprTooDeep => "Expression exceeds maximum complexity",
@@ -214,6 +220,49 @@ impl ParseErrorType {
             Self::LoopBreak => "Break statement should only be used inside a loop"
         }
     }
+    /// Get a short code identifying this type of error, e.g. `missing_token`.
+    ///
+    /// Errors in the script text (i.e. [`BadInput`][ParseErrorType::BadInput]) are identified by
+    /// the type of [`LexError`].
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::UnexpectedEOF => "unexpected_eof",
+            Self::BadInput(LexError::UnexpectedInput(_)) => "unexpected_input",
+            Self::BadInput(LexError::UnterminatedString) => "unterminated_string",
+            Self::BadInput(LexError::StringTooLong(_)) => "string_too_long",
+            Self::BadInput(LexError::MalformedEscapeSequence(_)) => "malformed_escape_sequence",
+            Self::BadInput(LexError::MalformedNumber(_)) => "malformed_number",
+            Self::BadInput(LexError::MalformedChar(_)) => "malformed_char",
+            Self::BadInput(LexError::MalformedIdentifier(_)) => "malformed_identifier",
+            Self::BadInput(LexError::ImproperSymbol(_, _)) => "improper_symbol",
+            Self::UnknownOperator(_) => "unknown_operator",
+            Self::MissingToken(_, _) => "missing_token",
+            Self::MalformedCallExpr(_) => "malformed_call_expr",
+            Self::MalformedIndexExpr(_) => "malformed_index_expr",
+            Self::MalformedInExpr(_) => "malformed_in_expr",
+            Self::MalformedCapture(_) => "malformed_capture",
+            Self::DuplicatedProperty(_) => "duplicated_property",
+            Self::DuplicatedSwitchCase => "duplicated_switch_case",
+            Self::PropertyExpected => "property_expected",
+            Self::VariableExpected => "variable_expected",
+            Self::Reserved(_) => "reserved",
+            Self::ExprExpected(_) => "expr_expected",
+            Self::WrongDocComment => "wrong_doc_comment",
+            Self::WrongFnDefinition => "wrong_fn_definition",
+            Self::FnDuplicatedDefinition(_, _) => "fn_duplicated_definition",
+            Self::FnMissingName => "fn_missing_name",
+            Self::FnMissingParams(_) => "fn_missing_params",
+            Self::FnDuplicatedParam(_, _) => "fn_duplicated_param",
+            Self::FnMissingBody(_) => "fn_missing_body",
+            Self::WrongExport => "wrong_export",
+            Self::ComputedImportPath => "computed_import_path",
+            Self::AssignmentToConstant(_) => "assignment_to_constant",
+            Self::AssignmentToInvalidLHS(_) => "assignment_to_invalid_lhs",
+            Self::ExprTooDeep => "expr_too_deep",
+            Self::LiteralTooLarge(_, _) => "literal_too_large",
+            Self::LoopBreak => "loop_break",
+        }
+    }
 }
 
 impl fmt::Display for ParseErrorType {
@@ -293,6 +342,15 @@ pub struct ParseError(pub Box<ParseErrorType>, pub Position);
 
 impl Error for ParseError {}
 
+impl ParseError {
+    /// Get the [`Span`][crate::Span] of the expression or token where this error occurs in the
+    /// script, as carried by its [position][Position].
+    #[inline(always)]
+    pub fn span(&self) -> crate::Span {
+        self.1.span()
+    }
+}
+
 impl fmt::Display for ParseError {
     #[inline(always)]
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
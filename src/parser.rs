@@ -5,24 +5,26 @@ use crate::ast::{
     ScriptFnDef, Stmt, StmtBlock,
 };
 use crate::dynamic::{AccessMode, Union};
-use crate::engine::{Precedence, KEYWORD_THIS, OP_CONTAINS};
+use crate::engine::{Precedence, FN_ASSERTIONS, KEYWORD_THIS, OP_CONTAINS};
 use crate::module::NamespaceRef;
 use crate::optimize::optimize_into_ast;
 use crate::optimize::OptimizationLevel;
 use crate::stdlib::{
     boxed::Box,
-    cell::Cell,
     collections::BTreeMap,
     format,
     hash::{Hash, Hasher},
     iter::empty,
+    mem,
     num::NonZeroUsize,
     string::{String, ToString},
     vec,
     vec::Vec,
 };
 use crate::syntax::{CustomSyntax, MARKER_BLOCK, MARKER_EXPR, MARKER_IDENT};
-use crate::token::{is_keyword_function, is_valid_identifier, Token, TokenStream};
+use crate::token::{
+    is_keyword_function, is_valid_identifier, Span, Token, TokenStream, TokenizerControl,
+};
 use crate::utils::{get_hasher, IdentifierBuilder};
 use crate::{
     calc_fn_hash, Dynamic, Engine, Identifier, LexError, ParseError, ParseErrorType, Position,
@@ -44,8 +46,8 @@ type FunctionsLib = BTreeMap<u64, Shared<ScriptFnDef>>;
 pub struct ParseState<'e> {
     /// Reference to the scripting [`Engine`].
     engine: &'e Engine,
-    /// Input stream buffer containing the next character to read.
-    buffer: Shared<Cell<Option<char>>>,
+    /// State shared with the tokenizer.
+    tokenizer_control: TokenizerControl,
     /// Interned strings.
     interned_strings: IdentifierBuilder,
     /// Encapsulates a local stack with variable names to simulate an actual runtime scope.
@@ -71,6 +73,11 @@ pub struct ParseState<'e> {
     #[cfg(not(feature = "unchecked"))]
     #[cfg(not(feature = "no_function"))]
     max_function_expr_depth: Option<NonZeroUsize>,
+    /// Errors collected so far when parsing in error-recovery mode.
+    /// If `None`, parsing stops at the first error.
+    errors: Option<Vec<ParseError>>,
+    /// Text of the scripts being parsed, if available.
+    scripts: &'e [&'e str],
 }
 
 impl<'e> ParseState<'e> {
@@ -78,7 +85,7 @@ impl<'e> ParseState<'e> {
     #[inline(always)]
     pub fn new(
         engine: &'e Engine,
-        buffer: Shared<Cell<Option<char>>>,
+        tokenizer_control: TokenizerControl,
         #[cfg(not(feature = "unchecked"))] max_expr_depth: Option<NonZeroUsize>,
         #[cfg(not(feature = "unchecked"))]
         #[cfg(not(feature = "no_function"))]
@@ -86,7 +93,7 @@ impl<'e> ParseState<'e> {
     ) -> Self {
         Self {
             engine,
-            buffer,
+            tokenizer_control,
             #[cfg(not(feature = "unchecked"))]
             max_expr_depth,
             #[cfg(not(feature = "unchecked"))]
@@ -101,6 +108,55 @@ impl<'e> ParseState<'e> {
             entry_stack_len: 0,
             #[cfg(not(feature = "no_module"))]
             modules: Default::default(),
+            errors: None,
+            scripts: &[],
+        }
+    }
+    /// Turn on error-recovery mode, collecting all errors instead of stopping at the first one.
+    #[inline(always)]
+    pub fn enable_error_recovery(&mut self) {
+        self.errors = Some(Default::default());
+    }
+    /// Set the text of the scripts being parsed, used to capture the source text of assertions.
+    #[inline(always)]
+    pub fn set_scripts(&mut self, scripts: &'e [&'e str]) {
+        self.scripts = scripts;
+    }
+    /// Get the source text of the scripts between two [positions][Position] (inclusive), if available.
+    pub fn source_text(&self, start: Position, end: Position) -> Option<String> {
+        if start.is_none() || end.is_none() || start > end {
+            return None;
+        }
+
+        let span = Span::new(start, end)?;
+
+        match self.scripts {
+            [] => None,
+            [script] => span.byte_range(script).map(|r| script[r].to_string()),
+            scripts => {
+                let script = scripts.concat();
+                span.byte_range(&script).map(|r| script[r].to_string())
+            }
+        }
+    }
+    /// Take all the errors collected in error-recovery mode.
+    #[inline(always)]
+    pub fn take_errors(&mut self) -> Vec<ParseError> {
+        self.errors
+            .as_mut()
+            .map_or_else(Default::default, crate::stdlib::mem::take)
+    }
+    /// Set the [`Span`] of an expression, from `start` up to the last token consumed.
+    fn set_span(&self, input: &mut TokenStream, start: Position, expr: &mut Expr) {
+        let next = input.peek().unwrap().1;
+
+        if let Some(span) = self
+            .tokenizer_control
+            .borrow()
+            .end_before(next)
+            .and_then(|end| Span::new(start, end))
+        {
+            expr.set_span(span);
         }
     }
 
@@ -174,6 +230,28 @@ impl<'e> ParseState<'e> {
     pub fn get_identifier(&mut self, text: impl AsRef<str> + Into<Identifier>) -> Identifier {
         self.interned_strings.get(text)
     }
+
+    /// Cache the hash key for a namespace-qualified variable at the root of an expression.
+    fn cache_namespace_hash(&self, expr: &mut Expr) {
+        let x = match expr {
+            Expr::Variable(x) if x.1.is_some() => x,
+            Expr::Index(x, _) | Expr::Dot(x, _) => match &mut x.lhs {
+                Expr::Variable(x) if x.1.is_some() => x,
+                _ => return,
+            },
+            _ => return,
+        };
+
+        match x.as_mut() {
+            (_, Some((ref mut hash, ref mut namespace)), Ident { name, .. }) => {
+                *hash = calc_fn_hash(namespace.iter().map(|v| v.name.as_str()), name, 0);
+
+                #[cfg(not(feature = "no_module"))]
+                namespace.set_index(self.find_module(&namespace[0].name));
+            }
+            _ => unreachable!("expecting namespace-qualified variable access"),
+        }
+    }
 }
 
 /// A type that encapsulates all the settings for a particular parsing function.
@@ -229,10 +307,10 @@ impl Expr {
     /// All other variants are untouched.
     #[cfg(not(feature = "no_object"))]
     #[inline(always)]
-    fn into_property(self, state: &mut ParseState) -> Self {
+    fn into_property(mut self, state: &mut ParseState) -> Self {
         match self {
-            Self::Variable(x) if x.1.is_none() => {
-                let ident = x.2;
+            Self::Variable(ref mut x) if x.1.is_none() => {
+                let ident = mem::take(&mut x.2);
                 let getter = state.get_identifier(crate::engine::make_getter(&ident.name));
                 let hash_get = calc_fn_hash(empty(), &getter, 1);
                 let setter = state.get_identifier(crate::engine::make_setter(&ident.name));
@@ -274,37 +352,88 @@ fn match_token(input: &mut TokenStream, token: Token) -> (bool, Position) {
     }
 }
 
-/// Parse ( expr )
-fn parse_paren_expr(
+/// Handle a parse error at statement level.
+///
+/// If the [`ParseState`] is not in error-recovery mode, the error is simply returned.
+///
+/// Otherwise, the error is recorded and the input stream is skipped up to the next statement
+/// boundary: a `;` (which is consumed), a `}` closing the current block (which is not consumed,
+/// except at global level where there is no enclosing block), or a keyword that can only start
+/// a new statement.
+///
+/// `stmt_pos` is the starting position of the statement in error. At least one token is always
+/// skipped if the input has not advanced beyond it, so parsing is guaranteed to make progress.
+fn recover_from_error(
     input: &mut TokenStream,
     state: &mut ParseState,
-    lib: &mut FunctionsLib,
-    mut settings: ParseSettings,
-) -> Result<Expr, ParseError> {
-    #[cfg(not(feature = "unchecked"))]
-    settings.ensure_level_within_max_limit(state.max_expr_depth)?;
+    err: ParseError,
+    stmt_pos: Position,
+    is_global: bool,
+) -> Result<(), ParseError> {
+    match state.errors {
+        Some(ref mut errors) => errors.push(err),
+        None => return Err(err),
+    }
 
-    // ( ...
-    settings.pos = eat_token(input, Token::LeftParen);
+    let mut level = 0_usize;
 
-    if match_token(input, Token::RightParen).0 {
-        return Ok(Expr::Unit(settings.pos));
-    }
+    loop {
+        let (token, pos) = input.peek().unwrap();
+        let advanced = *pos != stmt_pos;
 
-    let expr = parse_expr(input, state, lib, settings.level_up())?;
+        match token {
+            Token::EOF => break,
+            Token::SemiColon if level == 0 => {
+                eat_token(input, Token::SemiColon);
+                break;
+            }
+            Token::RightBrace if level == 0 && is_global => {
+                eat_token(input, Token::RightBrace);
+                break;
+            }
+            Token::RightBrace if level == 0 && advanced => break,
+            Token::RightBrace if level > 0 => level -= 1,
+            Token::LeftBrace | Token::MapStart => level += 1,
+
+            Token::Let
+            | Token::Const
+            | Token::While
+            | Token::Loop
+            | Token::Do
+            | Token::For
+            | Token::Return
+            | Token::Throw
+            | Token::Try
+            | Token::Continue
+            | Token::Break
+                if level == 0 && advanced =>
+            {
+                break
+            }
+            #[cfg(not(feature = "no_function"))]
+            Token::Fn | Token::Private if level == 0 && advanced => break,
+            #[cfg(not(feature = "no_module"))]
+            Token::Import | Token::Export if level == 0 && advanced => break,
 
-    match input.next().unwrap() {
-        // ( xxx )
-        (Token::RightParen, _) => Ok(expr),
-        // ( <error>
-        (Token::LexError(err), pos) => return Err(err.into_err(pos)),
-        // ( xxx ???
-        (_, pos) => Err(PERR::MissingToken(
-            Token::RightParen.into(),
-            "for a matching ( in this expression".into(),
-        )
-        .into_err(pos)),
+            _ => (),
+        }
+
+        input.next().unwrap();
     }
+
+    Ok(())
+}
+
+/// Parse ( expr ), an array literal or an object map literal.
+fn parse_nested_expr(
+    input: &mut TokenStream,
+    state: &mut ParseState,
+    lib: &mut FunctionsLib,
+    settings: ParseSettings,
+) -> Result<Expr, ParseError> {
+    let mut stack = Vec::new();
+    let step = begin_nested(input, state, &mut stack, settings)?;
+    parse_nested(input, state, lib, stack, step)
 }
 
 /// Parse a function call.
@@ -382,7 +511,16 @@ fn parse_fn_call(
         match input.peek().unwrap() {
             // id(...args)
             (Token::RightParen, _) => {
-                eat_token(input, Token::RightParen);
+                let end_pos = eat_token(input, Token::RightParen);
+
+                // Capture the source text of assertions
+                let source_text = if namespace.is_none() && FN_ASSERTIONS.contains(&id.as_str()) {
+                    state
+                        .source_text(settings.pos, end_pos)
+                        .map(|text| text.into())
+                } else {
+                    None
+                };
 
                 let hash = if let Some(modules) = namespace.as_mut() {
                     #[cfg(not(feature = "no_module"))]
@@ -406,6 +544,7 @@ fn parse_fn_call(
                         namespace,
                         hash,
                         args,
+                        source_text,
                         ..Default::default()
                     }),
                     settings.pos,
@@ -444,351 +583,169 @@ fn parse_index_chain(
     input: &mut TokenStream,
     state: &mut ParseState,
     lib: &mut FunctionsLib,
-    lhs: Expr,
+    mut lhs: Expr,
     mut settings: ParseSettings,
 ) -> Result<Expr, ParseError> {
-    #[cfg(not(feature = "unchecked"))]
-    settings.ensure_level_within_max_limit(state.max_expr_depth)?;
+    // Outer levels of indexing, with their positions, waiting for the inner levels
+    let mut outer: Vec<(Expr, Position)> = Vec::new();
 
-    let idx_expr = parse_expr(input, state, lib, settings.level_up())?;
+    loop {
+        #[cfg(not(feature = "unchecked"))]
+        settings.ensure_level_within_max_limit(state.max_expr_depth)?;
 
-    // Check type of indexing - must be integer or string
-    match &idx_expr {
-        // lhs[int]
-        Expr::IntegerConstant(x, pos) if *x < 0 => {
-            return Err(PERR::MalformedIndexExpr(format!(
-                "Array access expects non-negative index: {} < 0",
-                *x
-            ))
-            .into_err(*pos))
-        }
-        Expr::IntegerConstant(_, pos) => match lhs {
-            Expr::Array(_, _) | Expr::StringConstant(_, _) | Expr::InterpolatedString(_) => (),
+        let idx_expr = parse_expr(input, state, lib, settings.level_up())?;
 
-            Expr::Map(_, _) => {
-                return Err(PERR::MalformedIndexExpr(
-                    "Object map access expects string index, not a number".into(),
-                )
+        // Check type of indexing - must be integer or string
+        match &idx_expr {
+            // lhs[int]
+            Expr::IntegerConstant(x, pos) if *x < 0 => {
+                return Err(PERR::MalformedIndexExpr(format!(
+                    "Array access expects non-negative index: {} < 0",
+                    *x
+                ))
                 .into_err(*pos))
             }
+            Expr::IntegerConstant(_, pos) => match lhs {
+                Expr::Array(_, _) | Expr::StringConstant(_, _) | Expr::InterpolatedString(_) => (),
 
-            #[cfg(not(feature = "no_float"))]
-            Expr::FloatConstant(_, _) => {
-                return Err(PERR::MalformedIndexExpr(
-                    "Only arrays, object maps and strings can be indexed".into(),
-                )
-                .into_err(lhs.position()))
-            }
-
-            Expr::CharConstant(_, _)
-            | Expr::And(_, _)
-            | Expr::Or(_, _)
-            | Expr::BoolConstant(_, _)
-            | Expr::Unit(_) => {
-                return Err(PERR::MalformedIndexExpr(
-                    "Only arrays, object maps and strings can be indexed".into(),
-                )
-                .into_err(lhs.position()))
-            }
-
-            _ => (),
-        },
-
-        // lhs[string]
-        Expr::StringConstant(_, _) | Expr::InterpolatedString(_) => match lhs {
-            Expr::Map(_, _) => (),
-
-            Expr::Array(_, _) | Expr::StringConstant(_, _) | Expr::InterpolatedString(_) => {
-                return Err(PERR::MalformedIndexExpr(
-                    "Array or string expects numeric index, not a string".into(),
-                )
-                .into_err(idx_expr.position()))
-            }
-
-            #[cfg(not(feature = "no_float"))]
-            Expr::FloatConstant(_, _) => {
-                return Err(PERR::MalformedIndexExpr(
-                    "Only arrays, object maps and strings can be indexed".into(),
-                )
-                .into_err(lhs.position()))
-            }
+                Expr::Map(_, _) => {
+                    return Err(PERR::MalformedIndexExpr(
+                        "Object map access expects string index, not a number".into(),
+                    )
+                    .into_err(*pos))
+                }
 
-            Expr::CharConstant(_, _)
-            | Expr::And(_, _)
-            | Expr::Or(_, _)
-            | Expr::BoolConstant(_, _)
-            | Expr::Unit(_) => {
-                return Err(PERR::MalformedIndexExpr(
-                    "Only arrays, object maps and strings can be indexed".into(),
-                )
-                .into_err(lhs.position()))
-            }
+                #[cfg(not(feature = "no_float"))]
+                Expr::FloatConstant(_, _) => {
+                    return Err(PERR::MalformedIndexExpr(
+                        "Only arrays, object maps and strings can be indexed".into(),
+                    )
+                    .into_err(lhs.position()))
+                }
 
-            _ => (),
-        },
+                Expr::CharConstant(_, _)
+                | Expr::And(_, _)
+                | Expr::Or(_, _)
+                | Expr::BoolConstant(_, _)
+                | Expr::Unit(_) => {
+                    return Err(PERR::MalformedIndexExpr(
+                        "Only arrays, object maps and strings can be indexed".into(),
+                    )
+                    .into_err(lhs.position()))
+                }
 
-        // lhs[float]
-        #[cfg(not(feature = "no_float"))]
-        x @ Expr::FloatConstant(_, _) => {
-            return Err(PERR::MalformedIndexExpr(
-                "Array access expects integer index, not a float".into(),
-            )
-            .into_err(x.position()))
-        }
-        // lhs[char]
-        x @ Expr::CharConstant(_, _) => {
-            return Err(PERR::MalformedIndexExpr(
-                "Array access expects integer index, not a character".into(),
-            )
-            .into_err(x.position()))
-        }
-        // lhs[()]
-        x @ Expr::Unit(_) => {
-            return Err(PERR::MalformedIndexExpr(
-                "Array access expects integer index, not ()".into(),
-            )
-            .into_err(x.position()))
-        }
-        // lhs[??? && ???], lhs[??? || ???]
-        x @ Expr::And(_, _) | x @ Expr::Or(_, _) => {
-            return Err(PERR::MalformedIndexExpr(
-                "Array access expects integer index, not a boolean".into(),
-            )
-            .into_err(x.position()))
-        }
-        // lhs[true], lhs[false]
-        x @ Expr::BoolConstant(_, _) => {
-            return Err(PERR::MalformedIndexExpr(
-                "Array access expects integer index, not a boolean".into(),
-            )
-            .into_err(x.position()))
-        }
-        // All other expressions
-        _ => (),
-    }
+                _ => (),
+            },
 
-    // Check if there is a closing bracket
-    match input.peek().unwrap() {
-        (Token::RightBracket, _) => {
-            eat_token(input, Token::RightBracket);
+            // lhs[string]
+            Expr::StringConstant(_, _) | Expr::InterpolatedString(_) => match lhs {
+                Expr::Map(_, _) => (),
 
-            // Any more indexing following?
-            match input.peek().unwrap() {
-                // If another indexing level, right-bind it
-                (Token::LeftBracket, _) => {
-                    let prev_pos = settings.pos;
-                    settings.pos = eat_token(input, Token::LeftBracket);
-                    // Recursively parse the indexing chain, right-binding each
-                    let idx_expr =
-                        parse_index_chain(input, state, lib, idx_expr, settings.level_up())?;
-                    // Indexing binds to right
-                    Ok(Expr::Index(
-                        Box::new(BinaryExpr { lhs, rhs: idx_expr }),
-                        prev_pos,
-                    ))
+                Expr::Array(_, _) | Expr::StringConstant(_, _) | Expr::InterpolatedString(_) => {
+                    return Err(PERR::MalformedIndexExpr(
+                        "Array or string expects numeric index, not a string".into(),
+                    )
+                    .into_err(idx_expr.position()))
                 }
-                // Otherwise terminate the indexing chain
-                _ => Ok(Expr::Index(
-                    Box::new(BinaryExpr { lhs, rhs: idx_expr }),
-                    settings.pos,
-                )),
-            }
-        }
-        (Token::LexError(err), pos) => return Err(err.clone().into_err(*pos)),
-        (_, pos) => Err(PERR::MissingToken(
-            Token::RightBracket.into(),
-            "for a matching [ in this index expression".into(),
-        )
-        .into_err(*pos)),
-    }
-}
 
-/// Parse an array literal.
-#[cfg(not(feature = "no_index"))]
-fn parse_array_literal(
-    input: &mut TokenStream,
-    state: &mut ParseState,
-    lib: &mut FunctionsLib,
-    mut settings: ParseSettings,
-) -> Result<Expr, ParseError> {
-    #[cfg(not(feature = "unchecked"))]
-    settings.ensure_level_within_max_limit(state.max_expr_depth)?;
-
-    // [ ...
-    settings.pos = eat_token(input, Token::LeftBracket);
-
-    let mut arr = StaticVec::new();
+                #[cfg(not(feature = "no_float"))]
+                Expr::FloatConstant(_, _) => {
+                    return Err(PERR::MalformedIndexExpr(
+                        "Only arrays, object maps and strings can be indexed".into(),
+                    )
+                    .into_err(lhs.position()))
+                }
 
-    loop {
-        const MISSING_RBRACKET: &str = "to end this array literal";
+                Expr::CharConstant(_, _)
+                | Expr::And(_, _)
+                | Expr::Or(_, _)
+                | Expr::BoolConstant(_, _)
+                | Expr::Unit(_) => {
+                    return Err(PERR::MalformedIndexExpr(
+                        "Only arrays, object maps and strings can be indexed".into(),
+                    )
+                    .into_err(lhs.position()))
+                }
 
-        #[cfg(not(feature = "unchecked"))]
-        if state.engine.max_array_size() > 0 && arr.len() >= state.engine.max_array_size() {
-            return Err(PERR::LiteralTooLarge(
-                "Size of array literal".to_string(),
-                state.engine.max_array_size(),
-            )
-            .into_err(input.peek().unwrap().1));
-        }
+                _ => (),
+            },
 
-        match input.peek().unwrap() {
-            (Token::RightBracket, _) => {
-                eat_token(input, Token::RightBracket);
-                break;
-            }
-            (Token::EOF, pos) => {
-                return Err(
-                    PERR::MissingToken(Token::RightBracket.into(), MISSING_RBRACKET.into())
-                        .into_err(*pos),
+            // lhs[float]
+            #[cfg(not(feature = "no_float"))]
+            x @ Expr::FloatConstant(_, _) => {
+                return Err(PERR::MalformedIndexExpr(
+                    "Array access expects integer index, not a float".into(),
                 )
+                .into_err(x.position()))
             }
-            _ => {
-                let expr = parse_expr(input, state, lib, settings.level_up())?;
-                arr.push(expr);
-            }
-        }
-
-        match input.peek().unwrap() {
-            (Token::Comma, _) => {
-                eat_token(input, Token::Comma);
-            }
-            (Token::RightBracket, _) => (),
-            (Token::EOF, pos) => {
-                return Err(
-                    PERR::MissingToken(Token::RightBracket.into(), MISSING_RBRACKET.into())
-                        .into_err(*pos),
+            // lhs[char]
+            x @ Expr::CharConstant(_, _) => {
+                return Err(PERR::MalformedIndexExpr(
+                    "Array access expects integer index, not a character".into(),
                 )
+                .into_err(x.position()))
             }
-            (Token::LexError(err), pos) => return Err(err.clone().into_err(*pos)),
-            (_, pos) => {
-                return Err(PERR::MissingToken(
-                    Token::Comma.into(),
-                    "to separate the items of this array literal".into(),
+            // lhs[()]
+            x @ Expr::Unit(_) => {
+                return Err(PERR::MalformedIndexExpr(
+                    "Array access expects integer index, not ()".into(),
                 )
-                .into_err(*pos))
-            }
-        };
-    }
-
-    arr.shrink_to_fit();
-
-    Ok(Expr::Array(Box::new(arr), settings.pos))
-}
-
-/// Parse a map literal.
-#[cfg(not(feature = "no_object"))]
-fn parse_map_literal(
-    input: &mut TokenStream,
-    state: &mut ParseState,
-    lib: &mut FunctionsLib,
-    mut settings: ParseSettings,
-) -> Result<Expr, ParseError> {
-    #[cfg(not(feature = "unchecked"))]
-    settings.ensure_level_within_max_limit(state.max_expr_depth)?;
-
-    // #{ ...
-    settings.pos = eat_token(input, Token::MapStart);
-
-    let mut map: StaticVec<(Ident, Expr)> = Default::default();
-    let mut template: BTreeMap<Identifier, Dynamic> = Default::default();
-
-    loop {
-        const MISSING_RBRACE: &str = "to end this object map literal";
-
-        match input.peek().unwrap() {
-            (Token::RightBrace, _) => {
-                eat_token(input, Token::RightBrace);
-                break;
+                .into_err(x.position()))
             }
-            (Token::EOF, pos) => {
-                return Err(
-                    PERR::MissingToken(Token::RightBrace.into(), MISSING_RBRACE.into())
-                        .into_err(*pos),
+            // lhs[??? && ???], lhs[??? || ???]
+            x @ Expr::And(_, _) | x @ Expr::Or(_, _) => {
+                return Err(PERR::MalformedIndexExpr(
+                    "Array access expects integer index, not a boolean".into(),
                 )
+                .into_err(x.position()))
             }
-            _ => (),
-        }
-
-        let (name, pos) = match input.next().unwrap() {
-            (Token::Identifier(s), pos) | (Token::StringConstant(s), pos) => {
-                if map.iter().any(|(p, _)| p.name == s) {
-                    return Err(PERR::DuplicatedProperty(s).into_err(pos));
-                }
-                (s, pos)
-            }
-            (Token::Reserved(s), pos) if is_valid_identifier(s.chars()) => {
-                return Err(PERR::Reserved(s).into_err(pos));
-            }
-            (Token::LexError(err), pos) => return Err(err.into_err(pos)),
-            (Token::EOF, pos) => {
-                return Err(
-                    PERR::MissingToken(Token::RightBrace.into(), MISSING_RBRACE.into())
-                        .into_err(pos),
-                );
-            }
-            (_, pos) if map.is_empty() => {
-                return Err(
-                    PERR::MissingToken(Token::RightBrace.into(), MISSING_RBRACE.into())
-                        .into_err(pos),
-                );
-            }
-            (_, pos) => return Err(PERR::PropertyExpected.into_err(pos)),
-        };
-
-        match input.next().unwrap() {
-            (Token::Colon, _) => (),
-            (Token::LexError(err), pos) => return Err(err.into_err(pos)),
-            (_, pos) => {
-                return Err(PERR::MissingToken(
-                    Token::Colon.into(),
-                    format!(
-                        "to follow the property '{}' in this object map literal",
-                        name
-                    ),
+            // lhs[true], lhs[false]
+            x @ Expr::BoolConstant(_, _) => {
+                return Err(PERR::MalformedIndexExpr(
+                    "Array access expects integer index, not a boolean".into(),
                 )
-                .into_err(pos))
+                .into_err(x.position()))
             }
-        };
-
-        #[cfg(not(feature = "unchecked"))]
-        if state.engine.max_map_size() > 0 && map.len() >= state.engine.max_map_size() {
-            return Err(PERR::LiteralTooLarge(
-                "Number of properties in object map literal".to_string(),
-                state.engine.max_map_size(),
-            )
-            .into_err(input.peek().unwrap().1));
+            // All other expressions
+            _ => (),
         }
 
-        let expr = parse_expr(input, state, lib, settings.level_up())?;
-        let name = state.get_identifier(name);
-        template.insert(name.clone().into(), Default::default());
-        map.push((Ident { name, pos }, expr));
-
+        // Check if there is a closing bracket
         match input.peek().unwrap() {
-            (Token::Comma, _) => {
-                eat_token(input, Token::Comma);
-            }
-            (Token::RightBrace, _) => (),
-            (Token::Identifier(_), pos) => {
-                return Err(PERR::MissingToken(
-                    Token::Comma.into(),
-                    "to separate the items of this object map literal".into(),
-                )
-                .into_err(*pos))
+            (Token::RightBracket, _) => {
+                eat_token(input, Token::RightBracket);
+
+                // Any more indexing following?
+                match input.peek().unwrap() {
+                    // If another indexing level, right-bind it
+                    (Token::LeftBracket, _) => {
+                        outer.push((lhs, settings.pos));
+                        settings.pos = eat_token(input, Token::LeftBracket);
+                        settings = settings.level_up();
+                        lhs = idx_expr;
+                    }
+                    // Otherwise terminate the indexing chain
+                    _ => {
+                        let mut expr =
+                            Expr::Index(Box::new(BinaryExpr { lhs, rhs: idx_expr }), settings.pos);
+                        // Indexing binds to right
+                        while let Some((lhs, pos)) = outer.pop() {
+                            expr = Expr::Index(Box::new(BinaryExpr { lhs, rhs: expr }), pos);
+                        }
+                        return Ok(expr);
+                    }
+                }
             }
             (Token::LexError(err), pos) => return Err(err.clone().into_err(*pos)),
             (_, pos) => {
-                return Err(
-                    PERR::MissingToken(Token::RightBrace.into(), MISSING_RBRACE.into())
-                        .into_err(*pos),
+                return Err(PERR::MissingToken(
+                    Token::RightBracket.into(),
+                    "for a matching [ in this index expression".into(),
                 )
+                .into_err(*pos))
             }
         }
     }
-
-    map.shrink_to_fit();
-
-    Ok(Expr::Map(Box::new((map, template)), settings.pos))
 }
 
 /// Parse a switch expression.
@@ -929,8 +886,28 @@ fn parse_primary(
 
     let (token, token_pos) = input.peek().unwrap();
     settings.pos = *token_pos;
+    let grouped = *token == Token::LeftParen;
+
+    let mut root_expr = parse_atom(input, state, lib, settings)?;
+
+    // A grouped expression keeps its own span, without the parentheses
+    if !grouped {
+        state.set_span(input, settings.pos, &mut root_expr);
+    }
+
+    parse_postfix(input, state, lib, root_expr, settings)
+}
+
+/// Parse the operand of a primary expression, without any postfix operators.
+fn parse_atom(
+    input: &mut TokenStream,
+    state: &mut ParseState,
+    lib: &mut FunctionsLib,
+    settings: ParseSettings,
+) -> Result<Expr, ParseError> {
+    let token = &input.peek().unwrap().0;
 
-    let mut root_expr = match token {
+    Ok(match token {
         Token::EOF => return Err(PERR::UnexpectedEOF.into_err(settings.pos)),
 
         Token::IntegerConstant(_)
@@ -968,7 +945,7 @@ fn parse_primary(
             }
         }
         // ( - grouped expression
-        Token::LeftParen => parse_paren_expr(input, state, lib, settings.level_up())?,
+        Token::LeftParen => parse_nested_expr(input, state, lib, settings.level_up())?,
 
         // If statement is allowed to act as expressions
         Token::If if settings.allow_if_expr => Expr::Stmt(Box::new(
@@ -984,12 +961,13 @@ fn parse_primary(
         Token::Pipe | Token::Or if settings.allow_anonymous_fn => {
             let mut new_state = ParseState::new(
                 state.engine,
-                state.buffer.clone(),
+                state.tokenizer_control.clone(),
                 #[cfg(not(feature = "unchecked"))]
                 state.max_function_expr_depth,
                 #[cfg(not(feature = "unchecked"))]
                 state.max_function_expr_depth,
             );
+            new_state.set_scripts(state.scripts);
 
             let settings = ParseSettings {
                 allow_if_expr: true,
@@ -1003,7 +981,10 @@ fn parse_primary(
                 pos: settings.pos,
             };
 
-            let (expr, func) = parse_anon_fn(input, &mut new_state, lib, settings)?;
+            new_state.errors = state.errors.take();
+            let result = parse_anon_fn(input, &mut new_state, lib, settings);
+            state.errors = new_state.errors.take();
+            let (expr, func) = result?;
 
             #[cfg(not(feature = "no_closure"))]
             new_state.external_vars.iter().for_each(|(closure, pos)| {
@@ -1034,7 +1015,7 @@ fn parse_primary(
                 segments.push(expr);
 
                 // Make sure to parse the following as text
-                state.buffer.set(Some('`'));
+                state.tokenizer_control.borrow_mut().buffer = Some('`');
 
                 match input.next().unwrap() {
                     (Token::StringConstant(s), pos) => {
@@ -1061,11 +1042,11 @@ fn parse_primary(
 
         // Array literal
         #[cfg(not(feature = "no_index"))]
-        Token::LeftBracket => parse_array_literal(input, state, lib, settings.level_up())?,
+        Token::LeftBracket => parse_nested_expr(input, state, lib, settings.level_up())?,
 
         // Map literal
         #[cfg(not(feature = "no_object"))]
-        Token::MapStart => parse_map_literal(input, state, lib, settings.level_up())?,
+        Token::MapStart => parse_nested_expr(input, state, lib, settings.level_up())?,
 
         // Identifier
         Token::Identifier(_) => {
@@ -1158,7 +1139,27 @@ fn parse_primary(
         _ => {
             return Err(LexError::UnexpectedInput(token.syntax().to_string()).into_err(settings.pos))
         }
-    };
+    })
+}
+
+/// Parse all the postfix operators following a primary expression.
+///
+/// Property access binds to the right, but instead of parsing the rest of the chain recursively,
+/// the left-hand sides are kept and put together when the whole chain is parsed.
+#[allow(unused_mut)]
+fn parse_postfix(
+    input: &mut TokenStream,
+    state: &mut ParseState,
+    lib: &mut FunctionsLib,
+    mut root_expr: Expr,
+    mut settings: ParseSettings,
+) -> Result<Expr, ParseError> {
+    let mut start = settings.pos;
+
+    // Left-hand sides of the property access chain, with their starting positions
+    // and the positions of the `.`
+    #[cfg(not(feature = "no_object"))]
+    let mut dots: Vec<(Expr, Position, Position)> = Vec::new();
 
     // Tail processing all possible postfix operators
     loop {
@@ -1171,7 +1172,7 @@ fn parse_primary(
         let (tail_token, tail_pos) = input.next().unwrap();
         settings.pos = tail_pos;
 
-        root_expr = match (root_expr, tail_token) {
+        root_expr = match (&mut root_expr, tail_token) {
             // Qualified function call with !
             (Expr::Variable(x), Token::Bang) if x.1.is_some() => {
                 return Err(if !match_token(input, Token::LeftParen).0 {
@@ -1195,14 +1196,14 @@ fn parse_primary(
                     .into_err(pos));
                 }
 
-                let (_, namespace, Ident { name, pos, .. }) = *x;
+                let (_, namespace, Ident { name, pos, .. }) = mem::take(x.as_mut());
                 settings.pos = pos;
                 let ns = namespace.map(|(_, ns)| ns);
                 parse_fn_call(input, state, lib, name, true, ns, settings.level_up())?
             }
             // Function call
             (Expr::Variable(x), Token::LeftParen) => {
-                let (_, namespace, Ident { name, pos, .. }) = *x;
+                let (_, namespace, Ident { name, pos, .. }) = mem::take(x.as_mut());
                 settings.pos = pos;
                 let ns = namespace.map(|(_, ns)| ns);
                 parse_fn_call(input, state, lib, name, false, ns, settings.level_up())?
@@ -1210,7 +1211,7 @@ fn parse_primary(
             // module access
             (Expr::Variable(x), Token::DoubleColon) => match input.next().unwrap() {
                 (Token::Identifier(id2), pos2) => {
-                    let (index, mut namespace, var_name_def) = *x;
+                    let (index, mut namespace, var_name_def) = mem::take(x.as_mut());
 
                     if let Some((_, ref mut namespace)) = namespace {
                         namespace.push(var_name_def);
@@ -1235,7 +1236,7 @@ fn parse_primary(
             // Indexing
             #[cfg(not(feature = "no_index"))]
             (expr, Token::LeftBracket) => {
-                parse_index_chain(input, state, lib, expr, settings.level_up())?
+                parse_index_chain(input, state, lib, mem::take(expr), settings.level_up())?
             }
             // Property access
             #[cfg(not(feature = "no_object"))]
@@ -1253,9 +1254,18 @@ fn parse_primary(
                     (_, pos) => return Err(PERR::PropertyExpected.into_err(*pos)),
                 }
 
-                let rhs = parse_primary(input, state, lib, settings.level_up())?;
+                dots.push((mem::take(expr), start, tail_pos));
+
+                // Continue with the right-hand side as a new primary expression
+                settings = settings.level_up();
 
-                make_dot_expr(state, expr, rhs, tail_pos)?
+                #[cfg(not(feature = "unchecked"))]
+                settings.ensure_level_within_max_limit(state.max_expr_depth)?;
+
+                settings.pos = input.peek().unwrap().1;
+                start = settings.pos;
+
+                parse_atom(input, state, lib, settings)?
             }
             // Unknown postfix operator
             (expr, token) => unreachable!(
@@ -1263,132 +1273,122 @@ fn parse_primary(
                 token.syntax(),
                 expr
             ),
-        }
-    }
+        };
 
-    // Cache the hash key for namespace-qualified variables
-    match &mut root_expr {
-        Expr::Variable(x) if x.1.is_some() => Some(x),
-        Expr::Index(x, _) | Expr::Dot(x, _) => match &mut x.lhs {
-            Expr::Variable(x) if x.1.is_some() => Some(x),
-            _ => None,
-        },
-        _ => None,
+        state.set_span(input, start, &mut root_expr);
     }
-    .map(|x| match x.as_mut() {
-        (_, Some((ref mut hash, ref mut namespace)), Ident { name, .. }) => {
-            *hash = calc_fn_hash(namespace.iter().map(|v| v.name.as_str()), name, 0);
 
-            #[cfg(not(feature = "no_module"))]
-            namespace.set_index(state.find_module(&namespace[0].name));
-        }
-        _ => unreachable!("expecting namespace-qualified variable access"),
-    });
+    state.cache_namespace_hash(&mut root_expr);
+
+    // Put together the property access chain, right to left
+    #[cfg(not(feature = "no_object"))]
+    while let Some((lhs, start, op_pos)) = dots.pop() {
+        // Errors in a method call or property access cover the whole chain
+        state.set_span(input, start, &mut root_expr);
+        root_expr = make_dot_expr(state, lhs, root_expr, op_pos)?;
+        state.set_span(input, start, &mut root_expr);
+        state.cache_namespace_hash(&mut root_expr);
+    }
 
-    // Make sure identifiers are valid
     Ok(root_expr)
 }
 
-/// Parse a potential unary operator.
-fn parse_unary(
+/// Parse the unary operators in front of an operand.
+///
+/// Returns the operators in order, together with the settings for parsing the operand.
+fn parse_unary_ops(
     input: &mut TokenStream,
     state: &mut ParseState,
-    lib: &mut FunctionsLib,
     mut settings: ParseSettings,
-) -> Result<Expr, ParseError> {
-    let (token, token_pos) = input.peek().unwrap();
-    settings.pos = *token_pos;
+) -> Result<(StaticVec<(Token, Position)>, ParseSettings), ParseError> {
+    let mut ops = StaticVec::new();
 
-    #[cfg(not(feature = "unchecked"))]
-    settings.ensure_level_within_max_limit(state.max_expr_depth)?;
+    loop {
+        let (token, token_pos) = input.peek().unwrap();
+        settings.pos = *token_pos;
 
-    match token {
-        // -expr
-        Token::UnaryMinus => {
-            let pos = eat_token(input, Token::UnaryMinus);
-
-            match parse_unary(input, state, lib, settings.level_up())? {
-                // Negative integer
-                Expr::IntegerConstant(num, pos) => num
-                    .checked_neg()
-                    .map(|i| Expr::IntegerConstant(i, pos))
-                    .or_else(|| {
-                        #[cfg(not(feature = "no_float"))]
-                        return Some(Expr::FloatConstant((-(num as FLOAT)).into(), pos));
-                        #[cfg(feature = "no_float")]
-                        return None;
-                    })
-                    .ok_or_else(|| LexError::MalformedNumber(format!("-{}", num)).into_err(pos)),
-
-                // Negative float
-                #[cfg(not(feature = "no_float"))]
-                Expr::FloatConstant(x, pos) => Ok(Expr::FloatConstant((-(*x)).into(), pos)),
-
-                // Call negative function
-                expr => {
-                    let mut args = StaticVec::new();
-                    args.push(expr);
-
-                    Ok(Expr::FnCall(
-                        Box::new(FnCallExpr {
-                            name: state.get_identifier("-"),
-                            hash: FnCallHash::from_native(calc_fn_hash(empty(), "-", 1)),
-                            args,
-                            ..Default::default()
-                        }),
-                        pos,
-                    ))
-                }
+        #[cfg(not(feature = "unchecked"))]
+        settings.ensure_level_within_max_limit(state.max_expr_depth)?;
+
+        match token {
+            // -expr, +expr, !expr
+            Token::UnaryMinus | Token::UnaryPlus | Token::Bang => {
+                ops.push(input.next().unwrap());
+                settings = settings.level_up();
             }
+            // <EOF>
+            Token::EOF => return Err(PERR::UnexpectedEOF.into_err(settings.pos)),
+            // All other tokens
+            _ => return Ok((ops, settings)),
         }
-        // +expr
-        Token::UnaryPlus => {
-            let pos = eat_token(input, Token::UnaryPlus);
+    }
+}
 
-            match parse_unary(input, state, lib, settings.level_up())? {
-                expr @ Expr::IntegerConstant(_, _) => Ok(expr),
-                #[cfg(not(feature = "no_float"))]
-                expr @ Expr::FloatConstant(_, _) => Ok(expr),
-
-                // Call plus function
-                expr => {
-                    let mut args = StaticVec::new();
-                    args.push(expr);
-
-                    Ok(Expr::FnCall(
-                        Box::new(FnCallExpr {
-                            name: state.get_identifier("+"),
-                            hash: FnCallHash::from_native(calc_fn_hash(empty(), "+", 1)),
-                            args,
-                            ..Default::default()
-                        }),
-                        pos,
-                    ))
-                }
+/// Apply unary operators, in reverse order, to an operand.
+fn make_unary_exprs(
+    input: &mut TokenStream,
+    state: &mut ParseState,
+    ops: StaticVec<(Token, Position)>,
+    mut expr: Expr,
+) -> Result<Expr, ParseError> {
+    for (op, op_pos) in ops.into_iter().rev() {
+        expr = match (op, expr) {
+            // Negative integer
+            (Token::UnaryMinus, Expr::IntegerConstant(num, pos)) => num
+                .checked_neg()
+                .map(|i| Expr::IntegerConstant(i, pos))
+                .or_else(|| {
+                    #[cfg(not(feature = "no_float"))]
+                    return Some(Expr::FloatConstant((-(num as FLOAT)).into(), pos));
+                    #[cfg(feature = "no_float")]
+                    return None;
+                })
+                .ok_or_else(|| LexError::MalformedNumber(format!("-{}", num)).into_err(pos))?,
+
+            // Negative float
+            #[cfg(not(feature = "no_float"))]
+            (Token::UnaryMinus, Expr::FloatConstant(x, pos)) => {
+                Expr::FloatConstant((-(*x)).into(), pos)
             }
-        }
-        // !expr
-        Token::Bang => {
-            let pos = eat_token(input, Token::Bang);
-            let mut args = StaticVec::new();
-            let expr = parse_unary(input, state, lib, settings.level_up())?;
-            args.push(expr);
 
-            Ok(Expr::FnCall(
-                Box::new(FnCallExpr {
-                    name: state.get_identifier("!"),
-                    hash: FnCallHash::from_native(calc_fn_hash(empty(), "!", 1)),
-                    args,
-                    ..Default::default()
-                }),
-                pos,
-            ))
-        }
-        // <EOF>
-        Token::EOF => Err(PERR::UnexpectedEOF.into_err(settings.pos)),
-        // All other tokens
-        _ => parse_primary(input, state, lib, settings.level_up()),
+            (Token::UnaryPlus, expr @ Expr::IntegerConstant(_, _)) => expr,
+            #[cfg(not(feature = "no_float"))]
+            (Token::UnaryPlus, expr @ Expr::FloatConstant(_, _)) => expr,
+
+            // Call negative, plus or not function
+            (op, expr) => {
+                let op = op.syntax();
+                let mut args = StaticVec::new();
+                args.push(expr);
+
+                Expr::FnCall(
+                    Box::new(FnCallExpr {
+                        name: state.get_identifier(op.as_ref()),
+                        hash: FnCallHash::from_native(calc_fn_hash(empty(), &op, 1)),
+                        args,
+                        ..Default::default()
+                    }),
+                    op_pos,
+                )
+            }
+        };
+
+        state.set_span(input, op_pos, &mut expr);
     }
+
+    Ok(expr)
+}
+
+/// Parse a potential unary operator.
+fn parse_unary(
+    input: &mut TokenStream,
+    state: &mut ParseState,
+    lib: &mut FunctionsLib,
+    settings: ParseSettings,
+) -> Result<Expr, ParseError> {
+    let (ops, settings) = parse_unary_ops(input, state, settings)?;
+    let expr = parse_primary(input, state, lib, settings.level_up())?;
+    make_unary_exprs(input, state, ops, expr)
 }
 
 /// Make an assignment statement.
@@ -1447,7 +1447,7 @@ fn make_assignment_stmt<'a>(
         // xxx[???]... = rhs, xxx.prop... = rhs
         Expr::Index(x, _) | Expr::Dot(x, _) => {
             match check_lvalue(&x.rhs, matches!(lhs, Expr::Dot(_, _))) {
-                Position::NONE => match &x.lhs {
+                pos if pos.is_none() => match &x.lhs {
                     // var[???] (non-indexed) = rhs, var.??? (non-indexed) = rhs
                     Expr::Variable(x) if x.0.is_none() => {
                         Ok(Stmt::Assignment(Box::new((lhs, op_info, rhs)), op_pos))
@@ -1525,20 +1525,27 @@ fn parse_op_assignment_stmt(
 #[cfg(not(feature = "no_object"))]
 fn make_dot_expr(
     state: &mut ParseState,
-    lhs: Expr,
-    rhs: Expr,
+    mut lhs: Expr,
+    mut rhs: Expr,
     op_pos: Position,
 ) -> Result<Expr, ParseError> {
-    Ok(match (lhs, rhs) {
-        // idx_lhs[idx_expr].rhs
-        // Attach dot chain to the bottom level of indexing chain
-        (Expr::Index(mut x, pos), rhs) => {
-            x.rhs = make_dot_expr(state, x.rhs, rhs, op_pos)?;
-            Expr::Index(x, pos)
+    // idx_lhs[idx_expr].rhs
+    // Attach dot chain to the bottom level of indexing chain
+    if let Expr::Index(_, _) = lhs {
+        let mut idx_rhs = &mut lhs;
+
+        while let Expr::Index(x, _) = idx_rhs {
+            idx_rhs = &mut x.rhs;
         }
+
+        *idx_rhs = make_dot_expr(state, mem::take(idx_rhs), rhs, op_pos)?;
+        return Ok(lhs);
+    }
+
+    Ok(match rhs {
         // lhs.id
-        (lhs, Expr::Variable(x)) if x.1.is_none() => {
-            let ident = x.2;
+        Expr::Variable(ref mut x) if x.1.is_none() => {
+            let ident = mem::take(&mut x.2);
             let getter = state.get_identifier(crate::engine::make_getter(&ident.name));
             let hash_get = calc_fn_hash(empty(), &getter, 1);
             let setter = state.get_identifier(crate::engine::make_setter(&ident.name));
@@ -1549,60 +1556,39 @@ fn make_dot_expr(
             Expr::Dot(Box::new(BinaryExpr { lhs, rhs }), op_pos)
         }
         // lhs.module::id - syntax error
-        (_, Expr::Variable(x)) if x.1.is_some() => {
-            return Err(PERR::PropertyExpected.into_err(x.1.unwrap().1[0].pos))
+        Expr::Variable(ref x) if x.1.is_some() => {
+            return Err(PERR::PropertyExpected.into_err(x.1.as_ref().unwrap().1[0].pos))
         }
         // lhs.prop
-        (lhs, prop @ Expr::Property(_)) => {
-            Expr::Dot(Box::new(BinaryExpr { lhs, rhs: prop }), op_pos)
-        }
+        Expr::Property(_) => Expr::Dot(Box::new(BinaryExpr { lhs, rhs }), op_pos),
         // lhs.dot_lhs.dot_rhs
-        (lhs, Expr::Dot(x, pos)) => match x.lhs {
-            Expr::Variable(_) | Expr::Property(_) => {
-                let rhs = Expr::Dot(
-                    Box::new(BinaryExpr {
-                        lhs: x.lhs.into_property(state),
-                        rhs: x.rhs,
-                    }),
-                    pos,
-                );
-                Expr::Dot(Box::new(BinaryExpr { lhs, rhs }), op_pos)
-            }
-            Expr::FnCall(mut func, func_pos) => {
-                // Recalculate hash
-                func.hash = FnCallHash::from_script_and_native(
-                    calc_fn_hash(empty(), &func.name, func.num_args()),
-                    calc_fn_hash(empty(), &func.name, func.num_args() + 1),
-                );
-
-                let rhs = Expr::Dot(
-                    Box::new(BinaryExpr {
-                        lhs: Expr::FnCall(func, func_pos),
-                        rhs: x.rhs,
-                    }),
-                    pos,
-                );
-                Expr::Dot(Box::new(BinaryExpr { lhs, rhs }), op_pos)
+        Expr::Dot(ref mut x, _) => {
+            match x.lhs {
+                Expr::Variable(_) | Expr::Property(_) => {
+                    x.lhs = mem::take(&mut x.lhs).into_property(state);
+                }
+                Expr::FnCall(ref mut func, _) => {
+                    // Recalculate hash
+                    func.hash = FnCallHash::from_script_and_native(
+                        calc_fn_hash(empty(), &func.name, func.num_args()),
+                        calc_fn_hash(empty(), &func.name, func.num_args() + 1),
+                    );
+                }
+                _ => unreachable!("invalid dot expression: {:?}", x.lhs),
             }
-            _ => unreachable!("invalid dot expression: {:?}", x.lhs),
-        },
+            Expr::Dot(Box::new(BinaryExpr { lhs, rhs }), op_pos)
+        }
         // lhs.idx_lhs[idx_rhs]
-        (lhs, Expr::Index(x, pos)) => {
-            let rhs = Expr::Index(
-                Box::new(BinaryExpr {
-                    lhs: x.lhs.into_property(state),
-                    rhs: x.rhs,
-                }),
-                pos,
-            );
+        Expr::Index(ref mut x, _) => {
+            x.lhs = mem::take(&mut x.lhs).into_property(state);
             Expr::Dot(Box::new(BinaryExpr { lhs, rhs }), op_pos)
         }
         // lhs.nnn::func(...)
-        (_, Expr::FnCall(x, _)) if x.namespace.is_some() => {
+        Expr::FnCall(ref x, _) if x.namespace.is_some() => {
             unreachable!("method call should not be namespace-qualified")
         }
         // lhs.Fn() or lhs.eval()
-        (_, Expr::FnCall(x, pos))
+        Expr::FnCall(ref x, pos)
             if x.is_args_empty()
                 && [crate::engine::KEYWORD_FN_PTR, crate::engine::KEYWORD_EVAL]
                     .contains(&x.name.as_ref()) =>
@@ -1617,34 +1603,37 @@ fn make_dot_expr(
             .into_err(pos))
         }
         // lhs.func!(...)
-        (_, Expr::FnCall(x, pos)) if x.capture => {
+        Expr::FnCall(ref x, pos) if x.capture => {
             return Err(PERR::MalformedCapture(
                 "method-call style does not support capturing".into(),
             )
             .into_err(pos))
         }
         // lhs.func(...)
-        (lhs, Expr::FnCall(mut func, func_pos)) => {
+        Expr::FnCall(ref mut func, _) => {
             // Recalculate hash
             func.hash = FnCallHash::from_script_and_native(
                 calc_fn_hash(empty(), &func.name, func.num_args()),
                 calc_fn_hash(empty(), &func.name, func.num_args() + 1),
             );
-            let rhs = Expr::FnCall(func, func_pos);
             Expr::Dot(Box::new(BinaryExpr { lhs, rhs }), op_pos)
         }
         // lhs.rhs
-        (_, rhs) => return Err(PERR::PropertyExpected.into_err(rhs.position())),
+        _ => return Err(PERR::PropertyExpected.into_err(rhs.position())),
     })
 }
 
 /// Parse a binary expression.
+///
+/// Instead of recursively parsing the right-hand side of an operator that binds tighter, the
+/// pending left-hand sides are kept on a stack so that long chains do not overflow the call stack.
 fn parse_binary_op(
     input: &mut TokenStream,
     state: &mut ParseState,
     lib: &mut FunctionsLib,
-    parent_precedence: Option<Precedence>,
+    mut parent_precedence: Option<Precedence>,
     lhs: Expr,
+    mut start: Position,
     mut settings: ParseSettings,
 ) -> Result<Expr, ParseError> {
     #[cfg(not(feature = "unchecked"))]
@@ -1654,6 +1643,16 @@ fn parse_binary_op(
 
     let mut root = lhs;
 
+    // Enclosing binary expressions waiting for their right-hand sides
+    let mut outer: Vec<(
+        Option<Precedence>,
+        Expr,
+        Position,
+        ParseSettings,
+        Token,
+        Position,
+    )> = Vec::new();
+
     loop {
         let (current_op, current_pos) = input.peek().unwrap();
         let precedence = match current_op {
@@ -1673,11 +1672,25 @@ fn parse_binary_op(
         // Bind left to the parent lhs expression if precedence is higher
         // If same precedence, then check if the operator binds right
         if precedence < parent_precedence || (precedence == parent_precedence && !bind_right) {
-            return Ok(root);
+            // Complete the enclosing binary expression, if any
+            let (prev_precedence, lhs, lhs_start, lhs_settings, op_token, pos) = match outer.pop() {
+                Some(x) => x,
+                None => return Ok(root),
+            };
+
+            parent_precedence = prev_precedence;
+            start = lhs_start;
+            settings = lhs_settings;
+
+            let rhs = mem::take(&mut root);
+            root = make_binary_op(state, op_token, pos, lhs, rhs, &mut settings)?;
+            state.set_span(input, start, &mut root);
+            continue;
         }
 
         let (op_token, pos) = input.next().unwrap();
 
+        let rhs_start = input.peek().unwrap().1;
         let rhs = parse_unary(input, state, lib, settings)?;
 
         let (next_op, next_pos) = input.peek().unwrap();
@@ -1696,124 +1709,138 @@ fn parse_binary_op(
 
         // Bind to right if the next operator has higher precedence
         // If same precedence, then check if the operator binds right
-        let rhs = if (precedence == next_precedence && bind_right) || precedence < next_precedence {
-            parse_binary_op(input, state, lib, precedence, rhs, settings)?
-        } else {
-            // Otherwise bind to left (even if next operator has the same precedence)
-            rhs
-        };
+        if (precedence == next_precedence && bind_right) || precedence < next_precedence {
+            outer.push((parent_precedence, root, start, settings, op_token, pos));
+            parent_precedence = precedence;
+            root = rhs;
+            start = rhs_start;
+            settings.pos = root.position();
+            continue;
+        }
 
-        settings = settings.level_up();
-        settings.pos = pos;
+        // Otherwise bind to left (even if next operator has the same precedence)
+        root = make_binary_op(state, op_token, pos, root, rhs, &mut settings)?;
+        state.set_span(input, start, &mut root);
+    }
+}
 
-        #[cfg(not(feature = "unchecked"))]
-        settings.ensure_level_within_max_limit(state.max_expr_depth)?;
+/// Make a binary expression.
+fn make_binary_op(
+    state: &mut ParseState,
+    op_token: Token,
+    pos: Position,
+    lhs: Expr,
+    rhs: Expr,
+    settings: &mut ParseSettings,
+) -> Result<Expr, ParseError> {
+    *settings = settings.level_up();
+    settings.pos = pos;
 
-        let op = op_token.syntax();
-        let hash = calc_fn_hash(empty(), &op, 2);
+    #[cfg(not(feature = "unchecked"))]
+    settings.ensure_level_within_max_limit(state.max_expr_depth)?;
 
-        let op_base = FnCallExpr {
-            name: state.get_identifier(op.as_ref()),
-            hash: FnCallHash::from_native(hash),
-            capture: false,
-            ..Default::default()
-        };
+    let op = op_token.syntax();
+    let hash = calc_fn_hash(empty(), &op, 2);
 
-        let mut args = StaticVec::new();
-        args.push(root);
-        args.push(rhs);
-
-        root = match op_token {
-            Token::Plus
-            | Token::Minus
-            | Token::Multiply
-            | Token::Divide
-            | Token::LeftShift
-            | Token::RightShift
-            | Token::Modulo
-            | Token::PowerOf
-            | Token::Ampersand
-            | Token::Pipe
-            | Token::XOr => Expr::FnCall(Box::new(FnCallExpr { args, ..op_base }), pos),
-
-            // '!=' defaults to true when passed invalid operands
-            Token::NotEqualsTo => Expr::FnCall(Box::new(FnCallExpr { args, ..op_base }), pos),
-
-            // Comparison operators default to false when passed invalid operands
-            Token::EqualsTo
-            | Token::LessThan
-            | Token::LessThanEqualsTo
-            | Token::GreaterThan
-            | Token::GreaterThanEqualsTo => {
-                Expr::FnCall(Box::new(FnCallExpr { args, ..op_base }), pos)
-            }
-
-            Token::Or => {
-                let rhs = args.pop().unwrap();
-                let current_lhs = args.pop().unwrap();
-                Expr::Or(
-                    Box::new(BinaryExpr {
-                        lhs: current_lhs,
-                        rhs,
-                    }),
-                    pos,
-                )
-            }
-            Token::And => {
-                let rhs = args.pop().unwrap();
-                let current_lhs = args.pop().unwrap();
-                Expr::And(
-                    Box::new(BinaryExpr {
-                        lhs: current_lhs,
-                        rhs,
-                    }),
-                    pos,
-                )
-            }
-            Token::In => {
-                // Swap the arguments
-                let current_lhs = args.remove(0);
-                args.push(current_lhs);
+    let op_base = FnCallExpr {
+        name: state.get_identifier(op.as_ref()),
+        hash: FnCallHash::from_native(hash),
+        capture: false,
+        ..Default::default()
+    };
 
-                // Convert into a call to `contains`
-                let hash = calc_fn_hash(empty(), OP_CONTAINS, 2);
-                Expr::FnCall(
-                    Box::new(FnCallExpr {
-                        hash: FnCallHash::from_script(hash),
-                        args,
-                        name: state.get_identifier(OP_CONTAINS),
-                        ..op_base
-                    }),
-                    pos,
-                )
-            }
+    let mut args = StaticVec::new();
+    args.push(lhs);
+    args.push(rhs);
+
+    Ok(match op_token {
+        Token::Plus
+        | Token::Minus
+        | Token::Multiply
+        | Token::Divide
+        | Token::LeftShift
+        | Token::RightShift
+        | Token::Modulo
+        | Token::PowerOf
+        | Token::Ampersand
+        | Token::Pipe
+        | Token::XOr => Expr::FnCall(Box::new(FnCallExpr { args, ..op_base }), pos),
+
+        // '!=' defaults to true when passed invalid operands
+        Token::NotEqualsTo => Expr::FnCall(Box::new(FnCallExpr { args, ..op_base }), pos),
+
+        // Comparison operators default to false when passed invalid operands
+        Token::EqualsTo
+        | Token::LessThan
+        | Token::LessThanEqualsTo
+        | Token::GreaterThan
+        | Token::GreaterThanEqualsTo => Expr::FnCall(Box::new(FnCallExpr { args, ..op_base }), pos),
+
+        Token::Or => {
+            let rhs = args.pop().unwrap();
+            let current_lhs = args.pop().unwrap();
+            Expr::Or(
+                Box::new(BinaryExpr {
+                    lhs: current_lhs,
+                    rhs,
+                }),
+                pos,
+            )
+        }
+        Token::And => {
+            let rhs = args.pop().unwrap();
+            let current_lhs = args.pop().unwrap();
+            Expr::And(
+                Box::new(BinaryExpr {
+                    lhs: current_lhs,
+                    rhs,
+                }),
+                pos,
+            )
+        }
+        Token::In => {
+            // Swap the arguments
+            let current_lhs = args.remove(0);
+            args.push(current_lhs);
 
-            Token::Custom(s)
-                if state
-                    .engine
-                    .custom_keywords
-                    .get(s.as_str())
-                    .map_or(false, Option::is_some) =>
-            {
-                let hash = calc_fn_hash(empty(), &s, 2);
+            // Convert into a call to `contains`
+            let hash = calc_fn_hash(empty(), OP_CONTAINS, 2);
+            Expr::FnCall(
+                Box::new(FnCallExpr {
+                    hash: FnCallHash::from_script(hash),
+                    args,
+                    name: state.get_identifier(OP_CONTAINS),
+                    ..op_base
+                }),
+                pos,
+            )
+        }
 
-                Expr::FnCall(
-                    Box::new(FnCallExpr {
-                        hash: if is_valid_identifier(s.chars()) {
-                            FnCallHash::from_script(hash)
-                        } else {
-                            FnCallHash::from_native(hash)
-                        },
-                        args,
-                        ..op_base
-                    }),
-                    pos,
-                )
-            }
+        Token::Custom(s)
+            if state
+                .engine
+                .custom_keywords
+                .get(s.as_str())
+                .map_or(false, Option::is_some) =>
+        {
+            let hash = calc_fn_hash(empty(), &s, 2);
 
-            op_token => return Err(PERR::UnknownOperator(op_token.into()).into_err(pos)),
-        };
-    }
+            Expr::FnCall(
+                Box::new(FnCallExpr {
+                    hash: if is_valid_identifier(s.chars()) {
+                        FnCallHash::from_script(hash)
+                    } else {
+                        FnCallHash::from_native(hash)
+                    },
+                    args,
+                    ..op_base
+                }),
+                pos,
+            )
+        }
+
+        op_token => return Err(PERR::UnknownOperator(op_token.into()).into_err(pos)),
+    })
 }
 
 /// Parse a custom syntax.
@@ -1923,13 +1950,136 @@ fn parse_custom_syntax(
     ))
 }
 
+/// An expression being parsed by [`parse_nested`], waiting for the expressions nested in it.
+#[allow(clippy::large_enum_variant)]
+enum Nested {
+    /// An expression waiting for its operand, with the unary operators in front of the operand,
+    /// and the settings of the expression and of the operand.
+    Expr(StaticVec<(Token, Position)>, ParseSettings, ParseSettings),
+    /// `(` ... `)`
+    Paren,
+    /// `[` ... `]`
+    #[cfg(not(feature = "no_index"))]
+    Array(StaticVec<Expr>, ParseSettings),
+    /// `#{` ... `}`, with the property of the item being parsed.
+    #[cfg(not(feature = "no_object"))]
+    Map(
+        StaticVec<(Ident, Expr)>,
+        BTreeMap<Identifier, Dynamic>,
+        Ident,
+        ParseSettings,
+    ),
+}
+
+/// The next step of [`parse_nested`].
+enum NestedStep {
+    /// Parse an expression.
+    Expr(ParseSettings),
+    /// An operand has been parsed, together with whether it is grouped in parentheses.
+    Operand(Expr, bool),
+    /// An expression has been parsed.
+    Item(Expr),
+}
+
 /// Parse an expression.
 fn parse_expr(
     input: &mut TokenStream,
     state: &mut ParseState,
     lib: &mut FunctionsLib,
+    settings: ParseSettings,
+) -> Result<Expr, ParseError> {
+    parse_nested(input, state, lib, Vec::new(), NestedStep::Expr(settings))
+}
+
+/// Parse expressions nested in parentheses, array literals and object map literals,
+/// until the outermost expression is complete.
+///
+/// The enclosing expressions are kept on an explicit stack instead of parsing the nested
+/// expressions recursively, so deeply-nested expressions do not overflow the call stack.
+fn parse_nested(
+    input: &mut TokenStream,
+    state: &mut ParseState,
+    lib: &mut FunctionsLib,
+    mut stack: Vec<Nested>,
+    mut step: NestedStep,
+) -> Result<Expr, ParseError> {
+    loop {
+        step = match step {
+            NestedStep::Expr(settings) => begin_expr(input, state, lib, &mut stack, settings)?,
+
+            // The outermost parentheses or literal is complete
+            NestedStep::Operand(expr, _) if stack.is_empty() => return Ok(expr),
+
+            NestedStep::Operand(mut expr, grouped) => {
+                let (ops, settings, primary_settings) = match stack.pop() {
+                    Some(Nested::Expr(ops, settings, primary_settings)) => {
+                        (ops, settings, primary_settings)
+                    }
+                    _ => unreachable!("expecting an expression waiting for its operand"),
+                };
+
+                // A grouped expression keeps its own span, without the parentheses
+                if !grouped {
+                    state.set_span(input, primary_settings.pos, &mut expr);
+                }
+
+                let expr = parse_postfix(input, state, lib, expr, primary_settings)?;
+                let expr = make_unary_exprs(input, state, ops, expr)?;
+
+                NestedStep::Item(parse_binary_op(
+                    input,
+                    state,
+                    lib,
+                    Precedence::new(1),
+                    expr,
+                    settings.pos,
+                    settings.level_up(),
+                )?)
+            }
+
+            NestedStep::Item(expr) => match stack.last() {
+                None => return Ok(expr),
+
+                Some(Nested::Paren) => match input.next().unwrap() {
+                    // ( xxx )
+                    (Token::RightParen, _) => {
+                        stack.pop();
+                        NestedStep::Operand(expr, true)
+                    }
+                    // ( <error>
+                    (Token::LexError(err), pos) => return Err(err.into_err(pos)),
+                    // ( xxx ???
+                    (_, pos) => {
+                        return Err(PERR::MissingToken(
+                            Token::RightParen.into(),
+                            "for a matching ( in this expression".into(),
+                        )
+                        .into_err(pos))
+                    }
+                },
+
+                #[cfg(not(feature = "no_index"))]
+                Some(Nested::Array(_, _)) => next_array_item(input, state, &mut stack, Some(expr))?,
+
+                #[cfg(not(feature = "no_object"))]
+                Some(Nested::Map(_, _, _, _)) => {
+                    next_map_item(input, state, &mut stack, Some(expr))?
+                }
+
+                Some(Nested::Expr(_, _, _)) => unreachable!("expecting an enclosing expression"),
+            },
+        };
+    }
+}
+
+/// Begin parsing an expression for [`parse_nested`].
+fn begin_expr(
+    input: &mut TokenStream,
+    state: &mut ParseState,
+    lib: &mut FunctionsLib,
+    stack: &mut Vec<Nested>,
     mut settings: ParseSettings,
-) -> Result<Expr, ParseError> {
+) -> Result<NestedStep, ParseError> {
     #[cfg(not(feature = "unchecked"))]
     settings.ensure_level_within_max_limit(state.max_expr_depth)?;
 
@@ -1945,9 +2095,11 @@ fn parse_expr(
                 match state.engine.custom_syntax.get_key_value(key.as_str()) {
                     Some((key, syntax)) => {
                         input.next().unwrap();
-                        return parse_custom_syntax(
+                        let mut expr = parse_custom_syntax(
                             input, state, lib, settings, key, syntax, token_pos,
-                        );
+                        )?;
+                        state.set_span(input, token_pos, &mut expr);
+                        return Ok(NestedStep::Item(expr));
                     }
                     _ => (),
                 }
@@ -1957,15 +2109,268 @@ fn parse_expr(
     }
 
     // Parse expression normally.
-    let lhs = parse_unary(input, state, lib, settings.level_up())?;
-    parse_binary_op(
-        input,
-        state,
-        lib,
-        Precedence::new(1),
-        lhs,
-        settings.level_up(),
-    )
+    let (ops, unary_settings) = parse_unary_ops(input, state, settings.level_up())?;
+    let primary_settings = unary_settings.level_up();
+
+    #[cfg(not(feature = "unchecked"))]
+    primary_settings.ensure_level_within_max_limit(state.max_expr_depth)?;
+
+    let is_nested = match input.peek().unwrap().0 {
+        Token::LeftParen => true,
+        #[cfg(not(feature = "no_index"))]
+        Token::LeftBracket => true,
+        #[cfg(not(feature = "no_object"))]
+        Token::MapStart => true,
+        _ => false,
+    };
+
+    stack.push(Nested::Expr(ops, settings, primary_settings));
+
+    if is_nested {
+        begin_nested(input, state, stack, primary_settings.level_up())
+    } else {
+        let expr = parse_atom(input, state, lib, primary_settings)?;
+        Ok(NestedStep::Operand(expr, false))
+    }
+}
+
+/// Begin parsing ( expr ), an array literal or an object map literal for [`parse_nested`].
+#[allow(unused_variables)]
+fn begin_nested(
+    input: &mut TokenStream,
+    state: &mut ParseState,
+    stack: &mut Vec<Nested>,
+    mut settings: ParseSettings,
+) -> Result<NestedStep, ParseError> {
+    #[cfg(not(feature = "unchecked"))]
+    settings.ensure_level_within_max_limit(state.max_expr_depth)?;
+
+    match input.next().unwrap() {
+        // ( ...
+        (Token::LeftParen, pos) => {
+            settings.pos = pos;
+
+            if match_token(input, Token::RightParen).0 {
+                return Ok(NestedStep::Operand(Expr::Unit(settings.pos), true));
+            }
+
+            stack.push(Nested::Paren);
+            Ok(NestedStep::Expr(settings.level_up()))
+        }
+        // [ ...
+        #[cfg(not(feature = "no_index"))]
+        (Token::LeftBracket, pos) => {
+            settings.pos = pos;
+            stack.push(Nested::Array(StaticVec::new(), settings));
+            next_array_item(input, state, stack, None)
+        }
+        // #{ ...
+        #[cfg(not(feature = "no_object"))]
+        (Token::MapStart, pos) => {
+            settings.pos = pos;
+            let map = Default::default();
+            let template = Default::default();
+            stack.push(Nested::Map(map, template, Default::default(), settings));
+            next_map_item(input, state, stack, None)
+        }
+        (token, pos) => unreachable!(
+            "expecting (, [ or #{{ (found {}) at {}",
+            token.syntax(),
+            pos
+        ),
+    }
+}
+
+/// Continue parsing an array literal for [`parse_nested`], adding the item just parsed, if any.
+#[cfg(not(feature = "no_index"))]
+#[allow(unused_variables)]
+fn next_array_item(
+    input: &mut TokenStream,
+    state: &mut ParseState,
+    stack: &mut Vec<Nested>,
+    item: Option<Expr>,
+) -> Result<NestedStep, ParseError> {
+    const MISSING_RBRACKET: &str = "to end this array literal";
+
+    let (arr, settings) = match stack.last_mut() {
+        Some(Nested::Array(arr, settings)) => (arr, *settings),
+        _ => unreachable!("expecting an array literal"),
+    };
+
+    if let Some(expr) = item {
+        arr.push(expr);
+
+        match input.peek().unwrap() {
+            (Token::Comma, _) => {
+                eat_token(input, Token::Comma);
+            }
+            (Token::RightBracket, _) => (),
+            (Token::EOF, pos) => {
+                return Err(
+                    PERR::MissingToken(Token::RightBracket.into(), MISSING_RBRACKET.into())
+                        .into_err(*pos),
+                )
+            }
+            (Token::LexError(err), pos) => return Err(err.clone().into_err(*pos)),
+            (_, pos) => {
+                return Err(PERR::MissingToken(
+                    Token::Comma.into(),
+                    "to separate the items of this array literal".into(),
+                )
+                .into_err(*pos))
+            }
+        };
+    }
+
+    #[cfg(not(feature = "unchecked"))]
+    if state.engine.max_array_size() > 0 && arr.len() >= state.engine.max_array_size() {
+        return Err(PERR::LiteralTooLarge(
+            "Size of array literal".to_string(),
+            state.engine.max_array_size(),
+        )
+        .into_err(input.peek().unwrap().1));
+    }
+
+    match input.peek().unwrap() {
+        (Token::RightBracket, _) => {
+            eat_token(input, Token::RightBracket);
+
+            let mut arr = mem::take(arr);
+            arr.shrink_to_fit();
+            stack.pop();
+
+            Ok(NestedStep::Operand(
+                Expr::Array(Box::new(arr), settings.pos),
+                false,
+            ))
+        }
+        (Token::EOF, pos) => Err(PERR::MissingToken(
+            Token::RightBracket.into(),
+            MISSING_RBRACKET.into(),
+        )
+        .into_err(*pos)),
+        _ => Ok(NestedStep::Expr(settings.level_up())),
+    }
+}
+
+/// Continue parsing an object map literal for [`parse_nested`], adding the item just parsed, if any.
+#[cfg(not(feature = "no_object"))]
+fn next_map_item(
+    input: &mut TokenStream,
+    state: &mut ParseState,
+    stack: &mut Vec<Nested>,
+    item: Option<Expr>,
+) -> Result<NestedStep, ParseError> {
+    const MISSING_RBRACE: &str = "to end this object map literal";
+
+    let (map, template, prop, settings) = match stack.last_mut() {
+        Some(Nested::Map(map, template, prop, settings)) => (map, template, prop, *settings),
+        _ => unreachable!("expecting an object map literal"),
+    };
+
+    if let Some(expr) = item {
+        let prop = mem::take(prop);
+        template.insert(prop.name.clone().into(), Default::default());
+        map.push((prop, expr));
+
+        match input.peek().unwrap() {
+            (Token::Comma, _) => {
+                eat_token(input, Token::Comma);
+            }
+            (Token::RightBrace, _) => (),
+            (Token::Identifier(_), pos) => {
+                return Err(PERR::MissingToken(
+                    Token::Comma.into(),
+                    "to separate the items of this object map literal".into(),
+                )
+                .into_err(*pos))
+            }
+            (Token::LexError(err), pos) => return Err(err.clone().into_err(*pos)),
+            (_, pos) => {
+                return Err(
+                    PERR::MissingToken(Token::RightBrace.into(), MISSING_RBRACE.into())
+                        .into_err(*pos),
+                )
+            }
+        }
+    }
+
+    match input.peek().unwrap() {
+        (Token::RightBrace, _) => {
+            eat_token(input, Token::RightBrace);
+
+            let mut map = mem::take(map);
+            map.shrink_to_fit();
+            let template = mem::take(template);
+            stack.pop();
+
+            return Ok(NestedStep::Operand(
+                Expr::Map(Box::new((map, template)), settings.pos),
+                false,
+            ));
+        }
+        (Token::EOF, pos) => {
+            return Err(
+                PERR::MissingToken(Token::RightBrace.into(), MISSING_RBRACE.into()).into_err(*pos),
+            )
+        }
+        _ => (),
+    }
+
+    let (name, pos) = match input.next().unwrap() {
+        (Token::Identifier(s), pos) | (Token::StringConstant(s), pos) => {
+            if map.iter().any(|(p, _)| p.name == s) {
+                return Err(PERR::DuplicatedProperty(s).into_err(pos));
+            }
+            (s, pos)
+        }
+        (Token::Reserved(s), pos) if is_valid_identifier(s.chars()) => {
+            return Err(PERR::Reserved(s).into_err(pos));
+        }
+        (Token::LexError(err), pos) => return Err(err.into_err(pos)),
+        (Token::EOF, pos) => {
+            return Err(
+                PERR::MissingToken(Token::RightBrace.into(), MISSING_RBRACE.into()).into_err(pos),
+            );
+        }
+        (_, pos) if map.is_empty() => {
+            return Err(
+                PERR::MissingToken(Token::RightBrace.into(), MISSING_RBRACE.into()).into_err(pos),
+            );
+        }
+        (_, pos) => return Err(PERR::PropertyExpected.into_err(pos)),
+    };
+
+    match input.next().unwrap() {
+        (Token::Colon, _) => (),
+        (Token::LexError(err), pos) => return Err(err.into_err(pos)),
+        (_, pos) => {
+            return Err(PERR::MissingToken(
+                Token::Colon.into(),
+                format!(
+                    "to follow the property '{}' in this object map literal",
+                    name
+                ),
+            )
+            .into_err(pos))
+        }
+    };
+
+    #[cfg(not(feature = "unchecked"))]
+    if state.engine.max_map_size() > 0 && map.len() >= state.engine.max_map_size() {
+        return Err(PERR::LiteralTooLarge(
+            "Number of properties in object map literal".to_string(),
+            state.engine.max_map_size(),
+        )
+        .into_err(input.peek().unwrap().1));
+    }
+
+    *prop = Ident {
+        name: state.get_identifier(name),
+        pos,
+    };
+
+    Ok(NestedStep::Expr(settings.level_up()))
 }
 
 /// Make sure that the expression is not a statement expression (i.e. wrapped in `{}`).
@@ -2248,6 +2653,10 @@ fn parse_import(
     // import expr ...
     let expr = parse_expr(input, state, lib, settings.level_up())?;
 
+    if !state.engine.computed_imports && !matches!(expr, Expr::StringConstant(_, _)) {
+        return Err(PERR::ComputedImportPath.into_err(expr.position()));
+    }
+
     // import expr as ...
     if !match_token(input, Token::As).0 {
         return Ok(Stmt::Import(expr, None, settings.pos));
@@ -2362,7 +2771,95 @@ fn parse_export(
     Ok(Stmt::Export(exports, settings.pos))
 }
 
+/// A statement block being parsed by [`parse_block`].
+struct BlockState {
+    statements: Vec<Stmt>,
+    prev_entry_stack_len: usize,
+    #[cfg(not(feature = "no_module"))]
+    prev_mods_len: usize,
+    settings: ParseSettings,
+}
+
+impl BlockState {
+    /// Begin a new statement block.
+    fn new(state: &mut ParseState, settings: ParseSettings) -> Self {
+        let prev_entry_stack_len = state.entry_stack_len;
+        state.entry_stack_len = state.stack.len();
+
+        Self {
+            statements: Vec::with_capacity(8),
+            prev_entry_stack_len,
+            #[cfg(not(feature = "no_module"))]
+            prev_mods_len: state.modules.len(),
+            settings,
+        }
+    }
+    /// End the statement block.
+    fn end(self, state: &mut ParseState) -> Stmt {
+        state.stack.truncate(state.entry_stack_len);
+        state.entry_stack_len = self.prev_entry_stack_len;
+
+        #[cfg(not(feature = "no_module"))]
+        state.modules.truncate(self.prev_mods_len);
+
+        Stmt::Block(self.statements, self.settings.pos)
+    }
+    /// Add a statement to the statement block.
+    ///
+    /// Returns `true` if the statement block is closed.
+    fn push(
+        &mut self,
+        input: &mut TokenStream,
+        state: &mut ParseState,
+        stmt: Stmt,
+        stmt_pos: Position,
+    ) -> Result<bool, ParseError> {
+        if stmt.is_noop() {
+            return Ok(false);
+        }
+
+        // See if it needs a terminating semicolon
+        let need_semicolon = !stmt.is_self_terminated();
+
+        self.statements.push(stmt);
+
+        let err = match input.peek().unwrap() {
+            // { ... stmt }
+            (Token::RightBrace, _) => {
+                eat_token(input, Token::RightBrace);
+                return Ok(true);
+            }
+            // { ... stmt;
+            (Token::SemiColon, _) if need_semicolon => {
+                eat_token(input, Token::SemiColon);
+                return Ok(false);
+            }
+            // { ... { stmt } ;
+            (Token::SemiColon, _) if !need_semicolon => return Ok(false),
+            // { ... { stmt } ???
+            (_, _) if !need_semicolon => return Ok(false),
+            // { ... stmt <error>
+            (Token::LexError(err), pos) => err.clone().into_err(*pos),
+            // { ... stmt ???
+            (_, pos) => {
+                // Semicolons are not optional between statements
+                PERR::MissingToken(
+                    Token::SemiColon.into(),
+                    "to terminate this statement".into(),
+                )
+                .into_err(*pos)
+            }
+        };
+
+        recover_from_error(input, state, err, stmt_pos, false)?;
+        Ok(false)
+    }
+}
+
 /// Parse a statement block.
+///
+/// Statement blocks nested directly in the block are parsed with an explicit stack of the
+/// enclosing blocks instead of recursively, so deeply-nested blocks do not overflow the call stack.
 fn parse_block(
     input: &mut TokenStream,
     state: &mut ParseState,
@@ -2385,64 +2882,86 @@ fn parse_block(
     #[cfg(not(feature = "unchecked"))]
     settings.ensure_level_within_max_limit(state.max_expr_depth)?;
 
-    let mut statements = Vec::with_capacity(8);
+    // Enclosing blocks, with the positions of the nested blocks
+    let mut outer: Vec<(BlockState, Position)> = Vec::new();
+    let mut block = BlockState::new(state, settings);
 
-    let prev_entry_stack_len = state.entry_stack_len;
-    state.entry_stack_len = state.stack.len();
+    loop {
+        while !match_token(input, Token::RightBrace).0 {
+            // Parse statements inside the block
+            block.settings.is_global = false;
+
+            let stmt_pos = match input.peek().unwrap() {
+                // { ... <EOF>
+                (Token::EOF, pos) => {
+                    let pos = *pos;
+                    let err = PERR::MissingToken(
+                        Token::RightBrace.into(),
+                        "to end this statement block".into(),
+                    )
+                    .into_err(pos);
+                    recover_from_error(input, state, err, pos, false)?;
+                    break;
+                }
+                (_, pos) => *pos,
+            };
 
-    #[cfg(not(feature = "no_module"))]
-    let prev_mods_len = state.modules.len();
+            // { ... { - nested statements block
+            if input.peek().unwrap().0 == Token::LeftBrace {
+                #[cfg(not(feature = "unchecked"))]
+                {
+                    let mut settings = block.settings.level_up();
+                    settings.pos = stmt_pos;
 
-    while !match_token(input, Token::RightBrace).0 {
-        // Parse statements inside the block
-        settings.is_global = false;
+                    if let Err(err) = settings.ensure_level_within_max_limit(state.max_expr_depth) {
+                        recover_from_error(input, state, err, stmt_pos, false)?;
+                        continue;
+                    }
+                }
 
-        let stmt = parse_stmt(input, state, lib, settings.level_up())?;
+                let mut settings = block.settings.level_up().level_up();
+                settings.pos = eat_token(input, Token::LeftBrace);
 
-        if stmt.is_noop() {
-            continue;
-        }
+                #[cfg(not(feature = "unchecked"))]
+                if let Err(err) = settings.ensure_level_within_max_limit(state.max_expr_depth) {
+                    recover_from_error(input, state, err, stmt_pos, false)?;
+                    continue;
+                }
 
-        // See if it needs a terminating semicolon
-        let need_semicolon = !stmt.is_self_terminated();
+                outer.push((block, stmt_pos));
+                block = BlockState::new(state, settings);
+                continue;
+            }
 
-        statements.push(stmt);
+            let stmt = match parse_stmt(input, state, lib, block.settings.level_up()) {
+                Ok(stmt) => stmt,
+                Err(err) => {
+                    recover_from_error(input, state, err, stmt_pos, false)?;
+                    continue;
+                }
+            };
 
-        match input.peek().unwrap() {
-            // { ... stmt }
-            (Token::RightBrace, _) => {
-                eat_token(input, Token::RightBrace);
+            if block.push(input, state, stmt, stmt_pos)? {
                 break;
             }
-            // { ... stmt;
-            (Token::SemiColon, _) if need_semicolon => {
-                eat_token(input, Token::SemiColon);
-            }
-            // { ... { stmt } ;
-            (Token::SemiColon, _) if !need_semicolon => (),
-            // { ... { stmt } ???
-            (_, _) if !need_semicolon => (),
-            // { ... stmt <error>
-            (Token::LexError(err), pos) => return Err(err.clone().into_err(*pos)),
-            // { ... stmt ???
-            (_, pos) => {
-                // Semicolons are not optional between statements
-                return Err(PERR::MissingToken(
-                    Token::SemiColon.into(),
-                    "to terminate this statement".into(),
-                )
-                .into_err(*pos));
-            }
         }
-    }
 
-    state.stack.truncate(state.entry_stack_len);
-    state.entry_stack_len = prev_entry_stack_len;
+        // Close the block, and all enclosing blocks closed with it
+        loop {
+            let stmt = block.end(state);
 
-    #[cfg(not(feature = "no_module"))]
-    state.modules.truncate(prev_mods_len);
+            match outer.pop() {
+                None => return Ok(stmt),
+                Some((parent, stmt_pos)) => {
+                    block = parent;
 
-    Ok(Stmt::Block(statements, settings.pos))
+                    if !block.push(input, state, stmt, stmt_pos)? {
+                        break;
+                    }
+                }
+            }
+        }
+    }
 }
 
 /// Parse an expression as a statement.
@@ -2542,12 +3061,13 @@ fn parse_stmt(
                 (Token::Fn, pos) => {
                     let mut new_state = ParseState::new(
                         state.engine,
-                        state.buffer.clone(),
+                        state.tokenizer_control.clone(),
                         #[cfg(not(feature = "unchecked"))]
                         state.max_function_expr_depth,
                         #[cfg(not(feature = "unchecked"))]
                         state.max_function_expr_depth,
                     );
+                    new_state.set_scripts(state.scripts);
 
                     let settings = ParseSettings {
                         allow_if_expr: true,
@@ -2561,7 +3081,10 @@ fn parse_stmt(
                         pos: pos,
                     };
 
-                    let func = parse_fn(input, &mut new_state, lib, access, settings, _comments)?;
+                    new_state.errors = state.errors.take();
+                    let result = parse_fn(input, &mut new_state, lib, access, settings, _comments);
+                    state.errors = new_state.errors.take();
+                    let func = result?;
                     let hash = calc_fn_hash(empty(), &func.name, func.params.len());
 
                     if lib.contains_key(&hash) {
@@ -2804,6 +3327,7 @@ fn parse_fn(
         #[cfg(not(feature = "no_module"))]
         mods: Default::default(),
         comments,
+        bytecode: None,
     })
 }
 
@@ -2957,6 +3481,7 @@ fn parse_anon_fn(
         #[cfg(not(feature = "no_module"))]
         mods: Default::default(),
         comments: Default::default(),
+        bytecode: None,
     };
 
     let expr = Expr::FnPointer(fn_name.into(), settings.pos);
@@ -3030,7 +3555,15 @@ impl Engine {
                 pos: Position::NONE,
             };
 
-            let stmt = parse_stmt(input, state, &mut functions, settings)?;
+            let stmt_pos = input.peek().unwrap().1;
+
+            let stmt = match parse_stmt(input, state, &mut functions, settings) {
+                Ok(stmt) => stmt,
+                Err(err) => {
+                    recover_from_error(input, state, err, stmt_pos, true)?;
+                    continue;
+                }
+            };
 
             if stmt.is_noop() {
                 continue;
@@ -3040,29 +3573,32 @@ impl Engine {
 
             statements.push(stmt);
 
-            match input.peek().unwrap() {
+            let err = match input.peek().unwrap() {
                 // EOF
                 (Token::EOF, _) => break,
                 // stmt ;
                 (Token::SemiColon, _) if need_semicolon => {
                     eat_token(input, Token::SemiColon);
+                    continue;
                 }
                 // stmt ;
-                (Token::SemiColon, _) if !need_semicolon => (),
+                (Token::SemiColon, _) if !need_semicolon => continue,
                 // { stmt } ???
-                (_, _) if !need_semicolon => (),
+                (_, _) if !need_semicolon => continue,
                 // stmt <error>
-                (Token::LexError(err), pos) => return Err(err.clone().into_err(*pos)),
+                (Token::LexError(err), pos) => err.clone().into_err(*pos),
                 // stmt ???
                 (_, pos) => {
                     // Semicolons are not optional between statements
-                    return Err(PERR::MissingToken(
+                    PERR::MissingToken(
                         Token::SemiColon.into(),
                         "to terminate this statement".into(),
                     )
-                    .into_err(*pos));
+                    .into_err(*pos)
                 }
-            }
+            };
+
+            recover_from_error(input, state, err, stmt_pos, true)?;
         }
 
         Ok((statements, functions.into_iter().map(|(_, v)| v).collect()))
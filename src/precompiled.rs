@@ -0,0 +1,2020 @@
+//! Module implementing the serialization of compiled [`AST`]s into a stable, versioned format,
+//! so that precompiled scripts can be stored (e.g. on disk) and loaded without parsing.
+
+use crate::ast::{
+    BinaryExpr, CustomExpr, Expr, FnCallExpr, FnCallHash, Ident, OpAssignment, ReturnType, Stmt,
+    StmtBlock,
+};
+use crate::dynamic::{AccessMode, Union};
+use crate::module::NamespaceRef;
+use crate::stdlib::{
+    boxed::Box,
+    cell::Cell,
+    collections::BTreeMap,
+    convert::TryInto,
+    fmt,
+    fmt::Write,
+    format,
+    hash::{Hash, Hasher},
+    mem,
+    num::NonZeroUsize,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use crate::token::Span;
+use crate::utils::{calc_fn_hash, get_hasher};
+use crate::{Dynamic, Engine, EvalAltResult, FnPtr, Module, Position, Shared, StaticVec, AST, INT};
+
+/// Magic bytes at the start of a precompiled [`AST`] in binary form.
+const MAGIC: &[u8] = b"RHAIAST\0";
+
+/// Version of the precompiled format.
+///
+/// This must be bumped whenever the layout of any node changes.
+const FORMAT_VERSION: u64 = 1;
+
+/// Tag of the top-level node.
+const HEADER: &str = "RhaiAST";
+
+/// All tags of [`Node::Variant`] nodes.
+///
+/// In binary form, a tag is stored as its index into this list, so new tags must only ever be
+/// appended to the end.
+const TAGS: &[&str] = &[
+    // Top level
+    HEADER,
+    "Module",
+    "Fn",
+    // Values
+    "ReadOnly",
+    "Char",
+    "Decimal",
+    "ObjectMap",
+    "FnPtr",
+    "Float",
+    // Statements
+    "Noop",
+    "If",
+    "Switch",
+    "While",
+    "Do",
+    "For",
+    "Let",
+    "Const",
+    "Assignment",
+    "Block",
+    "TryCatch",
+    "Expr",
+    "Continue",
+    "Break",
+    "Return",
+    "Import",
+    "Export",
+    "Share",
+    // Expressions
+    "DynamicConstant",
+    "BoolConstant",
+    "IntegerConstant",
+    "FloatConstant",
+    "CharConstant",
+    "StringConstant",
+    "FnPointer",
+    "InterpolatedString",
+    "Array",
+    "Map",
+    "Unit",
+    "Variable",
+    "Property",
+    "Stmt",
+    "FnCall",
+    "Dot",
+    "Index",
+    "And",
+    "Or",
+    "Custom",
+];
+
+/// Feature flags that change the layout of an [`AST`].
+///
+/// A precompiled [`AST`] can only be loaded by an [`Engine`] built with the same set of these.
+const FEATURES: &[(&str, bool)] = &[
+    ("no_float", cfg!(feature = "no_float")),
+    ("f32_float", cfg!(feature = "f32_float")),
+    ("only_i32", cfg!(feature = "only_i32")),
+    ("decimal", cfg!(feature = "decimal")),
+    ("no_index", cfg!(feature = "no_index")),
+    ("no_object", cfg!(feature = "no_object")),
+    ("no_function", cfg!(feature = "no_function")),
+    ("no_closure", cfg!(feature = "no_closure")),
+    ("no_module", cfg!(feature = "no_module")),
+];
+
+/// Op-assignment operators.
+const OP_ASSIGNMENTS: &[&str] = &[
+    "+=", "-=", "*=", "/=", "%=", "**=", "<<=", ">>=", "&=", "|=", "^=",
+];
+
+/// Error when serializing or loading a precompiled [`AST`].
+#[derive(Debug, Clone)]
+struct PrecompiledError(String);
+
+impl fmt::Display for PrecompiledError {
+    #[inline(always)]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl crate::stdlib::error::Error for PrecompiledError {}
+
+type Res<T> = Result<T, Box<EvalAltResult>>;
+
+/// Make an error for an [`AST`] that cannot be serialized.
+fn cannot_serialize(msg: impl Into<String>) -> Box<EvalAltResult> {
+    EvalAltResult::ErrorSystem(
+        "Cannot serialize AST".to_string(),
+        Box::new(PrecompiledError(msg.into())),
+    )
+    .into()
+}
+
+/// Make an error for an invalid precompiled [`AST`].
+fn invalid(msg: impl Into<String>) -> Box<EvalAltResult> {
+    EvalAltResult::ErrorSystem(
+        "Invalid precompiled AST".to_string(),
+        Box::new(PrecompiledError(msg.into())),
+    )
+    .into()
+}
+
+/// Names of the layout-affecting features this crate is built with.
+fn features() -> impl Iterator<Item = &'static str> {
+    FEATURES.iter().filter(|(_, on)| *on).map(|(name, _)| *name)
+}
+
+/// Number of bits in [`INT`].
+fn int_bits() -> u64 {
+    (mem::size_of::<INT>() * 8) as u64
+}
+
+/// Number of bits in [`FLOAT`][crate::FLOAT] (zero under `no_float`).
+fn float_bits() -> u64 {
+    #[cfg(not(feature = "no_float"))]
+    return (mem::size_of::<crate::FLOAT>() * 8) as u64;
+    #[cfg(feature = "no_float")]
+    return 0;
+}
+
+/// Calculate a fingerprint of the hash functions used for the hashes stored inside an [`AST`].
+///
+/// Hashes are not recalculated when loading, so they must be calculated the same way.
+fn fingerprint() -> u64 {
+    let hasher = &mut get_hasher();
+    calc_fn_hash(["a", "b"].iter().copied(), "f", 2).hash(hasher);
+    Dynamic::UNIT.hash(hasher);
+    Dynamic::TRUE.hash(hasher);
+    Dynamic::from(42 as INT).hash(hasher);
+    Dynamic::from('x').hash(hasher);
+    Dynamic::from("rhai").hash(hasher);
+    hasher.finish()
+}
+
+#[allow(clippy::useless_conversion)]
+fn from_int(value: INT) -> i64 {
+    value.into()
+}
+
+#[cfg(not(feature = "no_float"))]
+#[allow(clippy::useless_conversion)]
+fn from_float(value: crate::FLOAT) -> f64 {
+    value.into()
+}
+
+#[allow(clippy::useless_conversion)]
+fn to_int(value: i64) -> Res<INT> {
+    value
+        .try_into()
+        .map_err(|_| invalid(format!("integer {} out of range", value)))
+}
+
+#[cfg(not(feature = "no_float"))]
+#[allow(clippy::unnecessary_cast)]
+fn to_float(value: f64) -> crate::FLOAT {
+    value as crate::FLOAT
+}
+
+/// A node in the serialized form of an [`AST`].
+///
+/// An [`AST`] is first converted into a tree of nodes, which is then written in binary or JSON.
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Unit,
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    Str(String),
+    List(Vec<Node>),
+    Variant(&'static str, Vec<Node>),
+}
+
+impl Node {
+    fn is_unit(&self) -> bool {
+        matches!(self, Self::Unit)
+    }
+    fn as_bool(&self) -> Res<bool> {
+        match self {
+            Self::Bool(b) => Ok(*b),
+            _ => Err(invalid("expecting a boolean")),
+        }
+    }
+    fn as_int(&self) -> Res<i64> {
+        match self {
+            Self::Int(n) => Ok(*n),
+            Self::UInt(n) => (*n)
+                .try_into()
+                .map_err(|_| invalid(format!("integer {} out of range", n))),
+            _ => Err(invalid("expecting an integer")),
+        }
+    }
+    fn as_uint(&self) -> Res<u64> {
+        match self {
+            Self::UInt(n) => Ok(*n),
+            Self::Int(n) => (*n)
+                .try_into()
+                .map_err(|_| invalid(format!("integer {} out of range", n))),
+            _ => Err(invalid("expecting an unsigned integer")),
+        }
+    }
+    fn as_usize(&self) -> Res<usize> {
+        let n = self.as_uint()?;
+        n.try_into()
+            .map_err(|_| invalid(format!("integer {} out of range", n)))
+    }
+    #[cfg(not(feature = "no_float"))]
+    fn as_float(&self) -> Res<f64> {
+        match self {
+            Self::Float(f) => Ok(*f),
+            Self::Int(n) => Ok(*n as f64),
+            Self::UInt(n) => Ok(*n as f64),
+            _ => Err(invalid("expecting a floating-point number")),
+        }
+    }
+    fn as_str(&self) -> Res<&str> {
+        match self {
+            Self::Str(s) => Ok(s),
+            _ => Err(invalid("expecting a string")),
+        }
+    }
+    fn as_char(&self) -> Res<char> {
+        let s = self.as_str()?;
+        let mut chars = s.chars();
+
+        match (chars.next(), chars.next()) {
+            (Some(ch), None) => Ok(ch),
+            _ => Err(invalid(format!("expecting a character, not '{}'", s))),
+        }
+    }
+    fn as_list(&self) -> Res<&[Node]> {
+        match self {
+            Self::List(items) => Ok(items),
+            _ => Err(invalid("expecting a list")),
+        }
+    }
+    fn as_variant(&self) -> Res<(&'static str, &[Node])> {
+        match self {
+            Self::Variant(tag, items) => Ok((tag, items)),
+            _ => Err(invalid("expecting a tagged node")),
+        }
+    }
+    /// Get the items in a list with a fixed number of items.
+    fn as_tuple(&self, len: usize) -> Res<&[Node]> {
+        fields(self.as_list()?, len)
+    }
+}
+
+/// Check the number of fields of a node.
+fn fields(items: &[Node], len: usize) -> Res<&[Node]> {
+    if items.len() == len {
+        Ok(items)
+    } else {
+        Err(invalid(format!(
+            "expecting {} fields, not {}",
+            len,
+            items.len()
+        )))
+    }
+}
+
+/// Map a list of nodes.
+fn map_list<'a, T>(node: &'a Node, f: impl FnMut(&'a Node) -> Res<T>) -> Res<Vec<T>> {
+    node.as_list()?.iter().map(f).collect()
+}
+
+/// Map a node that is [`Node::Unit`] for [`None`].
+fn map_opt<'a, T>(node: &'a Node, f: impl FnOnce(&'a Node) -> Res<T>) -> Res<Option<T>> {
+    if node.is_unit() {
+        Ok(None)
+    } else {
+        f(node).map(Some)
+    }
+}
+
+/// Look up a tag in [`TAGS`].
+fn find_tag(tag: &str) -> Option<(usize, &'static str)> {
+    TAGS.iter()
+        .enumerate()
+        .find(|(_, &t)| t == tag)
+        .map(|(i, &t)| (i, t))
+}
+
+// Conversion from an AST into nodes.
+
+fn enc_pos(pos: Position) -> Node {
+    fn enc(pos: Position) -> u64 {
+        let line = pos.line().unwrap_or(0) as u64;
+        let position = pos.position().unwrap_or(0) as u64;
+        line << 16 | position
+    }
+
+    let span = pos.span();
+
+    // Positions with a span also record its start and end
+    if pos.is_none() || span == Span::point(pos) {
+        Node::UInt(enc(pos))
+    } else {
+        Node::List(vec![
+            Node::UInt(enc(pos)),
+            Node::UInt(enc(span.start())),
+            Node::UInt(enc(span.end())),
+        ])
+    }
+}
+
+fn enc_ident(ident: &Ident) -> Node {
+    Node::List(vec![Node::Str(ident.name.to_string()), enc_pos(ident.pos)])
+}
+
+fn enc_opt<T>(value: Option<T>, f: impl FnOnce(T) -> Node) -> Node {
+    value.map_or(Node::Unit, f)
+}
+
+fn enc_str_list<'a>(items: impl IntoIterator<Item = &'a (impl AsRef<str> + 'a)>) -> Node {
+    Node::List(
+        items
+            .into_iter()
+            .map(|s| Node::Str(s.as_ref().to_string()))
+            .collect(),
+    )
+}
+
+fn enc_namespace(namespace: &NamespaceRef) -> Node {
+    Node::List(vec![
+        Node::UInt(namespace.index().map_or(0, |n| n.get() as u64)),
+        Node::List(namespace.iter().map(enc_ident).collect()),
+    ])
+}
+
+fn enc_value(value: &Dynamic) -> Res<Node> {
+    let node = match &value.0 {
+        Union::Unit(_, _) => Node::Unit,
+        Union::Bool(b, _) => Node::Bool(*b),
+        Union::Str(s, _) => Node::Str(s.to_string()),
+        Union::Char(ch, _) => Node::Variant("Char", vec![Node::Str(ch.to_string())]),
+        Union::Int(n, _) => Node::Int(from_int(*n)),
+        #[cfg(not(feature = "no_float"))]
+        Union::Float(f, _) => Node::Float(from_float(**f)),
+        #[cfg(feature = "decimal")]
+        Union::Decimal(d, _) => Node::Variant("Decimal", vec![Node::Str(d.to_string())]),
+        #[cfg(not(feature = "no_index"))]
+        Union::Array(a, _) => Node::List(a.iter().map(enc_value).collect::<Res<_>>()?),
+        #[cfg(not(feature = "no_object"))]
+        Union::Map(m, _) => Node::Variant(
+            "ObjectMap",
+            m.iter()
+                .map(|(k, v)| Ok(Node::List(vec![Node::Str(k.to_string()), enc_value(v)?])))
+                .collect::<Res<_>>()?,
+        ),
+        Union::FnPtr(f, _) => Node::Variant(
+            "FnPtr",
+            vec![
+                Node::Str(f.fn_name().to_string()),
+                Node::List(f.curry().iter().map(enc_value).collect::<Res<_>>()?),
+            ],
+        ),
+        #[cfg(not(feature = "no_closure"))]
+        Union::Shared(_, access) => {
+            let mut value = value.flatten_clone();
+            if *access == AccessMode::ReadOnly {
+                value.set_access_mode(AccessMode::ReadOnly);
+            }
+            return enc_value(&value);
+        }
+        _ => {
+            return Err(cannot_serialize(format!(
+                "value of type '{}' cannot be serialized",
+                value.type_name()
+            )))
+        }
+    };
+
+    if value.is_read_only() {
+        Ok(Node::Variant("ReadOnly", vec![node]))
+    } else {
+        Ok(node)
+    }
+}
+
+fn enc_block(block: &StmtBlock) -> Res<Node> {
+    Ok(Node::List(vec![
+        enc_stmts(&block.statements)?,
+        enc_pos(block.pos),
+    ]))
+}
+
+fn enc_stmts(statements: &[Stmt]) -> Res<Node> {
+    Ok(Node::List(
+        statements.iter().map(enc_stmt).collect::<Res<_>>()?,
+    ))
+}
+
+fn enc_stmt(stmt: &Stmt) -> Res<Node> {
+    Ok(match stmt {
+        Stmt::Noop(pos) => Node::Variant("Noop", vec![enc_pos(*pos)]),
+        Stmt::If(expr, x, pos) => Node::Variant(
+            "If",
+            vec![
+                enc_expr(expr)?,
+                enc_block(&x.0)?,
+                enc_block(&x.1)?,
+                enc_pos(*pos),
+            ],
+        ),
+        Stmt::Switch(expr, x, pos) => Node::Variant(
+            "Switch",
+            vec![
+                enc_expr(expr)?,
+                Node::List(
+                    x.0.iter()
+                        .map(|(hash, block)| {
+                            Ok(Node::List(vec![Node::UInt(*hash), enc_block(block)?]))
+                        })
+                        .collect::<Res<_>>()?,
+                ),
+                enc_block(&x.1)?,
+                enc_pos(*pos),
+            ],
+        ),
+        Stmt::While(expr, body, pos) => Node::Variant(
+            "While",
+            vec![enc_expr(expr)?, enc_block(body)?, enc_pos(*pos)],
+        ),
+        Stmt::Do(body, expr, is_while, pos) => Node::Variant(
+            "Do",
+            vec![
+                enc_block(body)?,
+                enc_expr(expr)?,
+                Node::Bool(*is_while),
+                enc_pos(*pos),
+            ],
+        ),
+        Stmt::For(expr, x, pos) => Node::Variant(
+            "For",
+            vec![
+                enc_expr(expr)?,
+                enc_ident(&x.0),
+                enc_block(&x.1)?,
+                enc_pos(*pos),
+            ],
+        ),
+        Stmt::Let(expr, name, export, pos) | Stmt::Const(expr, name, export, pos) => {
+            let tag = if matches!(stmt, Stmt::Let(_, _, _, _)) {
+                "Let"
+            } else {
+                "Const"
+            };
+            Node::Variant(
+                tag,
+                vec![
+                    enc_expr(expr)?,
+                    enc_ident(name),
+                    Node::Bool(*export),
+                    enc_pos(*pos),
+                ],
+            )
+        }
+        Stmt::Assignment(x, pos) => Node::Variant(
+            "Assignment",
+            vec![
+                enc_expr(&x.0)?,
+                enc_opt(x.1.as_ref(), |op| Node::Str(op.op.to_string())),
+                enc_expr(&x.2)?,
+                enc_pos(*pos),
+            ],
+        ),
+        Stmt::Block(statements, pos) => {
+            Node::Variant("Block", vec![enc_stmts(statements)?, enc_pos(*pos)])
+        }
+        Stmt::TryCatch(x, pos, catch_pos) => Node::Variant(
+            "TryCatch",
+            vec![
+                enc_block(&x.0)?,
+                enc_opt(x.1.as_ref(), enc_ident),
+                enc_block(&x.2)?,
+                enc_pos(*pos),
+                enc_pos(*catch_pos),
+            ],
+        ),
+        Stmt::Expr(expr) => Node::Variant("Expr", vec![enc_expr(expr)?]),
+        Stmt::Continue(pos) => Node::Variant("Continue", vec![enc_pos(*pos)]),
+        Stmt::Break(pos) => Node::Variant("Break", vec![enc_pos(*pos)]),
+        Stmt::Return(typ, expr, pos) => Node::Variant(
+            "Return",
+            vec![
+                Node::Bool(*typ == ReturnType::Exception),
+                match expr {
+                    Some(expr) => enc_expr(expr)?,
+                    None => Node::Unit,
+                },
+                enc_pos(*pos),
+            ],
+        ),
+        #[cfg(not(feature = "no_module"))]
+        Stmt::Import(expr, name, pos) => Node::Variant(
+            "Import",
+            vec![
+                enc_expr(expr)?,
+                enc_opt(name.as_deref(), enc_ident),
+                enc_pos(*pos),
+            ],
+        ),
+        #[cfg(not(feature = "no_module"))]
+        Stmt::Export(list, pos) => Node::Variant(
+            "Export",
+            vec![
+                Node::List(
+                    list.iter()
+                        .map(|(name, alias)| {
+                            Node::List(vec![enc_ident(name), enc_opt(alias.as_ref(), enc_ident)])
+                        })
+                        .collect(),
+                ),
+                enc_pos(*pos),
+            ],
+        ),
+        #[cfg(not(feature = "no_closure"))]
+        Stmt::Share(name) => Node::Variant("Share", vec![Node::Str(name.to_string())]),
+    })
+}
+
+fn enc_exprs<'a>(exprs: impl IntoIterator<Item = &'a Expr>) -> Res<Node> {
+    Ok(Node::List(
+        exprs.into_iter().map(enc_expr).collect::<Res<_>>()?,
+    ))
+}
+
+fn enc_binary(tag: &'static str, x: &BinaryExpr, pos: Position) -> Res<Node> {
+    Ok(Node::Variant(
+        tag,
+        vec![enc_expr(&x.lhs)?, enc_expr(&x.rhs)?, enc_pos(pos)],
+    ))
+}
+
+fn enc_fn_call(x: &FnCallExpr) -> Res<Node> {
+    Ok(Node::List(vec![
+        enc_opt(x.hash.script, Node::UInt),
+        Node::UInt(x.hash.native),
+        Node::Bool(x.capture),
+        enc_exprs(x.args.iter())?,
+        Node::List(
+            x.constant_args
+                .iter()
+                .map(|(value, pos)| Ok(Node::List(vec![enc_value(value)?, enc_pos(*pos)])))
+                .collect::<Res<_>>()?,
+        ),
+        enc_opt(x.namespace.as_ref(), enc_namespace),
+        Node::Str(x.name.to_string()),
+        enc_opt(x.source_text.as_ref(), |s| Node::Str(s.to_string())),
+    ]))
+}
+
+fn enc_expr(expr: &Expr) -> Res<Node> {
+    Ok(match expr {
+        Expr::DynamicConstant(value, pos) => {
+            Node::Variant("DynamicConstant", vec![enc_value(value)?, enc_pos(*pos)])
+        }
+        Expr::BoolConstant(b, pos) => {
+            Node::Variant("BoolConstant", vec![Node::Bool(*b), enc_pos(*pos)])
+        }
+        Expr::IntegerConstant(n, pos) => Node::Variant(
+            "IntegerConstant",
+            vec![Node::Int(from_int(*n)), enc_pos(*pos)],
+        ),
+        #[cfg(not(feature = "no_float"))]
+        Expr::FloatConstant(f, pos) => Node::Variant(
+            "FloatConstant",
+            vec![Node::Float(from_float(**f)), enc_pos(*pos)],
+        ),
+        Expr::CharConstant(ch, pos) => Node::Variant(
+            "CharConstant",
+            vec![Node::Str(ch.to_string()), enc_pos(*pos)],
+        ),
+        Expr::StringConstant(s, pos) => Node::Variant(
+            "StringConstant",
+            vec![Node::Str(s.to_string()), enc_pos(*pos)],
+        ),
+        Expr::FnPointer(s, pos) => {
+            Node::Variant("FnPointer", vec![Node::Str(s.to_string()), enc_pos(*pos)])
+        }
+        Expr::InterpolatedString(x) => {
+            Node::Variant("InterpolatedString", vec![enc_exprs(x.iter())?])
+        }
+        Expr::Array(x, pos) => Node::Variant("Array", vec![enc_exprs(x.iter())?, enc_pos(*pos)]),
+        Expr::Map(x, pos) => Node::Variant(
+            "Map",
+            vec![
+                Node::List(
+                    x.0.iter()
+                        .map(|(name, expr)| Ok(Node::List(vec![enc_ident(name), enc_expr(expr)?])))
+                        .collect::<Res<_>>()?,
+                ),
+                Node::List(
+                    x.1.iter()
+                        .map(|(k, v)| Ok(Node::List(vec![Node::Str(k.to_string()), enc_value(v)?])))
+                        .collect::<Res<_>>()?,
+                ),
+                enc_pos(*pos),
+            ],
+        ),
+        Expr::Unit(pos) => Node::Variant("Unit", vec![enc_pos(*pos)]),
+        Expr::Variable(x) => Node::Variant(
+            "Variable",
+            vec![
+                Node::UInt(x.0.map_or(0, |n| n.get() as u64)),
+                enc_opt(x.1.as_ref(), |(hash, namespace)| {
+                    Node::List(vec![Node::UInt(*hash), enc_namespace(namespace)])
+                }),
+                enc_ident(&x.2),
+            ],
+        ),
+        Expr::Property(x) => Node::Variant(
+            "Property",
+            vec![
+                Node::Str((x.0).0.to_string()),
+                Node::UInt((x.0).1),
+                Node::Str((x.1).0.to_string()),
+                Node::UInt((x.1).1),
+                enc_ident(&x.2),
+            ],
+        ),
+        Expr::Stmt(x) => Node::Variant("Stmt", vec![enc_block(x)?]),
+        Expr::FnCall(x, pos) => Node::Variant("FnCall", vec![enc_fn_call(x)?, enc_pos(*pos)]),
+        Expr::Dot(x, pos) => enc_binary("Dot", x, *pos)?,
+        Expr::Index(x, pos) => enc_binary("Index", x, *pos)?,
+        Expr::And(x, pos) => enc_binary("And", x, *pos)?,
+        Expr::Or(x, pos) => enc_binary("Or", x, *pos)?,
+        Expr::Custom(x, pos) => Node::Variant(
+            "Custom",
+            vec![
+                enc_exprs(x.keywords.iter())?,
+                enc_str_list(x.tokens.iter()),
+                Node::Int(x.scope_delta as i64),
+                enc_pos(*pos),
+            ],
+        ),
+    })
+}
+
+/// Encoder of the table of [modules][Module] referenced by an [`AST`].
+///
+/// Each distinct [module][Module] is encoded once, after all [modules][Module] it refers to,
+/// and is referred to by its index in the table.
+#[derive(Default)]
+struct Encoder {
+    /// Encoded [modules][Module].
+    modules: Vec<Node>,
+    /// Index of each encoded [module][Module], keyed by its address.
+    index: BTreeMap<usize, usize>,
+    /// Addresses of the [modules][Module] being encoded, to detect cycles.
+    pending: Vec<usize>,
+}
+
+impl Encoder {
+    /// Encode a [module][Module] into the table, returning its index.
+    fn module(&mut self, module: &Module) -> Res<u64> {
+        let addr = module as *const Module as usize;
+
+        if let Some(&index) = self.index.get(&addr) {
+            return Ok(index as u64);
+        }
+        if self.pending.contains(&addr) {
+            return Err(cannot_serialize("modules refer to each other cyclically"));
+        }
+        if let Some(f) = module.iter_fn().find(|f| !f.func.is_script()) {
+            return Err(cannot_serialize(format!(
+                "module contains native Rust function '{}'",
+                f.name
+            )));
+        }
+        if module.count().2 > 0 {
+            return Err(cannot_serialize("module contains type iterators"));
+        }
+
+        self.pending.push(addr);
+
+        let variables = module
+            .iter_var()
+            .map(|(name, value)| {
+                Ok(Node::List(vec![
+                    Node::Str(name.to_string()),
+                    enc_value(value)?,
+                ]))
+            })
+            .collect::<Res<_>>()?;
+
+        let sub_modules = module
+            .iter_sub_modules()
+            .map(|(name, m)| {
+                Ok(Node::List(vec![
+                    Node::Str(name.to_string()),
+                    Node::UInt(self.module(&m)?),
+                ]))
+            })
+            .collect::<Res<_>>()?;
+
+        #[allow(unused_mut)]
+        let mut functions = Vec::new();
+
+        #[cfg(not(feature = "no_function"))]
+        for (namespace, _, _, _, f) in module.iter_script_fn() {
+            let is_global = namespace == crate::FnNamespace::Global;
+            functions.push(Node::List(vec![Node::Bool(is_global), self.fn_def(f)?]));
+        }
+
+        self.pending.pop();
+
+        self.modules.push(Node::Variant(
+            "Module",
+            vec![
+                enc_opt(module.id(), |id| Node::Str(id.to_string())),
+                Node::Bool(module.is_indexed()),
+                Node::List(variables),
+                Node::List(sub_modules),
+                Node::List(functions),
+            ],
+        ));
+
+        let index = self.modules.len() - 1;
+        self.index.insert(addr, index);
+        Ok(index as u64)
+    }
+    /// Encode a script-defined function.
+    #[cfg(not(feature = "no_function"))]
+    fn fn_def(&mut self, f: &crate::ast::ScriptFnDef) -> Res<Node> {
+        let lib = match f.lib {
+            Some(ref lib) => Node::UInt(self.module(lib)?),
+            None => Node::Unit,
+        };
+
+        #[allow(unused_mut)]
+        let mut mods = Vec::new();
+
+        #[cfg(not(feature = "no_module"))]
+        {
+            let mut imports = f.mods.iter_lazy_raw().collect::<Vec<_>>();
+            imports.reverse();
+
+            for (name, module, lazy) in imports {
+                let lazy = enc_opt(lazy, |lazy| {
+                    Node::List(vec![
+                        Node::Str(lazy.path().to_string()),
+                        enc_opt(lazy.source(), |s| Node::Str(s.to_string())),
+                        enc_pos(lazy.position()),
+                    ])
+                });
+                mods.push(Node::List(vec![
+                    Node::Str(name.to_string()),
+                    Node::UInt(self.module(module)?),
+                    lazy,
+                ]));
+            }
+        }
+
+        #[cfg(not(feature = "no_closure"))]
+        let externals = enc_str_list(f.externals.iter());
+        #[cfg(feature = "no_closure")]
+        let externals = Node::List(Vec::new());
+
+        Ok(Node::Variant(
+            "Fn",
+            vec![
+                Node::Str(f.name.to_string()),
+                Node::Bool(f.access == crate::FnAccess::Private),
+                enc_str_list(f.params.iter()),
+                externals,
+                enc_str_list(f.comments.iter()),
+                enc_block(&f.body)?,
+                lib,
+                Node::List(mods),
+            ],
+        ))
+    }
+}
+
+/// Convert an [`AST`] into nodes.
+#[allow(deprecated)]
+fn encode(ast: &AST) -> Res<Node> {
+    let mut encoder = Encoder::default();
+
+    let lib = encoder.module(ast.lib())?;
+
+    #[cfg(not(feature = "no_module"))]
+    let resolver = match ast.resolver() {
+        Some(resolver) => {
+            let modules = resolver
+                .iter()
+                .map(|(path, m)| {
+                    Ok(Node::List(vec![
+                        Node::Str(path.to_string()),
+                        Node::UInt(encoder.module(m)?),
+                    ]))
+                })
+                .collect::<Res<_>>()?;
+            let scripts = resolver
+                .iter_scripts()
+                .map(|(path, script)| {
+                    Node::List(vec![
+                        Node::Str(path.to_string()),
+                        Node::Str(script.to_string()),
+                    ])
+                })
+                .collect();
+            Node::List(vec![Node::List(modules), Node::List(scripts)])
+        }
+        None => Node::Unit,
+    };
+    #[cfg(feature = "no_module")]
+    let resolver = Node::Unit;
+
+    let statements = enc_stmts(ast.statements())?;
+
+    Ok(Node::Variant(
+        HEADER,
+        vec![
+            Node::UInt(FORMAT_VERSION),
+            Node::List(features().map(|f| Node::Str(f.to_string())).collect()),
+            Node::UInt(int_bits()),
+            Node::UInt(float_bits()),
+            Node::UInt(fingerprint()),
+            Node::List(encoder.modules),
+            enc_opt(ast.source(), |s| Node::Str(s.to_string())),
+            statements,
+            Node::UInt(lib),
+            resolver,
+        ],
+    ))
+}
+
+// Conversion from nodes back into an AST.
+
+fn dec_pos(node: &Node) -> Res<Position> {
+    fn dec(node: &Node) -> Res<Position> {
+        let n = node.as_uint()?;
+
+        if n > u32::MAX as u64 {
+            return Err(invalid(format!("invalid position {}", n)));
+        }
+
+        match (n >> 16) as u16 {
+            0 => Ok(Position::NONE),
+            line => Ok(Position::new(line, (n & 0xffff) as u16)),
+        }
+    }
+
+    match node {
+        Node::List(_) => {
+            let x = node.as_tuple(3)?;
+            let pos = dec(&x[0])?;
+
+            match Span::new(dec(&x[1])?, dec(&x[2])?) {
+                Some(span) if !pos.is_none() && !span.is_none() => Ok(pos.with_span(span)),
+                _ => Err(invalid(format!("invalid span at {}", pos))),
+            }
+        }
+        _ => dec(node),
+    }
+}
+
+fn dec_ident(node: &Node) -> Res<Ident> {
+    let x = node.as_tuple(2)?;
+    Ok(Ident {
+        name: x[0].as_str()?.into(),
+        pos: dec_pos(&x[1])?,
+    })
+}
+
+/// Decode an index counting back from the end of a stack of `len` items.
+fn dec_index(node: &Node, len: usize, what: &str) -> Res<Option<NonZeroUsize>> {
+    match node.as_usize()? {
+        index if index > len => Err(invalid(format!(
+            "invalid {} index {} (only {} in scope)",
+            what, index, len
+        ))),
+        index => Ok(NonZeroUsize::new(index)),
+    }
+}
+
+fn dec_value(node: &Node) -> Res<Dynamic> {
+    let union = match node {
+        Node::Unit => Union::Unit((), AccessMode::ReadWrite),
+        Node::Bool(b) => Union::Bool(*b, AccessMode::ReadWrite),
+        Node::Int(_) | Node::UInt(_) => Union::Int(to_int(node.as_int()?)?, AccessMode::ReadWrite),
+        #[cfg(not(feature = "no_float"))]
+        Node::Float(f) => Union::Float(to_float(*f).into(), AccessMode::ReadWrite),
+        Node::Str(s) => Union::Str(s.as_str().into(), AccessMode::ReadWrite),
+        #[cfg(not(feature = "no_index"))]
+        Node::List(items) => Union::Array(
+            Box::new(items.iter().map(dec_value).collect::<Res<_>>()?),
+            AccessMode::ReadWrite,
+        ),
+        Node::Variant("ReadOnly", x) => {
+            let mut value = dec_value(&fields(x, 1)?[0])?;
+            value.set_access_mode(AccessMode::ReadOnly);
+            return Ok(value);
+        }
+        Node::Variant("Char", x) => Union::Char(fields(x, 1)?[0].as_char()?, AccessMode::ReadWrite),
+        #[cfg(feature = "decimal")]
+        Node::Variant("Decimal", x) => {
+            let s = fields(x, 1)?[0].as_str()?;
+            let d = s
+                .parse::<rust_decimal::Decimal>()
+                .map_err(|_| invalid(format!("invalid decimal number '{}'", s)))?;
+            Union::Decimal(Box::new(d), AccessMode::ReadWrite)
+        }
+        #[cfg(not(feature = "no_object"))]
+        Node::Variant("ObjectMap", x) => Union::Map(
+            Box::new(
+                x.iter()
+                    .map(|pair| {
+                        let pair = pair.as_tuple(2)?;
+                        Ok((pair[0].as_str()?.into(), dec_value(&pair[1])?))
+                    })
+                    .collect::<Res<_>>()?,
+            ),
+            AccessMode::ReadWrite,
+        ),
+        Node::Variant("FnPtr", x) => {
+            let x = fields(x, 2)?;
+            let curry = map_list(&x[1], dec_value)?;
+            let f = FnPtr::new_unchecked(x[0].as_str()?, curry.into_iter().collect());
+            Union::FnPtr(Box::new(f), AccessMode::ReadWrite)
+        }
+        _ => return Err(invalid("unsupported value")),
+    };
+
+    Ok(Dynamic(union))
+}
+
+/// Decoder of nodes back into an [`AST`].
+struct Decoder<'e> {
+    /// [`Engine`] loading the [`AST`].
+    engine: &'e Engine,
+    /// Table of [modules][Module] decoded so far.
+    modules: Vec<Shared<Module>>,
+    /// Number of variables declared in scope at the current node, to validate variable indices.
+    vars: Cell<usize>,
+    /// Number of imported modules in scope at the current node, to validate namespace indices.
+    imports: Cell<usize>,
+    /// Is the current node at global level (i.e. not in a block or function)?
+    global: Cell<bool>,
+}
+
+impl Decoder<'_> {
+    /// Decode within a new scope, removing the variables and imports declared in it afterwards.
+    fn scoped<T>(&self, f: impl FnOnce() -> Res<T>) -> Res<T> {
+        let (vars, imports) = (self.vars.get(), self.imports.get());
+        let global = self.global.replace(false);
+        let result = f();
+        self.vars.set(vars);
+        self.imports.set(imports);
+        self.global.set(global);
+        result
+    }
+    /// Declare a variable in the current scope.
+    fn declare_var(&self) {
+        self.vars.set(self.vars.get() + 1);
+    }
+    /// Decode a [namespace][NamespaceRef].
+    fn namespace(&self, node: &Node) -> Res<NamespaceRef> {
+        let x = node.as_tuple(2)?;
+        #[allow(unused_mut)]
+        let mut namespace: NamespaceRef = map_list(&x[1], dec_ident)?
+            .into_iter()
+            .collect::<StaticVec<_>>()
+            .into();
+
+        #[cfg(not(feature = "no_module"))]
+        namespace.set_index(dec_index(&x[0], self.imports.get(), "module")?);
+
+        Ok(namespace)
+    }
+    /// Get a [module][Module] from the table.
+    fn module_ref(&self, node: &Node) -> Res<Shared<Module>> {
+        let index = node.as_usize()?;
+        self.modules
+            .get(index)
+            .cloned()
+            .ok_or_else(|| invalid(format!("invalid module index {}", index)))
+    }
+    /// Decode a [module][Module].
+    fn module(&self, node: &Node) -> Res<Module> {
+        let (tag, x) = node.as_variant()?;
+
+        if tag != "Module" {
+            return Err(invalid("expecting a module"));
+        }
+
+        let x = fields(x, 5)?;
+        let mut module = Module::new();
+
+        module.set_id(map_opt(&x[0], |n| n.as_str())?);
+
+        for var in x[2].as_list()? {
+            let var = var.as_tuple(2)?;
+            module.set_var(var[0].as_str()?, dec_value(&var[1])?);
+        }
+        for sub_module in x[3].as_list()? {
+            let sub_module = sub_module.as_tuple(2)?;
+            module.set_sub_module(sub_module[0].as_str()?, self.module_ref(&sub_module[1])?);
+        }
+
+        #[cfg(not(feature = "no_function"))]
+        for f in x[4].as_list()? {
+            let f = f.as_tuple(2)?;
+            let hash = module.set_script_fn(self.fn_def(&f[1])?);
+
+            if f[0].as_bool()? {
+                module.update_fn_namespace(hash, crate::FnNamespace::Global);
+            }
+        }
+        #[cfg(feature = "no_function")]
+        if !x[4].as_list()?.is_empty() {
+            return Err(invalid("functions are not supported"));
+        }
+
+        if x[1].as_bool()? {
+            module.build_index();
+        }
+
+        Ok(module)
+    }
+    /// Decode a script-defined function.
+    #[cfg(not(feature = "no_function"))]
+    fn fn_def(&self, node: &Node) -> Res<crate::ast::ScriptFnDef> {
+        let (tag, x) = node.as_variant()?;
+
+        if tag != "Fn" {
+            return Err(invalid("expecting a function"));
+        }
+
+        let x = fields(x, 8)?;
+
+        #[cfg(not(feature = "no_module"))]
+        let mut mods = crate::engine::Imports::default();
+
+        #[cfg(not(feature = "no_module"))]
+        for import in x[7].as_list()? {
+            let import = import.as_tuple(3)?;
+            let lazy = map_opt(&import[2], |n| {
+                let lazy = n.as_tuple(3)?;
+                Ok(crate::engine::LazyModule::new(
+                    lazy[0].as_str()?.into(),
+                    map_opt(&lazy[1], |n| n.as_str().map(Into::into))?,
+                    dec_pos(&lazy[2])?,
+                ))
+            })?;
+            mods.push_raw(
+                import[0].as_str()?.into(),
+                self.module_ref(&import[1])?,
+                lazy,
+            );
+        }
+
+        let params: StaticVec<_> = map_list(&x[2], |n| n.as_str().map(Into::into))?
+            .into_iter()
+            .collect();
+
+        // Functions only see their own parameters
+        let (vars, imports) = (self.vars.replace(params.len()), self.imports.replace(0));
+        let global = self.global.replace(false);
+        let body = self.block(&x[5]);
+        self.vars.set(vars);
+        self.imports.set(imports);
+        self.global.set(global);
+
+        Ok(crate::ast::ScriptFnDef {
+            body: body?,
+            lib: map_opt(&x[6], |n| self.module_ref(n))?,
+            #[cfg(not(feature = "no_module"))]
+            mods,
+            name: x[0].as_str()?.into(),
+            access: if x[1].as_bool()? {
+                crate::FnAccess::Private
+            } else {
+                crate::FnAccess::Public
+            },
+            params,
+            #[cfg(not(feature = "no_closure"))]
+            externals: map_list(&x[3], |n| n.as_str().map(Into::into))?
+                .into_iter()
+                .collect(),
+            comments: map_list(&x[4], |n| n.as_str().map(Into::into))?
+                .into_iter()
+                .collect(),
+            bytecode: None,
+        })
+    }
+    fn block(&self, node: &Node) -> Res<StmtBlock> {
+        let x = node.as_tuple(2)?;
+        Ok(StmtBlock {
+            statements: self.scoped(|| self.stmts(&x[0]))?.into_iter().collect(),
+            pos: dec_pos(&x[1])?,
+        })
+    }
+    fn stmts(&self, node: &Node) -> Res<Vec<Stmt>> {
+        map_list(node, |n| self.stmt(n))
+    }
+    fn stmt(&self, node: &Node) -> Res<Stmt> {
+        let (tag, x) = node.as_variant()?;
+
+        Ok(match tag {
+            "Noop" => Stmt::Noop(dec_pos(&fields(x, 1)?[0])?),
+            "If" => {
+                let x = fields(x, 4)?;
+                Stmt::If(
+                    self.expr(&x[0])?,
+                    Box::new((self.block(&x[1])?, self.block(&x[2])?)),
+                    dec_pos(&x[3])?,
+                )
+            }
+            "Switch" => {
+                let x = fields(x, 4)?;
+                let table = map_list(&x[1], |n| {
+                    let case = n.as_tuple(2)?;
+                    Ok((case[0].as_uint()?, Box::new(self.block(&case[1])?)))
+                })?;
+                Stmt::Switch(
+                    self.expr(&x[0])?,
+                    Box::new((table.into_iter().collect(), self.block(&x[2])?)),
+                    dec_pos(&x[3])?,
+                )
+            }
+            "While" => {
+                let x = fields(x, 3)?;
+                Stmt::While(
+                    self.expr(&x[0])?,
+                    Box::new(self.block(&x[1])?),
+                    dec_pos(&x[2])?,
+                )
+            }
+            "Do" => {
+                let x = fields(x, 4)?;
+                Stmt::Do(
+                    Box::new(self.block(&x[0])?),
+                    self.expr(&x[1])?,
+                    x[2].as_bool()?,
+                    dec_pos(&x[3])?,
+                )
+            }
+            "For" => {
+                let x = fields(x, 4)?;
+                let expr = self.expr(&x[0])?;
+                let body = self.scoped(|| {
+                    self.declare_var();
+                    self.block(&x[2])
+                })?;
+                Stmt::For(expr, Box::new((dec_ident(&x[1])?, body)), dec_pos(&x[3])?)
+            }
+            "Let" | "Const" => {
+                let x = fields(x, 4)?;
+                let expr = self.expr(&x[0])?;
+                let name = Box::new(dec_ident(&x[1])?);
+                let export = x[2].as_bool()?;
+                let pos = dec_pos(&x[3])?;
+
+                if export && !self.global.get() {
+                    return Err(invalid("exported variable not at global level"));
+                }
+
+                self.declare_var();
+
+                if tag == "Let" {
+                    Stmt::Let(expr, name, export, pos)
+                } else {
+                    Stmt::Const(expr, name, export, pos)
+                }
+            }
+            "Assignment" => {
+                let x = fields(x, 4)?;
+                let op = map_opt(&x[1], |n| {
+                    let op = n.as_str()?;
+                    OP_ASSIGNMENTS
+                        .iter()
+                        .find(|&&o| o == op)
+                        .map(|&o| OpAssignment::new(o))
+                        .ok_or_else(|| invalid(format!("unknown operator '{}'", op)))
+                })?;
+                Stmt::Assignment(
+                    Box::new((self.expr(&x[0])?, op, self.expr(&x[2])?)),
+                    dec_pos(&x[3])?,
+                )
+            }
+            "Block" => {
+                let x = fields(x, 2)?;
+                Stmt::Block(self.scoped(|| self.stmts(&x[0]))?, dec_pos(&x[1])?)
+            }
+            "TryCatch" => {
+                let x = fields(x, 5)?;
+                let body = self.block(&x[0])?;
+                let var = map_opt(&x[1], dec_ident)?;
+                let catch_body = self.scoped(|| {
+                    if var.is_some() {
+                        self.declare_var();
+                    }
+                    self.block(&x[2])
+                })?;
+                Stmt::TryCatch(
+                    Box::new((body, var, catch_body)),
+                    dec_pos(&x[3])?,
+                    dec_pos(&x[4])?,
+                )
+            }
+            "Expr" => Stmt::Expr(self.expr(&fields(x, 1)?[0])?),
+            "Continue" => Stmt::Continue(dec_pos(&fields(x, 1)?[0])?),
+            "Break" => Stmt::Break(dec_pos(&fields(x, 1)?[0])?),
+            "Return" => {
+                let x = fields(x, 3)?;
+                let typ = if x[0].as_bool()? {
+                    ReturnType::Exception
+                } else {
+                    ReturnType::Return
+                };
+                Stmt::Return(typ, map_opt(&x[1], |n| self.expr(n))?, dec_pos(&x[2])?)
+            }
+            #[cfg(not(feature = "no_module"))]
+            "Import" => {
+                let x = fields(x, 3)?;
+                let expr = self.expr(&x[0])?;
+                let alias = map_opt(&x[1], dec_ident)?.map(Box::new);
+
+                if alias.is_some() {
+                    self.imports.set(self.imports.get() + 1);
+                }
+
+                Stmt::Import(expr, alias, dec_pos(&x[2])?)
+            }
+            #[cfg(not(feature = "no_module"))]
+            "Export" => {
+                let x = fields(x, 2)?;
+                let list = map_list(&x[0], |n| {
+                    let pair = n.as_tuple(2)?;
+                    Ok((dec_ident(&pair[0])?, map_opt(&pair[1], dec_ident)?))
+                })?;
+                Stmt::Export(list, dec_pos(&x[1])?)
+            }
+            #[cfg(not(feature = "no_closure"))]
+            "Share" => Stmt::Share(fields(x, 1)?[0].as_str()?.into()),
+            _ => return Err(invalid(format!("unsupported statement '{}'", tag))),
+        })
+    }
+    fn exprs(&self, node: &Node) -> Res<StaticVec<Expr>> {
+        Ok(map_list(node, |n| self.expr(n))?.into_iter().collect())
+    }
+    fn binary(&self, x: &[Node]) -> Res<(Box<BinaryExpr>, Position)> {
+        let x = fields(x, 3)?;
+        Ok((
+            Box::new(BinaryExpr {
+                lhs: self.expr(&x[0])?,
+                rhs: self.expr(&x[1])?,
+            }),
+            dec_pos(&x[2])?,
+        ))
+    }
+    fn fn_call(&self, node: &Node) -> Res<FnCallExpr> {
+        let x = node.as_tuple(8)?;
+        let constant_args = map_list(&x[4], |n| {
+            let arg = n.as_tuple(2)?;
+            Ok((dec_value(&arg[0])?, dec_pos(&arg[1])?))
+        })?;
+
+        Ok(FnCallExpr {
+            hash: FnCallHash {
+                script: map_opt(&x[0], Node::as_uint)?,
+                native: x[1].as_uint()?,
+            },
+            capture: x[2].as_bool()?,
+            args: self.exprs(&x[3])?,
+            constant_args: constant_args.into_iter().collect(),
+            namespace: map_opt(&x[5], |n| self.namespace(n))?,
+            name: x[6].as_str()?.into(),
+            source_text: map_opt(&x[7], |n| n.as_str().map(Into::into))?,
+        })
+    }
+    fn expr(&self, node: &Node) -> Res<Expr> {
+        let (tag, x) = node.as_variant()?;
+
+        Ok(match tag {
+            "DynamicConstant" => {
+                let x = fields(x, 2)?;
+                Expr::DynamicConstant(Box::new(dec_value(&x[0])?), dec_pos(&x[1])?)
+            }
+            "BoolConstant" => {
+                let x = fields(x, 2)?;
+                Expr::BoolConstant(x[0].as_bool()?, dec_pos(&x[1])?)
+            }
+            "IntegerConstant" => {
+                let x = fields(x, 2)?;
+                Expr::IntegerConstant(to_int(x[0].as_int()?)?, dec_pos(&x[1])?)
+            }
+            #[cfg(not(feature = "no_float"))]
+            "FloatConstant" => {
+                let x = fields(x, 2)?;
+                Expr::FloatConstant(to_float(x[0].as_float()?).into(), dec_pos(&x[1])?)
+            }
+            "CharConstant" => {
+                let x = fields(x, 2)?;
+                Expr::CharConstant(x[0].as_char()?, dec_pos(&x[1])?)
+            }
+            "StringConstant" => {
+                let x = fields(x, 2)?;
+                Expr::StringConstant(x[0].as_str()?.into(), dec_pos(&x[1])?)
+            }
+            "FnPointer" => {
+                let x = fields(x, 2)?;
+                Expr::FnPointer(x[0].as_str()?.into(), dec_pos(&x[1])?)
+            }
+            "InterpolatedString" => {
+                Expr::InterpolatedString(Box::new(self.exprs(&fields(x, 1)?[0])?))
+            }
+            "Array" => {
+                let x = fields(x, 2)?;
+                Expr::Array(Box::new(self.exprs(&x[0])?), dec_pos(&x[1])?)
+            }
+            "Map" => {
+                let x = fields(x, 3)?;
+                let props = map_list(&x[0], |n| {
+                    let prop = n.as_tuple(2)?;
+                    Ok((dec_ident(&prop[0])?, self.expr(&prop[1])?))
+                })?;
+                let template = map_list(&x[1], |n| {
+                    let pair = n.as_tuple(2)?;
+                    Ok((pair[0].as_str()?.into(), dec_value(&pair[1])?))
+                })?;
+                Expr::Map(
+                    Box::new((props.into_iter().collect(), template.into_iter().collect())),
+                    dec_pos(&x[2])?,
+                )
+            }
+            "Unit" => Expr::Unit(dec_pos(&fields(x, 1)?[0])?),
+            "Variable" => {
+                let x = fields(x, 3)?;
+                let namespace = map_opt(&x[1], |n| {
+                    let ns = n.as_tuple(2)?;
+                    Ok((ns[0].as_uint()?, self.namespace(&ns[1])?))
+                })?;
+                let index = dec_index(&x[0], self.vars.get(), "variable")?;
+                Expr::Variable(Box::new((index, namespace, dec_ident(&x[2])?)))
+            }
+            "Property" => {
+                let x = fields(x, 5)?;
+                Expr::Property(Box::new((
+                    (x[0].as_str()?.into(), x[1].as_uint()?),
+                    (x[2].as_str()?.into(), x[3].as_uint()?),
+                    dec_ident(&x[4])?,
+                )))
+            }
+            "Stmt" => Expr::Stmt(Box::new(self.block(&fields(x, 1)?[0])?)),
+            "FnCall" => {
+                let x = fields(x, 2)?;
+                Expr::FnCall(Box::new(self.fn_call(&x[0])?), dec_pos(&x[1])?)
+            }
+            "Dot" => {
+                let (x, pos) = self.binary(x)?;
+                Expr::Dot(x, pos)
+            }
+            "Index" => {
+                let (x, pos) = self.binary(x)?;
+                Expr::Index(x, pos)
+            }
+            "And" => {
+                let (x, pos) = self.binary(x)?;
+                Expr::And(x, pos)
+            }
+            "Or" => {
+                let (x, pos) = self.binary(x)?;
+                Expr::Or(x, pos)
+            }
+            "Custom" => {
+                let x = fields(x, 4)?;
+                let tokens = map_list(&x[1], |n| n.as_str().map(Into::into))?;
+                let scope_delta = x[2].as_int()? as isize;
+                let key = tokens
+                    .first()
+                    .ok_or_else(|| invalid("custom syntax has no tokens"))?;
+
+                // Custom syntax is evaluated by the engine, so it must be registered
+                match self.engine.custom_syntax.get(key) {
+                    Some(syntax) if syntax.scope_delta == scope_delta => (),
+                    Some(_) => {
+                        return Err(invalid(format!(
+                            "custom syntax '{}' is registered with a different scope delta",
+                            key
+                        )))
+                    }
+                    None => {
+                        return Err(invalid(format!(
+                            "custom syntax '{}' is not registered",
+                            key
+                        )))
+                    }
+                }
+
+                // Custom syntax adds or removes variables before its keywords are evaluated
+                let vars = self.vars.get() as isize + scope_delta;
+                self.vars.set(if vars < 0 { 0 } else { vars as usize });
+
+                Expr::Custom(
+                    Box::new(CustomExpr {
+                        keywords: self.exprs(&x[0])?,
+                        tokens,
+                        scope_delta,
+                    }),
+                    dec_pos(&x[3])?,
+                )
+            }
+            _ => return Err(invalid(format!("unsupported expression '{}'", tag))),
+        })
+    }
+}
+
+/// Convert nodes back into an [`AST`], validating that it can be run by an [`Engine`].
+fn decode(engine: &Engine, node: &Node) -> Res<AST> {
+    let (tag, x) = node.as_variant()?;
+
+    if tag != HEADER {
+        return Err(invalid("not a precompiled AST"));
+    }
+
+    let version = x
+        .first()
+        .ok_or_else(|| invalid("missing format version"))?
+        .as_uint()?;
+
+    if version != FORMAT_VERSION {
+        return Err(invalid(format!(
+            "format version {} is not supported (expecting {})",
+            version, FORMAT_VERSION
+        )));
+    }
+
+    let x = fields(x, 10)?;
+
+    let found = map_list(&x[1], |n| n.as_str().map(ToString::to_string))?;
+    let expected: Vec<_> = features().collect();
+
+    if found != expected {
+        return Err(invalid(format!(
+            "compiled with features [{}] but the engine is built with [{}]",
+            found.join(", "),
+            expected.join(", ")
+        )));
+    }
+    if x[2].as_uint()? != int_bits() {
+        return Err(invalid(format!(
+            "compiled with {}-bit integers but the engine uses {}-bit integers",
+            x[2].as_uint()?,
+            int_bits()
+        )));
+    }
+    if x[3].as_uint()? != float_bits() {
+        return Err(invalid(format!(
+            "compiled with {}-bit floating-point numbers but the engine uses {}-bit",
+            x[3].as_uint()?,
+            float_bits()
+        )));
+    }
+    if x[4].as_uint()? != fingerprint() {
+        return Err(invalid("compiled with incompatible hash functions"));
+    }
+
+    let mut decoder = Decoder {
+        engine,
+        modules: Vec::new(),
+        vars: Cell::new(0),
+        imports: Cell::new(0),
+        global: Cell::new(true),
+    };
+
+    for module in x[5].as_list()? {
+        let module = decoder.module(module)?;
+        decoder.modules.push(module.into());
+    }
+
+    let source = map_opt(&x[6], Node::as_str)?;
+    let statements = decoder.stmts(&x[7])?;
+    let lib = decoder.module_ref(&x[8])?;
+
+    #[allow(unused_mut)]
+    let mut ast = match source {
+        Some(source) => AST::new_with_source(statements, lib, source),
+        None => AST::new(statements, lib),
+    };
+
+    #[cfg(not(feature = "no_module"))]
+    if !x[9].is_unit() {
+        let resolver_node = x[9].as_tuple(2)?;
+        let mut resolver = crate::module::resolvers::StaticModuleResolver::new();
+
+        for m in resolver_node[0].as_list()? {
+            let m = m.as_tuple(2)?;
+            let module = crate::fn_native::shared_take_or_clone(decoder.module_ref(&m[1])?);
+            resolver.insert(m[0].as_str()?, module);
+        }
+        for script in resolver_node[1].as_list()? {
+            let script = script.as_tuple(2)?;
+            resolver.insert_script(script[0].as_str()?, script[1].as_str()?.to_string());
+        }
+
+        ast.set_resolver(resolver);
+    }
+    #[cfg(feature = "no_module")]
+    if !x[9].is_unit() {
+        return Err(invalid("embedded module resolvers are not supported"));
+    }
+
+    Ok(ast)
+}
+
+// Binary form.
+
+const NODE_UNIT: u8 = 0;
+const NODE_FALSE: u8 = 1;
+const NODE_TRUE: u8 = 2;
+const NODE_INT: u8 = 3;
+const NODE_UINT: u8 = 4;
+const NODE_FLOAT: u8 = 5;
+const NODE_STR: u8 = 6;
+const NODE_LIST: u8 = 7;
+const NODE_VARIANT: u8 = 8;
+
+/// Write an unsigned integer in LEB128 form.
+fn write_uint(buf: &mut Vec<u8>, mut n: u64) {
+    loop {
+        let byte = (n & 0x7f) as u8;
+        n >>= 7;
+
+        if n == 0 {
+            buf.push(byte);
+            break;
+        }
+        buf.push(byte | 0x80);
+    }
+}
+
+fn write_node(buf: &mut Vec<u8>, node: &Node) {
+    match node {
+        Node::Unit => buf.push(NODE_UNIT),
+        Node::Bool(false) => buf.push(NODE_FALSE),
+        Node::Bool(true) => buf.push(NODE_TRUE),
+        Node::Int(n) => {
+            buf.push(NODE_INT);
+            // Zig-zag encoding
+            write_uint(buf, ((n << 1) ^ (n >> 63)) as u64);
+        }
+        Node::UInt(n) => {
+            buf.push(NODE_UINT);
+            write_uint(buf, *n);
+        }
+        Node::Float(f) => {
+            buf.push(NODE_FLOAT);
+            buf.extend_from_slice(&f.to_le_bytes());
+        }
+        Node::Str(s) => {
+            buf.push(NODE_STR);
+            write_uint(buf, s.len() as u64);
+            buf.extend_from_slice(s.as_bytes());
+        }
+        Node::List(items) => {
+            buf.push(NODE_LIST);
+            write_uint(buf, items.len() as u64);
+            items.iter().for_each(|item| write_node(buf, item));
+        }
+        Node::Variant(tag, items) => {
+            let (index, _) = find_tag(tag).expect("tag is in TAGS");
+            buf.push(NODE_VARIANT);
+            write_uint(buf, index as u64);
+            write_uint(buf, items.len() as u64);
+            items.iter().for_each(|item| write_node(buf, item));
+        }
+    }
+}
+
+/// Reader of nodes in binary form.
+struct BinaryReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BinaryReader<'a> {
+    fn take(&mut self, len: usize) -> Res<&'a [u8]> {
+        let bytes = self
+            .bytes
+            .get(self.pos..self.pos + len)
+            .ok_or_else(|| invalid("unexpected end of data"))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+    fn uint(&mut self) -> Res<u64> {
+        let mut n = 0_u64;
+
+        for shift in (0..64).step_by(7) {
+            let byte = self.take(1)?[0];
+            n |= ((byte & 0x7f) as u64) << shift;
+
+            if byte & 0x80 == 0 {
+                return Ok(n);
+            }
+        }
+
+        Err(invalid("integer too large"))
+    }
+    /// Read a length, which cannot be larger than the number of remaining bytes.
+    fn len(&mut self) -> Res<usize> {
+        let len = self.uint()?;
+
+        if len > (self.bytes.len() - self.pos) as u64 {
+            return Err(invalid("unexpected end of data"));
+        }
+
+        Ok(len as usize)
+    }
+    fn items(&mut self) -> Res<Vec<Node>> {
+        let len = self.len()?;
+        (0..len).map(|_| self.node()).collect()
+    }
+    fn node(&mut self) -> Res<Node> {
+        Ok(match self.take(1)?[0] {
+            NODE_UNIT => Node::Unit,
+            NODE_FALSE => Node::Bool(false),
+            NODE_TRUE => Node::Bool(true),
+            NODE_INT => {
+                let n = self.uint()?;
+                Node::Int((n >> 1) as i64 ^ -((n & 1) as i64))
+            }
+            NODE_UINT => Node::UInt(self.uint()?),
+            NODE_FLOAT => {
+                let bytes = self.take(8)?;
+                Node::Float(f64::from_le_bytes(bytes.try_into().unwrap()))
+            }
+            NODE_STR => {
+                let len = self.len()?;
+                let s = crate::stdlib::str::from_utf8(self.take(len)?)
+                    .map_err(|_| invalid("invalid UTF-8 string"))?;
+                Node::Str(s.to_string())
+            }
+            NODE_LIST => Node::List(self.items()?),
+            NODE_VARIANT => {
+                let index = self.uint()?;
+                let tag = TAGS
+                    .get(index as usize)
+                    .ok_or_else(|| invalid(format!("unknown tag {}", index)))?;
+                Node::Variant(tag, self.items()?)
+            }
+            typ => return Err(invalid(format!("unknown node type {}", typ))),
+        })
+    }
+}
+
+// JSON form.
+
+fn write_json_str(out: &mut String, s: &str) {
+    out.push('"');
+    for ch in s.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            ch if (ch as u32) < 0x20 => {
+                write!(out, "\\u{:04x}", ch as u32).unwrap();
+            }
+            ch => out.push(ch),
+        }
+    }
+    out.push('"');
+}
+
+fn write_json_items(out: &mut String, items: &[Node]) {
+    out.push('[');
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        write_json(out, item);
+    }
+    out.push(']');
+}
+
+fn write_json(out: &mut String, node: &Node) {
+    match node {
+        Node::Unit => out.push_str("null"),
+        Node::Bool(b) => write!(out, "{}", b).unwrap(),
+        Node::Int(n) => write!(out, "{}", n).unwrap(),
+        Node::UInt(n) => write!(out, "{}", n).unwrap(),
+        // JSON has no representation for infinity and NaN
+        Node::Float(f) if !f.is_finite() => write!(out, "{{\"Float\":[\"{}\"]}}", f).unwrap(),
+        Node::Float(f) => write!(out, "{:?}", f).unwrap(),
+        Node::Str(s) => write_json_str(out, s),
+        Node::List(items) => write_json_items(out, items),
+        Node::Variant(tag, items) => {
+            out.push('{');
+            write_json_str(out, tag);
+            out.push(':');
+            write_json_items(out, items);
+            out.push('}');
+        }
+    }
+}
+
+/// Reader of nodes in JSON form.
+struct JsonReader<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> JsonReader<'a> {
+    fn error(&self, msg: &str) -> Box<EvalAltResult> {
+        invalid(format!("{} at offset {} in JSON", msg, self.pos))
+    }
+    fn skip_whitespace(&mut self) {
+        let rest = &self.text[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.text[self.pos..].chars().next()
+    }
+    fn next_char(&mut self) -> Res<char> {
+        let ch = self.text[self.pos..]
+            .chars()
+            .next()
+            .ok_or_else(|| self.error("unexpected end"))?;
+        self.pos += ch.len_utf8();
+        Ok(ch)
+    }
+    fn expect(&mut self, expected: char) -> Res<()> {
+        match self.peek() {
+            Some(ch) if ch == expected => {
+                self.pos += ch.len_utf8();
+                Ok(())
+            }
+            _ => Err(self.error(&format!("expecting '{}'", expected))),
+        }
+    }
+    fn keyword(&mut self, keyword: &str, node: Node) -> Res<Node> {
+        if self.text[self.pos..].starts_with(keyword) {
+            self.pos += keyword.len();
+            Ok(node)
+        } else {
+            Err(self.error("invalid keyword"))
+        }
+    }
+    fn string(&mut self) -> Res<String> {
+        self.expect('"')?;
+        let mut s = String::new();
+
+        loop {
+            match self.next_char()? {
+                '"' => return Ok(s),
+                '\\' => match self.next_char()? {
+                    '"' => s.push('"'),
+                    '\\' => s.push('\\'),
+                    '/' => s.push('/'),
+                    'b' => s.push('\u{8}'),
+                    'f' => s.push('\u{c}'),
+                    'n' => s.push('\n'),
+                    'r' => s.push('\r'),
+                    't' => s.push('\t'),
+                    'u' => {
+                        let mut code = self.hex4()?;
+
+                        // Surrogate pair
+                        if (0xd800..0xdc00).contains(&code) {
+                            if !self.text[self.pos..].starts_with("\\u") {
+                                return Err(self.error("invalid surrogate pair"));
+                            }
+                            self.pos += 2;
+                            let low = self.hex4()?;
+                            if !(0xdc00..0xe000).contains(&low) {
+                                return Err(self.error("invalid surrogate pair"));
+                            }
+                            code = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+                        }
+
+                        s.push(
+                            crate::stdlib::char::from_u32(code)
+                                .ok_or_else(|| self.error("invalid character"))?,
+                        );
+                    }
+                    _ => return Err(self.error("invalid escape sequence")),
+                },
+                ch => s.push(ch),
+            }
+        }
+    }
+    fn hex4(&mut self) -> Res<u32> {
+        let digits = self
+            .text
+            .get(self.pos..self.pos + 4)
+            .ok_or_else(|| self.error("unexpected end"))?;
+        let code =
+            u32::from_str_radix(digits, 16).map_err(|_| self.error("invalid escape sequence"))?;
+        self.pos += 4;
+        Ok(code)
+    }
+    fn number(&mut self) -> Res<Node> {
+        let rest = &self.text[self.pos..];
+        let len = rest
+            .find(|ch: char| !matches!(ch, '0'..='9' | '-' | '+' | '.' | 'e' | 'E'))
+            .unwrap_or(rest.len());
+        let s = &rest[..len];
+
+        let node = if s.contains(&['.', 'e', 'E'][..]) {
+            s.parse().ok().map(Node::Float)
+        } else if let Ok(n) = s.parse() {
+            Some(Node::Int(n))
+        } else {
+            s.parse().ok().map(Node::UInt)
+        };
+
+        let node = node.ok_or_else(|| self.error("invalid number"))?;
+        self.pos += len;
+        Ok(node)
+    }
+    fn items(&mut self) -> Res<Vec<Node>> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+
+        if self.peek() == Some(']') {
+            self.pos += 1;
+            return Ok(items);
+        }
+
+        loop {
+            items.push(self.node()?);
+
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some(']') => {
+                    self.pos += 1;
+                    return Ok(items);
+                }
+                _ => return Err(self.error("expecting ',' or ']'")),
+            }
+        }
+    }
+    fn node(&mut self) -> Res<Node> {
+        match self.peek() {
+            Some('n') => self.keyword("null", Node::Unit),
+            Some('t') => self.keyword("true", Node::Bool(true)),
+            Some('f') => self.keyword("false", Node::Bool(false)),
+            Some('"') => Ok(Node::Str(self.string()?)),
+            Some('[') => Ok(Node::List(self.items()?)),
+            Some('{') => {
+                self.pos += 1;
+                let tag = self.string()?;
+                let (_, tag) =
+                    find_tag(&tag).ok_or_else(|| self.error(&format!("unknown tag '{}'", tag)))?;
+                self.expect(':')?;
+                let items = self.items()?;
+                self.expect('}')?;
+
+                match (tag, items.as_slice()) {
+                    ("Float", [Node::Str(s)]) => s
+                        .parse()
+                        .map(Node::Float)
+                        .map_err(|_| self.error("invalid number")),
+                    _ => Ok(Node::Variant(tag, items)),
+                }
+            }
+            Some(_) => self.number(),
+            None => Err(self.error("unexpected end")),
+        }
+    }
+}
+
+impl AST {
+    /// Serialize the [`AST`] into a compact binary form, which can be loaded back via
+    /// [`Engine::ast_from_bytes`] without parsing the script again.
+    ///
+    /// The statements, script-defined functions (including doc-comments), positions and any
+    /// embedded [module resolver][crate::module_resolvers::StaticModuleResolver] (e.g. from
+    /// [`Engine::compile_into_self_contained`]) are all kept.
+    ///
+    /// The format is versioned and only loads into an [`Engine`] built with the same feature flags
+    /// that affect the [`AST`] (e.g. `no_float`, `only_i32`, `no_module`).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorSystem`][EvalAltResult::ErrorSystem] if the [`AST`] holds anything that
+    /// cannot be serialized, such as a constant of a custom type or a [module][Module] with
+    /// native Rust functions.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::{Engine, INT};
+    ///
+    /// let engine = Engine::new();
+    ///
+    /// let ast = engine.compile("let x = 40; x + 2")?;
+    /// let bytes = ast.to_bytes()?;
+    ///
+    /// let ast = engine.ast_from_bytes(&bytes)?;
+    ///
+    /// assert_eq!(engine.eval_ast::<INT>(&ast)?, 42);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Box<EvalAltResult>> {
+        let node = encode(self)?;
+        let mut buf = MAGIC.to_vec();
+        write_node(&mut buf, &node);
+        Ok(buf)
+    }
+    /// Serialize the [`AST`] into JSON, which can be loaded back via [`Engine::ast_from_json`]
+    /// without parsing the script again.
+    ///
+    /// This holds the same information as [`to_bytes`][AST::to_bytes], but is larger and slower
+    /// to load. It is mainly useful for inspection.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorSystem`][EvalAltResult::ErrorSystem] if the [`AST`] holds anything that
+    /// cannot be serialized.
+    pub fn to_json(&self) -> Result<String, Box<EvalAltResult>> {
+        let node = encode(self)?;
+        let mut json = String::new();
+        write_json(&mut json, &node);
+        Ok(json)
+    }
+}
+
+impl Engine {
+    /// Load an [`AST`] serialized by [`AST::to_bytes`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorSystem`][EvalAltResult::ErrorSystem] if the data is malformed, was
+    /// serialized in a different format version, or is not compatible with this [`Engine`]:
+    ///
+    /// * the feature flags affecting the [`AST`], or the sizes of integers and floating-point
+    ///   numbers, are different,
+    /// * the hash functions are different (e.g. a different version of this crate),
+    /// * a custom syntax used by the script is not registered.
+    pub fn ast_from_bytes(&self, bytes: &[u8]) -> Result<AST, Box<EvalAltResult>> {
+        if !bytes.starts_with(MAGIC) {
+            return Err(invalid("not a precompiled AST"));
+        }
+
+        let mut reader = BinaryReader {
+            bytes,
+            pos: MAGIC.len(),
+        };
+        let node = reader.node()?;
+
+        if reader.pos != bytes.len() {
+            return Err(invalid("unexpected data at the end"));
+        }
+
+        decode(self, &node)
+    }
+    /// Load an [`AST`] serialized by [`AST::to_json`].
+    ///
+    /// # Errors
+    ///
+    /// Same as [`ast_from_bytes`][Engine::ast_from_bytes].
+    pub fn ast_from_json(&self, json: &str) -> Result<AST, Box<EvalAltResult>> {
+        let mut reader = JsonReader { text: json, pos: 0 };
+        let node = reader.node()?;
+
+        if reader.peek().is_some() {
+            return Err(reader.error("unexpected data at the end"));
+        }
+
+        decode(self, &node)
+    }
+}
@@ -0,0 +1,285 @@
+//! Module implementing the per-function profiler.
+
+use crate::fn_native::{Locked, Shared};
+use crate::stdlib::{
+    collections::BTreeMap,
+    fmt,
+    fmt::Write,
+    ops::DerefMut,
+    string::{String, ToString},
+    time::Duration,
+    vec::Vec,
+};
+use crate::Position;
+
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+use crate::stdlib::time::Instant;
+
+#[cfg(any(target_arch = "wasm32", target_arch = "wasm64"))]
+use instant::Instant;
+
+/// Profile data recorded for a function.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct ProfileEntry {
+    /// Name of the function.
+    pub fn_name: String,
+    /// Source of the function (e.g. the path of the [module][crate::Module] defining it), if any.
+    /// For native Rust functions, this is the source of the script making the call.
+    pub source: Option<String>,
+    /// [Position][Position] of the function definition.
+    /// For native Rust functions, this is the position of the call site, so each call site
+    /// is recorded separately.
+    pub pos: Position,
+    /// Is this a script-defined function?
+    pub is_script: bool,
+    /// Number of calls.
+    pub calls: u64,
+    /// Total time spent in the function, including the functions it calls.
+    pub inclusive_time: Duration,
+    /// Total time spent in the function itself, excluding the functions it calls.
+    pub exclusive_time: Duration,
+    /// Number of operations performed by the function, including the functions it calls.
+    pub inclusive_operations: u64,
+    /// Number of operations performed by the function itself, excluding the functions it calls.
+    pub exclusive_operations: u64,
+}
+
+impl fmt::Display for ProfileEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.fn_name)?;
+
+        match (&self.source, self.pos.line()) {
+            (Some(source), Some(line)) => write!(f, " ({}:{})", source, line),
+            (Some(source), None) => write!(f, " ({})", source),
+            (None, Some(line)) => write!(f, " (line {})", line),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+/// A function call in progress.
+#[derive(Debug)]
+struct Frame {
+    /// Index of the [`ProfileEntry`] of the function.
+    entry: usize,
+    /// Time at the start of the call.
+    start_time: Instant,
+    /// Number of operations at the start of the call.
+    start_operations: u64,
+    /// Time spent in functions called by this function.
+    child_time: Duration,
+    /// Number of operations performed in functions called by this function.
+    child_operations: u64,
+}
+
+/// Profile data.
+#[derive(Debug, Default)]
+struct ProfileData {
+    /// Profile entries for all functions called.
+    entries: Vec<ProfileEntry>,
+    /// Index of the [`ProfileEntry`] for each function, keyed by source, position and name.
+    index: BTreeMap<(Option<String>, Position, String), usize>,
+    /// Calls in progress.
+    stack: Vec<Frame>,
+    /// Exclusive time spent in each call stack, keyed by the indices of the functions called.
+    stacks: BTreeMap<Vec<usize>, Duration>,
+}
+
+/// A profiler recording call counts, wall time and operation counts for all functions called
+/// by an [`Engine`][crate::Engine].
+///
+/// A [`Profiler`] is a shared handle. Cloning it shares the same profile data.
+///
+/// Not available under `no_std`.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+/// # #[cfg(not(feature = "no_function"))]
+/// # {
+/// use rhai::{Engine, Profiler};
+///
+/// let profiler = Profiler::new();
+///
+/// let mut engine = Engine::new();
+/// engine.set_profiler(Some(profiler.clone()));
+///
+/// engine.consume(r#"
+///     fn double(x) { x * 2 }
+///     for i in range(0, 10) { double(i); }
+/// "#)?;
+///
+/// let entry = profiler.entries().into_iter().find(|e| e.fn_name == "double").unwrap();
+///
+/// assert!(entry.is_script);
+/// assert_eq!(entry.calls, 10);
+/// # }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Profiler(Shared<Locked<ProfileData>>);
+
+impl Profiler {
+    /// Create a new [`Profiler`].
+    #[inline(always)]
+    pub fn new() -> Self {
+        Default::default()
+    }
+    /// Lock the profile data for writing.
+    #[inline(always)]
+    fn data(&self) -> impl DerefMut<Target = ProfileData> + '_ {
+        #[cfg(not(feature = "sync"))]
+        return self.0.borrow_mut();
+        #[cfg(feature = "sync")]
+        return self.0.write().unwrap();
+    }
+    /// Record the start of a function call.
+    pub(crate) fn enter(
+        &self,
+        fn_name: &str,
+        source: Option<&str>,
+        pos: Position,
+        is_script: bool,
+        operations: u64,
+    ) {
+        let mut data = self.data();
+        let data = data.deref_mut();
+
+        let key = (source.map(|s| s.to_string()), pos, fn_name.to_string());
+
+        let entries = &mut data.entries;
+        let entry = *data.index.entry(key).or_insert_with(|| {
+            entries.push(ProfileEntry {
+                fn_name: fn_name.into(),
+                source: source.map(|s| s.into()),
+                pos,
+                is_script,
+                calls: 0,
+                inclusive_time: Default::default(),
+                exclusive_time: Default::default(),
+                inclusive_operations: 0,
+                exclusive_operations: 0,
+            });
+            entries.len() - 1
+        });
+
+        data.entries[entry].calls += 1;
+
+        data.stack.push(Frame {
+            entry,
+            start_time: Instant::now(),
+            start_operations: operations,
+            child_time: Default::default(),
+            child_operations: 0,
+        });
+    }
+    /// Record the end of the current function call.
+    pub(crate) fn exit(&self, operations: u64) {
+        let mut data = self.data();
+        let data = data.deref_mut();
+
+        let frame = match data.stack.pop() {
+            Some(frame) => frame,
+            None => return,
+        };
+
+        let time = frame.start_time.elapsed();
+        let ops = operations.saturating_sub(frame.start_operations);
+        let exclusive_time = time.checked_sub(frame.child_time).unwrap_or_default();
+        let exclusive_ops = ops.saturating_sub(frame.child_operations);
+
+        let entry = &mut data.entries[frame.entry];
+        entry.exclusive_time += exclusive_time;
+        entry.exclusive_operations += exclusive_ops;
+
+        // Recursive calls are already counted in the outer call
+        if !data.stack.iter().any(|f| f.entry == frame.entry) {
+            entry.inclusive_time += time;
+            entry.inclusive_operations += ops;
+        }
+
+        let path = data
+            .stack
+            .iter()
+            .map(|f| f.entry)
+            .chain(crate::stdlib::iter::once(frame.entry))
+            .collect();
+        *data.stacks.entry(path).or_default() += exclusive_time;
+
+        if let Some(parent) = data.stack.last_mut() {
+            parent.child_time += time;
+            parent.child_operations += ops;
+        }
+    }
+    /// Get the profile entries of all functions called, in descending order of exclusive time.
+    pub fn entries(&self) -> Vec<ProfileEntry> {
+        let mut entries = self.data().entries.clone();
+        entries.sort_by_key(|e| crate::stdlib::cmp::Reverse(e.exclusive_time));
+        entries
+    }
+    /// Clear all profile data.
+    #[inline(always)]
+    pub fn clear(&self) {
+        *self.data() = Default::default();
+    }
+    /// Generate the call stacks recorded in collapsed-stack format, as used by flamegraph tools.
+    ///
+    /// Each line contains the functions in a call stack, separated by `;`, followed by the
+    /// exclusive time (in microseconds) spent in that call stack.
+    pub fn collapsed_stacks(&self) -> String {
+        let data = self.data();
+        let mut output = String::new();
+
+        for (path, time) in data.stacks.iter() {
+            let frames: Vec<_> = path
+                .iter()
+                .map(|&i| data.entries[i].to_string().replace(';', ","))
+                .collect();
+
+            writeln!(output, "{} {}", frames.join(";"), time.as_micros()).unwrap();
+        }
+
+        output
+    }
+    /// Generate a summary table of all functions called, in descending order of exclusive time.
+    pub fn summary(&self) -> String {
+        let entries = self.entries();
+
+        let names: Vec<_> = entries.iter().map(|e| e.to_string()).collect();
+        let width = names.iter().map(|s| s.len()).max().unwrap_or(0).max(8);
+
+        let mut output = String::new();
+
+        writeln!(
+            output,
+            "{:<w$} {:>8} {:>12} {:>12} {:>12} {:>12}",
+            "Function",
+            "Calls",
+            "Incl. (ms)",
+            "Excl. (ms)",
+            "Incl. ops",
+            "Excl. ops",
+            w = width
+        )
+        .unwrap();
+
+        for (entry, name) in entries.iter().zip(names.iter()) {
+            writeln!(
+                output,
+                "{:<w$} {:>8} {:>12.3} {:>12.3} {:>12} {:>12}",
+                name,
+                entry.calls,
+                entry.inclusive_time.as_secs_f64() * 1000.0,
+                entry.exclusive_time.as_secs_f64() * 1000.0,
+                entry.inclusive_operations,
+                entry.exclusive_operations,
+                w = width
+            )
+            .unwrap();
+        }
+
+        output
+    }
+}
@@ -3,9 +3,11 @@
 use crate::stdlib::{
     boxed::Box,
     error::Error,
-    fmt,
+    fmt, format,
     string::{String, ToString},
+    vec::Vec,
 };
+use crate::token::{Span, Token};
 use crate::{Dynamic, ImmutableString, ParseErrorType, Position, INT};
 
 /// Evaluation result.
@@ -36,8 +38,20 @@ pub enum EvalAltResult {
     /// An error has occurred inside a called function.
     /// Wrapped values are the function name, function source, and the interior error.
     ErrorInFunctionCall(String, String, Box<EvalAltResult>, Position),
-    /// Usage of an unknown [module][crate::Module]. Wrapped value is the [module][crate::Module] name.
-    ErrorModuleNotFound(String, Position),
+    /// Usage of an unknown [module][crate::Module].
+    /// Wrapped values are the [module][crate::Module] name and the locations tried (if any).
+    ErrorModuleNotFound(String, Vec<String>, Position),
+    /// A [module][crate::Module] imports itself, directly or indirectly, while being loaded.
+    /// Wrapped value is the chain of [modules][crate::Module] being loaded, starting and ending
+    /// with the same [module][crate::Module].
+    ErrorCyclicImport(Vec<String>, Position),
+    /// Loading a [module][crate::Module] violates the restrictions of a
+    /// [`RestrictedModuleResolver`][crate::module_resolvers::RestrictedModuleResolver].
+    /// Wrapped values are the [module][crate::Module] name and a description of the violation.
+    ErrorModuleRestricted(String, String, Position),
+    /// An `import` statement has a path that is not a string literal, when computed import paths
+    /// are disabled via [`Engine::enable_computed_imports`][crate::Engine::enable_computed_imports].
+    ErrorComputedImportPath(Position),
     /// An error has occurred while loading a [module][crate::Module].
     /// Wrapped value are the [module][crate::Module] name and the interior error.
     ErrorInModule(String, Box<EvalAltResult>, Position),
@@ -80,6 +94,10 @@ pub enum EvalAltResult {
     ErrorTerminated(Dynamic, Position),
     /// Run-time error encountered. Wrapped value is the error token.
     ErrorRuntime(Dynamic, Position),
+    /// An assertion failed.
+    /// Wrapped values are the failure message, the operands rendered via `to_debug` (if any),
+    /// and the source text of the assertion (if available).
+    ErrorAssertion(String, Option<Box<(String, String)>>, String, Position),
 
     /// Breaking out of loops - not an error if within a loop.
     /// The wrapped value, if true, means breaking clean out of the loop (i.e. a `break` statement).
@@ -116,7 +134,9 @@ impl EvalAltResult {
             Self::ErrorStringBounds(_, _, _) => "String index out of bounds",
             Self::ErrorFor(_) => "For loop expects an array, object map, or range",
             Self::ErrorVariableNotFound(_, _) => "Variable not found",
-            Self::ErrorModuleNotFound(_, _) => "Module not found",
+            Self::ErrorModuleNotFound(_, _, _) => "Module not found",
+            Self::ErrorCyclicImport(_, _) => "Cyclic import",
+            Self::ErrorModuleRestricted(_, _, _) => "Module restriction violated",
             Self::ErrorDataRace(_, _) => "Data race detected when accessing variable",
             Self::ErrorAssignmentToConstant(_, _) => "Cannot modify a constant",
             Self::ErrorMismatchOutputType(_, _, _) => "Output type is incorrect",
@@ -124,10 +144,12 @@ impl EvalAltResult {
             Self::ErrorArithmetic(_, _) => "Arithmetic error",
             Self::ErrorTooManyOperations(_) => "Too many operations",
             Self::ErrorTooManyModules(_) => "Too many modules imported",
+            Self::ErrorComputedImportPath(_) => "Import path must be a string literal",
             Self::ErrorStackOverflow(_) => "Stack overflow",
             Self::ErrorDataTooLarge(_, _) => "Data size exceeds maximum limit",
             Self::ErrorTerminated(_,_) => "Script terminated.",
             Self::ErrorRuntime(_, _) => "Runtime error",
+            Self::ErrorAssertion(_, _, _, _) => "Assertion failed",
             Self::LoopBreak(true, _) => "Break statement not inside a loop",
             Self::LoopBreak(false, _) => "Continue statement not inside a loop",
             Self::Return(_, _) => "[Not Error] Function returns value",
@@ -143,20 +165,35 @@ impl fmt::Display for EvalAltResult {
         let pos = self.position();
 
         match self {
-            Self::ErrorSystem(s, _) if s.is_empty() => f.write_str(desc)?,
-            Self::ErrorSystem(s, _) => write!(f, "{}: {}", s, desc)?,
+            Self::ErrorSystem(s, err) if s.is_empty() => write!(f, "{}", err)?,
+            Self::ErrorSystem(s, err) => write!(f, "{}: {}", s, err)?,
 
             Self::ErrorParsing(p, _) => write!(f, "Syntax error: {}", p)?,
 
-            #[cfg(not(feature = "no_function"))]
-            Self::ErrorInFunctionCall(s, src, err, _) if crate::engine::is_anonymous_fn(s) => {
-                write!(f, "{} in call to closure", err)?;
-                if !src.is_empty() {
-                    write!(f, " @ '{}'", src)?;
-                }
-            }
             Self::ErrorInFunctionCall(s, src, err, _) => {
-                write!(f, "{} in call to function {}", err, s)?;
+                // Flatten nested function calls into a single chain: `inner < ... < outer`
+                let mut fn_name = s.to_string();
+                let mut err = err;
+
+                while let Self::ErrorInFunctionCall(s, src, inner, _) = err.as_ref() {
+                    fn_name = if src.is_empty() {
+                        format!("{} < {}", s, fn_name)
+                    } else {
+                        format!("{} @ '{}' < {}", s, src, fn_name)
+                    };
+                    err = inner;
+                }
+
+                #[cfg(not(feature = "no_function"))]
+                let is_closure = crate::engine::is_anonymous_fn(&fn_name);
+                #[cfg(feature = "no_function")]
+                let is_closure = false;
+
+                if is_closure {
+                    write!(f, "{} in call to closure", err)?;
+                } else {
+                    write!(f, "{} in call to function {}", err, fn_name)?;
+                }
                 if !src.is_empty() {
                     write!(f, " @ '{}'", src)?;
                 }
@@ -171,7 +208,16 @@ impl fmt::Display for EvalAltResult {
             | Self::ErrorVariableNotFound(s, _)
             | Self::ErrorDataRace(s, _) => write!(f, "{}: {}", desc, s)?,
 
-            Self::ErrorModuleNotFound(s, _) => write!(f, "{}: '{}'", desc, s)?,
+            Self::ErrorModuleNotFound(s, tried, _) if tried.is_empty() => {
+                write!(f, "{}: '{}'", desc, s)?
+            }
+            Self::ErrorModuleNotFound(s, tried, _) => {
+                write!(f, "{}: '{}' (tried: {})", desc, s, tried.join(", "))?
+            }
+            Self::ErrorCyclicImport(cycle, _) => write!(f, "{}: {}", desc, cycle.join(" -> "))?,
+            Self::ErrorModuleRestricted(s, reason, _) => {
+                write!(f, "{}: '{}' ({})", desc, s, reason)?
+            }
 
             Self::ErrorDotExpr(s, _) if !s.is_empty() => write!(f, "{}", s)?,
 
@@ -182,6 +228,7 @@ impl fmt::Display for EvalAltResult {
             | Self::ErrorDotExpr(_, _)
             | Self::ErrorTooManyOperations(_)
             | Self::ErrorTooManyModules(_)
+            | Self::ErrorComputedImportPath(_)
             | Self::ErrorStackOverflow(_)
             | Self::ErrorTerminated(_, _) => f.write_str(desc)?,
 
@@ -192,6 +239,21 @@ impl fmt::Display for EvalAltResult {
             Self::ErrorRuntime(d, _) if d.is::<()>() => f.write_str(desc)?,
             Self::ErrorRuntime(d, _) => write!(f, "{}: {}", desc, d)?,
 
+            Self::ErrorAssertion(message, operands, expr, _) => {
+                f.write_str(desc)?;
+
+                if !expr.is_empty() {
+                    write!(f, ": `{}`", expr)?;
+                }
+                if !message.is_empty() {
+                    write!(f, ": {}", message)?;
+                }
+                if let Some(operands) = operands {
+                    let (left, right) = operands.as_ref();
+                    write!(f, ": left = {}, right = {}", left, right)?;
+                }
+            }
+
             Self::ErrorAssignmentToConstant(s, _) => write!(f, "Cannot modify constant {}", s)?,
             Self::ErrorMismatchOutputType(s, r, _) => {
                 write!(f, "Output type is incorrect: {} (expecting {})", r, s)?
@@ -297,16 +359,20 @@ impl EvalAltResult {
             | Self::ErrorIndexingType(_, _)
             | Self::ErrorFor(_)
             | Self::ErrorVariableNotFound(_, _)
-            | Self::ErrorModuleNotFound(_, _)
+            | Self::ErrorModuleNotFound(_, _, _)
+            | Self::ErrorCyclicImport(_, _)
             | Self::ErrorDataRace(_, _)
             | Self::ErrorAssignmentToConstant(_, _)
             | Self::ErrorMismatchOutputType(_, _, _)
             | Self::ErrorDotExpr(_, _)
             | Self::ErrorArithmetic(_, _)
-            | Self::ErrorRuntime(_, _) => true,
+            | Self::ErrorRuntime(_, _)
+            | Self::ErrorAssertion(_, _, _, _) => true,
 
             Self::ErrorTooManyOperations(_)
             | Self::ErrorTooManyModules(_)
+            | Self::ErrorModuleRestricted(_, _, _)
+            | Self::ErrorComputedImportPath(_)
             | Self::ErrorStackOverflow(_)
             | Self::ErrorDataTooLarge(_, _)
             | Self::ErrorTerminated(_, _) => false,
@@ -327,6 +393,8 @@ impl EvalAltResult {
 
             Self::ErrorTooManyOperations(_)
             | Self::ErrorTooManyModules(_)
+            | Self::ErrorModuleRestricted(_, _, _)
+            | Self::ErrorComputedImportPath(_)
             | Self::ErrorStackOverflow(_)
             | Self::ErrorDataTooLarge(_, _) => true,
 
@@ -338,6 +406,96 @@ impl EvalAltResult {
             _ => false,
         }
     }
+    /// Get the call stack recorded while this error propagated out of function calls,
+    /// most recent call first.
+    ///
+    /// The stack is empty if the error did not occur inside a function call.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[cfg(not(feature = "no_function"))]
+    /// # {
+    /// use rhai::Engine;
+    ///
+    /// let engine = Engine::new();
+    ///
+    /// let err = engine.eval::<()>(r#"
+    ///     fn foo(x) { throw x; }
+    ///     fn bar() { foo(42) }
+    ///     bar()
+    /// "#).expect_err("should error");
+    ///
+    /// let stack = err.backtrace();
+    ///
+    /// assert_eq!(stack.len(), 2);
+    /// assert_eq!(stack[0].fn_name, "foo");
+    /// assert_eq!(stack[0].pos.line(), Some(3));
+    /// assert_eq!(stack[1].fn_name, "bar");
+    /// assert_eq!(stack[1].pos.line(), Some(4));
+    /// # }
+    /// ```
+    pub fn backtrace(&self) -> Vec<CallFrame> {
+        let mut stack = Vec::new();
+        let mut err = self;
+
+        while let Self::ErrorInFunctionCall(name, src, inner, pos) = err {
+            let (namespace, fn_name) = match name.rfind(Token::DoubleColon.syntax().as_ref()) {
+                Some(index) => (
+                    Some(name[..index].to_string()),
+                    name[index + 2..].to_string(),
+                ),
+                None => (None, name.to_string()),
+            };
+
+            stack.push(CallFrame {
+                fn_name,
+                namespace,
+                source: if src.is_empty() {
+                    None
+                } else {
+                    Some(src.to_string())
+                },
+                pos: *pos,
+            });
+
+            err = inner;
+        }
+
+        stack.reverse();
+        stack
+    }
+    /// Get the innermost error, i.e. the error that originally occurred, skipping all the
+    /// [`ErrorInFunctionCall`][EvalAltResult::ErrorInFunctionCall] wrappers added by function
+    /// calls that it propagated out of.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[cfg(not(feature = "no_function"))]
+    /// # {
+    /// use rhai::{Engine, EvalAltResult};
+    ///
+    /// let engine = Engine::new();
+    ///
+    /// let err = engine.eval::<()>(r#"
+    ///     fn foo(x) { throw x; }
+    ///     fn bar() { foo(42) }
+    ///     bar()
+    /// "#).expect_err("should error");
+    ///
+    /// assert!(matches!(err.unwrap_inner(), EvalAltResult::ErrorRuntime(..)));
+    /// # }
+    /// ```
+    pub fn unwrap_inner(&self) -> &Self {
+        let mut err = self;
+
+        while let Self::ErrorInFunctionCall(_, _, inner, _) = err {
+            err = inner;
+        }
+
+        err
+    }
     /// Get the [position][Position] of this error.
     #[cfg(not(feature = "no_object"))]
     pub(crate) fn dump_fields(&self, map: &mut crate::Map) {
@@ -360,6 +518,7 @@ impl EvalAltResult {
             | Self::ErrorArithmetic(_, _)
             | Self::ErrorTooManyOperations(_)
             | Self::ErrorTooManyModules(_)
+            | Self::ErrorComputedImportPath(_)
             | Self::ErrorStackOverflow(_)
             | Self::ErrorRuntime(_, _) => (),
 
@@ -369,6 +528,20 @@ impl EvalAltResult {
             Self::ErrorInFunctionCall(f, s, _, _) => {
                 map.insert("function".into(), f.into());
                 map.insert("source".into(), s.into());
+
+                #[cfg(not(feature = "no_index"))]
+                map.insert(
+                    "stack".into(),
+                    self.backtrace()
+                        .into_iter()
+                        .map(|frame| {
+                            let mut frame_map: crate::Map = Default::default();
+                            frame.dump_fields(&mut frame_map);
+                            frame_map.into()
+                        })
+                        .collect::<crate::Array>()
+                        .into(),
+                );
             }
             Self::ErrorInModule(m, _, _) => {
                 map.insert("module".into(), m.into());
@@ -389,9 +562,26 @@ impl EvalAltResult {
             | Self::ErrorAssignmentToConstant(v, _) => {
                 map.insert("variable".into(), v.into());
             }
-            Self::ErrorModuleNotFound(m, _) => {
+            Self::ErrorModuleNotFound(m, _, _) => {
                 map.insert("module".into(), m.into());
             }
+            Self::ErrorModuleRestricted(m, reason, _) => {
+                map.insert("module".into(), m.into());
+                map.insert("reason".into(), reason.into());
+            }
+            Self::ErrorCyclicImport(cycle, _) => {
+                #[cfg(not(feature = "no_index"))]
+                map.insert(
+                    "cycle".into(),
+                    cycle
+                        .iter()
+                        .map(|m| m.into())
+                        .collect::<crate::Array>()
+                        .into(),
+                );
+                #[cfg(feature = "no_index")]
+                map.insert("cycle".into(), cycle.join(" -> ").into());
+            }
             Self::ErrorDotExpr(p, _) => {
                 map.insert("property".into(), p.into());
             }
@@ -402,6 +592,16 @@ impl EvalAltResult {
             Self::ErrorTerminated(t, _) => {
                 map.insert("token".into(), t.clone());
             }
+            Self::ErrorAssertion(_, operands, expr, _) => {
+                if let Some(operands) = operands {
+                    let (left, right) = operands.as_ref();
+                    map.insert("left".into(), left.into());
+                    map.insert("right".into(), right.into());
+                }
+                if !expr.is_empty() {
+                    map.insert("expression".into(), expr.into());
+                }
+            }
         };
     }
     /// Get the [position][Position] of this error.
@@ -420,7 +620,9 @@ impl EvalAltResult {
             | Self::ErrorIndexingType(_, pos)
             | Self::ErrorFor(pos)
             | Self::ErrorVariableNotFound(_, pos)
-            | Self::ErrorModuleNotFound(_, pos)
+            | Self::ErrorModuleNotFound(_, _, pos)
+            | Self::ErrorCyclicImport(_, pos)
+            | Self::ErrorModuleRestricted(_, _, pos)
             | Self::ErrorDataRace(_, pos)
             | Self::ErrorAssignmentToConstant(_, pos)
             | Self::ErrorMismatchOutputType(_, _, pos)
@@ -428,14 +630,24 @@ impl EvalAltResult {
             | Self::ErrorArithmetic(_, pos)
             | Self::ErrorTooManyOperations(pos)
             | Self::ErrorTooManyModules(pos)
+            | Self::ErrorComputedImportPath(pos)
             | Self::ErrorStackOverflow(pos)
             | Self::ErrorDataTooLarge(_, pos)
             | Self::ErrorTerminated(_, pos)
             | Self::ErrorRuntime(_, pos)
+            | Self::ErrorAssertion(_, _, _, pos)
             | Self::LoopBreak(_, pos)
             | Self::Return(_, pos) => *pos,
         }
     }
+    /// Get the [`Span`] of the expression where this error occurs, as carried by its
+    /// [position][Position].
+    ///
+    /// The [`Span`] is [`NONE`][Span::NONE] if this error has no [position][Position].
+    #[inline(always)]
+    pub fn span(&self) -> Span {
+        self.position().span()
+    }
     /// Remove the [position][Position] information from this error and return it.
     ///
     /// The [position][Position] of this error is set to [`NONE`][Position::NONE] afterwards.
@@ -460,7 +672,9 @@ impl EvalAltResult {
             | Self::ErrorIndexingType(_, pos)
             | Self::ErrorFor(pos)
             | Self::ErrorVariableNotFound(_, pos)
-            | Self::ErrorModuleNotFound(_, pos)
+            | Self::ErrorModuleNotFound(_, _, pos)
+            | Self::ErrorCyclicImport(_, pos)
+            | Self::ErrorModuleRestricted(_, _, pos)
             | Self::ErrorDataRace(_, pos)
             | Self::ErrorAssignmentToConstant(_, pos)
             | Self::ErrorMismatchOutputType(_, _, pos)
@@ -468,10 +682,12 @@ impl EvalAltResult {
             | Self::ErrorArithmetic(_, pos)
             | Self::ErrorTooManyOperations(pos)
             | Self::ErrorTooManyModules(pos)
+            | Self::ErrorComputedImportPath(pos)
             | Self::ErrorStackOverflow(pos)
             | Self::ErrorDataTooLarge(_, pos)
             | Self::ErrorTerminated(_, pos)
             | Self::ErrorRuntime(_, pos)
+            | Self::ErrorAssertion(_, _, _, pos)
             | Self::LoopBreak(_, pos)
             | Self::Return(_, pos) => *pos = new_position,
         }
@@ -487,6 +703,65 @@ impl EvalAltResult {
     }
 }
 
+/// A frame in the call stack of an [`EvalAltResult`], as returned by [`EvalAltResult::backtrace`].
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct CallFrame {
+    /// Name of the function called.
+    pub fn_name: String,
+    /// Namespace of the function if the call is qualified, e.g. `foo::bar` for `foo::bar::baz(42)`.
+    pub namespace: Option<String>,
+    /// Source of the function (e.g. the path of the [module][crate::Module] defining it), if any.
+    pub source: Option<String>,
+    /// [Position][Position] of the call site.
+    pub pos: Position,
+}
+
+impl fmt::Display for CallFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(ref namespace) = self.namespace {
+            write!(f, "{}{}", namespace, Token::DoubleColon.syntax())?;
+        }
+        f.write_str(&self.fn_name)?;
+
+        if let Some(ref source) = self.source {
+            write!(f, " @ '{}'", source)?;
+        }
+        if !self.pos.is_none() {
+            write!(f, " ({})", self.pos)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl CallFrame {
+    /// Dump the fields of this call frame into an object map.
+    #[cfg(not(feature = "no_object"))]
+    #[cfg(not(feature = "no_index"))]
+    fn dump_fields(&self, map: &mut crate::Map) {
+        map.insert("function".into(), self.fn_name.as_str().into());
+
+        if let Some(ref namespace) = self.namespace {
+            map.insert("namespace".into(), namespace.as_str().into());
+        }
+        if let Some(ref source) = self.source {
+            map.insert("source".into(), source.as_str().into());
+        }
+        if !self.pos.is_none() {
+            map.insert("line".into(), (self.pos.line().unwrap() as INT).into());
+            map.insert(
+                "position".into(),
+                if self.pos.is_beginning_of_line() {
+                    0
+                } else {
+                    self.pos.position().unwrap() as INT
+                }
+                .into(),
+            );
+        }
+    }
+}
+
 impl<T> From<EvalAltResult> for Result<T, Box<EvalAltResult>> {
     #[inline(always)]
     fn from(err: EvalAltResult) -> Self {
@@ -9,14 +9,14 @@ use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-enum FnType {
+pub(crate) enum FnType {
     Script,
     Native,
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-enum FnNamespace {
+pub(crate) enum FnNamespace {
     Global,
     Internal,
 }
@@ -32,7 +32,7 @@ impl From<crate::FnNamespace> for FnNamespace {
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-enum FnAccess {
+pub(crate) enum FnAccess {
     Public,
     Private,
 }
@@ -48,7 +48,7 @@ impl From<crate::FnAccess> for FnAccess {
 
 #[derive(Debug, Clone, Eq, PartialEq, Ord, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct FnParam {
+pub(crate) struct FnParam {
     pub name: String,
     #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
     pub typ: Option<String>,
@@ -76,7 +76,7 @@ impl PartialOrd for FnParam {
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct FnMetadata {
+pub(crate) struct FnMetadata {
     pub namespace: FnNamespace,
     pub access: FnAccess,
     pub name: String,
@@ -186,7 +186,7 @@ impl From<crate::ast::ScriptFnMetadata<'_>> for FnMetadata {
 
 #[derive(Debug, Clone, Default, Serialize)]
 #[serde(rename_all = "camelCase")]
-struct ModuleMetadata {
+pub(crate) struct ModuleMetadata {
     #[serde(skip_serializing_if = "BTreeMap::is_empty")]
     pub modules: BTreeMap<String, Self>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
@@ -8,7 +8,7 @@ mod serialize;
 mod str;
 
 #[cfg(feature = "metadata")]
-mod metadata;
+pub(crate) mod metadata;
 
 pub use de::from_dynamic;
 pub use ser::to_dynamic;
@@ -0,0 +1,167 @@
+//! Module containing a renderer for annotated snippets of script text.
+
+use crate::stdlib::{
+    cmp::max,
+    fmt::Write,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use crate::token::Span;
+use crate::{EvalAltResult, ParseError};
+
+/// Renders annotated snippets of a script, with carets under the [`Span`] of an error.
+///
+/// # Example
+///
+/// ```
+/// use rhai::{Engine, Snippet};
+///
+/// let engine = Engine::new();
+///
+/// let script = "let x = 40 + 2;\nfoo(x);";
+///
+/// let err = engine.eval::<()>(script).expect_err("should error");
+///
+/// let mut snippet = Snippet::new(script);
+/// snippet.set_source("test.rhai").set_context_lines(1);
+///
+/// assert_eq!(
+///     snippet.render_error(&err),
+/// "Function not found: foo (i64)
+///  --> test.rhai (line 2, position 1)
+///   |
+/// 1 | let x = 40 + 2;
+/// 2 | foo(x);
+///   | ^^^^^^"
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct Snippet<'a> {
+    /// Text of the script.
+    script: &'a str,
+    /// Source of the script (e.g. its file path), if any.
+    source: Option<&'a str>,
+    /// Number of lines to show before the annotated lines.
+    context_lines: usize,
+}
+
+impl<'a> Snippet<'a> {
+    /// Create a new [`Snippet`] renderer for a script.
+    #[inline(always)]
+    pub fn new(script: &'a str) -> Self {
+        Self {
+            script,
+            source: None,
+            context_lines: 0,
+        }
+    }
+    /// Set the source of the script (e.g. its file path), shown together with the location.
+    #[inline(always)]
+    pub fn set_source(&mut self, source: &'a str) -> &mut Self {
+        self.source = Some(source);
+        self
+    }
+    /// Set the number of lines to show before the annotated lines. The default is zero.
+    #[inline(always)]
+    pub fn set_context_lines(&mut self, lines: usize) -> &mut Self {
+        self.context_lines = lines;
+        self
+    }
+    /// Render a message annotating a [`Span`] in the script.
+    ///
+    /// Only the message is rendered if the [`Span`] is [`NONE`][Span::NONE] or not within the script.
+    pub fn render(&self, span: Span, message: &str) -> String {
+        let mut output = message.to_string();
+
+        if span.is_none() {
+            return output;
+        }
+
+        match self.source {
+            Some(source) => write!(output, "\n --> {} ({})", source, span.start()),
+            None => write!(output, "\n --> {}", span.start()),
+        }
+        .unwrap();
+
+        let lines: Vec<_> = self
+            .script
+            .split('\n')
+            .map(|line| line.trim_end_matches('\r'))
+            .collect();
+
+        let (start, end) = (span.start(), span.end());
+        let (first_line, last_line) = (start.line().unwrap(), end.line().unwrap());
+
+        if last_line > lines.len() {
+            return output;
+        }
+
+        let gutter = last_line.to_string().len();
+        let from_line = max(first_line.saturating_sub(self.context_lines), 1);
+
+        write!(output, "\n{:>1$} |", "", gutter).unwrap();
+
+        for line_no in from_line..=last_line {
+            let line = lines[line_no - 1];
+
+            write!(output, "\n{:>2$} | {}", line_no, line, gutter).unwrap();
+
+            if line_no < first_line {
+                continue;
+            }
+
+            // Carets cover the span on this line
+            let from = if line_no == first_line {
+                start.position().unwrap_or(1)
+            } else {
+                1
+            };
+            let to = if line_no == last_line {
+                end.position().unwrap_or(1)
+            } else {
+                max(line.chars().count(), from)
+            };
+
+            // Keep tabs so that the carets line up with the text
+            let indent: String = line
+                .chars()
+                .take(from - 1)
+                .map(|ch| if ch == '\t' { '\t' } else { ' ' })
+                .collect();
+
+            write!(
+                output,
+                "\n{:>3$} | {}{:^<4$}",
+                "",
+                indent,
+                "",
+                gutter,
+                to + 1 - from
+            )
+            .unwrap();
+        }
+
+        output
+    }
+    /// Render an error, annotating the [`Span`] of the expression where it occurs in the script.
+    pub fn render_error(&self, err: &EvalAltResult) -> String {
+        self.render(err.span(), strip_position(&err.to_string(), err.position()))
+    }
+    /// Render a [`ParseError`], annotating the [`Span`] of the expression or token where it
+    /// occurs in the script.
+    pub fn render_parse_error(&self, err: &ParseError) -> String {
+        self.render(err.span(), strip_position(&err.to_string(), err.1))
+    }
+}
+
+/// Remove the [position][crate::Position] from an error message since it is shown in the snippet.
+fn strip_position(message: &str, pos: crate::Position) -> &str {
+    if pos.is_none() {
+        message
+    } else {
+        message
+            .strip_suffix(&format!(" ({})", pos))
+            .unwrap_or(message)
+    }
+}
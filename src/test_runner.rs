@@ -0,0 +1,447 @@
+//! Module implementing a runner for tests written in Rhai script.
+
+use crate::fn_native::{shared_take, OnProgressCallback, Shared};
+use crate::stdlib::{
+    boxed::Box,
+    collections::BTreeMap,
+    fmt,
+    fmt::Write,
+    string::{String, ToString},
+    time::Duration,
+    vec::Vec,
+};
+use crate::{Dynamic, Engine, EvalAltResult, Scope, AST};
+
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+use crate::stdlib::time::Instant;
+
+#[cfg(any(target_arch = "wasm32", target_arch = "wasm64"))]
+use instant::Instant;
+
+/// Prefix of the names of test functions.
+const TEST_FN_PREFIX: &str = "test_";
+
+/// Doc-comment annotation marking a test function that is expected to fail.
+const SHOULD_FAIL_ANNOTATION: &str = "#[should_fail]";
+/// Outcome of running a test function.
+#[derive(Debug)]
+pub enum TestOutcome {
+    /// The test passed.
+    Passed,
+    /// The test failed with an error.
+    Failed(Box<EvalAltResult>),
+    /// The test is expected to fail (via `#[should_fail]`) but completed without error.
+    NotFailed,
+    /// The test exceeded the time limit.
+    TimedOut,
+}
+
+impl TestOutcome {
+    /// Did the test succeed?
+    #[inline(always)]
+    pub fn is_success(&self) -> bool {
+        matches!(self, Self::Passed)
+    }
+}
+
+impl fmt::Display for TestOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Passed => f.write_str("ok"),
+            Self::Failed(_) | Self::NotFailed | Self::TimedOut => f.write_str("FAILED"),
+        }
+    }
+}
+
+/// Result of running a test function.
+#[derive(Debug)]
+pub struct TestResult {
+    /// Name of the test function.
+    pub name: String,
+    /// Source of the script defining the test function (e.g. its file path), if any.
+    pub source: Option<String>,
+    /// Is the test expected to fail?
+    pub should_fail: bool,
+    /// Outcome of the test.
+    pub outcome: TestOutcome,
+    /// Time taken to run the test.
+    pub duration: Duration,
+}
+
+/// Results of running a number of test functions.
+#[derive(Debug, Default)]
+pub struct TestReport {
+    /// Results of all tests run, in order.
+    pub results: Vec<TestResult>,
+}
+
+impl TestReport {
+    /// Number of tests that succeeded.
+    #[inline(always)]
+    pub fn passed(&self) -> usize {
+        self.results
+            .iter()
+            .filter(|r| r.outcome.is_success())
+            .count()
+    }
+    /// Number of tests that did not succeed.
+    #[inline(always)]
+    pub fn failed(&self) -> usize {
+        self.results.len() - self.passed()
+    }
+    /// Did all tests succeed?
+    #[inline(always)]
+    pub fn is_success(&self) -> bool {
+        self.results.iter().all(|r| r.outcome.is_success())
+    }
+    /// Generate the results in JUnit XML format, with one test suite per source.
+    pub fn to_junit_xml(&self) -> String {
+        let mut suites: BTreeMap<&str, Vec<&TestResult>> = Default::default();
+
+        for result in self.results.iter() {
+            let source = result.source.as_deref().unwrap_or("");
+            suites.entry(source).or_default().push(result);
+        }
+
+        let total_time: Duration = self.results.iter().map(|r| r.duration).sum();
+
+        let mut output = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+
+        writeln!(
+            output,
+            "<testsuites tests=\"{}\" failures=\"{}\" time=\"{:.3}\">",
+            self.results.len(),
+            self.failed(),
+            total_time.as_secs_f64()
+        )
+        .unwrap();
+
+        for (source, results) in suites {
+            let failures = results.iter().filter(|r| !r.outcome.is_success()).count();
+            let time: Duration = results.iter().map(|r| r.duration).sum();
+
+            writeln!(
+                output,
+                "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" time=\"{:.3}\">",
+                xml_escape(source),
+                results.len(),
+                failures,
+                time.as_secs_f64()
+            )
+            .unwrap();
+
+            for result in results {
+                write!(
+                    output,
+                    "    <testcase name=\"{}\" classname=\"{}\" time=\"{:.3}\"",
+                    xml_escape(&result.name),
+                    xml_escape(source),
+                    result.duration.as_secs_f64()
+                )
+                .unwrap();
+
+                let (message, details) = match result.outcome {
+                    TestOutcome::Passed => {
+                        output.push_str("/>\n");
+                        continue;
+                    }
+                    TestOutcome::Failed(ref err) => {
+                        let mut details = err.to_string();
+
+                        for frame in err.backtrace() {
+                            write!(details, "\n    {}", frame).unwrap();
+                        }
+
+                        (err.to_string(), details)
+                    }
+                    TestOutcome::NotFailed => {
+                        let message = "Test is expected to fail but did not".to_string();
+                        (message.clone(), message)
+                    }
+                    TestOutcome::TimedOut => {
+                        let message = "Test timed out".to_string();
+                        (message.clone(), message)
+                    }
+                };
+
+                writeln!(
+                    output,
+                    ">\n      <failure message=\"{}\">{}</failure>\n    </testcase>",
+                    xml_escape(&message),
+                    xml_escape(&details)
+                )
+                .unwrap();
+            }
+
+            output.push_str("  </testsuite>\n");
+        }
+
+        output.push_str("</testsuites>\n");
+        output
+    }
+}
+
+/// A runner for tests written in Rhai script.
+///
+/// Tests are script functions taking no parameters whose names start with `test_`.
+///
+/// The top-level statements in the script are run once before the tests, and each test is then
+/// run in a copy of the resulting [`Scope`], in the order they are defined in the script.
+/// If the top-level statements fail, no tests are run and the report contains a single failed
+/// result named [`SETUP_TEST_NAME`][TestRunner::SETUP_TEST_NAME].
+///
+/// A test is expected to fail if its doc-comments contain `#[should_fail]`.
+///
+/// Each test is subject to a limit on the number of operations and a time limit.
+/// The same limits apply to the top-level statements.
+/// The time limit is enforced via the [`Engine`]'s progress callback, which wraps any progress
+/// callback already registered while tests are run.
+///
+/// Not available under `no_function` or `no_std`.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+/// use rhai::{Engine, TestRunner};
+///
+/// let mut engine = Engine::new();
+///
+/// let ast = engine.compile(r#"
+///     fn test_add() {
+///         if 40 + 2 != 42 { throw "wrong answer"; }
+///     }
+///
+///     /// #[should_fail]
+///     fn test_not_found() {
+///         throw "not found";
+///     }
+/// "#)?;
+///
+/// let report = TestRunner::new(&mut engine).run(&ast);
+///
+/// assert_eq!(report.results.len(), 2);
+/// assert!(report.is_success());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct TestRunner<'e> {
+    /// The [`Engine`] running the tests.
+    engine: &'e mut Engine,
+    /// Only run tests with names containing this text, if any.
+    filter: Option<String>,
+    /// Maximum number of operations allowed for each test (zero for unlimited).
+    #[cfg(not(feature = "unchecked"))]
+    max_operations: u64,
+    /// Maximum time allowed for each test, if any.
+    time_limit: Option<Duration>,
+}
+
+impl<'e> TestRunner<'e> {
+    /// Default maximum number of operations allowed for each test.
+    #[cfg(not(feature = "unchecked"))]
+    pub const DEFAULT_MAX_OPERATIONS: u64 = 10_000_000;
+    /// Default maximum time allowed for each test.
+    pub const DEFAULT_TIME_LIMIT: Duration = Duration::from_secs(10);
+    /// Name of the result reporting an error in the top-level statements of a script.
+    pub const SETUP_TEST_NAME: &'static str = "(setup)";
+
+    /// Create a new [`TestRunner`] running tests with an [`Engine`].
+    #[inline(always)]
+    pub fn new(engine: &'e mut Engine) -> Self {
+        Self {
+            engine,
+            filter: None,
+            #[cfg(not(feature = "unchecked"))]
+            max_operations: Self::DEFAULT_MAX_OPERATIONS,
+            time_limit: Some(Self::DEFAULT_TIME_LIMIT),
+        }
+    }
+    /// Only run tests with names containing a particular text.
+    #[inline(always)]
+    pub fn set_filter(&mut self, filter: impl Into<String>) -> &mut Self {
+        self.filter = Some(filter.into());
+        self
+    }
+    /// Set the maximum number of operations allowed for each test (zero for unlimited).
+    ///
+    /// Not available under `unchecked`.
+    #[cfg(not(feature = "unchecked"))]
+    #[inline(always)]
+    pub fn set_max_operations(&mut self, operations: u64) -> &mut Self {
+        self.max_operations = operations;
+        self
+    }
+    /// Set the maximum time allowed for each test, or [`None`] for unlimited.
+    #[inline(always)]
+    pub fn set_time_limit(&mut self, time_limit: Option<Duration>) -> &mut Self {
+        self.time_limit = time_limit;
+        self
+    }
+    /// Get the names of all tests in an [`AST`] that pass the filter, in order of definition.
+    pub fn tests<'a>(&self, ast: &'a AST) -> Vec<&'a str> {
+        let mut tests: Vec<_> = ast
+            .iter_fn_def()
+            .filter(|f| f.params.is_empty() && f.name.starts_with(TEST_FN_PREFIX))
+            .filter(|f| match self.filter {
+                Some(ref filter) => f.name.contains(filter.as_str()),
+                None => true,
+            })
+            .collect();
+
+        tests.sort_by_key(|f| f.body.pos);
+        tests.into_iter().map(|f| f.name.as_str()).collect()
+    }
+    /// Run all tests in an [`AST`] that pass the filter.
+    pub fn run(&mut self, ast: &AST) -> TestReport {
+        let tests: Vec<_> = self
+            .tests(ast)
+            .into_iter()
+            .map(|name| {
+                let should_fail = ast
+                    .iter_functions()
+                    .find(|f| f.name == name && f.params.is_empty())
+                    .is_some_and(|f| {
+                        f.comments
+                            .iter()
+                            .any(|c| c.contains(SHOULD_FAIL_ANNOTATION))
+                    });
+                (name, should_fail)
+            })
+            .collect();
+
+        // Replace limits for the tests
+        #[cfg(not(feature = "unchecked"))]
+        let orig_max_operations = self.engine.max_operations();
+        #[cfg(not(feature = "unchecked"))]
+        self.engine.set_max_operations(self.max_operations);
+        let orig_progress: Option<Shared<OnProgressCallback>> =
+            self.engine.progress.take().map(Into::into);
+
+        // Run the top-level statements once
+        let mut scope = Scope::new();
+        let start = Instant::now();
+        self.set_progress(&orig_progress, start);
+
+        let results = match self.engine.consume_ast_with_scope(&mut scope, ast) {
+            Ok(_) => tests
+                .into_iter()
+                .map(|(name, should_fail)| {
+                    self.run_test(&scope, ast, name, should_fail, &orig_progress)
+                })
+                .collect(),
+            Err(err) => {
+                let duration = start.elapsed();
+                let outcome = self.outcome(Err(err), Self::SETUP_TEST_NAME, false, duration);
+
+                vec![TestResult {
+                    name: Self::SETUP_TEST_NAME.into(),
+                    source: ast.source().map(|s| s.into()),
+                    should_fail: false,
+                    outcome,
+                    duration,
+                }]
+            }
+        };
+
+        // Restore limits
+        #[cfg(not(feature = "unchecked"))]
+        self.engine.set_max_operations(orig_max_operations);
+        // Drop the wrapping callback to release the original one
+        self.engine.progress = None;
+        self.engine.progress = orig_progress.map(shared_take);
+
+        TestReport { results }
+    }
+    /// Set the [`Engine`]'s progress callback to enforce the time limit from a start time,
+    /// calling the original progress callback (if any) as well.
+    fn set_progress(&mut self, orig_progress: &Option<Shared<OnProgressCallback>>, start: Instant) {
+        let time_limit = self.time_limit;
+        let orig_progress = orig_progress.clone();
+
+        self.engine.progress = match (time_limit, orig_progress) {
+            (None, None) => None,
+            (time_limit, orig_progress) => Some(Box::new(move |operations| match time_limit {
+                Some(limit) if start.elapsed() > limit => Some(Dynamic::UNIT),
+                _ => orig_progress.as_ref().and_then(|f| f(operations)),
+            })),
+        };
+    }
+    /// Run a single test function in a copy of a [`Scope`].
+    fn run_test(
+        &mut self,
+        scope: &Scope,
+        ast: &AST,
+        name: &str,
+        should_fail: bool,
+        orig_progress: &Option<Shared<OnProgressCallback>>,
+    ) -> TestResult {
+        let start = Instant::now();
+        self.set_progress(orig_progress, start);
+
+        let result = self
+            .engine
+            .call_fn_dynamic(&mut scope.clone(), ast, false, name, None, []);
+
+        let duration = start.elapsed();
+        let outcome = self.outcome(result, name, should_fail, duration);
+
+        TestResult {
+            name: name.into(),
+            source: ast.source().map(|s| s.into()),
+            should_fail,
+            outcome,
+            duration,
+        }
+    }
+    /// Determine the outcome of a test from its result.
+    fn outcome(
+        &self,
+        result: Result<Dynamic, Box<EvalAltResult>>,
+        name: &str,
+        should_fail: bool,
+        duration: Duration,
+    ) -> TestOutcome {
+        let timed_out = self.time_limit.is_some_and(|limit| duration > limit);
+
+        match result {
+            Ok(_) if should_fail => TestOutcome::NotFailed,
+            Ok(_) => TestOutcome::Passed,
+            // Termination by the original progress callback is reported as an error
+            Err(err) if timed_out && matches!(*err, EvalAltResult::ErrorTerminated(_, _)) => {
+                TestOutcome::TimedOut
+            }
+            Err(_) if should_fail => TestOutcome::Passed,
+            Err(err) => TestOutcome::Failed(match *err {
+                // Errors in the test function itself are reported as-is
+                EvalAltResult::ErrorInFunctionCall(fn_name, _, err, pos)
+                    if fn_name == name && pos.is_none() =>
+                {
+                    err
+                }
+                err => err.into(),
+            }),
+        }
+    }
+}
+
+/// Escape text for use in XML.
+fn xml_escape(s: &str) -> String {
+    let mut output = String::with_capacity(s.len());
+
+    for ch in s.chars() {
+        match ch {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            '\'' => output.push_str("&apos;"),
+            // Other control characters are not allowed in XML
+            ch if (ch as u32) < 0x20 && !matches!(ch, '\n' | '\r' | '\t') => (),
+            ch => output.push(ch),
+        }
+    }
+
+    output
+}
@@ -6,11 +6,14 @@ use crate::engine::{
 };
 use crate::stdlib::{
     borrow::Cow,
-    cell::Cell,
-    char, fmt, format,
+    cell::RefCell,
+    char,
+    cmp::Ordering,
+    fmt, format,
+    hash::{Hash, Hasher},
     iter::{FusedIterator, Peekable},
     num::NonZeroUsize,
-    ops::{Add, AddAssign},
+    ops::{Add, AddAssign, Range},
     str::{Chars, FromStr},
     string::{String, ToString},
 };
@@ -35,25 +38,40 @@ pub type TokenStream<'a> = Peekable<TokenIterator<'a>>;
 
 /// A location (line number + character position) in the input script.
 ///
+/// A [`Position`] produced by the tokenizer or parser also carries the [`Span`] of the token or
+/// expression at that location, which is available via [`span`][Position::span].
+/// The [`Span`] is not considered when comparing or hashing [`Position`]'s.
+///
 /// # Limitations
 ///
 /// In order to keep footprint small, both line number and character position have 16-bit resolution,
 /// meaning they go up to a maximum of 65,535 lines and 65,535 characters per line.
 ///
 /// Advancing beyond the maximum line length or maximum number of lines is not an error but has no effect.
-#[derive(Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy)]
+#[derive(Clone, Copy)]
 pub struct Position {
     /// Line number - 0 = none
     line: u16,
     /// Character position - 0 = BOL
     pos: u16,
+    /// Line numbers and character positions of the first and last characters of the [`Span`]
+    /// at this position - all zero = none
+    span: [u16; 4],
 }
 
 impl Position {
     /// A [`Position`] representing no position.
-    pub const NONE: Self = Self { line: 0, pos: 0 };
+    pub const NONE: Self = Self {
+        line: 0,
+        pos: 0,
+        span: [0; 4],
+    };
     /// A [`Position`] representing the first position.
-    pub const START: Self = Self { line: 1, pos: 0 };
+    pub const START: Self = Self {
+        line: 1,
+        pos: 0,
+        span: [0; 4],
+    };
 
     /// Create a new [`Position`].
     ///
@@ -70,6 +88,7 @@ impl Position {
         Self {
             line,
             pos: position,
+            span: [0; 4],
         }
     }
     /// Get the line number (1-based), or [`None`] if there is no position.
@@ -90,6 +109,36 @@ impl Position {
             Some(self.pos as usize)
         }
     }
+    /// Get the [`Span`] of the token or expression at this [`Position`].
+    ///
+    /// If no [`Span`] is recorded, it covers only the character at this [`Position`].
+    #[inline(always)]
+    pub fn span(self) -> Span {
+        match self.span {
+            [0, _, _, _] => Span::point(Self::new_raw(self.line, self.pos)),
+            [start_line, start_pos, end_line, end_pos] => Span {
+                start: Self::new_raw(start_line, start_pos),
+                end: Self::new_raw(end_line, end_pos),
+            },
+        }
+    }
+    /// Set the [`Span`] of the token or expression at this [`Position`].
+    ///
+    /// Setting [`Span::NONE`] removes any [`Span`] recorded.
+    #[inline(always)]
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = [span.start.line, span.start.pos, span.end.line, span.end.pos];
+        self
+    }
+    /// Create a [`Position`] without checking the line number.
+    #[inline(always)]
+    const fn new_raw(line: u16, pos: u16) -> Self {
+        Self {
+            line,
+            pos,
+            span: [0; 4],
+        }
+    }
     /// Advance by one character position.
     #[inline(always)]
     pub(crate) fn advance(&mut self) {
@@ -134,6 +183,36 @@ impl Position {
     }
 }
 
+impl PartialEq for Position {
+    #[inline(always)]
+    fn eq(&self, other: &Self) -> bool {
+        (self.line, self.pos) == (other.line, other.pos)
+    }
+}
+
+impl Eq for Position {}
+
+impl PartialOrd for Position {
+    #[inline(always)]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Position {
+    #[inline(always)]
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.line, self.pos).cmp(&(other.line, other.pos))
+    }
+}
+
+impl Hash for Position {
+    #[inline(always)]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (self.line, self.pos).hash(state);
+    }
+}
+
 impl Default for Position {
     #[inline(always)]
     fn default() -> Self {
@@ -166,14 +245,14 @@ impl Add for Position {
         if rhs.is_none() {
             self
         } else {
-            Self {
-                line: self.line + rhs.line - 1,
-                pos: if rhs.is_beginning_of_line() {
+            Self::new_raw(
+                self.line + rhs.line - 1,
+                if rhs.is_beginning_of_line() {
                     self.pos
                 } else {
                     self.pos + rhs.pos - 1
                 },
-            }
+            )
         }
     }
 }
@@ -184,6 +263,102 @@ impl AddAssign for Position {
     }
 }
 
+/// A range in the input script, from the [`Position`] of its first character to the
+/// [`Position`] of its last character (inclusive).
+///
+/// Spans are recorded while lexing and parsing, and are carried by the [`Position`]'s of tokens,
+/// [`AST`][crate::AST] nodes and errors.
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy)]
+pub struct Span {
+    /// Position of the first character.
+    start: Position,
+    /// Position of the last character.
+    end: Position,
+}
+
+impl Span {
+    /// A [`Span`] representing no position.
+    pub const NONE: Self = Self {
+        start: Position::NONE,
+        end: Position::NONE,
+    };
+
+    /// Create a new [`Span`].
+    ///
+    /// Returns [`None`] if `end` is before `start`, or only one of them is [`NONE`][Position::NONE].
+    #[inline(always)]
+    pub fn new(start: Position, end: Position) -> Option<Self> {
+        if start.is_none() != end.is_none() || start > end {
+            None
+        } else {
+            Some(Self {
+                start: Position::new_raw(start.line, start.pos),
+                end: Position::new_raw(end.line, end.pos),
+            })
+        }
+    }
+    /// Create a [`Span`] covering a single character.
+    #[inline(always)]
+    pub fn point(pos: Position) -> Self {
+        let pos = Position::new_raw(pos.line, pos.pos);
+        Self {
+            start: pos,
+            end: pos,
+        }
+    }
+    /// Get the [`Position`] of the first character.
+    #[inline(always)]
+    pub fn start(self) -> Position {
+        self.start
+    }
+    /// Get the [`Position`] of the last character.
+    #[inline(always)]
+    pub fn end(self) -> Position {
+        self.end
+    }
+    /// Is there no [`Span`]?
+    #[inline(always)]
+    pub fn is_none(self) -> bool {
+        self.start.is_none()
+    }
+    /// Get the range of byte offsets covered by this [`Span`] in a script,
+    /// or [`None`] if it is not within the script.
+    ///
+    /// A [`Position`] at the beginning of a line maps to the first character of that line.
+    pub fn byte_range(self, script: &str) -> Option<Range<usize>> {
+        fn offset_of(script: &str, pos: Position) -> Option<(usize, usize)> {
+            let mut line_start = 0;
+
+            for _ in 1..pos.line()? {
+                line_start += script[line_start..].find('\n')? + 1;
+            }
+
+            let line = script[line_start..].split('\n').next().unwrap();
+            let skip = pos.position().unwrap_or(1) - 1;
+
+            line.char_indices()
+                .nth(skip)
+                .map(|(i, ch)| (line_start + i, line_start + i + ch.len_utf8()))
+        }
+
+        let (start, _) = offset_of(script, self.start)?;
+        let (_, end) = offset_of(script, self.end)?;
+
+        Some(start..end)
+    }
+}
+
+impl fmt::Display for Span {
+    #[inline(always)]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.start == self.end {
+            write!(f, "{}", self.start)
+        } else {
+            write!(f, "{} to {}", self.start, self.end)
+        }
+    }
+}
+
 /// _(INTERNALS)_ A Rhai language token.
 /// Exported under the `internals` feature only.
 ///
@@ -1832,6 +2007,43 @@ impl InputStream for MultiInputsStream<'_> {
     }
 }
 
+/// _(INTERNALS)_ State shared between a [`TokenIterator`] and the parser consuming its tokens.
+/// Exported under the `internals` feature only.
+///
+/// # Volatile Data Structure
+///
+/// This type is volatile and may change.
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+pub struct TokenizerControlBlock {
+    /// Next character to read, if any.
+    pub buffer: Option<char>,
+    /// [`Position`]'s of the last two tokens produced, the latest last.
+    ///
+    /// A [`TokenStream`] holds back at most one token, so all tokens before the latest one have
+    /// been consumed.
+    pub last_tokens: [Position; 2],
+}
+
+impl TokenizerControlBlock {
+    /// Get the [`Position`] of the last character of the last token before a [`Position`], if any.
+    #[inline(always)]
+    pub fn end_before(&self, pos: Position) -> Option<Position> {
+        self.last_tokens
+            .iter()
+            .rev()
+            .find(|&&token| !token.is_none() && token < pos)
+            .map(|token| token.span().end())
+    }
+}
+
+/// _(INTERNALS)_ A shared handle to a [`TokenizerControlBlock`].
+/// Exported under the `internals` feature only.
+///
+/// # Volatile Data Structure
+///
+/// This type is volatile and may change.
+pub type TokenizerControl = Shared<RefCell<TokenizerControlBlock>>;
+
 /// An iterator on a [`Token`] stream.
 pub struct TokenIterator<'a> {
     /// Reference to the scripting `Engine`.
@@ -1840,8 +2052,8 @@ pub struct TokenIterator<'a> {
     state: TokenizeState,
     /// Current position.
     pos: Position,
-    /// Buffer containing the next character to read, if any.
-    buffer: Shared<Cell<Option<char>>>,
+    /// State shared with the parser.
+    control: TokenizerControl,
     /// Input character stream.
     stream: MultiInputsStream<'a>,
     /// A processor function that maps a token to another.
@@ -1852,7 +2064,7 @@ impl<'a> Iterator for TokenIterator<'a> {
     type Item = (Token, Position);
 
     fn next(&mut self) -> Option<Self::Item> {
-        if let Some(ch) = self.buffer.take() {
+        if let Some(ch) = self.control.borrow_mut().buffer.take() {
             self.stream.unget(ch);
             self.pos.rewind();
         }
@@ -1924,6 +2136,17 @@ impl<'a> Iterator for TokenIterator<'a> {
             Some(r) => r,
         };
 
+        // Record the span of the token
+        let pos = match Span::new(pos, self.pos) {
+            Some(span) if !pos.is_none() => {
+                let pos = pos.with_span(span);
+                let last_tokens = &mut self.control.borrow_mut().last_tokens;
+                *last_tokens = [last_tokens[1], pos];
+                pos
+            }
+            _ => pos,
+        };
+
         // Run the mapper, if any
         let token = if let Some(map) = self.map {
             map(token)
@@ -1945,7 +2168,7 @@ impl Engine {
     pub fn lex<'a>(
         &'a self,
         input: impl IntoIterator<Item = &'a &'a str>,
-    ) -> (TokenIterator<'a>, Shared<Cell<Option<char>>>) {
+    ) -> (TokenIterator<'a>, TokenizerControl) {
         self.lex_raw(input, None)
     }
     /// _(INTERNALS)_ Tokenize an input text stream with a mapping function.
@@ -1956,7 +2179,7 @@ impl Engine {
         &'a self,
         input: impl IntoIterator<Item = &'a &'a str>,
         map: fn(Token) -> Token,
-    ) -> (TokenIterator<'a>, Shared<Cell<Option<char>>>) {
+    ) -> (TokenIterator<'a>, TokenizerControl) {
         self.lex_raw(input, Some(map))
     }
     /// Tokenize an input text stream with an optional mapping function.
@@ -1965,9 +2188,9 @@ impl Engine {
         &'a self,
         input: impl IntoIterator<Item = &'a &'a str>,
         map: Option<fn(Token) -> Token>,
-    ) -> (TokenIterator<'a>, Shared<Cell<Option<char>>>) {
-        let buffer: Shared<Cell<Option<char>>> = Cell::new(None).into();
-        let buffer2 = buffer.clone();
+    ) -> (TokenIterator<'a>, TokenizerControl) {
+        let control: TokenizerControl = RefCell::new(Default::default()).into();
+        let control2 = control.clone();
 
         (
             TokenIterator {
@@ -1984,7 +2207,7 @@ impl Engine {
                     disable_doc_comments: self.disable_doc_comments,
                 },
                 pos: Position::new(1, 0),
-                buffer,
+                control,
                 stream: MultiInputsStream {
                     buf: None,
                     streams: input.into_iter().map(|s| s.chars().peekable()).collect(),
@@ -1992,7 +2215,7 @@ impl Engine {
                 },
                 map,
             },
-            buffer2,
+            control2,
         )
     }
 }
@@ -0,0 +1,1228 @@
+//! Module implementing a static type checking pass over the [`AST`].
+
+use crate::ast::{ASTNode, Expr, FnCallExpr, ReturnType, Stmt};
+use crate::engine::{
+    KEYWORD_DEBUG, KEYWORD_EVAL, KEYWORD_FN_PTR, KEYWORD_FN_PTR_CALL, KEYWORD_FN_PTR_CURRY,
+    KEYWORD_IS_DEF_VAR, KEYWORD_PRINT, KEYWORD_TYPE_OF,
+};
+use crate::fn_builtin::{get_builtin_binary_op_fn, get_builtin_op_assignment_fn};
+use crate::optimize::OptimizationLevel;
+use crate::stdlib::{
+    any::TypeId,
+    boxed::Box,
+    collections::{BTreeMap, BTreeSet},
+    error::Error,
+    fmt, format,
+    iter::empty,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use crate::token::is_valid_identifier;
+use crate::{
+    calc_fn_hash, Dynamic, Engine, FnNamespace, FnPtr, ImmutableString, Module, ParseError,
+    Position, Scope, StaticVec, AST, INT,
+};
+
+#[cfg(not(feature = "no_function"))]
+use crate::stdlib::mem;
+
+#[cfg(not(feature = "no_float"))]
+use crate::FLOAT;
+
+#[cfg(not(feature = "no_index"))]
+use crate::Array;
+
+#[cfg(not(feature = "no_object"))]
+use crate::Map;
+
+/// Type of error detected by the [`TypeChecker`].
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+#[non_exhaustive]
+pub enum TypeErrorType {
+    /// Data is not of the required type.
+    /// Wrapped values are the type requested and type of the actual result.
+    MismatchDataType(String, String),
+    /// No function matches the call.
+    /// Wrapped value is the function signature.
+    FunctionNotFound(String),
+    /// A property is not available on a type.
+    /// Wrapped values are the name of the property and the type name.
+    PropertyNotFound(String, String),
+    /// A type cannot be indexed.
+    /// Wrapped value is the type name.
+    IndexingType(String),
+    /// A type cannot be iterated by a `for` statement.
+    /// Wrapped value is the type name.
+    NotIterable(String),
+}
+
+impl TypeErrorType {
+    /// Make a [`TypeError`] using the current type and position.
+    #[inline(always)]
+    pub(crate) fn into_err(self, pos: Position) -> TypeError {
+        TypeError(Box::new(self), pos)
+    }
+}
+
+impl fmt::Display for TypeErrorType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MismatchDataType(s, r) => {
+                write!(f, "Data type is incorrect: {} (expecting {})", r, s)
+            }
+            Self::FunctionNotFound(s) => write!(f, "Function not found: {}", s),
+            Self::PropertyNotFound(p, t) => {
+                write!(f, "Property '{}' not found for type '{}'", p, t)
+            }
+            Self::IndexingType(s) => write!(f, "Indexer not registered for type '{}'", s),
+            Self::NotIterable(s) => write!(f, "Type '{}' cannot be iterated in a for loop", s),
+        }
+    }
+}
+
+/// Error detected by the [`TypeChecker`].
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+pub struct TypeError(pub Box<TypeErrorType>, pub Position);
+
+impl Error for TypeError {}
+
+impl fmt::Display for TypeError {
+    #[inline(always)]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)?;
+
+        // Do not write any position if None
+        if !self.1.is_none() {
+            write!(f, " ({})", self.1)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A static type checker that infers the types of expressions in a script and reports
+/// operations that are certain to fail at runtime.
+///
+/// Types are inferred from literals, the built-in operators, the parameter types of native
+/// functions registered with the [`Engine`], and the bodies of script-defined functions.
+///
+/// The checker is conservative: whenever a type cannot be determined, it is treated as
+/// [`Dynamic`] and no error is reported. The runtime behavior of scripts is never affected.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> Result<(), rhai::ParseError> {
+/// use rhai::{Engine, TypeChecker, TypeErrorType};
+///
+/// let engine = Engine::new();
+/// let checker = TypeChecker::new(&engine);
+///
+/// let errors = checker.check(r#"let x = 40 + 2; if x { print("hello"); }"#)?;
+///
+/// assert_eq!(errors.len(), 1);
+/// assert_eq!(
+///     *errors[0].0,
+///     TypeErrorType::MismatchDataType("bool".into(), "i64".into())
+/// );
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct TypeChecker<'e> {
+    /// The [`Engine`] providing registered functions.
+    engine: &'e Engine,
+}
+
+impl<'e> TypeChecker<'e> {
+    /// Create a new [`TypeChecker`] checking scripts against an [`Engine`].
+    #[inline(always)]
+    pub fn new(engine: &'e Engine) -> Self {
+        Self { engine }
+    }
+    /// Compile a script without optimizations and check it.
+    ///
+    /// Errors are returned in order of their positions.
+    #[inline(always)]
+    pub fn check(&self, script: &str) -> Result<Vec<TypeError>, ParseError> {
+        self.check_with_scope(&Default::default(), script)
+    }
+    /// Compile a script without optimizations and check it, taking the types of variables
+    /// from a [`Scope`].
+    ///
+    /// Errors are returned in order of their positions.
+    pub fn check_with_scope(
+        &self,
+        scope: &Scope,
+        script: &str,
+    ) -> Result<Vec<TypeError>, ParseError> {
+        let ast = self.engine.compile_with_scope_and_optimization_level(
+            scope,
+            &[script],
+            OptimizationLevel::None,
+        )?;
+        Ok(self.check_ast_with_scope(scope, &ast))
+    }
+    /// Check an [`AST`].
+    ///
+    /// Errors are returned in order of their positions.
+    #[inline(always)]
+    pub fn check_ast(&self, ast: &AST) -> Vec<TypeError> {
+        self.check_ast_with_scope(&Default::default(), ast)
+    }
+    /// Check an [`AST`], taking the types of variables from a [`Scope`].
+    ///
+    /// Errors are returned in order of their positions.
+    #[allow(deprecated)]
+    pub fn check_ast_with_scope(&self, scope: &Scope, ast: &AST) -> Vec<TypeError> {
+        let mut state = State::new(self.engine, ast);
+
+        state.stack = scope
+            .iter()
+            .map(|(name, _, value)| Var {
+                name: name.into(),
+                typ: Type::of(&value),
+                shared: false,
+            })
+            .collect();
+
+        state.check_block(ast.statements());
+
+        #[cfg(not(feature = "no_function"))]
+        for (_, _, _, _, fn_def) in ast.lib().iter_script_fn() {
+            state.stack = fn_def
+                .params
+                .iter()
+                .map(|name| Var {
+                    name: name.as_str().into(),
+                    typ: None,
+                    shared: false,
+                })
+                .collect();
+
+            state.check_block(&fn_def.body.statements);
+        }
+
+        let mut errors = state.errors;
+        errors.sort_by_key(|err| err.1);
+        errors.dedup();
+        errors
+    }
+}
+
+/// A statically-known type.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+enum Type {
+    Unit,
+    Bool,
+    Int,
+    #[cfg(not(feature = "no_float"))]
+    Float,
+    Char,
+    String,
+    #[cfg(not(feature = "no_index"))]
+    Array,
+    #[cfg(not(feature = "no_object"))]
+    Map,
+    FnPtr,
+}
+
+impl Type {
+    /// All statically-known types.
+    const ALL: &'static [Type] = &[
+        Self::Unit,
+        Self::Bool,
+        Self::Int,
+        #[cfg(not(feature = "no_float"))]
+        Self::Float,
+        Self::Char,
+        Self::String,
+        #[cfg(not(feature = "no_index"))]
+        Self::Array,
+        #[cfg(not(feature = "no_object"))]
+        Self::Map,
+        Self::FnPtr,
+    ];
+
+    /// Get the type of a value, if it is statically known.
+    fn of(value: &Dynamic) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|t| t.type_id() == value.type_id())
+    }
+    /// Make a sample value of this type.
+    fn sample(self) -> Dynamic {
+        match self {
+            Self::Unit => Dynamic::UNIT,
+            Self::Bool => Dynamic::TRUE,
+            Self::Int => (1 as INT).into(),
+            #[cfg(not(feature = "no_float"))]
+            Self::Float => (1.0 as FLOAT).into(),
+            Self::Char => 'a'.into(),
+            Self::String => "a".into(),
+            #[cfg(not(feature = "no_index"))]
+            Self::Array => Array::new().into(),
+            #[cfg(not(feature = "no_object"))]
+            Self::Map => Map::new().into(),
+            Self::FnPtr => FnPtr::new_unchecked("f", Default::default()).into(),
+        }
+    }
+    /// Get the [`TypeId`] of this type.
+    fn type_id(self) -> TypeId {
+        match self {
+            Self::Unit => TypeId::of::<()>(),
+            Self::Bool => TypeId::of::<bool>(),
+            Self::Int => TypeId::of::<INT>(),
+            #[cfg(not(feature = "no_float"))]
+            Self::Float => TypeId::of::<FLOAT>(),
+            Self::Char => TypeId::of::<char>(),
+            Self::String => TypeId::of::<ImmutableString>(),
+            #[cfg(not(feature = "no_index"))]
+            Self::Array => TypeId::of::<Array>(),
+            #[cfg(not(feature = "no_object"))]
+            Self::Map => TypeId::of::<Map>(),
+            Self::FnPtr => TypeId::of::<FnPtr>(),
+        }
+    }
+}
+
+/// Join the types of two alternative values.
+#[inline(always)]
+fn join(a: Option<Type>, b: Option<Type>) -> Option<Type> {
+    if a == b {
+        a
+    } else {
+        None
+    }
+}
+
+/// A variable in scope during type checking.
+#[derive(Debug, Clone)]
+struct Var<'a> {
+    /// Variable name.
+    name: crate::stdlib::borrow::Cow<'a, str>,
+    /// Type of the variable's current value, if known.
+    typ: Option<Type>,
+    /// Is the variable shared with a closure?
+    shared: bool,
+}
+
+/// Mutable state throughout a type checking pass.
+struct State<'a> {
+    /// The [`Engine`].
+    engine: &'a Engine,
+    /// Parameter types of native functions, by hash of name and number of parameters.
+    native_fns: BTreeMap<u64, Vec<StaticVec<TypeId>>>,
+    /// Script-defined functions, by name and number of parameters.
+    #[cfg(not(feature = "no_function"))]
+    script_fns: BTreeMap<(&'a str, usize), &'a crate::ast::ScriptFnDef>,
+    /// Inferred return types of script-defined functions.
+    #[cfg(not(feature = "no_function"))]
+    fn_types: BTreeMap<(&'a str, usize), Option<Type>>,
+    /// Variables currently in scope.
+    stack: Vec<Var<'a>>,
+    /// Variable types at each `break` or `continue` of the current loop.
+    loop_exits: Vec<Vec<Vec<Option<Type>>>>,
+    /// Types of values returned by `return` statements in the current function.
+    returns: Vec<Option<Type>>,
+    /// Errors detected so far.
+    errors: Vec<TypeError>,
+    /// Report errors?
+    report: bool,
+    /// Can variables be modified in ways that are not visible in the [`AST`]
+    /// (e.g. by `eval` or custom syntax)?
+    opaque_scope: bool,
+    /// Can functions come from imported modules?
+    has_imports: bool,
+}
+
+impl<'a> State<'a> {
+    /// Create a new [`State`] for checking an [`AST`].
+    #[allow(deprecated)]
+    fn new(engine: &'a Engine, ast: &'a AST) -> Self {
+        let mut native_fns = Default::default();
+        collect_native_fns(&engine.global_namespace, true, &mut native_fns);
+        engine
+            .global_modules
+            .iter()
+            .for_each(|m| collect_native_fns(m, true, &mut native_fns));
+        engine
+            .global_sub_modules
+            .values()
+            .for_each(|m| collect_native_fns(m, false, &mut native_fns));
+
+        let mut opaque_scope = false;
+        #[cfg(not(feature = "no_module"))]
+        let mut has_imports = false;
+        #[cfg(feature = "no_module")]
+        let has_imports = false;
+
+        ast.walk(&mut |path| {
+            match path.last().unwrap() {
+                ASTNode::Expr(Expr::FnCall(x, _)) if x.name == KEYWORD_EVAL => opaque_scope = true,
+                ASTNode::Expr(Expr::Custom(_, _)) => opaque_scope = true,
+                #[cfg(not(feature = "no_module"))]
+                ASTNode::Stmt(Stmt::Import(_, _, _)) => has_imports = true,
+                _ => (),
+            }
+            true
+        });
+
+        Self {
+            engine,
+            native_fns,
+            #[cfg(not(feature = "no_function"))]
+            script_fns: ast
+                .lib()
+                .iter_script_fn()
+                .map(|(_, _, name, params, fn_def)| ((name, params), fn_def.as_ref()))
+                .collect(),
+            #[cfg(not(feature = "no_function"))]
+            fn_types: Default::default(),
+            stack: Default::default(),
+            loop_exits: Default::default(),
+            returns: Default::default(),
+            errors: Default::default(),
+            report: true,
+            opaque_scope,
+            has_imports,
+        }
+    }
+    /// Report an error.
+    #[inline(always)]
+    fn error(&mut self, err: TypeErrorType, pos: Position) {
+        if self.report {
+            self.errors.push(err.into_err(pos));
+        }
+    }
+    /// Get the name of a type.
+    #[inline(always)]
+    fn type_name(&self, typ: Type) -> String {
+        self.engine.map_type_name(typ.sample().type_name()).into()
+    }
+    /// Report a data type mismatch if a type is known and is not the required type.
+    fn expect_type(&mut self, required: Type, typ: Option<Type>, pos: Position) {
+        match typ {
+            Some(t) if t != required => {
+                let err =
+                    TypeErrorType::MismatchDataType(self.type_name(required), self.type_name(t));
+                self.error(err, pos);
+            }
+            _ => (),
+        }
+    }
+
+    /// Get the types of all variables in scope.
+    #[inline(always)]
+    fn var_types(&self) -> Vec<Option<Type>> {
+        self.stack.iter().map(|v| v.typ).collect()
+    }
+    /// Set the types of the variables in scope.
+    #[inline(always)]
+    fn set_var_types(&mut self, types: &[Option<Type>]) {
+        self.stack
+            .iter_mut()
+            .zip(types)
+            .for_each(|(v, &t)| v.typ = t);
+    }
+    /// Set the types of the variables in scope by joining alternative types.
+    fn join_var_types(&mut self, alternatives: &[Vec<Option<Type>>]) {
+        for (index, var) in self.stack.iter_mut().enumerate() {
+            let mut types = alternatives.iter().map(|types| types[index]);
+            let first = types.next().unwrap_or(var.typ);
+            var.typ = types.fold(first, join);
+        }
+    }
+    /// Find a variable.
+    #[inline(always)]
+    fn find_var(&mut self, name: &str) -> Option<&mut Var<'a>> {
+        self.stack.iter_mut().rev().find(|v| v.name == name)
+    }
+    /// Get the type of a variable, if known.
+    fn var_type(&mut self, name: &str) -> Option<Type> {
+        if self.opaque_scope {
+            return None;
+        }
+        match self.find_var(name) {
+            Some(Var {
+                typ, shared: false, ..
+            }) => *typ,
+            _ => None,
+        }
+    }
+    /// Set the type of a variable.
+    fn set_var_type(&mut self, name: &str, typ: Option<Type>) {
+        if let Some(var) = self.find_var(name) {
+            var.typ = if var.shared { None } else { typ };
+        }
+    }
+    /// Forget the types of all variables in scope.
+    fn clear_var_types(&mut self) {
+        self.stack.iter_mut().for_each(|v| v.typ = None);
+    }
+    /// Check a statements block, returning the type of its value.
+    fn check_block(&mut self, statements: &'a [Stmt]) -> Option<Type> {
+        let scope_len = self.stack.len();
+        let mut typ = Some(Type::Unit);
+
+        for stmt in statements {
+            typ = self.check_stmt(stmt);
+        }
+
+        self.stack.truncate(scope_len);
+        typ
+    }
+    /// Check alternative blocks, returning the joined type of their values.
+    fn check_branches(
+        &mut self,
+        blocks: impl Iterator<Item = &'a [Stmt]>,
+        exhaustive: bool,
+    ) -> Option<Type> {
+        let before = self.var_types();
+        let mut alternatives = Vec::new();
+        let mut typ = None;
+
+        for (index, statements) in blocks.enumerate() {
+            self.set_var_types(&before);
+            let t = self.check_block(statements);
+            typ = if index == 0 { t } else { join(typ, t) };
+            alternatives.push(self.var_types());
+        }
+
+        if !exhaustive {
+            alternatives.push(before);
+            typ = join(typ, Some(Type::Unit));
+        }
+
+        self.join_var_types(&alternatives);
+        typ
+    }
+    /// Check a loop, which may run its body any number of times.
+    fn check_loop(&mut self, body: &'a [Stmt], var: Option<&'a str>, condition: Option<&'a Expr>) {
+        let report = self.report;
+        let scope_len = self.stack.len();
+
+        // Find the types of variables at the start of each iteration without reporting errors,
+        // then check the loop once more with those types
+        self.report = false;
+        let mut is_final = false;
+
+        loop {
+            let before = self.var_types();
+
+            self.loop_exits.push(Vec::new());
+
+            if let Some(expr) = condition {
+                let t = self.infer_expr(expr);
+                self.expect_type(Type::Bool, t, expr.position());
+            }
+            if let Some(name) = var {
+                self.stack.push(Var {
+                    name: name.into(),
+                    typ: None,
+                    shared: false,
+                });
+            }
+
+            self.check_block(body);
+            self.stack.truncate(scope_len);
+
+            let mut alternatives = self.loop_exits.pop().unwrap();
+            alternatives.iter_mut().for_each(|v| v.truncate(scope_len));
+            alternatives.push(self.var_types());
+            alternatives.push(before.clone());
+            self.join_var_types(&alternatives);
+
+            if is_final {
+                break;
+            }
+            if self.var_types() == before {
+                self.report = report;
+                is_final = true;
+            }
+        }
+    }
+    /// Check a statement, returning the type of its value.
+    fn check_stmt(&mut self, stmt: &'a Stmt) -> Option<Type> {
+        match stmt {
+            Stmt::Noop(_) => Some(Type::Unit),
+            Stmt::Expr(expr) => self.infer_expr(expr),
+            Stmt::Block(statements, _) => self.check_block(statements),
+
+            Stmt::Let(expr, ident, _, _) | Stmt::Const(expr, ident, _, _) => {
+                let typ = self.infer_expr(expr);
+                self.stack.push(Var {
+                    name: ident.name.as_str().into(),
+                    typ,
+                    shared: false,
+                });
+                Some(Type::Unit)
+            }
+
+            Stmt::Assignment(x, pos) => {
+                let rhs = self.infer_expr(&x.2);
+
+                match (&x.0, &x.1) {
+                    // var = value
+                    (Expr::Variable(v), None) if v.1.is_none() => {
+                        self.set_var_type(&v.2.name, rhs);
+                    }
+                    // var op= value
+                    (Expr::Variable(v), Some(op_info)) if v.1.is_none() => {
+                        let lhs = self.var_type(&v.2.name);
+                        let typ = self.infer_op_assignment(op_info.op, lhs, rhs, *pos);
+                        self.set_var_type(&v.2.name, typ);
+                    }
+                    // expr op= value
+                    (lhs, Some(op_info)) => {
+                        let lhs = self.infer_expr(lhs);
+                        self.infer_op_assignment(op_info.op, lhs, rhs, *pos);
+                    }
+                    // expr = value
+                    (lhs, None) => {
+                        self.infer_expr(lhs);
+                    }
+                }
+
+                Some(Type::Unit)
+            }
+
+            Stmt::If(expr, x, _) => {
+                let t = self.infer_expr(expr);
+                self.expect_type(Type::Bool, t, expr.position());
+
+                let blocks = [x.0.statements.as_ref(), x.1.statements.as_ref()];
+                self.check_branches(blocks.iter().copied(), true)
+            }
+
+            Stmt::Switch(expr, x, _) => {
+                self.infer_expr(expr);
+
+                let blocks =
+                    x.0.values()
+                        .map(|block| block.statements.as_ref())
+                        .chain(crate::stdlib::iter::once(x.1.statements.as_ref()));
+                self.check_branches(blocks, true)
+            }
+
+            Stmt::While(expr, body, _) => {
+                // `loop` is parsed as `while ()`
+                let condition = if expr.is_unit() { None } else { Some(expr) };
+                self.check_loop(&body.statements, None, condition);
+                Some(Type::Unit)
+            }
+            Stmt::Do(body, expr, _, _) => {
+                self.check_loop(&body.statements, None, Some(expr));
+                Some(Type::Unit)
+            }
+            Stmt::For(expr, x, _) => {
+                let t = self.infer_expr(expr);
+
+                match t {
+                    Some(t) if !self.has_imports && !self.has_iterator(t) => {
+                        let err = TypeErrorType::NotIterable(self.type_name(t));
+                        self.error(err, expr.position());
+                    }
+                    _ => (),
+                }
+
+                self.check_loop(&x.1.statements, Some(&x.0.name), None);
+                Some(Type::Unit)
+            }
+
+            Stmt::Break(_) | Stmt::Continue(_) => {
+                let types = self.var_types();
+                if let Some(exits) = self.loop_exits.last_mut() {
+                    exits.push(types);
+                }
+                None
+            }
+            Stmt::Return(ReturnType::Return, expr, _) => {
+                let t = match expr {
+                    Some(expr) => self.infer_expr(expr),
+                    None => Some(Type::Unit),
+                };
+                self.returns.push(t);
+                None
+            }
+            Stmt::Return(ReturnType::Exception, expr, _) => {
+                if let Some(expr) = expr {
+                    self.infer_expr(expr);
+                }
+                None
+            }
+
+            Stmt::TryCatch(x, _, _) => {
+                let before = self.var_types();
+                let t1 = self.check_block(&x.0.statements);
+                let after_try = self.var_types();
+
+                // The `try` block may fail at any point
+                self.set_var_types(&before);
+                self.join_var_types(&[before.clone(), after_try.clone()]);
+                for name in assigned_vars(&x.0.statements) {
+                    self.set_var_type(&name, None);
+                }
+
+                let scope_len = self.stack.len();
+                if let Some(ident) = &x.1 {
+                    self.stack.push(Var {
+                        name: ident.name.as_str().into(),
+                        typ: None,
+                        shared: false,
+                    });
+                }
+                let t2 = self.check_block(&x.2.statements);
+                self.stack.truncate(scope_len);
+
+                let after_catch = self.var_types();
+                self.join_var_types(&[after_try, after_catch]);
+
+                join(t1, t2)
+            }
+
+            #[cfg(not(feature = "no_module"))]
+            Stmt::Import(expr, _, _) => {
+                self.infer_expr(expr);
+                Some(Type::Unit)
+            }
+            #[cfg(not(feature = "no_module"))]
+            Stmt::Export(_, _) => Some(Type::Unit),
+
+            #[cfg(not(feature = "no_closure"))]
+            Stmt::Share(name) => {
+                if let Some(var) = self.find_var(name) {
+                    var.shared = true;
+                    var.typ = None;
+                }
+                Some(Type::Unit)
+            }
+        }
+    }
+    /// Infer the type of an expression.
+    fn infer_expr(&mut self, expr: &'a Expr) -> Option<Type> {
+        match expr {
+            Expr::DynamicConstant(x, _) => Type::of(x),
+            Expr::BoolConstant(_, _) => Some(Type::Bool),
+            Expr::IntegerConstant(_, _) => Some(Type::Int),
+            #[cfg(not(feature = "no_float"))]
+            Expr::FloatConstant(_, _) => Some(Type::Float),
+            Expr::CharConstant(_, _) => Some(Type::Char),
+            Expr::StringConstant(_, _) => Some(Type::String),
+            Expr::FnPointer(_, _) => Some(Type::FnPtr),
+            Expr::Unit(_) => Some(Type::Unit),
+
+            Expr::InterpolatedString(x) => {
+                x.iter().for_each(|e| {
+                    self.infer_expr(e);
+                });
+                Some(Type::String)
+            }
+            #[cfg(not(feature = "no_index"))]
+            Expr::Array(x, _) => {
+                x.iter().for_each(|e| {
+                    self.infer_expr(e);
+                });
+                Some(Type::Array)
+            }
+            #[cfg(not(feature = "no_object"))]
+            Expr::Map(x, _) => {
+                x.0.iter().for_each(|(_, e)| {
+                    self.infer_expr(e);
+                });
+                Some(Type::Map)
+            }
+            #[cfg(feature = "no_index")]
+            Expr::Array(_, _) => None,
+            #[cfg(feature = "no_object")]
+            Expr::Map(_, _) => None,
+
+            Expr::Variable(x) if x.1.is_none() => self.var_type(&x.2.name),
+            Expr::Variable(_) | Expr::Property(_) => None,
+
+            Expr::Stmt(x) => self.check_block(&x.statements),
+
+            Expr::And(x, _) | Expr::Or(x, _) => {
+                let lhs = self.infer_expr(&x.lhs);
+                self.expect_type(Type::Bool, lhs, x.lhs.position());
+                let rhs = self.infer_expr(&x.rhs);
+                self.expect_type(Type::Bool, rhs, x.rhs.position());
+                Some(Type::Bool)
+            }
+
+            Expr::Dot(x, _) => {
+                let lhs = self.infer_expr(&x.lhs);
+                let typ = self.infer_chain(lhs, &x.rhs, true);
+
+                // Calling a method on a variable may change its value
+                match x.lhs {
+                    Expr::Variable(ref v)
+                        if v.1.is_none() && self.chain_may_change_type(lhs, &x.rhs) =>
+                    {
+                        self.set_var_type(&v.2.name, None);
+                    }
+                    _ => (),
+                }
+
+                typ
+            }
+            Expr::Index(x, _) => {
+                let lhs = self.infer_expr(&x.lhs);
+                self.infer_chain(lhs, &x.rhs, false)
+            }
+
+            Expr::FnCall(x, pos) => {
+                let args = self.infer_args(None, x);
+
+                let typ = self.infer_fn_call(x, &args, false, *pos);
+
+                // Functions capturing the scope may modify any variable
+                if x.capture {
+                    self.clear_var_types();
+                }
+
+                typ
+            }
+
+            Expr::Custom(x, _) => {
+                x.keywords.iter().for_each(|e| {
+                    self.infer_expr(e);
+                });
+                None
+            }
+        }
+    }
+    /// Infer the type of a dot or index chain, applied to a target of a particular type.
+    fn infer_chain(&mut self, target: Option<Type>, rhs: &'a Expr, is_dot: bool) -> Option<Type> {
+        let (item, next) = match rhs {
+            Expr::Dot(x, _) => (&x.lhs, Some((&x.rhs, true))),
+            Expr::Index(x, _) => (&x.lhs, Some((&x.rhs, false))),
+            _ => (rhs, None),
+        };
+
+        let typ = if is_dot {
+            match item {
+                Expr::Property(x) => self.infer_property(target, &x.0 .0, &x.2.name, x.2.pos),
+                Expr::FnCall(x, pos) => {
+                    let args = self.infer_args(Some(target), x);
+                    self.infer_fn_call(x, &args, true, *pos)
+                }
+                _ => self.infer_expr(item),
+            }
+        } else {
+            let index = self.infer_expr(item);
+            self.infer_index(target, index, item.position(), rhs.position())
+        };
+
+        match next {
+            Some((rhs, is_dot)) => self.infer_chain(typ, rhs, is_dot),
+            None => typ,
+        }
+    }
+    /// Can a dot chain change the type of its target?
+    fn chain_may_change_type(&self, target: Option<Type>, rhs: &Expr) -> bool {
+        let item = match rhs {
+            Expr::Dot(x, _) | Expr::Index(x, _) => &x.lhs,
+            _ => rhs,
+        };
+
+        match item {
+            Expr::FnCall(x, _) => {
+                #[cfg(not(feature = "no_function"))]
+                if self
+                    .script_fns
+                    .contains_key(&(x.name.as_str(), x.num_args()))
+                {
+                    // Script-defined methods may assign to `this`
+                    return true;
+                }
+
+                match target {
+                    // Native methods taking a `Dynamic` may change its type
+                    Some(t) => {
+                        let hash = calc_fn_hash(empty(), &x.name, x.num_args() + 1);
+                        self.native_fns.get(&hash).is_some_and(|fns| {
+                            fns.iter()
+                                .any(|params| params[0] == TypeId::of::<Dynamic>())
+                                && !fns.iter().any(|params| params[0] == t.type_id())
+                        })
+                    }
+                    None => true,
+                }
+            }
+            _ => false,
+        }
+    }
+    /// Infer the type of a property access.
+    fn infer_property(
+        &mut self,
+        target: Option<Type>,
+        getter: &str,
+        name: &str,
+        pos: Position,
+    ) -> Option<Type> {
+        match target {
+            #[cfg(not(feature = "no_object"))]
+            Some(Type::Map) => (),
+            Some(t) if !self.has_imports && self.find_native_fn(getter, &[Some(t)]).is_none() => {
+                let err = TypeErrorType::PropertyNotFound(name.into(), self.type_name(t));
+                self.error(err, pos);
+            }
+            _ => (),
+        }
+        None
+    }
+    /// Infer the type of an indexing operation.
+    fn infer_index(
+        &mut self,
+        target: Option<Type>,
+        _index: Option<Type>,
+        _index_pos: Position,
+        pos: Position,
+    ) -> Option<Type> {
+        match target {
+            #[cfg(not(feature = "no_index"))]
+            Some(Type::Array) => {
+                self.expect_type(Type::Int, _index, _index_pos);
+                None
+            }
+            #[cfg(not(feature = "no_object"))]
+            Some(Type::Map) => {
+                self.expect_type(Type::String, _index, _index_pos);
+                None
+            }
+            #[cfg(not(feature = "no_index"))]
+            Some(Type::String) => {
+                self.expect_type(Type::Int, _index, _index_pos);
+                Some(Type::Char)
+            }
+            Some(t) if !self.has_imports => {
+                #[cfg(not(feature = "no_index"))]
+                let found = self
+                    .find_native_fn(crate::engine::FN_IDX_GET, &[Some(t), _index])
+                    .is_some();
+                #[cfg(feature = "no_index")]
+                let found = false;
+
+                if !found {
+                    let err = TypeErrorType::IndexingType(self.type_name(t));
+                    self.error(err, pos);
+                }
+                None
+            }
+            _ => None,
+        }
+    }
+    /// Infer the types of the arguments to a function call, with an optional method call target.
+    fn infer_args(
+        &mut self,
+        target: Option<Option<Type>>,
+        x: &'a FnCallExpr,
+    ) -> StaticVec<Option<Type>> {
+        let mut args: StaticVec<_> = target.into_iter().collect();
+        args.extend(x.args.iter().map(|e| self.infer_expr(e)));
+        args.extend(x.constant_args.iter().map(|(v, _)| Type::of(v)));
+        args
+    }
+    /// Infer the type of a function or method call.
+    fn infer_fn_call(
+        &mut self,
+        x: &'a FnCallExpr,
+        args: &[Option<Type>],
+        is_method: bool,
+        pos: Position,
+    ) -> Option<Type> {
+        let name = x.name.as_str();
+
+        if x.namespace.is_some() {
+            return None;
+        }
+
+        // Keyword functions
+        match (name, args.len()) {
+            (KEYWORD_TYPE_OF, 1) => return Some(Type::String),
+            (KEYWORD_FN_PTR, 1) => return Some(Type::FnPtr),
+            (KEYWORD_FN_PTR_CURRY, _) if !args.is_empty() => return Some(Type::FnPtr),
+            (KEYWORD_IS_DEF_VAR, 1) => return Some(Type::Bool),
+            #[cfg(not(feature = "no_closure"))]
+            (crate::engine::KEYWORD_IS_SHARED, 1) => return Some(Type::Bool),
+            #[cfg(not(feature = "no_function"))]
+            (crate::engine::KEYWORD_IS_DEF_FN, 2) => return Some(Type::Bool),
+            (KEYWORD_PRINT, 1) | (KEYWORD_DEBUG, 1) | (KEYWORD_EVAL, 1) => return None,
+            (KEYWORD_FN_PTR_CALL, _) if !args.is_empty() => return None,
+            _ => (),
+        }
+
+        // Script-defined functions take precedence
+        #[cfg(not(feature = "no_function"))]
+        if x.hash.script.is_some() {
+            let num_params = if is_method {
+                args.len() - 1
+            } else {
+                args.len()
+            };
+
+            if let Some(&fn_def) = self.script_fns.get(&(name, num_params)) {
+                return self.infer_fn_return_type(fn_def);
+            }
+        }
+
+        if self.find_native_fn(name, args).is_some() {
+            return None;
+        }
+
+        // Built-in operators
+        if args.len() == 2 {
+            let mut found = false;
+            let mut result = None;
+
+            let samples = |t: Option<Type>| match t {
+                Some(t) => vec![t],
+                None => Type::ALL.to_vec(),
+            };
+
+            for &t1 in &samples(args[0]) {
+                for &t2 in &samples(args[1]) {
+                    if let Some(t) = self.builtin_op_type(name, t1, t2) {
+                        result = if found { join(result, t) } else { t };
+                        found = true;
+                    }
+                }
+            }
+
+            if found {
+                return if args.iter().all(Option::is_some) {
+                    result
+                } else {
+                    None
+                };
+            }
+        }
+
+        if self.has_imports {
+            return None;
+        }
+
+        // Object maps may hold function pointers that are called as methods
+        if is_method {
+            match args[0] {
+                #[cfg(not(feature = "no_object"))]
+                Some(Type::Map) => return None,
+                None => return None,
+                _ => (),
+            }
+        }
+
+        // Only report when certain that the call fails
+        let hash = calc_fn_hash(empty(), name, args.len());
+
+        if !self.native_fns.contains_key(&hash) {
+            // No function by this name and number of parameters at all
+            let sig = if !is_method && is_valid_identifier(name.chars()) {
+                let n = args.len();
+                format!(
+                    "{} with {} parameter{}",
+                    name,
+                    n,
+                    if n == 1 { "" } else { "s" }
+                )
+            } else {
+                self.make_signature(name, args)
+            };
+
+            self.error(TypeErrorType::FunctionNotFound(sig), pos);
+        } else if args.iter().all(Option::is_some) {
+            let sig = self.make_signature(name, args);
+            self.error(TypeErrorType::FunctionNotFound(sig), pos);
+        }
+
+        None
+    }
+    /// Infer the return type of a script-defined function from its body.
+    #[cfg(not(feature = "no_function"))]
+    fn infer_fn_return_type(&mut self, fn_def: &'a crate::ast::ScriptFnDef) -> Option<Type> {
+        let key = (fn_def.name.as_str(), fn_def.params.len());
+
+        if let Some(&typ) = self.fn_types.get(&key) {
+            return typ;
+        }
+
+        // Recursive calls are unknown
+        self.fn_types.insert(key, None);
+
+        let stack = mem::take(&mut self.stack);
+        let loop_exits = mem::take(&mut self.loop_exits);
+        let returns = mem::take(&mut self.returns);
+        let report = mem::replace(&mut self.report, false);
+
+        self.stack = fn_def
+            .params
+            .iter()
+            .map(|name| Var {
+                name: name.as_str().into(),
+                typ: None,
+                shared: false,
+            })
+            .collect();
+
+        let statements = &fn_def.body.statements;
+        let typ = self.check_block(statements);
+
+        // Join the types of all returned values
+        let returns = mem::replace(&mut self.returns, returns);
+
+        let typ = match statements.last() {
+            Some(Stmt::Return(_, _, _)) => returns.into_iter().reduce(join).flatten(),
+            _ => returns.into_iter().fold(typ, join),
+        };
+
+        self.stack = stack;
+        self.loop_exits = loop_exits;
+        self.report = report;
+
+        self.fn_types.insert(key, typ);
+        typ
+    }
+    /// Infer the type of an op-assignment.
+    fn infer_op_assignment(
+        &mut self,
+        op: &str,
+        lhs: Option<Type>,
+        rhs: Option<Type>,
+        pos: Position,
+    ) -> Option<Type> {
+        let (lhs_type, rhs_type) = match (lhs, rhs) {
+            (Some(l), Some(r)) => (l, r),
+            _ => return None,
+        };
+
+        if let Some(dynamic_first) = self.find_native_fn(op, &[lhs, rhs]) {
+            return if dynamic_first { None } else { lhs };
+        }
+
+        let (target, value) = (lhs_type.sample(), rhs_type.sample());
+        if get_builtin_op_assignment_fn(op, &target, &value).is_some() {
+            return lhs;
+        }
+
+        // Expand to `var = var op rhs`
+        let op = &op[..op.len() - 1];
+
+        if self.find_native_fn(op, &[lhs, rhs]).is_some() {
+            return None;
+        }
+
+        if let Some(t) = self.builtin_op_type(op, lhs_type, rhs_type) {
+            return t;
+        }
+
+        if !self.has_imports {
+            let sig = self.make_signature(op, &[lhs, rhs]);
+            self.error(TypeErrorType::FunctionNotFound(sig), pos);
+        }
+
+        None
+    }
+    /// Get the result type of a built-in binary operator, if it exists for the operand types.
+    ///
+    /// The result type is determined by applying the operator to sample values.
+    fn builtin_op_type(&self, op: &str, lhs: Type, rhs: Type) -> Option<Option<Type>> {
+        let mut args = [lhs.sample(), rhs.sample()];
+
+        get_builtin_binary_op_fn(op, &args[0], &args[1]).map(|func| {
+            let lib: &[&Module] = &[];
+            let ctx = (self.engine, op, lib).into();
+            let (first, second) = args.split_first_mut().unwrap();
+            func(ctx, &mut [first, &mut second[0]])
+                .ok()
+                .and_then(|v| Type::of(&v))
+        })
+    }
+    /// Find a native function that can be called with arguments of particular types.
+    ///
+    /// Arguments of unknown types match any parameter type.
+    /// Returns whether the first parameter of the function takes a [`Dynamic`] value.
+    fn find_native_fn(&self, name: &str, args: &[Option<Type>]) -> Option<bool> {
+        let hash = calc_fn_hash(empty(), name, args.len());
+
+        self.native_fns.get(&hash).and_then(|fns| {
+            fns.iter()
+                .find(|params| {
+                    params.iter().zip(args).all(|(&param, arg)| match arg {
+                        Some(t) => param == t.type_id() || param == TypeId::of::<Dynamic>(),
+                        None => true,
+                    })
+                })
+                .map(|params| params.first() == Some(&TypeId::of::<Dynamic>()))
+        })
+    }
+    /// Does a type have an iterator registered?
+    fn has_iterator(&self, typ: Type) -> bool {
+        let type_id = typ.type_id();
+
+        self.engine.global_namespace.contains_iter(type_id)
+            || self
+                .engine
+                .global_modules
+                .iter()
+                .any(|m| m.contains_iter(type_id))
+            || self
+                .engine
+                .global_sub_modules
+                .values()
+                .any(|m| m.contains_qualified_iter(type_id))
+    }
+    /// Make a function call signature for error messages.
+    fn make_signature(&self, name: &str, args: &[Option<Type>]) -> String {
+        let types: StaticVec<_> = args
+            .iter()
+            .map(|arg| match arg {
+                Some(t) => self.type_name(*t),
+                None => "?".to_string(),
+            })
+            .collect();
+
+        format!("{} ({})", name, types.join(", "))
+    }
+}
+
+/// Collect the parameter types of all functions in a [`Module`] that are accessible without
+/// qualifiers.
+fn collect_native_fns(
+    module: &Module,
+    include_internal: bool,
+    fns: &mut BTreeMap<u64, Vec<StaticVec<TypeId>>>,
+) {
+    module
+        .iter_fn()
+        .filter(|f| f.func.is_native())
+        .filter(|f| include_internal || f.namespace == FnNamespace::Global)
+        .for_each(|f| {
+            fns.entry(calc_fn_hash(empty(), &f.name, f.params))
+                .or_default()
+                .push(f.param_types.clone());
+        });
+    module
+        .iter_sub_modules()
+        .for_each(|(_, m)| collect_native_fns(&m, false, fns));
+}
+
+/// Collect the names of all variables assigned in a statements block.
+fn assigned_vars(statements: &[Stmt]) -> BTreeSet<String> {
+    let mut names = BTreeSet::new();
+
+    for stmt in statements {
+        stmt.walk(&mut Default::default(), &mut |path| {
+            if let ASTNode::Stmt(Stmt::Assignment(x, _)) = path.last().unwrap() {
+                if let Expr::Variable(v) = &x.0 {
+                    names.insert(v.2.name.to_string());
+                }
+            }
+            true
+        });
+    }
+
+    names
+}
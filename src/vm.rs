@@ -0,0 +1,1040 @@
+//! Module implementing the bytecode backend: a compiler flattening [`AST`] statements into a
+//! compact stack-based instruction list, and the virtual machine evaluating it.
+//!
+//! The bytecode keeps the pre-resolved variable slots and function hashes already in the
+//! [`AST`].  Nodes without a dedicated instruction (e.g. dot/index chains, `switch`, `try`,
+//! custom syntax, closures capturing the scope) are embedded as-is and delegated to the
+//! tree-walking evaluator, so the two backends always agree on semantics.
+
+use crate::ast::{Expr, FnCallExpr, FnCallHash, Ident, OpAssignment, ReturnType, Stmt};
+use crate::dynamic::AccessMode;
+use crate::engine::{Imports, State, KEYWORD_THIS};
+use crate::eval_stack::{first_var_arg, is_plain_call, BlockFrame};
+use crate::r#unsafe::unsafe_cast_var_name_to_lifetime;
+use crate::stdlib::{borrow::Cow, boxed::Box, string::ToString, vec::Vec};
+use crate::{
+    Dynamic, Engine, EvalAltResult, FnPtr, Identifier, ImmutableString, Module, Position,
+    RhaiResult, Scope, StaticVec,
+};
+
+#[cfg(not(feature = "no_function"))]
+use crate::engine::TailCall;
+
+#[cfg(not(feature = "no_index"))]
+use crate::Array;
+
+#[cfg(not(feature = "no_object"))]
+use crate::Map;
+
+/// Index of an instruction within a [`Chunk`], used as the target of jumps.
+type Label = usize;
+
+/// A normal function call, with all arguments except the first (if it is a variable)
+/// already on the stack.
+#[derive(Debug, Clone)]
+struct FnCall {
+    /// Function name.
+    name: Identifier,
+    /// Pre-calculated hash.
+    hash: FnCallHash,
+    /// Number of arguments on the stack.
+    num_args: usize,
+    /// Arguments that are constants, following those on the stack.
+    constant_args: StaticVec<(Dynamic, Position)>,
+    /// First argument, if it is a variable to be passed by reference (method-call style).
+    first: Option<Expr>,
+    /// Source text of the function call, captured for assertions only.
+    source_text: Option<ImmutableString>,
+    /// Position of the function call.
+    pos: Position,
+}
+
+/// A bytecode instruction.
+///
+/// Every instruction that evaluates an [`Expr`] or [`Stmt`] node counts exactly the same
+/// operations as the tree-walking evaluator does for that node.
+#[derive(Debug, Clone)]
+enum Instr {
+    /// Count one operation for an expression.
+    Ops(Position),
+    /// Count one operation for a statement and record its coverage.
+    Stmt(Position),
+    /// Push `()`.
+    Unit,
+    /// Discard the top of the stack.
+    Pop,
+    /// Flatten the top of the stack.
+    Flatten,
+    /// Count one operation and push a constant.
+    Const(Dynamic, Position),
+    /// Count one operation and push a copy of `this`.
+    This(Position),
+    /// Count one operation and push a copy of a variable.
+    Var(Box<Expr>),
+    /// Call a function with arguments on the stack, pushing the result.
+    Call(Box<FnCall>),
+    /// Call a function in tail position with arguments on the stack, returning its result from
+    /// the function.
+    TailCall(Box<FnCall>, Position),
+    /// Collect values on the stack into an array.
+    #[cfg(not(feature = "no_index"))]
+    Array(usize, Position),
+    /// Fill values on the stack into a copy of an object map template.
+    #[cfg(not(feature = "no_object"))]
+    Map(Box<(StaticVec<Identifier>, Map)>, Position),
+    /// Test the left-hand side of `&&` (if `true`) or `||` (if `false`), short-circuiting to
+    /// a [`Label`].
+    Logic(bool, Position, Position, Label),
+    /// Convert the top of the stack into a `bool`.
+    ToBool(Position),
+    /// Evaluate an expression with the tree-walking evaluator.
+    Eval(Box<Expr>),
+    /// Evaluate a statement with the tree-walking evaluator, in tail position (if `true`).
+    Exec(Box<Stmt>, bool),
+    /// Evaluate an `import` statement with the tree-walking evaluator.
+    #[cfg(not(feature = "no_module"))]
+    Import(Box<Stmt>),
+    /// Jump unconditionally.
+    Jump(Label),
+    /// Pop the guard of an `if` statement, jumping to the `else` branch if it is `false`.
+    Branch(Position, Position, Label),
+    /// Pop a loop condition, jumping if it equals the expected value.
+    Test(bool, Position, Label),
+    /// Enter a statements block.
+    EnterBlock,
+    /// Exit a statements block, restoring the previous state.
+    ExitBlock,
+    /// Pop a value into a new variable.
+    Let(Box<(Identifier, AccessMode, bool)>),
+    /// Pop a value and assign it to a variable.
+    Assign(Box<(Expr, Option<OpAssignment>, Position, Position)>),
+    /// Enter a `while` or `do` loop, with targets for `break` and `continue`.
+    EnterLoop(Label, Label),
+    /// Pop the object to iterate and enter a `for` loop, with targets for `break` and `continue`.
+    EnterFor(Box<(Identifier, Position)>, Label, Label),
+    /// Advance the current `for` loop, jumping when it is finished.
+    ForNext(Position, Label),
+    /// Exit the current loop.
+    ExitLoop,
+    /// Break out of (if `true`) or continue the current loop.
+    Break(bool, Position),
+    /// Return from the function (or throw an exception, if `true`), with or without a value.
+    Return(bool, bool, Position),
+}
+
+/// _(INTERNALS)_ Bytecode compiled from a list of statements.
+/// Exported under the `internals` feature only.
+///
+/// # Volatile Data Structure
+///
+/// This type is volatile and may change.
+#[derive(Debug, Clone, Default)]
+pub struct Chunk {
+    code: Box<[Instr]>,
+}
+
+impl Chunk {
+    /// Compile a list of statements into bytecode.
+    pub(crate) fn compile(statements: &[Stmt]) -> Self {
+        Self::compile_statements(statements, false)
+    }
+    /// Compile the body of a script-defined function into bytecode.
+    #[cfg(not(feature = "no_function"))]
+    pub(crate) fn compile_fn(statements: &[Stmt]) -> Self {
+        Self::compile_statements(statements, true)
+    }
+    /// Compile a list of statements into bytecode, with the last one in tail position (if `true`).
+    fn compile_statements(statements: &[Stmt], tail: bool) -> Self {
+        let mut compiler = Compiler { code: Vec::new() };
+
+        if !statements.is_empty() {
+            compiler.statements(statements, tail);
+        }
+
+        Self {
+            code: compiler.code.into(),
+        }
+    }
+    /// Is the bytecode empty?
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.code.is_empty()
+    }
+}
+
+/// Compiler from [`AST`] nodes into bytecode.
+struct Compiler {
+    code: Vec<Instr>,
+}
+
+impl Compiler {
+    /// Append an instruction, returning its [`Label`].
+    #[inline(always)]
+    fn emit(&mut self, instr: Instr) -> Label {
+        self.code.push(instr);
+        self.code.len() - 1
+    }
+    /// [`Label`] of the next instruction.
+    #[inline(always)]
+    fn here(&self) -> Label {
+        self.code.len()
+    }
+    /// Point a forward jump to the next instruction.
+    fn patch(&mut self, at: Label) {
+        let target = self.here();
+        self.patch_to(at, target, target);
+    }
+    /// Point the jump targets of an instruction.
+    fn patch_to(&mut self, at: Label, target: Label, other: Label) {
+        match &mut self.code[at] {
+            Instr::Logic(_, _, _, label)
+            | Instr::Jump(label)
+            | Instr::Branch(_, _, label)
+            | Instr::Test(_, _, label)
+            | Instr::ForNext(_, label) => *label = target,
+            Instr::EnterLoop(brk, cont) | Instr::EnterFor(_, brk, cont) => {
+                *brk = target;
+                *cont = other;
+            }
+            instr => unreachable!("not a jump instruction: {:?}", instr),
+        }
+    }
+    /// Compile statements, leaving the value of the last one on the stack.
+    /// The last statement is in tail position if the statements are.
+    fn statements(&mut self, statements: &[Stmt], tail: bool) {
+        if statements.is_empty() {
+            self.emit(Instr::Unit);
+            return;
+        }
+
+        let last = statements.len() - 1;
+
+        for (i, stmt) in statements.iter().enumerate() {
+            if i > 0 {
+                self.emit(Instr::Pop);
+            }
+            self.stmt(stmt, tail && i == last);
+        }
+    }
+    /// Compile a statements block in its own scope, leaving its value on the stack.
+    fn block(&mut self, statements: &[Stmt], tail: bool) {
+        if statements.is_empty() {
+            self.emit(Instr::Unit);
+        } else {
+            self.emit(Instr::EnterBlock);
+            self.statements(statements, tail);
+            self.emit(Instr::ExitBlock);
+        }
+    }
+    /// Compile a statement, leaving its value on the stack.
+    /// A function call in tail position (if `tail` is `true`) returns from the function.
+    fn stmt(&mut self, stmt: &Stmt, tail: bool) {
+        let pos = stmt.position();
+
+        match stmt {
+            Stmt::Noop(_) => {
+                self.emit(Instr::Stmt(pos));
+                self.emit(Instr::Unit);
+            }
+
+            Stmt::Expr(Expr::FnCall(x, _)) if tail && is_plain_call(x) => {
+                self.emit(Instr::Stmt(pos));
+                self.call(x, pos, Some(pos));
+            }
+
+            Stmt::Expr(expr) => {
+                self.emit(Instr::Stmt(pos));
+                self.expr(expr);
+                self.emit(Instr::Flatten);
+            }
+
+            Stmt::Assignment(x, op_pos) if x.0.get_variable_access(false).is_some() => {
+                let (lhs_expr, op_info, rhs_expr) = x.as_ref();
+                self.emit(Instr::Stmt(pos));
+                self.expr(rhs_expr);
+                self.emit(Instr::Assign(Box::new((
+                    lhs_expr.clone(),
+                    op_info.clone(),
+                    *op_pos,
+                    rhs_expr.position(),
+                ))));
+            }
+
+            Stmt::Block(statements, _) => {
+                self.emit(Instr::Stmt(pos));
+                self.block(statements, tail);
+            }
+
+            Stmt::If(expr, x, if_pos) => {
+                let (if_block, else_block) = x.as_ref();
+                self.emit(Instr::Stmt(pos));
+                self.expr(expr);
+                let branch = self.emit(Instr::Branch(*if_pos, expr.position(), 0));
+                self.block(&if_block.statements, tail);
+                let jump = self.emit(Instr::Jump(0));
+                self.patch(branch);
+                self.block(&else_block.statements, tail);
+                self.patch(jump);
+            }
+
+            Stmt::While(expr, body, _) => {
+                self.emit(Instr::Stmt(pos));
+                let enter = self.emit(Instr::EnterLoop(0, 0));
+                let top = self.here();
+                let test = if !expr.is_unit() {
+                    self.expr(expr);
+                    Some(self.emit(Instr::Test(false, expr.position(), 0)))
+                } else {
+                    None
+                };
+                if !body.statements.is_empty() {
+                    self.block(&body.statements, false);
+                    self.emit(Instr::Pop);
+                }
+                self.emit(Instr::Jump(top));
+                let exit = self.here();
+                if let Some(test) = test {
+                    self.patch(test);
+                }
+                self.patch_to(enter, exit, top);
+                self.emit(Instr::ExitLoop);
+            }
+
+            Stmt::Do(body, expr, is_while, _) => {
+                self.emit(Instr::Stmt(pos));
+                let enter = self.emit(Instr::EnterLoop(0, 0));
+                let top = self.here();
+                if !body.statements.is_empty() {
+                    self.block(&body.statements, false);
+                    self.emit(Instr::Pop);
+                }
+                self.expr(expr);
+                self.emit(Instr::Test(*is_while, expr.position(), top));
+                let exit = self.here();
+                self.patch_to(enter, exit, top);
+                self.emit(Instr::ExitLoop);
+            }
+
+            Stmt::For(expr, x, _) => {
+                let (Ident { name, .. }, body) = x.as_ref();
+                self.emit(Instr::Stmt(pos));
+                self.expr(expr);
+                let enter = self.emit(Instr::EnterFor(
+                    Box::new((name.clone(), expr.position())),
+                    0,
+                    0,
+                ));
+                let next = self.emit(Instr::ForNext(body.pos, 0));
+                if !body.statements.is_empty() {
+                    self.block(&body.statements, false);
+                    self.emit(Instr::Pop);
+                }
+                self.emit(Instr::Jump(next));
+                let exit = self.here();
+                self.patch(next);
+                self.patch_to(enter, exit, next);
+                self.emit(Instr::ExitLoop);
+            }
+
+            Stmt::Continue(_) | Stmt::Break(_) => {
+                self.emit(Instr::Stmt(pos));
+                self.emit(Instr::Break(matches!(stmt, Stmt::Break(_)), pos));
+            }
+
+            Stmt::Return(ReturnType::Return, Some(Expr::FnCall(x, call_pos)), _)
+                if is_plain_call(x) =>
+            {
+                self.emit(Instr::Stmt(pos));
+                self.call(x, *call_pos, Some(pos));
+            }
+
+            Stmt::Return(return_type, expr, _) => {
+                self.emit(Instr::Stmt(pos));
+                if let Some(expr) = expr {
+                    self.expr(expr);
+                }
+                let is_exception = matches!(return_type, ReturnType::Exception);
+                self.emit(Instr::Return(is_exception, expr.is_some(), pos));
+            }
+
+            Stmt::Let(expr, x, export, _) | Stmt::Const(expr, x, export, _) => {
+                let access = match stmt {
+                    Stmt::Const(_, _, _, _) => AccessMode::ReadOnly,
+                    _ => AccessMode::ReadWrite,
+                };
+                self.emit(Instr::Stmt(pos));
+                self.expr(expr);
+                self.emit(Instr::Let(Box::new((x.name.clone(), access, *export))));
+            }
+
+            #[cfg(not(feature = "no_module"))]
+            Stmt::Import(_, _, _) => {
+                self.emit(Instr::Import(Box::new(stmt.clone())));
+            }
+
+            _ => {
+                self.emit(Instr::Exec(Box::new(stmt.clone()), tail));
+            }
+        }
+    }
+    /// Compile an expression, leaving its value on the stack.
+    fn expr(&mut self, expr: &Expr) {
+        let pos = expr.position();
+
+        match expr {
+            Expr::DynamicConstant(x, _) => {
+                self.emit(Instr::Const(x.as_ref().clone(), pos));
+            }
+            Expr::IntegerConstant(x, _) => {
+                self.emit(Instr::Const((*x).into(), pos));
+            }
+            #[cfg(not(feature = "no_float"))]
+            Expr::FloatConstant(x, _) => {
+                self.emit(Instr::Const((*x).into(), pos));
+            }
+            Expr::StringConstant(x, _) => {
+                self.emit(Instr::Const(x.clone().into(), pos));
+            }
+            Expr::CharConstant(x, _) => {
+                self.emit(Instr::Const((*x).into(), pos));
+            }
+            Expr::FnPointer(x, _) => {
+                let fn_ptr = FnPtr::new_unchecked(x.clone(), Default::default());
+                self.emit(Instr::Const(fn_ptr.into(), pos));
+            }
+            Expr::BoolConstant(x, _) => {
+                self.emit(Instr::Const((*x).into(), pos));
+            }
+            Expr::Unit(_) => {
+                self.emit(Instr::Const(Dynamic::UNIT, pos));
+            }
+
+            Expr::Variable(x) if (x.2).name == KEYWORD_THIS => {
+                self.emit(Instr::This(pos));
+            }
+            Expr::Variable(_) => {
+                self.emit(Instr::Var(Box::new(expr.clone())));
+            }
+
+            Expr::Stmt(x) => {
+                self.emit(Instr::Ops(pos));
+                self.block(&x.statements, false);
+            }
+
+            #[cfg(not(feature = "no_index"))]
+            Expr::Array(x, _) => {
+                self.emit(Instr::Ops(pos));
+                x.iter().for_each(|item| self.expr(item));
+                self.emit(Instr::Array(x.len(), pos));
+            }
+
+            #[cfg(not(feature = "no_object"))]
+            Expr::Map(x, _) => {
+                self.emit(Instr::Ops(pos));
+                x.0.iter().for_each(|(_, value)| self.expr(value));
+                let keys = x.0.iter().map(|(key, _)| key.name.clone()).collect();
+                self.emit(Instr::Map(Box::new((keys, x.1.clone())), pos));
+            }
+
+            Expr::FnCall(x, _) if is_plain_call(x) => self.call(x, pos, None),
+
+            Expr::And(x, op_pos) | Expr::Or(x, op_pos) => {
+                let is_and = matches!(expr, Expr::And(_, _));
+                self.emit(Instr::Ops(pos));
+                self.expr(&x.lhs);
+                let logic = self.emit(Instr::Logic(is_and, *op_pos, x.lhs.position(), 0));
+                self.expr(&x.rhs);
+                self.emit(Instr::ToBool(x.rhs.position()));
+                self.patch(logic);
+            }
+
+            _ => {
+                self.emit(Instr::Eval(Box::new(expr.clone())));
+            }
+        }
+    }
+    /// Compile a function call, leaving its value on the stack, or returning it from the function
+    /// at the specified [`Position`] if the call is in tail position.
+    fn call(&mut self, x: &FnCallExpr, pos: Position, tail: Option<Position>) {
+        self.emit(Instr::Ops(pos));
+
+        // If the first argument is a variable, pass it by reference
+        let first = first_var_arg(x).cloned();
+        let skip = if first.is_some() { 1 } else { 0 };
+        x.args.iter().skip(skip).for_each(|arg| self.expr(arg));
+
+        let call = Box::new(FnCall {
+            name: x.name.clone(),
+            hash: x.hash,
+            num_args: x.args.len() - skip,
+            constant_args: x.constant_args.iter().cloned().collect(),
+            first,
+            source_text: x.source_text.clone(),
+            pos,
+        });
+
+        match tail {
+            Some(return_pos) => self.emit(Instr::TailCall(call, return_pos)),
+            None => self.emit(Instr::Call(call)),
+        };
+    }
+}
+
+/// A frame on the control stack of the virtual machine.
+enum Frame {
+    /// A statements block.
+    Block(BlockFrame),
+    /// A loop.
+    Loop {
+        /// Target of `break`.
+        brk: Label,
+        /// Target of `continue`.
+        cont: Label,
+        /// Height of the value stack on entry.
+        stack_len: usize,
+        /// Iterator and index of the loop variable, for `for` loops.
+        iter: Option<(Box<dyn Iterator<Item = Dynamic>>, usize)>,
+    },
+}
+
+impl Frame {
+    /// Create a new block frame.
+    #[inline(always)]
+    fn block(scope: &Scope, mods: &Imports, state: &mut State, restore: bool) -> Self {
+        Self::Block(BlockFrame::new(scope, mods, state, restore))
+    }
+    /// Exit the frame, restoring the previous state.
+    fn exit(self, scope: &mut Scope, mods: &mut Imports, state: &mut State) {
+        match self {
+            Self::Block(block) => block.exit(scope, mods, state),
+            Self::Loop {
+                iter: Some((_, index)),
+                ..
+            } => {
+                state.scope_level -= 1;
+                scope.rewind(index);
+            }
+            Self::Loop { iter: None, .. } => (),
+        }
+    }
+}
+
+/// State of the virtual machine running a [`Chunk`].
+struct Vm {
+    /// Value stack.
+    stack: Vec<Dynamic>,
+    /// Control stack.
+    frames: Vec<Frame>,
+}
+
+impl Vm {
+    #[inline(always)]
+    fn push(&mut self, value: Dynamic) {
+        self.stack.push(value);
+    }
+    #[inline(always)]
+    fn pop(&mut self) -> Dynamic {
+        self.stack.pop().expect("value stack should not be empty")
+    }
+    /// Exit all frames above `len`.
+    fn unwind(&mut self, len: usize, scope: &mut Scope, mods: &mut Imports, state: &mut State) {
+        while self.frames.len() > len {
+            self.frames.pop().unwrap().exit(scope, mods, state);
+        }
+    }
+}
+
+impl Engine {
+    /// Evaluate a [`Chunk`] of bytecode.
+    ///
+    /// This is the bytecode counterpart of [`eval_stmt_block`][Engine::eval_stmt_block].
+    pub(crate) fn eval_chunk(
+        &self,
+        scope: &mut Scope,
+        mods: &mut Imports,
+        state: &mut State,
+        lib: &[&Module],
+        this_ptr: &mut Option<&mut Dynamic>,
+        chunk: &Chunk,
+        restore_prev_state: bool,
+        level: usize,
+    ) -> RhaiResult {
+        if chunk.is_empty() {
+            return Ok(Dynamic::UNIT);
+        }
+
+        let code = &chunk.code;
+        let vm = &mut Vm {
+            stack: Vec::new(),
+            frames: Vec::new(),
+        };
+        vm.frames
+            .push(Frame::block(scope, mods, state, restore_prev_state));
+
+        let mut ip = 0;
+
+        while ip < code.len() {
+            let instr = &code[ip];
+            ip += 1;
+
+            match self.exec_instr(scope, mods, state, lib, this_ptr, vm, instr, level) {
+                Ok(None) => (),
+                Ok(Some(target)) => ip = target,
+                Err(err) => {
+                    // Break out of (or continue) the innermost loop, if any
+                    if let EvalAltResult::LoopBreak(is_break, _) = *err {
+                        let found = vm
+                            .frames
+                            .iter()
+                            .rposition(|frame| matches!(frame, Frame::Loop { .. }));
+
+                        if let Some(index) = found {
+                            vm.unwind(index + 1, scope, mods, state);
+
+                            if let Frame::Loop {
+                                brk,
+                                cont,
+                                stack_len,
+                                ..
+                            } = vm.frames[index]
+                            {
+                                vm.stack.truncate(stack_len);
+                                ip = if is_break { brk } else { cont };
+                            }
+                            continue;
+                        }
+                    }
+
+                    vm.unwind(0, scope, mods, state);
+                    return Err(err);
+                }
+            }
+        }
+
+        vm.unwind(0, scope, mods, state);
+        Ok(vm.pop())
+    }
+
+    /// Call a function with arguments on the stack.
+    fn exec_call(
+        &self,
+        scope: &mut Scope,
+        mods: &mut Imports,
+        state: &mut State,
+        lib: &[&Module],
+        this_ptr: &mut Option<&mut Dynamic>,
+        vm: &mut Vm,
+        call: &FnCall,
+        level: usize,
+    ) -> RhaiResult {
+        let FnCall {
+            name,
+            hash,
+            num_args,
+            constant_args,
+            first,
+            source_text,
+            pos,
+        } = call;
+
+        let arg_values = vm
+            .stack
+            .drain(vm.stack.len() - num_args..)
+            .map(Dynamic::flatten)
+            .chain(constant_args.iter().map(|(v, _)| v.clone()))
+            .collect();
+
+        let result = self.make_function_call_with_values(
+            scope,
+            mods,
+            state,
+            lib,
+            this_ptr,
+            name,
+            *hash,
+            first.as_ref(),
+            arg_values,
+            *pos,
+            level,
+        );
+
+        let result = match source_text {
+            // Fill in the source text of a failed assertion
+            Some(text) => result.map_err(|mut err| {
+                if let EvalAltResult::ErrorAssertion(_, _, ref mut expr, _) = *err {
+                    if expr.is_empty() {
+                        *expr = text.to_string();
+                    }
+                }
+                err
+            }),
+            None => result,
+        };
+
+        self.check_data_size(result, *pos)
+    }
+
+    /// Execute a single instruction, returning the target of a jump, if any.
+    fn exec_instr(
+        &self,
+        scope: &mut Scope,
+        mods: &mut Imports,
+        state: &mut State,
+        lib: &[&Module],
+        this_ptr: &mut Option<&mut Dynamic>,
+        vm: &mut Vm,
+        instr: &Instr,
+        level: usize,
+    ) -> Result<Option<Label>, Box<EvalAltResult>> {
+        match instr {
+            Instr::Ops(pos) => self.inc_operations(state, *pos)?,
+
+            Instr::Stmt(pos) => {
+                self.inc_operations(state, *pos)?;
+
+                if let Some(ref coverage) = self.coverage {
+                    coverage.hit_statement(state.coverage_source(), *pos);
+                }
+            }
+
+            Instr::Unit => vm.push(Dynamic::UNIT),
+
+            Instr::Pop => {
+                vm.pop();
+            }
+
+            Instr::Flatten => {
+                let value = vm.pop().flatten();
+                vm.push(value);
+            }
+
+            Instr::Const(value, pos) => {
+                self.inc_operations(state, *pos)?;
+                vm.push(value.clone());
+            }
+
+            Instr::This(pos) => {
+                self.inc_operations(state, *pos)?;
+                let value = this_ptr
+                    .as_deref()
+                    .cloned()
+                    .ok_or_else(|| EvalAltResult::ErrorUnboundThis(*pos))?;
+                vm.push(value);
+            }
+
+            Instr::Var(expr) => {
+                let pos = expr.position();
+                self.inc_operations(state, pos)?;
+                let value = self
+                    .search_namespace(scope, mods, state, lib, this_ptr, expr)?
+                    .0
+                    .take_or_clone();
+                vm.push(self.check_data_size(Ok(value), pos)?);
+            }
+
+            Instr::Call(x) => {
+                let value = self.exec_call(scope, mods, state, lib, this_ptr, vm, x, level)?;
+                vm.push(value);
+            }
+
+            Instr::TailCall(x, return_pos) => {
+                // Leave a call to a script-defined function pending for the caller
+                #[cfg(not(feature = "no_function"))]
+                if let Some(func) = self.resolve_tail_call(mods, state, lib, &x.name, x.hash) {
+                    let mut args: StaticVec<_> = vm
+                        .stack
+                        .drain(vm.stack.len() - x.num_args..)
+                        .map(Dynamic::flatten)
+                        .chain(x.constant_args.iter().map(|(v, _)| v.clone()))
+                        .collect();
+
+                    if let Some(ref first) = x.first {
+                        let (target, var_pos) =
+                            self.search_namespace(scope, mods, state, lib, this_ptr, first)?;
+                        self.inc_operations(state, var_pos)?;
+                        args.insert(0, target.take_or_clone().flatten());
+                    }
+
+                    state.set_tail_call(TailCall {
+                        func,
+                        args,
+                        pos: x.pos,
+                        level,
+                    });
+                    return EvalAltResult::Return(Dynamic::UNIT, *return_pos).into();
+                }
+
+                let value = self.exec_call(scope, mods, state, lib, this_ptr, vm, x, level)?;
+                return EvalAltResult::Return(value.flatten(), *return_pos).into();
+            }
+
+            #[cfg(not(feature = "no_index"))]
+            Instr::Array(len, pos) => {
+                let arr: Array = vm
+                    .stack
+                    .drain(vm.stack.len() - len..)
+                    .map(Dynamic::flatten)
+                    .collect();
+                vm.push(self.check_data_size(Ok(arr.into()), *pos)?);
+            }
+
+            #[cfg(not(feature = "no_object"))]
+            Instr::Map(x, pos) => {
+                let (keys, template) = x.as_ref();
+                let mut map = template.clone();
+                let values = vm.stack.drain(vm.stack.len() - keys.len()..);
+                keys.iter().zip(values).for_each(|(key, value)| {
+                    *map.get_mut(key.as_str()).unwrap() = value.flatten();
+                });
+                vm.push(self.check_data_size(Ok(map.into()), *pos)?);
+            }
+
+            Instr::Logic(is_and, pos, lhs_pos, end) => {
+                let lhs = vm
+                    .pop()
+                    .as_bool()
+                    .map_err(|err| self.make_type_mismatch_err::<bool>(err, *lhs_pos))?;
+
+                if let Some(ref coverage) = self.coverage {
+                    let branch = if *is_and { lhs } else { !lhs };
+                    coverage.hit_branch(state.coverage_source(), *pos, branch as usize);
+                }
+
+                // Short-circuit
+                if lhs != *is_and {
+                    vm.push(lhs.into());
+                    return Ok(Some(*end));
+                }
+            }
+
+            Instr::ToBool(pos) => {
+                let value = vm
+                    .pop()
+                    .as_bool()
+                    .map_err(|err| self.make_type_mismatch_err::<bool>(err, *pos))?;
+                vm.push(value.into());
+            }
+
+            Instr::Eval(expr) => {
+                let value = self.eval_expr(scope, mods, state, lib, this_ptr, expr, level)?;
+                vm.push(value);
+            }
+
+            Instr::Exec(stmt, tail) => {
+                state.in_tail = *tail;
+                let value = self.eval_stmt(scope, mods, state, lib, this_ptr, stmt, level)?;
+                vm.push(value);
+            }
+
+            #[cfg(not(feature = "no_module"))]
+            Instr::Import(stmt) => {
+                let mods_len = mods.len();
+                let value = self.eval_stmt(scope, mods, state, lib, this_ptr, stmt, level)?;
+
+                vm.frames
+                    .iter_mut()
+                    .rev()
+                    .find_map(|frame| match frame {
+                        Frame::Block(block) => Some(block),
+                        Frame::Loop { .. } => None,
+                    })
+                    .expect("import should be in a block")
+                    .imported(mods, mods_len, state);
+
+                vm.push(value);
+            }
+
+            Instr::Jump(target) => return Ok(Some(*target)),
+
+            Instr::Branch(pos, cond_pos, otherwise) => {
+                let guard_val = vm
+                    .pop()
+                    .as_bool()
+                    .map_err(|err| self.make_type_mismatch_err::<bool>(err, *cond_pos))?;
+
+                if let Some(ref coverage) = self.coverage {
+                    let source = state.coverage_source();
+                    coverage.hit_branch(source, *pos, !guard_val as usize);
+                }
+
+                if !guard_val {
+                    return Ok(Some(*otherwise));
+                }
+            }
+
+            Instr::Test(expected, cond_pos, target) => {
+                let condition = vm
+                    .pop()
+                    .as_bool()
+                    .map_err(|err| self.make_type_mismatch_err::<bool>(err, *cond_pos))?;
+
+                if condition == *expected {
+                    return Ok(Some(*target));
+                }
+            }
+
+            Instr::EnterBlock => vm.frames.push(Frame::block(scope, mods, state, true)),
+
+            Instr::ExitBlock => vm.frames.pop().unwrap().exit(scope, mods, state),
+
+            Instr::Let(x) => {
+                let (name, entry_type, export) = x.as_ref();
+                let value = vm.pop().flatten();
+
+                let (var_name, _alias): (Cow<'_, str>, _) = if state.is_global() {
+                    (
+                        name.to_string().into(),
+                        if *export { Some(name.clone()) } else { None },
+                    )
+                } else if *export {
+                    unreachable!("exported variable not on global level");
+                } else {
+                    (unsafe_cast_var_name_to_lifetime(name).into(), None)
+                };
+
+                scope.push_dynamic_value(var_name, *entry_type, value);
+
+                #[cfg(not(feature = "no_module"))]
+                if let Some(alias) = _alias {
+                    scope.add_entry_alias(scope.len() - 1, alias);
+                }
+                vm.push(Dynamic::UNIT);
+            }
+
+            Instr::Assign(x) => {
+                let (lhs_expr, op_info, op_pos, rhs_pos) = x.as_ref();
+                let rhs_val = vm.pop().flatten();
+                let (lhs_ptr, pos) =
+                    self.search_namespace(scope, mods, state, lib, this_ptr, lhs_expr)?;
+
+                if !lhs_ptr.is_ref() {
+                    return EvalAltResult::ErrorAssignmentToConstant(
+                        lhs_expr.get_variable_access(false).unwrap().to_string(),
+                        pos,
+                    )
+                    .into();
+                }
+
+                self.inc_operations(state, pos)?;
+
+                if lhs_ptr.as_ref().is_read_only() {
+                    // Assignment to constant variable
+                    return EvalAltResult::ErrorAssignmentToConstant(
+                        lhs_expr.get_variable_access(false).unwrap().to_string(),
+                        pos,
+                    )
+                    .into();
+                }
+
+                self.eval_op_assignment(
+                    mods,
+                    state,
+                    lib,
+                    op_info.clone(),
+                    *op_pos,
+                    lhs_ptr,
+                    rhs_val,
+                    *rhs_pos,
+                )?;
+                vm.push(Dynamic::UNIT);
+            }
+
+            Instr::EnterLoop(brk, cont) => vm.frames.push(Frame::Loop {
+                brk: *brk,
+                cont: *cont,
+                stack_len: vm.stack.len(),
+                iter: None,
+            }),
+
+            Instr::EnterFor(x, brk, cont) => {
+                let (name, expr_pos) = x.as_ref();
+                let iter_obj = vm.pop().flatten();
+                let iter_type = iter_obj.type_id();
+
+                // Same search order as the tree-walking evaluator
+                let func = self
+                    .global_namespace
+                    .get_iter(iter_type)
+                    .or_else(|| {
+                        self.global_modules
+                            .iter()
+                            .find_map(|m| m.get_iter(iter_type))
+                    })
+                    .or_else(|| mods.get_iter(iter_type))
+                    .or_else(|| {
+                        self.global_sub_modules
+                            .values()
+                            .find_map(|m| m.get_qualified_iter(iter_type))
+                    })
+                    .ok_or_else(|| EvalAltResult::ErrorFor(*expr_pos))?;
+
+                // Add the loop variable
+                let var_name: Cow<'_, str> = if state.is_global() {
+                    name.to_string().into()
+                } else {
+                    unsafe_cast_var_name_to_lifetime(name).into()
+                };
+                scope.push(var_name, ());
+                state.scope_level += 1;
+
+                vm.frames.push(Frame::Loop {
+                    brk: *brk,
+                    cont: *cont,
+                    stack_len: vm.stack.len(),
+                    iter: Some((func(iter_obj), scope.len() - 1)),
+                });
+            }
+
+            Instr::ForNext(pos, end) => {
+                let (iter, index) = match vm.frames.last_mut() {
+                    Some(Frame::Loop {
+                        iter: Some((iter, index)),
+                        ..
+                    }) => (iter, *index),
+                    _ => unreachable!("not in a for loop"),
+                };
+
+                match iter.next() {
+                    Some(iter_value) => {
+                        let loop_var = scope.get_mut_by_index(index);
+                        let value = iter_value.flatten();
+
+                        if cfg!(not(feature = "no_closure")) && loop_var.is_shared() {
+                            *loop_var.write_lock().unwrap() = value;
+                        } else {
+                            *loop_var = value;
+                        }
+
+                        self.inc_operations(state, *pos)?;
+                    }
+                    None => return Ok(Some(*end)),
+                }
+            }
+
+            Instr::ExitLoop => {
+                vm.frames.pop().unwrap().exit(scope, mods, state);
+                vm.push(Dynamic::UNIT);
+            }
+
+            Instr::Break(is_break, pos) => {
+                return EvalAltResult::LoopBreak(*is_break, *pos).into();
+            }
+
+            Instr::Return(is_exception, has_value, pos) => {
+                let value = if *has_value {
+                    vm.pop().flatten()
+                } else {
+                    Dynamic::UNIT
+                };
+
+                return if *is_exception {
+                    EvalAltResult::ErrorRuntime(value, *pos).into()
+                } else {
+                    EvalAltResult::Return(value, *pos).into()
+                };
+            }
+        }
+
+        Ok(None)
+    }
+}
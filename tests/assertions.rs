@@ -0,0 +1,82 @@
+use rhai::{Engine, EvalAltResult};
+
+#[cfg(not(feature = "no_optimize"))]
+use rhai::{OptimizationLevel, INT};
+
+#[test]
+fn test_assertions() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    engine.eval::<()>("assert(true); assert(1 < 2, \"ordering\")")?;
+    engine.eval::<()>("assert_eq(40 + 2, 42); assert_ne(1, \"1\")")?;
+
+    #[cfg(not(feature = "no_index"))]
+    engine.eval::<()>("assert_ne([1, 2], [2, 1])")?;
+
+    assert!(matches!(
+        *engine.eval::<()>("assert(1 > 2, \"boom\")").expect_err("expects error"),
+        EvalAltResult::ErrorAssertion(m, None, e, _) if m == "boom" && e == "assert(1 > 2, \"boom\")"
+    ));
+
+    assert!(matches!(
+        *engine
+            .eval::<()>("let x = 1;\nassert_eq(x + 1, 3);")
+            .expect_err("expects error"),
+        EvalAltResult::ErrorAssertion(_, Some(ops), e, pos)
+            if ops.0 == "2" && ops.1 == "3" && e == "assert_eq(x + 1, 3)" && pos.line() == Some(2)
+    ));
+
+    assert!(matches!(
+        *engine.eval::<()>(r#"assert_ne("a", "a")"#).expect_err("expects error"),
+        EvalAltResult::ErrorAssertion(_, Some(ops), _, _) if ops.0 == r#""a""# && ops.1 == r#""a""#
+    ));
+
+    #[cfg(not(feature = "no_object"))]
+    assert_eq!(
+        engine.eval::<String>(
+            "try { assert_eq(1, 2); return \"\"; } catch (e) { return e.left + e.right; }"
+        )?,
+        "12"
+    );
+
+    Ok(())
+}
+
+#[cfg(not(feature = "no_float"))]
+#[test]
+fn test_assert_approx() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    engine.eval::<()>("assert_approx(1.0, 1.05, 0.1)")?;
+
+    assert!(matches!(
+        *engine.eval::<()>("assert_approx(1.0, 1.5, 0.1)").expect_err("expects error"),
+        EvalAltResult::ErrorAssertion(m, Some(_), _, _) if m.contains("0.1")
+    ));
+
+    Ok(())
+}
+
+#[cfg(not(feature = "no_optimize"))]
+#[test]
+fn test_assertions_strip() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    engine.set_strip_assertions(true);
+
+    for level in [OptimizationLevel::Simple, OptimizationLevel::Full] {
+        engine.set_optimization_level(level);
+
+        engine.eval::<()>("assert(false); assert_eq(1, 2)")?;
+        assert_eq!(
+            engine.eval::<INT>("let x = 40; assert_ne(x, 40); x + 2")?,
+            42
+        );
+    }
+
+    engine.set_strip_assertions(false);
+
+    assert!(engine.eval::<()>("assert(false)").is_err());
+
+    Ok(())
+}
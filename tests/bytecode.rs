@@ -0,0 +1,218 @@
+use rhai::{Engine, EvalAltResult, Position, Scope, INT};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Evaluate a script with both backends, checking that the results and the number of
+/// operations are the same.
+fn eval_both(engine: &mut Engine, script: &str) -> Result<String, Box<EvalAltResult>> {
+    let mut ast = engine.compile(script)?;
+
+    let count = Arc::new(AtomicU64::new(0));
+    let counter = count.clone();
+    engine.on_progress(move |n| {
+        counter.store(n, Ordering::SeqCst);
+        None
+    });
+
+    let expected = engine
+        .eval_ast::<rhai::Dynamic>(&ast)
+        .map(|v| v.to_string());
+    let expected_count = count.swap(0, Ordering::SeqCst);
+
+    ast.compile_bytecode();
+    assert!(ast.has_bytecode());
+    let actual = engine
+        .eval_ast::<rhai::Dynamic>(&ast)
+        .map(|v| v.to_string());
+    let actual_count = count.load(Ordering::SeqCst);
+
+    match (&expected, &actual) {
+        (Ok(x), Ok(y)) => assert_eq!(x, y),
+        (Err(x), Err(y)) => assert_eq!(x.to_string(), y.to_string()),
+        _ => panic!("backends disagree: {:?} vs {:?}", expected, actual),
+    }
+    assert_eq!(expected_count, actual_count, "operations count");
+
+    actual
+}
+
+#[test]
+fn test_bytecode() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    let scripts = [
+        "40 + 2",
+        "let x = 1; { let x = 2; x += 1; } x",
+        "let x = 0; let s = \"\"; for n in range(0, 10) { if n % 2 == 0 { continue; } x += n; s += n; } x + len(s)",
+        "let x = 0; while true { x += 1; if x > 10 { break; } } x",
+        "let x = 0; loop { x += 1; if x >= 7 { break; } } x",
+        "let x = 0; do { x += 1; if x == 2 { continue; } } while x < 5; x",
+        "let x = 10; do { x -= 1; } until x < 5; x",
+        "let x = 0; for a in range(0, 5) { for b in range(0, 5) { if b > a { break; } x += b; } } x",
+        "let x = 0; for n in range(0, 20) { switch n { 3 => continue, 10 => break, _ => x += n } } x",
+        "let x = 0; for n in range(0, 5) { try { if n == 3 { throw n; } x += n; } catch (e) { x += 100 * e; } } x",
+        "let x = 5; let y = if x > 3 && x < 10 || x == 0 { `yes ${x}` } else { \"no\" }; y",
+        "let x = 42; x = x * 2 - 1; x",
+        "let c = 'x'; let s = `${c}${c}`; s + len(s)",
+        "let x = { let y = 1; y + 1 }; x",
+        "const X = 40; X + 2",
+    ];
+
+    for script in &scripts {
+        eval_both(&mut engine, script)?;
+    }
+
+    #[cfg(not(any(feature = "no_index", feature = "no_object")))]
+    eval_both(
+        &mut engine,
+        "let a = [1, 2, [3, 4]]; a.push(5); push(a, 6); a[2][1] + len(a)",
+    )?;
+
+    #[cfg(not(feature = "no_object"))]
+    eval_both(
+        &mut engine,
+        "let m = #{ a: 1, b: #{ c: 2 } }; m.b.c += 1; m.a + m.b.c",
+    )?;
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_function"))]
+fn test_bytecode_functions() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    let script = r#"
+        fn fib(n) { if n < 2 { n } else { fib(n - 1) + fib(n - 2) } }
+        fn sum(n) { let total = 0; for i in range(0, n) { total += i; } return total; }
+        fn check(n) { if n < 0 { throw "negative"; } n }
+        fib(7) + sum(100) + check(1)
+    "#;
+
+    assert_eq!(eval_both(&mut engine, script)?, "4964");
+
+    let mut ast = engine.compile(script)?;
+    ast.compile_bytecode();
+    let result: INT = engine.call_fn(&mut Scope::new(), &ast, "fib", (6 as INT,))?;
+    assert_eq!(result, 8);
+
+    assert!(matches!(
+        *engine.call_fn::<INT>(&mut Scope::new(), &ast, "check", (-1 as INT,))
+            .expect_err("should error"),
+        EvalAltResult::ErrorInFunctionCall(name, _, err, _)
+            if name == "check" && matches!(*err, EvalAltResult::ErrorRuntime(_, _))
+    ));
+
+    #[cfg(not(feature = "no_object"))]
+    eval_both(
+        &mut engine,
+        "fn inc(n) { this += n; } let x = 40; x.inc(2); x",
+    )?;
+
+    #[cfg(not(feature = "no_closure"))]
+    eval_both(
+        &mut engine,
+        "let x = 40; let f = |y| x + y; x += 1; call(f, 1)",
+    )?;
+
+    Ok(())
+}
+
+#[test]
+fn test_bytecode_errors() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    let script = "let x = 1;\nif x { 0 }";
+    eval_both(&mut engine, script).expect_err("should error");
+
+    let mut ast = engine.compile(script)?;
+    ast.compile_bytecode();
+    assert!(matches!(
+        *engine.eval_ast::<INT>(&ast).expect_err("should error"),
+        EvalAltResult::ErrorMismatchDataType(_, _, pos) if pos == Position::new(2, 4)
+    ));
+
+    for script in &[
+        "let x = 1; x + undefined_var",
+        "const X = 1; X = 2;",
+        "let x = 0; while x < 10 { x += 'a'; }",
+        "let x = 1; x == 1 && x",
+    ] {
+        eval_both(&mut engine, script).expect_err("should error");
+    }
+
+    #[cfg(not(feature = "unchecked"))]
+    {
+        engine.set_max_operations(100);
+
+        let script = "let x = 0; loop { x += 1; }";
+        let mut ast = engine.compile(script)?;
+        ast.compile_bytecode();
+
+        assert!(matches!(
+            *engine.eval_ast::<()>(&ast).expect_err("should error"),
+            EvalAltResult::ErrorTooManyOperations(_)
+        ));
+        eval_both(&mut engine, script).expect_err("should error");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_bytecode_engine_features() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    engine.on_var(|name, _, _| match name {
+        "MAGIC" => Ok(Some((42 as INT).into())),
+        _ => Ok(None),
+    });
+    engine.register_custom_syntax(&["double", "$expr$"], 0, |context, inputs| {
+        let value = context.eval_expression_tree(&inputs[0])?.as_int().unwrap();
+        Ok((value * 2).into())
+    })?;
+
+    let script = "let x = 0; for n in range(0, 3) { x += double n; } x + MAGIC";
+    assert_eq!(eval_both(&mut engine, script)?, "48");
+
+    #[cfg(not(feature = "no_module"))]
+    {
+        use rhai::module_resolvers::StaticModuleResolver;
+
+        let mut resolver = StaticModuleResolver::new();
+        resolver.insert_script("consts", "export const TWO = 2;");
+        engine.set_module_resolver(resolver);
+
+        let script =
+            r#"import "consts" as c; let x = c::TWO; { import "consts" as d; x += d::TWO; } x"#;
+        assert_eq!(eval_both(&mut engine, script)?, "4");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_bytecode_ast() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let mut ast = engine.compile("let x = 40; x + 2")?;
+    assert!(!ast.has_bytecode());
+
+    ast.compile_bytecode();
+    assert!(ast.has_bytecode());
+    assert!(ast.clone_statements_only().has_bytecode());
+
+    // Bytecode is discarded when the statements change
+    ast.combine(engine.compile("x + 1")?);
+    assert!(!ast.has_bytecode());
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 41);
+
+    ast.compile_bytecode();
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 41);
+
+    ast.clear_bytecode();
+    assert!(!ast.has_bytecode());
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 41);
+
+    Ok(())
+}
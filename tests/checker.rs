@@ -0,0 +1,92 @@
+use rhai::{Checker, Engine, Lint, Severity};
+use std::fs;
+
+#[test]
+fn test_checker_errors_and_warnings() {
+    let dir = std::env::temp_dir().join(format!("rhai-checker-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("main.rhai"), "let x = ;\nlet y = 1;\nfoo(;\n").unwrap();
+    fs::write(dir.join("lint.rhai"), "let x = 1;\nbar();\n").unwrap();
+
+    let engine = Engine::new();
+    let mut checker = Checker::new(&engine);
+    checker.linter_mut().deny(Lint::UnknownFunction);
+
+    let report = checker.check_files(&[dir.join("main.rhai"), dir.join("lint.rhai")]);
+
+    fs::remove_dir_all(&dir).unwrap();
+
+    let diagnostics: Vec<_> = report
+        .diagnostics
+        .iter()
+        .map(|d| (d.severity, d.code, d.pos.line().unwrap()))
+        .collect();
+
+    assert_eq!(
+        diagnostics,
+        [
+            (Severity::Error, "unexpected_input", 1),
+            (Severity::Error, "unexpected_input", 3),
+            (Severity::Warning, "unused_variable", 1),
+            (Severity::Error, "unknown_function", 2),
+        ]
+    );
+    assert_eq!(report.files.len(), 2);
+    assert_eq!(report.errors(), 3);
+    assert_eq!(report.warnings(), 1);
+    assert!(report.has_errors());
+
+    let json = report.to_json();
+    assert!(
+        json.contains(r#""line": 3, "column": 5, "severity": "error", "code": "unexpected_input""#)
+    );
+    assert!(json.contains(r#""errors": 3"#));
+}
+
+#[test]
+#[cfg(not(feature = "no_function"))]
+#[cfg(not(feature = "no_module"))]
+fn test_checker_imports() {
+    let dir = std::env::temp_dir().join(format!("rhai-checker-imports-{}", std::process::id()));
+    fs::create_dir_all(dir.join("lib")).unwrap();
+    fs::write(
+        dir.join("main.rhai"),
+        "import \"util\" as u;\nimport \"shared\" as s;\nimport \"missing\" as m;\nu::f()\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("util.rhai"),
+        "import \"shared\" as s;\nfn f() { 1 + }\n",
+    )
+    .unwrap();
+    fs::write(dir.join("lib").join("shared.rhai"), "fn g() { 42 }\n").unwrap();
+
+    let engine = Engine::new();
+    let mut checker = Checker::new(&engine);
+    checker.add_module_path(dir.join("lib"));
+
+    let report = checker.check_file(dir.join("main.rhai"));
+
+    fs::remove_dir_all(&dir).unwrap();
+
+    // Each module is checked once
+    let files: Vec<_> = report
+        .files
+        .iter()
+        .map(|f| f.rsplit(&['/', '\\'][..]).next().unwrap())
+        .collect();
+    assert_eq!(files, ["main.rhai", "util.rhai", "shared.rhai"]);
+
+    let diagnostics: Vec<_> = report
+        .diagnostics
+        .iter()
+        .map(|d| (d.code, d.pos.line().unwrap()))
+        .collect();
+    assert_eq!(
+        diagnostics,
+        [("unexpected_input", 2), ("module_not_found", 3)]
+    );
+    assert!(report.diagnostics[0].file.ends_with("util.rhai"));
+    assert!(report.diagnostics[1].file.ends_with("main.rhai"));
+    assert!(report.diagnostics[1].message.contains("missing.rhai"));
+}
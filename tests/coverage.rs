@@ -0,0 +1,132 @@
+use rhai::{Coverage, Engine, EvalAltResult, INT};
+
+#[test]
+fn test_coverage_lines() -> Result<(), Box<EvalAltResult>> {
+    let coverage = Coverage::new();
+
+    let mut engine = Engine::new();
+    engine.set_coverage(Some(coverage.clone()));
+
+    assert!(engine.coverage().is_some());
+
+    let mut ast = engine.compile(
+        "
+            let x = 0;
+            for i in range(0, 3) {
+                x += i;
+            }
+            if x > 100 {
+                x = 0;
+            }
+            x
+        ",
+    )?;
+    ast.set_source("test.rhai");
+
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 3);
+
+    assert_eq!(coverage.sources(), vec!["test.rhai".to_string()]);
+    assert_eq!(
+        coverage.lines("test.rhai"),
+        vec![(2, 1), (3, 1), (4, 3), (6, 1), (7, 0), (9, 1)]
+    );
+
+    let lcov = coverage.to_lcov();
+    assert!(lcov.starts_with("SF:test.rhai\n"));
+    assert!(lcov.contains("DA:4,3\n"));
+    assert!(lcov.contains("DA:7,0\n"));
+    assert!(lcov.contains("LF:6\nLH:5\n"));
+    assert!(lcov.ends_with("end_of_record\n"));
+
+    coverage.clear();
+    assert!(coverage.sources().is_empty());
+    assert!(coverage.to_lcov().is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_coverage_branches() -> Result<(), Box<EvalAltResult>> {
+    let coverage = Coverage::new();
+
+    let mut engine = Engine::new();
+    engine.set_coverage(Some(coverage.clone()));
+
+    engine.consume(
+        r#"
+            let x = 1;
+            if x > 0 { x = 2; } else { x = 3; }
+            let y = x > 10 && x < 20;
+            let z = x > 0 || x < -10;
+            switch x { 1 => x = 5, _ => x = 6 }
+            try { throw 42; } catch { x = 7; }
+        "#,
+    )?;
+
+    let branches = coverage.branches("");
+
+    let hits = |line: usize| -> Vec<(usize, u64)> {
+        branches
+            .iter()
+            .filter(|(pos, _, _)| pos.line() == Some(line))
+            .map(|(_, branch, hits)| (*branch, *hits))
+            .collect()
+    };
+
+    assert_eq!(hits(3), vec![(0, 1), (1, 0)]);
+    assert_eq!(hits(4), vec![(0, 1), (1, 0)]);
+    assert_eq!(hits(5), vec![(0, 1), (1, 0)]);
+    assert_eq!(hits(6), vec![(0, 0), (1, 1)]);
+    assert_eq!(hits(7), vec![(0, 0), (1, 1)]);
+
+    let lcov = coverage.to_lcov();
+    assert!(lcov.contains("BRF:10\nBRH:5\n"));
+
+    let json = coverage.to_json();
+    assert!(json.contains(r#""": {"#));
+    assert!(json.contains(r#"{ "line": 3, "position": 13, "branch": 0, "hits": 1 }"#));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_function"))]
+#[cfg(not(feature = "no_module"))]
+#[cfg(not(feature = "no_std"))]
+fn test_coverage_file_module() -> Result<(), Box<EvalAltResult>> {
+    use rhai::module_resolvers::FileModuleResolver;
+    use std::fs;
+
+    let dir = std::env::temp_dir().join(format!("rhai-coverage-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        dir.join("lib.rhai"),
+        "fn sign(x) {\n    if x < 0 {\n        -1\n    } else {\n        1\n    }\n}\n",
+    )
+    .unwrap();
+
+    let coverage = Coverage::new();
+
+    let mut engine = Engine::new();
+    engine
+        .set_module_resolver(FileModuleResolver::new_with_path(&dir))
+        .set_coverage(Some(coverage.clone()));
+
+    let result = engine.eval::<INT>(r#"import "lib" as lib; lib::sign(42)"#);
+
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(result?, 1);
+
+    assert_eq!(coverage.sources(), vec!["".to_string(), "lib".to_string()]);
+    assert_eq!(coverage.lines("lib"), vec![(2, 1), (3, 0), (5, 1)]);
+
+    let file_path = coverage
+        .file_path("lib")
+        .expect("file path should be recorded");
+    assert!(file_path.ends_with("lib.rhai"));
+    assert!(coverage.to_lcov().contains(&format!("SF:{}\n", file_path)));
+    assert_eq!(coverage.file_path(""), None);
+
+    Ok(())
+}
@@ -0,0 +1,63 @@
+#![cfg(feature = "metadata")]
+#![cfg(not(feature = "only_i32"))]
+#![cfg(not(any(feature = "no_index", feature = "no_object", feature = "no_module")))]
+
+use rhai::{Engine, Module, INT};
+
+#[derive(Debug, Clone)]
+struct TestStruct {
+    x: INT,
+}
+
+#[test]
+fn test_definitions() {
+    let mut engine = Engine::new();
+
+    engine
+        .register_type_with_name::<TestStruct>("TestStruct")
+        .register_fn("new_ts", || TestStruct { x: 1 })
+        .register_get_set(
+            "x",
+            |ts: &mut TestStruct| ts.x,
+            |ts: &mut TestStruct, x: INT| ts.x = x,
+        )
+        .register_indexer_get(|ts: &mut TestStruct, i: INT| ts.x + i)
+        .register_fn("+", |a: TestStruct, b: TestStruct| a.x + b.x);
+
+    let mut module = Module::new();
+    let hash = module.set_native_fn("calc", |x: INT, y: INT| Ok(x * y));
+    module.update_fn_metadata(hash, &["x: INT", "y: INT", "INT"]);
+
+    let mut sub_module = Module::new();
+    sub_module.set_native_fn("answer", || Ok(42 as INT));
+    module.set_sub_module("inner", sub_module);
+
+    engine.register_static_module("math", module.into());
+
+    let files = engine.gen_definitions(false);
+
+    assert_eq!(
+        files.iter().map(|(f, _)| f.as_str()).collect::<Vec<_>>(),
+        ["global.d.rhai", "math.d.rhai", "math.inner.d.rhai"]
+    );
+
+    let global = &files[0].1;
+    assert!(global.contains("module global;"));
+    assert!(global.contains("fn new_ts() -> TestStruct;"));
+    assert!(global.contains("fn get x(_: TestStruct) -> i64;"));
+    assert!(global.contains("fn set x(_: TestStruct, _: i64);"));
+    assert!(global.contains("fn index get(_: TestStruct, _: i64) -> i64;"));
+    assert!(global.contains("op +(_: TestStruct, _: TestStruct) -> i64;"));
+    assert!(!global.contains("fn len("));
+
+    assert!(files[1].1.contains("fn calc(x: int, y: int) -> int;"));
+    assert!(files[2].1.contains("module math::inner;"));
+
+    let json = engine.gen_definitions_json(false).unwrap();
+    assert!(json.contains(r#""kind": "getter""#));
+    assert!(json.contains(r#""name": "math::inner""#));
+    assert!(json.contains(r#""returnType": "int""#));
+
+    let files = engine.gen_definitions(true);
+    assert!(files[0].1.contains("fn len("));
+}
@@ -0,0 +1,109 @@
+use rhai::{Engine, EvalAltResult, ParseErrorType, INT};
+
+#[test]
+fn test_diagnostics_multiple_errors() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let (ast, errors) = engine.compile_with_diagnostics(
+        r"
+            let x = 40 +;
+            let y = 2;
+            let z = (y;
+            let w = 21;
+            y * w
+        ",
+    );
+
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[0].1.line(), Some(2));
+    assert_eq!(errors[1].1.line(), Some(4));
+    assert!(matches!(
+        *errors[1].0,
+        ParseErrorType::MissingToken(ref token, _) if token == ")"
+    ));
+
+    // Only the statements that parse correctly remain
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 42);
+
+    Ok(())
+}
+
+#[test]
+fn test_diagnostics_blocks() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let (_, errors) = engine.compile_with_diagnostics(
+        r"
+            if true {
+                let a = ;
+                let b = 1 2;
+            }
+            let c = #{ a: 1, b: };
+            while false { let d = -; }
+            42
+        ",
+    );
+
+    let lines: Vec<_> = errors.iter().map(|err| err.1.line().unwrap()).collect();
+    assert_eq!(lines, [3, 4, 6, 7]);
+
+    let (_, errors) = engine.compile_with_diagnostics("let x = 1; { let y = 2;");
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(
+        *errors[0].0,
+        ParseErrorType::MissingToken(ref token, _) if token == "}"
+    ));
+
+    let (_, errors) = engine.compile_with_diagnostics("let x = 1; } let y = ;");
+    assert_eq!(errors.len(), 2);
+
+    Ok(())
+}
+
+#[cfg(not(feature = "no_function"))]
+#[test]
+fn test_diagnostics_functions() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let (ast, errors) = engine.compile_with_diagnostics(
+        r"
+            fn foo(x) {
+                let y = x +;
+                x * 2
+            }
+            fn bar(x, x) { x }
+            fn baz() { 42 }
+            let f = |x| { x + };
+            baz()
+        ",
+    );
+
+    assert_eq!(errors.len(), 3);
+    assert_eq!(errors[0].1.line(), Some(3));
+    assert!(matches!(
+        *errors[1].0,
+        ParseErrorType::FnDuplicatedParam(ref f, ref p) if f == "bar" && p == "x"
+    ));
+    assert_eq!(errors[2].1.line(), Some(8));
+
+    assert!(ast.iter_functions().any(|f| f.name == "foo"));
+    assert!(ast.iter_functions().any(|f| f.name == "baz"));
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 42);
+
+    Ok(())
+}
+
+#[test]
+fn test_diagnostics_no_errors() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let (ast, errors) = engine.compile_with_diagnostics("let x = 40; x + 2");
+    assert!(errors.is_empty());
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 42);
+
+    // Fail-fast compile is unchanged
+    let err = engine.compile("let x = 40 +; let y = ;").expect_err("should error");
+    assert_eq!(err.1.line(), Some(1));
+
+    Ok(())
+}
@@ -0,0 +1,93 @@
+#![cfg(feature = "serde")]
+#![cfg(feature = "metadata")]
+#![cfg(not(feature = "no_function"))]
+
+use rhai::{DocGenerator, Engine, EvalAltResult, Module, INT};
+
+#[test]
+fn test_doc_gen_markdown() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    engine.register_fn("add", |x: INT, y: INT| x + y);
+
+    let mut module = Module::new();
+    module.set_native_fn("triple", |x: INT| Ok(x * 3));
+    engine.register_static_module("calc", module.into());
+
+    let ast = engine.compile(
+        r"
+            /// Increment a number.
+            ///
+            /// See also [`decrement`] and [`calc::triple`].
+            fn increment(x) { x + 1 }
+
+            /** Decrement a number. */
+            fn decrement(x) { x - 1 }
+
+            /// Not documented.
+            private fn hidden() { 0 }
+        ",
+    )?;
+
+    let mut docs = DocGenerator::new(&engine);
+    docs.set_title("My API")
+        .add_script_module("utils::math", &ast);
+
+    let pages = docs.gen_markdown();
+
+    assert_eq!(
+        pages.keys().cloned().collect::<Vec<_>>(),
+        ["calc.md", "global.md", "index.md", "utils.math.md"]
+    );
+
+    let index = &pages["index.md"];
+    assert!(index.starts_with("# My API"));
+    assert!(index.contains("[`utils::math`](utils.math.md)"));
+
+    assert!(pages["global.md"].contains("fn add("));
+    assert!(pages["calc.md"].contains("fn triple("));
+
+    let page = &pages["utils.math.md"];
+    assert!(page.contains("fn increment(x)"));
+    assert!(page.contains("Increment a number."));
+    assert!(page.contains("[`decrement`](#fn.decrement)"));
+    assert!(page.contains("[`calc::triple`](calc.md#fn.triple)"));
+    assert!(page.contains("Decrement a number."));
+    assert!(!page.contains("hidden"));
+
+    Ok(())
+}
+
+#[test]
+fn test_doc_gen_html() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let ast = engine.compile(
+        r"
+            /// Compare `a < b` and see [`max`].
+            fn min(a, b) { if a < b { a } else { b } }
+
+            /// The larger of two values.
+            fn max(a, b) { if a > b { a } else { b } }
+        ",
+    )?;
+
+    let mut docs = DocGenerator::new(&engine);
+    docs.add_script_module("ord", &ast);
+
+    let pages = docs.gen_html();
+
+    assert!(pages.contains_key("index.html"));
+    assert!(pages.contains_key("search-index.js"));
+
+    let page = &pages["ord.html"];
+    assert!(page.contains("<code>a &lt; b</code>"));
+    assert!(page.contains("<a href=\"#fn.max\"><code>max</code></a>"));
+    assert!(page.contains("id=\"fn.min\""));
+
+    let index = &pages["search-index.json"];
+    assert!(index.contains("\"url\": \"ord.html#fn.max\""));
+    assert!(index.contains("\"summary\": \"The larger of two values.\""));
+
+    Ok(())
+}
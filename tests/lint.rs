@@ -0,0 +1,147 @@
+use rhai::{Engine, EvalAltResult, Lint, LintLevel, Linter, INT};
+
+fn lints(linter: &Linter, script: &str) -> Result<Vec<(Lint, usize)>, Box<EvalAltResult>> {
+    Ok(linter
+        .lint(script)?
+        .into_iter()
+        .map(|w| (w.lint, w.pos.line().unwrap()))
+        .collect())
+}
+
+#[test]
+fn test_lint_variables() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+    let linter = Linter::new(&engine);
+
+    assert_eq!(
+        lints(
+            &linter,
+            r"
+                let x = 1;
+                let y = 2;
+                let _z = 3;
+                {
+                    let y = 3;
+                    print(y);
+                }
+                x
+            "
+        )?,
+        [(Lint::UnusedVariable, 3), (Lint::ShadowedVariable, 6)]
+    );
+
+    assert_eq!(
+        lints(
+            &linter,
+            r"
+                let x = 1;
+                x = 2;
+                x = 3;
+                let y = 0;
+                y += x;
+                y
+            "
+        )?,
+        [(Lint::UnusedAssignment, 2), (Lint::UnusedAssignment, 3)]
+    );
+
+    assert_eq!(lints(&linter, "let x = 1; if x > 0 { x = 2; } x")?, []);
+
+    // `eval` may access any variable
+    assert_eq!(lints(&linter, "let x = 1; eval(\"x\")")?, []);
+
+    Ok(())
+}
+
+#[cfg(not(feature = "no_function"))]
+#[test]
+fn test_lint_functions() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    engine.register_fn("double", |x: INT| x * 2);
+
+    let linter = Linter::new(&engine);
+
+    assert_eq!(
+        lints(
+            &linter,
+            r"
+                fn foo(a, b) {
+                    return a;
+                    a
+                }
+                let x = foo(1, 2);
+                double(x) + bar(x) + len(x)
+            "
+        )?,
+        [
+            (Lint::UnusedParameter, 2),
+            (Lint::UnreachableCode, 4),
+            (Lint::UnknownFunction, 7)
+        ]
+    );
+
+    #[cfg(not(feature = "no_object"))]
+    assert_eq!(lints(&linter, "let x = 1; x.double() + x.len()")?, []);
+
+    #[cfg(not(feature = "no_closure"))]
+    assert_eq!(
+        lints(&linter, "let x = 1; let f = || x; x = 2; call(f)")?,
+        []
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_lint_conditions() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+    let linter = Linter::new(&engine);
+
+    assert_eq!(
+        lints(
+            &linter,
+            r"
+                let x = 42;
+                if true { print(x); }
+                while false { }
+                loop { break; }
+                x == ()
+            "
+        )?,
+        [
+            (Lint::ConstantCondition, 3),
+            (Lint::ConstantCondition, 4),
+            (Lint::UnitComparison, 6)
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_lint_levels() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+    let mut linter = Linter::new(&engine);
+
+    assert_eq!(linter.level(Lint::UnusedVariable), LintLevel::Warn);
+
+    linter
+        .allow(Lint::UnusedVariable)
+        .deny(Lint::UnknownFunction);
+
+    let warnings = linter.lint("let x = 1; foo()")?;
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].lint, Lint::UnknownFunction);
+    assert_eq!(warnings[0].level, LintLevel::Deny);
+    assert_eq!(
+        warnings[0].to_string(),
+        "Function 'foo' with 0 parameters is not defined [unknown_function] (line 1, position 12)"
+    );
+
+    assert_eq!(Lint::from_id("unit_comparison"), Some(Lint::UnitComparison));
+    assert_eq!(Lint::from_id("foo"), None);
+
+    assert!(linter.lint("let x = ;").is_err());
+
+    Ok(())
+}
@@ -217,6 +217,7 @@ fn test_module_resolver() -> Result<(), Box<EvalAltResult>> {
             EvalAltResult::ErrorTooManyModules(_)
         ));
 
+        // Cached imports inside functions still count towards the maximum number of modules
         #[cfg(not(feature = "no_function"))]
         assert!(matches!(
             *engine
@@ -226,18 +227,18 @@ fn test_module_resolver() -> Result<(), Box<EvalAltResult>> {
 
                         fn foo() {
                             import "hello" as h;
-                            sum += h::answer;
+                            h::answer
                         }
 
                         for x in range(0, 10) {
-                            foo();
+                            sum += foo();
                         }
 
                         sum
                     "#
                 )
                 .expect_err("should error"),
-            EvalAltResult::ErrorInFunctionCall(fn_name, _, _, _) if fn_name == "foo"
+            EvalAltResult::ErrorTooManyModules(_)
         ));
 
         engine.set_max_modules(1000);
@@ -382,13 +383,13 @@ fn test_module_export() -> Result<(), Box<EvalAltResult>> {
 
     assert!(matches!(
         engine.compile(r"let x = 10; { export x; }").expect_err("should error"),
-        ParseError(x, _) if *x == ParseErrorType::WrongExport
+        ParseError(x, ..) if *x == ParseErrorType::WrongExport
     ));
 
     #[cfg(not(feature = "no_function"))]
     assert!(matches!(
         engine.compile(r"fn abc(x) { export x; }").expect_err("should error"),
-        ParseError(x, _) if *x == ParseErrorType::WrongExport
+        ParseError(x, ..) if *x == ParseErrorType::WrongExport
     ));
 
     Ok(())
@@ -515,3 +516,938 @@ fn test_module_file() -> Result<(), Box<EvalAltResult>> {
     Module::eval_ast_as_new(Default::default(), &ast, &engine)?;
     Ok(())
 }
+
+#[cfg(not(feature = "no_function"))]
+#[test]
+fn test_module_backtrace() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    let ast = engine.compile(
+        r#"
+            fn check(x) { if x < 0 { throw "negative"; } x }
+            fn run(x) { check(x) }
+        "#,
+    )?;
+
+    let mut module = Module::eval_ast_as_new(Default::default(), &ast, &engine)?;
+    module.set_id(Some("checks"));
+
+    let mut resolver = StaticModuleResolver::new();
+    resolver.insert("checks", module);
+    engine.set_module_resolver(resolver);
+
+    let err = engine
+        .eval::<INT>(r#"import "checks" as c; c::run(-1)"#)
+        .expect_err("should error");
+
+    let stack = err.backtrace();
+
+    assert_eq!(stack.len(), 2);
+    assert_eq!(stack[0].fn_name, "check");
+    assert_eq!(stack[0].namespace, None);
+    assert_eq!(stack[1].fn_name, "run");
+    assert_eq!(stack[1].namespace.as_deref(), Some("c"));
+    assert_eq!(stack[1].source.as_deref(), Some("checks"));
+    assert_eq!(stack[1].pos.line(), Some(1));
+    assert_eq!(
+        stack[1].to_string(),
+        "c::run @ 'checks' (line 1, position 26)"
+    );
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_std"))]
+fn test_module_file_hot_reload() -> Result<(), Box<EvalAltResult>> {
+    use rhai::module_resolvers::FileModuleResolver;
+    use std::fs;
+    use std::sync::{Arc, Mutex};
+
+    let dir = std::env::temp_dir().join(format!("rhai-hot-reload-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("dep.rhai"), "export const VALUE = 1;").unwrap();
+    fs::write(
+        dir.join("main.rhai"),
+        r#"import "dep" as d; export const VALUE = d::VALUE * 10;"#,
+    )
+    .unwrap();
+
+    let changes = Arc::new(Mutex::new(Vec::new()));
+    let changes2 = changes.clone();
+
+    let mut resolver = FileModuleResolver::new_with_path(&dir);
+    resolver.enable_hot_reload(true).on_change(move |path| {
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+        changes2.lock().unwrap().push(name);
+    });
+
+    let mut engine = Engine::new();
+    engine.set_module_resolver(resolver);
+
+    let script = r#"import "main" as m; m::VALUE"#;
+
+    let result1 = engine.eval::<INT>(script);
+    let result2 = engine.eval::<INT>(script);
+
+    // Modifying a module reloads it and all modules importing it
+    fs::write(dir.join("dep.rhai"), "export const VALUE = 42;").unwrap();
+    let result3 = engine.eval::<INT>(script);
+    let changes3 = changes.lock().unwrap().clone();
+
+    // Rewriting a module with the same contents does not reload it
+    fs::write(
+        dir.join("main.rhai"),
+        r#"import "dep" as d; export const VALUE = d::VALUE * 10;"#,
+    )
+    .unwrap();
+    let result4 = engine.eval::<INT>(script);
+    let changes4 = changes.lock().unwrap().clone();
+
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(result1?, 10);
+    assert_eq!(result2?, 10);
+    assert_eq!(result3?, 420);
+    assert_eq!(changes3, ["dep.rhai", "main.rhai"]);
+    assert_eq!(result4?, 420);
+    assert_eq!(changes4, ["dep.rhai", "main.rhai"]);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "sync")]
+#[cfg(not(feature = "no_std"))]
+fn test_module_file_hot_reload_threads() -> Result<(), Box<EvalAltResult>> {
+    use rhai::module_resolvers::FileModuleResolver;
+    use std::fs;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Barrier, Mutex};
+    use std::thread;
+
+    let dir = std::env::temp_dir().join(format!("rhai-hot-reload-threads-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    // Both modules are being loaded at the same time when they import their dependencies
+    for name in ["a", "b"] {
+        fs::write(
+            dir.join(format!("{}_dep.rhai", name)),
+            "export const VALUE = 1;",
+        )
+        .unwrap();
+        fs::write(
+            dir.join(format!("{}.rhai", name)),
+            format!(
+                r#"wait(); import "{}_dep" as d; wait(); export const VALUE = d::VALUE * 10;"#,
+                name
+            ),
+        )
+        .unwrap();
+    }
+
+    let changes = Arc::new(Mutex::new(Vec::new()));
+    let changes2 = changes.clone();
+
+    let mut resolver = FileModuleResolver::new_with_path(&dir);
+    resolver.enable_hot_reload(true).on_change(move |path| {
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+        changes2.lock().unwrap().push(name);
+    });
+
+    let barrier = Arc::new(Barrier::new(2));
+    let waiting = Arc::new(AtomicBool::new(true));
+    let waiting2 = waiting.clone();
+
+    let mut engine = Engine::new();
+    engine
+        .set_module_resolver(resolver)
+        .register_fn("wait", move || {
+            if waiting2.load(Ordering::SeqCst) {
+                barrier.wait();
+            }
+        });
+    let engine = Arc::new(engine);
+
+    let handles: Vec<_> = ["a", "b"]
+        .iter()
+        .map(|name| {
+            let engine = engine.clone();
+            let script = format!(r#"import "{}" as m; m::VALUE"#, name);
+            thread::spawn(move || engine.eval::<INT>(&script))
+        })
+        .collect();
+
+    let results: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+    waiting.store(false, Ordering::SeqCst);
+
+    // Modifying a dependency reloads only the module importing it
+    fs::write(dir.join("a_dep.rhai"), "export const VALUE = 42;").unwrap();
+    let result_a = engine.eval::<INT>(r#"import "a" as m; m::VALUE"#);
+    let result_b = engine.eval::<INT>(r#"import "b" as m; m::VALUE"#);
+
+    fs::remove_dir_all(&dir).unwrap();
+
+    for result in results {
+        assert_eq!(result?, 10);
+    }
+    assert_eq!(result_a?, 420);
+    assert_eq!(result_b?, 10);
+    assert_eq!(*changes.lock().unwrap(), ["a_dep.rhai", "a.rhai"]);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_function"))]
+fn test_module_memory_resolver() -> Result<(), Box<EvalAltResult>> {
+    use rhai::module_resolvers::MemoryModuleResolver;
+
+    let mut resolver = MemoryModuleResolver::new();
+    resolver
+        .insert(
+            "main.rhai",
+            r#"import "lib/math" as m; export const X = m::double(21);"#,
+        )
+        .insert(
+            "lib/math.rhai",
+            r#"import "../consts" as c; fn double(x) { x * c::TWO }"#,
+        )
+        .insert("consts.rhai", "export const TWO = 2;")
+        .insert("bad.rhai", "let x = ;");
+
+    assert!(resolver.contains_path("./lib/../main.rhai"));
+
+    let mut engine = Engine::new();
+    engine.set_module_resolver(resolver);
+
+    assert_eq!(engine.eval::<INT>(r#"import "main" as m; m::X"#)?, 42);
+    assert_eq!(
+        engine.eval::<INT>(r#"import "/lib/math" as m; m::double(5)"#)?,
+        10
+    );
+
+    assert!(matches!(
+        *engine.eval::<INT>(r#"import "missing" as m; 0"#).expect_err("should error"),
+        EvalAltResult::ErrorModuleNotFound(p, _, _) if p == "missing"
+    ));
+    assert!(matches!(
+        *engine.eval::<INT>(r#"import "bad" as m; 0"#).expect_err("should error"),
+        EvalAltResult::ErrorInModule(p, _, _) if p == "bad"
+    ));
+
+    let ast = engine
+        .compile_into_self_contained(&Default::default(), r#"import "consts" as c; c::TWO"#)?;
+    engine.set_module_resolver(DummyModuleResolver::new());
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 2);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "archive")]
+fn test_module_archive_resolver() -> Result<(), Box<EvalAltResult>> {
+    use rhai::module_resolvers::ArchiveModuleResolver;
+
+    let files = [
+        (
+            "scripts/main.rhai",
+            r#"import "util" as u; fn run() { u::VALUE }"#,
+        ),
+        ("scripts/util.rhai", "export const VALUE = 42;"),
+    ];
+
+    // tar
+    let mut tar = Vec::new();
+    for (name, contents) in files.iter() {
+        let mut header = [0_u8; 512];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        header[124..135].copy_from_slice(format!("{:011o}", contents.len()).as_bytes());
+        header[156] = b'0';
+        tar.extend_from_slice(&header);
+        tar.extend_from_slice(contents.as_bytes());
+        tar.resize((tar.len() + 511) / 512 * 512, 0);
+    }
+    tar.resize(tar.len() + 1024, 0);
+
+    // zip (stored)
+    let mut zip = Vec::new();
+    let mut central = Vec::new();
+    for (name, contents) in files.iter() {
+        let offset = zip.len() as u32;
+        let sizes = [(contents.len() as u32).to_le_bytes(); 2].concat();
+        zip.extend_from_slice(&[0x50, 0x4b, 0x03, 0x04, 20, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        zip.extend_from_slice(&[0; 4]);
+        zip.extend_from_slice(&sizes);
+        zip.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        zip.extend_from_slice(&[0, 0]);
+        zip.extend_from_slice(name.as_bytes());
+        zip.extend_from_slice(contents.as_bytes());
+
+        central.extend_from_slice(&[0x50, 0x4b, 0x01, 0x02, 20, 0, 20, 0, 0, 0, 0, 0, 0, 0]);
+        central.extend_from_slice(&[0; 6]);
+        central.extend_from_slice(&sizes);
+        central.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        central.extend_from_slice(&[0; 12]);
+        central.extend_from_slice(&offset.to_le_bytes());
+        central.extend_from_slice(name.as_bytes());
+    }
+    let central_offset = zip.len() as u32;
+    zip.extend_from_slice(&central);
+    zip.extend_from_slice(&[0x50, 0x4b, 0x05, 0x06, 0, 0, 0, 0, 2, 0, 2, 0]);
+    zip.extend_from_slice(&(central.len() as u32).to_le_bytes());
+    zip.extend_from_slice(&central_offset.to_le_bytes());
+    zip.extend_from_slice(&[0, 0]);
+
+    for bytes in [tar, zip].iter() {
+        let resolver = ArchiveModuleResolver::from_bytes(bytes)?;
+        assert_eq!(
+            resolver.paths().collect::<Vec<_>>(),
+            ["scripts/main.rhai", "scripts/util.rhai"]
+        );
+
+        let mut engine = Engine::new();
+        engine.set_module_resolver(resolver);
+
+        assert_eq!(
+            engine.eval::<INT>(r#"import "scripts/main" as m; m::run()"#)?,
+            42
+        );
+    }
+
+    assert!(ArchiveModuleResolver::from_zip(b"PK\x03\x04 not a zip").is_err());
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "archive")]
+fn test_module_archive_resolver_limits() -> Result<(), Box<EvalAltResult>> {
+    use rhai::module_resolvers::ArchiveModuleResolver;
+
+    // A single-entry zip archive with a declared uncompressed size
+    fn zip(method: u8, size: u32, data: &[u8]) -> Vec<u8> {
+        let name = b"big.rhai";
+        let sizes = [(data.len() as u32).to_le_bytes(), size.to_le_bytes()].concat();
+
+        let mut zip = vec![0x50, 0x4b, 0x03, 0x04, 20, 0, 0, 0, method, 0, 0, 0, 0, 0];
+        zip.extend_from_slice(&[0; 4]);
+        zip.extend_from_slice(&sizes);
+        zip.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        zip.extend_from_slice(&[0, 0]);
+        zip.extend_from_slice(name);
+        zip.extend_from_slice(data);
+
+        let central_offset = zip.len() as u32;
+        zip.extend_from_slice(&[0x50, 0x4b, 0x01, 0x02, 20, 0, 20, 0, 0, 0, method, 0, 0, 0]);
+        zip.extend_from_slice(&[0; 6]);
+        zip.extend_from_slice(&sizes);
+        zip.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        zip.extend_from_slice(&[0; 16]);
+        zip.extend_from_slice(name);
+        let central_len = zip.len() as u32 - central_offset;
+
+        zip.extend_from_slice(&[0x50, 0x4b, 0x05, 0x06, 0, 0, 0, 0, 1, 0, 1, 0]);
+        zip.extend_from_slice(&central_len.to_le_bytes());
+        zip.extend_from_slice(&central_offset.to_le_bytes());
+        zip.extend_from_slice(&[0, 0]);
+        zip
+    }
+
+    // A `deflate` stream holding the data in a single stored block
+    fn deflate(data: &[u8]) -> Vec<u8> {
+        let len = data.len() as u16;
+        let mut deflated = vec![0x01];
+        deflated.extend_from_slice(&len.to_le_bytes());
+        deflated.extend_from_slice(&(!len).to_le_bytes());
+        deflated.extend_from_slice(data);
+        deflated
+    }
+
+    fn is_too_large(result: Result<ArchiveModuleResolver, Box<EvalAltResult>>) -> bool {
+        matches!(*result.unwrap_err(), EvalAltResult::ErrorDataTooLarge(_, _))
+    }
+
+    let script = [b' '; 1000];
+
+    assert!(
+        ArchiveModuleResolver::from_zip_with_limits(&zip(0, 1000, &script), 1000, 1000).is_ok()
+    );
+    assert!(is_too_large(ArchiveModuleResolver::from_zip_with_limits(
+        &zip(0, 1000, &script),
+        999,
+        0
+    )));
+    assert!(is_too_large(ArchiveModuleResolver::from_zip_with_limits(
+        &zip(8, 1000, &deflate(&script)),
+        0,
+        999
+    )));
+
+    // Entries declaring a huge size are rejected before decompressing
+    assert!(is_too_large(ArchiveModuleResolver::from_zip(&zip(
+        8,
+        u32::MAX,
+        &deflate(&script)
+    ))));
+
+    // Entries decompressing to more than their declared size are invalid
+    let result = ArchiveModuleResolver::from_zip(&zip(8, 10, &deflate(&script)));
+    assert!(matches!(
+        *result.unwrap_err(),
+        EvalAltResult::ErrorSystem(_, _)
+    ));
+
+    // The decompressed tar archive is limited in size
+    let mut tar = vec![0_u8; 512];
+    tar[..8].copy_from_slice(b"big.rhai");
+    tar[124..135].copy_from_slice(format!("{:011o}", script.len()).as_bytes());
+    tar[156] = b'0';
+    tar.extend_from_slice(&script);
+    tar.resize(3072, 0);
+
+    let mut gz = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff];
+    gz.extend_from_slice(&deflate(&tar));
+    gz.extend_from_slice(&[0; 8]);
+
+    assert!(ArchiveModuleResolver::from_bytes(&gz)?.contains_path("big.rhai"));
+    assert!(is_too_large(ArchiveModuleResolver::from_bytes_with_limits(
+        &gz, 0, 2048
+    )));
+    assert!(is_too_large(ArchiveModuleResolver::from_bytes_with_limits(
+        &gz, 999, 0
+    )));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_function"))]
+fn test_module_static_resolver_scripts() -> Result<(), Box<EvalAltResult>> {
+    let mut resolver = StaticModuleResolver::new();
+    resolver.insert_script("util", "export const VALUE = 40; fn add(x) { x + 40 }");
+    resolver.insert_script("main", r#"import "util" as u; fn run() { u::add(2) }"#);
+    resolver.insert_script("bad", "fn f( {");
+
+    assert_eq!(resolver.len(), 3);
+    assert!(!resolver.is_compiled("main"));
+
+    let mut engine = Engine::new();
+    engine.set_module_resolver(resolver);
+
+    assert_eq!(engine.eval::<INT>(r#"import "main" as m; m::run()"#)?, 42);
+    assert!(matches!(
+        *engine.eval::<INT>(r#"import "bad" as m; 0"#).expect_err("should error"),
+        EvalAltResult::ErrorInModule(p, _, _) if p == "bad"
+    ));
+
+    let ast = engine
+        .compile_into_self_contained(&Default::default(), r#"import "util" as u; u::VALUE"#)?;
+    engine.set_module_resolver(DummyModuleResolver::new());
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 40);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_function"))]
+#[cfg(not(feature = "no_std"))]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+fn test_module_file_search_paths_and_packages() -> Result<(), Box<EvalAltResult>> {
+    use rhai::module_resolvers::{FileModuleResolver, PackageManifest};
+    use std::fs;
+
+    let dir = std::env::temp_dir().join(format!("rhai-packages-{}", std::process::id()));
+    for sub_dir in ["app", "lib", "utils", "text"].iter() {
+        fs::create_dir_all(dir.join(sub_dir)).unwrap();
+    }
+    fs::write(
+        dir.join("app").join("rhai.toml"),
+        "# The application\n[package]\nname = \"app\"\n\n[dependencies]\nutils = { path = \"../utils\" }\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("utils").join("rhai.toml"),
+        "[package]\nname = \"utils\"\nentry = \"main\"\nversion = \"1.0\"\n\n[dependencies]\ntext = \"../text\"\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("text").join("rhai.toml"),
+        "[package]\nname = \"text\"\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("utils").join("main.rhai"),
+        r#"import "utils/strings" as s; fn greet(x) { s::hello(x) }"#,
+    )
+    .unwrap();
+    fs::write(
+        dir.join("utils").join("strings.rhai"),
+        r#"import "text" as t; fn hello(x) { t::join("hello", x) }"#,
+    )
+    .unwrap();
+    fs::write(
+        dir.join("text").join("lib.rhai"),
+        r#"fn join(a, b) { a + " " + b }"#,
+    )
+    .unwrap();
+    fs::write(dir.join("lib").join("extra.rhai"), "fn answer() { 42 }").unwrap();
+
+    let mut resolver = FileModuleResolver::new_with_path(dir.join("app"));
+    resolver
+        .add_search_path(dir.join("lib"))
+        .add_package(dir.join("app").join("rhai.toml"))?;
+
+    assert_eq!(
+        resolver
+            .packages()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>(),
+        ["app", "text", "utils"]
+    );
+
+    let mut engine = Engine::new();
+    engine.set_module_resolver(resolver);
+
+    let result = engine.eval::<String>(r#"import "utils" as u; u::greet("world")"#);
+    let answer = engine.eval::<INT>(r#"import "extra" as e; e::answer()"#);
+    let err = *engine
+        .eval::<INT>(r#"import "missing" as m; 0"#)
+        .expect_err("should error");
+
+    let mut resolver = FileModuleResolver::new();
+    resolver.add_package(dir.join("app"))?;
+    fs::write(
+        dir.join("lib").join("rhai.toml"),
+        "[package]\nname = \"text\"\n",
+    )
+    .unwrap();
+    let duplicated = resolver.add_package(dir.join("lib")).is_err();
+
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(result?, "hello world");
+    assert_eq!(answer?, 42);
+    assert!(duplicated);
+
+    match err {
+        EvalAltResult::ErrorModuleNotFound(ref path, ref tried, _) => {
+            assert_eq!(path, "missing");
+            assert_eq!(tried.len(), 2);
+            assert!(tried[1].ends_with("missing.rhai"));
+            assert!(err.to_string().contains("(tried: "));
+        }
+        _ => panic!("{:?}", err),
+    }
+
+    // Manifests
+    let manifest = PackageManifest::parse(
+        "[package] # comment\nname = 'a#b'\n[dependencies]\nx = { version = \"1\", path = \"../x,y\" }\n",
+    )?;
+    assert_eq!(manifest.name, "a#b");
+    assert_eq!(manifest.entry, "lib");
+    assert_eq!(manifest.dependencies, [("x".to_string(), "../x,y".into())]);
+
+    let err = PackageManifest::parse("[package]\nname = \"a\"\n[dependencies]\nx = ../x\n")
+        .expect_err("should error");
+    assert!(err.to_string().contains("line 4: expecting a string"));
+    assert!(PackageManifest::parse("[package]\nentry = \"main\"\n").is_err());
+    assert!(PackageManifest::parse("[package]\nname = \"a/b\"\n").is_err());
+
+    Ok(())
+}
+
+#[cfg(not(feature = "no_function"))]
+fn cyclic_import(err: Box<EvalAltResult>) -> Option<Vec<String>> {
+    match *err {
+        EvalAltResult::ErrorInModule(_, err, _) => cyclic_import(err),
+        EvalAltResult::ErrorCyclicImport(cycle, _) => Some(cycle),
+        _ => None,
+    }
+}
+
+#[test]
+#[cfg(not(feature = "no_function"))]
+fn test_module_cyclic_import() -> Result<(), Box<EvalAltResult>> {
+    use rhai::module_resolvers::MemoryModuleResolver;
+
+    let mut resolver = MemoryModuleResolver::new();
+    resolver
+        .insert(
+            "even.rhai",
+            r#"import "odd" as odd; fn is_even(n) { if n == 0 { true } else { odd::is_odd(n - 1) } }"#,
+        )
+        .insert(
+            "odd.rhai",
+            r#"import "even" as even; fn is_odd(n) { if n == 0 { false } else { even::is_even(n - 1) } }"#,
+        )
+        .insert("a.rhai", r#"import "b" as b; export const X = b::Y;"#)
+        .insert("b.rhai", r#"import "a" as a; export const Y = a::X + 1;"#);
+
+    let mut engine = Engine::new();
+    engine.set_module_resolver(resolver);
+
+    let err = engine
+        .eval::<bool>(r#"import "even" as e; e::is_even(4)"#)
+        .expect_err("should error");
+    assert_eq!(
+        cyclic_import(err).expect("should be cyclic import"),
+        ["even.rhai", "odd.rhai", "even.rhai"]
+    );
+
+    // Modules only defining functions can import each other when imports are lazy
+    engine.enable_lazy_imports(true);
+
+    assert!(engine.eval::<bool>(r#"import "even" as e; e::is_even(4)"#)?);
+    assert!(engine.eval::<bool>(r#"import "odd" as o; o::is_odd(7)"#)?);
+    assert!(!engine.eval::<bool>(r#"import "even" as e; e::is_even(7)"#)?);
+
+    let err = engine
+        .eval::<INT>(r#"import "a" as a; a::X"#)
+        .expect_err("should error");
+    assert_eq!(
+        cyclic_import(err).expect("should be cyclic import"),
+        ["a.rhai", "b.rhai", "a.rhai"]
+    );
+
+    // Errors in lazily-imported modules are raised on first access
+    engine.eval::<()>(r#"import "missing" as m;"#)?;
+    assert!(matches!(
+        *engine
+            .eval::<INT>(r#"import "missing" as m; m::X"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorModuleNotFound(p, _, _) if p == "missing"
+    ));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_function"))]
+#[cfg(not(feature = "no_std"))]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+fn test_module_file_cyclic_import() -> Result<(), Box<EvalAltResult>> {
+    use rhai::module_resolvers::FileModuleResolver;
+    use std::fs;
+
+    let dir = std::env::temp_dir().join(format!("rhai-cyclic-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("a.rhai"), r#"import "b" as b; fn f() { b::g() }"#).unwrap();
+    fs::write(dir.join("b.rhai"), r#"import "a" as a; fn g() { 42 }"#).unwrap();
+
+    let mut engine = Engine::new();
+    engine.set_module_resolver(FileModuleResolver::new_with_path(&dir));
+
+    let err = engine
+        .eval::<INT>(r#"import "a" as a; a::f()"#)
+        .expect_err("should error");
+
+    let cycle = cyclic_import(err).expect("should be cyclic import");
+    assert_eq!(cycle.len(), 3);
+    assert!(cycle[0].ends_with("a.rhai"));
+    assert!(cycle[1].ends_with("b.rhai"));
+    assert_eq!(cycle[0], cycle[2]);
+
+    engine.enable_lazy_imports(true);
+    let result = engine.eval::<INT>(r#"import "a" as a; a::f()"#);
+
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(result?, 42);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_function"))]
+fn test_module_import_in_function() -> Result<(), Box<EvalAltResult>> {
+    use rhai::module_resolvers::{MemoryModuleResolver, RestrictedModuleResolver};
+    use rhai::{ModuleResolver, Position, Shared};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    struct CountingResolver(MemoryModuleResolver, Arc<AtomicUsize>);
+
+    impl ModuleResolver for CountingResolver {
+        fn resolve(
+            &self,
+            engine: &Engine,
+            source_path: Option<&str>,
+            path: &str,
+            pos: Position,
+        ) -> Result<Shared<Module>, Box<EvalAltResult>> {
+            self.1.fetch_add(1, Ordering::SeqCst);
+            self.0.resolve(engine, source_path, path, pos)
+        }
+    }
+
+    let mut scripts = MemoryModuleResolver::new();
+    scripts
+        .enable_cache(false)
+        .insert("plugins/double.rhai", "fn run(x) { x * 2 }")
+        .insert("plugins/square.rhai", "fn run(x) { x * x }");
+
+    let count = Arc::new(AtomicUsize::new(0));
+
+    let mut engine = Engine::new();
+    engine.set_module_resolver(CountingResolver(scripts, count.clone()));
+
+    let ast = engine.compile(
+        r#"
+            fn apply(name, x) {
+                import "plugins/" + name as p;
+                p::run(x)
+            }
+            let sum = 0;
+            for i in range(0, 10) {
+                sum += apply("double", i) + apply("square", i);
+            }
+            sum
+        "#,
+    )?;
+
+    // Each module is only resolved once per AST
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 375);
+    assert_eq!(count.load(Ordering::SeqCst), 2);
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 375);
+    assert_eq!(count.load(Ordering::SeqCst), 2);
+
+    ast.clear_module_cache();
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 375);
+    assert_eq!(count.load(Ordering::SeqCst), 4);
+
+    // Modules are resolved by the module resolver of the engine evaluating the AST
+    let mut scripts = MemoryModuleResolver::new();
+    scripts
+        .insert("plugins/double.rhai", "fn run(x) { x * 2 }")
+        .insert("plugins/square.rhai", "fn run(x) { x * x }");
+    let mut restricted = RestrictedModuleResolver::new(scripts);
+    restricted.deny_path("plugins/square");
+
+    let mut engine2 = Engine::new();
+    engine2.set_module_resolver(restricted);
+
+    assert!(matches!(
+        *engine2.eval_ast::<INT>(&ast).expect_err("should error"),
+        EvalAltResult::ErrorModuleRestricted(p, _, _) if p == "plugins/square"
+    ));
+
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 375);
+    assert_eq!(count.load(Ordering::SeqCst), 6);
+
+    // Imports at global level are not cached
+    let ast = engine.compile(r#"import "plugins/double" as p; p::run(21)"#)?;
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 42);
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 42);
+    assert_eq!(count.load(Ordering::SeqCst), 8);
+
+    // Computed import paths are forbidden
+    let computed =
+        engine.compile(r#"fn f(x) { import "plugins/" + x as p; p::run(3) } f("square")"#)?;
+
+    engine.enable_computed_imports(false);
+
+    assert_eq!(
+        *engine
+            .compile(r#"let base = "plugins"; import base + "/double" as p;"#)
+            .expect_err("should error")
+            .0,
+        ParseErrorType::ComputedImportPath
+    );
+    assert!(matches!(
+        *engine.eval_ast::<INT>(&computed).expect_err("should error"),
+        EvalAltResult::ErrorComputedImportPath(_)
+    ));
+    assert_eq!(
+        engine.eval::<INT>(r#"fn f() { import "plugins/square" as p; p::run(3) } f()"#)?,
+        9
+    );
+
+    Ok(())
+}
+
+#[cfg(not(feature = "no_function"))]
+fn module_restricted(err: Box<EvalAltResult>) -> Option<String> {
+    match *err {
+        EvalAltResult::ErrorInModule(_, err, _) => module_restricted(err),
+        EvalAltResult::ErrorModuleRestricted(_, reason, _) => Some(reason),
+        _ => None,
+    }
+}
+
+#[test]
+#[cfg(not(feature = "no_function"))]
+fn test_module_restricted_resolver() -> Result<(), Box<EvalAltResult>> {
+    use rhai::module_resolvers::{
+        MemoryModuleResolver, ModuleResolversCollection, RestrictedModuleResolver,
+        StaticModuleResolver,
+    };
+
+    let mut scripts = MemoryModuleResolver::new();
+    scripts
+        .insert(
+            "lib/a.rhai",
+            r#"import "b" as b; export const X = b::X + 1;"#,
+        )
+        .insert(
+            "lib/b.rhai",
+            r#"import "c" as c; export const X = c::X + 1;"#,
+        )
+        .insert("lib/c.rhai", "export const X = 40;")
+        .insert(
+            "lib/up.rhai",
+            r#"import "../secret/key" as k; export const X = k::X;"#,
+        )
+        .insert("lib/print.rhai", r#"print("hello"); export const X = 1;"#)
+        .insert("lib/len.rhai", r#"export const X = len("hello");"#)
+        .insert("secret/key.rhai", "export const X = 42;");
+
+    let mut resolver = RestrictedModuleResolver::new(MemoryModuleResolver::new());
+    resolver.allow_path("lib/*").allow_path("x/**/y");
+
+    assert!(resolver.is_path_allowed("lib/a"));
+    assert!(resolver.is_path_allowed("./lib/../lib/a"));
+    assert!(!resolver.is_path_allowed("lib/x/a"));
+    assert!(resolver.is_path_allowed("x/y"));
+    assert!(resolver.is_path_allowed("x/a/b/y"));
+    assert!(!resolver.is_path_allowed("x/a/b/z"));
+
+    let mut resolver = RestrictedModuleResolver::new(scripts);
+    resolver.deny_path("secret/**").deny_path("../**");
+
+    assert!(resolver.is_path_allowed("lib/a"));
+    assert!(!resolver.is_path_allowed("secret/key"));
+    assert!(!resolver.is_path_allowed("lib/../../key"));
+
+    let mut engine = Engine::new();
+    engine.set_module_resolver(resolver);
+
+    assert_eq!(engine.eval::<INT>(r#"import "lib/a" as a; a::X"#)?, 42);
+    assert_eq!(
+        module_restricted(engine.consume(r#"import "secret/key" as k;"#).unwrap_err()),
+        Some("import path is not allowed".to_string())
+    );
+    // Nested imports are restricted as well
+    assert_eq!(
+        module_restricted(engine.consume(r#"import "lib/up" as up;"#).unwrap_err()),
+        Some("import path is not allowed".to_string())
+    );
+    // Violations are not catchable
+    assert!(module_restricted(
+        engine
+            .consume(r#"try { import "secret/key" as k; print(k::X); } catch { }"#)
+            .unwrap_err()
+    )
+    .is_some());
+
+    // Restrict function calls
+    let mut scripts = MemoryModuleResolver::new();
+    scripts
+        .insert("print.rhai", r#"print("hello"); export const X = 1;"#)
+        .insert("len.rhai", r#"export const X = len("hello") + 1;"#)
+        .insert("math.rhai", "export const X = abs(-42);")
+        .insert(
+            "own.rhai",
+            "fn len(x) { 0 } export const X = len(1) * 2 + 1;",
+        );
+
+    let mut resolver = RestrictedModuleResolver::new(scripts);
+    resolver.allow_function("abs").allow_function("l*");
+    engine.set_module_resolver(resolver);
+
+    assert_eq!(
+        module_restricted(engine.consume(r#"import "print" as m;"#).unwrap_err()),
+        Some("call to function 'print' is not allowed".to_string())
+    );
+    assert_eq!(engine.eval::<INT>(r#"import "len" as m; m::X"#)?, 6);
+    assert_eq!(engine.eval::<INT>(r#"import "math" as m; m::X"#)?, 42);
+    assert_eq!(engine.eval::<INT>(r#"import "own" as m; m::X"#)?, 1);
+
+    // Limit the import depth and source size
+    let mut scripts = MemoryModuleResolver::new();
+    scripts
+        .insert("a.rhai", r#"import "b" as b; export const X = b::X + 1;"#)
+        .insert("b.rhai", r#"import "c" as c; export const X = c::X + 1;"#)
+        .insert("c.rhai", "export const X = 40;");
+
+    let size = scripts.paths().map(|p| scripts.get(p).unwrap().len()).sum();
+
+    let mut resolver = RestrictedModuleResolver::new(scripts);
+    resolver.set_max_depth(2);
+    engine.set_module_resolver(resolver);
+
+    assert_eq!(
+        module_restricted(engine.consume(r#"import "a" as a;"#).unwrap_err()),
+        Some("import depth exceeds the limit of 2".to_string())
+    );
+    assert_eq!(engine.eval::<INT>(r#"import "b" as b; b::X"#)?, 41);
+
+    let mut scripts = MemoryModuleResolver::new();
+    scripts
+        .insert("a.rhai", r#"import "b" as b; export const X = b::X + 1;"#)
+        .insert("b.rhai", r#"import "c" as c; export const X = c::X + 1;"#)
+        .insert("c.rhai", "export const X = 40;")
+        .enable_cache(false);
+
+    let mut resolver = RestrictedModuleResolver::new(scripts);
+    resolver.set_max_source_size(size);
+    engine.set_module_resolver(resolver);
+
+    // Each import is counted separately
+    assert_eq!(
+        engine.eval::<INT>(r#"import "a" as a; import "a" as a2; a::X + a2::X"#)?,
+        84
+    );
+
+    let mut scripts = MemoryModuleResolver::new();
+    scripts
+        .insert("a.rhai", r#"import "b" as b; export const X = b::X + 1;"#)
+        .insert("b.rhai", r#"import "c" as c; export const X = c::X + 1;"#)
+        .insert("c.rhai", "export const X = 40;");
+
+    let mut resolver = RestrictedModuleResolver::new(scripts);
+    resolver.set_max_source_size(size - 1);
+    engine.set_module_resolver(resolver);
+
+    assert_eq!(
+        module_restricted(engine.consume(r#"import "a" as a;"#).unwrap_err()),
+        Some(format!(
+            "total module source size exceeds the limit of {} bytes",
+            size - 1
+        ))
+    );
+    assert_eq!(engine.eval::<INT>(r#"import "b" as b; b::X"#)?, 41);
+
+    // Compose with a collection
+    let mut scripts = MemoryModuleResolver::new();
+    scripts.insert("secret.rhai", "export const X = 42;");
+
+    let mut fallback = StaticModuleResolver::new();
+    fallback.insert_script("secret", "export const X = 0;");
+
+    let mut resolver = RestrictedModuleResolver::new(scripts);
+    resolver.deny_path("secret");
+
+    let mut collection = ModuleResolversCollection::new();
+    collection.push(resolver).push(fallback);
+    engine.set_module_resolver(collection);
+
+    assert!(module_restricted(engine.consume(r#"import "secret" as s;"#).unwrap_err()).is_some());
+
+    let mut collection = ModuleResolversCollection::new();
+    collection.push(MemoryModuleResolver::new());
+    let mut scripts = MemoryModuleResolver::new();
+    scripts.insert("print.rhai", r#"print("hello");"#);
+    collection.push(scripts);
+
+    let mut resolver = RestrictedModuleResolver::new(collection);
+    resolver.deny_function("print");
+    engine.set_module_resolver(resolver);
+
+    assert_eq!(
+        module_restricted(engine.consume(r#"import "print" as m;"#).unwrap_err()),
+        Some("call to function 'print' is not allowed".to_string())
+    );
+
+    Ok(())
+}
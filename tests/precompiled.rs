@@ -0,0 +1,294 @@
+use rhai::{Engine, EvalAltResult, Position, AST, INT};
+
+fn round_trip(engine: &Engine, ast: &AST) -> Result<(AST, AST), Box<EvalAltResult>> {
+    let from_bytes = engine.ast_from_bytes(&ast.to_bytes()?)?;
+    let from_json = engine.ast_from_json(&ast.to_json()?)?;
+    Ok((from_bytes, from_json))
+}
+
+#[test]
+fn test_precompiled() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let ast = engine.compile(
+        r#"
+            let x = 0;
+            let s = "";
+
+            for n in range(0, 10) {
+                if n % 2 == 0 { continue; }
+                x += n;
+            }
+            while x > 30 { x -= 1; }
+            do { x += 1; } until x >= 30;
+
+            switch x {
+                30 => s += "thirty",
+                _ => s += "other"
+            }
+
+            try {
+                throw "oops";
+            } catch (err) {
+                s += `-${err}-${'!'}`;
+            }
+
+            x + len(s)
+        "#,
+    )?;
+
+    let (from_bytes, from_json) = round_trip(&engine, &ast)?;
+
+    let expected = engine.eval_ast::<INT>(&ast)?;
+
+    assert_eq!(engine.eval_ast::<INT>(&from_bytes)?, expected);
+    assert_eq!(engine.eval_ast::<INT>(&from_json)?, expected);
+
+    #[cfg(not(feature = "no_index"))]
+    #[cfg(not(feature = "no_object"))]
+    {
+        let ast = engine.compile(
+            r#"
+                let a = [1, 2, [3, "x\n\"y\""], #{ z: true }];
+                let m = #{ a: a, b: (), c: 'c' };
+                m.a[2][0] + m.a.len + if m.a[3].z { 1 } else { 0 }
+            "#,
+        )?;
+
+        let (from_bytes, from_json) = round_trip(&engine, &ast)?;
+
+        assert_eq!(engine.eval_ast::<INT>(&from_bytes)?, 8);
+        assert_eq!(engine.eval_ast::<INT>(&from_json)?, 8);
+    }
+
+    #[cfg(not(feature = "no_float"))]
+    {
+        let ast = engine.compile("let x = 1.5; x * 2.0 + 1e-3")?;
+        let (from_bytes, from_json) = round_trip(&engine, &ast)?;
+
+        assert_eq!(
+            engine.eval_ast::<rhai::FLOAT>(&from_bytes)?,
+            engine.eval_ast::<rhai::FLOAT>(&ast)?
+        );
+        assert_eq!(
+            engine.eval_ast::<rhai::FLOAT>(&from_json)?,
+            engine.eval_ast::<rhai::FLOAT>(&ast)?
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_function"))]
+fn test_precompiled_functions() -> Result<(), Box<EvalAltResult>> {
+    #[allow(unused_mut)]
+    let mut engine = Engine::new();
+
+    #[cfg(not(feature = "no_optimize"))]
+    engine.set_optimization_level(rhai::OptimizationLevel::None);
+
+    let ast = engine.compile(
+        r#"
+            /// Calculate a factorial.
+            fn fact(n) {
+                if n <= 1 { 1 } else { n * fact(n - 1) }
+            }
+
+            private fn double(x) { x * 2 }
+
+            fact(5)
+        "#,
+    )?;
+
+    let (from_bytes, from_json) = round_trip(&engine, &ast)?;
+
+    for ast in &[from_bytes, from_json] {
+        assert_eq!(engine.eval_ast::<INT>(ast)?, 120);
+
+        let f = ast.iter_functions().find(|f| f.name == "fact").unwrap();
+        assert_eq!(f.comments, vec!["/// Calculate a factorial."]);
+        assert_eq!(f.params, vec!["n"]);
+
+        let f = ast.iter_functions().find(|f| f.name == "double").unwrap();
+        assert_eq!(f.access, rhai::FnAccess::Private);
+
+        let result: INT = engine.call_fn(&mut Default::default(), ast, "fact", (4 as INT,))?;
+        assert_eq!(result, 24);
+    }
+
+    #[cfg(not(feature = "no_closure"))]
+    {
+        let ast = engine.compile(
+            r#"
+                let x = 40;
+                let f = |y| x + y;
+                x += 1;
+                call(f, 1)
+            "#,
+        )?;
+
+        let (from_bytes, from_json) = round_trip(&engine, &ast)?;
+
+        assert_eq!(engine.eval_ast::<INT>(&from_bytes)?, 42);
+        assert_eq!(engine.eval_ast::<INT>(&from_json)?, 42);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_precompiled_positions() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let mut ast = engine.compile("let x = 1;\n\nx + undefined_var")?;
+    ast.set_source("test");
+
+    let (from_bytes, from_json) = round_trip(&engine, &ast)?;
+
+    for ast in &[from_bytes, from_json] {
+        assert_eq!(ast.source(), Some("test"));
+        assert!(matches!(
+            *engine.eval_ast::<INT>(ast).expect_err("should error"),
+            EvalAltResult::ErrorVariableNotFound(name, pos)
+                if name == "undefined_var" && pos == Position::new(3, 5)
+        ));
+    }
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_module"))]
+fn test_precompiled_self_contained() -> Result<(), Box<EvalAltResult>> {
+    use rhai::module_resolvers::{DummyModuleResolver, StaticModuleResolver};
+    use rhai::Module;
+
+    let mut engine = Engine::new();
+    let mut resolver = StaticModuleResolver::new();
+    resolver.insert_script("consts", "export const TWO = 2;");
+    engine.set_module_resolver(resolver);
+
+    let ast = engine
+        .compile_into_self_contained(&Default::default(), r#"import "consts" as c; c::TWO"#)?;
+
+    engine.set_module_resolver(DummyModuleResolver::new());
+
+    let (from_bytes, from_json) = round_trip(&engine, &ast)?;
+
+    assert_eq!(engine.eval_ast::<INT>(&from_bytes)?, 2);
+    assert_eq!(engine.eval_ast::<INT>(&from_json)?, 2);
+
+    #[cfg(not(feature = "no_function"))]
+    {
+        let mut resolver = StaticModuleResolver::new();
+        resolver.insert_script("consts", "export const TWO = 2;");
+        resolver.insert_script(
+            "util",
+            r#"import "consts" as c; fn double(x) { x * c::TWO } fn quad(x) { double(double(x)) }"#,
+        );
+        resolver.insert_script("unused", "export const X = 0;");
+        engine.set_module_resolver(resolver);
+
+        let ast = engine.compile_into_self_contained(
+            &Default::default(),
+            r#"import "util" as u; u::quad(10) + u::double(1)"#,
+        )?;
+
+        engine.set_module_resolver(DummyModuleResolver::new());
+
+        let (from_bytes, from_json) = round_trip(&engine, &ast)?;
+
+        assert_eq!(engine.eval_ast::<INT>(&from_bytes)?, 42);
+        assert_eq!(engine.eval_ast::<INT>(&from_json)?, 42);
+    }
+
+    // Modules with native Rust functions cannot be serialized
+    let mut module = Module::new();
+    module.set_native_fn("answer", || Ok(42 as INT));
+    let mut resolver = StaticModuleResolver::new();
+    resolver.insert("native", module);
+    engine.set_module_resolver(resolver);
+
+    let ast = engine
+        .compile_into_self_contained(&Default::default(), r#"import "native" as n; n::answer()"#)?;
+
+    assert!(matches!(
+        *ast.to_bytes().expect_err("should error"),
+        EvalAltResult::ErrorSystem(msg, err)
+            if msg == "Cannot serialize AST" && err.to_string().contains("'answer'")
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_precompiled_custom_syntax() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    engine.register_custom_syntax(&["answer", "!"], 0, |_, _| Ok((42 as INT).into()))?;
+
+    let ast = engine.compile("answer!")?;
+    let bytes = ast.to_bytes()?;
+
+    assert_eq!(engine.eval_ast::<INT>(&engine.ast_from_bytes(&bytes)?)?, 42);
+
+    assert!(matches!(
+        *Engine::new().ast_from_bytes(&bytes).expect_err("should error"),
+        EvalAltResult::ErrorSystem(_, err) if err.to_string() == "custom syntax 'answer' is not registered"
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_precompiled_invalid() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let bytes = engine.compile("40 + 2")?.to_bytes()?;
+
+    assert!(engine.ast_from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    assert!(engine.ast_from_bytes(b"not an AST").is_err());
+    assert!(engine.ast_from_json("{}").is_err());
+    assert!(engine.ast_from_json(r#"{"RhaiAST":[999]}"#).is_err());
+
+    let json = engine.compile("40 + 2")?.to_json()?;
+    assert!(engine.ast_from_json(&json[..json.len() - 1]).is_err());
+    assert_eq!(engine.eval_ast::<INT>(&engine.ast_from_json(&json)?)?, 42);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "unchecked"))]
+fn test_precompiled_corrupted() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    engine.set_max_operations(10000);
+
+    let bytes = engine
+        .compile(
+            r#"
+                let x = 40;
+                let y = 2;
+                {
+                    let z = x;
+                    for i in range(0, 3) { z += i; }
+                    x = z - 3;
+                }
+                try { throw y; } catch (e) { x += e; }
+                x
+            "#,
+        )?
+        .to_bytes()?;
+
+    // Corrupted input fails to load or to run, but never panics
+    for n in 0..bytes.len() {
+        let mut bytes = bytes.clone();
+        bytes[n] = 127;
+
+        if let Ok(ast) = engine.ast_from_bytes(&bytes) {
+            let _ = engine.eval_ast::<INT>(&ast);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,109 @@
+#![cfg(not(feature = "no_std"))]
+use rhai::{Engine, EvalAltResult, Profiler, INT};
+
+#[test]
+fn test_profiler_native() -> Result<(), Box<EvalAltResult>> {
+    let profiler = Profiler::new();
+
+    let mut engine = Engine::new();
+    engine
+        .register_fn("inc", |x: INT| x + 1)
+        .set_profiler(Some(profiler.clone()));
+
+    assert!(engine.profiler().is_some());
+
+    assert_eq!(
+        engine.eval::<INT>("let x = 0; for i in range(0, 5) { x = inc(x); } x")?,
+        5
+    );
+
+    let entry = profiler
+        .entries()
+        .into_iter()
+        .find(|e| e.fn_name == "inc")
+        .unwrap();
+
+    assert!(!entry.is_script);
+    assert_eq!(entry.calls, 5);
+    assert_eq!(entry.source, None);
+    assert_eq!(entry.pos.line(), Some(1));
+    assert_eq!(entry.pos.position(), Some(39));
+    assert_eq!(entry.exclusive_time, entry.inclusive_time);
+
+    assert!(profiler
+        .collapsed_stacks()
+        .lines()
+        .any(|l| l.starts_with("inc ")));
+    assert!(profiler.summary().starts_with("Function"));
+
+    // Each call site is recorded separately
+    profiler.clear();
+    engine.eval::<INT>("let x = inc(1);\ninc(x)")?;
+
+    let lines: Vec<_> = profiler
+        .entries()
+        .into_iter()
+        .filter(|e| e.fn_name == "inc")
+        .map(|e| (e.pos.line().unwrap(), e.calls))
+        .collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines.contains(&(1, 1)));
+    assert!(lines.contains(&(2, 1)));
+
+    profiler.clear();
+    assert!(profiler.entries().is_empty());
+    assert!(profiler.collapsed_stacks().is_empty());
+
+    engine.set_profiler(None);
+    engine.eval::<INT>("inc(1)")?;
+    assert!(profiler.entries().is_empty());
+
+    Ok(())
+}
+
+#[cfg(not(feature = "no_function"))]
+#[test]
+fn test_profiler_script() -> Result<(), Box<EvalAltResult>> {
+    let profiler = Profiler::new();
+
+    let mut engine = Engine::new();
+    engine.set_profiler(Some(profiler.clone()));
+
+    let mut ast = engine.compile(
+        r"
+            fn fact(n) { if n <= 1 { 1 } else { n * fact(n - 1) } }
+            fn run() { fact(5) }
+            run()
+        ",
+    )?;
+    ast.set_source("test");
+
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 120);
+
+    let entries = profiler.entries();
+    let fact = entries.iter().find(|e| e.fn_name == "fact").unwrap();
+    let run = entries.iter().find(|e| e.fn_name == "run").unwrap();
+
+    assert!(fact.is_script);
+    assert_eq!(fact.calls, 5);
+    assert_eq!(fact.source.as_deref(), Some("test"));
+    assert_eq!(fact.pos.line(), Some(2));
+    assert_eq!(fact.to_string(), "fact (test:2)");
+    assert_eq!(run.calls, 1);
+    assert_eq!(run.pos.line(), Some(3));
+
+    // Operations in recursive calls are only counted once
+    assert!(fact.inclusive_operations > 0);
+    assert!(fact.inclusive_operations >= fact.exclusive_operations);
+    assert_eq!(
+        run.inclusive_operations,
+        run.exclusive_operations + fact.inclusive_operations
+    );
+
+    let stacks = profiler.collapsed_stacks();
+    assert!(stacks
+        .lines()
+        .any(|l| l.starts_with("run (test:3);fact (test:2);fact (test:2) ")));
+
+    Ok(())
+}
@@ -0,0 +1,148 @@
+use rhai::{Engine, EvalAltResult, Position, Snippet, Span};
+
+#[test]
+fn test_span_new() {
+    let script = "let x = 123_456 + \"héllo\";\n  x += 1.5; foo::bar(42)";
+
+    let span = Span::new(Position::new(1, 19), Position::new(1, 25)).unwrap();
+    assert_eq!(span.start(), Position::new(1, 19));
+    assert_eq!(span.end(), Position::new(1, 25));
+    assert_eq!(&script[span.byte_range(script).unwrap()], "\"héllo\"");
+
+    let span = Span::new(Position::new(2, 13), Position::new(2, 15)).unwrap();
+    assert_eq!(&script[span.byte_range(script).unwrap()], "foo");
+    assert_eq!(
+        span.to_string(),
+        "line 2, position 13 to line 2, position 15"
+    );
+
+    assert_eq!(Span::new(Position::new(2, 5), Position::new(1, 9)), None);
+    assert_eq!(Span::new(Position::new(2, 5), Position::NONE), None);
+    assert_eq!(Span::new(Position::NONE, Position::NONE), Some(Span::NONE));
+    assert_eq!(Span::point(Position::new(9, 1)).byte_range(script), None);
+}
+
+#[test]
+fn test_span_parse_errors() {
+    let engine = Engine::new();
+
+    let script = "let x = 42 +;";
+    let err = engine.compile(script).expect_err("should error");
+    let span = err.span();
+    assert_eq!(&script[span.byte_range(script).unwrap()], ";");
+
+    let script = "let x = 123_456\n  \"héllo\";";
+    let err = engine.compile(script).expect_err("should error");
+    let span = err.span();
+    assert_eq!(span.start(), Position::new(2, 3));
+    assert_eq!(span.end(), Position::new(2, 9));
+    assert_eq!(&script[span.byte_range(script).unwrap()], "\"héllo\"");
+
+    let script = "let x = 42;\n(x + 1) = 0;";
+    let err = engine.compile(script).expect_err("should error");
+    let span = err.span();
+    assert_eq!(&script[span.byte_range(script).unwrap()], "x + 1");
+}
+
+#[test]
+fn test_span_errors() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let script = "let x = 42;\nlet y = x + foobar;";
+    let err = engine.eval::<()>(script).expect_err("should error");
+    let span = err.span();
+    assert_eq!(&script[span.byte_range(script).unwrap()], "foobar");
+
+    let script = "let x = 42;\nlet y = \"!\";\nprint(x * y);";
+    let err = engine.eval::<()>(script).expect_err("should error");
+    let span = err.span();
+    assert_eq!(&script[span.byte_range(script).unwrap()], "x * y");
+
+    let script = "let x = 42;\nlet y = -(x + \"!\") * 2;";
+    let err = engine.eval::<()>(script).expect_err("should error");
+    let span = err.span();
+    assert_eq!(&script[span.byte_range(script).unwrap()], "-(x + \"!\")");
+
+    #[cfg(not(feature = "no_object"))]
+    {
+        let script = "let a = 1;\nlet c = 2;\nlet d = a.b(c) + 1;";
+        let err = engine.eval::<()>(script).expect_err("should error");
+        let span = err.span();
+        assert_eq!(&script[span.byte_range(script).unwrap()], "a.b(c)");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_span_other_asts() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let script1 = "let x = 42;\nlet y = x;";
+    let script2 = "let z = \"!\";\nx - z";
+    let ast = engine.compile(script1)?.merge(&engine.compile(script2)?);
+    let err = engine.eval_ast::<()>(&ast).expect_err("should error");
+    let span = err.span();
+    assert_eq!(&script2[span.byte_range(script2).unwrap()], "x - z");
+
+    #[cfg(not(feature = "no_function"))]
+    #[cfg(not(feature = "no_module"))]
+    {
+        use rhai::{module_resolvers::StaticModuleResolver, Module};
+
+        let script = "fn greet(x) {\n    x + \"!\" - 1\n}";
+        let ast = engine.compile(script)?;
+
+        let mut resolver = StaticModuleResolver::new();
+        resolver.insert(
+            "m",
+            Module::eval_ast_as_new(Default::default(), &ast, &engine)?,
+        );
+
+        let mut engine = Engine::new();
+        engine.set_module_resolver(resolver);
+
+        let err = engine
+            .eval::<()>("import \"m\" as m;\nm::greet(42)")
+            .expect_err("should error");
+        let span = err.unwrap_inner().span();
+        assert_eq!(&script[span.byte_range(script).unwrap()], "x + \"!\" - 1");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_span_snippet() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let script = "let x = 42;\n\tlet y = x + undefined_var;";
+    let err = engine.eval::<()>(script).expect_err("should error");
+
+    assert_eq!(
+        Snippet::new(script).render_error(&err),
+        "Variable not found: undefined_var\n --> line 2, position 14\n  |\n2 | \tlet y = x + undefined_var;\n  | \t            ^^^^^^^^^^^^^"
+    );
+
+    let script = "let x = 40 + 2;\nfoo(x";
+    let err = engine.compile(script).expect_err("should error");
+
+    assert_eq!(
+        Snippet::new(script).render_parse_error(&err),
+        "Expecting ')' to close the arguments list of this function call 'foo'\n --> line 2, position 6\n  |\n2 | foo(x\n  |      ^"
+    );
+
+    let script = "let s = \"hello \\\n world\";";
+    let span = Span::new(Position::new(1, 9), Position::new(2, 7)).unwrap();
+
+    assert_eq!(
+        Snippet::new(script)
+            .set_source("test.rhai")
+            .render(span, "string"),
+        "string\n --> test.rhai (line 1, position 9)\n  |\n1 | let s = \"hello \\\n  |         ^^^^^^^^\n2 |  world\";\n  | ^^^^^^^"
+    );
+
+    assert_eq!(Snippet::new(script).render(Span::NONE, "oops"), "oops");
+
+    Ok(())
+}
@@ -35,6 +35,63 @@ fn test_stack_overflow_fn_calls() -> Result<(), Box<EvalAltResult>> {
     Ok(())
 }
 
+#[test]
+#[cfg(not(feature = "no_function"))]
+fn test_stack_tail_calls() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    engine.set_max_call_levels(10);
+
+    let script = r#"
+        fn count(n, acc) { if n == 0 { return acc; } return count(n - 1, acc + 1); }
+        fn is_even(n) { if n == 0 { true } else { is_odd(n - 1) } }
+        fn is_odd(n) { if n == 0 { false } else { is_even(n - 1) } }
+        fn sum(n) { if n == 0 { 0 } else { n + sum(n - 1) } }
+        fn fail(n) { if n == 0 { throw "boom"; } fail(n - 1) }
+        fn safe(n) { try { return fail(n); } catch { return 42; } }
+        fn down(n) { while true { if n == 0 { return 0; } return down(n - 1); } }
+        fn select(n) { switch n { 0 => 0, _ => select(n - 1) } }
+        fn forever(n) { forever(n + 1) }
+    "#;
+
+    let mut ast = engine.compile(script)?;
+
+    for _ in 0..2 {
+        let run = |script: &str| engine.eval_ast::<INT>(&ast.merge(&engine.compile(script)?));
+
+        assert_eq!(run("count(1000, 0)")?, 1000);
+        assert_eq!(run("if is_even(1001) { 1 } else { 0 }")?, 0);
+        assert_eq!(run("down(1000) + select(1000)")?, 0);
+        assert_eq!(run("safe(100)")?, 42);
+
+        // Calls not in tail position still grow the stack
+        assert!(matches!(
+            *run("sum(100)").expect_err("should error"),
+            EvalAltResult::ErrorStackOverflow(_)
+        ));
+
+        // Only the first and last calls are kept in the error backtrace
+        assert!(matches!(
+            *run("fail(100)").expect_err("should error"),
+            EvalAltResult::ErrorInFunctionCall(f, _, err, _)
+                if f == "fail" && matches!(*err, EvalAltResult::ErrorInFunctionCall(ref f, _, ref err, _)
+                    if f == "fail" && matches!(**err, EvalAltResult::ErrorRuntime(_, _)))
+        ));
+
+        ast.compile_bytecode();
+    }
+
+    engine.set_max_operations(1000);
+
+    assert!(matches!(
+        *engine
+            .eval_ast::<INT>(&ast.merge(&engine.compile("forever(0)")?))
+            .expect_err("should error"),
+        EvalAltResult::ErrorTooManyOperations(_)
+    ));
+
+    Ok(())
+}
+
 #[test]
 fn test_stack_overflow_parsing() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
@@ -94,3 +151,107 @@ fn test_stack_overflow_parsing() -> Result<(), Box<EvalAltResult>> {
 
     Ok(())
 }
+
+#[test]
+fn test_stack_deep_expressions() {
+    // Compile and evaluate on a thread with a small stack; this runs the whole test
+    // on that thread because engines and values are not `Send` without `sync`
+    std::thread::Builder::new()
+        .stack_size(256 * 1024)
+        .spawn(|| deep_expressions().unwrap())
+        .unwrap()
+        .join()
+        .unwrap();
+}
+
+fn deep_expressions() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    engine.set_max_expr_depths(
+        0,
+        #[cfg(not(feature = "no_function"))]
+        0,
+    );
+
+    let script = vec!["1"; 100_000].join(" + ");
+    assert_eq!(engine.eval::<INT>(&script)?, 100_000);
+
+    let script = vec!["true"; 100_000].join(" && ");
+    assert!(engine.eval::<bool>(&script)?);
+
+    let script = format!("{} || true", vec!["false"; 100_000].join(" || "));
+    assert!(engine.eval::<bool>(&script)?);
+
+    let script = format!("{}42{}", "(".repeat(10_000), ")".repeat(10_000));
+    assert_eq!(engine.eval::<INT>(&script)?, 42);
+
+    let script = format!("{}42{}", "{ ".repeat(10_000), " }".repeat(10_000));
+    assert_eq!(engine.eval::<INT>(&script)?, 42);
+
+    #[cfg(not(feature = "no_index"))]
+    {
+        let script = format!("{}42{}", "[".repeat(10_000), "]".repeat(10_000));
+        engine.compile(&script)?;
+
+        let script = format!("{}42{}", "[".repeat(30), "]".repeat(30));
+        let script = format!("let x = {}; x{}", script, "[0]".repeat(30));
+        assert_eq!(engine.eval::<INT>(&script)?, 42);
+    }
+
+    #[cfg(not(feature = "no_object"))]
+    {
+        let script = format!("{}42{}", "#{a: ".repeat(10_000), "}".repeat(10_000));
+        engine.compile(&script)?;
+
+        let script = format!("{}42{}", "#{a: ".repeat(30), "}".repeat(30));
+        let script = format!("let x = {}; x{}", script, ".a".repeat(30));
+        assert_eq!(engine.eval::<INT>(&script)?, 42);
+
+        let script = format!(
+            "let x = 0; for i in range(0, 50) {{ x = #{{a: x}}; }} x{} = 42; x{}",
+            ".a".repeat(50),
+            ".a".repeat(50)
+        );
+        assert_eq!(engine.eval::<INT>(&script)?, 42);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_stack_deep_values() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    // Deeply-nested values are still cloned and dropped recursively
+    #[cfg(not(feature = "no_index"))]
+    assert_eq!(
+        engine.eval::<INT>(
+            r"
+                let x = 42;
+                for i in range(0, 1000) { x = [x]; }
+
+                let y = x;
+                for i in range(0, 1000) { y = y[0]; }
+                y
+            "
+        )?,
+        42
+    );
+
+    #[cfg(not(feature = "no_object"))]
+    assert_eq!(
+        engine.eval::<INT>(
+            r"
+                let x = 42;
+                for i in range(0, 300) { x = #{a: x}; }
+
+                let y = x;
+                for i in range(0, 300) { y = y.a; }
+                y
+            "
+        )?,
+        42
+    );
+
+    Ok(())
+}
@@ -0,0 +1,227 @@
+#![cfg(not(feature = "no_function"))]
+#![cfg(not(feature = "no_std"))]
+use rhai::{Engine, EvalAltResult, TestOutcome, TestRunner};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[test]
+fn test_test_runner() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    let mut ast = engine.compile(
+        r#"
+            fn helper(x) {
+                if x > 1 { throw "too big"; }
+                x
+            }
+
+            fn test_pass() { helper(1); }
+
+            fn test_fail() { helper(2); }
+
+            /// #[should_fail]
+            fn test_should_fail() { helper(3); }
+
+            /// #[should_fail]
+            fn test_not_failed() { helper(1); }
+
+            fn test_with_params(x) { }
+        "#,
+    )?;
+    ast.set_source("tests.rhai");
+
+    let mut runner = TestRunner::new(&mut engine);
+
+    assert_eq!(
+        runner.tests(&ast),
+        vec![
+            "test_pass",
+            "test_fail",
+            "test_should_fail",
+            "test_not_failed"
+        ]
+    );
+
+    let report = runner.run(&ast);
+
+    assert_eq!(report.results.len(), 4);
+    assert_eq!(report.passed(), 2);
+    assert_eq!(report.failed(), 2);
+    assert!(!report.is_success());
+
+    let result = &report.results[1];
+    assert_eq!(result.name, "test_fail");
+    assert_eq!(result.source.as_deref(), Some("tests.rhai"));
+    assert!(!result.should_fail);
+
+    match result.outcome {
+        TestOutcome::Failed(ref err) => {
+            assert_eq!(err.position().line(), Some(9));
+            assert_eq!(err.backtrace()[0].fn_name, "helper");
+        }
+        ref outcome => panic!("unexpected outcome: {:?}", outcome),
+    }
+
+    assert!(report.results[2].should_fail);
+    assert!(report.results[2].outcome.is_success());
+    assert!(matches!(report.results[3].outcome, TestOutcome::NotFailed));
+
+    let xml = report.to_junit_xml();
+    assert!(xml.starts_with("<?xml"));
+    assert!(xml.contains(r#"<testsuite name="tests.rhai" tests="4" failures="2""#));
+    assert!(xml.contains(r#"<testcase name="test_pass" classname="tests.rhai""#));
+    assert!(xml.contains(r#"<failure message="Test is expected to fail but did not">"#));
+
+    runner.set_filter("fail");
+    assert_eq!(
+        runner.tests(&ast),
+        vec!["test_fail", "test_should_fail", "test_not_failed"]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_test_runner_limits() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    #[cfg(not(feature = "unchecked"))]
+    engine.set_max_operations(42);
+
+    let ast = engine.compile(
+        "
+            fn test_forever() {
+                let x = 0;
+                loop { x += 1; }
+            }
+        ",
+    )?;
+
+    let mut runner = TestRunner::new(&mut engine);
+
+    #[cfg(not(feature = "unchecked"))]
+    runner.set_max_operations(0);
+    runner.set_time_limit(Some(Duration::from_millis(50)));
+
+    let report = runner.run(&ast);
+    assert!(matches!(report.results[0].outcome, TestOutcome::TimedOut));
+
+    #[cfg(not(feature = "unchecked"))]
+    {
+        runner.set_max_operations(1000).set_time_limit(None);
+
+        let report = runner.run(&ast);
+
+        match report.results[0].outcome {
+            TestOutcome::Failed(ref err) => {
+                assert!(matches!(**err, EvalAltResult::ErrorTooManyOperations(_)))
+            }
+            ref outcome => panic!("unexpected outcome: {:?}", outcome),
+        }
+
+        // Engine limits are restored
+        assert_eq!(engine.max_operations(), 42);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_test_runner_setup() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    let ast = engine.compile(
+        r#"
+            const ANSWER = 42;
+            let count = 0;
+
+            fn test_change() {
+                count += 1;
+                if count != 1 { throw "count not reset"; }
+            }
+
+            fn test_change_again() {
+                count += 1;
+                if count != 1 { throw "count not reset"; }
+            }
+
+            fn test_constant() {
+                if ANSWER != 42 { throw "wrong answer"; }
+            }
+        "#,
+    )?;
+
+    let report = TestRunner::new(&mut engine).run(&ast);
+    assert_eq!(report.results.len(), 3);
+    assert!(report.is_success());
+
+    let ast = engine.compile(
+        r#"
+            throw "broken setup";
+
+            fn test_never_run() { }
+        "#,
+    )?;
+
+    let report = TestRunner::new(&mut engine).run(&ast);
+    assert_eq!(report.results.len(), 1);
+    assert_eq!(report.results[0].name, TestRunner::SETUP_TEST_NAME);
+
+    match report.results[0].outcome {
+        TestOutcome::Failed(ref err) => {
+            assert!(matches!(**err, EvalAltResult::ErrorRuntime(_, _)))
+        }
+        ref outcome => panic!("unexpected outcome: {:?}", outcome),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_test_runner_progress() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    let count = Arc::new(AtomicU64::new(0));
+    let counter = count.clone();
+
+    engine.on_progress(move |ops| {
+        counter.fetch_add(1, Ordering::SeqCst);
+        if ops > 1000 {
+            Some("stop".into())
+        } else {
+            None
+        }
+    });
+
+    let ast = engine.compile(
+        "
+            fn test_pass() { 42 }
+
+            fn test_forever() {
+                let x = 0;
+                loop { x += 1; }
+            }
+        ",
+    )?;
+
+    let report = TestRunner::new(&mut engine).run(&ast);
+
+    // The host's progress callback keeps running during the tests
+    assert!(report.results[0].outcome.is_success());
+    assert!(count.load(Ordering::SeqCst) > 0);
+
+    match report.results[1].outcome {
+        TestOutcome::Failed(ref err) => {
+            assert!(matches!(**err, EvalAltResult::ErrorTerminated(_, _)))
+        }
+        ref outcome => panic!("unexpected outcome: {:?}", outcome),
+    }
+
+    // The host's progress callback is restored
+    count.store(0, Ordering::SeqCst);
+    engine.eval::<rhai::INT>("40 + 2")?;
+    assert!(count.load(Ordering::SeqCst) > 0);
+
+    Ok(())
+}
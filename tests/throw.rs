@@ -39,3 +39,61 @@ fn test_try_catch() -> Result<(), Box<EvalAltResult>> {
 
     Ok(())
 }
+
+#[cfg(not(feature = "no_function"))]
+#[test]
+fn test_throw_backtrace() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let err = engine
+        .eval::<()>(
+            r#"
+                fn foo(x) { throw x; }
+                fn bar(x) { foo(x + 1) }
+                bar(41);
+            "#,
+        )
+        .expect_err("expects error");
+
+    assert_eq!(
+        err.backtrace()
+            .into_iter()
+            .map(|frame| (frame.fn_name, frame.pos.line().unwrap()))
+            .collect::<Vec<_>>(),
+        [("foo".to_string(), 3), ("bar".to_string(), 4)]
+    );
+    assert_eq!(
+        err.to_string(),
+        "Runtime error: 42 (line 2, position 29) in call to function foo < bar (line 4, position 17)"
+    );
+    assert!(matches!(
+        err.unwrap_inner(),
+        EvalAltResult::ErrorRuntime(x, _) if x.as_int().unwrap() == 42
+    ));
+
+    assert!(engine
+        .eval::<()>("throw 42")
+        .expect_err("expects error")
+        .backtrace()
+        .is_empty());
+
+    #[cfg(not(feature = "no_object"))]
+    #[cfg(not(feature = "no_index"))]
+    assert_eq!(
+        engine.eval::<String>(
+            r#"
+                fn foo(x) { x.bar }
+                fn baz() { foo(42) }
+                try {
+                    baz();
+                } catch (err) {
+                    let frame = err.stack[0];
+                    return `${err.stack.len()} ${frame.function} ${frame.line}`;
+                }
+            "#
+        )?,
+        "2 foo 3"
+    );
+
+    Ok(())
+}
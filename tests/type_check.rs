@@ -0,0 +1,244 @@
+use rhai::{Engine, EvalAltResult, Scope, TypeChecker, TypeErrorType, INT};
+use std::any::type_name;
+
+fn errors(checker: &TypeChecker, script: &str) -> Result<Vec<(usize, String)>, Box<EvalAltResult>> {
+    Ok(checker
+        .check(script)?
+        .into_iter()
+        .map(|err| (err.1.line().unwrap(), err.0.to_string()))
+        .collect())
+}
+
+#[test]
+fn test_type_check_operators() -> Result<(), Box<EvalAltResult>> {
+    let int = type_name::<INT>();
+    let engine = Engine::new();
+    let checker = TypeChecker::new(&engine);
+
+    assert_eq!(
+        errors(
+            &checker,
+            r#"
+                let x = 40 + 2;
+                let s = "hello" + x;
+                let y = x - "world";
+                let b = true && x;
+                x *= "!";
+                s += 1;
+            "#
+        )?,
+        [
+            (4, format!("Function not found: - ({}, string)", int)),
+            (
+                5,
+                format!("Data type is incorrect: {} (expecting bool)", int)
+            ),
+            (6, format!("Function not found: * ({}, string)", int)),
+        ]
+    );
+
+    // Types flow through built-in operators
+    assert_eq!(
+        errors(&checker, "let x = (1 + 2) * 3 < 4; if x == true { x - 1 }")?,
+        [(1, format!("Function not found: - (bool, {})", int))]
+    );
+
+    // Unknown types are never reported
+    assert_eq!(
+        errors(&checker, "let x = parse_int(\"42\"); x - \"a\"")?,
+        []
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_type_check_statements() -> Result<(), Box<EvalAltResult>> {
+    let int = type_name::<INT>();
+    let engine = Engine::new();
+    let checker = TypeChecker::new(&engine);
+
+    assert_eq!(
+        errors(
+            &checker,
+            r"
+                let x = 42;
+                if x { }
+                while x + 1 { }
+                for i in true { }
+                for i in range(1, 4) { i - 1 }
+            "
+        )?,
+        [
+            (
+                3,
+                format!("Data type is incorrect: {} (expecting bool)", int)
+            ),
+            (
+                4,
+                format!("Data type is incorrect: {} (expecting bool)", int)
+            ),
+            (
+                5,
+                "Type 'bool' cannot be iterated in a for loop".to_string()
+            ),
+        ]
+    );
+
+    // Variables changing type in branches and loops are unknown
+    assert_eq!(
+        errors(
+            &checker,
+            r#"
+                let x = 42;
+                if x > 0 { x = "hello"; }
+                x - 1;
+
+                let y = 0;
+                loop { y - 1; y = "hello"; }
+
+                let z = 0;
+                try { z = "hello"; throw 42; } catch { z - 1; }
+            "#
+        )?,
+        []
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_type_check_functions() -> Result<(), Box<EvalAltResult>> {
+    let int = type_name::<INT>();
+    let mut engine = Engine::new();
+    engine.register_fn("double", |x: INT| x * 2);
+
+    let checker = TypeChecker::new(&engine);
+
+    assert_eq!(
+        errors(
+            &checker,
+            r#"
+                double(21);
+                double("hello");
+                double(1, 2);
+                triple(1);
+                let x = "hello";
+                double(x);
+            "#
+        )?,
+        [
+            (3, "Function not found: double (string)".to_string()),
+            (
+                4,
+                "Function not found: double with 2 parameters".to_string()
+            ),
+            (5, "Function not found: triple with 1 parameter".to_string()),
+            (7, "Function not found: double (string)".to_string()),
+        ]
+    );
+
+    #[cfg(not(feature = "no_object"))]
+    assert_eq!(
+        errors(&checker, r#"let x = "hello"; x.double();"#)?,
+        [(1, "Function not found: double (string)".to_string())]
+    );
+
+    #[cfg(not(feature = "no_function"))]
+    assert_eq!(
+        errors(
+            &checker,
+            r#"
+                fn greet(name) { `hello, ${name}` }
+                fn answer() { if true { return 42; } 0 }
+                fn unknown(x) { x }
+                greet(1) - 1;
+                answer() && true;
+                unknown(1) - "a";
+            "#
+        )?,
+        [
+            (5, format!("Function not found: - (string, {})", int)),
+            (
+                6,
+                format!("Data type is incorrect: {} (expecting bool)", int)
+            ),
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_type_check_properties() -> Result<(), Box<EvalAltResult>> {
+    let _int = type_name::<INT>();
+    let engine = Engine::new();
+    let checker = TypeChecker::new(&engine);
+
+    #[cfg(not(feature = "no_object"))]
+    assert_eq!(
+        errors(
+            &checker,
+            r#"
+                let x = 42;
+                x.foo;
+                let m = #{ a: 1 };
+                m.b;
+                let s = "hello";
+                s.len;
+                s.len();
+            "#
+        )?,
+        [(3, format!("Property 'foo' not found for type '{}'", _int))]
+    );
+
+    #[cfg(not(feature = "no_index"))]
+    assert_eq!(
+        errors(
+            &checker,
+            r#"
+                let a = [1, 2, 3];
+                let i = "x";
+                a[i];
+                let b = true;
+                b[0];
+                let s = "hello";
+                s[0] + 1;
+            "#
+        )?,
+        [
+            (
+                4,
+                format!("Data type is incorrect: string (expecting {})", _int)
+            ),
+            (6, "Indexer not registered for type 'bool'".to_string()),
+            (8, format!("Function not found: + (char, {})", _int)),
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_type_check_scope() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+    let checker = TypeChecker::new(&engine);
+
+    let mut scope = Scope::new();
+    scope.push("x", 42 as INT);
+    scope.push("y", "hello");
+
+    let errs = checker.check_with_scope(&scope, "if y { x }")?;
+    assert_eq!(errs.len(), 1);
+    assert_eq!(
+        *errs[0].0,
+        TypeErrorType::MismatchDataType("bool".into(), "string".into())
+    );
+
+    // `eval` may change any variable
+    assert!(checker
+        .check_with_scope(&scope, "eval(\"y = true\"); if y { x }")?
+        .is_empty());
+
+    Ok(())
+}